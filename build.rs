@@ -0,0 +1,25 @@
+use std::process::Command;
+
+/// Embed the git commit as `SPIN_GIT_COMMIT` for GET /api/info. A `SPIN_GIT_COMMIT` set in the
+/// build environment wins, for builds without a `.git` directory (e.g. Docker)
+fn main() {
+    println!("cargo:rerun-if-env-changed=SPIN_GIT_COMMIT");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+
+    let commit = std::env::var("SPIN_GIT_COMMIT")
+        .ok()
+        .filter(|commit| !commit.is_empty())
+        .or_else(|| {
+            Command::new("git")
+                .args(["rev-parse", "--short=12", "HEAD"])
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .and_then(|output| String::from_utf8(output.stdout).ok())
+                .map(|commit| commit.trim().to_string())
+                .filter(|commit| !commit.is_empty())
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=SPIN_GIT_COMMIT={}", commit);
+}
@@ -1,4 +1,5 @@
 use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
 use std::env;
 
 #[derive(Debug, Deserialize, Clone)]
@@ -10,12 +11,65 @@ pub struct Config {
     pub database: DatabaseConfig,
     pub ipfs: IpfsConfig,
     pub kline: KlineServiceConfig,
+    #[serde(default)]
+    pub admin: AdminConfig,
+    #[serde(default)]
+    pub events: EventPipelineConfig,
+    #[serde(default)]
+    pub persistence: PersistenceConfig,
+    #[serde(default)]
+    pub orders: OrderConfig,
+    #[serde(default)]
+    pub stats: StatsConfig,
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    #[serde(default)]
+    pub tsdb: TsdbConfig,
+    #[serde(default)]
+    pub api_keys: ApiKeyConfig,
+    #[serde(default)]
+    pub bus: BusConfig,
+    #[serde(default)]
+    pub response_cache: ResponseCacheConfig,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
+    /// Scan-heavy queries allowed to run at once; extra requests get 429 (default: 32)
+    #[serde(default = "default_max_concurrent_queries")]
+    pub max_concurrent_queries: usize,
+    /// Serve REST queries over the existing database only: no Solana listener, K-line push or background tasks (default: false)
+    #[serde(default)]
+    pub query_only: bool,
+    /// Longest a scan-heavy query may run before the request fails with 504, in
+    /// milliseconds; 0 disables the limit (default: 10000)
+    #[serde(default = "default_query_timeout_ms")]
+    pub query_timeout_ms: u64,
+    /// Return u64 amount fields of events and orders as JSON strings in REST responses, for JS
+    /// clients that lose precision above 2^53; stored values stay numbers (default: false)
+    #[serde(default)]
+    pub bigint_as_string: bool,
+    /// Soft cap in MB on the dedup cache plus K-line subscription state; when the estimate exceeds
+    /// it the dedup cache is trimmed and the longest-idle connections reaped. 0 only logs (default: 0)
+    #[serde(default)]
+    pub max_state_memory_mb: u64,
+    /// Serve GET /api/info with the version, git commit, uptime and program id (default: true)
+    #[serde(default = "default_expose_info")]
+    pub expose_info: bool,
+}
+
+fn default_max_concurrent_queries() -> usize {
+    32
+}
+
+fn default_query_timeout_ms() -> u64 {
+    10_000
+}
+
+fn default_expose_info() -> bool {
+    true
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -27,6 +81,20 @@ pub struct CorsConfig {
 #[derive(Debug, Deserialize, Clone)]
 pub struct LoggingConfig {
     pub level: String,
+    /// Log line format (default: pretty)
+    #[serde(default)]
+    pub format: LogFormat,
+}
+
+/// Log output format
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// Human-readable text for local development
+    #[default]
+    Pretty,
+    /// One JSON object per line for log aggregation (Loki, ELK)
+    Json,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -40,6 +108,14 @@ pub struct SolanaConfig {
     pub reconnect_interval: u64,
     #[allow(dead_code)]
     pub max_reconnect_attempts: u32,
+    /// Wait before re-arming the connection loop after `max_reconnect_attempts` is exhausted,
+    /// in seconds (default: 300)
+    #[serde(default = "default_listener_restart_cooldown_secs")]
+    pub listener_restart_cooldown_secs: u64,
+    /// Times the connection loop is re-armed after giving up; 0 leaves the listener
+    /// disconnected like before (default: 12)
+    #[serde(default = "default_max_listener_restart_cycles")]
+    pub max_listener_restart_cycles: u32,
     #[allow(dead_code)]
     pub event_buffer_size: usize,
     #[allow(dead_code)]
@@ -49,11 +125,222 @@ pub struct SolanaConfig {
     /// Whether to process failed transactions for development/testing (default: false)
     #[serde(default)]
     pub process_failed_transactions: bool,
+    /// Maximum number of CPI full-transaction fetches running concurrently (default: 8)
+    #[serde(default = "default_max_inflight_cpi_fetches")]
+    pub max_inflight_cpi_fetches: usize,
+    /// Skip the CPI full-transaction fetch for signatures more than this many slots behind the
+    /// chain head and keep only the WebSocket log events; 0 always fetches (default: 0)
+    #[serde(default)]
+    pub cpi_fetch_max_slot_age: u64,
+    /// Extra CPI full-transaction fetches when the RPC does not have the transaction yet;
+    /// 0 keeps only the first attempt (default: 0)
+    #[serde(default)]
+    pub cpi_fetch_retries: u32,
+    /// Delay before each CPI fetch retry, in milliseconds (default: 2000)
+    #[serde(default = "default_cpi_fetch_retry_delay_ms")]
+    pub cpi_fetch_retry_delay_ms: u64,
+    /// getTransaction calls per fetch while the node does not have the transaction yet or the
+    /// request fails in transit; 1 disables retries (default: 3)
+    #[serde(default = "default_transaction_fetch_attempts")]
+    pub transaction_fetch_attempts: u32,
+    /// Delay before the first getTransaction retry, doubled for each further one, in
+    /// milliseconds (default: 500)
+    #[serde(default = "default_transaction_fetch_retry_delay_ms")]
+    pub transaction_fetch_retry_delay_ms: u64,
+    /// Tasks processing WebSocket messages off the read loop; more than 1 lets transactions
+    /// complete out of arrival order (default: 1)
+    #[serde(default = "default_message_workers")]
+    pub message_workers: usize,
+    /// WebSocket messages buffered between the read loop and the workers (default: 1000)
+    #[serde(default = "default_message_queue_size")]
+    pub message_queue_size: usize,
+    /// Parse embedded event fixtures at startup: "off", "warn" or "strict" (default: off)
+    #[serde(default)]
+    pub parser_selftest: ParserSelftestMode,
+    /// Event timestamp source: "block_time", "server_receive" or "prefer_block_time" (default: server_receive)
+    #[serde(default)]
+    pub timestamp_source: TimestampSourceMode,
+    /// Budget for resolving block time in prefer_block_time mode, in milliseconds (default: 500)
+    #[serde(default = "default_block_time_fetch_timeout_ms")]
+    pub block_time_fetch_timeout_ms: u64,
+    /// TokenCreated events whose name is longer than this many bytes are skipped (default: 64)
+    #[serde(default = "default_max_token_name_len")]
+    pub max_token_name_len: usize,
+    /// TokenCreated events whose symbol is longer than this many bytes are skipped (default: 32)
+    #[serde(default = "default_max_token_symbol_len")]
+    pub max_token_symbol_len: usize,
+    /// TokenCreated events whose uri is longer than this many bytes are skipped (default: 512)
+    #[serde(default = "default_max_token_uri_len")]
+    pub max_token_uri_len: usize,
+    /// How long a fetched chain head slot is reused for lag monitoring, in milliseconds (default: 1000)
+    #[serde(default = "default_head_slot_cache_ttl_ms")]
+    pub head_slot_cache_ttl_ms: u64,
+    /// Per-event-type minimum data lengths raising the parser's compiled-in ones, keyed by
+    /// snake_case event type (e.g. `buy_sell`)
+    #[serde(default)]
+    pub event_min_lengths: HashMap<String, usize>,
+    /// How long to wait for the logsSubscribe reply before reconnecting, in seconds; 0 waits
+    /// without a limit. A JSON-RPC error reply always reconnects (default: 30)
+    #[serde(default = "default_subscribe_confirm_timeout_secs")]
+    pub subscribe_confirm_timeout_secs: u64,
+    /// Listener connection state changes (with the error behind each reconnect) kept for
+    /// `/api/events/reconnect-history`; 0 disables the history (default: 50)
+    #[serde(default = "default_reconnect_history_size")]
+    pub reconnect_history_size: usize,
+    /// Reject events whose curve/pool accounts are not the PDAs the program derives for their
+    /// mint (default: false)
+    #[serde(default)]
+    pub verify_pdas: bool,
+}
+
+/// What to do when the startup parser self-test finds a fixture that no longer parses
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ParserSelftestMode {
+    #[default]
+    Off,
+    /// Log an error and keep starting
+    Warn,
+    /// Refuse to start
+    Strict,
+}
+
+/// How event timestamps are resolved
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampSourceMode {
+    /// Always wait for the slot's block time
+    BlockTime,
+    /// Use the time the event was received (no extra RPC call)
+    #[default]
+    ServerReceive,
+    /// Use block time if it resolves within the fetch budget, else the receive time
+    PreferBlockTime,
+}
+
+fn default_block_time_fetch_timeout_ms() -> u64 {
+    500
+}
+
+pub const DEFAULT_MAX_TOKEN_NAME_LEN: usize = 64;
+pub const DEFAULT_MAX_TOKEN_SYMBOL_LEN: usize = 32;
+pub const DEFAULT_MAX_TOKEN_URI_LEN: usize = 512;
+
+fn default_max_token_name_len() -> usize {
+    DEFAULT_MAX_TOKEN_NAME_LEN
+}
+
+fn default_max_token_symbol_len() -> usize {
+    DEFAULT_MAX_TOKEN_SYMBOL_LEN
+}
+
+fn default_max_token_uri_len() -> usize {
+    DEFAULT_MAX_TOKEN_URI_LEN
+}
+
+fn default_head_slot_cache_ttl_ms() -> u64 {
+    1000
+}
+
+fn default_subscribe_confirm_timeout_secs() -> u64 {
+    30
+}
+
+fn default_reconnect_history_size() -> usize {
+    50
+}
+
+fn default_listener_restart_cooldown_secs() -> u64 {
+    300
+}
+
+fn default_max_listener_restart_cycles() -> u32 {
+    12
+}
+
+fn default_max_inflight_cpi_fetches() -> usize {
+    8
+}
+
+fn default_cpi_fetch_retry_delay_ms() -> u64 {
+    2000
+}
+
+fn default_transaction_fetch_attempts() -> u32 {
+    3
+}
+
+fn default_transaction_fetch_retry_delay_ms() -> u64 {
+    500
+}
+
+fn default_message_workers() -> usize {
+    1
+}
+
+fn default_message_queue_size() -> usize {
+    1000
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct DatabaseConfig {
     pub rocksdb_path: String,
+    /// Whether large query scans populate the block cache (default: false)
+    #[serde(default)]
+    pub cache_scans: bool,
+    /// Maximum keys an event history query examines before returning a truncated result; 0 disables the cap (default: 100000)
+    #[serde(default = "default_max_scan_keys")]
+    pub max_scan_keys: usize,
+    /// Seconds between scheduled checkpoints; 0 disables them (default: 0)
+    #[serde(default)]
+    pub checkpoint_interval: u64,
+    /// Directory that scheduled checkpoints are written under (default: ./data/checkpoints)
+    #[serde(default = "default_checkpoint_dir")]
+    pub checkpoint_dir: String,
+    /// WAL directory with archival enabled: obsolete WAL files move to `{wal_archive_dir}/archive`
+    /// instead of being deleted (default: unset, WAL lives in rocksdb_path and is deleted)
+    #[serde(default)]
+    pub wal_archive_dir: Option<String>,
+    /// How long archived WAL files are kept, in seconds (default: 86400)
+    #[serde(default = "default_wal_archive_ttl_secs")]
+    pub wal_archive_ttl_secs: u64,
+    /// Lock shards serializing mint detail read-modify-write updates, keyed by mint hash (default: 64)
+    #[serde(default = "default_mint_lock_shards")]
+    pub mint_lock_shards: usize,
+    /// Accumulate mint detail totals as `mc:{mint}:{field}` merge counters instead of inside the
+    /// `in:` record, so trades add to them without a read-modify-write (default: false)
+    #[serde(default)]
+    pub merge_counters: bool,
+    /// Also store each live event's receive latency under `lat:{event key}` (default: false)
+    #[serde(default)]
+    pub store_event_latency: bool,
+    /// Free space (MB) on the `rocksdb_path` filesystem below which the server turns read-only:
+    /// event storage is refused and the listener paused until space is back. 0 disables (default: 0)
+    #[serde(default)]
+    pub min_free_disk_mb: u64,
+    /// Seconds between free space checks (default: 30)
+    #[serde(default = "default_disk_check_interval_secs")]
+    pub disk_check_interval_secs: u64,
+}
+
+fn default_disk_check_interval_secs() -> u64 {
+    30
+}
+
+fn default_checkpoint_dir() -> String {
+    "./data/checkpoints".to_string()
+}
+
+fn default_wal_archive_ttl_secs() -> u64 {
+    86_400
+}
+
+fn default_mint_lock_shards() -> usize {
+    64
+}
+
+fn default_max_scan_keys() -> usize {
+    100_000
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -62,6 +349,35 @@ pub struct IpfsConfig {
     pub request_timeout_seconds: u64,
     pub max_retries: u32,
     pub retry_delay_seconds: u64,
+    /// Fill uri_data from the on-chain name/symbol when the URI is empty or can't be fetched (default: false)
+    #[serde(default)]
+    pub use_onchain_fallback: bool,
+    /// Metadata responses larger than this many bytes, or not JSON, are skipped (default: 262144)
+    #[serde(default = "default_max_metadata_bytes")]
+    pub max_metadata_bytes: usize,
+    /// Creations sharing a URI while its fetch is in flight wait for that fetch instead of
+    /// requesting the gateway again (default: true)
+    #[serde(default = "default_coalesce_uri_fetches")]
+    pub coalesce_uri_fetches: bool,
+    /// Refetch the metadata of mints last fetched more than this many seconds ago, picking up
+    /// images/socials changed after creation; 0 disables refetching (default: 0)
+    #[serde(default)]
+    pub refetch_after_secs: u64,
+    /// Mints whose metadata is refetched per minute, one gateway request at a time (default: 30)
+    #[serde(default = "default_refetch_per_minute")]
+    pub refetch_per_minute: usize,
+}
+
+fn default_max_metadata_bytes() -> usize {
+    256 * 1024
+}
+
+fn default_coalesce_uri_fetches() -> bool {
+    true
+}
+
+fn default_refetch_per_minute() -> usize {
+    30
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -72,6 +388,553 @@ pub struct KlineServiceConfig {
     pub history_data_limit: usize,
     pub ping_interval_secs: u64,
     pub ping_timeout_secs: u64,
+    /// Minimum BuySell sol_amount (lamports) that updates klines; 0 disables the filter (default: 0)
+    #[serde(default)]
+    pub min_trade_sol: u64,
+    /// Accept an initial `{ symbol, interval }` subscription in the Socket.IO connect auth (default: true)
+    #[serde(default = "default_subscribe_on_connect")]
+    pub subscribe_on_connect: bool,
+    /// Number of klines pushed on subscribe, per interval
+    #[serde(default)]
+    pub history_limits: KlineHistoryLimits,
+    /// Seconds between passes announcing closed candles to subscribers; 0 disables (default: 1)
+    #[serde(default = "default_finalization_interval_secs")]
+    pub finalization_interval_secs: u64,
+    /// Send each subscriber one `candles_closed` array per pass instead of a `candle_closed` per candle (default: false)
+    #[serde(default)]
+    pub batch_finalizations: bool,
+    /// Open a new bucket at the previous bucket's close rather than its first trade price (default: true)
+    #[serde(default = "default_continuous_open")]
+    pub continuous_open: bool,
+    /// Without `continuous_open`, still open a mint's first new bucket after startup at the stored
+    /// previous candle's close when that candle is the adjacent bucket (default: false)
+    #[serde(default)]
+    pub seed_open_after_restart: bool,
+    /// Seconds a disconnected client's subscriptions are kept for resumption by session token; 0 disables (default: 0)
+    #[serde(default)]
+    pub session_resume_grace_secs: u64,
+    /// Largest accepted move of `latest_price` against the mint's stored price, as a ratio in either
+    /// direction; larger moves skip kline and price updates but the event is still stored. 0 disables (default: 0)
+    #[serde(default)]
+    pub max_price_jump_ratio: f64,
+    /// Consecutive out-of-bound prices agreeing with each other (within `max_price_jump_ratio`) after
+    /// which the new level is accepted as a genuine repricing; 0 never accepts them (default: 3)
+    #[serde(default = "default_price_jump_confirmations")]
+    pub price_jump_confirmations: u32,
+    /// Only update and push candles for events whose `latest_price` differs from the mint's stored
+    /// price; an unchanged price opens no new candle either (default: false)
+    #[serde(default)]
+    pub skip_unchanged_price: bool,
+    /// Rolling window of the per-mint buy/sell `imbalance` push, in seconds; 0 disables (default: 60)
+    #[serde(default = "default_imbalance_window_secs")]
+    pub imbalance_window_secs: u64,
+    /// Seconds after connecting during which a client is never reaped as inactive; 0 disables (default: 0)
+    #[serde(default)]
+    pub min_connection_age_secs: u64,
+    /// Window in milliseconds within which a mint's realtime `kline_data` updates per interval are
+    /// coalesced into one push of the latest candle; final candles are always sent. 0 disables (default: 0)
+    #[serde(default)]
+    pub broadcast_throttle_ms: u64,
+    /// Consecutive failed emits to one socket after which the client and its subscriptions are
+    /// removed; 0 disables (default: 5)
+    #[serde(default = "default_max_emit_failures")]
+    pub max_emit_failures: u32,
+    /// Accept `subscribe_type` to the cross-mint per-event-type rooms such as `type:TokenCreated` (default: true)
+    #[serde(default = "default_type_channels")]
+    pub type_channels: bool,
+    /// How long `/api/klines/{mint}/watchers` reuses a mint's counts, in milliseconds; 0 reads the
+    /// subscriptions on every request (default: 1000)
+    #[serde(default = "default_watchers_cache_ms")]
+    pub watchers_cache_ms: u64,
+    /// Let clients that connect with `auth.binary_history = true` receive `history_data` as a
+    /// binary frame of gzip-compressed JSON; live updates stay text frames (default: false)
+    #[serde(default)]
+    pub binary_history: bool,
+    /// Seconds between checks that drop tracked connections whose socket the `/kline` namespace no
+    /// longer has (a missed disconnect), with the rooms and empty subscription maps they leave; 0 disables (default: 60)
+    #[serde(default = "default_reconcile_interval_secs")]
+    pub reconcile_interval_secs: u64,
+}
+
+fn default_subscribe_on_connect() -> bool {
+    true
+}
+
+fn default_finalization_interval_secs() -> u64 {
+    1
+}
+
+fn default_continuous_open() -> bool {
+    true
+}
+
+fn default_imbalance_window_secs() -> u64 {
+    60
+}
+
+fn default_price_jump_confirmations() -> u32 {
+    3
+}
+
+fn default_max_emit_failures() -> u32 {
+    5
+}
+
+fn default_type_channels() -> bool {
+    true
+}
+
+fn default_watchers_cache_ms() -> u64 {
+    1000
+}
+
+fn default_reconcile_interval_secs() -> u64 {
+    60
+}
+
+/// Per-interval history counts sent when a client subscribes
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+pub struct KlineHistoryLimits {
+    pub s1: usize,
+    pub s30: usize,
+    pub m5: usize,
+}
+
+impl Default for KlineHistoryLimits {
+    fn default() -> Self {
+        Self {
+            s1: 300,
+            s30: 200,
+            m5: 500,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AdminConfig {
+    /// Whether admin endpoints such as slot replay are exposed (default: false)
+    #[serde(default)]
+    pub enable_admin_api: bool,
+    /// Maximum slot span accepted by a single replay request (default: 10000)
+    #[serde(default = "default_replay_max_slot_range")]
+    pub replay_max_slot_range: u64,
+    /// How replay timestamps events whose transaction has no block time (missing or zero):
+    /// "off", "block_time" or "slot_anchor" (default: off)
+    #[serde(default)]
+    pub replay_timestamp_derivation: ReplayTimestampMode,
+    /// Slot of the slot_anchor reference point
+    #[serde(default)]
+    pub replay_anchor_slot: u64,
+    /// Unix time in seconds of `replay_anchor_slot`; required for slot_anchor
+    #[serde(default)]
+    pub replay_anchor_time: i64,
+    /// Slot rate used to extrapolate from the anchor (default: 2.5)
+    #[serde(default = "default_replay_slots_per_second")]
+    pub replay_slots_per_second: f64,
+}
+
+impl Default for AdminConfig {
+    fn default() -> Self {
+        Self {
+            enable_admin_api: false,
+            replay_max_slot_range: default_replay_max_slot_range(),
+            replay_timestamp_derivation: ReplayTimestampMode::default(),
+            replay_anchor_slot: 0,
+            replay_anchor_time: 0,
+            replay_slots_per_second: default_replay_slots_per_second(),
+        }
+    }
+}
+
+fn default_replay_max_slot_range() -> u64 {
+    10000
+}
+
+fn default_replay_slots_per_second() -> f64 {
+    2.5
+}
+
+/// How replay derives timestamps for events lacking one
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplayTimestampMode {
+    /// Keep the receive time; no timestamp is derived
+    #[default]
+    Off,
+    /// Ask getBlockTime for the event's slot
+    BlockTime,
+    /// Extrapolate from `replay_anchor_slot`/`replay_anchor_time` at `replay_slots_per_second`
+    SlotAnchor,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct EventPipelineConfig {
+    /// Event handlers run in order for each event: "stats" (storage + statistics), "kline" (real-time push),
+    /// "bus" (publish to the `[bus]` message bus)
+    #[serde(default = "default_event_handlers")]
+    pub handlers: Vec<String>,
+    /// At startup, replay from the newest stored slot to the chain head (at most
+    /// `admin.replay_max_slot_range` slots) before K-line history is served (default: false)
+    #[serde(default)]
+    pub catch_up_on_start: bool,
+}
+
+impl Default for EventPipelineConfig {
+    fn default() -> Self {
+        Self {
+            handlers: default_event_handlers(),
+            catch_up_on_start: false,
+        }
+    }
+}
+
+fn default_event_handlers() -> Vec<String> {
+    vec!["stats".to_string(), "kline".to_string()]
+}
+
+/// Which records an order event writes to RocksDB
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PersistencePolicy {
+    /// Event record (`tr:`) plus order indexes (`or:`/`uo:`)
+    #[default]
+    Full,
+    /// Order indexes plus a `tm:` replay marker; the event is missing from event queries
+    IndexOnly,
+    /// Event record only; the event doesn't show up in order queries
+    EventOnly,
+}
+
+impl PersistencePolicy {
+    pub fn stores_event_record(self) -> bool {
+        self != Self::IndexOnly
+    }
+
+    pub fn stores_order_index(self) -> bool {
+        self != Self::EventOnly
+    }
+}
+
+/// Per-event-type persistence policy for events that write both an event record and order indexes
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct PersistenceConfig {
+    #[serde(default)]
+    pub long_short: PersistencePolicy,
+    #[serde(default)]
+    pub partial_close: PersistencePolicy,
+}
+
+/// Mint statistics settings
+#[derive(Debug, Deserialize, Clone)]
+pub struct StatsConfig {
+    /// Wallets whose trades are left out of mint detail totals; their events are still stored
+    #[serde(default)]
+    pub excluded_wallets: HashSet<String>,
+    /// Seconds between snapshots of the mint details updated since the previous one, kept under
+    /// `insnap:{mint}:{ts}` for /api/mints/{mint}/history; 0 disables (default: 0)
+    #[serde(default)]
+    pub mint_snapshot_interval_secs: u64,
+    /// Serve GET /api/mints/{mint}/ticker, a mint's latest price, 24h volume and change, open
+    /// orders and liquidations in one call (default: true)
+    #[serde(default = "default_expose_ticker")]
+    pub expose_ticker: bool,
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        Self {
+            excluded_wallets: HashSet::new(),
+            mint_snapshot_interval_secs: 0,
+            expose_ticker: default_expose_ticker(),
+        }
+    }
+}
+
+fn default_expose_ticker() -> bool {
+    true
+}
+
+/// Optional dual-write of klines to an external time-series database in line protocol
+#[derive(Debug, Deserialize, Clone)]
+pub struct TsdbConfig {
+    /// Write every kline update to `url` as well as RocksDB (default: false)
+    #[serde(default)]
+    pub enabled: bool,
+    /// Line-protocol write endpoint, e.g. `http://localhost:8086/api/v2/write?org=spin&bucket=klines`
+    #[serde(default)]
+    pub url: String,
+    /// `Authorization` header sent with each write, e.g. `Token <api token>`
+    #[serde(default)]
+    pub auth_header: Option<String>,
+    /// Measurement name of the written points (default: "kline")
+    #[serde(default = "default_tsdb_measurement")]
+    pub measurement: String,
+    /// Points buffered before a write is sent early (default: 500)
+    #[serde(default = "default_tsdb_batch_size")]
+    pub batch_size: usize,
+    /// Milliseconds between flushes of partially filled batches (default: 1000)
+    #[serde(default = "default_tsdb_flush_interval_ms")]
+    pub flush_interval_ms: u64,
+}
+
+impl Default for TsdbConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: String::new(),
+            auth_header: None,
+            measurement: default_tsdb_measurement(),
+            batch_size: default_tsdb_batch_size(),
+            flush_interval_ms: default_tsdb_flush_interval_ms(),
+        }
+    }
+}
+
+fn default_tsdb_measurement() -> String {
+    "kline".to_string()
+}
+
+fn default_tsdb_batch_size() -> usize {
+    500
+}
+
+fn default_tsdb_flush_interval_ms() -> u64 {
+    1000
+}
+
+/// Message bus the "bus" event handler publishes every event to
+#[derive(Debug, Deserialize, Clone)]
+pub struct BusConfig {
+    /// NATS server address (default: nats://127.0.0.1:4222)
+    #[serde(default = "default_bus_url")]
+    pub url: String,
+    /// Subject of each event; `{mint}` and `{type}` are substituted (default: spinpet.events.{mint}.{type})
+    #[serde(default = "default_bus_subject_template")]
+    pub subject_template: String,
+    /// Publish retries at `retry_delay_ms`, each on a fresh connection, before the delay starts
+    /// doubling and failures are logged as errors; publishing never gives up (default: 3)
+    #[serde(default = "default_bus_max_retries")]
+    pub max_retries: u32,
+    /// Delay before each publish retry, in milliseconds (default: 500)
+    #[serde(default = "default_bus_retry_delay_ms")]
+    pub retry_delay_ms: u64,
+    /// Cap of the doubling retry delay, in milliseconds (default: 30000)
+    #[serde(default = "default_bus_max_retry_delay_ms")]
+    pub max_retry_delay_ms: u64,
+    /// Events waiting for the publish task; while it is full, event handling waits (default: 10000)
+    #[serde(default = "default_bus_queue_size")]
+    pub queue_size: usize,
+}
+
+impl Default for BusConfig {
+    fn default() -> Self {
+        Self {
+            url: default_bus_url(),
+            subject_template: default_bus_subject_template(),
+            max_retries: default_bus_max_retries(),
+            retry_delay_ms: default_bus_retry_delay_ms(),
+            max_retry_delay_ms: default_bus_max_retry_delay_ms(),
+            queue_size: default_bus_queue_size(),
+        }
+    }
+}
+
+fn default_bus_url() -> String {
+    "nats://127.0.0.1:4222".to_string()
+}
+
+fn default_bus_subject_template() -> String {
+    "spinpet.events.{mint}.{type}".to_string()
+}
+
+fn default_bus_max_retries() -> u32 {
+    3
+}
+
+fn default_bus_retry_delay_ms() -> u64 {
+    500
+}
+
+fn default_bus_max_retry_delay_ms() -> u64 {
+    30_000
+}
+
+fn default_bus_queue_size() -> usize {
+    10_000
+}
+
+/// Read-through in-memory cache of hot query responses
+#[derive(Debug, Deserialize, Clone)]
+pub struct ResponseCacheConfig {
+    /// Responses kept across all endpoints; the least recently used is evicted first (default: 1000)
+    #[serde(default = "default_response_cache_max_entries")]
+    pub max_entries: usize,
+    /// TTL in milliseconds per endpoint ("mints", "mint_details"); endpoints not listed are not cached
+    #[serde(default)]
+    pub ttl_ms: HashMap<String, u64>,
+}
+
+impl Default for ResponseCacheConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: default_response_cache_max_entries(),
+            ttl_ms: HashMap::new(),
+        }
+    }
+}
+
+fn default_response_cache_max_entries() -> usize {
+    1000
+}
+
+/// `X-API-Key` validation and per-key rate limits on the read endpoints
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ApiKeyConfig {
+    /// Reject read requests without a valid key with 401 (default: false)
+    #[serde(default)]
+    pub enforce: bool,
+    /// Requests per minute shared by all clients without a valid key while not enforcing;
+    /// 0 leaves them unlimited (default: 0)
+    #[serde(default)]
+    pub anonymous_requests_per_minute: u32,
+    /// Accepted keys
+    #[serde(default)]
+    pub keys: Vec<ApiKeyEntry>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ApiKeyEntry {
+    pub key: String,
+    /// Requests per minute for this key, bursting up to one minute's worth; 0 is unlimited (default: 600)
+    #[serde(default = "default_api_key_requests_per_minute")]
+    pub requests_per_minute: u32,
+}
+
+fn default_api_key_requests_per_minute() -> u32 {
+    600
+}
+
+/// Order lifecycle settings
+#[derive(Debug, Deserialize, Clone)]
+pub struct OrderConfig {
+    /// Seconds between sweeps that move orders past their end_time to the expired set; 0 disables the sweep (default: 60)
+    #[serde(default = "default_expiry_sweep_interval_secs")]
+    pub expiry_sweep_interval_secs: u64,
+    /// Order PDAs are only unique per mint: reject timeline queries for a PDA stored under
+    /// several mints unless `?mint=` picks one, instead of returning every mint's events (default: false)
+    #[serde(default)]
+    pub require_mint_for_shared_pda: bool,
+    /// Serve GET /api/users/{user}/positions/summary, a user's open margin, borrow and position
+    /// amounts summed by side (default: true)
+    #[serde(default = "default_expose_position_summary")]
+    pub expose_position_summary: bool,
+    /// Order events whose raw `order_type` is neither 1 (long) nor 2 (short): "legacy",
+    /// "skip" or "quarantine" (default: quarantine)
+    #[serde(default)]
+    pub unknown_order_type: UnknownOrderTypeMode,
+}
+
+impl Default for OrderConfig {
+    fn default() -> Self {
+        Self {
+            expiry_sweep_interval_secs: default_expiry_sweep_interval_secs(),
+            require_mint_for_shared_pda: false,
+            expose_position_summary: default_expose_position_summary(),
+            unknown_order_type: UnknownOrderTypeMode::default(),
+        }
+    }
+}
+
+/// Handling of LongShort/PartialClose events with an unknown `order_type`
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UnknownOrderTypeMode {
+    /// Log and index them as longs ("dn"), as before
+    Legacy,
+    /// Log and leave them out of the order indexes
+    Skip,
+    /// Log and store the order under `oq:{mint}:{order_pda}` instead of the up/down indexes
+    #[default]
+    Quarantine,
+}
+
+fn default_expiry_sweep_interval_secs() -> u64 {
+    60
+}
+
+fn default_expose_position_summary() -> bool {
+    true
+}
+
+/// Retention pruning of stored events and the global indexes
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct RetentionConfig {
+    /// Seconds between pruning sweeps; 0 disables the pruner (default: 0)
+    #[serde(default)]
+    pub prune_interval_secs: u64,
+    /// `tr:` events more than this many slots behind the newest stored slot are pruned; 0 keeps them (default: 0)
+    #[serde(default)]
+    pub events_retention_slots: u64,
+    /// `insnap:` mint detail snapshots older than this many seconds are pruned; 0 keeps them (default: 0)
+    #[serde(default)]
+    pub mint_snapshot_retention_secs: u64,
+    /// `slotidx:` index behind /api/events/by-slot and /api/events/recent
+    #[serde(default)]
+    pub slot_index: GlobalIndexConfig,
+    /// `liq:` index behind /api/liquidations/recent
+    #[serde(default)]
+    pub liquidations: GlobalIndexConfig,
+    /// `opda:` index behind /api/orders/{order_pda}/timeline
+    #[serde(default)]
+    pub order_timeline: GlobalIndexConfig,
+    /// At startup, rebuild enabled global indexes not yet built over the stored events (newly
+    /// enabled, or an interrupted rebuild) in the background (default: false)
+    #[serde(default)]
+    pub auto_rebuild_indexes: bool,
+    /// Events scanned between the saved progress cursors of an index rebuild (default: 10000)
+    #[serde(default = "default_rebuild_batch_size")]
+    pub rebuild_batch_size: usize,
+}
+
+fn default_rebuild_batch_size() -> usize {
+    10_000
+}
+
+impl RetentionConfig {
+    /// Slots an index keeps, never more than the events it mirrors; 0 keeps everything
+    pub fn index_retention_slots(&self, index: &GlobalIndexConfig) -> u64 {
+        match (index.retention_slots, self.events_retention_slots) {
+            (0, events) => events,
+            (own, 0) => own,
+            (own, events) => own.min(events),
+        }
+    }
+}
+
+/// Whether a global index is written, and how long its entries are kept
+#[derive(Debug, Deserialize, Clone, Copy)]
+pub struct GlobalIndexConfig {
+    /// Write and serve the index (default: true)
+    #[serde(default = "default_index_enabled")]
+    pub enabled: bool,
+    /// Entries more than this many slots behind the newest stored slot are pruned; 0 follows
+    /// `events_retention_slots` (default: 0)
+    #[serde(default)]
+    pub retention_slots: u64,
+}
+
+impl Default for GlobalIndexConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_index_enabled(),
+            retention_slots: 0,
+        }
+    }
+}
+
+fn default_index_enabled() -> bool {
+    true
 }
 
 impl Config {
@@ -95,3 +958,64 @@ impl Config {
         Ok(config)
     }
 }
+
+/// Required keys only; every other setting takes its serde default
+#[cfg(test)]
+const TEST_CONFIG_TOML: &str = r#"
+[server]
+host = "localhost"
+port = 8080
+
+[cors]
+enabled = true
+allow_origins = ["*"]
+
+[logging]
+level = "debug"
+
+[solana]
+rpc_url = "http://localhost:8899"
+ws_url = "ws://localhost:8900"
+program_id = "JBMmrp6jhksqnxDBskkmVvWHhJLaPBjgiMHEroJbUTBZ"
+enable_event_listener = false
+commitment = "processed"
+reconnect_interval = 1
+max_reconnect_attempts = 20
+event_buffer_size = 1000
+event_batch_size = 100
+ping_interval_seconds = 60
+transaction_fetch_attempts = 1
+
+[database]
+rocksdb_path = ""
+
+[ipfs]
+gateway_url = "https://gateway.pinata.cloud/ipfs/"
+request_timeout_seconds = 30
+max_retries = 3
+retry_delay_seconds = 5
+
+[kline]
+enable_kline_service = false
+connection_timeout_secs = 60
+max_subscriptions_per_client = 100
+history_data_limit = 100
+ping_interval_secs = 25
+ping_timeout_secs = 60
+"#;
+
+#[cfg(test)]
+impl Config {
+    /// Config for unit tests: no event listener or K-line service, data under `rocksdb_path`
+    pub fn for_tests(rocksdb_path: &str) -> Self {
+        config::Config::builder()
+            .add_source(config::File::from_str(
+                TEST_CONFIG_TOML,
+                config::FileFormat::Toml,
+            ))
+            .set_override("database.rocksdb_path", rocksdb_path)
+            .and_then(|builder| builder.build())
+            .and_then(|settings| settings.try_deserialize())
+            .expect("test config")
+    }
+}
@@ -1,19 +1,80 @@
 use axum::{
     extract::{Query, State},
+    http::StatusCode,
     response::Json as ResponseJson,
 };
-use chrono::{Local, Utc};
+use chrono::{DateTime, Local, Utc};
+use std::collections::HashSet;
+use std::future::Future;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Semaphore, SemaphorePermit};
 use tracing::info;
 
+use crate::config::AdminConfig;
 use crate::models::*;
-use crate::services::{EventService, EventStorage, KlineSocketService};
+use crate::services::{
+    ApiKeyLimiter, EventService, EventStorage, KlineSocketService, ResponseCache, StateMemoryGuard,
+    WarmupGate,
+};
+use crate::solana::{ListenerConnectionStatus, ParserStats};
 
 /// Application state
 pub struct AppState {
-    pub event_service: Arc<tokio::sync::RwLock<EventService>>,
+    /// Unset in `server.query_only` mode
+    pub event_service: Option<Arc<tokio::sync::RwLock<EventService>>>,
     pub event_storage: Arc<EventStorage>,
     pub kline_service: Option<Arc<KlineSocketService>>,
+    pub admin_config: AdminConfig,
+    pub warmup: Arc<WarmupGate>,
+    pub query_limiter: Semaphore,
+    /// `server.query_timeout_ms`; zero disables the limit
+    pub query_timeout: Duration,
+    pub dedup_cache: Option<Arc<tokio::sync::RwLock<HashSet<String>>>>,
+    /// Counters of the listener's event parser; unset in `server.query_only` mode
+    pub parser_stats: Option<Arc<ParserStats>>,
+    /// Dedup + subscription memory estimate and `server.max_state_memory_mb`; unset in `server.query_only` mode
+    pub state_memory: Option<Arc<StateMemoryGuard>>,
+    /// `[response_cache]` read-through cache of hot query responses
+    pub response_cache: ResponseCache,
+    /// `[api_keys]` validation and rate limits for the read endpoints
+    pub api_keys: ApiKeyLimiter,
+    /// Wall-clock start time reported by /api/info
+    pub started_at: DateTime<Utc>,
+    /// Monotonic start time the uptime is measured from
+    pub started: Instant,
+    /// Programs whose events are indexed (`solana.program_id`)
+    pub program_ids: Vec<String>,
+}
+
+/// Error for endpoints that need the event service while running in `server.query_only` mode
+pub const QUERY_ONLY_MESSAGE: &str = "Event service is not running (server.query_only)";
+
+impl AppState {
+    /// Permit for a scan-heavy query; 429 once `server.max_concurrent_queries` are running
+    pub fn acquire_query_permit(&self) -> Result<SemaphorePermit<'_>, StatusCode> {
+        self.query_limiter.try_acquire().map_err(|_| {
+            tracing::warn!("⚠️ Too many concurrent queries, rejecting request");
+            StatusCode::TOO_MANY_REQUESTS
+        })
+    }
+
+    /// Run a storage query within `server.query_timeout_ms`; 504 once it runs longer.
+    /// The query future (and its RocksDB iterator) is dropped when the timeout fires.
+    pub async fn with_query_timeout<T>(
+        &self,
+        query: impl Future<Output = T>,
+    ) -> Result<T, StatusCode> {
+        if self.query_timeout.is_zero() {
+            return Ok(query.await);
+        }
+        tokio::time::timeout(self.query_timeout, query)
+            .await
+            .map_err(|_| {
+                tracing::warn!("⚠️ Query exceeded {:?}, cancelling", self.query_timeout);
+                StatusCode::GATEWAY_TIMEOUT
+            })
+    }
 }
 
 /// Get current time
@@ -47,6 +108,60 @@ pub async fn get_time(Query(params): Query<TimeQuery>) -> ResponseJson<ApiRespon
     ResponseJson(ApiResponse::success(time_response))
 }
 
+/// Get the deployed version, git commit, start time and uptime
+#[utoipa::path(
+    get,
+    path = "/api/info",
+    responses(
+        (status = 200, description = "Successfully returned server information", body = ApiResponse<ServerInfoResponse>)
+    ),
+    tag = "time"
+)]
+pub async fn get_server_info(
+    State(state): State<Arc<AppState>>,
+) -> ResponseJson<ApiResponse<ServerInfoResponse>> {
+    ResponseJson(ApiResponse::success(ServerInfoResponse {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_commit: env!("SPIN_GIT_COMMIT").to_string(),
+        started_at: state.started_at.timestamp(),
+        uptime_ms: state.started.elapsed().as_millis() as u64,
+        program_ids: state.program_ids.clone(),
+    }))
+}
+
+/// Readiness probe - not ready while the initial catch-up is still warming up or the database
+/// disk is below `database.min_free_disk_mb`
+#[utoipa::path(
+    get,
+    path = "/ready",
+    responses(
+        (status = 200, description = "Server is ready", body = ApiResponse<ReadinessResponse>),
+        (status = 503, description = "Server is warming up or read-only", body = ApiResponse<ReadinessResponse>)
+    ),
+    tag = "events"
+)]
+pub async fn get_readiness(
+    State(state): State<Arc<AppState>>,
+) -> (StatusCode, ResponseJson<ApiResponse<ReadinessResponse>>) {
+    let warm = state.warmup.is_ready();
+    let read_only = state.event_storage.is_read_only();
+    let ready = warm && !read_only;
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (
+        status,
+        ResponseJson(ApiResponse::success(ReadinessResponse {
+            ready,
+            warming_up: !warm,
+            read_only,
+        })),
+    )
+}
+
 /// Get event service status
 #[utoipa::path(
     get,
@@ -59,13 +174,72 @@ pub async fn get_time(Query(params): Query<TimeQuery>) -> ResponseJson<ApiRespon
 pub async fn get_event_status(
     State(state): State<Arc<AppState>>,
 ) -> ResponseJson<ApiResponse<EventServiceStatus>> {
-    let event_service = state.event_service.read().await;
-    let status = event_service.get_status().await;
+    let Some(event_service) = &state.event_service else {
+        return ResponseJson(ApiResponse::error(QUERY_ONLY_MESSAGE));
+    };
+    let status = event_service.read().await.get_status().await;
 
     info!("Event service status query: running={}", status.is_running);
     ResponseJson(ApiResponse::success(status))
 }
 
+/// Listener WebSocket connection state and when it was entered
+#[utoipa::path(
+    get,
+    path = "/api/events/state",
+    responses(
+        (status = 200, description = "Successfully returned the connection state", body = ApiResponse<ListenerConnectionStatus>)
+    ),
+    tag = "events"
+)]
+pub async fn get_connection_state(
+    State(state): State<Arc<AppState>>,
+) -> ResponseJson<ApiResponse<ListenerConnectionStatus>> {
+    let Some(event_service) = &state.event_service else {
+        return ResponseJson(ApiResponse::error(QUERY_ONLY_MESSAGE));
+    };
+    let Some(status) = event_service.read().await.connection_status() else {
+        return ResponseJson(ApiResponse::error("Event listener is not initialized"));
+    };
+    let status = status.read().await.clone();
+
+    info!("Connection state query: {:?}", status.state);
+    ResponseJson(ApiResponse::success(status))
+}
+
+/// Recent listener connection state changes with the error behind each reconnect
+#[utoipa::path(
+    get,
+    path = "/api/events/reconnect-history",
+    responses(
+        (status = 200, description = "Successfully returned the reconnect history", body = ApiResponse<ReconnectHistoryResponse>)
+    ),
+    tag = "events"
+)]
+pub async fn get_reconnect_history(
+    State(state): State<Arc<AppState>>,
+) -> ResponseJson<ApiResponse<ReconnectHistoryResponse>> {
+    let Some(event_service) = &state.event_service else {
+        return ResponseJson(ApiResponse::error(QUERY_ONLY_MESSAGE));
+    };
+    let Some(status) = event_service.read().await.connection_status() else {
+        return ResponseJson(ApiResponse::error("Event listener is not initialized"));
+    };
+    let status = status.read().await;
+    if status.history_size() == 0 {
+        return ResponseJson(ApiResponse::error(
+            "Reconnect history is disabled (solana.reconnect_history_size = 0)",
+        ));
+    }
+
+    let response = ReconnectHistoryResponse {
+        capacity: status.history_size(),
+        events: status.history(),
+    };
+    info!("Reconnect history query: {} events", response.events.len());
+    ResponseJson(ApiResponse::success(response))
+}
+
 /// Get event statistics
 #[utoipa::path(
     get,
@@ -78,8 +252,10 @@ pub async fn get_event_status(
 pub async fn get_event_stats(
     State(state): State<Arc<AppState>>,
 ) -> ResponseJson<ApiResponse<EventStats>> {
-    let event_service = state.event_service.read().await;
-    let stats = event_service.get_stats().await;
+    let Some(event_service) = &state.event_service else {
+        return ResponseJson(ApiResponse::error(QUERY_ONLY_MESSAGE));
+    };
+    let stats = event_service.read().await.get_stats().await;
 
     info!("Event statistics query: total_events={}", stats.total);
     ResponseJson(ApiResponse::success(stats))
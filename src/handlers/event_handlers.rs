@@ -1,20 +1,38 @@
 use axum::{
-    extract::{Query, State},
-    http::StatusCode,
-    response::Json,
+    body::{Body, Bytes},
+    extract::{Path, Query, State},
+    http::{
+        header::{ACCEPT, CONTENT_TYPE},
+        HeaderMap, StatusCode,
+    },
+    response::{IntoResponse, Json, Response},
 };
-use serde::Deserialize;
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use utoipa::ToSchema;
 
 use crate::handlers::AppState;
-use crate::models::{ApiResponse, KlineQuery, KlineQueryResponse};
+use crate::models::{
+    ApiResponse, DedupCacheClearResult, DedupCacheStatus, KlineBatchResponse, KlineData,
+    KlineQuery, KlineQueryResponse, LatestKlinesResponse, MintWatchersResponse,
+};
+use crate::services::event_service::{ReplayResult, TimestampDerivation};
 use crate::services::event_storage::{
-    EventQuery, EventQueryResponse, MintDetailsQueryResponse, MintQuery, MintQueryResponse,
-    OrderQuery, OrderQueryResponse, UserQuery, UserQueryResponse,
+    parse_feed_cursor, ActivityHistogramResponse, CheckpointInfo, DbMetrics, EventQuery,
+    EventQueryResponse, IndexRebuildResult, MintDetailHistoryResponse, MintDetailsQueryResponse,
+    MintQuery, MintQueryResponse, MintTickerResponse, OrderBookQuery, OrderBookResponse,
+    OrderQuery, OrderQueryResponse, OrderTimelineResponse, PrefetchUriResult, RawKeyValue,
+    RecentEventsResponse, RecentLiquidationsResponse, RecentTradesResponse, SlotEventsResponse,
+    UserQuery, UserQueryResponse, UserTransactionData,
 };
+use crate::services::kline_socket::SubscriptionAudit;
+use crate::solana::{ParserStatsSnapshot, SpinPetEvent};
+use crate::utils::flatten_json_record;
 use tracing::info;
 
+const SLOT_INDEX_DISABLED_MESSAGE: &str = "Slot index is disabled (retention.slot_index.enabled)";
+
 /// Event query parameters
 #[derive(Debug, Deserialize, ToSchema, utoipa::IntoParams)]
 pub struct EventQueryParams {
@@ -26,6 +44,8 @@ pub struct EventQueryParams {
     pub limit: Option<usize>,
     /// Sort order: "slot_asc" or "slot_desc"
     pub order_by: Option<String>,
+    /// Response format: "json" (default) or "csv"; `Accept: text/csv` also selects CSV
+    pub format: Option<String>,
 }
 
 /// Mint query parameters
@@ -53,6 +73,67 @@ pub struct OrderQueryParams {
     pub page: Option<usize>,
     /// Items per page (maximum 1000)
     pub limit: Option<usize>,
+    /// Also return orders whose end_time has passed (default false)
+    pub include_expired: Option<bool>,
+}
+
+/// Order book query parameters
+#[derive(Debug, Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct OrderBookQueryParams {
+    /// Maximum price levels per side (default 50, maximum 1000)
+    pub depth: Option<usize>,
+}
+
+/// Order timeline query parameters
+#[derive(Debug, Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct OrderTimelineQueryParams {
+    /// Only return the order's events under this mint (order PDAs are unique per mint only)
+    pub mint: Option<String>,
+}
+
+/// Recent trades query parameters
+#[derive(Debug, Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct RecentTradesQueryParams {
+    /// Number of trades to return (default 50, maximum 1000)
+    pub limit: Option<usize>,
+}
+
+/// Mint activity histogram parameters
+#[derive(Debug, Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct ActivityQueryParams {
+    /// Bucket width in seconds (default 3600)
+    pub bucket_secs: Option<u64>,
+    /// Range start, Unix timestamp in seconds (default: 24 hours before `to`)
+    pub from: Option<i64>,
+    /// Range end (exclusive), Unix timestamp in seconds (default: now)
+    pub to: Option<i64>,
+}
+
+/// Mint detail history parameters
+#[derive(Debug, Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct MintHistoryQueryParams {
+    /// Range start, Unix timestamp in seconds (default: oldest snapshot)
+    pub from: Option<i64>,
+    /// Range end (inclusive), Unix timestamp in seconds (default: newest snapshot)
+    pub to: Option<i64>,
+}
+
+/// Recent liquidations query parameters
+#[derive(Debug, Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct RecentLiquidationsQueryParams {
+    /// Number of liquidations to return (default 50, maximum 1000)
+    pub limit: Option<usize>,
+    /// Only return liquidations after this `slot:signature` cursor (the `next` of a previous response)
+    pub since: Option<String>,
+}
+
+/// Recent events query parameters
+#[derive(Debug, Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct RecentEventsQueryParams {
+    /// Number of events to return (default 50, maximum 1000)
+    pub limit: Option<usize>,
+    /// Only return events after this `slot:signature` cursor (the `next` of a previous response)
+    pub since: Option<String>,
 }
 
 /// User transaction query parameters
@@ -68,6 +149,8 @@ pub struct UserQueryParams {
     pub limit: Option<usize>,
     /// Sort order: "slot_asc" or "slot_desc"
     pub order_by: Option<String>,
+    /// Response format: "json" (default) or "csv"; `Accept: text/csv` also selects CSV
+    pub format: Option<String>,
 }
 
 /// Mint details query parameters
@@ -108,6 +191,223 @@ pub struct KlineQueryParams {
     pub limit: Option<usize>,
     /// Sort order: "time_asc" (oldest first) or "time_desc" (newest first, default)
     pub order_by: Option<String>,
+    /// Insert flat candles (`is_gap: true`) for buckets without trades between stored klines (default: false)
+    pub fill_gaps: Option<bool>,
+    /// Exclude candles with fewer trades (`update_count`) than this, e.g. 2 to hide single-trade candles (default: unfiltered)
+    pub min_update_count: Option<u32>,
+    /// Response format: "json" (default) or "csv"; `Accept: text/csv` also selects CSV
+    pub format: Option<String>,
+}
+
+/// Maximum number of mints accepted by a batch kline query
+const MAX_KLINE_BATCH_MINTS: usize = 20;
+
+/// Batch kline query parameters
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(
+    example = r#"{"mints": ["2M5dgwGNYHAC3CQVYiriY1DYC4GETDDb3ABWv3qsx3Jr", "3TcTZaiCMhCDF2PM7QBzX2aHFeJqLKJrd9LFGLugkr5x"], "interval": "s30", "limit": 100}"#
+)]
+pub struct KlineBatchQueryParams {
+    /// Token addresses (maximum 20)
+    pub mints: Vec<String>,
+    /// Time interval: "s1" (1 second), "s30" (30 seconds), "m5" (5 minutes)
+    pub interval: String,
+    /// Latest klines per mint (maximum 1000, default 50)
+    pub limit: Option<usize>,
+}
+
+/// Maximum number of mints accepted by a latest-kline query
+const MAX_LATEST_KLINE_MINTS: usize = 500;
+
+/// Latest kline query parameters
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(
+    example = r#"{"mints": ["2M5dgwGNYHAC3CQVYiriY1DYC4GETDDb3ABWv3qsx3Jr", "3TcTZaiCMhCDF2PM7QBzX2aHFeJqLKJrd9LFGLugkr5x"], "interval": "m5"}"#
+)]
+pub struct LatestKlinesQueryParams {
+    /// Token addresses (maximum 500)
+    pub mints: Vec<String>,
+    /// Time interval: "s1" (1 second), "s30" (30 seconds), "m5" (5 minutes)
+    pub interval: String,
+}
+
+/// Whether the caller asked for CSV via `?format=csv` or an `Accept: text/csv` header
+fn wants_csv(headers: &HeaderMap, format: Option<&str>) -> bool {
+    if let Some(format) = format {
+        return format.eq_ignore_ascii_case("csv");
+    }
+    headers
+        .get(ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|accept| accept.to_ascii_lowercase().contains("text/csv"))
+}
+
+/// Records served as CSV. The header is a fixed column list for the record type, so rows are
+/// flattened and encoded one at a time while the body streams out
+trait CsvRecord: Serialize {
+    /// Flattened columns in output order; `None` takes the columns of the first record
+    const CSV_COLUMNS: Option<&'static [&'static str]> = None;
+}
+
+/// Flattened columns of every `SpinPetEvent` variant, sorted
+const EVENT_CSV_COLUMNS: &[&str] = &[
+    "base_fee_recipient",
+    "borrow_amount",
+    "borrow_fee",
+    "curve_account",
+    "end_time",
+    "event_type",
+    "fee_discount_flag",
+    "fee_recipient",
+    "final_sol_amount",
+    "final_token_amount",
+    "is_buy",
+    "is_close_long",
+    "latest_price",
+    "lock_lp_end_price",
+    "lock_lp_sol_amount",
+    "lock_lp_start_price",
+    "lock_lp_token_amount",
+    "log_index",
+    "margin_sol_amount",
+    "mint",
+    "mint_account",
+    "name",
+    "order_pda",
+    "order_type",
+    "params_account",
+    "payer",
+    "pool_sol_account",
+    "pool_token_account",
+    "position_asset_amount",
+    "signature",
+    "slot",
+    "sol_amount",
+    "start_time",
+    "swap_fee",
+    "symbol",
+    "timestamp",
+    "timestamp_source",
+    "token_amount",
+    "uri",
+    "user",
+    "user_close_profit",
+    "user_sol_account",
+];
+
+/// Flattened columns of `UserTransactionData` over every user event type, sorted
+const USER_TRANSACTION_CSV_COLUMNS: &[&str] = &[
+    "event_data.borrow_amount",
+    "event_data.borrow_fee",
+    "event_data.curve_account",
+    "event_data.end_time",
+    "event_data.fee_discount_flag",
+    "event_data.final_sol_amount",
+    "event_data.final_token_amount",
+    "event_data.is_close_long",
+    "event_data.latest_price",
+    "event_data.lock_lp_end_price",
+    "event_data.lock_lp_sol_amount",
+    "event_data.lock_lp_start_price",
+    "event_data.lock_lp_token_amount",
+    "event_data.log_index",
+    "event_data.margin_sol_amount",
+    "event_data.mint",
+    "event_data.mint_account",
+    "event_data.order_pda",
+    "event_data.order_type",
+    "event_data.payer",
+    "event_data.position_asset_amount",
+    "event_data.signature",
+    "event_data.slot",
+    "event_data.start_time",
+    "event_data.swap_fee",
+    "event_data.timestamp",
+    "event_data.timestamp_source",
+    "event_data.user",
+    "event_data.user_close_profit",
+    "event_data.user_sol_account",
+    "event_type",
+    "mint_account",
+    "signature",
+    "slot",
+    "timestamp",
+    "user",
+];
+
+/// Flattened columns of `KlineData`, sorted
+const KLINE_CSV_COLUMNS: &[&str] = &[
+    "close",
+    "high",
+    "is_final",
+    "is_gap",
+    "last_slot",
+    "low",
+    "open",
+    "time",
+    "update_count",
+    "volume",
+];
+
+impl CsvRecord for SpinPetEvent {
+    const CSV_COLUMNS: Option<&'static [&'static str]> = Some(EVENT_CSV_COLUMNS);
+}
+
+impl CsvRecord for UserTransactionData {
+    const CSV_COLUMNS: Option<&'static [&'static str]> = Some(USER_TRANSACTION_CSV_COLUMNS);
+}
+
+impl CsvRecord for KlineData {
+    const CSV_COLUMNS: Option<&'static [&'static str]> = Some(KLINE_CSV_COLUMNS);
+}
+
+/// Stream records as CSV, one flattened record per row; cells of columns a record lacks are empty
+fn csv_response<T: CsvRecord + Send + 'static>(records: Vec<T>) -> Response {
+    let columns: Vec<String> = match T::CSV_COLUMNS {
+        Some(columns) => columns.iter().map(|column| column.to_string()).collect(),
+        None => records
+            .first()
+            .map(|record| {
+                flatten_json_record(&serde_json::to_value(record).unwrap_or_default())
+                    .into_keys()
+                    .collect()
+            })
+            .unwrap_or_default(),
+    };
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let header = csv_line(&mut writer, &columns);
+    let lines = stream::iter(records).map(move |record| {
+        let mut row = flatten_json_record(&serde_json::to_value(&record).unwrap_or_default());
+        let cells = columns
+            .iter()
+            .map(|column| row.remove(column).unwrap_or_default());
+        csv_line(&mut writer, cells)
+    });
+    let body = stream::once(async move { header })
+        .chain(lines)
+        .map(Ok::<_, std::io::Error>);
+
+    (
+        [(CONTENT_TYPE, "text/csv; charset=utf-8")],
+        Body::from_stream(body),
+    )
+        .into_response()
+}
+
+/// Encode a single CSV record, quoting cells as needed, and take the bytes out of the writer
+fn csv_line<I>(writer: &mut csv::Writer<Vec<u8>>, cells: I) -> Bytes
+where
+    I: IntoIterator,
+    I::Item: AsRef<[u8]>,
+{
+    if let Err(e) = writer
+        .write_record(cells)
+        .and_then(|()| writer.flush().map_err(csv::Error::from))
+    {
+        tracing::warn!("⚠️ Failed to encode CSV row: {}", e);
+    }
+    Bytes::from(std::mem::take(writer.get_mut()))
 }
 
 /// Event query API
@@ -124,21 +424,33 @@ pub struct KlineQueryParams {
 )]
 pub async fn query_events(
     State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
     Query(params): Query<EventQueryParams>,
-) -> Result<Json<ApiResponse<EventQueryResponse>>, StatusCode> {
+) -> Result<Response, StatusCode> {
+    let csv = wants_csv(&headers, params.format.as_deref());
+
     // Validate parameters
     if params.mint.is_empty() {
-        return Ok(Json(ApiResponse::error("mint parameter cannot be empty")));
+        return Ok(Json(ApiResponse::<EventQueryResponse>::error(
+            "mint parameter cannot be empty",
+        ))
+        .into_response());
     }
 
     let limit = params.limit.unwrap_or(50);
     if limit > 1000 {
-        return Ok(Json(ApiResponse::error("limit cannot exceed 1000")));
+        return Ok(Json(ApiResponse::<EventQueryResponse>::error(
+            "limit cannot exceed 1000",
+        ))
+        .into_response());
     }
 
     let page = params.page.unwrap_or(1);
     if page < 1 {
-        return Ok(Json(ApiResponse::error("page must be greater than 0")));
+        return Ok(Json(ApiResponse::<EventQueryResponse>::error(
+            "page must be greater than 0",
+        ))
+        .into_response());
     }
 
     // Build query
@@ -150,8 +462,13 @@ pub async fn query_events(
     };
 
     // Execute query
-    match state.event_storage.query_events(query).await {
-        Ok(response) => Ok(Json(ApiResponse::success(response))),
+    let _permit = state.acquire_query_permit()?;
+    match state
+        .with_query_timeout(state.event_storage.query_events(query))
+        .await?
+    {
+        Ok(response) if csv => Ok(csv_response(response.events)),
+        Ok(response) => Ok(Json(ApiResponse::success(response)).into_response()),
         Err(e) => {
             tracing::error!("Failed to query events: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
@@ -194,6 +511,17 @@ pub async fn query_mints(
         }
     }
 
+    let cache_key = format!(
+        "{}:{}:{}:{}",
+        page,
+        limit,
+        params.sort_by.as_deref().unwrap_or(""),
+        params.cursor.as_deref().unwrap_or("")
+    );
+    if let Some(response) = state.response_cache.get("mints", &cache_key) {
+        return Ok(Json(ApiResponse::success(response)));
+    }
+
     // Build query
     let query = MintQuery {
         page: Some(page),
@@ -203,8 +531,17 @@ pub async fn query_mints(
     };
 
     // Execute query
-    match state.event_storage.query_mints(query).await {
-        Ok(response) => Ok(Json(ApiResponse::success(response))),
+    let _permit = state.acquire_query_permit()?;
+    match state
+        .with_query_timeout(state.event_storage.query_mints(query))
+        .await?
+    {
+        Ok(response) => {
+            state
+                .response_cache
+                .insert("mints", &cache_key, response.clone());
+            Ok(Json(ApiResponse::success(response)))
+        }
         Err(e) => {
             tracing::error!("Failed to query mint information: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
@@ -233,7 +570,7 @@ pub async fn query_orders(
         return Ok(Json(ApiResponse::error("mint parameter cannot be empty")));
     }
 
-    if !matches!(params.order_type.as_str(), "up_orders" | "down_orders") {
+    if crate::solana::OrderType::from_query_name(&params.order_type).is_none() {
         return Ok(Json(ApiResponse::error(
             "type parameter must be 'up_orders' or 'down_orders'",
         )));
@@ -255,10 +592,15 @@ pub async fn query_orders(
         order_type: params.order_type,
         page: Some(page),
         limit: Some(limit),
+        include_expired: params.include_expired.unwrap_or(false),
     };
 
     // Execute query
-    match state.event_storage.query_orders(query).await {
+    let _permit = state.acquire_query_permit()?;
+    match state
+        .with_query_timeout(state.event_storage.query_orders(query))
+        .await?
+    {
         Ok(response) => Ok(Json(ApiResponse::success(response))),
         Err(e) => {
             tracing::error!("Failed to query order information: {}", e);
@@ -267,204 +609,751 @@ pub async fn query_orders(
     }
 }
 
-/// Query user transaction information
+/// Query aggregated order book depth
 #[utoipa::path(
     get,
-    path = "/api/user_event",
-    params(UserQueryParams),
+    path = "/api/orders/{mint}/book",
+    params(
+        ("mint" = String, Path, description = "Token address"),
+        OrderBookQueryParams
+    ),
     responses(
-        (status = 200, description = "Query successful", body = UserQueryResponse),
+        (status = 200, description = "Query successful", body = OrderBookResponse),
         (status = 400, description = "Bad request"),
         (status = 500, description = "Internal server error")
     ),
-    tags = ["user"]
+    tags = ["orders"]
 )]
-pub async fn query_user_transactions(
+pub async fn query_order_book(
     State(state): State<Arc<AppState>>,
-    Query(params): Query<UserQueryParams>,
-) -> Result<Json<ApiResponse<UserQueryResponse>>, StatusCode> {
+    Path(mint): Path<String>,
+    Query(params): Query<OrderBookQueryParams>,
+) -> Result<Json<ApiResponse<OrderBookResponse>>, StatusCode> {
     // Validate parameters
-    if params.user.is_empty() {
-        return Ok(Json(ApiResponse::error("user parameter cannot be empty")));
-    }
-
-    let limit = params.limit.unwrap_or(50);
-    if limit > 1000 {
-        return Ok(Json(ApiResponse::error("limit cannot exceed 1000")));
+    if mint.is_empty() {
+        return Ok(Json(ApiResponse::error("mint parameter cannot be empty")));
     }
 
-    let page = params.page.unwrap_or(1);
-    if page < 1 {
-        return Ok(Json(ApiResponse::error("page must be greater than 0")));
+    let depth = params.depth.unwrap_or(50);
+    if !(1..=1000).contains(&depth) {
+        return Ok(Json(ApiResponse::error("depth must be between 1 and 1000")));
     }
 
     // Build query
-    let query = UserQuery {
-        user: params.user,
-        mint_account: params.mint,
-        page: Some(page),
-        limit: Some(limit),
-        order_by: params.order_by,
+    let query = OrderBookQuery {
+        mint_account: mint,
+        depth: Some(depth),
     };
 
     // Execute query
-    match state.event_storage.query_user_transactions(query).await {
+    let _permit = state.acquire_query_permit()?;
+    match state
+        .with_query_timeout(state.event_storage.query_order_book(query))
+        .await?
+    {
         Ok(response) => Ok(Json(ApiResponse::success(response))),
         Err(e) => {
-            tracing::error!("Failed to query user transaction information: {}", e);
+            tracing::error!("Failed to query order book: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
     }
 }
 
-/// Query mint details
+/// Query the most recent trades for a mint in compact form
 #[utoipa::path(
-    post,
-    path = "/api/details",
-    request_body = MintDetailsQueryParams,
+    get,
+    path = "/api/mints/{mint}/trades",
+    params(
+        ("mint" = String, Path, description = "Token address"),
+        RecentTradesQueryParams
+    ),
     responses(
-        (status = 200, description = "Query successful", body = MintDetailsQueryResponse),
+        (status = 200, description = "Query successful", body = RecentTradesResponse),
         (status = 400, description = "Bad request"),
         (status = 500, description = "Internal server error")
     ),
     tags = ["mints"]
 )]
-pub async fn query_mint_details(
+pub async fn query_recent_trades(
     State(state): State<Arc<AppState>>,
-    Json(params): Json<MintDetailsQueryParams>,
-) -> Result<Json<ApiResponse<MintDetailsQueryResponse>>, StatusCode> {
-    // Extract mint accounts from params
-    let mut mint_accounts = params.mints;
-
-    if mint_accounts.is_empty() {
-        return Ok(Json(ApiResponse::error("mints parameter cannot be empty")));
+    Path(mint): Path<String>,
+    Query(params): Query<RecentTradesQueryParams>,
+) -> Result<Json<ApiResponse<RecentTradesResponse>>, StatusCode> {
+    // Validate parameters
+    if mint.is_empty() {
+        return Ok(Json(ApiResponse::error("mint parameter cannot be empty")));
     }
 
-    // Limit to 1000 mint addresses
-    if mint_accounts.len() > 1000 {
-        tracing::warn!(
-            "Too many mint addresses requested: {}, limiting to 1000",
-            mint_accounts.len()
-        );
-        mint_accounts = mint_accounts[0..1000].to_vec();
+    let limit = params.limit.unwrap_or(50);
+    if !(1..=1000).contains(&limit) {
+        return Ok(Json(ApiResponse::error("limit must be between 1 and 1000")));
     }
 
-    // Build query
-    let query = crate::services::MintDetailsQuery { mint_accounts };
-
     // Execute query
-    match state.event_storage.query_mint_details(query).await {
-        Ok(response) => {
-            tracing::info!("Mint details query: found {} mint details", response.total);
-            Ok(Json(ApiResponse::success(response)))
-        }
+    let _permit = state.acquire_query_permit()?;
+    match state
+        .with_query_timeout(state.event_storage.query_recent_trades(&mint, limit))
+        .await?
+    {
+        Ok(response) => Ok(Json(ApiResponse::success(response))),
         Err(e) => {
-            tracing::error!("Failed to query mint details: {}", e);
+            tracing::error!("Failed to query recent trades: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
     }
 }
 
-/// Query user orders
+/// Count a mint's events per time bucket (activity heatmap)
 #[utoipa::path(
     get,
-    path = "/api/user_orders",
-    params(UserOrderQueryParams),
+    path = "/api/mints/{mint}/activity",
+    params(
+        ("mint" = String, Path, description = "Token address"),
+        ActivityQueryParams
+    ),
     responses(
-        (status = 200, description = "Query successful", body = crate::services::UserOrderQueryResponse),
+        (status = 200, description = "Query successful", body = ActivityHistogramResponse),
         (status = 400, description = "Bad request"),
         (status = 500, description = "Internal server error")
     ),
-    tags = ["user"]
+    tags = ["mints"]
 )]
-pub async fn query_user_orders(
+pub async fn query_mint_activity(
     State(state): State<Arc<AppState>>,
-    Query(params): Query<UserOrderQueryParams>,
-) -> Result<Json<ApiResponse<crate::services::UserOrderQueryResponse>>, StatusCode> {
+    Path(mint): Path<String>,
+    Query(params): Query<ActivityQueryParams>,
+) -> Result<Json<ApiResponse<ActivityHistogramResponse>>, StatusCode> {
     // Validate parameters
-    if params.user.is_empty() {
-        return Ok(Json(ApiResponse::error("user parameter cannot be empty")));
+    if mint.is_empty() {
+        return Ok(Json(ApiResponse::error("mint parameter cannot be empty")));
     }
 
-    let limit = params.limit.unwrap_or(50);
-    if limit > 1000 {
-        return Ok(Json(ApiResponse::error("limit cannot exceed 1000")));
+    let bucket_secs = params.bucket_secs.unwrap_or(3600);
+    if bucket_secs == 0 {
+        return Ok(Json(ApiResponse::error(
+            "bucket_secs must be greater than 0",
+        )));
     }
-
-    let page = params.page.unwrap_or(1);
-    if page < 1 {
-        return Ok(Json(ApiResponse::error("page must be greater than 0")));
+    let to = params.to.unwrap_or_else(|| chrono::Utc::now().timestamp());
+    let from = params.from.unwrap_or(to - 86_400);
+    if from >= to {
+        return Ok(Json(ApiResponse::error("from must be earlier than to")));
+    }
+    if (to - from) as u64 / bucket_secs > 1000 {
+        return Ok(Json(ApiResponse::error(
+            "range covers more than 1000 buckets, increase bucket_secs",
+        )));
     }
 
-    // Validate order_by parameter
-    if let Some(ref order_by) = params.order_by {
-        if !matches!(order_by.as_str(), "start_time_asc" | "start_time_desc") {
-            return Ok(Json(ApiResponse::error(
-                "order_by must be 'start_time_asc' or 'start_time_desc'",
-            )));
+    // Execute query
+    let _permit = state.acquire_query_permit()?;
+    match state
+        .with_query_timeout(
+            state
+                .event_storage
+                .query_event_histogram(&mint, bucket_secs, from, to),
+        )
+        .await?
+    {
+        Ok(response) => Ok(Json(ApiResponse::success(response))),
+        Err(e) => {
+            tracing::error!("Failed to query mint activity: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
     }
+}
 
-    // Build query
-    let query = crate::services::UserOrderQuery {
-        user: params.user,
-        mint_account: params.mint,
-        page: Some(page),
-        limit: Some(limit),
-        order_by: params.order_by,
-    };
-
-    // Execute query
-    match state.event_storage.query_user_orders(query).await {
-        Ok(response) => {
-            tracing::info!(
-                "User orders query: found {} orders for user {}",
-                response.total,
-                response.user
-            );
-            Ok(Json(ApiResponse::success(response)))
+/// Snapshots of a mint's detail over time, taken every `stats.mint_snapshot_interval_secs`
+/// while the mint is updated
+#[utoipa::path(
+    get,
+    path = "/api/mints/{mint}/history",
+    params(
+        ("mint" = String, Path, description = "Token address"),
+        MintHistoryQueryParams
+    ),
+    responses(
+        (status = 200, description = "Query successful", body = MintDetailHistoryResponse),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal server error")
+    ),
+    tags = ["mints"]
+)]
+pub async fn query_mint_history(
+    State(state): State<Arc<AppState>>,
+    Path(mint): Path<String>,
+    Query(params): Query<MintHistoryQueryParams>,
+) -> Result<Json<ApiResponse<MintDetailHistoryResponse>>, StatusCode> {
+    if mint.is_empty() {
+        return Ok(Json(ApiResponse::error("mint parameter cannot be empty")));
+    }
+    if let (Some(from), Some(to)) = (params.from, params.to) {
+        if from > to {
+            return Ok(Json(ApiResponse::error("from must not be later than to")));
         }
+    }
+
+    let _permit = state.acquire_query_permit()?;
+    match state
+        .with_query_timeout(state.event_storage.query_mint_detail_history(
+            &mint,
+            params.from,
+            params.to,
+        ))
+        .await?
+    {
+        Ok(response) => Ok(Json(ApiResponse::success(response))),
         Err(e) => {
-            tracing::error!("Failed to query user orders: {}", e);
+            tracing::error!("Failed to query mint history: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
     }
 }
 
-/// Get database statistics
+/// Latest price, 24h volume and price change, open orders by side and total liquidations of
+/// a mint in one call
 #[utoipa::path(
     get,
-    path = "/api/events/stats",
+    path = "/api/mints/{mint}/ticker",
+    params(
+        ("mint" = String, Path, description = "Token address")
+    ),
     responses(
-        (status = 200, description = "Get successful", body = String),
+        (status = 200, description = "Query successful", body = MintTickerResponse),
+        (status = 400, description = "Bad request"),
         (status = 500, description = "Internal server error")
     ),
-    tags = ["events"]
+    tags = ["mints"]
 )]
-pub async fn get_db_stats(
+pub async fn query_mint_ticker(
     State(state): State<Arc<AppState>>,
-) -> Result<Json<ApiResponse<String>>, StatusCode> {
-    match state.event_storage.get_stats() {
-        Ok(stats) => Ok(Json(ApiResponse::success(stats))),
+    Path(mint): Path<String>,
+) -> Result<Json<ApiResponse<MintTickerResponse>>, StatusCode> {
+    if mint.is_empty() {
+        return Ok(Json(ApiResponse::error("mint parameter cannot be empty")));
+    }
+
+    let _permit = state.acquire_query_permit()?;
+    match state
+        .with_query_timeout(state.event_storage.query_ticker(&mint))
+        .await?
+    {
+        Ok(Some(ticker)) => Ok(Json(ApiResponse::success(ticker))),
+        Ok(None) => Ok(Json(ApiResponse::error("Mint not found"))),
         Err(e) => {
-            tracing::error!("Failed to get database statistics: {}", e);
+            tracing::error!("Failed to query mint ticker: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
     }
 }
 
-/// Test IPFS functionality - Create a test token with URI
+/// Query every event of one order (open, partial closes, full close or liquidation). A PDA
+/// stored under several mints returns every mint's events, listed in `mints`, unless `mint`
+/// picks one; with `orders.require_mint_for_shared_pda` such queries must pick one
 #[utoipa::path(
-    post,
-    path = "/api/test-ipfs",
-    request_body = TestIpfsParams,
+    get,
+    path = "/api/orders/{order_pda}/timeline",
+    params(
+        ("order_pda" = String, Path, description = "Order PDA address"),
+        OrderTimelineQueryParams
+    ),
     responses(
-        (status = 200, description = "Test token created and IPFS fetch triggered", body = ApiResponse<String>),
-        (status = 500, description = "Internal server error"),
+        (status = 200, description = "Query successful", body = OrderTimelineResponse),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal server error")
     ),
-    tags = ["test"]
+    tags = ["orders"]
 )]
-pub async fn test_ipfs_functionality(
+pub async fn query_order_timeline(
+    State(state): State<Arc<AppState>>,
+    Path(order_pda): Path<String>,
+    Query(params): Query<OrderTimelineQueryParams>,
+) -> Result<Json<ApiResponse<OrderTimelineResponse>>, StatusCode> {
+    if order_pda.is_empty() {
+        return Ok(Json(ApiResponse::error(
+            "order_pda parameter cannot be empty",
+        )));
+    }
+    if !state
+        .event_storage
+        .retention_config()
+        .order_timeline
+        .enabled
+    {
+        return Ok(Json(ApiResponse::error(
+            "Order timeline index is disabled (retention.order_timeline.enabled)",
+        )));
+    }
+
+    match state
+        .event_storage
+        .query_order_timeline(&order_pda, params.mint.as_deref())
+        .await
+    {
+        Ok(response)
+            if response.mint_account.is_none()
+                && response.mints.len() > 1
+                && state
+                    .event_storage
+                    .order_config()
+                    .require_mint_for_shared_pda =>
+        {
+            Ok(Json(ApiResponse::error(&format!(
+                "order_pda is stored under several mints ({}), pass mint to pick one",
+                response.mints.join(", ")
+            ))))
+        }
+        Ok(response) => Ok(Json(ApiResponse::success(response))),
+        Err(e) => {
+            tracing::error!("Failed to query order timeline: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Query every event stored for one slot across all mints
+#[utoipa::path(
+    get,
+    path = "/api/events/by-slot/{slot}",
+    params(
+        ("slot" = u64, Path, description = "Slot number")
+    ),
+    responses(
+        (status = 200, description = "Query successful", body = SlotEventsResponse),
+        (status = 500, description = "Internal server error")
+    ),
+    tags = ["events"]
+)]
+pub async fn query_events_by_slot(
+    State(state): State<Arc<AppState>>,
+    Path(slot): Path<u64>,
+) -> Result<Json<ApiResponse<SlotEventsResponse>>, StatusCode> {
+    if !state.event_storage.retention_config().slot_index.enabled {
+        return Ok(Json(ApiResponse::error(SLOT_INDEX_DISABLED_MESSAGE)));
+    }
+
+    match state.event_storage.query_events_by_slot(slot).await {
+        Ok(response) => Ok(Json(ApiResponse::success(response))),
+        Err(e) => {
+            tracing::error!("Failed to query events for slot {}: {}", slot, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Query the most recent events across all mints, or only those after a `since` cursor
+#[utoipa::path(
+    get,
+    path = "/api/events/recent",
+    params(RecentEventsQueryParams),
+    responses(
+        (status = 200, description = "Query successful", body = RecentEventsResponse),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal server error")
+    ),
+    tags = ["events"]
+)]
+pub async fn query_recent_events(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<RecentEventsQueryParams>,
+) -> Result<Json<ApiResponse<RecentEventsResponse>>, StatusCode> {
+    if !state.event_storage.retention_config().slot_index.enabled {
+        return Ok(Json(ApiResponse::error(SLOT_INDEX_DISABLED_MESSAGE)));
+    }
+    let limit = params.limit.unwrap_or(50);
+    if !(1..=1000).contains(&limit) {
+        return Ok(Json(ApiResponse::error("limit must be between 1 and 1000")));
+    }
+    let since = match params.since.as_deref().map(parse_feed_cursor) {
+        Some(None) => {
+            return Ok(Json(ApiResponse::error(
+                "since must be a slot:signature cursor",
+            )))
+        }
+        Some(Some(cursor)) => Some(cursor),
+        None => None,
+    };
+
+    // Execute query
+    let _permit = state.acquire_query_permit()?;
+    match state
+        .with_query_timeout(state.event_storage.query_recent_events(
+            limit,
+            since.as_ref().map(|(slot, sig)| (*slot, sig.as_str())),
+        ))
+        .await?
+    {
+        Ok(response) => Ok(Json(ApiResponse::success(response))),
+        Err(e) => {
+            tracing::error!("Failed to query recent events: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Query the most recent force liquidations across all mints
+#[utoipa::path(
+    get,
+    path = "/api/liquidations/recent",
+    params(RecentLiquidationsQueryParams),
+    responses(
+        (status = 200, description = "Query successful", body = RecentLiquidationsResponse),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal server error")
+    ),
+    tags = ["orders"]
+)]
+pub async fn query_recent_liquidations(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<RecentLiquidationsQueryParams>,
+) -> Result<Json<ApiResponse<RecentLiquidationsResponse>>, StatusCode> {
+    if !state.event_storage.retention_config().liquidations.enabled {
+        return Ok(Json(ApiResponse::error(
+            "Liquidation index is disabled (retention.liquidations.enabled)",
+        )));
+    }
+    let limit = params.limit.unwrap_or(50);
+    if !(1..=1000).contains(&limit) {
+        return Ok(Json(ApiResponse::error("limit must be between 1 and 1000")));
+    }
+    let since = match params.since.as_deref().map(parse_feed_cursor) {
+        Some(None) => {
+            return Ok(Json(ApiResponse::error(
+                "since must be a slot:signature cursor",
+            )))
+        }
+        Some(Some(cursor)) => Some(cursor),
+        None => None,
+    };
+
+    // Execute query
+    let _permit = state.acquire_query_permit()?;
+    match state
+        .with_query_timeout(state.event_storage.query_recent_liquidations(
+            limit,
+            since.as_ref().map(|(slot, sig)| (*slot, sig.as_str())),
+        ))
+        .await?
+    {
+        Ok(response) => Ok(Json(ApiResponse::success(response))),
+        Err(e) => {
+            tracing::error!("Failed to query recent liquidations: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Query user transaction information
+#[utoipa::path(
+    get,
+    path = "/api/user_event",
+    params(UserQueryParams),
+    responses(
+        (status = 200, description = "Query successful", body = UserQueryResponse),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal server error")
+    ),
+    tags = ["user"]
+)]
+pub async fn query_user_transactions(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<UserQueryParams>,
+) -> Result<Response, StatusCode> {
+    let csv = wants_csv(&headers, params.format.as_deref());
+
+    // Validate parameters
+    if params.user.is_empty() {
+        return Ok(Json(ApiResponse::<UserQueryResponse>::error(
+            "user parameter cannot be empty",
+        ))
+        .into_response());
+    }
+
+    let limit = params.limit.unwrap_or(50);
+    if limit > 1000 {
+        return Ok(Json(ApiResponse::<UserQueryResponse>::error(
+            "limit cannot exceed 1000",
+        ))
+        .into_response());
+    }
+
+    let page = params.page.unwrap_or(1);
+    if page < 1 {
+        return Ok(Json(ApiResponse::<UserQueryResponse>::error(
+            "page must be greater than 0",
+        ))
+        .into_response());
+    }
+
+    // Build query
+    let query = UserQuery {
+        user: params.user,
+        mint_account: params.mint,
+        page: Some(page),
+        limit: Some(limit),
+        order_by: params.order_by,
+    };
+
+    // Execute query
+    let _permit = state.acquire_query_permit()?;
+    match state
+        .with_query_timeout(state.event_storage.query_user_transactions(query))
+        .await?
+    {
+        Ok(response) if csv => Ok(csv_response(response.transactions)),
+        Ok(response) => Ok(Json(ApiResponse::success(response)).into_response()),
+        Err(e) => {
+            tracing::error!("Failed to query user transaction information: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Query mint details
+#[utoipa::path(
+    post,
+    path = "/api/details",
+    request_body = MintDetailsQueryParams,
+    responses(
+        (status = 200, description = "Query successful", body = MintDetailsQueryResponse),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal server error")
+    ),
+    tags = ["mints"]
+)]
+pub async fn query_mint_details(
+    State(state): State<Arc<AppState>>,
+    Json(params): Json<MintDetailsQueryParams>,
+) -> Result<Json<ApiResponse<MintDetailsQueryResponse>>, StatusCode> {
+    // Extract mint accounts from params
+    let mut mint_accounts = params.mints;
+
+    if mint_accounts.is_empty() {
+        return Ok(Json(ApiResponse::error("mints parameter cannot be empty")));
+    }
+
+    // Limit to 1000 mint addresses
+    if mint_accounts.len() > 1000 {
+        tracing::warn!(
+            "Too many mint addresses requested: {}, limiting to 1000",
+            mint_accounts.len()
+        );
+        mint_accounts = mint_accounts[0..1000].to_vec();
+    }
+
+    let cache_key = mint_accounts.join(",");
+    if let Some(response) = state.response_cache.get("mint_details", &cache_key) {
+        return Ok(Json(ApiResponse::success(response)));
+    }
+
+    // Build query
+    let query = crate::services::MintDetailsQuery { mint_accounts };
+
+    // Execute query
+    match state.event_storage.query_mint_details(query).await {
+        Ok(response) => {
+            tracing::info!("Mint details query: found {} mint details", response.total);
+            state
+                .response_cache
+                .insert("mint_details", &cache_key, response.clone());
+            Ok(Json(ApiResponse::success(response)))
+        }
+        Err(e) => {
+            tracing::error!("Failed to query mint details: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Query user orders
+#[utoipa::path(
+    get,
+    path = "/api/user_orders",
+    params(UserOrderQueryParams),
+    responses(
+        (status = 200, description = "Query successful", body = crate::services::UserOrderQueryResponse),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal server error")
+    ),
+    tags = ["user"]
+)]
+pub async fn query_user_orders(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<UserOrderQueryParams>,
+) -> Result<Json<ApiResponse<crate::services::UserOrderQueryResponse>>, StatusCode> {
+    // Validate parameters
+    if params.user.is_empty() {
+        return Ok(Json(ApiResponse::error("user parameter cannot be empty")));
+    }
+
+    let limit = params.limit.unwrap_or(50);
+    if limit > 1000 {
+        return Ok(Json(ApiResponse::error("limit cannot exceed 1000")));
+    }
+
+    let page = params.page.unwrap_or(1);
+    if page < 1 {
+        return Ok(Json(ApiResponse::error("page must be greater than 0")));
+    }
+
+    // Validate order_by parameter
+    if let Some(ref order_by) = params.order_by {
+        if !matches!(order_by.as_str(), "start_time_asc" | "start_time_desc") {
+            return Ok(Json(ApiResponse::error(
+                "order_by must be 'start_time_asc' or 'start_time_desc'",
+            )));
+        }
+    }
+
+    // Build query
+    let query = crate::services::UserOrderQuery {
+        user: params.user,
+        mint_account: params.mint,
+        page: Some(page),
+        limit: Some(limit),
+        order_by: params.order_by,
+    };
+
+    // Execute query
+    let _permit = state.acquire_query_permit()?;
+    match state
+        .with_query_timeout(state.event_storage.query_user_orders(query))
+        .await?
+    {
+        Ok(response) => {
+            tracing::info!(
+                "User orders query: found {} orders for user {}",
+                response.total,
+                response.user
+            );
+            Ok(Json(ApiResponse::success(response)))
+        }
+        Err(e) => {
+            tracing::error!("Failed to query user orders: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// A user's open orders across all mints summed by side, for summary widgets that don't need
+/// the order list. Served unless `orders.expose_position_summary` is off
+#[utoipa::path(
+    get,
+    path = "/api/users/{user}/positions/summary",
+    params(
+        ("user" = String, Path, description = "User wallet address")
+    ),
+    responses(
+        (status = 200, description = "Query successful", body = crate::services::UserPositionSummaryResponse),
+        (status = 500, description = "Internal server error")
+    ),
+    tags = ["user"]
+)]
+pub async fn query_user_position_summary(
+    State(state): State<Arc<AppState>>,
+    Path(user): Path<String>,
+) -> Result<Json<ApiResponse<crate::services::UserPositionSummaryResponse>>, StatusCode> {
+    let _permit = state.acquire_query_permit()?;
+    match state
+        .with_query_timeout(state.event_storage.query_user_position_summary(&user))
+        .await?
+    {
+        Ok(response) => Ok(Json(ApiResponse::success(response))),
+        Err(e) => {
+            tracing::error!("Failed to query user position summary: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Get database statistics
+#[utoipa::path(
+    get,
+    path = "/api/events/stats",
+    responses(
+        (status = 200, description = "Get successful", body = String),
+        (status = 500, description = "Internal server error")
+    ),
+    tags = ["events"]
+)]
+pub async fn get_db_stats(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    match state.event_storage.get_stats() {
+        Ok(stats) => Ok(Json(ApiResponse::success(stats))),
+        Err(e) => {
+            tracing::error!("Failed to get database statistics: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Get structured RocksDB metrics
+#[utoipa::path(
+    get,
+    path = "/api/events/db-metrics",
+    responses(
+        (status = 200, description = "Get successful", body = DbMetrics),
+        (status = 500, description = "Internal server error")
+    ),
+    tags = ["events"]
+)]
+pub async fn get_db_metrics(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ApiResponse<DbMetrics>>, StatusCode> {
+    match state.event_storage.get_db_metrics() {
+        Ok(metrics) => Ok(Json(ApiResponse::success(metrics))),
+        Err(e) => {
+            tracing::error!("Failed to get database metrics: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Prometheus metrics
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    responses(
+        (status = 200, description = "Metrics in the Prometheus text format", body = String),
+        (status = 500, description = "Internal server error")
+    ),
+    tags = ["events"]
+)]
+pub async fn get_metrics(State(state): State<Arc<AppState>>) -> Result<Response, StatusCode> {
+    match state.event_storage.get_db_metrics() {
+        Ok(metrics) => {
+            let mut body = metrics.to_prometheus();
+            if let Some(stats) = &state.parser_stats {
+                body.push_str(&stats.snapshot().to_prometheus());
+            }
+            body.push_str(&state.response_cache.to_prometheus());
+            body.push_str(&state.event_storage.receive_latency().to_prometheus());
+            if let Some(guard) = &state.state_memory {
+                body.push_str(&guard.estimate().await.to_prometheus(guard.budget_bytes()));
+            }
+            Ok(([(CONTENT_TYPE, "text/plain; version=0.0.4")], body).into_response())
+        }
+        Err(e) => {
+            tracing::error!("Failed to get database metrics: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Test IPFS functionality - Create a test token with URI
+#[utoipa::path(
+    post,
+    path = "/api/test-ipfs",
+    request_body = TestIpfsParams,
+    responses(
+        (status = 200, description = "Test token created and IPFS fetch triggered", body = ApiResponse<String>),
+        (status = 500, description = "Internal server error"),
+    ),
+    tags = ["test"]
+)]
+pub async fn test_ipfs_functionality(
     State(state): State<Arc<AppState>>,
     Json(params): Json<TestIpfsParams>,
 ) -> Result<Json<ApiResponse<String>>, StatusCode> {
@@ -489,6 +1378,8 @@ pub async fn test_ipfs_functionality(
         fee_discount_flag: 0,
         slot: 123456789,
         timestamp: Utc::now(),
+        timestamp_source: TimestampSource::ServerReceive,
+        log_index: 0,
         signature: "test_signature".to_string(),
     });
 
@@ -560,35 +1451,49 @@ pub async fn create_test_order(
 )]
 pub async fn query_kline_data(
     State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
     Query(params): Query<KlineQueryParams>,
-) -> Result<Json<ApiResponse<KlineQueryResponse>>, StatusCode> {
+) -> Result<Response, StatusCode> {
+    let csv = wants_csv(&headers, params.format.as_deref());
+
     // Validate parameters
     if params.mint.is_empty() {
-        return Ok(Json(ApiResponse::error("mint parameter cannot be empty")));
+        return Ok(Json(ApiResponse::<KlineQueryResponse>::error(
+            "mint parameter cannot be empty",
+        ))
+        .into_response());
     }
 
     if !matches!(params.interval.as_str(), "s1" | "s30" | "m5") {
-        return Ok(Json(ApiResponse::error(
+        return Ok(Json(ApiResponse::<KlineQueryResponse>::error(
             "interval parameter must be one of: s1, s30, m5",
-        )));
+        ))
+        .into_response());
     }
 
     let limit = params.limit.unwrap_or(50);
     if limit > 1000 {
-        return Ok(Json(ApiResponse::error("limit cannot exceed 1000")));
+        return Ok(Json(ApiResponse::<KlineQueryResponse>::error(
+            "limit cannot exceed 1000",
+        ))
+        .into_response());
     }
 
     let page = params.page.unwrap_or(1);
     if page < 1 {
-        return Ok(Json(ApiResponse::error("page must be greater than 0")));
+        return Ok(Json(ApiResponse::<KlineQueryResponse>::error(
+            "page must be greater than 0",
+        ))
+        .into_response());
     }
 
     // Validate order_by parameter
     if let Some(ref order_by) = params.order_by {
         if !matches!(order_by.as_str(), "time_asc" | "time_desc") {
-            return Ok(Json(ApiResponse::error(
+            return Ok(Json(ApiResponse::<KlineQueryResponse>::error(
                 "order_by must be 'time_asc' or 'time_desc'",
-            )));
+            ))
+            .into_response());
         }
     }
 
@@ -599,10 +1504,16 @@ pub async fn query_kline_data(
         page: Some(page),
         limit: Some(limit),
         order_by: params.order_by,
+        fill_gaps: params.fill_gaps.unwrap_or(false),
+        min_update_count: params.min_update_count,
     };
 
     // Execute query
-    match state.event_storage.query_kline_data(query).await {
+    let _permit = state.acquire_query_permit()?;
+    match state
+        .with_query_timeout(state.event_storage.query_kline_data(query))
+        .await?
+    {
         Ok(response) => {
             tracing::info!(
                 "Kline query: found {} klines for mint {} interval {}",
@@ -610,7 +1521,10 @@ pub async fn query_kline_data(
                 response.mint_account,
                 response.interval
             );
-            Ok(Json(ApiResponse::success(response)))
+            if csv {
+                return Ok(csv_response(response.klines));
+            }
+            Ok(Json(ApiResponse::success(response)).into_response())
         }
         Err(e) => {
             tracing::error!("Failed to query kline data: {}", e);
@@ -619,6 +1533,143 @@ pub async fn query_kline_data(
     }
 }
 
+/// Query klines for several mints at once
+#[utoipa::path(
+    post,
+    path = "/api/klines/batch",
+    request_body = KlineBatchQueryParams,
+    responses(
+        (status = 200, description = "Query successful", body = KlineBatchResponse),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal server error")
+    ),
+    tags = ["kline"]
+)]
+pub async fn query_kline_batch(
+    State(state): State<Arc<AppState>>,
+    Json(params): Json<KlineBatchQueryParams>,
+) -> Result<Json<ApiResponse<KlineBatchResponse>>, StatusCode> {
+    let mut mints = params.mints;
+    mints.sort();
+    mints.dedup();
+
+    if mints.is_empty() || mints.iter().any(|mint| mint.is_empty()) {
+        return Ok(Json(ApiResponse::error("mints parameter cannot be empty")));
+    }
+
+    if mints.len() > MAX_KLINE_BATCH_MINTS {
+        return Ok(Json(ApiResponse::error(&format!(
+            "mints cannot exceed {}",
+            MAX_KLINE_BATCH_MINTS
+        ))));
+    }
+
+    if !matches!(params.interval.as_str(), "s1" | "s30" | "m5") {
+        return Ok(Json(ApiResponse::error(
+            "interval parameter must be one of: s1, s30, m5",
+        )));
+    }
+
+    let limit = params.limit.unwrap_or(50);
+    if !(1..=1000).contains(&limit) {
+        return Ok(Json(ApiResponse::error("limit must be between 1 and 1000")));
+    }
+
+    let _permit = state.acquire_query_permit()?;
+    match state
+        .with_query_timeout(
+            state
+                .event_storage
+                .query_kline_batch(&mints, &params.interval, limit),
+        )
+        .await?
+    {
+        Ok(response) => Ok(Json(ApiResponse::success(response))),
+        Err(e) => {
+            tracing::error!("Failed to query batch kline data: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Newest kline of each mint, for overviews of many mints at once
+#[utoipa::path(
+    post,
+    path = "/api/klines/latest",
+    request_body = LatestKlinesQueryParams,
+    responses(
+        (status = 200, description = "Query successful", body = LatestKlinesResponse),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal server error")
+    ),
+    tags = ["kline"]
+)]
+pub async fn query_latest_klines(
+    State(state): State<Arc<AppState>>,
+    Json(params): Json<LatestKlinesQueryParams>,
+) -> Result<Json<ApiResponse<LatestKlinesResponse>>, StatusCode> {
+    let mut mints = params.mints;
+    mints.sort();
+    mints.dedup();
+
+    if mints.is_empty() || mints.iter().any(|mint| mint.is_empty()) {
+        return Ok(Json(ApiResponse::error("mints parameter cannot be empty")));
+    }
+
+    if mints.len() > MAX_LATEST_KLINE_MINTS {
+        return Ok(Json(ApiResponse::error(&format!(
+            "mints cannot exceed {}",
+            MAX_LATEST_KLINE_MINTS
+        ))));
+    }
+
+    if !matches!(params.interval.as_str(), "s1" | "s30" | "m5") {
+        return Ok(Json(ApiResponse::error(
+            "interval parameter must be one of: s1, s30, m5",
+        )));
+    }
+
+    let _permit = state.acquire_query_permit()?;
+    match state
+        .with_query_timeout(
+            state
+                .event_storage
+                .query_latest_klines(&mints, &params.interval),
+        )
+        .await?
+    {
+        Ok(response) => Ok(Json(ApiResponse::success(response))),
+        Err(e) => {
+            tracing::error!("Failed to query latest klines: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Live subscriber counts of a mint's K-line rooms ("N people watching")
+#[utoipa::path(
+    get,
+    path = "/api/klines/{mint}/watchers",
+    params(
+        ("mint" = String, Path, description = "Mint account address")
+    ),
+    responses(
+        (status = 200, description = "Subscriber counts per interval", body = MintWatchersResponse)
+    ),
+    tags = ["kline"]
+)]
+pub async fn get_mint_watchers(
+    State(state): State<Arc<AppState>>,
+    Path(mint): Path<String>,
+) -> Json<ApiResponse<MintWatchersResponse>> {
+    match &state.kline_service {
+        Some(kline_service) => Json(ApiResponse::success(
+            kline_service.get_watchers(&mint).await,
+        )),
+        None => Json(ApiResponse::error("K-line service is not enabled")),
+    }
+}
+
 /// Get K-line subscription details and communication statistics
 #[utoipa::path(
     get,
@@ -654,3 +1705,372 @@ pub async fn get_kline_subscriptions(
         }
     }
 }
+
+/// Slot replay request
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ReplayRequest {
+    /// First slot to re-ingest (inclusive)
+    pub from_slot: u64,
+    /// Last slot to re-ingest (inclusive)
+    pub to_slot: u64,
+}
+
+/// Re-ingest the program's transactions in a slot range from chain
+#[utoipa::path(
+    post,
+    path = "/api/admin/replay",
+    request_body = ReplayRequest,
+    responses(
+        (status = 200, description = "Replay completed", body = ReplayResult),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal server error")
+    ),
+    tags = ["admin"]
+)]
+pub async fn replay_slot_range(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<ReplayRequest>,
+) -> Result<Json<ApiResponse<ReplayResult>>, StatusCode> {
+    if request.from_slot > request.to_slot {
+        return Ok(Json(ApiResponse::error(
+            "from_slot must not be greater than to_slot",
+        )));
+    }
+
+    let max_range = state.admin_config.replay_max_slot_range;
+    if request.to_slot - request.from_slot > max_range {
+        return Ok(Json(ApiResponse::error(&format!(
+            "slot range cannot exceed {}",
+            max_range
+        ))));
+    }
+
+    let timestamps = match TimestampDerivation::from_config(&state.admin_config) {
+        Ok(timestamps) => timestamps,
+        Err(e) => return Ok(Json(ApiResponse::error(&e.to_string()))),
+    };
+
+    info!(
+        "Admin replay requested for slots {}..={} (timestamp derivation: {:?})",
+        request.from_slot, request.to_slot, timestamps
+    );
+
+    let Some(event_service) = &state.event_service else {
+        return Ok(Json(ApiResponse::error(
+            crate::handlers::QUERY_ONLY_MESSAGE,
+        )));
+    };
+    let event_service = event_service.read().await;
+    match event_service
+        .replay_slot_range(request.from_slot, request.to_slot, timestamps)
+        .await
+    {
+        Ok(result) => Ok(Json(ApiResponse::success(result))),
+        Err(e) => {
+            tracing::error!("Failed to replay slot range: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Inspect the listener's processed-signature dedup cache
+#[utoipa::path(
+    get,
+    path = "/api/admin/dedup",
+    responses(
+        (status = 200, description = "Dedup cache status", body = DedupCacheStatus)
+    ),
+    tags = ["admin"]
+)]
+pub async fn get_dedup_cache(
+    State(state): State<Arc<AppState>>,
+) -> Json<ApiResponse<DedupCacheStatus>> {
+    match &state.dedup_cache {
+        Some(cache) => {
+            let cache = cache.read().await;
+            Json(ApiResponse::success(DedupCacheStatus {
+                size: cache.len(),
+                capacity: cache.capacity(),
+            }))
+        }
+        None => Json(ApiResponse::error("Event listener is not initialized")),
+    }
+}
+
+/// Event parser counters: data lines seen, parses and failures per event type
+#[utoipa::path(
+    get,
+    path = "/api/events/parser-stats",
+    responses(
+        (status = 200, description = "Parser counters", body = ParserStatsSnapshot)
+    ),
+    tags = ["events"]
+)]
+pub async fn get_parser_stats(
+    State(state): State<Arc<AppState>>,
+) -> Json<ApiResponse<ParserStatsSnapshot>> {
+    match &state.parser_stats {
+        Some(stats) => Json(ApiResponse::success(stats.snapshot())),
+        None => Json(ApiResponse::error("Event listener is not initialized")),
+    }
+}
+
+/// Clear the dedup cache so the next occurrence of each signature is reprocessed
+#[utoipa::path(
+    delete,
+    path = "/api/admin/dedup",
+    responses(
+        (status = 200, description = "Dedup cache cleared", body = DedupCacheClearResult)
+    ),
+    tags = ["admin"]
+)]
+pub async fn clear_dedup_cache(
+    State(state): State<Arc<AppState>>,
+) -> Json<ApiResponse<DedupCacheClearResult>> {
+    match &state.dedup_cache {
+        Some(cache) => {
+            let mut cache = cache.write().await;
+            let cleared = cache.len();
+            cache.clear();
+            info!("Admin cleared {} processed signatures", cleared);
+            Json(ApiResponse::success(DedupCacheClearResult { cleared }))
+        }
+        None => Json(ApiResponse::error("Event listener is not initialized")),
+    }
+}
+
+/// Checkpoint request
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CheckpointRequest {
+    /// Directory to write the checkpoint to; must not exist yet
+    pub path: String,
+}
+
+/// Create a consistent hard-linked snapshot of the database
+#[utoipa::path(
+    post,
+    path = "/api/admin/checkpoint",
+    request_body = CheckpointRequest,
+    responses(
+        (status = 200, description = "Checkpoint created", body = CheckpointInfo),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal server error")
+    ),
+    tags = ["admin"]
+)]
+pub async fn create_checkpoint(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<CheckpointRequest>,
+) -> Result<Json<ApiResponse<CheckpointInfo>>, StatusCode> {
+    if request.path.trim().is_empty() {
+        return Ok(Json(ApiResponse::error("path cannot be empty")));
+    }
+    if std::path::Path::new(&request.path).exists() {
+        return Ok(Json(ApiResponse::error("path already exists")));
+    }
+
+    info!("Admin checkpoint requested at {}", request.path);
+
+    match state.event_storage.create_checkpoint(&request.path) {
+        Ok(checkpoint) => Ok(Json(ApiResponse::success(checkpoint))),
+        Err(e) => {
+            tracing::error!("Failed to create checkpoint: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Index rebuild request
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RebuildIndexRequest {
+    /// `slot_index`, `liquidations` or `order_timeline`
+    pub index: String,
+}
+
+/// Repopulate a global index from the stored events, resuming an interrupted rebuild
+#[utoipa::path(
+    post,
+    path = "/api/admin/rebuild-index",
+    request_body = RebuildIndexRequest,
+    responses(
+        (status = 200, description = "Index rebuilt", body = IndexRebuildResult),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal server error")
+    ),
+    tags = ["admin"]
+)]
+pub async fn rebuild_index(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<RebuildIndexRequest>,
+) -> Result<Json<ApiResponse<IndexRebuildResult>>, StatusCode> {
+    match state.event_storage.rebuildable_index(&request.index) {
+        Err(e) => return Ok(Json(ApiResponse::error(&e.to_string()))),
+        Ok(index) if !index.enabled => {
+            return Ok(Json(ApiResponse::error(&format!(
+                "Index is disabled (retention.{}.enabled)",
+                request.index
+            ))))
+        }
+        Ok(_) => {}
+    }
+    if state.event_storage.index_rebuild_running(&request.index) {
+        return Ok(Json(ApiResponse::error(&format!(
+            "A rebuild of the {} index is already running",
+            request.index
+        ))));
+    }
+
+    info!("Admin rebuild of the {} index requested", request.index);
+
+    let event_storage = Arc::clone(&state.event_storage);
+    match tokio::task::spawn_blocking(move || event_storage.rebuild_index(&request.index)).await {
+        Ok(Ok(result)) => Ok(Json(ApiResponse::success(result))),
+        Ok(Err(e)) => {
+            tracing::error!("Failed to rebuild index: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+        Err(e) => {
+            tracing::error!("Index rebuild task failed: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Raw key lookup parameters
+#[derive(Debug, Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct RawKeyQueryParams {
+    /// Exact storage key, e.g. `in:{mint}` or a kline key
+    pub key: String,
+}
+
+/// Return the exact bytes stored under a key (base64) plus an attempted JSON parse
+#[utoipa::path(
+    get,
+    path = "/api/admin/raw",
+    params(RawKeyQueryParams),
+    responses(
+        (status = 200, description = "Stored value", body = RawKeyValue),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal server error")
+    ),
+    tags = ["admin"]
+)]
+pub async fn get_raw_value(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<RawKeyQueryParams>,
+) -> Result<Json<ApiResponse<RawKeyValue>>, StatusCode> {
+    if params.key.is_empty() {
+        return Ok(Json(ApiResponse::error("key cannot be empty")));
+    }
+
+    match state.event_storage.get_raw(&params.key) {
+        Ok(Some(value)) => Ok(Json(ApiResponse::success(value))),
+        Ok(None) => Ok(Json(ApiResponse::error("key not found"))),
+        Err(e) => {
+            tracing::error!("Failed to read raw key {}: {}", params.key, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// URI prefetch request
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct PrefetchUriRequest {
+    /// Mints whose stored URI metadata should be (re)fetched
+    pub mints: Vec<String>,
+}
+
+/// Fetch and store IPFS metadata for a batch of mints, bounded by `server.max_concurrent_queries`
+#[utoipa::path(
+    post,
+    path = "/api/admin/prefetch-uri",
+    request_body = PrefetchUriRequest,
+    responses(
+        (status = 200, description = "Prefetch finished", body = PrefetchUriResult),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal server error")
+    ),
+    tags = ["admin"]
+)]
+pub async fn prefetch_uri_data(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<PrefetchUriRequest>,
+) -> Result<Json<ApiResponse<PrefetchUriResult>>, StatusCode> {
+    if request.mints.is_empty() {
+        return Ok(Json(ApiResponse::error("mints cannot be empty")));
+    }
+    if request.mints.len() > 1000 {
+        return Ok(Json(ApiResponse::error(
+            "Cannot prefetch more than 1000 mints at once",
+        )));
+    }
+
+    info!(
+        "Admin URI prefetch requested for {} mints",
+        request.mints.len()
+    );
+
+    match state
+        .event_storage
+        .prefetch_uri_data(request.mints, &state.query_limiter)
+        .await
+    {
+        Ok(result) => Ok(Json(ApiResponse::success(result))),
+        Err(e) => {
+            tracing::error!("Failed to prefetch URI data: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Report subscription index entries that reference missing connections or are empty
+#[utoipa::path(
+    get,
+    path = "/api/admin/subscriptions/audit",
+    responses(
+        (status = 200, description = "Audit finished", body = SubscriptionAudit)
+    ),
+    tags = ["admin"]
+)]
+pub async fn audit_kline_subscriptions(
+    State(state): State<Arc<AppState>>,
+) -> Json<ApiResponse<SubscriptionAudit>> {
+    match &state.kline_service {
+        Some(kline_service) => {
+            let manager = kline_service.subscriptions.read().await;
+            Json(ApiResponse::success(manager.audit()))
+        }
+        None => Json(ApiResponse::error("K-line service is not enabled")),
+    }
+}
+
+/// Remove the subscription index entries reported by the audit
+#[utoipa::path(
+    post,
+    path = "/api/admin/subscriptions/audit",
+    responses(
+        (status = 200, description = "Removed entries", body = SubscriptionAudit)
+    ),
+    tags = ["admin"]
+)]
+pub async fn repair_kline_subscriptions(
+    State(state): State<Arc<AppState>>,
+) -> Json<ApiResponse<SubscriptionAudit>> {
+    match &state.kline_service {
+        Some(kline_service) => {
+            let mut manager = kline_service.subscriptions.write().await;
+            let repaired = manager.repair();
+            if !repaired.is_clean() {
+                info!(
+                    "Admin repaired subscription index: {} dangling subscribers, {} empty intervals, {} empty mints, {} orphan clients",
+                    repaired.dangling_subscribers.len(),
+                    repaired.empty_intervals.len(),
+                    repaired.empty_mints.len(),
+                    repaired.orphan_client_subscriptions.len()
+                );
+            }
+            Json(ApiResponse::success(repaired))
+        }
+        None => Json(ApiResponse::error("K-line service is not enabled")),
+    }
+}
@@ -8,16 +8,232 @@ mod utils;
 
 use std::env;
 use std::sync::Arc;
+use std::time::Duration;
 use tracing::{error, info, warn};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, Layer};
 
-use crate::config::Config;
+use crate::config::{Config, LogFormat, ParserSelftestMode};
 use crate::handlers::AppState;
 use crate::routes::create_router;
 use crate::services::{
-    start_connection_cleanup_task, start_performance_monitoring_task, EventService, KlineConfig,
-    KlineEventHandler, KlineSocketService, StatsEventHandler,
+    start_checkpoint_task, start_connection_cleanup_task, start_connection_reconcile_task,
+    start_disk_space_task, start_heartbeat_task, start_index_rebuild_task,
+    start_kline_finalization_task, start_mint_snapshot_task, start_order_expiry_task,
+    start_performance_monitoring_task, start_retention_task, start_state_memory_task,
+    start_tsdb_flush_task, start_uri_refetch_task, ApiKeyLimiter, BusEventHandler, EventService,
+    EventStorage, KlineConfig, KlineEventHandler, KlineSocketService, ResponseCache,
+    StateMemoryGuard, StatsEventHandler, TimestampDerivation, WarmupGate,
 };
+use crate::solana::selftest::PARSER_FIXTURES;
+use crate::solana::{CompositeEventHandler, EventHandler, EventParser};
+use socketioxide::layer::SocketIoLayer;
+
+/// Initialize the K-line socket service if enabled.
+/// A failure is logged and leaves real-time push disabled instead of aborting startup.
+fn init_kline_service<F>(
+    config: &Config,
+    event_storage: &Arc<EventStorage>,
+    warmup: &Arc<WarmupGate>,
+    create: F,
+) -> (Option<Arc<KlineSocketService>>, Option<SocketIoLayer>)
+where
+    F: FnOnce(
+        Arc<EventStorage>,
+        KlineConfig,
+    ) -> anyhow::Result<(KlineSocketService, SocketIoLayer)>,
+{
+    if !config.kline.enable_kline_service {
+        info!("ℹ️ K-line WebSocket service is disabled");
+        return (None, None);
+    }
+
+    info!("🚀 Initializing K-line WebSocket service");
+
+    // 创建K线配置
+    let kline_config = KlineConfig::from_config(&config.kline);
+
+    // 创建K线推送服务 - 使用共享的事件存储
+    match create(Arc::clone(event_storage), kline_config) {
+        Ok((mut service, layer)) => {
+            service.warmup = Arc::clone(warmup);
+            let kline_service = Arc::new(service);
+
+            // 设置事件处理器
+            kline_service.setup_socket_handlers();
+
+            info!("✅ K-line WebSocket service initialized");
+            (Some(kline_service), Some(layer))
+        }
+        Err(e) => {
+            error!("❌ Failed to create K-line socket service: {}", e);
+            warn!("⚠️ Continuing with REST API only, K-line real-time push is disabled");
+            (None, None)
+        }
+    }
+}
+
+/// Timestamp derivation of automatic catch-up replays (`admin.replay_timestamp_derivation`)
+fn replay_timestamps(config: &Config) -> TimestampDerivation {
+    TimestampDerivation::from_config(&config.admin).unwrap_or_else(|e| {
+        warn!("⚠️ Catch-up replays keep receive times: {}", e);
+        TimestampDerivation::Off
+    })
+}
+
+/// Build the event handler pipeline from `events.handlers`, in configured order
+fn build_event_handler(
+    config: &Config,
+    event_storage: &Arc<EventStorage>,
+    kline_service: Option<&Arc<KlineSocketService>>,
+) -> Arc<dyn EventHandler> {
+    let mut handlers: Vec<Arc<dyn EventHandler>> = Vec::new();
+
+    for name in &config.events.handlers {
+        match name.as_str() {
+            "stats" => handlers.push(Arc::new(StatsEventHandler::new(Arc::clone(event_storage)))),
+            "kline" => match kline_service {
+                Some(kline_service) => {
+                    handlers.push(Arc::new(KlineEventHandler::new(Arc::clone(kline_service))))
+                }
+                None => warn!("⚠️ Event handler 'kline' skipped: K-line service is not running"),
+            },
+            "bus" => {
+                info!(
+                    "📤 Publishing events to {} on {}",
+                    config.bus.url, config.bus.subject_template
+                );
+                handlers.push(Arc::new(BusEventHandler::from_config(&config.bus)))
+            }
+            other => warn!(
+                "⚠️ Unknown event handler '{}' in events.handlers, skipping",
+                other
+            ),
+        }
+    }
+
+    if !config.events.handlers.iter().any(|name| name == "stats") {
+        warn!("⚠️ 'stats' handler is not enabled, events will not be stored");
+    }
+    info!("✅ Event handler pipeline: {:?}", config.events.handlers);
+
+    Arc::new(CompositeEventHandler::new(handlers))
+}
+
+/// Formatting layer for `logging.format`; JSON lines carry timestamp, level, target and span fields
+fn log_format_layer<S, W>(format: LogFormat, writer: W) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer().with_writer(writer);
+    match format {
+        LogFormat::Pretty => layer.boxed(),
+        LogFormat::Json => layer
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .boxed(),
+    }
+}
+
+/// Run the parser self-test per `solana.parser_selftest`; an error means startup must stop
+fn run_parser_selftest(config: &Config) -> anyhow::Result<()> {
+    let mode = config.solana.parser_selftest;
+    if mode == ParserSelftestMode::Off {
+        return Ok(());
+    }
+
+    let result =
+        EventParser::new(&config.solana.program_id).and_then(|parser| parser.run_selftest());
+    match (result, mode) {
+        (Ok(()), _) => {
+            info!(
+                "✅ Parser self-test passed ({} fixtures)",
+                PARSER_FIXTURES.len()
+            );
+            Ok(())
+        }
+        (Err(e), ParserSelftestMode::Strict) => Err(e),
+        (Err(e), _) => {
+            error!("❌ {}", e);
+            warn!("⚠️ Continuing startup, on-chain events may be misparsed");
+            Ok(())
+        }
+    }
+}
+
+/// Router for `server.query_only`: REST queries over the existing storage with no event service,
+/// K-line push or background tasks
+fn build_query_only_app(config: &Config, event_storage: Arc<EventStorage>) -> axum::Router {
+    let app_state = Arc::new(AppState {
+        event_service: None,
+        event_storage,
+        kline_service: None,
+        admin_config: config.admin.clone(),
+        warmup: Arc::new(WarmupGate::new(true)),
+        query_limiter: tokio::sync::Semaphore::new(config.server.max_concurrent_queries),
+        query_timeout: Duration::from_millis(config.server.query_timeout_ms),
+        dedup_cache: None,
+        parser_stats: None,
+        state_memory: None,
+        response_cache: ResponseCache::new(&config.response_cache),
+        api_keys: ApiKeyLimiter::new(&config.api_keys),
+        started_at: chrono::Utc::now(),
+        started: std::time::Instant::now(),
+        program_ids: vec![config.solana.program_id.clone()],
+    });
+    create_router(config, app_state)
+}
+
+/// Bind the configured address and serve `app` until the server stops
+async fn serve(config: &Config, app: axum::Router, kline_enabled: bool) {
+    // Create listener
+    let addr = format!("{}:{}", config.server.host, config.server.port);
+    let listener = match tokio::net::TcpListener::bind(&addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("❌ Cannot bind to address {}: {}", addr, e);
+            std::process::exit(1);
+        }
+    };
+
+    // Startup information
+    info!("🚀 Spin Server started successfully!");
+    info!("📍 Listening on: http://{}", addr);
+    info!("📖 API documentation: http://{}/swagger-ui", addr);
+    info!(
+        "🔧 Environment: {}",
+        env::var("RUST_ENV").unwrap_or_else(|_| "development".to_string())
+    );
+    info!("🔗 Solana program ID: {}", config.solana.program_id);
+    info!("📋 Available endpoints:");
+    info!("  GET  /api/time           - Get current time");
+    if config.server.expose_info {
+        info!("  GET  /api/info           - Get version, git commit and uptime");
+    }
+    info!("  GET  /api/events/status  - Get event service status");
+    info!("  GET  /api/events/state   - Get listener connection state");
+    info!("  GET  /api/events/stats   - Get event statistics");
+    info!("  GET  /api/events         - Query event data");
+    info!("  GET  /api/events/db-stats - Get database statistics");
+
+    info!("  GET  /swagger-ui         - API documentation interface");
+
+    if kline_enabled {
+        info!("📊 K-line WebSocket service:");
+        info!("  WS   ws://0.0.0.0:5051/kline - Real-time K-line data subscription");
+        info!("  Events: subscribe, unsubscribe, history, kline_data");
+        info!("  Supported intervals: s1, s30, m5");
+    }
+
+    // Start server
+    if let Err(e) = axum::serve(listener, app).await {
+        error!("❌ Server runtime error: {}", e);
+        std::process::exit(1);
+    }
+}
 
 #[tokio::main]
 async fn main() {
@@ -37,9 +253,15 @@ async fn main() {
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| format!("spin_server={}", log_level).into()),
         )
-        .with(tracing_subscriber::fmt::layer())
+        .with(log_format_layer(config.logging.format, std::io::stdout))
         .init();
 
+    // Catch discriminator/offset regressions before serving traffic
+    if let Err(e) = run_parser_selftest(&config) {
+        error!("❌ {}", e);
+        std::process::exit(1);
+    }
+
     // 首先创建共享的事件存储 - 避免重复初始化 RocksDB
     let event_storage = match crate::services::EventStorage::new(&config) {
         Ok(storage) => Arc::new(storage),
@@ -50,102 +272,54 @@ async fn main() {
     };
     info!("✅ Event storage initialized successfully");
 
-    // Initialize K线推送服务 (如果启用)
-    let (kline_socket_service, socketio_layer) = if config.kline.enable_kline_service {
-        info!("🚀 Initializing K-line WebSocket service");
-
-        // 创建K线配置
-        let kline_config = KlineConfig::from_config(&config.kline);
-
-        // 创建K线推送服务 - 使用共享的事件存储
-        let (kline_service, layer) =
-            match KlineSocketService::new(Arc::clone(&event_storage), kline_config) {
-                Ok((service, layer)) => (Arc::new(service), Some(layer)),
-                Err(e) => {
-                    error!("❌ Failed to create K-line socket service: {}", e);
-                    std::process::exit(1);
-                }
-            };
-
-        // 设置事件处理器
-        kline_service.setup_socket_handlers();
-
-        info!("✅ K-line WebSocket service initialized");
-        (Some(kline_service), layer)
-    } else {
-        info!("ℹ️ K-line WebSocket service is disabled");
-        (None, None)
-    };
-
-    // Initialize event service with K-line support
-    let event_service = match &kline_socket_service {
-        Some(kline_service) => {
-            // 使用共享的事件存储
-            let shared_event_storage = Arc::clone(&event_storage);
-
-            // 创建统计事件处理器
-            let stats_handler = Arc::new(StatsEventHandler::new(Arc::clone(&shared_event_storage)));
+    if config.server.query_only {
+        info!("🔎 Query-only mode: no event listener, K-line push or background tasks");
+        serve(&config, build_query_only_app(&config, event_storage), false).await;
+        return;
+    }
 
-            // 创建K线事件处理器
-            let kline_handler = Arc::new(KlineEventHandler::new(
-                Arc::clone(&stats_handler),
-                Arc::clone(kline_service),
-            ));
+    // Initialize K线推送服务 (如果启用) - 失败时仅禁用实时推送, REST API 继续运行
+    // 预热状态: 初始追赶完成前延迟K线历史推送
+    let warmup = Arc::new(WarmupGate::new(false));
+    let (kline_socket_service, socketio_layer) =
+        init_kline_service(&config, &event_storage, &warmup, KlineSocketService::new);
 
-            // 使用自定义事件处理器和共享存储创建事件服务
+    // Initialize event service with the configured handler pipeline
+    let event_handler = build_event_handler(&config, &event_storage, kline_socket_service.as_ref());
+    let event_service = match EventService::with_handler_and_storage(
+        &config,
+        Arc::clone(&event_handler),
+        Arc::clone(&event_storage),
+    ) {
+        Ok(service) => Arc::new(tokio::sync::RwLock::new(service)),
+        Err(e) => {
+            error!("❌ Failed to initialize event service: {}", e);
+            warn!("⚠️ Continuing without event listener enabled");
+            // Create a disabled config but use same storage
+            let mut disabled_config = config.clone();
+            disabled_config.solana.enable_event_listener = false;
+            disabled_config.solana.program_id = "11111111111111111111111111111111".to_string(); // Use a valid program ID
+            let fallback_handler = Arc::new(StatsEventHandler::new(Arc::clone(&event_storage)));
             match EventService::with_handler_and_storage(
-                &config,
-                Arc::clone(&kline_handler) as Arc<dyn crate::solana::EventHandler>,
-                Arc::clone(&shared_event_storage),
+                &disabled_config,
+                Arc::clone(&fallback_handler) as Arc<dyn EventHandler>,
+                Arc::clone(&event_storage),
             ) {
                 Ok(service) => Arc::new(tokio::sync::RwLock::new(service)),
-                Err(e) => {
+                Err(fallback_err) => {
                     error!(
-                        "❌ Failed to initialize event service with K-line handler: {}",
-                        e
+                        "❌ Unable to create disabled event service: {}",
+                        fallback_err
                     );
                     std::process::exit(1);
                 }
             }
         }
-        None => {
-            // 创建标准的事件服务 - 但重用现有的事件存储
-            let stats_handler = Arc::new(StatsEventHandler::new(Arc::clone(&event_storage)));
-            match EventService::with_handler_and_storage(
-                &config,
-                Arc::clone(&stats_handler) as Arc<dyn crate::solana::EventHandler>,
-                Arc::clone(&event_storage),
-            ) {
-                Ok(service) => Arc::new(tokio::sync::RwLock::new(service)),
-                Err(e) => {
-                    error!("❌ Failed to initialize event service: {}", e);
-                    warn!("⚠️ Continuing without event listener enabled");
-                    // Create a disabled config but use same storage
-                    let mut disabled_config = config.clone();
-                    disabled_config.solana.enable_event_listener = false;
-                    disabled_config.solana.program_id =
-                        "11111111111111111111111111111111".to_string(); // Use a valid program ID
-                    let fallback_handler =
-                        Arc::new(StatsEventHandler::new(Arc::clone(&event_storage)));
-                    match EventService::with_handler_and_storage(
-                        &disabled_config,
-                        Arc::clone(&fallback_handler) as Arc<dyn crate::solana::EventHandler>,
-                        Arc::clone(&event_storage),
-                    ) {
-                        Ok(service) => Arc::new(tokio::sync::RwLock::new(service)),
-                        Err(fallback_err) => {
-                            error!(
-                                "❌ Unable to create disabled event service: {}",
-                                fallback_err
-                            );
-                            std::process::exit(1);
-                        }
-                    }
-                }
-            }
-        }
     };
 
+    // Read before the listener starts storing new events: the catch-up starts here
+    let stored_slot = event_storage.latest_slot();
+
     // Try to start event listener
     if config.solana.enable_event_listener {
         let mut service = event_service.write().await;
@@ -162,13 +336,178 @@ async fn main() {
         info!("ℹ️ Event listener is disabled");
     }
 
+    // Turn read-only and pause the listener while the database disk is low
+    if let Some(disk_guard) = event_storage.disk_guard() {
+        let _disk_handle = start_disk_space_task(
+            Arc::clone(disk_guard),
+            Arc::clone(&event_service),
+            config.database.disk_check_interval_secs.max(1),
+            config.admin.replay_max_slot_range,
+            replay_timestamps(&config),
+        )
+        .await;
+        info!(
+            "✅ Disk space watch started ({} MB minimum, every {}s)",
+            config.database.min_free_disk_mb, config.database.disk_check_interval_secs
+        );
+    }
+
+    // Warm-up: K-line history waits until events missed while down are replayed
+    if config.events.catch_up_on_start && config.solana.enable_event_listener {
+        let event_service = Arc::clone(&event_service);
+        let warmup = Arc::clone(&warmup);
+        let max_slots = config.admin.replay_max_slot_range;
+        let timestamps = replay_timestamps(&config);
+        tokio::spawn(async move {
+            let result = event_service
+                .read()
+                .await
+                .catch_up(stored_slot, max_slots, timestamps)
+                .await;
+            match result {
+                Ok(Some(result)) => info!(
+                    "✅ Caught up slots {}..={} ({} events)",
+                    result.from_slot, result.to_slot, result.events_processed
+                ),
+                Ok(None) => info!("ℹ️ Nothing to catch up"),
+                Err(e) => warn!("⚠️ Startup catch-up failed: {}", e),
+            }
+            warmup.mark_ready();
+            info!("✅ Warm-up complete, serving K-line history");
+        });
+    } else {
+        warmup.mark_ready();
+        info!("✅ Warm-up complete, serving K-line history");
+    }
+
     // 使用已经创建的共享事件存储
 
+    // Scheduled consistent DB checkpoints
+    if config.database.checkpoint_interval > 0 {
+        let _checkpoint_handle = start_checkpoint_task(
+            Arc::clone(&event_storage),
+            config.database.checkpoint_dir.clone(),
+            config.database.checkpoint_interval,
+        )
+        .await;
+        info!(
+            "✅ Scheduled checkpoints enabled (every {}s into {})",
+            config.database.checkpoint_interval, config.database.checkpoint_dir
+        );
+    }
+
+    // Move orders past their end_time out of the open set
+    if config.orders.expiry_sweep_interval_secs > 0 {
+        let _expiry_handle = start_order_expiry_task(
+            Arc::clone(&event_storage),
+            config.orders.expiry_sweep_interval_secs,
+        )
+        .await;
+        info!(
+            "✅ Order expiry sweep started (every {}s)",
+            config.orders.expiry_sweep_interval_secs
+        );
+    }
+
+    // Prune events and global indexes past their retention
+    if config.retention.prune_interval_secs > 0 {
+        let _retention_handle = start_retention_task(
+            Arc::clone(&event_storage),
+            config.retention.prune_interval_secs,
+        )
+        .await;
+        info!(
+            "✅ Retention pruner started (every {}s)",
+            config.retention.prune_interval_secs
+        );
+    }
+
+    // Pick up token metadata updated on IPFS after creation
+    if config.ipfs.refetch_after_secs > 0 && config.ipfs.refetch_per_minute > 0 {
+        let _refetch_handle = start_uri_refetch_task(
+            Arc::clone(&event_storage),
+            config.ipfs.refetch_after_secs,
+            config.ipfs.refetch_per_minute,
+        )
+        .await;
+        info!(
+            "✅ Metadata refetch started (after {}s, {} mints/minute)",
+            config.ipfs.refetch_after_secs, config.ipfs.refetch_per_minute
+        );
+    }
+
+    // Snapshot recently updated mint details for /api/mints/{mint}/history
+    if config.stats.mint_snapshot_interval_secs > 0 {
+        let _snapshot_handle = start_mint_snapshot_task(
+            Arc::clone(&event_storage),
+            config.stats.mint_snapshot_interval_secs,
+        )
+        .await;
+        info!(
+            "✅ Mint detail snapshots started (every {}s)",
+            config.stats.mint_snapshot_interval_secs
+        );
+    }
+
+    // Global indexes enabled since events were stored without them miss those events
+    match event_storage.pending_index_rebuilds() {
+        Ok(pending) if pending.is_empty() => {}
+        Ok(pending) if config.retention.auto_rebuild_indexes => {
+            info!("🔨 Rebuilding indexes in the background: {}", pending.join(", "));
+            let _rebuild_handle =
+                start_index_rebuild_task(Arc::clone(&event_storage), pending).await;
+        }
+        Ok(pending) => warn!(
+            "⚠️ Indexes missing events stored while they were disabled: {} (POST /api/admin/rebuild-index or retention.auto_rebuild_indexes)",
+            pending.join(", ")
+        ),
+        Err(e) => warn!("⚠️ Failed to check indexes for pending rebuilds: {}", e),
+    }
+
+    // Flush partial TSDB batches of kline points
+    if let Some(tsdb) = event_storage.tsdb_writer() {
+        let _tsdb_handle = start_tsdb_flush_task(
+            tsdb,
+            config.tsdb.flush_interval_ms.max(1),
+            event_storage.disk_guard().cloned(),
+        )
+        .await;
+        info!(
+            "✅ TSDB flush task started (every {}ms)",
+            config.tsdb.flush_interval_ms
+        );
+    }
+
     // Create application state
+    let dedup_cache = event_service.read().await.processed_signatures();
+    let parser_stats = event_service.read().await.parser_stats();
+
+    // Estimate the dedup + subscription state and enforce server.max_state_memory_mb
+    let state_memory = Arc::new(StateMemoryGuard::new(
+        config.server.max_state_memory_mb,
+        dedup_cache.clone(),
+        kline_socket_service
+            .as_ref()
+            .map(|kline_service| Arc::clone(&kline_service.subscriptions)),
+        Duration::from_secs(config.kline.ping_interval_secs),
+    ));
+    let _state_memory_handle = start_state_memory_task(Arc::clone(&state_memory)).await;
     let app_state = Arc::new(AppState {
-        event_service: Arc::clone(&event_service),
+        event_service: Some(Arc::clone(&event_service)),
         event_storage,
         kline_service: kline_socket_service.clone(),
+        admin_config: config.admin.clone(),
+        warmup: Arc::clone(&warmup),
+        query_limiter: tokio::sync::Semaphore::new(config.server.max_concurrent_queries),
+        query_timeout: Duration::from_millis(config.server.query_timeout_ms),
+        dedup_cache,
+        parser_stats,
+        state_memory: Some(state_memory),
+        response_cache: ResponseCache::new(&config.response_cache),
+        api_keys: ApiKeyLimiter::new(&config.api_keys),
+        started_at: chrono::Utc::now(),
+        started: std::time::Instant::now(),
+        program_ids: vec![config.solana.program_id.clone()],
     });
 
     // Create router with optional SocketIO layer
@@ -188,51 +527,870 @@ async fn main() {
             start_connection_cleanup_task(Arc::clone(&subscription_manager), kline_config.clone())
                 .await;
 
+        // Start reconcile task (drops connections whose disconnect was missed)
+        if !kline_config.reconcile_interval.is_zero() {
+            let _reconcile_handle = start_connection_reconcile_task(
+                Arc::clone(kline_service),
+                kline_config.reconcile_interval,
+            )
+            .await;
+        }
+
+        // Start heartbeat task (clients that answer are reaped on missed acks)
+        let _heartbeat_handle = start_heartbeat_task(Arc::clone(kline_service)).await;
+
         // Start performance monitoring task
         let _monitoring_handle =
             start_performance_monitoring_task(Arc::clone(&subscription_manager)).await;
 
+        // Start closed-candle announcement task
+        if !kline_config.finalization_interval.is_zero() {
+            let _finalization_handle = start_kline_finalization_task(
+                Arc::clone(kline_service),
+                kline_config.finalization_interval,
+            )
+            .await;
+        }
+
         info!("✅ K-line service background tasks started");
     }
 
-    // Create listener
-    let addr = format!("{}:{}", config.server.host, config.server.port);
-    let listener = match tokio::net::TcpListener::bind(&addr).await {
-        Ok(listener) => listener,
-        Err(e) => {
-            error!("❌ Cannot bind to address {}: {}", addr, e);
-            std::process::exit(1);
+    serve(&config, app, kline_socket_service.is_some()).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use tempfile::TempDir;
+    use tower::Service;
+
+    fn create_test_config(temp_dir: &TempDir) -> Config {
+        let mut config = Config::for_tests(temp_dir.path().to_str().unwrap());
+        config.kline.enable_kline_service = true;
+        config
+    }
+
+    fn create_test_app_state(
+        config: &Config,
+        event_storage: Arc<EventStorage>,
+        kline_service: Option<Arc<KlineSocketService>>,
+    ) -> Arc<AppState> {
+        let event_handler = build_event_handler(config, &event_storage, kline_service.as_ref());
+        let event_service = EventService::with_handler_and_storage(
+            config,
+            event_handler,
+            Arc::clone(&event_storage),
+        )
+        .unwrap();
+        let dedup_cache = event_service.processed_signatures();
+        let parser_stats = event_service.parser_stats();
+        Arc::new(AppState {
+            event_service: Some(Arc::new(tokio::sync::RwLock::new(event_service))),
+            event_storage,
+            kline_service,
+            admin_config: config.admin.clone(),
+            warmup: Arc::new(WarmupGate::default()),
+            query_limiter: tokio::sync::Semaphore::new(config.server.max_concurrent_queries),
+            query_timeout: Duration::from_millis(config.server.query_timeout_ms),
+            dedup_cache,
+            parser_stats,
+            state_memory: None,
+            response_cache: ResponseCache::new(&config.response_cache),
+            api_keys: ApiKeyLimiter::new(&config.api_keys),
+            started_at: chrono::Utc::now(),
+            started: std::time::Instant::now(),
+            program_ids: vec![config.solana.program_id.clone()],
+        })
+    }
+
+    async fn response_json(app: &mut axum::Router, request: Request<Body>) -> serde_json::Value {
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_kline_init_failure_keeps_rest_api() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = create_test_config(&temp_dir);
+        let event_storage = Arc::new(EventStorage::new(&config).unwrap());
+
+        let (kline_service, socketio_layer) = init_kline_service(
+            &config,
+            &event_storage,
+            &Arc::new(WarmupGate::default()),
+            |_, _| Err(anyhow::anyhow!("socket.io setup failed")),
+        );
+        assert!(kline_service.is_none());
+        assert!(socketio_layer.is_none());
+
+        // The kline handler is skipped since the service never came up
+        let app_state = create_test_app_state(&config, event_storage, kline_service);
+
+        // REST endpoints are still served without the Socket.IO layer
+        let mut app = create_router(&config, app_state);
+        let response = app
+            .call(Request::get("/api/time").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_query_only_serves_queries_without_event_service() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.server.query_only = true;
+        let event_storage = Arc::new(EventStorage::new(&config).unwrap());
+
+        let mut app = build_query_only_app(&config, event_storage);
+
+        let events = response_json(
+            &mut app,
+            Request::get("/api/events?mint=query_only_mint")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(events["success"], true);
+
+        // No event service (and so no listener) exists in this mode
+        let status = response_json(
+            &mut app,
+            Request::get("/api/events/status")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status["success"], false);
+
+        // No Socket.IO layer: K-line push reports itself disabled
+        let kline = response_json(
+            &mut app,
+            Request::get("/api/kline/status")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(kline["data"]["enabled"], false);
+    }
+
+    #[tokio::test]
+    async fn test_bigint_as_string_only_changes_responses() {
+        use crate::services::event_storage::EVENT_TYPE_BUY_SELL;
+        use crate::solana::{BuySellEvent, SpinPetEvent, TimestampSource};
+
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.kline.enable_kline_service = false;
+        config.server.bigint_as_string = true;
+        let event_storage = Arc::new(EventStorage::new(&config).unwrap());
+
+        let sol_amount: u64 = (1 << 53) + 1;
+        event_storage
+            .store_event(SpinPetEvent::BuySell(BuySellEvent {
+                payer: "test_payer".to_string(),
+                mint_account: "big_mint".to_string(),
+                is_buy: true,
+                token_amount: u64::MAX,
+                sol_amount,
+                latest_price: 3_000,
+                timestamp: chrono::Utc::now(),
+                timestamp_source: TimestampSource::ServerReceive,
+                log_index: 0,
+                signature: "sig_big".to_string(),
+                slot: 100,
+            }))
+            .await
+            .unwrap();
+
+        // Stored values stay numeric
+        let key = format!(
+            "tr:big_mint:{:010}:0000:{}:sig_big",
+            100, EVENT_TYPE_BUY_SELL
+        );
+        let stored = event_storage.get_raw(&key).unwrap().unwrap().json.unwrap();
+        assert_eq!(stored["sol_amount"], sol_amount);
+
+        let app_state = create_test_app_state(&config, Arc::clone(&event_storage), None);
+        let mut app = create_router(&config, app_state);
+        let events = response_json(
+            &mut app,
+            Request::get("/api/events?mint=big_mint")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        let event = &events["data"]["events"][0];
+        assert_eq!(event["sol_amount"], sol_amount.to_string());
+        assert_eq!(event["token_amount"], u64::MAX.to_string());
+    }
+
+    #[tokio::test]
+    async fn test_bigint_as_string_mint_detail_totals() {
+        use crate::solana::{BuySellEvent, SpinPetEvent, TimestampSource};
+
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.kline.enable_kline_service = false;
+        config.server.bigint_as_string = true;
+        let event_storage = Arc::new(EventStorage::new(&config).unwrap());
+
+        let sol_amount: u64 = (1 << 53) + 1;
+        event_storage
+            .store_event(SpinPetEvent::BuySell(BuySellEvent {
+                payer: "test_payer".to_string(),
+                mint_account: "big_mint".to_string(),
+                is_buy: true,
+                token_amount: 1_000,
+                sol_amount,
+                latest_price: 3_000,
+                timestamp: chrono::Utc::now(),
+                timestamp_source: TimestampSource::ServerReceive,
+                log_index: 0,
+                signature: "sig_big".to_string(),
+                slot: 100,
+            }))
+            .await
+            .unwrap();
+
+        let app_state = create_test_app_state(&config, Arc::clone(&event_storage), None);
+        let mut app = create_router(&config, app_state);
+        let details = response_json(
+            &mut app,
+            Request::post("/api/details")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"mint_accounts":["big_mint"]}"#))
+                .unwrap(),
+        )
+        .await;
+        let detail = &details["data"]["details"][0];
+        assert_eq!(detail["total_sol_amount"], sol_amount.to_string());
+        assert_eq!(detail["total_margin_sol_amount"], "0");
+        assert_eq!(detail["total_force_liquidations"], "0");
+        assert_eq!(detail["total_close_profit"], "0");
+
+        // The mint detail record written for the trade stays numeric
+        let stored = event_storage
+            .get_raw("in:big_mint")
+            .unwrap()
+            .unwrap()
+            .json
+            .unwrap();
+        assert_eq!(stored["total_sol_amount"], sol_amount);
+    }
+
+    #[tokio::test]
+    async fn test_ts_format_millis_normalizes_timestamps() {
+        use crate::solana::{BuySellEvent, SpinPetEvent, TimestampSource, TokenCreatedEvent};
+        use chrono::{TimeZone, Utc};
+
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.kline.enable_kline_service = false;
+        let event_storage = Arc::new(EventStorage::new(&config).unwrap());
+
+        let created_at = Utc.timestamp_opt(1_758_343_400, 0).unwrap();
+        let traded_at = Utc.timestamp_opt(1_758_343_500, 0).unwrap();
+        event_storage
+            .store_event(SpinPetEvent::TokenCreated(TokenCreatedEvent {
+                payer: "test_payer".to_string(),
+                mint_account: "ts_mint".to_string(),
+                curve_account: "test_curve".to_string(),
+                pool_token_account: "test_pool_token".to_string(),
+                pool_sol_account: "test_pool_sol".to_string(),
+                fee_recipient: "test_fee_recipient".to_string(),
+                base_fee_recipient: "test_base_fee_recipient".to_string(),
+                params_account: "test_params_account".to_string(),
+                name: "Test Token".to_string(),
+                symbol: "TEST".to_string(),
+                uri: String::new(),
+                swap_fee: 100,
+                borrow_fee: 200,
+                fee_discount_flag: 0,
+                timestamp: created_at,
+                timestamp_source: TimestampSource::ServerReceive,
+                log_index: 0,
+                signature: "sig_created".to_string(),
+                slot: 100,
+            }))
+            .await
+            .unwrap();
+        event_storage
+            .store_event(SpinPetEvent::BuySell(BuySellEvent {
+                payer: "test_payer".to_string(),
+                mint_account: "ts_mint".to_string(),
+                is_buy: true,
+                token_amount: 1_000,
+                sol_amount: 2_000,
+                latest_price: 3_000,
+                timestamp: traded_at,
+                timestamp_source: TimestampSource::ServerReceive,
+                log_index: 0,
+                signature: "sig_trade".to_string(),
+                slot: 101,
+            }))
+            .await
+            .unwrap();
+
+        let app_state = create_test_app_state(&config, event_storage, None);
+        let mut app = create_router(&config, app_state);
+
+        let klines = response_json(
+            &mut app,
+            Request::get("/api/kline?mint=ts_mint&interval=m5&ts_format=ms")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        let bucket: i64 = 1_758_343_500 / 300 * 300;
+        assert_eq!(klines["data"]["klines"][0]["time"], bucket * 1000);
+
+        let details = response_json(
+            &mut app,
+            Request::post("/api/details?ts_format=ms")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"mints":["ts_mint"]}"#))
+                .unwrap(),
+        )
+        .await;
+        let detail = &details["data"]["details"][0];
+        assert_eq!(detail["create_timestamp"], 1_758_343_400_000i64);
+        assert_eq!(detail["latest_trade_time"], 1_758_343_500_000i64);
+        assert_eq!(detail["last_updated_at"], 1_758_343_500_000i64);
+
+        // Without ts_format the existing representation is kept
+        let details = response_json(
+            &mut app,
+            Request::post("/api/details")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"mints":["ts_mint"]}"#))
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(
+            details["data"]["details"][0]["create_timestamp"],
+            1_758_343_400i64
+        );
+    }
+
+    #[tokio::test]
+    async fn test_response_cache_serves_repeat_query_until_ttl() {
+        use crate::solana::{SpinPetEvent, TimestampSource, TokenCreatedEvent};
+        use chrono::Utc;
+
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.kline.enable_kline_service = false;
+        config
+            .response_cache
+            .ttl_ms
+            .insert("mints".to_string(), 300);
+        let event_storage = Arc::new(EventStorage::new(&config).unwrap());
+        let create = |mint: &str, slot: u64| {
+            SpinPetEvent::TokenCreated(TokenCreatedEvent {
+                payer: "test_payer".to_string(),
+                mint_account: mint.to_string(),
+                curve_account: "test_curve".to_string(),
+                pool_token_account: "test_pool_token".to_string(),
+                pool_sol_account: "test_pool_sol".to_string(),
+                fee_recipient: "test_fee_recipient".to_string(),
+                base_fee_recipient: "test_base_fee_recipient".to_string(),
+                params_account: "test_params_account".to_string(),
+                name: "Test Token".to_string(),
+                symbol: "TEST".to_string(),
+                uri: String::new(),
+                swap_fee: 100,
+                borrow_fee: 200,
+                fee_discount_flag: 0,
+                timestamp: Utc::now(),
+                timestamp_source: TimestampSource::ServerReceive,
+                log_index: 0,
+                signature: format!("sig_{}", mint),
+                slot,
+            })
+        };
+        event_storage
+            .store_event(create("cached_mint_a", 100))
+            .await
+            .unwrap();
+
+        let app_state = create_test_app_state(&config, Arc::clone(&event_storage), None);
+        let mut app = create_router(&config, Arc::clone(&app_state));
+        let mint_count = |body: serde_json::Value| body["data"]["mints"].as_array().unwrap().len();
+        let mints = || Request::get("/api/mints").body(Body::empty()).unwrap();
+
+        assert_eq!(mint_count(response_json(&mut app, mints()).await), 1);
+
+        // A mint stored within the TTL is not visible: the repeat query never reaches the DB
+        event_storage
+            .store_event(create("cached_mint_b", 101))
+            .await
+            .unwrap();
+        assert_eq!(mint_count(response_json(&mut app, mints()).await), 1);
+        assert_eq!(app_state.response_cache.counters(), (1, 1));
+
+        tokio::time::sleep(Duration::from_millis(350)).await;
+        assert_eq!(mint_count(response_json(&mut app, mints()).await), 2);
+        assert_eq!(app_state.response_cache.counters(), (1, 2));
+
+        // Endpoints without a configured TTL are never cached
+        assert!(app_state
+            .response_cache
+            .get::<crate::services::MintDetailsQueryResponse>("mint_details", "cached_mint_a")
+            .is_none());
+        assert_eq!(app_state.response_cache.counters(), (1, 2));
+    }
+
+    #[tokio::test]
+    async fn test_kline_query_serves_csv() {
+        use crate::solana::{BuySellEvent, SpinPetEvent, TimestampSource};
+        use chrono::{TimeZone, Utc};
+
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.kline.enable_kline_service = false;
+        let event_storage = Arc::new(EventStorage::new(&config).unwrap());
+
+        for (i, ts) in [1_758_343_500i64, 1_758_343_800].into_iter().enumerate() {
+            event_storage
+                .store_event(SpinPetEvent::BuySell(BuySellEvent {
+                    payer: "test_payer".to_string(),
+                    mint_account: "csv_mint".to_string(),
+                    is_buy: true,
+                    token_amount: 1_000,
+                    sol_amount: 2_000,
+                    latest_price: 3_000,
+                    timestamp: Utc.timestamp_opt(ts, 0).unwrap(),
+                    timestamp_source: TimestampSource::ServerReceive,
+                    log_index: 0,
+                    signature: format!("sig_csv_{}", i),
+                    slot: 100 + i as u64,
+                }))
+                .await
+                .unwrap();
         }
-    };
 
-    // Startup information
-    info!("🚀 Spin Server started successfully!");
-    info!("📍 Listening on: http://{}", addr);
-    info!("📖 API documentation: http://{}/swagger-ui", addr);
-    info!(
-        "🔧 Environment: {}",
-        env::var("RUST_ENV").unwrap_or_else(|_| "development".to_string())
-    );
-    info!("🔗 Solana program ID: {}", config.solana.program_id);
-    info!("📋 Available endpoints:");
-    info!("  GET  /api/time           - Get current time");
-    info!("  GET  /api/events/status  - Get event service status");
-    info!("  GET  /api/events/stats   - Get event statistics");
-    info!("  GET  /api/events         - Query event data");
-    info!("  GET  /api/events/db-stats - Get database statistics");
+        let app_state = create_test_app_state(&config, event_storage, None);
+        let mut app = create_router(&config, app_state);
 
-    info!("  GET  /swagger-ui         - API documentation interface");
+        for request in [
+            Request::get("/api/kline?mint=csv_mint&interval=m5&format=csv")
+                .body(Body::empty())
+                .unwrap(),
+            Request::get("/api/kline?mint=csv_mint&interval=m5")
+                .header("accept", "text/csv")
+                .body(Body::empty())
+                .unwrap(),
+        ] {
+            let response = app.call(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert!(response.headers()["content-type"]
+                .to_str()
+                .unwrap()
+                .starts_with("text/csv"));
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let text = String::from_utf8(bytes.to_vec()).unwrap();
+            let lines: Vec<&str> = text.lines().collect();
 
-    if config.kline.enable_kline_service {
-        info!("📊 K-line WebSocket service:");
-        info!("  WS   ws://0.0.0.0:5051/kline - Real-time K-line data subscription");
-        info!("  Events: subscribe, unsubscribe, history, kline_data");
-        info!("  Supported intervals: s1, s30, m5");
+            assert_eq!(
+                lines[0],
+                "close,high,is_final,is_gap,last_slot,low,open,time,update_count,volume"
+            );
+            // One row per candle, newest first by default
+            assert_eq!(lines.len(), 3);
+            assert!(lines[1].contains(&(1_758_343_800 / 300 * 300).to_string()));
+            assert!(lines[2].contains(&(1_758_343_500 / 300 * 300).to_string()));
+        }
+
+        // JSON stays the default
+        let klines = response_json(
+            &mut app,
+            Request::get("/api/kline?mint=csv_mint&interval=m5")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(klines["data"]["klines"].as_array().unwrap().len(), 2);
     }
 
-    // Start server
-    if let Err(e) = axum::serve(listener, app).await {
-        error!("❌ Server runtime error: {}", e);
-        std::process::exit(1);
+    #[tokio::test]
+    async fn test_slow_query_times_out_with_gateway_timeout() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.kline.enable_kline_service = false;
+        config.server.query_timeout_ms = 50;
+        let event_storage = Arc::new(EventStorage::new(&config).unwrap());
+        let app_state = create_test_app_state(&config, Arc::clone(&event_storage), None);
+
+        // A storage query wrapped to take far longer than the timeout
+        let started = std::time::Instant::now();
+        let slow = app_state
+            .with_query_timeout(async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                event_storage.query_recent_events(10, None).await
+            })
+            .await;
+        assert_eq!(slow.err(), Some(StatusCode::GATEWAY_TIMEOUT));
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        let fast = app_state
+            .with_query_timeout(event_storage.query_recent_events(10, None))
+            .await;
+        assert!(fast.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_heavy_queries_rejected_beyond_concurrency_limit() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.kline.enable_kline_service = false;
+        config.server.max_concurrent_queries = 1;
+        let event_storage = Arc::new(EventStorage::new(&config).unwrap());
+
+        let app_state = create_test_app_state(&config, event_storage, None);
+        let mut app = create_router(&config, Arc::clone(&app_state));
+
+        // One heavy query is already running and holds the only permit
+        let running = app_state.acquire_query_permit().unwrap();
+
+        let response = app
+            .call(
+                Request::get("/api/events?mint=busy_mint")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        // Point lookups are not throttled
+        let details = response_json(
+            &mut app,
+            Request::post("/api/details")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"mints":["busy_mint"]}"#))
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(details["success"], true);
+
+        drop(running);
+        response_json(
+            &mut app,
+            Request::get("/api/events?mint=busy_mint")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_api_key_enforcement_and_rate_limit() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.kline.enable_kline_service = false;
+        config.api_keys.enforce = true;
+        config.api_keys.keys = vec![crate::config::ApiKeyEntry {
+            key: "partner-key".to_string(),
+            requests_per_minute: 2,
+        }];
+        let event_storage = Arc::new(EventStorage::new(&config).unwrap());
+
+        let app_state = create_test_app_state(&config, event_storage, None);
+        let mut app = create_router(&config, app_state);
+        let mints_request = |api_key: Option<&str>| {
+            let mut request = Request::get("/api/mints");
+            if let Some(key) = api_key {
+                request = request.header("x-api-key", key);
+            }
+            request.body(Body::empty()).unwrap()
+        };
+
+        // Missing and unknown keys are rejected
+        for api_key in [None, Some("wrong-key")] {
+            let response = app.call(mints_request(api_key)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{:?}", api_key);
+        }
+
+        // A valid key passes until its bucket is empty
+        for _ in 0..2 {
+            let body = response_json(&mut app, mints_request(Some("partner-key"))).await;
+            assert_eq!(body["success"], true);
+        }
+        let response = app.call(mints_request(Some("partner-key"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()["retry-after"], "30");
+
+        // Operational endpoints are not behind the key check
+        response_json(
+            &mut app,
+            Request::get("/api/time").body(Body::empty()).unwrap(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_admin_dedup_cache_inspect_and_clear() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.kline.enable_kline_service = false;
+        config.admin.enable_admin_api = true;
+        let event_storage = Arc::new(EventStorage::new(&config).unwrap());
+
+        let app_state = create_test_app_state(&config, event_storage, None);
+        let cache = app_state.dedup_cache.clone().unwrap();
+        cache.write().await.insert("seen_sig".to_string());
+        let mut app = create_router(&config, app_state);
+
+        let status = response_json(
+            &mut app,
+            Request::get("/api/admin/dedup")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status["data"]["size"], 1);
+
+        let cleared = response_json(
+            &mut app,
+            Request::delete("/api/admin/dedup")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(cleared["data"]["cleared"], 1);
+        assert!(cache.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_admin_raw_value_returns_stored_bytes() {
+        use crate::solana::{BuySellEvent, SpinPetEvent, TimestampSource};
+        use base64::engine::Engine;
+
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.kline.enable_kline_service = false;
+        config.admin.enable_admin_api = true;
+        let event_storage = Arc::new(EventStorage::new(&config).unwrap());
+        event_storage
+            .store_event(SpinPetEvent::BuySell(BuySellEvent {
+                payer: "raw_payer".to_string(),
+                mint_account: "raw_mint".to_string(),
+                is_buy: true,
+                token_amount: 1_000,
+                sol_amount: 2_000,
+                latest_price: 1,
+                timestamp: chrono::Utc::now(),
+                timestamp_source: TimestampSource::ServerReceive,
+                log_index: 0,
+                signature: "raw_sig".to_string(),
+                slot: 7,
+            }))
+            .await
+            .unwrap();
+
+        let app_state = create_test_app_state(&config, event_storage, None);
+        let mut app = create_router(&config, app_state);
+
+        let raw = response_json(
+            &mut app,
+            Request::get("/api/admin/raw?key=in:raw_mint")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(raw["data"]["value_base64"].as_str().unwrap())
+            .unwrap();
+        let decoded: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(decoded, raw["data"]["json"]);
+        assert_eq!(decoded["mint_account"], "raw_mint");
+        assert_eq!(raw["data"]["size_bytes"], bytes.len());
+
+        let missing = response_json(
+            &mut app,
+            Request::get("/api/admin/raw?key=in:unknown_mint")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(missing["success"], false);
+    }
+
+    #[tokio::test]
+    async fn test_connection_state_endpoint_reports_latest_transition() {
+        use crate::solana::ListenerConnectionState;
+        use chrono::TimeZone;
+
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.kline.enable_kline_service = false;
+        let event_storage = Arc::new(EventStorage::new(&config).unwrap());
+        let app_state = create_test_app_state(&config, event_storage, None);
+        let connection_status = app_state
+            .event_service
+            .as_ref()
+            .unwrap()
+            .read()
+            .await
+            .connection_status()
+            .unwrap();
+        let mut app = create_router(&config, Arc::clone(&app_state));
+
+        {
+            let mut status = connection_status.write().await;
+            let connected_at = chrono::Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+            status.transition_at(ListenerConnectionState::Connecting, connected_at);
+            status.transition_at(ListenerConnectionState::Connected, connected_at);
+            let dropped_at = chrono::Utc.timestamp_opt(1_700_000_060, 0).unwrap();
+            status.transition_at(ListenerConnectionState::Reconnecting, dropped_at);
+            // Repeating the current state keeps its transition time
+            status.transition_at(ListenerConnectionState::Reconnecting, connected_at);
+        }
+
+        let state = response_json(
+            &mut app,
+            Request::get("/api/events/state?ts_format=s")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(state["success"], true);
+        assert_eq!(state["data"]["state"], "Reconnecting");
+        assert_eq!(state["data"]["previous_state"], "Connected");
+        assert_eq!(state["data"]["since"], 1_700_000_060);
+        assert_eq!(state["data"]["transitions"], 3);
+    }
+
+    #[tokio::test]
+    async fn test_envelope_wraps_response_with_meta() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.kline.enable_kline_service = false;
+        let event_storage = Arc::new(EventStorage::new(&config).unwrap());
+
+        let app_state = create_test_app_state(&config, event_storage, None);
+        let mut app = create_router(&config, app_state);
+
+        let wrapped = response_json(
+            &mut app,
+            Request::get("/api/events/stats?envelope=true")
+                .header("x-request-id", "req-123")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(wrapped["data"]["success"], true);
+        assert!(wrapped["data"]["data"].is_object());
+        let meta = &wrapped["meta"];
+        assert!(meta["server_time"].as_i64().unwrap() > 0);
+        assert_eq!(meta["version"], crate::services::SCHEMA_VERSION);
+        assert_eq!(meta["source"], "primary");
+        assert_eq!(meta["request_id"], "req-123");
+
+        // The bare response stays the default
+        let bare = response_json(
+            &mut app,
+            Request::get("/api/events/stats")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(bare["success"], true);
+        assert!(bare.get("meta").is_none());
+    }
+
+    #[test]
+    fn test_json_log_format_emits_structured_lines() {
+        use std::io::Write;
+        use std::sync::Mutex;
+
+        #[derive(Clone, Default)]
+        struct Captured(Arc<Mutex<Vec<u8>>>);
+
+        impl Write for Captured {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let capture = |format: LogFormat| {
+            let captured = Captured::default();
+            let writer = captured.clone();
+            let subscriber = tracing_subscriber::registry()
+                .with(log_format_layer(format, move || writer.clone()));
+            tracing::subscriber::with_default(subscriber, || {
+                let span = tracing::info_span!("ingest", mint = "log_mint");
+                let _guard = span.enter();
+                info!(slot = 42, "stored event");
+            });
+            let bytes = captured.0.lock().unwrap().clone();
+            String::from_utf8(bytes).unwrap()
+        };
+
+        let json_output = capture(LogFormat::Json);
+        let line: serde_json::Value = serde_json::from_str(json_output.trim()).unwrap();
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(line["target"], "spin_server::tests");
+        assert!(line["timestamp"].is_string());
+        assert_eq!(line["fields"]["message"], "stored event");
+        assert_eq!(line["fields"]["slot"], 42);
+        assert_eq!(line["span"]["name"], "ingest");
+        assert_eq!(line["spans"][0]["mint"], "log_mint");
+
+        // The default stays human-readable text
+        let pretty_output = capture(LogFormat::Pretty);
+        assert!(pretty_output.contains("stored event"));
+        assert!(serde_json::from_str::<serde_json::Value>(pretty_output.trim()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_info_endpoint_reports_version_and_growing_uptime() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.kline.enable_kline_service = false;
+        let event_storage = Arc::new(EventStorage::new(&config).unwrap());
+        let app_state = create_test_app_state(&config, event_storage, None);
+        let mut app = create_router(&config, Arc::clone(&app_state));
+
+        let info_request = || Request::get("/api/info").body(Body::empty()).unwrap();
+        let first = response_json(&mut app, info_request()).await;
+        assert_eq!(first["success"], true);
+        assert_eq!(first["data"]["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(first["data"]["git_commit"], env!("SPIN_GIT_COMMIT"));
+        assert_eq!(
+            first["data"]["program_ids"],
+            serde_json::json!([config.solana.program_id])
+        );
+        assert_eq!(
+            first["data"]["started_at"],
+            app_state.started_at.timestamp()
+        );
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let second = response_json(&mut app, info_request()).await;
+        let uptime = |info: &serde_json::Value| info["data"]["uptime_ms"].as_u64().unwrap();
+        assert!(uptime(&second) >= uptime(&first) + 20);
+        assert_eq!(second["data"]["started_at"], first["data"]["started_at"]);
+
+        // Hidden when not exposed
+        config.server.expose_info = false;
+        let response = create_router(&config, app_state)
+            .call(info_request())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
     }
 }
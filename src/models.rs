@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use utoipa::ToSchema;
 // use chrono::{DateTime, Utc};
 
@@ -6,6 +7,10 @@ use utoipa::ToSchema;
 #[derive(Serialize, ToSchema)]
 pub struct ApiResponse<T> {
     pub success: bool,
+    #[serde(
+        serialize_with = "crate::utils::serialize_response_data",
+        bound(serialize = "T: Serialize")
+    )]
     pub data: T,
     pub message: String,
 }
@@ -19,6 +24,20 @@ pub struct TimeResponse {
     pub iso8601: String,
 }
 
+/// Deployed build, start time and uptime
+#[derive(Debug, Serialize, Deserialize, ToSchema, Default)]
+pub struct ServerInfoResponse {
+    /// Crate version
+    pub version: String,
+    /// Git commit the binary was built from, "unknown" when built outside a checkout
+    pub git_commit: String,
+    /// Unix timestamp (seconds) the server started at
+    pub started_at: i64,
+    pub uptime_ms: u64,
+    /// Solana programs whose events are indexed
+    pub program_ids: Vec<String>,
+}
+
 // Time query parameters
 #[derive(Deserialize, ToSchema)]
 pub struct TimeQuery {
@@ -36,6 +55,12 @@ pub struct KlineData {
     pub volume: f64,
     pub is_final: bool,
     pub update_count: u32,
+    /// Synthetic flat candle filling a bucket without trades (only with `fill_gaps`)
+    #[serde(default)]
+    pub is_gap: bool,
+    /// Slot of the newest trade applied to this candle
+    #[serde(default)]
+    pub last_slot: u64,
 }
 
 // Kline query parameters
@@ -46,6 +71,12 @@ pub struct KlineQuery {
     pub page: Option<usize>,
     pub limit: Option<usize>,
     pub order_by: Option<String>, // "time_asc" or "time_desc" (default)
+    /// Insert flat gap candles for empty buckets between stored klines
+    #[serde(default)]
+    pub fill_gaps: bool,
+    /// Drop candles built from fewer trades than this (unfiltered when unset)
+    #[serde(default)]
+    pub min_update_count: Option<u32>,
 }
 
 // Kline query response
@@ -59,6 +90,82 @@ pub struct KlineQueryResponse {
     pub has_prev: bool,
     pub interval: String,
     pub mint_account: String,
+    /// Length of one candle, in seconds
+    pub bucket_secs: u64,
+    /// Unix time buckets are aligned to; a candle starts where `(time - alignment_epoch) % bucket_secs == 0`
+    pub alignment_epoch: u64,
+}
+
+// Batch kline response: one series per mint, trimmed to a shared time window when possible
+#[derive(Debug, Serialize, Default, ToSchema)]
+pub struct KlineBatchResponse {
+    pub interval: String,
+    /// mint_account -> klines ordered oldest first
+    pub series: BTreeMap<String, Vec<KlineData>>,
+    /// Shared window start, set when every non-empty series overlaps it
+    pub window_start: Option<u64>,
+    pub window_end: Option<u64>,
+}
+
+// Newest kline per mint for one interval
+#[derive(Debug, Serialize, Default, ToSchema)]
+pub struct LatestKlinesResponse {
+    pub interval: String,
+    /// mint_account -> newest kline; mints without klines are omitted
+    pub klines: BTreeMap<String, KlineData>,
+}
+
+// Live subscriber counts of one mint's kline rooms, without socket ids
+#[derive(Debug, Clone, Serialize, Default, ToSchema)]
+pub struct MintWatchersResponse {
+    pub mint_account: String,
+    /// interval -> subscribed connections
+    pub intervals: BTreeMap<String, usize>,
+    /// Distinct connections subscribed to any interval
+    pub total: usize,
+}
+
+// Readiness response structure
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReadinessResponse {
+    pub ready: bool,
+    pub warming_up: bool,
+    /// Database disk below `database.min_free_disk_mb`; events are not being stored
+    pub read_only: bool,
+}
+
+// Reconnect history response structure
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct ReconnectHistoryResponse {
+    /// `solana.reconnect_history_size`
+    pub capacity: usize,
+    /// Recent connection state changes, oldest first
+    pub events: Vec<crate::solana::ConnectionEvent>,
+}
+
+// Metadata added around a response by `?envelope=true`
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ResponseMeta {
+    /// Server time (unix seconds)
+    pub server_time: i64,
+    /// Storage schema version
+    pub version: u32,
+    /// Where the data was read from, "primary" or "replica"
+    pub source: String,
+    pub request_id: String,
+}
+
+// Dedup cache status structure
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DedupCacheStatus {
+    pub size: usize,
+    pub capacity: usize,
+}
+
+// Dedup cache clear result structure
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DedupCacheClearResult {
+    pub cleared: usize,
 }
 
 // Re-export types from services module
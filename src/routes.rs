@@ -1,7 +1,12 @@
-use axum::response::Html;
+use axum::body::{to_bytes, Body};
+use axum::extract::{Request, State};
+use axum::http::header::{CONTENT_LENGTH, CONTENT_TYPE, RETRY_AFTER};
+use axum::http::StatusCode;
+use axum::middleware::{self, Next};
+use axum::response::{Html, IntoResponse, Response};
 use axum::{
     routing::{get, post},
-    Router,
+    Json, Router,
 };
 use std::sync::Arc;
 use tower_http::cors::{Any, CorsLayer};
@@ -11,53 +16,140 @@ use utoipa::OpenApi;
 use crate::config::Config;
 use crate::handlers::{self, AppState};
 use crate::models::*;
+use crate::services::ApiKeyDecision;
+use crate::utils::{normalize_timestamps, with_amounts_as_strings, TimestampFormat};
 
 // OpenAPI documentation definition
 #[derive(OpenApi)]
 #[openapi(
     paths(
         handlers::get_time,
+        handlers::get_server_info,
+        handlers::get_readiness,
         handlers::get_event_status,
+        handlers::get_connection_state,
+        handlers::get_reconnect_history,
         handlers::get_event_stats,
         handlers::query_events,
         handlers::get_db_stats,
+        handlers::get_db_metrics,
+        handlers::get_metrics,
+        handlers::get_parser_stats,
         handlers::query_mints,
+        handlers::query_recent_trades,
+        handlers::query_mint_activity,
+        handlers::query_mint_history,
+        handlers::query_mint_ticker,
+        handlers::query_recent_liquidations,
         handlers::query_orders,
+        handlers::query_order_book,
+        handlers::query_order_timeline,
+        handlers::query_events_by_slot,
+        handlers::query_recent_events,
         handlers::query_user_transactions,
         handlers::query_user_orders,
+        handlers::query_user_position_summary,
         handlers::test_ipfs_functionality,
         handlers::query_mint_details,
         handlers::query_kline_data,
+        handlers::query_kline_batch,
+        handlers::query_latest_klines,
+        handlers::get_mint_watchers,
         handlers::get_kline_status,
         handlers::get_kline_subscriptions,
+        handlers::replay_slot_range,
+        handlers::get_dedup_cache,
+        handlers::clear_dedup_cache,
+        handlers::create_checkpoint,
+        handlers::get_raw_value,
+        handlers::prefetch_uri_data,
+        handlers::rebuild_index,
+        handlers::audit_kline_subscriptions,
+        handlers::repair_kline_subscriptions,
     ),
     components(
         schemas(
             ApiResponse<TimeResponse>,
+            ApiResponse<ServerInfoResponse>,
             ApiResponse<EventServiceStatus>,
+            ApiResponse<crate::solana::ListenerConnectionStatus>,
+            ApiResponse<ReconnectHistoryResponse>,
             ApiResponse<EventStats>,
+            ApiResponse<ReadinessResponse>,
+            ReadinessResponse,
             TimeResponse,
+            ServerInfoResponse,
             TimeQuery,
             EventServiceStatus,
+            crate::solana::ListenerConnectionStatus,
+            crate::solana::ListenerConnectionState,
+            crate::solana::ConnectionEvent,
+            ReconnectHistoryResponse,
             EventStats,
             handlers::EventQueryParams,
             handlers::MintQueryParams,
+            handlers::RecentTradesQueryParams,
+            handlers::OrderTimelineQueryParams,
+            handlers::ActivityQueryParams,
+            handlers::MintHistoryQueryParams,
+            handlers::RecentLiquidationsQueryParams,
+            handlers::RecentEventsQueryParams,
             handlers::OrderQueryParams,
+            handlers::OrderBookQueryParams,
             handlers::UserQueryParams,
             handlers::MintDetailsQueryParams,
             handlers::TestIpfsParams,
             handlers::KlineQueryParams,
+            handlers::KlineBatchQueryParams,
+            handlers::LatestKlinesQueryParams,
+            handlers::ReplayRequest,
+            handlers::CheckpointRequest,
+            handlers::RawKeyQueryParams,
+            handlers::PrefetchUriRequest,
+            handlers::RebuildIndexRequest,
             crate::services::EventQueryResponse,
             crate::services::MintQueryResponse,
+            crate::services::RecentTradesResponse,
+            crate::services::TradeTick,
+            crate::services::ActivityHistogramResponse,
+            crate::services::ActivityBucket,
+            crate::services::MintDetailHistoryResponse,
+            crate::services::MintTickerResponse,
+            crate::services::MintDetailSnapshot,
+            crate::services::RecentLiquidationsResponse,
+            crate::services::LiquidationRecord,
             crate::services::OrderQueryResponse,
             crate::services::OrderData,
+            crate::services::OrderStatus,
+            crate::services::OrderBookResponse,
+            crate::services::OrderBookLevel,
+            crate::services::OrderTimelineResponse,
+            crate::services::SlotEventsResponse,
+            crate::services::RecentEventsResponse,
             crate::services::UserQueryResponse,
             crate::services::UserTransactionData,
             crate::services::UserOrderQueryResponse,
+            crate::services::UserPositionSummaryResponse,
+            crate::services::PositionSideSummary,
             crate::services::MintDetailsQueryResponse,
             crate::services::MintDetailData,
+            crate::services::ReplayResult,
+            crate::services::CheckpointInfo,
+            crate::services::DbMetrics,
+            crate::solana::ParserStatsSnapshot,
+            crate::services::RawKeyValue,
+            crate::services::PrefetchUriResult,
+            crate::services::IndexRebuildResult,
+            crate::services::SubscriptionAudit,
+            crate::services::DanglingSubscriber,
             KlineData,
             KlineQueryResponse,
+            KlineBatchResponse,
+            LatestKlinesResponse,
+            MintWatchersResponse,
+            DedupCacheStatus,
+            DedupCacheClearResult,
+            ResponseMeta,
             crate::solana::SpinPetEvent,
             crate::solana::TokenCreatedEvent,
             crate::solana::BuySellEvent,
@@ -65,6 +157,7 @@ use crate::models::*;
             crate::solana::ForceLiquidateEvent,
             crate::solana::FullCloseEvent,
             crate::solana::PartialCloseEvent,
+            crate::solana::TimestampSource,
         )
     ),
     tags(
@@ -73,7 +166,8 @@ use crate::models::*;
         (name = "mints", description = "Mint query APIs"),
         (name = "orders", description = "Order query APIs"),
         (name = "user", description = "User transaction query APIs"),
-        (name = "kline", description = "Kline data query APIs")
+        (name = "kline", description = "Kline data query APIs"),
+        (name = "admin", description = "Operator APIs (disabled unless admin.enable_admin_api is set)")
     ),
     info(
         title = "Spin API Service",
@@ -84,27 +178,91 @@ use crate::models::*;
 pub struct ApiDoc;
 
 pub fn create_router(config: &Config, app_state: Arc<AppState>) -> Router {
+    // Read endpoints, behind the `[api_keys]` key check and rate limits
     let app = Router::new()
-        // API routes
-        .route("/api/time", get(handlers::get_time))
-        // Event-related routes
-        .route("/api/events/status", get(handlers::get_event_status))
-        .route("/api/events/stats", get(handlers::get_event_stats))
         // Event query routes
         .route("/api/events", get(handlers::query_events))
-        .route("/api/events/db-stats", get(handlers::get_db_stats))
+        .route(
+            "/api/events/by-slot/:slot",
+            get(handlers::query_events_by_slot),
+        )
+        .route("/api/events/recent", get(handlers::query_recent_events))
         // Mint query routes
         .route("/api/mints", get(handlers::query_mints))
+        .route(
+            "/api/mints/:mint/trades",
+            get(handlers::query_recent_trades),
+        )
+        .route(
+            "/api/mints/:mint/activity",
+            get(handlers::query_mint_activity),
+        )
+        .route(
+            "/api/mints/:mint/history",
+            get(handlers::query_mint_history),
+        )
         // Mint details query route
         .route("/api/details", post(handlers::query_mint_details))
         // Order query routes
         .route("/api/mint_orders", get(handlers::query_orders))
+        .route("/api/orders/:mint/book", get(handlers::query_order_book))
+        // Segment named like the book route's; the router needs one parameter name per position
+        .route(
+            "/api/orders/:mint/timeline",
+            get(handlers::query_order_timeline),
+        )
+        .route(
+            "/api/liquidations/recent",
+            get(handlers::query_recent_liquidations),
+        )
         // User transaction query routes
         .route("/api/user_event", get(handlers::query_user_transactions))
         // User order query routes
         .route("/api/user_orders", get(handlers::query_user_orders))
         // Kline query routes
         .route("/api/kline", get(handlers::query_kline_data))
+        .route("/api/klines/batch", post(handlers::query_kline_batch))
+        .route("/api/klines/latest", post(handlers::query_latest_klines))
+        .route(
+            "/api/klines/:mint/watchers",
+            get(handlers::get_mint_watchers),
+        );
+    let app = if config.orders.expose_position_summary {
+        app.route(
+            "/api/users/:user/positions/summary",
+            get(handlers::query_user_position_summary),
+        )
+    } else {
+        app
+    };
+    let app = if config.stats.expose_ticker {
+        app.route("/api/mints/:mint/ticker", get(handlers::query_mint_ticker))
+    } else {
+        app
+    };
+
+    let app = app
+        .route_layer(middleware::from_fn_with_state(
+            Arc::clone(&app_state),
+            api_key_middleware,
+        ))
+        // API routes
+        .route("/api/time", get(handlers::get_time))
+        // Readiness probe
+        .route("/ready", get(handlers::get_readiness))
+        // Event-related routes
+        .route("/api/events/status", get(handlers::get_event_status))
+        .route("/api/events/state", get(handlers::get_connection_state))
+        .route(
+            "/api/events/reconnect-history",
+            get(handlers::get_reconnect_history),
+        )
+        .route("/api/events/stats", get(handlers::get_event_stats))
+        .route("/api/events/db-stats", get(handlers::get_db_stats))
+        .route("/api/events/db-metrics", get(handlers::get_db_metrics))
+        .route("/metrics", get(handlers::get_metrics))
+        .route("/api/events/parser-stats", get(handlers::get_parser_stats))
+        // Kline service routes
         .route("/api/kline/status", get(handlers::get_kline_status))
         .route(
             "/api/kline/subscriptions",
@@ -117,9 +275,43 @@ pub fn create_router(config: &Config, app_state: Arc<AppState>) -> Router {
         // OpenAPI specification
         .route("/api-docs/openapi.json", get(serve_openapi))
         // Swagger UI
-        .route("/swagger-ui", get(serve_swagger_ui))
-        // Add application state
-        .with_state(app_state);
+        .route("/swagger-ui", get(serve_swagger_ui));
+
+    let app = if config.server.expose_info {
+        app.route("/api/info", get(handlers::get_server_info))
+    } else {
+        app
+    };
+
+    // Admin routes are only exposed when explicitly enabled
+    let app = if config.admin.enable_admin_api {
+        app.route("/api/admin/replay", post(handlers::replay_slot_range))
+            .route(
+                "/api/admin/dedup",
+                get(handlers::get_dedup_cache).delete(handlers::clear_dedup_cache),
+            )
+            .route("/api/admin/checkpoint", post(handlers::create_checkpoint))
+            .route("/api/admin/raw", get(handlers::get_raw_value))
+            .route("/api/admin/prefetch-uri", post(handlers::prefetch_uri_data))
+            .route("/api/admin/rebuild-index", post(handlers::rebuild_index))
+            .route(
+                "/api/admin/subscriptions/audit",
+                get(handlers::audit_kline_subscriptions).post(handlers::repair_kline_subscriptions),
+            )
+    } else {
+        app
+    };
+
+    // Add application state
+    let app = app
+        .with_state(app_state)
+        .layer(middleware::from_fn(timestamp_format_middleware));
+    let app = if config.server.bigint_as_string {
+        app.layer(middleware::from_fn(amounts_as_strings_middleware))
+    } else {
+        app
+    };
+    let app = app.layer(middleware::from_fn(envelope_middleware));
 
     // Add middleware
     let app = if config.cors.enabled {
@@ -131,6 +323,132 @@ pub fn create_router(config: &Config, app_state: Arc<AppState>) -> Router {
     app.layer(TraceLayer::new_for_http())
 }
 
+// Value of a query string parameter
+fn query_param<'a>(request: &'a Request, name: &str) -> Option<&'a str> {
+    request.uri().query().and_then(|query| {
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    })
+}
+
+// Check `X-API-Key` against `[api_keys]` and apply the key's rate limit
+async fn api_key_middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let api_key = request
+        .headers()
+        .get("x-api-key")
+        .and_then(|v| v.to_str().ok());
+    let decision = state.api_keys.check(api_key);
+
+    match decision {
+        ApiKeyDecision::Allowed => next.run(request).await,
+        ApiKeyDecision::Unauthorized => (
+            StatusCode::UNAUTHORIZED,
+            Json(ApiResponse::<()>::error("Missing or invalid X-API-Key")),
+        )
+            .into_response(),
+        ApiKeyDecision::RateLimited(retry_after) => {
+            tracing::warn!("⚠️ API rate limit exceeded for {}", request.uri().path());
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(RETRY_AFTER, retry_after.to_string())],
+                Json(ApiResponse::<()>::error("Rate limit exceeded")),
+            )
+                .into_response()
+        }
+    }
+}
+
+// Rewrite timestamp fields of JSON responses when `?ts_format=ms|s|iso` is given
+async fn timestamp_format_middleware(request: Request, next: Next) -> Response {
+    let ts_format =
+        query_param(&request, "ts_format").map(|value| value.parse::<TimestampFormat>());
+
+    let format = match ts_format {
+        None => return next.run(request).await,
+        Some(Ok(format)) => format,
+        Some(Err(e)) => return Json(ApiResponse::<()>::error(&e.to_string())).into_response(),
+    };
+
+    let response = next.run(request).await;
+    map_json_response(response, |value| normalize_timestamps(value, format)).await
+}
+
+// Serialize u64 amount fields of JSON responses as strings (`server.bigint_as_string`)
+async fn amounts_as_strings_middleware(request: Request, next: Next) -> Response {
+    with_amounts_as_strings(next.run(request)).await
+}
+
+// Wrap JSON responses in `{ data, meta }` when `?envelope=true` is given
+async fn envelope_middleware(request: Request, next: Next) -> Response {
+    match query_param(&request, "envelope").map(|value| value.parse::<bool>()) {
+        None | Some(Ok(false)) => return next.run(request).await,
+        Some(Ok(true)) => {}
+        Some(Err(_)) => {
+            return Json(ApiResponse::<()>::error(
+                "envelope must be 'true' or 'false'",
+            ))
+            .into_response()
+        }
+    }
+
+    let request_id = request
+        .headers()
+        .get("x-request-id")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    let response = next.run(request).await;
+    map_json_response(response, |value| {
+        let meta = ResponseMeta {
+            server_time: chrono::Utc::now().timestamp(),
+            version: crate::services::SCHEMA_VERSION,
+            source: "primary".to_string(),
+            request_id,
+        };
+        *value = serde_json::json!({ "data": value.take(), "meta": meta });
+    })
+    .await
+}
+
+// Apply `f` to the body of a JSON response, passing other responses through unchanged
+async fn map_json_response(response: Response, f: impl FnOnce(&mut serde_json::Value)) -> Response {
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    if !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::error!("Failed to buffer JSON response: {}", e);
+            return axum::http::StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    match serde_json::from_slice::<serde_json::Value>(&bytes) {
+        Ok(mut value) => {
+            f(&mut value);
+            parts.headers.remove(CONTENT_LENGTH);
+            let body = serde_json::to_vec(&value).unwrap_or_else(|_| bytes.to_vec());
+            Response::from_parts(parts, Body::from(body))
+        }
+        Err(_) => Response::from_parts(parts, Body::from(bytes)),
+    }
+}
+
 // OpenAPI specification handler
 async fn serve_openapi() -> axum::Json<utoipa::openapi::OpenApi> {
     axum::Json(ApiDoc::openapi())
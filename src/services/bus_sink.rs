@@ -0,0 +1,452 @@
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::sync::Mutex;
+use tokio::time::sleep;
+use tracing::{debug, error, info, warn};
+
+use crate::config::BusConfig;
+use crate::solana::events::SpinPetEvent;
+use crate::solana::EventHandler;
+
+/// Timeout of connecting to the bus plus one confirmed publish
+const BUS_PUBLISH_TIMEOUT_SECS: u64 = 5;
+
+/// Destination of published events
+#[async_trait]
+pub trait BusPublisher: Send + Sync {
+    /// Publish `payload` on `subject`; Ok once the server has accepted it
+    async fn publish(&self, subject: &str, message_id: &str, payload: &[u8]) -> Result<()>;
+}
+
+/// Minimal NATS client over the text protocol. Messages go out as HPUB with a `Nats-Msg-Id`
+/// header (JetStream de-duplicates on it) and are confirmed by a PING/PONG round trip. That only
+/// proves the NATS server processed the frame: no JetStream `PubAck` is awaited, so whether a
+/// stream stored the message is best-effort
+pub struct NatsPublisher {
+    address: String,
+    connection: Mutex<Option<BufReader<TcpStream>>>,
+}
+
+impl NatsPublisher {
+    pub fn new(url: &str) -> Self {
+        Self {
+            address: url
+                .trim_start_matches("nats://")
+                .trim_end_matches('/')
+                .to_string(),
+            connection: Mutex::new(None),
+        }
+    }
+
+    async fn connect(&self) -> Result<BufReader<TcpStream>> {
+        let mut stream = BufReader::new(TcpStream::connect(&self.address).await?);
+        let info = read_line(&mut stream).await?;
+        if !info.starts_with("INFO") {
+            bail!("unexpected NATS greeting: {}", info);
+        }
+        stream
+            .get_mut()
+            .write_all(
+                b"CONNECT {\"verbose\":false,\"pedantic\":false,\"headers\":true,\"name\":\"spin-server\"}\r\n",
+            )
+            .await?;
+        info!("🔌 Connected to NATS at {}", self.address);
+        Ok(stream)
+    }
+
+    async fn publish_on(
+        stream: &mut BufReader<TcpStream>,
+        subject: &str,
+        message_id: &str,
+        payload: &[u8],
+    ) -> Result<()> {
+        let headers = format!("NATS/1.0\r\nNats-Msg-Id: {}\r\n\r\n", message_id);
+        let mut frame = format!(
+            "HPUB {} {} {}\r\n",
+            subject,
+            headers.len(),
+            headers.len() + payload.len()
+        )
+        .into_bytes();
+        frame.extend_from_slice(headers.as_bytes());
+        frame.extend_from_slice(payload);
+        frame.extend_from_slice(b"\r\nPING\r\n");
+        stream.get_mut().write_all(&frame).await?;
+
+        // The server answers PING only after processing the HPUB, or sends -ERR if it rejected it
+        loop {
+            let line = read_line(stream).await?;
+            match line.as_str() {
+                "PONG" => return Ok(()),
+                "PING" => stream.get_mut().write_all(b"PONG\r\n").await?,
+                error if error.starts_with("-ERR") => bail!("NATS rejected publish: {}", error),
+                _ => {}
+            }
+        }
+    }
+}
+
+async fn read_line(stream: &mut BufReader<TcpStream>) -> Result<String> {
+    let mut line = String::new();
+    if stream.read_line(&mut line).await? == 0 {
+        bail!("NATS connection closed");
+    }
+    Ok(line.trim_end().to_string())
+}
+
+#[async_trait]
+impl BusPublisher for NatsPublisher {
+    async fn publish(&self, subject: &str, message_id: &str, payload: &[u8]) -> Result<()> {
+        let mut connection = self.connection.lock().await;
+        let publish = async {
+            if connection.is_none() {
+                *connection = Some(self.connect().await?);
+            }
+            let stream = connection
+                .as_mut()
+                .ok_or_else(|| anyhow!("NATS connection missing"))?;
+            Self::publish_on(stream, subject, message_id, payload).await
+        };
+        let result = tokio::time::timeout(Duration::from_secs(BUS_PUBLISH_TIMEOUT_SECS), publish)
+            .await
+            .unwrap_or_else(|_| Err(anyhow!("NATS publish timed out")));
+
+        // Reconnect on the next publish after any failure
+        if result.is_err() {
+            *connection = None;
+        }
+        result
+    }
+}
+
+/// Id that stays the same when an event is delivered again: signature, log index, event type
+/// and order PDA (mint for non-order events)
+pub fn event_idempotency_key(event: &SpinPetEvent) -> String {
+    let target = event.order_pda().unwrap_or_else(|| event.mint_account());
+    format!(
+        "{}:{}:{}:{}",
+        event.signature(),
+        event.log_index(),
+        event.type_name(),
+        target
+    )
+}
+
+/// Publishes every event as JSON on `bus.subject_template`, at least once. Events wait in a queue
+/// of `bus.queue_size` drained by one publish task, which absorbs short outages; once the queue
+/// is full, event handling waits for room rather than dropping events. A failed publish is
+/// retried on a fresh connection until it succeeds, backing off up to `bus.max_retry_delay_ms`;
+/// receivers dedup retried deliveries on the message id
+pub struct BusEventHandler {
+    queue: mpsc::Sender<SpinPetEvent>,
+}
+
+impl BusEventHandler {
+    pub fn new(config: &BusConfig, publisher: Arc<dyn BusPublisher>) -> Self {
+        let (queue, mut receiver) = mpsc::channel(config.queue_size.max(1));
+        let worker = BusPublishWorker {
+            publisher,
+            subject_template: config.subject_template.clone(),
+            max_retries: config.max_retries,
+            retry_delay: Duration::from_millis(config.retry_delay_ms),
+            max_retry_delay: Duration::from_millis(
+                config.max_retry_delay_ms.max(config.retry_delay_ms),
+            ),
+        };
+        tokio::spawn(async move {
+            while let Some(event) = receiver.recv().await {
+                if let Err(e) = worker.publish(&event).await {
+                    error!("❌ Unpublishable bus event: {}", e);
+                }
+            }
+            debug!("📤 Bus publish task stopped");
+        });
+        Self { queue }
+    }
+
+    /// Handler publishing to the NATS server at `bus.url`
+    pub fn from_config(config: &BusConfig) -> Self {
+        Self::new(config, Arc::new(NatsPublisher::new(&config.url)))
+    }
+}
+
+/// Subject of an event, with `{mint}` and `{type}` substituted into `template`
+pub fn bus_subject(template: &str, event: &SpinPetEvent) -> String {
+    template
+        .replace("{mint}", event.mint_account())
+        .replace("{type}", event.type_name())
+}
+
+/// Publishes queued events one at a time, retrying each until it goes through. The delay
+/// doubles from `retry_delay` up to `max_retry_delay`; after `max_retries` retries failures are
+/// logged as errors instead of warnings
+struct BusPublishWorker {
+    publisher: Arc<dyn BusPublisher>,
+    subject_template: String,
+    max_retries: u32,
+    retry_delay: Duration,
+    max_retry_delay: Duration,
+}
+
+impl BusPublishWorker {
+    async fn publish(&self, event: &SpinPetEvent) -> Result<()> {
+        let subject = bus_subject(&self.subject_template, event);
+        let message_id = event_idempotency_key(event);
+        let payload = serde_json::to_vec(event)?;
+
+        let mut attempt: u32 = 0;
+        let mut delay = self.retry_delay;
+        loop {
+            match self
+                .publisher
+                .publish(&subject, &message_id, &payload)
+                .await
+            {
+                Ok(()) => {
+                    debug!("📤 Published {} on {}", message_id, subject);
+                    return Ok(());
+                }
+                Err(e) => {
+                    attempt = attempt.saturating_add(1);
+                    if attempt <= self.max_retries {
+                        warn!(
+                            "⚠️ Bus publish of {} failed ({}), retry {}/{}",
+                            message_id, e, attempt, self.max_retries
+                        );
+                    } else {
+                        error!(
+                            "❌ Bus publish of {} still failing after {} retries ({}), retrying in {:?}",
+                            message_id,
+                            attempt - 1,
+                            e,
+                            delay
+                        );
+                    }
+                    sleep(delay).await;
+                    if attempt >= self.max_retries {
+                        delay = (delay * 2).min(self.max_retry_delay);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl EventHandler for BusEventHandler {
+    async fn handle_event(&self, event: SpinPetEvent) -> Result<()> {
+        // A full queue holds event handling back until the publish task catches up
+        self.queue
+            .send(event)
+            .await
+            .map_err(|_| anyhow!("bus publish task stopped"))
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solana::events::{LongShortEvent, TimestampSource};
+    use chrono::Utc;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+    use tokio::sync::Notify;
+
+    /// Minimal NATS server: drops the first connection right after INFO, then records every
+    /// HPUB as (subject, headers, payload) and answers PING with PONG
+    async fn start_mock_nats() -> (String, mpsc::UnboundedReceiver<(String, String, String)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let (published, receiver) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            let (mut dropped, _) = listener.accept().await.unwrap();
+            dropped.write_all(b"INFO {}\r\n").await.unwrap();
+            drop(dropped);
+
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut stream = BufReader::new(socket);
+            stream.get_mut().write_all(b"INFO {}\r\n").await.unwrap();
+            loop {
+                let mut line = String::new();
+                if stream.read_line(&mut line).await.unwrap() == 0 {
+                    return;
+                }
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                match parts.first().copied() {
+                    Some("HPUB") => {
+                        let header_len: usize = parts[2].parse().unwrap();
+                        let total_len: usize = parts[3].parse().unwrap();
+                        let mut body = vec![0u8; total_len + 2];
+                        stream.read_exact(&mut body).await.unwrap();
+                        let headers = String::from_utf8_lossy(&body[..header_len]).to_string();
+                        let payload =
+                            String::from_utf8_lossy(&body[header_len..total_len]).to_string();
+                        published
+                            .send((parts[1].to_string(), headers, payload))
+                            .unwrap();
+                    }
+                    Some("PING") => stream.get_mut().write_all(b"PONG\r\n").await.unwrap(),
+                    _ => {}
+                }
+            }
+        });
+
+        (format!("nats://{}", address), receiver)
+    }
+
+    /// Publisher whose publishes never complete, announcing each one it starts
+    struct StalledPublisher(Arc<Notify>);
+
+    #[async_trait]
+    impl BusPublisher for StalledPublisher {
+        async fn publish(&self, _subject: &str, _message_id: &str, _payload: &[u8]) -> Result<()> {
+            self.0.notify_one();
+            std::future::pending().await
+        }
+    }
+
+    fn long_short_event(signature: &str) -> SpinPetEvent {
+        SpinPetEvent::LongShort(LongShortEvent {
+            payer: "payer".to_string(),
+            mint_account: "bus_mint".to_string(),
+            order_pda: "bus_order".to_string(),
+            latest_price: 1,
+            order_type: 1,
+            mint: "bus_mint".to_string(),
+            user: "user".to_string(),
+            lock_lp_start_price: 1,
+            lock_lp_end_price: 2,
+            lock_lp_sol_amount: 3,
+            lock_lp_token_amount: 4,
+            start_time: 0,
+            end_time: 0,
+            margin_sol_amount: 5,
+            borrow_amount: 6,
+            position_asset_amount: 7,
+            borrow_fee: 0,
+            timestamp: Utc::now(),
+            timestamp_source: TimestampSource::ServerReceive,
+            signature: signature.to_string(),
+            slot: 1,
+            log_index: 0,
+        })
+    }
+
+    /// Publisher failing its first `failures` publishes, recording the message ids it accepts
+    struct FlakyPublisher {
+        failures: std::sync::atomic::AtomicU32,
+        published: std::sync::Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl BusPublisher for FlakyPublisher {
+        async fn publish(&self, _subject: &str, message_id: &str, _payload: &[u8]) -> Result<()> {
+            let remaining = self.failures.load(std::sync::atomic::Ordering::SeqCst);
+            if remaining > 0 {
+                self.failures
+                    .store(remaining - 1, std::sync::atomic::Ordering::SeqCst);
+                bail!("bus unavailable");
+            }
+            self.published.lock().unwrap().push(message_id.to_string());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_waits_instead_of_dropping_events() {
+        let started = Arc::new(Notify::new());
+        let config = BusConfig {
+            queue_size: 1,
+            ..BusConfig::default()
+        };
+        let handler =
+            BusEventHandler::new(&config, Arc::new(StalledPublisher(Arc::clone(&started))));
+
+        // The publish task holds the first event, the second waits in the queue and the
+        // third waits for room instead of being dropped
+        handler
+            .handle_event(long_short_event("sig_1"))
+            .await
+            .unwrap();
+        started.notified().await;
+        handler
+            .handle_event(long_short_event("sig_2"))
+            .await
+            .unwrap();
+        let third = tokio::time::timeout(
+            Duration::from_millis(50),
+            handler.handle_event(long_short_event("sig_3")),
+        )
+        .await;
+        assert!(third.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_event_delivered_after_more_than_max_retries_failures() {
+        let publisher = Arc::new(FlakyPublisher {
+            failures: std::sync::atomic::AtomicU32::new(5),
+            published: std::sync::Mutex::new(Vec::new()),
+        });
+        let config = BusConfig {
+            max_retries: 2,
+            retry_delay_ms: 1,
+            max_retry_delay_ms: 4,
+            ..BusConfig::default()
+        };
+        let handler = BusEventHandler::new(&config, publisher.clone());
+
+        handler
+            .handle_event(long_short_event("flaky_sig"))
+            .await
+            .unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while publisher.published.lock().unwrap().is_empty() {
+                sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            *publisher.published.lock().unwrap(),
+            vec!["flaky_sig:0:long_short:bus_order".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_event_published_to_mint_subject_after_reconnect() {
+        let (url, mut published) = start_mock_nats().await;
+        let config = BusConfig {
+            url,
+            retry_delay_ms: 10,
+            ..BusConfig::default()
+        };
+        let handler = BusEventHandler::from_config(&config);
+
+        let event = long_short_event("bus_sig");
+
+        // The first connection is dropped, the retry reconnects and the server confirms
+        handler.handle_event(event.clone()).await.unwrap();
+
+        let (subject, headers, payload) = published.recv().await.unwrap();
+        assert_eq!(subject, "spinpet.events.bus_mint.long_short");
+        assert!(headers.contains("Nats-Msg-Id: bus_sig:0:long_short:bus_order"));
+        assert_eq!(
+            event_idempotency_key(&event),
+            "bus_sig:0:long_short:bus_order"
+        );
+        let payload: serde_json::Value = serde_json::from_str(&payload).unwrap();
+        assert_eq!(payload["event_type"], "LongShort");
+        assert_eq!(payload["signature"], "bus_sig");
+        assert!(published.try_recv().is_err());
+    }
+}
@@ -0,0 +1,189 @@
+use anyhow::{bail, Result};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{error, info, warn};
+
+use crate::services::event_service::{EventService, TimestampDerivation};
+
+/// Free space of the filesystem holding a path
+pub trait DiskSpaceProbe: Send + Sync {
+    /// Bytes available to unprivileged writers
+    fn free_bytes(&self, path: &Path) -> Result<u64>;
+}
+
+/// `statvfs(3)` on the path
+pub struct StatvfsProbe;
+
+impl DiskSpaceProbe for StatvfsProbe {
+    fn free_bytes(&self, path: &Path) -> Result<u64> {
+        use std::os::unix::ffi::OsStrExt;
+
+        let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())?;
+        let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+        // SAFETY: c_path is NUL-terminated and stat is a valid, writable statvfs
+        if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+            bail!(
+                "statvfs({}) failed: {}",
+                path.display(),
+                std::io::Error::last_os_error()
+            );
+        }
+        Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+    }
+}
+
+/// Degraded (read-only) mode while the database filesystem has less than
+/// `database.min_free_disk_mb` free. With fsync off a full disk may only fail at flush time,
+/// after events were acknowledged, so event storage is refused before that point
+pub struct DiskSpaceGuard {
+    path: PathBuf,
+    min_free_bytes: u64,
+    probe: Arc<dyn DiskSpaceProbe>,
+    degraded: AtomicBool,
+    free_bytes: AtomicU64,
+}
+
+impl DiskSpaceGuard {
+    pub fn new(path: &str, min_free_disk_mb: u64, probe: Arc<dyn DiskSpaceProbe>) -> Self {
+        Self {
+            path: PathBuf::from(path),
+            min_free_bytes: min_free_disk_mb.saturating_mul(1024 * 1024),
+            probe,
+            degraded: AtomicBool::new(false),
+            free_bytes: AtomicU64::new(u64::MAX),
+        }
+    }
+
+    /// Probe the free space and enter or leave degraded mode; returns whether degraded.
+    /// A failed probe keeps the current mode
+    pub fn check(&self) -> bool {
+        let free_bytes = match self.probe.free_bytes(&self.path) {
+            Ok(free_bytes) => free_bytes,
+            Err(e) => {
+                warn!("⚠️ Disk space check failed: {}", e);
+                return self.is_degraded();
+            }
+        };
+        self.free_bytes.store(free_bytes, Ordering::Relaxed);
+
+        let degraded = free_bytes < self.min_free_bytes;
+        if self.degraded.swap(degraded, Ordering::Relaxed) != degraded {
+            if degraded {
+                error!(
+                    "🚨 {} has {} bytes free, below database.min_free_disk_mb ({} bytes): entering read-only mode, event storage refused",
+                    self.path.display(),
+                    free_bytes,
+                    self.min_free_bytes
+                );
+            } else {
+                info!(
+                    "✅ {} has {} bytes free again, leaving read-only mode",
+                    self.path.display(),
+                    free_bytes
+                );
+            }
+        }
+        degraded
+    }
+
+    pub fn is_degraded(&self) -> bool {
+        self.degraded.load(Ordering::Relaxed)
+    }
+
+    /// Free bytes seen by the last successful check
+    pub fn free_bytes(&self) -> u64 {
+        self.free_bytes.load(Ordering::Relaxed)
+    }
+}
+
+/// Periodically check the database free space, stopping the event listener while degraded.
+/// Once space is back the listener restarts and the slots it missed are replayed from the
+/// newest stored slot, at most `max_replay_slots` of them
+pub async fn start_disk_space_task(
+    guard: Arc<DiskSpaceGuard>,
+    event_service: Arc<RwLock<EventService>>,
+    interval_secs: u64,
+    max_replay_slots: u64,
+    timestamps: TimestampDerivation,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+        let mut paused = false;
+
+        loop {
+            interval.tick().await;
+            let degraded = guard.check();
+            if degraded == paused {
+                continue;
+            }
+
+            let mut service = event_service.write().await;
+            let stored_slot = service.latest_stored_slot();
+            let result = if degraded {
+                warn!("⏸️ Pausing the event listener while the disk is low");
+                service.stop().await
+            } else {
+                info!("▶️ Resuming the event listener");
+                service.start().await
+            };
+            let resumed = match result {
+                Ok(()) => {
+                    paused = degraded;
+                    !degraded
+                }
+                Err(e) => {
+                    warn!("⚠️ Failed to switch the event listener: {}", e);
+                    false
+                }
+            };
+            drop(service);
+
+            if resumed {
+                let service = event_service.read().await;
+                match service
+                    .catch_up(stored_slot, max_replay_slots, timestamps)
+                    .await
+                {
+                    Ok(Some(result)) => info!(
+                        "✅ Replayed slots {}..={} missed while paused ({} events)",
+                        result.from_slot, result.to_slot, result.events_processed
+                    ),
+                    Ok(None) => {}
+                    Err(e) => warn!("⚠️ Replay of the slots missed while paused failed: {}", e),
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FixedProbe(AtomicU64);
+
+    impl DiskSpaceProbe for FixedProbe {
+        fn free_bytes(&self, _path: &Path) -> Result<u64> {
+            Ok(self.0.load(Ordering::Relaxed))
+        }
+    }
+
+    #[test]
+    fn test_degraded_below_free_space_threshold() {
+        let probe = Arc::new(FixedProbe(AtomicU64::new(500 * 1024 * 1024)));
+        let guard = DiskSpaceGuard::new("/data/rocksdb", 100, probe.clone());
+        assert!(!guard.check());
+        assert_eq!(guard.free_bytes(), 500 * 1024 * 1024);
+
+        probe.0.store(99 * 1024 * 1024, Ordering::Relaxed);
+        assert!(guard.check());
+        assert!(guard.is_degraded());
+
+        probe.0.store(100 * 1024 * 1024, Ordering::Relaxed);
+        assert!(!guard.check());
+        assert!(!guard.is_degraded());
+    }
+}
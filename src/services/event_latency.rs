@@ -0,0 +1,74 @@
+use chrono::{DateTime, Utc};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::solana::events::{SpinPetEvent, TimestampSource};
+
+/// Upper bounds of the receive latency histogram buckets, in milliseconds
+pub const RECEIVE_LATENCY_BUCKETS_MS: [u64; 10] = [
+    100, 250, 500, 1_000, 2_500, 5_000, 10_000, 30_000, 60_000, 300_000,
+];
+
+/// Milliseconds between an event's block time and `processed_at`; None unless the event carries
+/// its block time. Block times have second resolution, so an early clock reads as zero
+pub fn receive_latency_ms(event: &SpinPetEvent, processed_at: DateTime<Utc>) -> Option<u64> {
+    if event.timestamp_source() != TimestampSource::BlockTime {
+        return None;
+    }
+    let latency = processed_at.signed_duration_since(event.timestamp());
+    Some(latency.num_milliseconds().max(0) as u64)
+}
+
+/// Cumulative histogram of how long after their block time live events were processed
+#[derive(Debug, Default)]
+pub struct ReceiveLatencyHistogram {
+    buckets: [AtomicU64; RECEIVE_LATENCY_BUCKETS_MS.len()],
+    count: AtomicU64,
+    sum_ms: AtomicU64,
+}
+
+impl ReceiveLatencyHistogram {
+    pub fn observe(&self, latency_ms: u64) {
+        for (bound, bucket) in RECEIVE_LATENCY_BUCKETS_MS.iter().zip(&self.buckets) {
+            if latency_ms <= *bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_ms.fetch_add(latency_ms, Ordering::Relaxed);
+    }
+
+    /// (observations, sum of latencies in milliseconds)
+    pub fn totals(&self) -> (u64, u64) {
+        (
+            self.count.load(Ordering::Relaxed),
+            self.sum_ms.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Observations at or below `bound_ms`, which must be one of the bucket bounds
+    pub fn bucket_count(&self, bound_ms: u64) -> Option<u64> {
+        RECEIVE_LATENCY_BUCKETS_MS
+            .iter()
+            .position(|bound| *bound == bound_ms)
+            .map(|index| self.buckets[index].load(Ordering::Relaxed))
+    }
+
+    /// The histogram in the Prometheus text format
+    pub fn to_prometheus(&self) -> String {
+        let (count, sum_ms) = self.totals();
+        let mut body = String::from("# TYPE spin_event_receive_latency_ms histogram\n");
+        for (bound, bucket) in RECEIVE_LATENCY_BUCKETS_MS.iter().zip(&self.buckets) {
+            body.push_str(&format!(
+                "spin_event_receive_latency_ms_bucket{{le=\"{}\"}} {}\n",
+                bound,
+                bucket.load(Ordering::Relaxed)
+            ));
+        }
+        body.push_str(&format!(
+            "spin_event_receive_latency_ms_bucket{{le=\"+Inf\"}} {count}\n\
+             spin_event_receive_latency_ms_sum {sum_ms}\n\
+             spin_event_receive_latency_ms_count {count}\n"
+        ));
+        body
+    }
+}
@@ -1,17 +1,21 @@
-use crate::config::SolanaConfig;
+use crate::config::{AdminConfig, ReplayTimestampMode, SolanaConfig};
 use crate::services::event_storage::EventStorage;
 use crate::solana::{
-    DefaultEventHandler, EventHandler, EventListenerManager, SolanaClient, SpinPetEvent,
+    CompositeEventHandler, DefaultEventHandler, EventHandler, EventListenerManager,
+    EventMinLengths, EventParser, ListenerConnectionStatus, ParserStats, SolanaClient,
+    SpinPetEvent, TimestampSource, TokenStringLimits, TransactionFetcher,
 };
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, TimeZone, Utc};
 use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashSet;
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use tracing::{error, info};
+use tracing::{debug, error, info, warn};
 use utoipa::ToSchema;
 
 /// Event service status
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
 pub struct EventServiceStatus {
     pub is_running: bool,
     #[schema(value_type = Option<String>)]
@@ -19,10 +23,23 @@ pub struct EventServiceStatus {
     pub total_events_processed: u64,
     pub connection_status: String,
     pub program_id: String,
+    /// Chain head slot at the configured commitment; None when RPC is unavailable
+    pub head_slot: Option<u64>,
+    /// Highest slot among processed events
+    pub last_event_slot: Option<u64>,
+    /// How far event processing trails the chain head; None when either slot is unknown
+    pub lag_slots: Option<u64>,
+    /// Times the listener re-armed its connection loop after exhausting reconnect attempts
+    pub listener_restart_cycles: u32,
+}
+
+/// Slots between the chain head and the last processed event, unknown if either is missing
+pub fn compute_lag_slots(head_slot: Option<u64>, last_event_slot: Option<u64>) -> Option<u64> {
+    Some(head_slot?.saturating_sub(last_event_slot?))
 }
 
 /// Event statistics
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
 pub struct EventStats {
     pub token_created: u64,
     pub buy_sell: u64,
@@ -34,10 +51,291 @@ pub struct EventStats {
     pub total: u64,
 }
 
+/// Slots whose transactions a replay fetches and applies together, bounding what it buffers
+const REPLAY_WINDOW_SLOTS: u64 = 1_000;
+
+/// Slot replay result
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct ReplayResult {
+    pub from_slot: u64,
+    pub to_slot: u64,
+    pub signatures_processed: usize,
+    pub events_processed: usize,
+    /// Events whose timestamp was derived because their transaction had no block time
+    pub timestamps_derived: usize,
+}
+
+/// How replay fills in the timestamp of events whose transaction carries no block time
+/// (missing or zero). Off unless configured, so replay never makes up times silently
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum TimestampDerivation {
+    /// Keep the receive time
+    #[default]
+    Off,
+    /// Ask getBlockTime for the event's slot
+    BlockTime,
+    /// `anchor_time + (slot - anchor_slot) / slots_per_second`
+    SlotAnchor {
+        anchor_slot: u64,
+        anchor_time: i64,
+        slots_per_second: f64,
+    },
+}
+
+impl TimestampDerivation {
+    pub fn from_config(config: &AdminConfig) -> anyhow::Result<Self> {
+        Ok(match config.replay_timestamp_derivation {
+            ReplayTimestampMode::Off => Self::Off,
+            ReplayTimestampMode::BlockTime => Self::BlockTime,
+            ReplayTimestampMode::SlotAnchor => {
+                if config.replay_anchor_time <= 0
+                    || !config.replay_slots_per_second.is_finite()
+                    || config.replay_slots_per_second <= 0.0
+                {
+                    anyhow::bail!(
+                        "slot_anchor timestamps need admin.replay_anchor_time and a positive admin.replay_slots_per_second"
+                    );
+                }
+                Self::SlotAnchor {
+                    anchor_slot: config.replay_anchor_slot,
+                    anchor_time: config.replay_anchor_time,
+                    slots_per_second: config.replay_slots_per_second,
+                }
+            }
+        })
+    }
+
+    /// Derived time of `slot` and its source; None when off or unresolvable
+    async fn derive(
+        &self,
+        fetcher: &dyn TransactionFetcher,
+        slot: u64,
+    ) -> Option<(DateTime<Utc>, TimestampSource)> {
+        let (seconds, source) = match *self {
+            Self::Off => return None,
+            Self::BlockTime => (
+                fetcher.get_block_time(slot).await.ok().flatten()?,
+                TimestampSource::BlockTime,
+            ),
+            Self::SlotAnchor {
+                anchor_slot,
+                anchor_time,
+                slots_per_second,
+            } => {
+                let offset = (slot as f64 - anchor_slot as f64) / slots_per_second;
+                (
+                    anchor_time + offset.round() as i64,
+                    TimestampSource::SlotDerived,
+                )
+            }
+        };
+        if seconds <= 0 {
+            return None;
+        }
+        Some((Utc.timestamp_opt(seconds, 0).single()?, source))
+    }
+}
+
+/// Re-fetch the program's transactions in [from_slot, to_slot] and run them through the handler pipeline
+/// as replayed events: stored like live events, but not broadcast to live subscribers.
+/// Events are applied in (slot, log index) order with the handlers in backfill mode, so
+/// reconstructed candles match live processing regardless of fetch order
+pub async fn replay_slot_range(
+    fetcher: &dyn TransactionFetcher,
+    event_parser: &EventParser,
+    event_handler: &dyn EventHandler,
+    from_slot: u64,
+    to_slot: u64,
+    timestamps: TimestampDerivation,
+) -> anyhow::Result<ReplayResult> {
+    event_handler.begin_backfill();
+    let result = replay_in_slot_order(
+        fetcher,
+        event_parser,
+        event_handler,
+        from_slot,
+        to_slot,
+        timestamps,
+    )
+    .await;
+    event_handler.end_backfill();
+    result
+}
+
+/// Replay from the newest stored slot (`meta:latest_slot`) to the chain head, limited to the newest
+/// `max_slots` slots. None when nothing is stored yet or the head isn't ahead of it
+pub async fn catch_up_to_head(
+    fetcher: &dyn TransactionFetcher,
+    event_parser: &EventParser,
+    event_handler: &dyn EventHandler,
+    stored_slot: u64,
+    max_slots: u64,
+    timestamps: TimestampDerivation,
+) -> anyhow::Result<Option<ReplayResult>> {
+    if stored_slot == 0 {
+        return Ok(None);
+    }
+    let head_slot = fetcher.get_head_slot().await?;
+    if head_slot <= stored_slot {
+        return Ok(None);
+    }
+
+    let from_slot = stored_slot.max(head_slot.saturating_sub(max_slots));
+    if from_slot > stored_slot {
+        warn!(
+            "⚠️ {} slots behind the chain head, catching up the newest {}; slots {}..={} need an admin replay",
+            head_slot - stored_slot,
+            max_slots,
+            stored_slot,
+            from_slot - 1
+        );
+    }
+    replay_slot_range(
+        fetcher,
+        event_parser,
+        event_handler,
+        from_slot,
+        head_slot,
+        timestamps,
+    )
+    .await
+    .map(Some)
+}
+
+async fn replay_in_slot_order(
+    fetcher: &dyn TransactionFetcher,
+    event_parser: &EventParser,
+    event_handler: &dyn EventHandler,
+    from_slot: u64,
+    to_slot: u64,
+    timestamps: TimestampDerivation,
+) -> anyhow::Result<ReplayResult> {
+    info!("🔁 Replaying slot range {}..={}", from_slot, to_slot);
+    let mut result = ReplayResult {
+        from_slot,
+        to_slot,
+        ..Default::default()
+    };
+
+    // Windows are applied oldest first, so only one window's events are held at a time
+    let mut window_start = from_slot;
+    while window_start <= to_slot {
+        let window_end = window_start
+            .saturating_add(REPLAY_WINDOW_SLOTS - 1)
+            .min(to_slot);
+        replay_window(
+            fetcher,
+            event_parser,
+            event_handler,
+            window_start,
+            window_end,
+            timestamps,
+            &mut result,
+        )
+        .await?;
+        if window_end == to_slot {
+            break;
+        }
+        window_start = window_end + 1;
+    }
+
+    info!(
+        "✅ Replay complete: {} signatures, {} events, {} derived timestamps",
+        result.signatures_processed, result.events_processed, result.timestamps_derived
+    );
+    Ok(result)
+}
+
+/// Fetch the transactions of one replay window, then apply their events in slot order
+async fn replay_window(
+    fetcher: &dyn TransactionFetcher,
+    event_parser: &EventParser,
+    event_handler: &dyn EventHandler,
+    from_slot: u64,
+    to_slot: u64,
+    timestamps: TimestampDerivation,
+    result: &mut ReplayResult,
+) -> anyhow::Result<()> {
+    let signatures = fetcher
+        .get_signatures_for_slot_range(from_slot, to_slot)
+        .await?;
+    debug!(
+        "🔁 Replaying {} signatures in slot window {}..={}",
+        signatures.len(),
+        from_slot,
+        to_slot
+    );
+
+    // Fetch the whole window first; (slot, position, log index, event)
+    let mut events = Vec::new();
+    for (position, signature) in signatures.iter().enumerate() {
+        let transaction = fetcher.get_transaction_with_logs(signature).await?;
+        let logs: Vec<String> = match transaction
+            .get("meta")
+            .and_then(|meta| meta.get("logMessages"))
+            .and_then(|logs| logs.as_array())
+        {
+            Some(logs) => logs
+                .iter()
+                .filter_map(|l| l.as_str())
+                .map(|s| s.to_string())
+                .collect(),
+            None => {
+                warn!(
+                    "⚠️ No logs available for replayed transaction {}",
+                    signature
+                );
+                continue;
+            }
+        };
+        let slot = transaction
+            .get("slot")
+            .and_then(|s| s.as_u64())
+            .unwrap_or(0);
+
+        // A zero block time is as good as none; it would bucket candles at the epoch
+        let block_time = transaction
+            .get("blockTime")
+            .and_then(|t| t.as_i64())
+            .filter(|t| *t > 0)
+            .and_then(|t| Utc.timestamp_opt(t, 0).single());
+
+        let parsed = event_parser.parse_events_with_call_stack(&logs, signature, slot)?;
+        let (timestamp, derived) = match block_time {
+            Some(block_time) => (Some((block_time, TimestampSource::BlockTime)), false),
+            None if parsed.is_empty() => (None, false),
+            None => {
+                let derived = timestamps.derive(fetcher, slot).await;
+                (derived, derived.is_some())
+            }
+        };
+        for (log_index, mut event) in parsed.into_iter().enumerate() {
+            if let Some((timestamp, source)) = timestamp {
+                event.set_timestamp(timestamp, source);
+            }
+            if derived {
+                result.timestamps_derived += 1;
+            }
+            events.push((slot, position, log_index, event));
+        }
+        result.signatures_processed += 1;
+    }
+
+    // Signatures are listed newest first, so within a slot a later position is an earlier transaction
+    events.sort_by_key(|(slot, position, log_index, _)| (*slot, Reverse(*position), *log_index));
+    for (_, _, _, event) in events {
+        event_handler.handle_replayed_event(event).await?;
+        result.events_processed += 1;
+    }
+
+    Ok(())
+}
+
 /// Enhanced event handler with statistics and storage functionality
 pub struct StatsEventHandler {
     stats: Arc<RwLock<EventStats>>,
     last_event_time: Arc<RwLock<Option<DateTime<Utc>>>>,
+    last_event_slot: Arc<RwLock<Option<u64>>>,
     event_storage: Arc<EventStorage>,
 }
 
@@ -55,6 +353,7 @@ impl StatsEventHandler {
                 total: 0,
             })),
             last_event_time: Arc::new(RwLock::new(None)),
+            last_event_slot: Arc::new(RwLock::new(None)),
             event_storage,
         }
     }
@@ -66,11 +365,13 @@ impl StatsEventHandler {
     pub async fn get_last_event_time(&self) -> Option<DateTime<Utc>> {
         *self.last_event_time.read().await
     }
-}
 
-#[async_trait::async_trait]
-impl EventHandler for StatsEventHandler {
-    async fn handle_event(&self, event: SpinPetEvent) -> anyhow::Result<()> {
+    pub async fn get_last_event_slot(&self) -> Option<u64> {
+        *self.last_event_slot.read().await
+    }
+
+    /// Store the event and update statistics
+    async fn record_event(&self, event: SpinPetEvent) -> anyhow::Result<()> {
         // Store event in RocksDB
         if let Err(e) = self.event_storage.store_event(event.clone()).await {
             error!("❌ Failed to store event: {}", e);
@@ -98,12 +399,44 @@ impl EventHandler for StatsEventHandler {
             *last_time = Some(Utc::now());
         }
 
+        // Track the highest processed slot for lag monitoring
+        {
+            let mut last_slot = self.last_event_slot.write().await;
+            *last_slot = (*last_slot).max(Some(event.slot()));
+        }
+
         // Call default handler for log output
         let default_handler = DefaultEventHandler;
         default_handler.handle_event(event).await?;
 
         Ok(())
     }
+}
+
+#[async_trait::async_trait]
+impl EventHandler for StatsEventHandler {
+    async fn handle_event(&self, event: SpinPetEvent) -> anyhow::Result<()> {
+        if let Err(e) = self
+            .event_storage
+            .record_receive_latency(&event, Utc::now())
+        {
+            warn!("⚠️ Failed to store event receive latency: {}", e);
+        }
+        self.record_event(event).await
+    }
+
+    /// Replayed events are long past their block time, so they stay out of the latency histogram
+    async fn handle_replayed_event(&self, event: SpinPetEvent) -> anyhow::Result<()> {
+        self.record_event(event).await
+    }
+
+    fn begin_backfill(&self) {
+        self.event_storage.begin_strict_kline_ordering();
+    }
+
+    fn end_backfill(&self) {
+        self.event_storage.end_strict_kline_ordering();
+    }
 
     fn as_any(&self) -> &dyn std::any::Any {
         self
@@ -115,7 +448,6 @@ pub struct EventService {
     client: Arc<SolanaClient>,
     listener_manager: EventListenerManager,
     event_handler: Arc<dyn EventHandler>,
-    #[allow(dead_code)]
     event_storage: Arc<EventStorage>,
     config: SolanaConfig,
 }
@@ -140,10 +472,17 @@ impl EventService {
         config: &crate::config::Config,
         event_handler: Arc<dyn EventHandler>,
     ) -> anyhow::Result<Self> {
-        let client = Arc::new(SolanaClient::new(
-            &config.solana.rpc_url,
-            &config.solana.program_id,
-        )?);
+        let client = Arc::new(
+            SolanaClient::new(&config.solana.rpc_url, &config.solana.program_id)?
+                .with_head_slot_config(
+                    &config.solana.commitment,
+                    config.solana.head_slot_cache_ttl_ms,
+                )?
+                .with_transaction_fetch_retry(
+                    config.solana.transaction_fetch_attempts,
+                    config.solana.transaction_fetch_retry_delay_ms,
+                ),
+        );
         let event_storage = Arc::new(EventStorage::new(config)?);
         let mut listener_manager = EventListenerManager::new();
 
@@ -169,10 +508,17 @@ impl EventService {
         event_handler: Arc<dyn EventHandler>,
         event_storage: Arc<EventStorage>,
     ) -> anyhow::Result<Self> {
-        let client = Arc::new(SolanaClient::new(
-            &config.solana.rpc_url,
-            &config.solana.program_id,
-        )?);
+        let client = Arc::new(
+            SolanaClient::new(&config.solana.rpc_url, &config.solana.program_id)?
+                .with_head_slot_config(
+                    &config.solana.commitment,
+                    config.solana.head_slot_cache_ttl_ms,
+                )?
+                .with_transaction_fetch_retry(
+                    config.solana.transaction_fetch_attempts,
+                    config.solana.transaction_fetch_retry_delay_ms,
+                ),
+        );
         let mut listener_manager = EventListenerManager::new();
 
         // Initialize listener
@@ -212,7 +558,6 @@ impl EventService {
         Ok(())
     }
 
-    #[allow(dead_code)]
     pub async fn stop(&mut self) -> anyhow::Result<()> {
         info!("🛑 Stopping event service");
         self.listener_manager.stop().await?;
@@ -220,34 +565,46 @@ impl EventService {
         Ok(())
     }
 
+    /// Locate the StatsEventHandler, either directly or inside a handler pipeline
+    fn stats_handler(&self) -> Option<&StatsEventHandler> {
+        let handler = self.event_handler.as_any();
+        if let Some(stats_handler) = handler.downcast_ref::<StatsEventHandler>() {
+            return Some(stats_handler);
+        }
+        handler
+            .downcast_ref::<CompositeEventHandler>()?
+            .handlers()
+            .iter()
+            .find_map(|h| h.as_any().downcast_ref::<StatsEventHandler>())
+    }
+
     /// Get service status
     pub async fn get_status(&self) -> EventServiceStatus {
         // Try to downcast to StatsEventHandler to get stats
-        let (stats, last_event_time) = if let Some(stats_handler) =
-            self.event_handler
-                .as_any()
-                .downcast_ref::<StatsEventHandler>()
-        {
-            (
-                stats_handler.get_stats().await,
-                stats_handler.get_last_event_time().await,
-            )
-        } else {
-            // If not a StatsEventHandler, use default values
-            (
-                EventStats {
-                    token_created: 0,
-                    buy_sell: 0,
-                    long_short: 0,
-                    force_liquidate: 0,
-                    full_close: 0,
-                    partial_close: 0,
-                    milestone_discount: 0,
-                    total: 0,
-                },
-                None,
-            )
-        };
+        let (stats, last_event_time, last_event_slot) =
+            if let Some(stats_handler) = self.stats_handler() {
+                (
+                    stats_handler.get_stats().await,
+                    stats_handler.get_last_event_time().await,
+                    stats_handler.get_last_event_slot().await,
+                )
+            } else {
+                // If not a StatsEventHandler, use default values
+                (
+                    EventStats {
+                        token_created: 0,
+                        buy_sell: 0,
+                        long_short: 0,
+                        force_liquidate: 0,
+                        full_close: 0,
+                        partial_close: 0,
+                        milestone_discount: 0,
+                        total: 0,
+                    },
+                    None,
+                    None,
+                )
+            };
 
         let connection_status = match self.client.check_connection().await {
             Ok(true) => "Connected".to_string(),
@@ -255,23 +612,80 @@ impl EventService {
             Err(e) => format!("Connection error: {}", e),
         };
 
+        // RPC failures leave the lag unknown instead of failing the status query
+        let head_slot = match self.client.get_slot().await {
+            Ok(slot) => Some(slot),
+            Err(e) => {
+                warn!("⚠️ Failed to fetch head slot for lag monitoring: {}", e);
+                None
+            }
+        };
+
         EventServiceStatus {
             is_running: self.listener_manager.is_running(),
             last_event_time,
             total_events_processed: stats.total,
             connection_status,
             program_id: self.config.program_id.clone(),
+            head_slot,
+            last_event_slot,
+            lag_slots: compute_lag_slots(head_slot, last_event_slot),
+            listener_restart_cycles: self.listener_manager.restart_cycles(),
         }
     }
 
+    /// Re-ingest the program's transactions in a slot range from chain
+    pub async fn replay_slot_range(
+        &self,
+        from_slot: u64,
+        to_slot: u64,
+        timestamps: TimestampDerivation,
+    ) -> anyhow::Result<ReplayResult> {
+        replay_slot_range(
+            self.client.as_ref(),
+            &self.replay_parser()?,
+            self.event_handler.as_ref(),
+            from_slot,
+            to_slot,
+            timestamps,
+        )
+        .await
+    }
+
+    /// Replay what was missed since `stored_slot`, the newest stored slot read before the
+    /// listener (re)started, at most `max_slots` slots
+    pub async fn catch_up(
+        &self,
+        stored_slot: u64,
+        max_slots: u64,
+        timestamps: TimestampDerivation,
+    ) -> anyhow::Result<Option<ReplayResult>> {
+        catch_up_to_head(
+            self.client.as_ref(),
+            &self.replay_parser()?,
+            self.event_handler.as_ref(),
+            stored_slot,
+            max_slots,
+            timestamps,
+        )
+        .await
+    }
+
+    /// Newest slot among stored events (`meta:latest_slot`)
+    pub fn latest_stored_slot(&self) -> u64 {
+        self.event_storage.latest_slot()
+    }
+
+    fn replay_parser(&self) -> anyhow::Result<EventParser> {
+        Ok(EventParser::new(&self.config.program_id)?
+            .with_string_limits(TokenStringLimits::from(&self.config))
+            .with_min_lengths(EventMinLengths::from(&self.config)))
+    }
+
     /// Get event statistics
     pub async fn get_stats(&self) -> EventStats {
         // Try to downcast to StatsEventHandler to get stats
-        if let Some(stats_handler) = self
-            .event_handler
-            .as_any()
-            .downcast_ref::<StatsEventHandler>()
-        {
+        if let Some(stats_handler) = self.stats_handler() {
             stats_handler.get_stats().await
         } else {
             // If not a StatsEventHandler, use default values
@@ -293,6 +707,21 @@ impl EventService {
         self.listener_manager.is_running()
     }
 
+    /// Get the listener's processed-signature dedup cache
+    pub fn processed_signatures(&self) -> Option<Arc<RwLock<HashSet<String>>>> {
+        self.listener_manager.processed_signatures()
+    }
+
+    /// Get the listener's parser counters
+    pub fn parser_stats(&self) -> Option<Arc<ParserStats>> {
+        self.listener_manager.parser_stats()
+    }
+
+    /// Get the listener's WebSocket connection state
+    pub fn connection_status(&self) -> Option<Arc<RwLock<ListenerConnectionStatus>>> {
+        self.listener_manager.connection_status()
+    }
+
     #[allow(dead_code)]
     pub fn get_program_id(&self) -> &str {
         &self.config.program_id
@@ -308,6 +737,98 @@ impl EventService {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::{AdminConfig, Config};
+    use crate::services::{EventQuery, MintDetailsQuery};
+    use crate::solana::BUY_SELL_EVENT_DISCRIMINATOR;
+    use async_trait::async_trait;
+    use base64::engine::Engine;
+    use serde_json::{json, Value};
+    use solana_sdk::pubkey::Pubkey;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    const PROGRAM_ID: &str = "JBMmrp6jhksqnxDBskkmVvWHhJLaPBjgiMHEroJbUTBZ";
+
+    fn create_test_config(temp_dir: &TempDir) -> Config {
+        Config::for_tests(temp_dir.path().to_str().unwrap())
+    }
+
+    /// Chain stand-in serving signatures by slot and their transaction bodies
+    struct MockChain {
+        transactions: Vec<(u64, String, Value)>,
+    }
+
+    #[async_trait]
+    impl TransactionFetcher for MockChain {
+        async fn get_transaction_with_logs(&self, signature: &str) -> anyhow::Result<Value> {
+            Ok(self
+                .transactions
+                .iter()
+                .find(|(_, sig, _)| sig == signature)
+                .map(|(_, _, tx)| tx.clone())
+                .unwrap_or_else(|| json!({})))
+        }
+
+        async fn get_signatures_for_slot_range(
+            &self,
+            from_slot: u64,
+            to_slot: u64,
+        ) -> anyhow::Result<Vec<String>> {
+            Ok(self
+                .transactions
+                .iter()
+                .filter(|(slot, _, _)| (from_slot..=to_slot).contains(slot))
+                .map(|(_, sig, _)| sig.clone())
+                .collect())
+        }
+
+        async fn get_block_time(&self, _slot: u64) -> anyhow::Result<Option<i64>> {
+            Ok(None)
+        }
+
+        async fn get_head_slot(&self) -> anyhow::Result<u64> {
+            Ok(self
+                .transactions
+                .iter()
+                .map(|(slot, _, _)| *slot)
+                .max()
+                .unwrap_or(0))
+        }
+    }
+
+    fn buy_sell_transaction(slot: u64, mint: &Pubkey, sol_amount: u64) -> Value {
+        buy_sell_transaction_at(slot, mint, sol_amount, 300, None)
+    }
+
+    fn buy_sell_transaction_at(
+        slot: u64,
+        mint: &Pubkey,
+        sol_amount: u64,
+        latest_price: u128,
+        block_time: Option<i64>,
+    ) -> Value {
+        let mut data = BUY_SELL_EVENT_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.extend_from_slice(mint.as_ref());
+        data.push(1);
+        data.extend_from_slice(&100u64.to_le_bytes());
+        data.extend_from_slice(&sol_amount.to_le_bytes());
+        data.extend_from_slice(&latest_price.to_le_bytes());
+        json!({
+            "slot": slot,
+            "blockTime": block_time,
+            "meta": {
+                "logMessages": [
+                    format!("Program {} invoke [1]", PROGRAM_ID),
+                    format!(
+                        "Program data: {}",
+                        base64::engine::general_purpose::STANDARD.encode(data)
+                    ),
+                    format!("Program {} success", PROGRAM_ID),
+                ]
+            }
+        })
+    }
 
     #[test]
     fn test_event_stats_creation() {
@@ -329,55 +850,8 @@ mod tests {
     async fn test_stats_event_handler() {
         // This is just a test stub, in real code we need to provide event_storage
         // Create a mock storage for testing
-        use crate::config::{
-            Config, CorsConfig, DatabaseConfig, IpfsConfig, KlineServiceConfig, LoggingConfig,
-            ServerConfig, SolanaConfig,
-        };
-        use tempfile::TempDir;
-
         let temp_dir = TempDir::new().unwrap();
-        let config = Config {
-            server: ServerConfig {
-                host: "localhost".to_string(),
-                port: 8080,
-            },
-            cors: CorsConfig {
-                enabled: true,
-                allow_origins: vec!["*".to_string()],
-            },
-            logging: LoggingConfig {
-                level: "debug".to_string(),
-            },
-            solana: SolanaConfig {
-                rpc_url: "http://localhost:8899".to_string(),
-                ws_url: "ws://localhost:8900".to_string(),
-                program_id: "JBMmrp6jhksqnxDBskkmVvWHhJLaPBjgiMHEroJbUTBZ".to_string(),
-                enable_event_listener: false,
-                commitment: "processed".to_string(),
-                reconnect_interval: 1,
-                max_reconnect_attempts: 20,
-                event_buffer_size: 1000,
-                event_batch_size: 100,
-                ping_interval_seconds: 60,
-            },
-            database: DatabaseConfig {
-                rocksdb_path: temp_dir.path().to_str().unwrap().to_string(),
-            },
-            ipfs: IpfsConfig {
-                gateway_url: "https://gateway.pinata.cloud/ipfs/".to_string(),
-                request_timeout_seconds: 30,
-                max_retries: 3,
-                retry_delay_seconds: 5,
-            },
-            kline: KlineServiceConfig {
-                enable_kline_service: false,
-                connection_timeout_secs: 60,
-                max_subscriptions_per_client: 100,
-                history_data_limit: 100,
-                ping_interval_secs: 25,
-                ping_timeout_secs: 60,
-            },
-        };
+        let config = create_test_config(&temp_dir);
         let event_storage = Arc::new(EventStorage::new(&config).unwrap());
 
         let handler = StatsEventHandler::new(event_storage);
@@ -386,4 +860,357 @@ mod tests {
         assert_eq!(initial_stats.total, 0);
         assert!(handler.get_last_event_time().await.is_none());
     }
+
+    #[tokio::test]
+    async fn test_replay_slot_range_restores_events() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = create_test_config(&temp_dir);
+        let event_storage = Arc::new(EventStorage::new(&config).unwrap());
+        let event_handler = StatsEventHandler::new(Arc::clone(&event_storage));
+        let event_parser = EventParser::new(PROGRAM_ID).unwrap();
+
+        let mint = Pubkey::new_unique();
+        let chain = MockChain {
+            transactions: vec![
+                (
+                    100,
+                    "sig_a".to_string(),
+                    buy_sell_transaction(100, &mint, 1_000),
+                ),
+                (
+                    150,
+                    "sig_b".to_string(),
+                    buy_sell_transaction(150, &mint, 2_000),
+                ),
+                (
+                    300,
+                    "sig_c".to_string(),
+                    buy_sell_transaction(300, &mint, 4_000),
+                ),
+            ],
+        };
+
+        let result = replay_slot_range(
+            &chain,
+            &event_parser,
+            &event_handler,
+            100,
+            200,
+            TimestampDerivation::Off,
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.signatures_processed, 2);
+        assert_eq!(result.events_processed, 2);
+
+        // Replaying the same range again must not double-count aggregates
+        let result = replay_slot_range(
+            &chain,
+            &event_parser,
+            &event_handler,
+            100,
+            200,
+            TimestampDerivation::Off,
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.events_processed, 2);
+
+        let events = event_storage
+            .query_events(EventQuery {
+                mint_account: mint.to_string(),
+                page: Some(1),
+                limit: Some(10),
+                order_by: Some("slot_asc".to_string()),
+            })
+            .await
+            .unwrap();
+        let signatures: HashMap<String, u64> = events
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                SpinPetEvent::BuySell(e) => Some((e.signature.clone(), e.slot)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(signatures.len(), 2);
+        assert_eq!(signatures.get("sig_a"), Some(&100));
+        assert_eq!(signatures.get("sig_b"), Some(&150));
+
+        let details = event_storage
+            .query_mint_details(MintDetailsQuery {
+                mint_accounts: vec![mint.to_string()],
+            })
+            .await
+            .unwrap();
+        assert_eq!(details.details[0].total_sol_amount, 3_000);
+    }
+
+    #[tokio::test]
+    async fn test_replay_applies_slot_windows_oldest_first() {
+        let temp_dir = TempDir::new().unwrap();
+        let event_storage = Arc::new(EventStorage::new(&create_test_config(&temp_dir)).unwrap());
+        let event_handler = StatsEventHandler::new(Arc::clone(&event_storage));
+        let event_parser = EventParser::new(PROGRAM_ID).unwrap();
+
+        // Three windows, listed newest first
+        let mint = Pubkey::new_unique();
+        let chain = MockChain {
+            transactions: [(2_100, 30), (1_000, 20), (999, 10)]
+                .iter()
+                .map(|&(slot, price)| {
+                    (
+                        slot,
+                        format!("window_sig_{}", slot),
+                        buy_sell_transaction_at(
+                            slot,
+                            &mint,
+                            1_000,
+                            price * crate::services::event_storage::PRICE_PRECISION,
+                            Some(1_700_000_000 + slot as i64),
+                        ),
+                    )
+                })
+                .collect(),
+        };
+
+        let result = replay_slot_range(
+            &chain,
+            &event_parser,
+            &event_handler,
+            0,
+            2 * REPLAY_WINDOW_SLOTS + 500,
+            TimestampDerivation::Off,
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.signatures_processed, 3);
+        assert_eq!(result.events_processed, 3);
+
+        let details = event_storage
+            .query_mint_details(MintDetailsQuery {
+                mint_accounts: vec![mint.to_string()],
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            details.details[0].latest_price,
+            Some(30 * crate::services::event_storage::PRICE_PRECISION)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_catch_up_replays_from_stored_slot_to_head() {
+        let temp_dir = TempDir::new().unwrap();
+        let event_storage = Arc::new(EventStorage::new(&create_test_config(&temp_dir)).unwrap());
+        let event_handler = StatsEventHandler::new(Arc::clone(&event_storage));
+        let event_parser = EventParser::new(PROGRAM_ID).unwrap();
+
+        let mint = Pubkey::new_unique();
+        let chain = MockChain {
+            transactions: vec![
+                (
+                    100,
+                    "up_a".to_string(),
+                    buy_sell_transaction(100, &mint, 1_000),
+                ),
+                (
+                    150,
+                    "up_b".to_string(),
+                    buy_sell_transaction(150, &mint, 2_000),
+                ),
+                (
+                    300,
+                    "up_c".to_string(),
+                    buy_sell_transaction(300, &mint, 4_000),
+                ),
+            ],
+        };
+        let catch_up = |stored_slot: u64, max_slots: u64| {
+            catch_up_to_head(
+                &chain,
+                &event_parser,
+                &event_handler,
+                stored_slot,
+                max_slots,
+                TimestampDerivation::Off,
+            )
+        };
+
+        // Nothing stored yet: there is no point to catch up from
+        assert!(catch_up(0, 10_000).await.unwrap().is_none());
+
+        replay_slot_range(
+            &chain,
+            &event_parser,
+            &event_handler,
+            100,
+            100,
+            TimestampDerivation::Off,
+        )
+        .await
+        .unwrap();
+        assert_eq!(event_storage.latest_slot(), 100);
+
+        // The stored slot is replayed again (deduplicated) along with everything up to the head
+        let result = catch_up(event_storage.latest_slot(), 10_000)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((result.from_slot, result.to_slot), (100, 300));
+        assert_eq!(result.events_processed, 3);
+        let details = event_storage
+            .query_mint_details(MintDetailsQuery {
+                mint_accounts: vec![mint.to_string()],
+            })
+            .await
+            .unwrap();
+        assert_eq!(details.details[0].total_sol_amount, 7_000);
+
+        // Caught up: the head is no longer ahead
+        assert!(catch_up(event_storage.latest_slot(), 10_000)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_replay_derives_zero_block_time_from_slot() {
+        let temp_dir = TempDir::new().unwrap();
+        let event_storage = Arc::new(EventStorage::new(&create_test_config(&temp_dir)).unwrap());
+        let event_handler = StatsEventHandler::new(Arc::clone(&event_storage));
+        let event_parser = EventParser::new(PROGRAM_ID).unwrap();
+
+        let mint = Pubkey::new_unique();
+        let chain = MockChain {
+            transactions: vec![(
+                120,
+                "zero_time_sig".to_string(),
+                buy_sell_transaction_at(120, &mint, 1_000, 300, Some(0)),
+            )],
+        };
+        let timestamps = TimestampDerivation::from_config(&AdminConfig {
+            replay_timestamp_derivation: ReplayTimestampMode::SlotAnchor,
+            replay_anchor_slot: 100,
+            replay_anchor_time: 1_700_000_000,
+            replay_slots_per_second: 2.0,
+            ..AdminConfig::default()
+        })
+        .unwrap();
+
+        let result = replay_slot_range(&chain, &event_parser, &event_handler, 0, 200, timestamps)
+            .await
+            .unwrap();
+        assert_eq!(result.timestamps_derived, 1);
+
+        let events = event_storage
+            .query_events(EventQuery {
+                mint_account: mint.to_string(),
+                page: Some(1),
+                limit: Some(10),
+                order_by: None,
+            })
+            .await
+            .unwrap();
+        match &events.events[..] {
+            [SpinPetEvent::BuySell(e)] => {
+                // 20 slots past the anchor at 2 slots per second
+                assert_eq!(e.timestamp.timestamp(), 1_700_000_010);
+                assert_eq!(e.timestamp_source, TimestampSource::SlotDerived);
+            }
+            other => panic!("unexpected events: {:?}", other),
+        }
+
+        // Without a configured anchor slot_anchor is refused rather than guessing
+        assert!(TimestampDerivation::from_config(&AdminConfig {
+            replay_timestamp_derivation: ReplayTimestampMode::SlotAnchor,
+            ..AdminConfig::default()
+        })
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_shuffled_backfill_builds_in_order_candles() {
+        let mint = Pubkey::new_unique();
+        let base_time = 1_700_000_000;
+        // Two trades per second with prices that make any reordering visible in open/close
+        let transactions: Vec<(u64, String, Value)> =
+            [(10, 0), (14, 0), (9, 1), (12, 1), (15, 2), (11, 2)]
+                .iter()
+                .enumerate()
+                .map(|(i, (price, second))| {
+                    let slot = 100 + i as u64;
+                    (
+                        slot,
+                        format!("backfill_sig_{}", i),
+                        buy_sell_transaction_at(
+                            slot,
+                            &mint,
+                            1_000,
+                            price * crate::services::event_storage::PRICE_PRECISION,
+                            Some(base_time + second),
+                        ),
+                    )
+                })
+                .collect();
+
+        let candles = |transactions: Vec<(u64, String, Value)>| async move {
+            let temp_dir = TempDir::new().unwrap();
+            let event_storage =
+                Arc::new(EventStorage::new(&create_test_config(&temp_dir)).unwrap());
+            let event_handler = StatsEventHandler::new(Arc::clone(&event_storage));
+            let event_parser = EventParser::new(PROGRAM_ID).unwrap();
+            replay_slot_range(
+                &MockChain { transactions },
+                &event_parser,
+                &event_handler,
+                0,
+                1_000,
+                TimestampDerivation::Off,
+            )
+            .await
+            .unwrap();
+            assert!(!event_storage.strict_kline_ordering());
+            event_storage
+                .query_kline_data(crate::models::KlineQuery {
+                    mint_account: mint.to_string(),
+                    interval: "s1".to_string(),
+                    page: Some(1),
+                    limit: Some(10),
+                    order_by: Some("time_asc".to_string()),
+                    fill_gaps: false,
+                    min_update_count: None,
+                })
+                .await
+                .unwrap()
+                .klines
+                .into_iter()
+                .map(|k| {
+                    (
+                        k.time,
+                        k.open,
+                        k.high,
+                        k.low,
+                        k.close,
+                        k.update_count,
+                        k.last_slot,
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let in_order = candles(transactions.clone()).await;
+        let mut shuffled = transactions;
+        shuffled.swap(0, 5);
+        shuffled.swap(1, 3);
+        shuffled.swap(2, 4);
+        let reconstructed = candles(shuffled).await;
+
+        assert_eq!(in_order.len(), 3);
+        assert_eq!(
+            in_order.iter().map(|k| k.4.round()).collect::<Vec<_>>(),
+            vec![14.0, 12.0, 11.0]
+        );
+        assert_eq!(reconstructed, in_order);
+    }
 }
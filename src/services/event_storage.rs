@@ -1,16 +1,28 @@
 use anyhow::Result;
 use chrono::{DateTime, Utc};
-use rocksdb::{Direction, IteratorMode, Options, DB};
+use futures::stream::{self, StreamExt};
+use rocksdb::{DBIterator, Direction, IteratorMode, Options, ReadOptions, DB};
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DisplayFromStr};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::{Mutex, Semaphore};
 use tokio::time::sleep;
 use tracing::{debug, error, info, warn};
 
-use crate::config::Config;
-use crate::models::{KlineData, KlineQuery, KlineQueryResponse};
+use crate::config::{Config, GlobalIndexConfig, PersistencePolicy, UnknownOrderTypeMode};
+use crate::models::{
+    KlineBatchResponse, KlineData, KlineQuery, KlineQueryResponse, LatestKlinesResponse,
+};
+use crate::services::disk_guard::{DiskSpaceGuard, StatvfsProbe};
+use crate::services::event_latency::{receive_latency_ms, ReceiveLatencyHistogram};
+use crate::services::tsdb_sink::TsdbWriter;
 use crate::solana::events::*;
+use crate::utils::AmountU64;
 
 /// Event type constants - used for key generation (2 characters to save space)
 pub const EVENT_TYPE_TOKEN_CREATED: &str = "tc";
@@ -26,14 +38,200 @@ pub const KLINE_INTERVAL_1S: &str = "s1";
 pub const KLINE_INTERVAL_30S: &str = "s30";
 pub const KLINE_INTERVAL_5M: &str = "m5";
 
+/// Unix time that kline buckets are aligned to: a bucket starts where `time % bucket_secs == 0`,
+/// so m5 candles start on the hour and at every fifth minute
+pub const KLINE_ALIGNMENT_EPOCH: u64 = 0;
+
 /// Precision constant for u128 to f64 conversion (28 decimal places)
 pub const PRICE_PRECISION: u128 = 10_u128.pow(28);
 
+/// Number of mints a batch kline query reads concurrently
+const KLINE_BATCH_CONCURRENCY: usize = 4;
+
+/// Schema version of the values written by this build
+pub const SCHEMA_VERSION: u32 = 3;
+
+/// Key holding the schema version of the stored values
+const SCHEMA_VERSION_KEY: &str = "meta:schema_version";
+
+/// Key holding the newest slot among stored events, the reference point for retention
+const LATEST_SLOT_KEY: &str = "meta:latest_slot";
+
+/// Keys deleted per write batch while pruning
+const PRUNE_BATCH_SIZE: usize = 10_000;
+
+/// Global indexes `rebuild_index` can repopulate from the `tr:` event records
+pub const REBUILDABLE_INDEXES: [&str; 3] = ["slot_index", "liquidations", "order_timeline"];
+
+/// Last `tr:` key written by an unfinished index rebuild, per index
+const REBUILD_CURSOR_PREFIX: &str = "meta:rebuild_index:";
+
+/// Set while an index is disabled, as events stored meanwhile are missing from it
+const INDEX_DISABLED_PREFIX: &str = "meta:index_disabled:";
+
+/// Long query scans yield to the runtime every this many keys, so `server.query_timeout_ms`
+/// can cancel them (dropping the iterator) instead of waiting for the scan to finish
+const SCAN_YIELD_KEYS: usize = 1024;
+
+/// Rewrites every JSON value under `prefix` from `from_version` to `from_version + 1`
+pub struct SchemaMigration {
+    pub from_version: u32,
+    pub prefix: &'static str,
+    pub migrate: fn(serde_json::Value) -> Result<serde_json::Value>,
+    /// New key for a (migrated) value, None to keep it where it is
+    pub rekey: Option<fn(&str, &serde_json::Value) -> Option<String>>,
+}
+
+/// Migrations shipped with this build, applied at startup when the stored version is older
+fn schema_migrations() -> Vec<SchemaMigration> {
+    vec![
+        SchemaMigration {
+            from_version: 1,
+            prefix: "tr:",
+            migrate: Ok,
+            rekey: Some(legacy_event_key_to_v2),
+        },
+        SchemaMigration {
+            from_version: 2,
+            prefix: "opda:",
+            migrate: Ok,
+            rekey: Some(legacy_order_timeline_key_to_v3),
+        },
+    ]
+}
+
+/// Log index given to `tr:` records written before keys carried one
+const LEGACY_EVENT_LOG_INDEX: u32 = 0;
+
+/// Move a v1 `tr:{mint}:{slot}:{event_type}:{signature}` key to the v2 layout at
+/// `LEGACY_EVENT_LOG_INDEX`; keys already carrying a log index are left alone
+fn legacy_event_key_to_v2(key: &str, _value: &serde_json::Value) -> Option<String> {
+    let mut segments = key.splitn(4, ':');
+    let (prefix, mint, slot, rest) = (
+        segments.next()?,
+        segments.next()?,
+        segments.next()?,
+        segments.next()?,
+    );
+    if rest.split(':').next()?.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some(format!(
+        "{}:{}:{}:{:04}:{}",
+        prefix, mint, slot, LEGACY_EVENT_LOG_INDEX, rest
+    ))
+}
+
+/// Move a v2 `opda:{order_pda}:{slot}:{event_type}:{mint_account}` key to the v3 layout, taking
+/// the log index and signature from the stored event; current keys are left alone
+fn legacy_order_timeline_key_to_v3(key: &str, value: &serde_json::Value) -> Option<String> {
+    let event = serde_json::from_value::<SpinPetEvent>(value.clone()).ok()?;
+    order_timeline_key(&event).filter(|new_key| new_key != key)
+}
+
+/// Order timeline key for events that belong to an order, None for other events
+/// Format: opda:{order_pda}:{slot:010}:{log_index:04}:{event_type}:{mint_account}:{signature}
+/// (the mint keeps a PDA string shared by two mints from overwriting entries, the log index and
+/// signature keep several events of one order in a slot apart)
+fn order_timeline_key(event: &SpinPetEvent) -> Option<String> {
+    let (order_pda, event_type) = match event {
+        SpinPetEvent::LongShort(e) => (&e.order_pda, EVENT_TYPE_LONG_SHORT),
+        SpinPetEvent::PartialClose(e) => (&e.order_pda, EVENT_TYPE_PARTIAL_CLOSE),
+        SpinPetEvent::FullClose(e) => (&e.order_pda, EVENT_TYPE_FULL_CLOSE),
+        SpinPetEvent::ForceLiquidate(e) => (&e.order_pda, EVENT_TYPE_FORCE_LIQUIDATE),
+        _ => return None,
+    };
+    Some(format!(
+        "opda:{}:{:010}:{:04}:{}:{}:{}",
+        order_pda,
+        event.slot(),
+        event.log_index(),
+        event_type,
+        event.mint_account(),
+        event.signature()
+    ))
+}
+
+/// Upper bound on synthetic candles a single `fill_gaps` kline query may insert
+const MAX_GAP_CANDLES: usize = 10_000;
+
+/// Insert flat candles carrying the previous close into empty buckets between
+/// time-ascending klines, stopping once `MAX_GAP_CANDLES` have been added
+fn fill_kline_gaps(klines: Vec<KlineData>, step: u64) -> Vec<KlineData> {
+    let mut filled: Vec<KlineData> = Vec::with_capacity(klines.len());
+    let mut inserted = 0;
+    for kline in klines {
+        if let Some(prev) = filled.last() {
+            let (close, last_slot) = (prev.close, prev.last_slot);
+            let mut time = prev.time + step;
+            while time < kline.time && inserted < MAX_GAP_CANDLES {
+                filled.push(KlineData {
+                    time,
+                    open: close,
+                    high: close,
+                    low: close,
+                    close,
+                    volume: 0.0,
+                    is_final: true,
+                    update_count: 0,
+                    is_gap: true,
+                    last_slot,
+                });
+                inserted += 1;
+                time += step;
+            }
+        }
+        filled.push(kline);
+    }
+    if inserted >= MAX_GAP_CANDLES {
+        warn!(
+            "⚠️ Kline gap filling stopped after {} synthetic candles",
+            MAX_GAP_CANDLES
+        );
+    }
+    filled
+}
+
 /// Event storage service
 pub struct EventStorage {
     db: Arc<DB>,
     config: Config,
     http_client: reqwest::Client,
+    /// Sharded locks serializing read-modify-write of `in:{mint}` records
+    mint_locks: Arc<Vec<Mutex<()>>>,
+    /// Active strict kline ordering sections (backfills); while non-zero, trades older than a
+    /// candle's `last_slot` no longer move its close
+    strict_kline_ordering: Arc<AtomicUsize>,
+    /// Newest slot among stored events, mirrored at `meta:latest_slot`
+    latest_slot: Arc<AtomicU64>,
+    /// Kline dual-write to an external TSDB (`tsdb.enabled`)
+    tsdb: Option<Arc<TsdbWriter>>,
+    /// Mints that had a kline update since startup (`kline.seed_open_after_restart`)
+    kline_seen_mints: Arc<std::sync::Mutex<HashSet<String>>>,
+    /// Block time to processing delay of live events
+    receive_latency: Arc<ReceiveLatencyHistogram>,
+    /// In-flight metadata fetches keyed by URI (`ipfs.coalesce_uri_fetches`)
+    uri_fetches: Arc<std::sync::Mutex<HashMap<String, Arc<UriFetch>>>>,
+    /// Free space watch of the database path (`database.min_free_disk_mb`)
+    disk_guard: Option<Arc<DiskSpaceGuard>>,
+    /// Mints whose detail changed since the last snapshot (`stats.mint_snapshot_interval_secs`)
+    snapshot_mints: Arc<std::sync::Mutex<HashSet<String>>>,
+    /// Global indexes with a rebuild running, so the admin endpoint and the startup
+    /// auto-rebuild never advance the same cursor at once
+    rebuilding_indexes: Arc<std::sync::Mutex<HashSet<String>>>,
+}
+
+/// One metadata fetch that every creation sharing its URI waits on
+type UriFetch = tokio::sync::OnceCell<Option<TokenUriData>>;
+
+/// Entries removed by one retention sweep
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RetentionPruneResult {
+    pub events: usize,
+    pub slot_index: usize,
+    pub liquidations: usize,
+    pub order_timeline: usize,
+    pub mint_snapshots: usize,
 }
 
 /// Event query parameters
@@ -54,6 +252,9 @@ pub struct EventQueryResponse {
     pub limit: usize,
     pub has_next: bool,
     pub has_prev: bool,
+    /// Only the newest `database.max_scan_keys` events were examined; `total` counts those
+    #[serde(default)]
+    pub truncated: bool,
 }
 
 /// Mint query parameters
@@ -74,7 +275,7 @@ pub struct MintInfo {
 }
 
 /// Mint query response
-#[derive(Debug, Serialize, Deserialize, Default, utoipa::ToSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, utoipa::ToSchema)]
 pub struct MintQueryResponse {
     pub mints: Vec<String>,   // 直接返回mint地址字符串数组，减少数据传输量
     pub total: Option<usize>, // 对于大数据集，计算总数可能很慢，设为可选
@@ -97,12 +298,17 @@ pub struct OrderData {
     pub lock_lp_start_price: u128,
     #[serde_as(as = "DisplayFromStr")]
     pub lock_lp_end_price: u128,
+    #[serde_as(as = "AmountU64")]
     pub lock_lp_sol_amount: u64,
+    #[serde_as(as = "AmountU64")]
     pub lock_lp_token_amount: u64,
     pub start_time: u32,
     pub end_time: u32,
+    #[serde_as(as = "AmountU64")]
     pub margin_sol_amount: u64,
+    #[serde_as(as = "AmountU64")]
     pub borrow_amount: u64,
+    #[serde_as(as = "AmountU64")]
     pub position_asset_amount: u64,
     pub borrow_fee: u16,
     pub order_pda: String,
@@ -113,6 +319,18 @@ pub struct OrderData {
     pub name: String,
     pub symbol: String,
     pub image: String,
+    #[serde(default)]
+    pub status: OrderStatus,
+}
+
+/// Order lifecycle status
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderStatus {
+    #[default]
+    Open,
+    /// end_time passed without a close or liquidation being seen
+    Expired,
 }
 
 /// Order query parameters
@@ -122,6 +340,8 @@ pub struct OrderQuery {
     pub order_type: String, // "up_orders" or "down_orders"
     pub page: Option<usize>,
     pub limit: Option<usize>,
+    #[serde(default)]
+    pub include_expired: bool, // also return orders moved to the expired set
 }
 
 /// Order query response
@@ -137,6 +357,155 @@ pub struct OrderQueryResponse {
     pub has_prev: bool,
 }
 
+/// Order book query parameters
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrderBookQuery {
+    pub mint_account: String,
+    pub depth: Option<usize>, // Maximum price levels per side
+}
+
+/// Aggregated order book price level
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct OrderBookLevel {
+    #[serde_as(as = "DisplayFromStr")]
+    #[schema(value_type = String)]
+    pub price: u128, // lock_lp_start_price shared by all orders in this level
+    #[serde_as(as = "AmountU64")]
+    pub position_asset_amount: u64,
+    pub order_count: usize,
+}
+
+/// Order book depth snapshot response
+#[derive(Debug, Serialize, Deserialize, Default, utoipa::ToSchema)]
+pub struct OrderBookResponse {
+    pub mint_account: String,
+    pub longs: Vec<OrderBookLevel>,  // down_orders, price descending
+    pub shorts: Vec<OrderBookLevel>, // up_orders, price ascending
+    pub depth: usize,
+}
+
+/// Compact trade row for tickers
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct TradeTick {
+    #[serde_as(as = "DisplayFromStr")]
+    #[schema(value_type = String)]
+    pub price: u128, // latest_price after the trade
+    #[serde_as(as = "AmountU64")]
+    pub sol_amount: u64,
+    pub is_buy: bool,
+    pub ts: i64, // Unix timestamp (seconds)
+}
+
+/// Recent trades response
+#[derive(Debug, Serialize, Deserialize, Default, utoipa::ToSchema)]
+pub struct RecentTradesResponse {
+    pub mint_account: String,
+    pub trades: Vec<TradeTick>, // Newest first
+}
+
+/// Lifecycle events of a single order. Order PDAs are only unique per mint, so a PDA string
+/// seen under several mints lists all of them in `mints`
+#[derive(Debug, Serialize, Deserialize, Default, utoipa::ToSchema)]
+pub struct OrderTimelineResponse {
+    pub order_pda: String,
+    /// Mint the events were restricted to, when one was requested
+    pub mint_account: Option<String>,
+    /// Every mint with events for this PDA, sorted
+    pub mints: Vec<String>,
+    pub events: Vec<SpinPetEvent>, // Oldest slot first
+}
+
+/// Every stored event observed in one slot
+#[derive(Debug, Serialize, Deserialize, Default, utoipa::ToSchema)]
+pub struct SlotEventsResponse {
+    pub slot: u64,
+    pub events: Vec<SpinPetEvent>, // Ordered by signature
+}
+
+/// Number of events in one time bucket
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ActivityBucket {
+    pub bucket_ts: i64, // Bucket start, Unix timestamp (seconds), aligned to bucket_secs
+    pub count: u64,
+}
+
+/// Time-bucketed event counts for a mint
+#[derive(Debug, Serialize, Deserialize, Default, utoipa::ToSchema)]
+pub struct ActivityHistogramResponse {
+    pub mint_account: String,
+    pub bucket_secs: u64,
+    pub from: i64,
+    pub to: i64,
+    pub buckets: Vec<ActivityBucket>, // Oldest first, empty buckets included
+    /// Only the newest `database.max_scan_keys` events were examined
+    pub truncated: bool,
+}
+
+/// Combined market summary of one mint over the last 24 hours
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize, Default, utoipa::ToSchema)]
+pub struct MintTickerResponse {
+    pub mint_account: String,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[schema(value_type = Option<String>)]
+    pub latest_price: Option<u128>,
+    pub latest_trade_time: Option<i64>,
+    /// SOL amount (lamports) of BuySell trades in the last 24h
+    #[serde_as(as = "AmountU64")]
+    pub volume_24h: u64,
+    pub trades_24h: u64,
+    /// Price 24h ago: close of the last 5m candle before the window, else the open of the
+    /// first one in it
+    pub open_price_24h: Option<f64>,
+    /// Percent change from `open_price_24h` to the latest price
+    pub price_change_24h_pct: Option<f64>,
+    pub open_long_orders: usize,
+    pub open_short_orders: usize,
+    #[serde_as(as = "AmountU64")]
+    pub total_force_liquidations: u64,
+    /// Only the newest `database.max_scan_keys` events were examined for the 24h volume
+    pub truncated: bool,
+}
+
+/// Liquidation entry in the global liquidation feed
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct LiquidationRecord {
+    pub mint_account: String,
+    pub order_pda: String,
+    pub owner: Option<String>, // Order owner, None when the order was never indexed
+    pub liquidator: String,
+    pub slot: u64,
+    pub signature: String,
+    pub ts: i64, // Unix timestamp (seconds)
+}
+
+/// Recent liquidations response
+#[derive(Debug, Serialize, Deserialize, Default, utoipa::ToSchema)]
+pub struct RecentLiquidationsResponse {
+    pub liquidations: Vec<LiquidationRecord>, // Newest first
+    /// Cursor (`slot:signature`) to pass as `since` on the next poll
+    pub next: Option<String>,
+}
+
+/// Recent events across all mints
+#[derive(Debug, Serialize, Deserialize, Default, utoipa::ToSchema)]
+pub struct RecentEventsResponse {
+    pub events: Vec<SpinPetEvent>, // Newest first
+    /// Cursor (`slot:signature`) to pass as `since` on the next poll
+    pub next: Option<String>,
+}
+
+/// Parse a `slot:signature` feed cursor
+pub fn parse_feed_cursor(cursor: &str) -> Option<(u64, String)> {
+    let (slot, signature) = cursor.split_once(':')?;
+    if signature.is_empty() || signature.contains(':') {
+        return None;
+    }
+    Some((slot.parse().ok()?, signature.to_string()))
+}
+
 /// User order query parameters
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UserOrderQuery {
@@ -160,6 +529,59 @@ pub struct UserOrderQueryResponse {
     pub has_prev: bool,
 }
 
+/// Open orders of one side summed up
+#[serde_as]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct PositionSideSummary {
+    pub order_count: usize,
+    #[serde_as(as = "AmountU64")]
+    pub margin_sol_amount: u64,
+    #[serde_as(as = "AmountU64")]
+    pub borrow_amount: u64,
+    #[serde_as(as = "AmountU64")]
+    pub position_asset_amount: u64,
+}
+
+impl PositionSideSummary {
+    fn add(&mut self, order: &OrderAmounts) {
+        self.order_count += 1;
+        self.margin_sol_amount = self
+            .margin_sol_amount
+            .saturating_add(order.margin_sol_amount);
+        self.borrow_amount = self.borrow_amount.saturating_add(order.borrow_amount);
+        self.position_asset_amount = self
+            .position_asset_amount
+            .saturating_add(order.position_asset_amount);
+    }
+}
+
+/// A user's open orders across all mints, summed by side
+#[derive(Debug, Serialize, Deserialize, Default, utoipa::ToSchema)]
+pub struct UserPositionSummaryResponse {
+    pub user: String,
+    pub longs: PositionSideSummary,
+    pub shorts: PositionSideSummary,
+}
+
+/// Fields of a stored order the position summary needs; also reads the old `uo:` format
+#[serde_as]
+#[derive(Deserialize)]
+struct OrderAmounts {
+    #[serde(default)]
+    order_type: u8,
+    #[serde_as(as = "AmountU64")]
+    #[serde(default)]
+    margin_sol_amount: u64,
+    #[serde_as(as = "AmountU64")]
+    #[serde(default)]
+    borrow_amount: u64,
+    #[serde_as(as = "AmountU64")]
+    #[serde(default)]
+    position_asset_amount: u64,
+    #[serde(default)]
+    status: OrderStatus,
+}
+
 /// User transaction data
 #[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
 pub struct UserTransactionData {
@@ -196,7 +618,7 @@ pub struct UserQueryResponse {
 }
 
 /// Token URI metadata information from IPFS
-#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema, Default, Clone)]
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema, Default, Clone, PartialEq)]
 pub struct TokenUriData {
     pub name: Option<String>,
     pub symbol: Option<String>,
@@ -211,9 +633,23 @@ pub struct TokenUriData {
     pub telegram: Option<String>,
 }
 
+/// A mint detail as it was at `ts`
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct MintDetailSnapshot {
+    pub ts: i64,
+    pub detail: MintDetailData,
+}
+
+/// Mint detail snapshots response
+#[derive(Debug, Serialize, Deserialize, Default, utoipa::ToSchema)]
+pub struct MintDetailHistoryResponse {
+    pub mint_account: String,
+    pub snapshots: Vec<MintDetailSnapshot>, // oldest first
+}
+
 /// Mint detail information
 #[serde_as]
-#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema, Default)]
 pub struct MintDetailData {
     pub mint_account: String,
     pub payer: Option<String>,
@@ -235,14 +671,96 @@ pub struct MintDetailData {
     pub latest_price: Option<u128>,
     #[schema(value_type = String)]
     pub latest_trade_time: Option<i64>,
+    #[serde_as(as = "AmountU64")]
     pub total_sol_amount: u64,
+    #[serde_as(as = "AmountU64")]
     pub total_margin_sol_amount: u64,
+    #[serde_as(as = "AmountU64")]
     pub total_force_liquidations: u64,
+    #[serde_as(as = "AmountU64")]
     pub total_close_profit: u64,
+    /// Net SOL result of closes (returned SOL minus released margin); negative for net losses
+    #[serde(default)]
+    #[serde_as(as = "DisplayFromStr")]
+    #[schema(value_type = String)]
+    pub total_realized_pnl: i128,
     pub created_by: Option<String>,
     #[schema(value_type = Option<String>)]
     pub last_updated_at: Option<DateTime<Utc>>,
     pub uri_data: Option<TokenUriData>,
+    /// Last metadata fetch attempt (`ipfs.refetch_after_secs`)
+    #[serde(default)]
+    #[schema(value_type = Option<String>)]
+    pub uri_last_fetched_at: Option<DateTime<Utc>>,
+    /// Prices skipped by the `kline.max_price_jump_ratio` sanity bound
+    #[serde(default)]
+    pub rejected_price_count: u64,
+    /// Level of the current run of rejected prices awaiting `kline.price_jump_confirmations`
+    #[serde(default)]
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[schema(value_type = Option<String>)]
+    pub pending_price: Option<u128>,
+    /// Consecutive rejected prices agreeing with `pending_price`
+    #[serde(default)]
+    pub pending_price_count: u32,
+}
+
+/// Name of the merge operator summing little-endian u64 counters
+const COUNTER_MERGE_OPERATOR: &str = "u64_add";
+
+/// Mint detail totals that `database.merge_counters` keeps in `mc:` merge counters
+#[derive(Debug, Clone, Copy)]
+enum MintTotal {
+    SolAmount,
+    MarginSolAmount,
+    ForceLiquidations,
+    CloseProfit,
+}
+
+impl MintTotal {
+    const ALL: [MintTotal; 4] = [
+        MintTotal::SolAmount,
+        MintTotal::MarginSolAmount,
+        MintTotal::ForceLiquidations,
+        MintTotal::CloseProfit,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            MintTotal::SolAmount => "total_sol_amount",
+            MintTotal::MarginSolAmount => "total_margin_sol_amount",
+            MintTotal::ForceLiquidations => "total_force_liquidations",
+            MintTotal::CloseProfit => "total_close_profit",
+        }
+    }
+
+    fn field_mut(self, detail: &mut MintDetailData) -> &mut u64 {
+        match self {
+            MintTotal::SolAmount => &mut detail.total_sol_amount,
+            MintTotal::MarginSolAmount => &mut detail.total_margin_sol_amount,
+            MintTotal::ForceLiquidations => &mut detail.total_force_liquidations,
+            MintTotal::CloseProfit => &mut detail.total_close_profit,
+        }
+    }
+}
+
+/// Counter value; malformed values count as 0
+fn decode_counter(value: &[u8]) -> u64 {
+    value.try_into().map(u64::from_le_bytes).unwrap_or(0)
+}
+
+/// Saturating sum of the existing counter value and all merge operands
+fn merge_u64_add(
+    _key: &[u8],
+    existing: Option<&[u8]>,
+    operands: &rocksdb::MergeOperands,
+) -> Option<Vec<u8>> {
+    let total = operands
+        .iter()
+        .fold(existing.map_or(0, decode_counter), |total, operand| {
+            total.saturating_add(decode_counter(operand))
+        });
+    Some(total.to_le_bytes().to_vec())
 }
 
 /// Mint details query parameters
@@ -252,15 +770,120 @@ pub struct MintDetailsQuery {
 }
 
 /// Mint details query response
-#[derive(Debug, Serialize, Deserialize, Default, utoipa::ToSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, utoipa::ToSchema)]
 pub struct MintDetailsQueryResponse {
     pub details: Vec<MintDetailData>,
     pub total: usize,
 }
 
+/// Checkpoint result
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct CheckpointInfo {
+    pub path: String,
+    /// Total size of the checkpoint files (hard links share storage with the live DB)
+    pub size_bytes: u64,
+}
+
+/// Outcome of a global index rebuild
+#[derive(Debug, Clone, Default, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct IndexRebuildResult {
+    pub index: String,
+    /// `tr:` events scanned by this run
+    pub scanned: usize,
+    /// Index entries written by this run
+    pub written: usize,
+    /// The run continued from the progress cursor of an interrupted rebuild
+    pub resumed: bool,
+}
+
+/// RocksDB property gauges (estimates as reported by RocksDB)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct DbMetrics {
+    /// `rocksdb.estimate-num-keys`
+    pub estimate_num_keys: u64,
+    /// `rocksdb.cur-size-all-mem-tables`, in bytes
+    pub cur_size_all_mem_tables: u64,
+    /// `rocksdb.num-files-at-level0`
+    pub num_files_at_level0: u64,
+    /// `rocksdb.estimate-live-data-size`, in bytes
+    pub estimate_live_data_size: u64,
+    /// `rocksdb.total-sst-files-size`, in bytes
+    pub total_sst_files_size: u64,
+    /// `rocksdb.estimate-pending-compaction-bytes`
+    pub estimate_pending_compaction_bytes: u64,
+    /// `rocksdb.num-running-compactions`
+    pub num_running_compactions: u64,
+    /// `rocksdb.num-running-flushes`
+    pub num_running_flushes: u64,
+}
+
+impl DbMetrics {
+    /// Gauges in the Prometheus text exposition format
+    pub fn to_prometheus(&self) -> String {
+        [
+            ("estimate_num_keys", self.estimate_num_keys),
+            (
+                "cur_size_all_mem_tables_bytes",
+                self.cur_size_all_mem_tables,
+            ),
+            ("num_files_at_level0", self.num_files_at_level0),
+            (
+                "estimate_live_data_size_bytes",
+                self.estimate_live_data_size,
+            ),
+            ("total_sst_files_size_bytes", self.total_sst_files_size),
+            (
+                "estimate_pending_compaction_bytes",
+                self.estimate_pending_compaction_bytes,
+            ),
+            ("num_running_compactions", self.num_running_compactions),
+            ("num_running_flushes", self.num_running_flushes),
+        ]
+        .iter()
+        .map(|(name, value)| format!("# TYPE rocksdb_{name} gauge\nrocksdb_{name} {value}\n"))
+        .collect()
+    }
+}
+
+/// Outcome of a bulk URI metadata prefetch
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct PrefetchUriResult {
+    pub requested: usize,
+    pub fetched: usize,
+    /// Mints without a stored URI or whose fetch failed
+    pub failed: Vec<String>,
+}
+
+/// Outcome of one stale metadata refetch sweep
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct UriRefetchResult {
+    pub refetched: usize,
+    /// Mints whose `uri_data` changed
+    pub updated: usize,
+}
+
+/// Raw value stored under a key, for diagnosing serialization issues
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct RawKeyValue {
+    pub key: String,
+    pub size_bytes: usize,
+    pub value_base64: String,
+    /// The value parsed as JSON, None when it isn't valid JSON
+    pub json: Option<serde_json::Value>,
+}
+
 impl EventStorage {
     /// Create a new event storage instance
     pub fn new(config: &Config) -> Result<Self> {
+        Self::with_migrations(config, SCHEMA_VERSION, schema_migrations())
+    }
+
+    /// Open the database and migrate stored values up to `schema_version`
+    pub fn with_migrations(
+        config: &Config,
+        schema_version: u32,
+        migrations: Vec<SchemaMigration>,
+    ) -> Result<Self> {
         let mut opts = Options::default();
         opts.create_if_missing(true);
         opts.create_missing_column_families(true);
@@ -319,6 +942,19 @@ impl EventStorage {
         // 10. Optimize memory allocation
         opts.set_arena_block_size(64 * 1024 * 1024); // 64MB arena blocks
 
+        // 11. Summing merge operator for the `mc:` mint detail counters
+        opts.set_merge_operator_associative(COUNTER_MERGE_OPERATOR, merge_u64_add);
+
+        // 12. Optional WAL archival (point-in-time recovery)
+        if let Some(wal_dir) = &config.database.wal_archive_dir {
+            opts.set_wal_dir(wal_dir);
+            opts.set_wal_ttl_seconds(config.database.wal_archive_ttl_secs);
+            info!(
+                "🗃️ WAL archival enabled, archive: {}/archive, ttl: {}s",
+                wal_dir, config.database.wal_archive_ttl_secs
+            );
+        }
+
         let db = DB::open(&opts, &config.database.rocksdb_path)?;
 
         let http_client = reqwest::Client::builder()
@@ -329,16 +965,160 @@ impl EventStorage {
             "🗄️ RocksDB initialized successfully, path: {}",
             config.database.rocksdb_path
         );
-        Ok(Self {
+        if config.persistence.long_short != PersistencePolicy::Full
+            || config.persistence.partial_close != PersistencePolicy::Full
+        {
+            warn!(
+                "⚠️ Selective persistence enabled (long_short: {:?}, partial_close: {:?}); event and order queries only return what is stored",
+                config.persistence.long_short, config.persistence.partial_close
+            );
+        }
+        let latest_slot = match db.get(LATEST_SLOT_KEY.as_bytes())? {
+            Some(value) => String::from_utf8_lossy(&value).parse()?,
+            None => 0,
+        };
+        let storage = Self {
             db: Arc::new(db),
             config: config.clone(),
             http_client,
-        })
+            mint_locks: Arc::new(
+                (0..config.database.mint_lock_shards.max(1))
+                    .map(|_| Mutex::new(()))
+                    .collect(),
+            ),
+            strict_kline_ordering: Arc::new(AtomicUsize::new(0)),
+            latest_slot: Arc::new(AtomicU64::new(latest_slot)),
+            tsdb: TsdbWriter::from_config(&config.tsdb)?,
+            kline_seen_mints: Arc::new(std::sync::Mutex::new(HashSet::new())),
+            receive_latency: Arc::new(ReceiveLatencyHistogram::default()),
+            uri_fetches: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            disk_guard: (config.database.min_free_disk_mb > 0).then(|| {
+                Arc::new(DiskSpaceGuard::new(
+                    &config.database.rocksdb_path,
+                    config.database.min_free_disk_mb,
+                    Arc::new(StatvfsProbe),
+                ))
+            }),
+            snapshot_mints: Arc::new(std::sync::Mutex::new(HashSet::new())),
+            rebuilding_indexes: Arc::new(std::sync::Mutex::new(HashSet::new())),
+        };
+        storage.migrate_schema(schema_version, &migrations)?;
+        Ok(storage)
+    }
+
+    /// Stored schema version; databases written before versioning count as version 1
+    fn stored_schema_version(&self) -> Result<u32> {
+        match self.db.get(SCHEMA_VERSION_KEY.as_bytes())? {
+            Some(value) => Ok(String::from_utf8_lossy(&value).parse()?),
+            None => Ok(1),
+        }
+    }
+
+    /// Bring stored values up to `target` by applying registered migrations in version order
+    fn migrate_schema(&self, target: u32, migrations: &[SchemaMigration]) -> Result<()> {
+        let stored = self.stored_schema_version()?;
+        if stored > target {
+            return Err(anyhow::anyhow!(
+                "Database schema version {} is newer than this build supports ({})",
+                stored,
+                target
+            ));
+        }
+
+        for version in stored..target {
+            let steps: Vec<&SchemaMigration> = migrations
+                .iter()
+                .filter(|m| m.from_version == version)
+                .collect();
+            if steps.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "No migration registered from schema version {}",
+                    version
+                ));
+            }
+
+            let mut batch = rocksdb::WriteBatch::default();
+            for step in steps {
+                let mut migrated = 0usize;
+                for item in self.prefix_scan(step.prefix) {
+                    let (key, value) = item?;
+                    let old_value = serde_json::from_slice(&value)?;
+                    let migrated_value = (step.migrate)(old_value)?;
+                    let new_key = step
+                        .rekey
+                        .and_then(|rekey| rekey(&String::from_utf8_lossy(&key), &migrated_value));
+                    let new_value = serde_json::to_vec(&migrated_value)?;
+                    match new_key {
+                        Some(new_key) => {
+                            batch.delete(&key);
+                            batch.put(new_key.as_bytes(), new_value);
+                        }
+                        None => batch.put(&key, new_value),
+                    }
+                    migrated += 1;
+                }
+                info!(
+                    "🔄 Schema migration v{} -> v{}: {} values under {}",
+                    version,
+                    version + 1,
+                    migrated,
+                    step.prefix
+                );
+            }
+            batch.put(
+                SCHEMA_VERSION_KEY.as_bytes(),
+                (version + 1).to_string().as_bytes(),
+            );
+            self.db.write(batch)?;
+        }
+
+        if self.db.get(SCHEMA_VERSION_KEY.as_bytes())?.is_none() {
+            self.db
+                .put(SCHEMA_VERSION_KEY.as_bytes(), target.to_string().as_bytes())?;
+        }
+        debug!("🗄️ Database schema version: {}", target);
+        Ok(())
+    }
+
+    /// Whether large prefix scans populate the block cache
+    fn scan_fills_cache(&self) -> bool {
+        self.config.database.cache_scans
+    }
+
+    /// Read options for large prefix scans: bounded to the prefix so iteration stops at the
+    /// last matching key, and kept out of the block cache unless `database.cache_scans` is set
+    fn scan_read_options(&self, prefix: &str) -> ReadOptions {
+        let mut read_opts = ReadOptions::default();
+        read_opts.fill_cache(self.scan_fills_cache());
+        read_opts.set_iterate_range(rocksdb::PrefixRange(prefix.as_bytes()));
+        read_opts
+    }
+
+    /// Forward iterator over all keys starting with `prefix`
+    fn prefix_scan(&self, prefix: &str) -> DBIterator<'_> {
+        self.db.iterator_opt(
+            IteratorMode::From(prefix.as_bytes(), Direction::Forward),
+            self.scan_read_options(prefix),
+        )
+    }
+
+    /// Persistence policy configured for an event's type
+    fn persistence_policy(&self, event: &SpinPetEvent) -> PersistencePolicy {
+        match event {
+            SpinPetEvent::LongShort(_) => self.config.persistence.long_short,
+            SpinPetEvent::PartialClose(_) => self.config.persistence.partial_close,
+            _ => PersistencePolicy::Full,
+        }
     }
 
     /// Generate event storage key
-    /// Format: tr:{mint_account}:{slot(10 digits with leading zeros)}:{event_type}:{signature}
+    /// Format: tr:{mint_account}:{slot(10 digits with leading zeros)}:{log_index(4 digits)}:{event_type}:{signature}
     fn generate_event_key(&self, event: &SpinPetEvent) -> String {
+        self.generate_event_key_at(event, event.log_index())
+    }
+
+    /// Event storage key with the log index segment set to `log_index`
+    fn generate_event_key_at(&self, event: &SpinPetEvent, log_index: u32) -> String {
         let (mint_account, slot, signature, event_type) = match event {
             SpinPetEvent::TokenCreated(e) => (
                 &e.mint_account,
@@ -375,13 +1155,56 @@ impl EventStorage {
             ),
         };
 
-        // Format slot as 10 digits with leading zeros, for correct sorting by dictionary order
+        // Format slot and log index with leading zeros, for correct sorting by dictionary order
         format!(
-            "tr:{}:{:010}:{}:{}",
-            mint_account, slot, event_type, signature
+            "tr:{}:{:010}:{:04}:{}:{}",
+            mint_account, slot, log_index, event_type, signature
         )
     }
 
+    /// Marker recording that an event without a `tr:` record (`index_only`) was stored
+    /// Format: tm:{mint_account}:{slot}:{log_index}:{event_type}:{signature}
+    fn generate_event_marker_key(&self, event_key: &str) -> String {
+        format!("tm:{}", &event_key["tr:".len()..])
+    }
+
+    /// Key `event` is already stored under: its own (or its `tm:` marker), or the
+    /// `LEGACY_EVENT_LOG_INDEX` key a v1 record was migrated to, told apart from a genuine
+    /// first event by its missing `log_index`
+    fn stored_event_key(&self, event: &SpinPetEvent, key: &str) -> Result<Option<String>> {
+        if self.db.get(key.as_bytes())?.is_some()
+            || self
+                .db
+                .get(self.generate_event_marker_key(key).as_bytes())?
+                .is_some()
+        {
+            return Ok(Some(key.to_string()));
+        }
+        if event.log_index() == LEGACY_EVENT_LOG_INDEX {
+            return Ok(None);
+        }
+        let legacy_key = self.generate_event_key_at(event, LEGACY_EVENT_LOG_INDEX);
+        match self.db.get(legacy_key.as_bytes())? {
+            Some(value) => {
+                let value: serde_json::Value = serde_json::from_slice(&value)?;
+                Ok(value.get("log_index").is_none().then_some(legacy_key))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Event type segment of a `tr:` key with the `tr:{mint}:` prefix stripped; keys written
+    /// before the log index was added go straight from the slot to the event type
+    fn event_key_type(rest: &str) -> &str {
+        let mut segments = rest.split(':').skip(1);
+        match segments.next() {
+            Some(segment) if segment.bytes().all(|b| b.is_ascii_digit()) => {
+                segments.next().unwrap_or("")
+            }
+            segment => segment.unwrap_or(""),
+        }
+    }
+
     /// Generate mint marker key (slot-based index)
     /// Format: mt:{slot:010}:{mint_account}
     fn generate_mint_key(&self, slot: u64, mint_account: &str) -> String {
@@ -390,9 +1213,66 @@ impl EventStorage {
 
     /// Generate order key
     /// Format: or:{mint_account}:up:{order_pda} or or:{mint_account}:dn:{order_pda}
-    fn generate_order_key(&self, mint_account: &str, order_type: u8, order_pda: &str) -> String {
-        let type_str = if order_type == 2 { "up" } else { "dn" };
-        format!("or:{}:{}:{}", mint_account, type_str, order_pda)
+    fn generate_order_key(
+        &self,
+        mint_account: &str,
+        order_type: OrderType,
+        order_pda: &str,
+    ) -> String {
+        format!(
+            "or:{}:{}:{}",
+            mint_account,
+            order_type.key_segment(),
+            order_pda
+        )
+    }
+
+    /// Generate expired order key
+    /// Format: ox:{mint_account}:up:{order_pda} or ox:{mint_account}:dn:{order_pda}
+    fn generate_expired_order_key(
+        &self,
+        mint_account: &str,
+        order_type: OrderType,
+        order_pda: &str,
+    ) -> String {
+        format!(
+            "ox:{}:{}:{}",
+            mint_account,
+            order_type.key_segment(),
+            order_pda
+        )
+    }
+
+    /// Generate global liquidation key
+    /// Format: liq:{slot:010}:{signature}:{order_pda} (one transaction can liquidate several orders)
+    fn generate_liquidation_key(&self, slot: u64, signature: &str, order_pda: &str) -> String {
+        format!("liq:{:010}:{}:{}", slot, signature, order_pda)
+    }
+
+    /// Generate order timeline key for events that belong to an order, None for other events
+    /// Format: opda:{order_pda}:{slot:010}:{log_index:04}:{event_type}:{mint_account}:{signature}
+    fn generate_order_timeline_key(&self, event: &SpinPetEvent) -> Option<String> {
+        order_timeline_key(event)
+    }
+
+    /// Generate global slot index key, covering every stored event
+    /// Format: slotidx:{slot:010}:{signature}:{event_type}
+    fn generate_slot_index_key(&self, event: &SpinPetEvent) -> String {
+        let event_type = match event {
+            SpinPetEvent::TokenCreated(_) => EVENT_TYPE_TOKEN_CREATED,
+            SpinPetEvent::BuySell(_) => EVENT_TYPE_BUY_SELL,
+            SpinPetEvent::LongShort(_) => EVENT_TYPE_LONG_SHORT,
+            SpinPetEvent::ForceLiquidate(_) => EVENT_TYPE_FORCE_LIQUIDATE,
+            SpinPetEvent::FullClose(_) => EVENT_TYPE_FULL_CLOSE,
+            SpinPetEvent::PartialClose(_) => EVENT_TYPE_PARTIAL_CLOSE,
+            SpinPetEvent::MilestoneDiscount(_) => EVENT_TYPE_MILESTONE_DISCOUNT,
+        };
+        format!(
+            "slotidx:{:010}:{}:{}",
+            event.slot(),
+            event.signature(),
+            event_type
+        )
     }
 
     /// Generate user transaction key
@@ -401,6 +1281,12 @@ impl EventStorage {
         format!("us:{}:{}:{:010}", user, mint_account, slot)
     }
 
+    /// Generate quarantined order key, for order events with an unknown `order_type`
+    /// Format: oq:{mint_account}:{order_pda}
+    fn generate_quarantined_order_key(&self, mint_account: &str, order_pda: &str) -> String {
+        format!("oq:{}:{}", mint_account, order_pda)
+    }
+
     /// Generate user order key
     /// Format: uo:{user}:{mint}:{order_pda}
     fn generate_user_order_key(&self, user: &str, mint: &str, order_pda: &str) -> String {
@@ -424,43 +1310,74 @@ impl EventStorage {
         (price_f64 * 1e12).round() / 1e12
     }
 
+    /// Bucket size of a kline interval, in seconds
+    pub fn interval_seconds(interval: &str) -> u64 {
+        match interval {
+            KLINE_INTERVAL_30S => 30,
+            KLINE_INTERVAL_5M => 300,
+            _ => 1,
+        }
+    }
+
     /// Calculate time bucket for different intervals
     /// Returns the aligned timestamp for the time bucket
     fn calculate_time_bucket(&self, timestamp: u64, interval: &str) -> u64 {
-        match interval {
-            KLINE_INTERVAL_1S => timestamp, // 1-second intervals - no alignment needed
-            KLINE_INTERVAL_30S => {
-                // 30-second intervals - align to 30-second boundary
-                // Floor timestamp to 30-second boundary, then return the aligned timestamp
-                (timestamp / 30) * 30
-            }
-            KLINE_INTERVAL_5M => {
-                // 5-minute intervals - align to 5-minute boundary
-                // Floor timestamp to 5-minute boundary, then return the aligned timestamp
-                (timestamp / 300) * 300
-            }
-            _ => timestamp, // default to 1-second
-        }
+        // Floor to the interval boundary counted from the alignment epoch (unknown intervals are 1s)
+        let bucket_secs = Self::interval_seconds(interval);
+        let offset = timestamp.saturating_sub(KLINE_ALIGNMENT_EPOCH);
+        KLINE_ALIGNMENT_EPOCH + offset / bucket_secs * bucket_secs
     }
 
     /// Get order by PDA for user order operations
-    async fn get_order_by_pda(
+    pub async fn get_order_by_pda(
         &self,
         mint_account: &str,
-        order_type: u8,
+        order_type: OrderType,
         order_pda: &str,
     ) -> Result<Option<OrderData>> {
-        let order_key = self.generate_order_key(mint_account, order_type, order_pda);
-        match self.db.get(order_key.as_bytes())? {
-            Some(data) => match serde_json::from_slice::<OrderData>(&data) {
-                Ok(order_data) => Ok(Some(order_data)),
-                Err(e) => {
-                    error!("❌ Failed to parse order data: {}, key: {}", e, order_key);
-                    Ok(None)
-                }
-            },
-            None => Ok(None),
+        // Open orders first, then orders the expiry sweep has moved out of the open set
+        let keys = [
+            self.generate_order_key(mint_account, order_type, order_pda),
+            self.generate_expired_order_key(mint_account, order_type, order_pda),
+        ];
+        for order_key in keys {
+            if let Some(data) = self.db.get(order_key.as_bytes())? {
+                return match serde_json::from_slice::<OrderData>(&data) {
+                    Ok(order_data) => Ok(Some(order_data)),
+                    Err(e) => {
+                        error!("❌ Failed to parse order data: {}, key: {}", e, order_key);
+                        Ok(None)
+                    }
+                };
+            }
         }
+        Ok(None)
+    }
+
+    /// Realized PnL of a close: SOL returned to the user minus the margin the close released.
+    /// Borrowed funds are repaid on-chain before `final_sol_amount` is paid out, so margin is
+    /// the user's cost basis. None when the order was never indexed and its margin is unknown.
+    async fn realized_pnl(
+        &self,
+        mint_account: &str,
+        order_type: OrderType,
+        order_pda: &str,
+        final_sol_amount: u64,
+        remaining_margin: u64,
+    ) -> Result<Option<i128>> {
+        let Some(order) = self
+            .get_order_by_pda(mint_account, order_type, order_pda)
+            .await?
+        else {
+            debug!(
+                "⚠️ Order not found for realized PnL, skipping: {}",
+                order_pda
+            );
+            return Ok(None);
+        };
+
+        let released_margin = order.margin_sol_amount.saturating_sub(remaining_margin);
+        Ok(Some(final_sol_amount as i128 - released_margin as i128))
     }
 
     /// Create OrderData from LongShortEvent
@@ -486,6 +1403,7 @@ impl EventStorage {
             name: String::new(),
             symbol: String::new(),
             image: String::new(),
+            status: OrderStatus::Open,
         }
     }
 
@@ -512,7 +1430,58 @@ impl EventStorage {
             name: String::new(),
             symbol: String::new(),
             image: String::new(),
+            status: OrderStatus::Open,
+        }
+    }
+
+    /// Whether an order event's `order_type` is unknown and `orders.unknown_order_type` keeps it
+    /// out of the up/down indexes; "legacy" only logs that the order is indexed as a long
+    fn has_unindexed_order_type(&self, event: &SpinPetEvent) -> bool {
+        let raw = match event {
+            SpinPetEvent::LongShort(e) => e.order_type,
+            SpinPetEvent::PartialClose(e) => e.order_type,
+            _ => return false,
+        };
+        if OrderType::try_from_raw(raw).is_some() {
+            return false;
+        }
+        if self.config.orders.unknown_order_type == UnknownOrderTypeMode::Legacy {
+            warn!(
+                "⚠️ Unknown order_type {} in {} {}, indexed as long (orders.unknown_order_type = legacy)",
+                raw,
+                event.type_name(),
+                event.signature()
+            );
+            return false;
+        }
+        true
+    }
+
+    /// Log an order event with an unknown `order_type`, storing it under the quarantine prefix
+    /// when `orders.unknown_order_type` is "quarantine"
+    fn quarantine_order(
+        &self,
+        batch: &mut rocksdb::WriteBatch,
+        event: &SpinPetEvent,
+    ) -> Result<()> {
+        let order_data = match event {
+            SpinPetEvent::LongShort(e) => self.create_order_data_from_long_short(e),
+            SpinPetEvent::PartialClose(e) => self.create_order_data_from_partial_close(e),
+            _ => return Ok(()),
+        };
+        warn!(
+            "⚠️ Unknown order_type {} in {} {} for order {}, kept out of the order indexes",
+            order_data.order_type,
+            event.type_name(),
+            event.signature(),
+            order_data.order_pda
+        );
+        if self.config.orders.unknown_order_type == UnknownOrderTypeMode::Quarantine {
+            let key =
+                self.generate_quarantined_order_key(event.mint_account(), &order_data.order_pda);
+            batch.put(key.as_bytes(), serde_json::to_vec(&order_data)?);
         }
+        Ok(())
     }
 
     /// Create user transaction data
@@ -577,49 +1546,85 @@ impl EventStorage {
     }
 
     /// Get the latest kline data before the given time bucket for price continuity
-    fn get_previous_kline_close_price(
+    fn get_previous_kline(
         &self,
         interval: &str,
         mint_account: &str,
         current_time_bucket: u64,
-    ) -> Option<f64> {
+    ) -> Option<KlineData> {
         // Build prefix key for the specific mint and interval
         let prefix = format!("{}:{}:", interval, mint_account);
+        let current_key = self.generate_kline_key(interval, mint_account, current_time_bucket);
 
-        // Iterate from the beginning to find the latest kline before current_time_bucket
-        let iter = self
-            .db
-            .iterator(IteratorMode::From(prefix.as_bytes(), Direction::Forward));
-        let mut latest_close_price = None;
+        // Bucket timestamps are zero-padded, so the nearest earlier kline is one reverse step away
+        let iter = self.db.iterator(IteratorMode::From(
+            current_key.as_bytes(),
+            Direction::Reverse,
+        ));
 
         for item in iter {
-            if let Ok((key, value)) = item {
-                let key_str = String::from_utf8_lossy(&key);
+            let Ok((key, value)) = item else { break };
+            let key_str = String::from_utf8_lossy(&key);
 
-                // Check if still matches prefix
-                if !key_str.starts_with(&prefix) {
-                    break;
-                }
+            // Check if still matches prefix
+            if !key_str.starts_with(&prefix) {
+                break;
+            }
 
-                // Extract timestamp from key format: "interval:mint_account:timestamp"
-                if let Some(timestamp_str) = key_str.split(':').nth(2) {
-                    if let Ok(timestamp) = timestamp_str.parse::<u64>() {
-                        // Only consider klines before the current time bucket
-                        if timestamp < current_time_bucket {
-                            // Parse kline data to get close price
-                            if let Ok(kline_data) = serde_json::from_slice::<KlineData>(&value) {
-                                latest_close_price = Some(kline_data.close);
-                            }
-                        } else {
-                            // We've reached klines at or after current time bucket, stop
-                            break;
-                        }
-                    }
+            // Skip the current bucket itself; anything else here is earlier
+            if key_str.as_ref() == current_key {
+                continue;
+            }
+
+            return serde_json::from_slice::<KlineData>(&value).ok();
+        }
+
+        None
+    }
+
+    /// Open price of a new bucket: the previous bucket's close with `kline.continuous_open`,
+    /// otherwise the bucket's first trade price. A mint's first update after startup opens at
+    /// the adjacent bucket's close with `kline.seed_open_after_restart`
+    fn new_bucket_open_price(
+        &self,
+        interval: &str,
+        mint_account: &str,
+        time_bucket: u64,
+        price: f64,
+        first_since_startup: bool,
+    ) -> f64 {
+        if self.config.kline.continuous_open {
+            return self
+                .get_previous_kline(interval, mint_account, time_bucket)
+                .map_or(price, |kline| kline.close); // Use current price if no previous kline found (first kline)
+        }
+        if first_since_startup {
+            if let Some(kline) = self.get_previous_kline(interval, mint_account, time_bucket) {
+                if kline.time + Self::interval_seconds(interval) == time_bucket {
+                    return kline.close;
                 }
             }
         }
+        price
+    }
+
+    /// Enter a strict kline ordering section; pair with `end_strict_kline_ordering`
+    pub fn begin_strict_kline_ordering(&self) {
+        self.strict_kline_ordering.fetch_add(1, Ordering::SeqCst);
+    }
 
-        latest_close_price
+    /// Leave a strict kline ordering section
+    pub fn end_strict_kline_ordering(&self) {
+        let _ =
+            self.strict_kline_ordering
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |active| {
+                    active.checked_sub(1)
+                });
+    }
+
+    /// Whether any strict kline ordering section is active
+    pub fn strict_kline_ordering(&self) -> bool {
+        self.strict_kline_ordering.load(Ordering::SeqCst) > 0
     }
 
     /// Process kline data for price events
@@ -628,9 +1633,18 @@ impl EventStorage {
         mint_account: &str,
         latest_price: u128,
         timestamp: DateTime<Utc>,
+        slot: u64,
     ) -> Result<()> {
         let price = self.convert_price_to_f64(latest_price);
         let unix_timestamp = timestamp.timestamp() as u64;
+        let strict = self.strict_kline_ordering();
+        // A bucket already stored before a restart keeps its open through the update path below
+        let first_since_startup = self.config.kline.seed_open_after_restart
+            && self
+                .kline_seen_mints
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(mint_account.to_string());
 
         let intervals = [KLINE_INTERVAL_1S, KLINE_INTERVAL_30S, KLINE_INTERVAL_5M];
 
@@ -646,7 +1660,16 @@ impl EventStorage {
                             // Update existing kline data (same time bucket)
                             existing_kline.high = existing_kline.high.max(price);
                             existing_kline.low = existing_kline.low.min(price);
-                            existing_kline.close = price;
+                            // Strict ordering: a trade older than the candle's newest one can't be its close
+                            if strict && slot < existing_kline.last_slot {
+                                debug!(
+                                    "💹 Out-of-order trade at slot {} kept out of close for {} {} (last slot {})",
+                                    slot, interval, mint_account, existing_kline.last_slot
+                                );
+                            } else {
+                                existing_kline.close = price;
+                            }
+                            existing_kline.last_slot = existing_kline.last_slot.max(slot);
                             existing_kline.update_count += 1;
                             existing_kline.is_final = false; // Mark as not final since it's being updated
                             existing_kline
@@ -657,10 +1680,13 @@ impl EventStorage {
                                 e
                             );
                             // Create new kline data if parsing fails
-                            // Get previous kline close price to avoid gaps
-                            let open_price = self
-                                .get_previous_kline_close_price(interval, mint_account, time_bucket)
-                                .unwrap_or(price); // Use current price if no previous kline found
+                            let open_price = self.new_bucket_open_price(
+                                interval,
+                                mint_account,
+                                time_bucket,
+                                price,
+                                first_since_startup,
+                            );
 
                             KlineData {
                                 time: time_bucket,
@@ -671,16 +1697,21 @@ impl EventStorage {
                                 volume: 0.0, // Volume is 0 as requested
                                 is_final: false,
                                 update_count: 1,
+                                is_gap: false,
+                                last_slot: slot,
                             }
                         }
                     }
                 }
                 None => {
                     // Create new kline data for different time bucket
-                    // Get previous kline close price to maintain price continuity and avoid gaps
-                    let open_price = self
-                        .get_previous_kline_close_price(interval, mint_account, time_bucket)
-                        .unwrap_or(price); // Use current price if no previous kline found (first kline)
+                    let open_price = self.new_bucket_open_price(
+                        interval,
+                        mint_account,
+                        time_bucket,
+                        price,
+                        first_since_startup,
+                    );
 
                     KlineData {
                         time: time_bucket,
@@ -691,6 +1722,8 @@ impl EventStorage {
                         volume: 0.0, // Volume is 0 as requested
                         is_final: false,
                         update_count: 1,
+                        is_gap: false,
+                        last_slot: slot,
                     }
                 }
             };
@@ -699,6 +1732,16 @@ impl EventStorage {
             let value = serde_json::to_vec(&kline_data)?;
             self.db.put(kline_key.as_bytes(), &value)?;
 
+            // Dual-write to the TSDB; full batches are flushed in the background
+            if let Some(tsdb) = &self.tsdb {
+                if tsdb.record(mint_account, interval, &kline_data) {
+                    let tsdb = Arc::clone(tsdb);
+                    tokio::spawn(async move {
+                        tsdb.flush().await;
+                    });
+                }
+            }
+
             debug!(
                 "💹 Kline data updated for interval {}, mint: {}, time: {}, open: {}, close: {}",
                 interval, mint_account, time_bucket, kline_data.open, price
@@ -708,6 +1751,66 @@ impl EventStorage {
         Ok(())
     }
 
+    /// Whether `price` moves further from `previous` than `kline.max_price_jump_ratio` allows, in either direction
+    fn price_jump_exceeded(&self, previous: Option<u128>, price: u128) -> bool {
+        let max_ratio = self.config.kline.max_price_jump_ratio;
+        match previous {
+            Some(previous) if max_ratio > 0.0 && previous > 0 => {
+                price == 0 || price.max(previous) as f64 / price.min(previous) as f64 > max_ratio
+            }
+            _ => false,
+        }
+    }
+
+    /// Length of the run of rejected prices `price` would extend: prices agreeing with the pending
+    /// level continue it, anything else starts a new one
+    fn pending_price_run(&self, detail: &MintDetailData, price: u128) -> u32 {
+        match detail.pending_price {
+            Some(pending) if !self.price_jump_exceeded(Some(pending), price) => {
+                detail.pending_price_count.saturating_add(1)
+            }
+            _ => 1,
+        }
+    }
+
+    /// Whether `price` stays out of `latest_price` and klines: it exceeds the sanity bound and hasn't
+    /// yet been confirmed by `kline.price_jump_confirmations` agreeing prices
+    fn price_rejected(&self, detail: &MintDetailData, price: u128) -> bool {
+        if !self.price_jump_exceeded(detail.latest_price, price) {
+            return false;
+        }
+        let confirmations = self.config.kline.price_jump_confirmations;
+        confirmations == 0 || self.pending_price_run(detail, price) < confirmations
+    }
+
+    /// Mint detail as stored, without applying merge counters
+    fn stored_mint_detail(&self, mint_account: &str) -> Result<Option<MintDetailData>> {
+        let key = self.generate_mint_detail_key(mint_account);
+        Ok(match self.db.get(key.as_bytes())? {
+            Some(data) => serde_json::from_slice::<MintDetailData>(&data).ok(),
+            None => None,
+        })
+    }
+
+    /// Generate mint detail counter key
+    /// Format: mc:{mint_account}:{field}
+    fn generate_mint_counter_key(&self, mint_account: &str, total: MintTotal) -> String {
+        format!("mc:{}:{}", mint_account, total.name())
+    }
+
+    /// Add the `mc:` merge counters of a mint onto the totals of its `in:` record
+    fn apply_mint_counters(&self, detail: &mut MintDetailData) -> Result<()> {
+        let keys =
+            MintTotal::ALL.map(|total| self.generate_mint_counter_key(&detail.mint_account, total));
+        for (total, value) in MintTotal::ALL.into_iter().zip(self.db.multi_get(keys)) {
+            if let Some(value) = value? {
+                let field = total.field_mut(detail);
+                *field = field.saturating_add(decode_counter(&value));
+            }
+        }
+        Ok(())
+    }
+
     /// Generate mint detail key
     /// Format: in:{mint_account}
     fn generate_mint_detail_key(&self, mint_account: &str) -> String {
@@ -741,8 +1844,35 @@ impl EventStorage {
         }
     }
 
-    /// Fetch token metadata from IPFS with retry logic
+    /// Fetch token metadata, joining an in-flight fetch of the same URI when
+    /// `ipfs.coalesce_uri_fetches` is on
     async fn fetch_token_uri_data(&self, uri: &str) -> Option<TokenUriData> {
+        if !self.config.ipfs.coalesce_uri_fetches {
+            return self.request_token_uri_data(uri).await;
+        }
+
+        let fetch = {
+            let mut fetches = self.uri_fetches.lock().unwrap_or_else(|e| e.into_inner());
+            Arc::clone(fetches.entry(uri.to_string()).or_default())
+        };
+        let uri_data = fetch
+            .get_or_init(|| self.request_token_uri_data(uri))
+            .await
+            .clone();
+
+        // Only in-flight fetches are shared; a later creation with this URI fetches again
+        let mut fetches = self.uri_fetches.lock().unwrap_or_else(|e| e.into_inner());
+        if fetches
+            .get(uri)
+            .is_some_and(|current| Arc::ptr_eq(current, &fetch))
+        {
+            fetches.remove(uri);
+        }
+        uri_data
+    }
+
+    /// Fetch token metadata from IPFS with retry logic
+    async fn request_token_uri_data(&self, uri: &str) -> Option<TokenUriData> {
         let ipfs_hash = Self::extract_ipfs_hash(uri)?;
         let ipfs_url = format!("{}{}", self.config.ipfs.gateway_url, ipfs_hash);
 
@@ -752,11 +1882,18 @@ impl EventStorage {
             match self.http_client.get(&ipfs_url).send().await {
                 Ok(response) => {
                     if response.status().is_success() {
-                        match response.json::<TokenUriData>().await {
-                            Ok(uri_data) => {
+                        match Self::read_metadata_body(
+                            response,
+                            self.config.ipfs.max_metadata_bytes,
+                        )
+                        .await
+                        {
+                            Ok(Some(uri_data)) => {
                                 debug!("Successfully fetched token metadata for URI: {}", uri);
                                 return Some(uri_data);
                             }
+                            // Wrong type or oversized: the gateway will answer the same way again
+                            Ok(None) => return None,
                             Err(e) => {
                                 warn!(
                                     "Failed to parse JSON from IPFS (attempt {}/{}): {}",
@@ -794,9 +1931,106 @@ impl EventStorage {
         None
     }
 
+    /// Read a metadata response as JSON without buffering more than `max_bytes`.
+    /// Ok(None) means it was rejected (not JSON, or too large) and logged; Err is worth retrying.
+    async fn read_metadata_body(
+        mut response: reqwest::Response,
+        max_bytes: usize,
+    ) -> Result<Option<TokenUriData>> {
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        if !content_type.contains("json") {
+            warn!(
+                "⚠️ Skipping IPFS metadata with non-JSON content type '{}': {}",
+                content_type,
+                response.url()
+            );
+            return Ok(None);
+        }
+
+        if let Some(length) = response.content_length() {
+            if length > max_bytes as u64 {
+                warn!(
+                    "⚠️ Skipping IPFS metadata of {} bytes (max_metadata_bytes {}): {}",
+                    length,
+                    max_bytes,
+                    response.url()
+                );
+                return Ok(None);
+            }
+        }
+
+        // Content-Length may be missing or wrong, so enforce the cap while reading
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if body.len() + chunk.len() > max_bytes {
+                warn!(
+                    "⚠️ Skipping IPFS metadata larger than max_metadata_bytes ({}): {}",
+                    max_bytes,
+                    response.url()
+                );
+                return Ok(None);
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        Ok(Some(serde_json::from_slice(&body)?))
+    }
+
+    /// Placeholder metadata built from the on-chain TokenCreated fields
+    fn onchain_uri_data(event: &TokenCreatedEvent) -> TokenUriData {
+        TokenUriData {
+            name: Some(event.name.clone()),
+            symbol: Some(event.symbol.clone()),
+            ..Default::default()
+        }
+    }
+
+    /// Lock shard guarding the mint detail record of `mint_account`
+    fn mint_lock(&self, mint_account: &str) -> &Mutex<()> {
+        let mut hasher = DefaultHasher::new();
+        mint_account.hash(&mut hasher);
+        &self.mint_locks[hasher.finish() as usize % self.mint_locks.len()]
+    }
+
+    /// Write the `mt:` marker of a newly created mint unless the mint is already known.
+    /// Runs under the mint lock and creates the `in:` record in the same write, so concurrent
+    /// TokenCreated events for one mint (e.g. reprocessed) store a single marker
+    async fn claim_mint_marker(&self, token_event: &TokenCreatedEvent) -> Result<()> {
+        let mint_account = &token_event.mint_account;
+        let mint_detail_key = self.generate_mint_detail_key(mint_account);
+        let _guard = self.mint_lock(mint_account).lock().await;
+
+        // Check if mint already exists using in: key to avoid duplicates
+        if self.db.get(mint_detail_key.as_bytes())?.is_some() {
+            debug!(
+                "⚠️ Mint already exists (found in: key), skipping mint marker for: {}",
+                mint_account
+            );
+            return Ok(());
+        }
+
+        let mint_key = self.generate_mint_key(token_event.slot, mint_account);
+        let detail = MintDetailData {
+            mint_account: mint_account.clone(),
+            ..Default::default()
+        };
+        let mut batch = rocksdb::WriteBatch::default();
+        batch.put(mint_key.as_bytes(), b""); // Empty value marker
+        batch.put(mint_detail_key.as_bytes(), serde_json::to_vec(&detail)?);
+        self.db.write(batch)?;
+        debug!("💾 New mint marker stored: {}", mint_key);
+        Ok(())
+    }
+
     /// Update mint detail with URI data
     async fn update_mint_uri_data(&self, mint_account: &str, uri_data: TokenUriData) -> Result<()> {
         let key = self.generate_mint_detail_key(mint_account);
+        let _guard = self.mint_lock(mint_account).lock().await;
 
         // Get existing detail
         let mut detail = match self.db.get(key.as_bytes())? {
@@ -815,6 +2049,7 @@ impl EventStorage {
         // Update URI data
         detail.uri_data = Some(uri_data);
         detail.last_updated_at = Some(Utc::now());
+        detail.uri_last_fetched_at = detail.last_updated_at;
 
         // Save back to database
         let value = serde_json::to_vec(&detail)?;
@@ -827,6 +2062,156 @@ impl EventStorage {
         Ok(())
     }
 
+    /// Fetch and store `uri_data` for many mints at once; each fetch holds a permit from `limiter`
+    pub async fn prefetch_uri_data(
+        &self,
+        mints: Vec<String>,
+        limiter: &Semaphore,
+    ) -> Result<PrefetchUriResult> {
+        let requested = mints.len();
+        let mut details = Vec::with_capacity(requested);
+        let mut failed = Vec::new();
+        for mint_account in mints {
+            match self.get_mint_detail(&mint_account)? {
+                Some(detail) if detail.uri.as_deref().is_some_and(|uri| !uri.is_empty()) => {
+                    details.push((mint_account, detail.uri.unwrap_or_default()));
+                }
+                _ => {
+                    warn!("No stored URI to prefetch for mint: {}", mint_account);
+                    failed.push(mint_account);
+                }
+            }
+        }
+
+        let results: Vec<(String, Result<bool>)> = stream::iter(details)
+            .map(|(mint_account, uri)| async move {
+                let _permit = match limiter.acquire().await {
+                    Ok(permit) => permit,
+                    Err(e) => return (mint_account, Err(e.into())),
+                };
+                let result = match self.fetch_token_uri_data(&uri).await {
+                    Some(uri_data) => self
+                        .update_mint_uri_data(&mint_account, uri_data)
+                        .await
+                        .map(|_| true),
+                    None => Ok(false),
+                };
+                (mint_account, result)
+            })
+            .buffer_unordered(requested.max(1))
+            .collect()
+            .await;
+
+        let mut fetched = 0;
+        for (mint_account, result) in results {
+            match result {
+                Ok(true) => fetched += 1,
+                Ok(false) => failed.push(mint_account),
+                Err(e) => {
+                    error!(
+                        "Failed to prefetch URI data for mint {}: {}",
+                        mint_account, e
+                    );
+                    failed.push(mint_account);
+                }
+            }
+        }
+
+        info!("📦 Prefetched URI data for {}/{} mints", fetched, requested);
+        Ok(PrefetchUriResult {
+            requested,
+            fetched,
+            failed,
+        })
+    }
+
+    /// Mints with a URI whose metadata was last fetched before `cutoff`, never-fetched first,
+    /// then oldest first; at most `limit` as (mint, uri)
+    fn stale_uri_mints(
+        &self,
+        cutoff: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<(String, String)>> {
+        let mut stale = Vec::new();
+        for item in self.prefix_scan("in:") {
+            let (_, value) = item?;
+            let Ok(detail) = serde_json::from_slice::<MintDetailData>(&value) else {
+                continue;
+            };
+            let Some(uri) = detail.uri.filter(|uri| !uri.is_empty()) else {
+                continue;
+            };
+            if !matches!(detail.uri_last_fetched_at, Some(at) if at >= cutoff) {
+                stale.push((detail.uri_last_fetched_at, detail.mint_account, uri));
+            }
+        }
+        stale.sort_by_key(|(fetched_at, _, _)| *fetched_at);
+        Ok(stale
+            .into_iter()
+            .take(limit)
+            .map(|(_, mint_account, uri)| (mint_account, uri))
+            .collect())
+    }
+
+    /// Refetch the metadata of up to `limit` mints last fetched before `cutoff`, one gateway
+    /// request at a time. `uri_data` is only rewritten when the gateway returns something new
+    pub async fn refetch_stale_uri_data(
+        &self,
+        cutoff: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<UriRefetchResult> {
+        let mut result = UriRefetchResult::default();
+        for (mint_account, uri) in self.stale_uri_mints(cutoff, limit)? {
+            let uri_data = self.request_token_uri_data(&uri).await;
+            result.refetched += 1;
+            if self
+                .apply_refetched_uri_data(&mint_account, uri_data)
+                .await?
+            {
+                debug!("🔁 Metadata changed for mint: {}", mint_account);
+                result.updated += 1;
+            }
+        }
+        Ok(result)
+    }
+
+    /// Store a refetch attempt; returns whether `uri_data` changed. Failed fetches also wait
+    /// `ipfs.refetch_after_secs` before the next attempt, so a dead URI can't hold up the others
+    async fn apply_refetched_uri_data(
+        &self,
+        mint_account: &str,
+        uri_data: Option<TokenUriData>,
+    ) -> Result<bool> {
+        let key = self.generate_mint_detail_key(mint_account);
+        let _guard = self.mint_lock(mint_account).lock().await;
+        let Some(data) = self.db.get(key.as_bytes())? else {
+            return Ok(false);
+        };
+        let mut detail: MintDetailData = serde_json::from_slice(&data)?;
+
+        let now = Utc::now();
+        let changed = uri_data.is_some() && uri_data != detail.uri_data;
+        if changed {
+            detail.uri_data = uri_data;
+            detail.last_updated_at = Some(now);
+        }
+        detail.uri_last_fetched_at = Some(now);
+        self.db.put(key.as_bytes(), serde_json::to_vec(&detail)?)?;
+        Ok(changed)
+    }
+
+    /// Whether a trade event comes from a wallet in `stats.excluded_wallets`
+    fn is_excluded_trader(&self, event: &SpinPetEvent) -> bool {
+        let payer = match event {
+            SpinPetEvent::BuySell(e) => &e.payer,
+            SpinPetEvent::LongShort(e) => &e.payer,
+            SpinPetEvent::FullClose(e) => &e.payer,
+            SpinPetEvent::PartialClose(e) => &e.payer,
+            _ => return false,
+        };
+        self.config.stats.excluded_wallets.contains(payer)
+    }
+
     /// Process events for mint detail data
     pub async fn process_event_for_mint_detail(&self, event: &SpinPetEvent) -> Result<()> {
         let mint_account = match event {
@@ -838,8 +2223,16 @@ impl EventStorage {
             SpinPetEvent::PartialClose(e) => &e.mint_account,
             SpinPetEvent::MilestoneDiscount(e) => &e.mint_account,
         };
+        if self.config.stats.mint_snapshot_interval_secs > 0 {
+            self.snapshot_mints
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(mint_account.clone());
+        }
 
         let key = self.generate_mint_detail_key(mint_account);
+        // Held until the updated record is written so concurrent events for a mint don't lose updates
+        let guard = self.mint_lock(mint_account).lock().await;
         let mut detail = match self.db.get(key.as_bytes())? {
             Some(data) => {
                 serde_json::from_slice::<MintDetailData>(&data).unwrap_or_else(|_| MintDetailData {
@@ -853,6 +2246,32 @@ impl EventStorage {
             },
         };
 
+        // Trades from excluded wallets still move the price but not the totals
+        let counts_toward_totals = !self.is_excluded_trader(event);
+        let mut total_deltas: Vec<(MintTotal, u64)> = Vec::new();
+
+        // Implausible price jumps (e.g. a mis-parsed price) leave latest_price untouched until enough
+        // agreeing prices confirm the new level
+        let price_rejected =
+            match event.latest_price() {
+                Some(price) if self.price_rejected(&detail, price) => {
+                    warn!(
+                    "⚠️ Rejected price {} for mint {}: more than {}x away from stored price {:?}",
+                    price, mint_account, self.config.kline.max_price_jump_ratio, detail.latest_price
+                );
+                    detail.rejected_price_count = detail.rejected_price_count.saturating_add(1);
+                    detail.pending_price_count = self.pending_price_run(&detail, price);
+                    detail.pending_price = Some(price);
+                    true
+                }
+                Some(_) => {
+                    detail.pending_price = None;
+                    detail.pending_price_count = 0;
+                    false
+                }
+                None => false,
+            };
+
         // Update detail based on event type
         match event {
             SpinPetEvent::TokenCreated(e) => {
@@ -869,6 +2288,9 @@ impl EventStorage {
                 detail.name = Some(e.name.clone());
                 detail.symbol = Some(e.symbol.clone());
                 detail.uri = Some(e.uri.clone());
+                if e.uri.is_empty() && self.config.ipfs.use_onchain_fallback {
+                    detail.uri_data = Some(Self::onchain_uri_data(e));
+                }
                 detail.create_timestamp = Some(e.timestamp.timestamp());
                 detail.created_by = Some(e.payer.clone());
                 detail.last_updated_at = Some(e.timestamp);
@@ -881,43 +2303,92 @@ impl EventStorage {
                 detail.last_updated_at = Some(e.timestamp);
             }
             SpinPetEvent::BuySell(e) => {
-                detail.latest_price = Some(e.latest_price);
-                detail.latest_trade_time = Some(e.timestamp.timestamp());
-                detail.total_sol_amount = detail.total_sol_amount.saturating_add(e.sol_amount);
+                if !price_rejected {
+                    detail.latest_price = Some(e.latest_price);
+                    detail.latest_trade_time = Some(e.timestamp.timestamp());
+                }
+                if counts_toward_totals {
+                    total_deltas.push((MintTotal::SolAmount, e.sol_amount));
+                }
                 detail.last_updated_at = Some(e.timestamp);
             }
             SpinPetEvent::LongShort(e) => {
-                detail.latest_price = Some(e.latest_price);
-                detail.latest_trade_time = Some(e.timestamp.timestamp());
-                detail.total_margin_sol_amount = detail
-                    .total_margin_sol_amount
-                    .saturating_add(e.margin_sol_amount);
+                if !price_rejected {
+                    detail.latest_price = Some(e.latest_price);
+                    detail.latest_trade_time = Some(e.timestamp.timestamp());
+                }
+                if counts_toward_totals {
+                    total_deltas.push((MintTotal::MarginSolAmount, e.margin_sol_amount));
+                }
                 detail.last_updated_at = Some(e.timestamp);
             }
             SpinPetEvent::ForceLiquidate(e) => {
-                detail.total_force_liquidations = detail.total_force_liquidations.saturating_add(1);
+                total_deltas.push((MintTotal::ForceLiquidations, 1));
                 detail.last_updated_at = Some(e.timestamp);
             }
             SpinPetEvent::FullClose(e) => {
-                detail.latest_price = Some(e.latest_price);
-                detail.latest_trade_time = Some(e.timestamp.timestamp());
-                detail.total_close_profit = detail
-                    .total_close_profit
-                    .saturating_add(e.user_close_profit);
+                if !price_rejected {
+                    detail.latest_price = Some(e.latest_price);
+                    detail.latest_trade_time = Some(e.timestamp.timestamp());
+                }
+                if counts_toward_totals {
+                    total_deltas.push((MintTotal::CloseProfit, e.user_close_profit));
+                }
+                if let Some(pnl) = self
+                    .realized_pnl(
+                        &e.mint_account,
+                        OrderType::from_close_long(e.is_close_long),
+                        &e.order_pda,
+                        e.final_sol_amount,
+                        0,
+                    )
+                    .await?
+                {
+                    if counts_toward_totals {
+                        detail.total_realized_pnl = detail.total_realized_pnl.saturating_add(pnl);
+                    }
+                }
                 detail.last_updated_at = Some(e.timestamp);
             }
             SpinPetEvent::PartialClose(e) => {
-                detail.latest_price = Some(e.latest_price);
-                detail.latest_trade_time = Some(e.timestamp.timestamp());
-                detail.total_close_profit = detail
-                    .total_close_profit
-                    .saturating_add(e.user_close_profit);
+                if !price_rejected {
+                    detail.latest_price = Some(e.latest_price);
+                    detail.latest_trade_time = Some(e.timestamp.timestamp());
+                }
+                if counts_toward_totals {
+                    total_deltas.push((MintTotal::CloseProfit, e.user_close_profit));
+                }
+                if let Some(pnl) = self
+                    .realized_pnl(
+                        &e.mint_account,
+                        OrderType::from_raw(e.order_type),
+                        &e.order_pda,
+                        e.final_sol_amount,
+                        e.margin_sol_amount,
+                    )
+                    .await?
+                {
+                    if counts_toward_totals {
+                        detail.total_realized_pnl = detail.total_realized_pnl.saturating_add(pnl);
+                    }
+                }
                 detail.last_updated_at = Some(e.timestamp);
             }
         }
 
-        let value = serde_json::to_vec(&detail)?;
-        self.db.put(key.as_bytes(), &value)?;
+        let mut batch = rocksdb::WriteBatch::default();
+        for (total, delta) in total_deltas {
+            if self.config.database.merge_counters {
+                let counter_key = self.generate_mint_counter_key(mint_account, total);
+                batch.merge(counter_key.as_bytes(), delta.to_le_bytes());
+            } else {
+                let field = total.field_mut(&mut detail);
+                *field = field.saturating_add(delta);
+            }
+        }
+        batch.put(key.as_bytes(), serde_json::to_vec(&detail)?);
+        self.db.write(batch)?;
+        drop(guard);
 
         debug!("💾 Mint detail updated successfully, key: {}", key);
 
@@ -928,13 +2399,29 @@ impl EventStorage {
                     db: self.db.clone(),
                     config: self.config.clone(),
                     http_client: self.http_client.clone(),
+                    mint_locks: self.mint_locks.clone(),
+                    strict_kline_ordering: self.strict_kline_ordering.clone(),
+                    latest_slot: self.latest_slot.clone(),
+                    tsdb: self.tsdb.clone(),
+                    kline_seen_mints: self.kline_seen_mints.clone(),
+                    receive_latency: self.receive_latency.clone(),
+                    uri_fetches: self.uri_fetches.clone(),
+                    disk_guard: self.disk_guard.clone(),
+                    snapshot_mints: self.snapshot_mints.clone(),
+                    rebuilding_indexes: self.rebuilding_indexes.clone(),
                 };
                 let uri = token_event.uri.clone();
                 let mint_account = token_event.mint_account.clone();
+                let fallback = if self.config.ipfs.use_onchain_fallback {
+                    Some(Self::onchain_uri_data(token_event))
+                } else {
+                    None
+                };
 
                 // Spawn async task to fetch URI data without blocking
                 tokio::spawn(async move {
-                    if let Some(uri_data) = storage.fetch_token_uri_data(&uri).await {
+                    let uri_data = storage.fetch_token_uri_data(&uri).await.or(fallback);
+                    if let Some(uri_data) = uri_data {
                         if let Err(e) = storage.update_mint_uri_data(&mint_account, uri_data).await
                         {
                             error!("Failed to update URI data for mint {}: {}", mint_account, e);
@@ -958,7 +2445,10 @@ impl EventStorage {
             let key = self.generate_mint_detail_key(&mint_account);
             if let Some(data) = self.db.get(key.as_bytes())? {
                 match serde_json::from_slice::<MintDetailData>(&data) {
-                    Ok(detail) => details.push(detail),
+                    Ok(mut detail) => {
+                        self.apply_mint_counters(&mut detail)?;
+                        details.push(detail);
+                    }
                     Err(e) => {
                         error!(
                             "❌ Failed to parse mint detail data: {}, mint: {}",
@@ -980,36 +2470,66 @@ impl EventStorage {
     /// Store event
     pub async fn store_event(&self, event: SpinPetEvent) -> Result<()> {
         let key = self.generate_event_key(&event);
+        if self.is_read_only() {
+            error!("🚨 Disk space low, refusing to store event: {}", key);
+            return Err(anyhow::anyhow!(
+                "database disk below database.min_free_disk_mb, event not stored: {}",
+                key
+            ));
+        }
         let value = serde_json::to_vec(&event)?;
 
+        // Re-storing a known event (e.g. slot replay) rewrites its records but must not
+        // re-apply aggregates such as klines and mint totals
+        let stored_key = self.stored_event_key(&event, &key)?;
+        let already_stored = stored_key.is_some();
+
+        let policy = self.persistence_policy(&event);
+
         let mut batch = rocksdb::WriteBatch::default();
-        batch.put(key.as_bytes(), &value);
+        // A replayed v1 record moves to the key carrying its real log index
+        if let Some(legacy_key) = stored_key.filter(|stored| *stored != key) {
+            batch.delete(legacy_key.as_bytes());
+        }
+        if policy.stores_event_record() {
+            batch.put(key.as_bytes(), &value);
+        } else {
+            // Lets a replay recognise the event without keeping its record
+            batch.put(self.generate_event_marker_key(&key).as_bytes(), b"");
+        }
+        if self.config.retention.order_timeline.enabled {
+            if let Some(timeline_key) = self.generate_order_timeline_key(&event) {
+                batch.put(timeline_key.as_bytes(), &value);
+            }
+        }
+        if self.config.retention.slot_index.enabled {
+            batch.put(self.generate_slot_index_key(&event).as_bytes(), &value);
+        }
+        self.advance_latest_slot(&mut batch, event.slot());
 
         // Only store mint marker for TokenCreatedEvent and avoid duplicates
         if let SpinPetEvent::TokenCreated(token_event) = &event {
-            let mint_detail_key = self.generate_mint_detail_key(&token_event.mint_account);
-
-            // Check if mint already exists using in: key to avoid duplicates
-            if self.db.get(mint_detail_key.as_bytes())?.is_none() {
-                let mint_key = self.generate_mint_key(token_event.slot, &token_event.mint_account);
-                batch.put(mint_key.as_bytes(), b""); // Empty value marker
-                debug!("💾 New mint marker stored: {}", mint_key);
-            } else {
-                debug!(
-                    "⚠️ Mint already exists (found in: key), skipping mint marker for: {}",
-                    token_event.mint_account
-                );
-            }
+            self.claim_mint_marker(token_event).await?;
         }
 
         // Process order-related events
         match &event {
+            SpinPetEvent::LongShort(_) | SpinPetEvent::PartialClose(_)
+                if !policy.stores_order_index() =>
+            {
+                debug!("⏭️ Order index disabled by persistence policy: {}", key);
+            }
+            SpinPetEvent::LongShort(_) | SpinPetEvent::PartialClose(_)
+                if self.has_unindexed_order_type(&event) =>
+            {
+                self.quarantine_order(&mut batch, &event)?;
+            }
             SpinPetEvent::LongShort(long_short_event) => {
                 // Create order data
                 let order_data = self.create_order_data_from_long_short(long_short_event);
                 let order_key = self.generate_order_key(
                     &long_short_event.mint_account,
-                    long_short_event.order_type,
+                    OrderType::from_raw(long_short_event.order_type),
                     &long_short_event.order_pda,
                 );
                 let order_value = serde_json::to_vec(&order_data)?;
@@ -1033,13 +2553,21 @@ impl EventStorage {
                 let order_data = self.create_order_data_from_partial_close(partial_close_event);
                 let order_key = self.generate_order_key(
                     &partial_close_event.mint_account,
-                    partial_close_event.order_type,
+                    OrderType::from_raw(partial_close_event.order_type),
                     &partial_close_event.order_pda,
                 );
                 let order_value = serde_json::to_vec(&order_data)?;
                 batch.put(order_key.as_bytes(), &order_value);
                 debug!("💾 Order data updated successfully, key: {}", order_key);
 
+                // The order is active again, drop it from the expired set
+                let expired_key = self.generate_expired_order_key(
+                    &partial_close_event.mint_account,
+                    OrderType::from_raw(partial_close_event.order_type),
+                    &partial_close_event.order_pda,
+                );
+                batch.delete(expired_key.as_bytes());
+
                 // Update user order data
                 let user_order_key = self.generate_user_order_key(
                     &partial_close_event.user,
@@ -1053,9 +2581,8 @@ impl EventStorage {
                 );
             }
             SpinPetEvent::FullClose(full_close_event) => {
-                // Delete order data (need to know order_type, get from event)
-                // Since FullCloseEvent includes is_close_long field, we can infer order_type
-                let order_type = if full_close_event.is_close_long { 1 } else { 2 };
+                // Delete order data; FullCloseEvent carries the side as is_close_long
+                let order_type = OrderType::from_close_long(full_close_event.is_close_long);
                 let order_key = self.generate_order_key(
                     &full_close_event.mint_account,
                     order_type,
@@ -1063,6 +2590,12 @@ impl EventStorage {
                 );
                 batch.delete(order_key.as_bytes());
                 debug!("💾 Order data deleted successfully, key: {}", order_key);
+                let expired_key = self.generate_expired_order_key(
+                    &full_close_event.mint_account,
+                    order_type,
+                    &full_close_event.order_pda,
+                );
+                batch.delete(expired_key.as_bytes());
 
                 // Delete user order data - need to find user from existing order
                 if let Some(existing_order) = self
@@ -1086,15 +2619,51 @@ impl EventStorage {
                 }
             }
             SpinPetEvent::ForceLiquidate(force_liquidate_event) => {
-                // Force liquidation: search and delete in both up and dn
-                let up_key = self.generate_order_key(
-                    &force_liquidate_event.mint_account,
-                    2, // up (short)
+                // Global liquidation feed, with the owner resolved before the order is deleted
+                let mut owner = None;
+                for order_type in [OrderType::Short, OrderType::Long] {
+                    if let Some(order) = self
+                        .get_order_by_pda(
+                            &force_liquidate_event.mint_account,
+                            order_type,
+                            &force_liquidate_event.order_pda,
+                        )
+                        .await?
+                    {
+                        owner = Some(order.user);
+                        break;
+                    }
+                }
+                let liquidation = LiquidationRecord {
+                    mint_account: force_liquidate_event.mint_account.clone(),
+                    order_pda: force_liquidate_event.order_pda.clone(),
+                    owner,
+                    liquidator: force_liquidate_event.payer.clone(),
+                    slot: force_liquidate_event.slot,
+                    signature: force_liquidate_event.signature.clone(),
+                    ts: force_liquidate_event.timestamp.timestamp(),
+                };
+                let liquidation_key = self.generate_liquidation_key(
+                    force_liquidate_event.slot,
+                    &force_liquidate_event.signature,
                     &force_liquidate_event.order_pda,
                 );
-                let dn_key = self.generate_order_key(
+                if self.config.retention.liquidations.enabled {
+                    batch.put(
+                        liquidation_key.as_bytes(),
+                        serde_json::to_vec(&liquidation)?,
+                    );
+                }
+
+                // Force liquidation: search and delete in both up and dn
+                let up_key = self.generate_order_key(
+                    &force_liquidate_event.mint_account,
+                    OrderType::Short,
+                    &force_liquidate_event.order_pda,
+                );
+                let dn_key = self.generate_order_key(
                     &force_liquidate_event.mint_account,
-                    1, // dn (long)
+                    OrderType::Long,
                     &force_liquidate_event.order_pda,
                 );
 
@@ -1110,7 +2679,7 @@ impl EventStorage {
                     if let Some(existing_order) = self
                         .get_order_by_pda(
                             &force_liquidate_event.mint_account,
-                            2,
+                            OrderType::Short,
                             &force_liquidate_event.order_pda,
                         )
                         .await?
@@ -1138,7 +2707,7 @@ impl EventStorage {
                     if let Some(existing_order) = self
                         .get_order_by_pda(
                             &force_liquidate_event.mint_account,
-                            1,
+                            OrderType::Long,
                             &force_liquidate_event.order_pda,
                         )
                         .await?
@@ -1155,38 +2724,37 @@ impl EventStorage {
                         );
                     }
                 }
-            }
-            SpinPetEvent::MilestoneDiscount(milestone_discount_event) => {
-                // MilestoneDiscountEvent doesn't have a user field, we need to get user info from order_pda
-                // This requires additional query, for now we'll use payer as user
-                let user_transaction = UserTransactionData {
-                    event_type: "milestone_discount".to_string(),
-                    user: milestone_discount_event.payer.clone(), // Use payer as user
-                    mint_account: milestone_discount_event.mint_account.clone(),
-                    slot: milestone_discount_event.slot,
-                    timestamp: milestone_discount_event.timestamp.timestamp(),
-                    signature: milestone_discount_event.signature.clone(),
-                    event_data: serde_json::to_value(milestone_discount_event)
-                        .unwrap_or(serde_json::Value::Null),
-                };
-                let user_key = self.generate_user_transaction_key(
-                    &user_transaction.user,
-                    &user_transaction.mint_account,
-                    user_transaction.slot,
-                );
-                let user_value = serde_json::to_vec(&user_transaction)?;
-                batch.put(user_key.as_bytes(), &user_value);
-                debug!(
-                    "💾 User transaction recorded successfully, key: {}",
-                    user_key
-                );
+
+                // Orders the expiry sweep already moved out of the open set
+                for order_type in [OrderType::Short, OrderType::Long] {
+                    let expired_key = self.generate_expired_order_key(
+                        &force_liquidate_event.mint_account,
+                        order_type,
+                        &force_liquidate_event.order_pda,
+                    );
+                    if let Some(data) = self.db.get(expired_key.as_bytes())? {
+                        batch.delete(expired_key.as_bytes());
+                        if let Ok(existing_order) = serde_json::from_slice::<OrderData>(&data) {
+                            let user_order_key = self.generate_user_order_key(
+                                &existing_order.user,
+                                &force_liquidate_event.mint_account,
+                                &force_liquidate_event.order_pda,
+                            );
+                            batch.delete(user_order_key.as_bytes());
+                        }
+                        debug!(
+                            "💾 Force liquidation expired order deleted successfully, key: {}",
+                            expired_key
+                        );
+                    }
+                }
             }
             _ => {
                 // Other event types don't need order processing
             }
         }
 
-        // Process user transaction records
+        // Process user transaction records (the only `us:` write for every event type)
         if let Some(user_transaction) = self.create_user_transaction_data(&event) {
             let user_key = self.generate_user_transaction_key(
                 &user_transaction.user,
@@ -1201,57 +2769,112 @@ impl EventStorage {
             );
         }
 
-        // Process kline data for price events
-        match &event {
-            SpinPetEvent::BuySell(e) => {
-                if let Err(err) = self
-                    .process_kline_data(&e.mint_account, e.latest_price, e.timestamp)
-                    .await
+        if already_stored {
+            debug!(
+                "⏭️ Event already stored, skipping aggregate updates: {}",
+                key
+            );
+        } else {
+            // Prices outside the sanity bound stay out of klines (counted in the mint detail)
+            let stored_detail = match event.latest_price() {
+                Some(_)
+                    if self.config.kline.max_price_jump_ratio > 0.0
+                        || self.config.kline.skip_unchanged_price =>
                 {
-                    error!("❌ Failed to process kline data for BuySell event: {}", err);
+                    self.stored_mint_detail(event.mint_account())?
                 }
-            }
-            SpinPetEvent::LongShort(e) => {
-                if let Err(err) = self
-                    .process_kline_data(&e.mint_account, e.latest_price, e.timestamp)
-                    .await
-                {
-                    error!(
-                        "❌ Failed to process kline data for LongShort event: {}",
-                        err
+                _ => None,
+            };
+            let previous_price = stored_detail
+                .as_ref()
+                .and_then(|detail| detail.latest_price);
+            let price_rejected = match (event.latest_price(), &stored_detail) {
+                (Some(price), Some(detail)) => self.price_rejected(detail, price),
+                _ => false,
+            };
+            // With `kline.skip_unchanged_price`, a price equal to the mint's stored one leaves
+            // the candles untouched
+            let price_unchanged = self.config.kline.skip_unchanged_price
+                && previous_price.is_some()
+                && event.latest_price() == previous_price;
+
+            // Process kline data for price events
+            match &event {
+                _ if price_rejected => {
+                    debug!(
+                        "🚫 Price outside sanity bound, skipping kline update: {}",
+                        key
                     );
                 }
-            }
-            SpinPetEvent::FullClose(e) => {
-                if let Err(err) = self
-                    .process_kline_data(&e.mint_account, e.latest_price, e.timestamp)
-                    .await
-                {
-                    error!(
-                        "❌ Failed to process kline data for FullClose event: {}",
-                        err
-                    );
+                _ if price_unchanged => {
+                    debug!("⏸️ Price unchanged, skipping kline update: {}", key);
                 }
-            }
-            SpinPetEvent::PartialClose(e) => {
-                if let Err(err) = self
-                    .process_kline_data(&e.mint_account, e.latest_price, e.timestamp)
-                    .await
-                {
-                    error!(
-                        "❌ Failed to process kline data for PartialClose event: {}",
-                        err
+                SpinPetEvent::BuySell(e) if e.sol_amount < self.config.kline.min_trade_sol => {
+                    debug!(
+                        "🧹 Dust trade below min_trade_sol ({} < {}), skipping kline update: {}",
+                        e.sol_amount, self.config.kline.min_trade_sol, e.signature
                     );
                 }
+                SpinPetEvent::BuySell(e) => {
+                    if let Err(err) = self
+                        .process_kline_data(&e.mint_account, e.latest_price, e.timestamp, e.slot)
+                        .await
+                    {
+                        error!("❌ Failed to process kline data for BuySell event: {}", err);
+                    }
+                }
+                SpinPetEvent::LongShort(e) => {
+                    if let Err(err) = self
+                        .process_kline_data(&e.mint_account, e.latest_price, e.timestamp, e.slot)
+                        .await
+                    {
+                        error!(
+                            "❌ Failed to process kline data for LongShort event: {}",
+                            err
+                        );
+                    }
+                }
+                SpinPetEvent::FullClose(e) => {
+                    if let Err(err) = self
+                        .process_kline_data(&e.mint_account, e.latest_price, e.timestamp, e.slot)
+                        .await
+                    {
+                        error!(
+                            "❌ Failed to process kline data for FullClose event: {}",
+                            err
+                        );
+                    }
+                }
+                SpinPetEvent::PartialClose(e) => {
+                    if let Err(err) = self
+                        .process_kline_data(&e.mint_account, e.latest_price, e.timestamp, e.slot)
+                        .await
+                    {
+                        error!(
+                            "❌ Failed to process kline data for PartialClose event: {}",
+                            err
+                        );
+                    }
+                }
+                _ => {
+                    // Other events don't have latest_price, so no kline processing needed
+                }
             }
-            _ => {
-                // Other events don't have latest_price, so no kline processing needed
-            }
-        }
 
-        // Process mint detail data
-        self.process_event_for_mint_detail(&event).await?;
+            // Process mint detail data
+            self.process_event_for_mint_detail(&event).await?;
+        }
 
+        // Order changes are written under the mint lock `expire_orders` re-reads orders in
+        let _order_guard = match &event {
+            SpinPetEvent::LongShort(_)
+            | SpinPetEvent::PartialClose(_)
+            | SpinPetEvent::FullClose(_)
+            | SpinPetEvent::ForceLiquidate(_) => {
+                Some(self.mint_lock(event.mint_account()).lock().await)
+            }
+            _ => None,
+        };
         self.db.write(batch)?;
 
         debug!("💾 Event stored successfully, key: {}", key);
@@ -1261,37 +2884,36 @@ impl EventStorage {
     #[allow(dead_code)]
     pub async fn store_events(&self, events: Vec<SpinPetEvent>) -> Result<()> {
         let mut batch = rocksdb::WriteBatch::default();
-        let mut processed_mints = std::collections::HashSet::new();
 
         for event in &events {
-            let key = self.generate_event_key(event);
-            let value = serde_json::to_vec(event)?;
-            batch.put(key.as_bytes(), &value);
+            let policy = self.persistence_policy(event);
+            if policy.stores_event_record() {
+                let key = self.generate_event_key(event);
+                let value = serde_json::to_vec(event)?;
+                batch.put(key.as_bytes(), &value);
+            }
+            if self.config.retention.order_timeline.enabled {
+                if let Some(timeline_key) = self.generate_order_timeline_key(event) {
+                    batch.put(timeline_key.as_bytes(), serde_json::to_vec(event)?);
+                }
+            }
+            if self.config.retention.slot_index.enabled {
+                batch.put(
+                    self.generate_slot_index_key(event).as_bytes(),
+                    serde_json::to_vec(event)?,
+                );
+            }
+            self.advance_latest_slot(&mut batch, event.slot());
 
             // Only store mint marker for TokenCreatedEvent and avoid duplicates
             if let SpinPetEvent::TokenCreated(token_event) = event {
-                // Check if already processed in this batch
-                if !processed_mints.contains(&token_event.mint_account) {
-                    let mint_detail_key = self.generate_mint_detail_key(&token_event.mint_account);
-
-                    // Check if mint already exists using in: key to avoid duplicates
-                    if self.db.get(mint_detail_key.as_bytes())?.is_none() {
-                        let mint_key =
-                            self.generate_mint_key(token_event.slot, &token_event.mint_account);
-                        batch.put(mint_key.as_bytes(), b""); // Empty value marker
-                        processed_mints.insert(token_event.mint_account.clone());
-                        debug!("💾 New mint marker stored in batch: {}", mint_key);
-                    } else {
-                        debug!(
-                            "⚠️ Mint already exists in DB (found in: key), skipping: {}",
-                            token_event.mint_account
-                        );
-                    }
-                }
+                self.claim_mint_marker(token_event).await?;
             }
 
             // Process order-related events for user order data
             match event {
+                SpinPetEvent::LongShort(_) | SpinPetEvent::PartialClose(_)
+                    if !policy.stores_order_index() => {}
                 SpinPetEvent::LongShort(long_short_event) => {
                     let order_data = self.create_order_data_from_long_short(long_short_event);
                     let user_order_key = self.generate_user_order_key(
@@ -1347,21 +2969,28 @@ impl EventStorage {
             mint_account, page, limit, order_by
         );
 
-        // Collect all matching events
+        // Collect matching events newest first, examining at most max_scan_keys keys
+        let max_scan_keys = self.config.database.max_scan_keys;
         let mut all_events = Vec::new();
+        let mut scanned = 0usize;
+        let mut truncated = false;
 
         let iter = self
             .db
-            .iterator(IteratorMode::From(prefix.as_bytes(), Direction::Forward));
+            .iterator_opt(IteratorMode::End, self.scan_read_options(&prefix));
 
         for item in iter {
-            let (key, value) = item?;
-            let key_str = String::from_utf8_lossy(&key);
-
-            // Check if still matches prefix
-            if !key_str.starts_with(&prefix) {
+            if max_scan_keys > 0 && scanned >= max_scan_keys {
+                truncated = true;
                 break;
             }
+            scanned += 1;
+            if scanned % SCAN_YIELD_KEYS == 0 {
+                tokio::task::yield_now().await;
+            }
+
+            let (key, value) = item?;
+            let key_str = String::from_utf8_lossy(&key);
 
             // Parse event data
             match serde_json::from_slice::<SpinPetEvent>(&value) {
@@ -1373,20 +3002,25 @@ impl EventStorage {
             }
         }
 
-        // Sort by slot
+        // Sort by slot, then by emission order within a transaction
         match order_by.as_str() {
             "slot_asc" => {
-                all_events.sort_by(|a, b| self.get_event_slot(a).cmp(&self.get_event_slot(b)));
-            }
-            "slot_desc" => {
-                all_events.sort_by(|a, b| self.get_event_slot(b).cmp(&self.get_event_slot(a)));
+                all_events.sort_by_key(|e| (self.get_event_slot(e), e.log_index()));
             }
             _ => {
                 // Default sort by slot descending
-                all_events.sort_by(|a, b| self.get_event_slot(b).cmp(&self.get_event_slot(a)));
+                all_events
+                    .sort_by_key(|e| std::cmp::Reverse((self.get_event_slot(e), e.log_index())));
             }
         }
 
+        if truncated {
+            warn!(
+                "⚠️ Event query for {} hit max_scan_keys ({}), returning the newest events only",
+                mint_account, max_scan_keys
+            );
+        }
+
         let total = all_events.len();
         let offset = (page - 1) * limit;
         let has_prev = page > 1;
@@ -1406,6 +3040,7 @@ impl EventStorage {
             limit,
             has_next,
             has_prev,
+            truncated,
         })
     }
 
@@ -1476,7 +3111,10 @@ impl EventStorage {
         let mut count = 0;
         let mut skip_first = query.cursor.is_some(); // 如果有cursor，跳过第一个（它是上一页的最后一个）
 
-        for item in iterator {
+        for (scanned, item) in iterator.enumerate() {
+            if (scanned + 1) % SCAN_YIELD_KEYS == 0 {
+                tokio::task::yield_now().await;
+            }
             let (key, _) = item?;
             let key_str = String::from_utf8_lossy(&key);
 
@@ -1539,669 +3177,4912 @@ impl EventStorage {
         })
     }
 
-    /// Query order information
-    pub async fn query_orders(&self, query: OrderQuery) -> Result<OrderQueryResponse> {
-        let mint_account = &query.mint_account;
-        let order_type = &query.order_type;
-        let page = query.page.unwrap_or(1);
-        let limit = query.limit.unwrap_or(50);
-
-        debug!(
-            "🔍 Querying order information, mint: {}, type: {}, page: {}, limit: {}",
-            mint_account, order_type, page, limit
-        );
-
+    /// Load all orders of one side for a mint, sorted by lock_lp_start_price
+    async fn load_sorted_orders(
+        &self,
+        mint_account: &str,
+        side: OrderType,
+        include_expired: bool,
+    ) -> Result<Vec<OrderData>> {
         // Determine search prefix
-        let type_str = match order_type.as_str() {
-            "up_orders" => "up",
-            "down_orders" => "dn",
-            _ => return Err(anyhow::anyhow!("Invalid order type: {}", order_type)),
-        };
+        let type_str = side.key_segment();
 
-        let prefix = format!("or:{}:{}:", mint_account, type_str);
+        let mut prefixes = vec![format!("or:{}:{}:", mint_account, type_str)];
+        if include_expired {
+            prefixes.push(format!("ox:{}:{}:", mint_account, type_str));
+        }
         let mut orders = Vec::new();
 
-        let iter = self
-            .db
-            .iterator(IteratorMode::From(prefix.as_bytes(), Direction::Forward));
+        for prefix in &prefixes {
+            let iter = self.prefix_scan(prefix);
 
-        for item in iter {
-            let (key, value) = item?;
-            let key_str = String::from_utf8_lossy(&key);
+            for (scanned, item) in iter.enumerate() {
+                if (scanned + 1) % SCAN_YIELD_KEYS == 0 {
+                    tokio::task::yield_now().await;
+                }
+                let (key, value) = item?;
+                let key_str = String::from_utf8_lossy(&key);
 
-            // Check if still matches prefix
-            if !key_str.starts_with(&prefix) {
-                break;
-            }
+                // Check if still matches prefix
+                if !key_str.starts_with(prefix.as_str()) {
+                    break;
+                }
 
-            // Parse order data
-            match serde_json::from_slice::<OrderData>(&value) {
-                Ok(order_data) => orders.push(order_data),
-                Err(e) => {
-                    error!("❌ Failed to parse order data: {}, key: {}", e, key_str);
-                    continue;
+                // Parse order data
+                match serde_json::from_slice::<OrderData>(&value) {
+                    Ok(order_data) => orders.push(order_data),
+                    Err(e) => {
+                        error!("❌ Failed to parse order data: {}, key: {}", e, key_str);
+                        continue;
+                    }
                 }
             }
         }
 
         // Sort orders based on lock_lp_start_price
-        match order_type.as_str() {
-            "up_orders" => {
+        match side {
+            OrderType::Short => {
                 // For up_orders: sort by lock_lp_start_price ascending (small to large)
                 orders.sort_by(|a, b| a.lock_lp_start_price.cmp(&b.lock_lp_start_price));
             }
-            "down_orders" => {
+            OrderType::Long => {
                 // For down_orders: sort by lock_lp_start_price descending (large to small)
                 orders.sort_by(|a, b| b.lock_lp_start_price.cmp(&a.lock_lp_start_price));
             }
-            _ => {} // Should never reach here due to check above
         }
 
-        let total = orders.len();
+        Ok(orders)
+    }
 
-        let offset = (page - 1) * limit;
-        let has_prev = page > 1;
-        let has_next = offset + limit < total;
+    /// Aggregate sorted orders into price levels by lock_lp_start_price
+    fn aggregate_order_levels(orders: &[OrderData], depth: usize) -> Vec<OrderBookLevel> {
+        let mut levels: Vec<OrderBookLevel> = Vec::new();
+
+        for order in orders {
+            // Orders are sorted by price, so equal prices are always adjacent
+            match levels.last_mut() {
+                Some(level) if level.price == order.lock_lp_start_price => {
+                    level.position_asset_amount = level
+                        .position_asset_amount
+                        .saturating_add(order.position_asset_amount);
+                    level.order_count += 1;
+                }
+                _ => {
+                    if levels.len() >= depth {
+                        break;
+                    }
+                    levels.push(OrderBookLevel {
+                        price: order.lock_lp_start_price,
+                        position_asset_amount: order.position_asset_amount,
+                        order_count: 1,
+                    });
+                }
+            }
+        }
 
-        // Apply pagination
-        let orders = orders
-            .into_iter()
-            .skip(offset)
-            .take(limit)
-            .collect::<Vec<_>>();
+        levels
+    }
 
-        Ok(OrderQueryResponse {
-            orders,
-            total,
-            order_type: order_type.clone(),
+    /// Query aggregated order book depth for a mint
+    pub async fn query_order_book(&self, query: OrderBookQuery) -> Result<OrderBookResponse> {
+        let mint_account = &query.mint_account;
+        let depth = query.depth.unwrap_or(50);
+
+        debug!(
+            "🔍 Querying order book, mint: {}, depth: {}",
+            mint_account, depth
+        );
+
+        let down_orders = self
+            .load_sorted_orders(mint_account, OrderType::Long, false)
+            .await?;
+        let up_orders = self
+            .load_sorted_orders(mint_account, OrderType::Short, false)
+            .await?;
+
+        Ok(OrderBookResponse {
             mint_account: mint_account.clone(),
-            page,
-            limit,
-            has_next,
-            has_prev,
+            longs: Self::aggregate_order_levels(&down_orders, depth),
+            shorts: Self::aggregate_order_levels(&up_orders, depth),
+            depth,
         })
     }
 
-    /// Query user transaction information
-    pub async fn query_user_transactions(&self, query: UserQuery) -> Result<UserQueryResponse> {
-        let user = &query.user;
-        let mint_account = &query.mint_account;
-        let page = query.page.unwrap_or(1);
-        let limit = query.limit.unwrap_or(50);
-        let order_by = query.order_by.unwrap_or_else(|| "slot_desc".to_string());
+    /// Project a trade-like event into a compact tick; other events yield None
+    fn trade_tick_from_event(event: &SpinPetEvent) -> Option<TradeTick> {
+        match event {
+            SpinPetEvent::BuySell(e) => Some(TradeTick {
+                price: e.latest_price,
+                sol_amount: e.sol_amount,
+                is_buy: e.is_buy,
+                ts: e.timestamp.timestamp(),
+            }),
+            // Opening a long (order_type 1) buys, opening a short sells
+            SpinPetEvent::LongShort(e) => Some(TradeTick {
+                price: e.latest_price,
+                sol_amount: e.margin_sol_amount,
+                is_buy: OrderType::from_raw(e.order_type).is_long(),
+                ts: e.timestamp.timestamp(),
+            }),
+            // Closing a long sells, closing a short buys back
+            SpinPetEvent::FullClose(e) => Some(TradeTick {
+                price: e.latest_price,
+                sol_amount: e.final_sol_amount,
+                is_buy: !e.is_close_long,
+                ts: e.timestamp.timestamp(),
+            }),
+            SpinPetEvent::PartialClose(e) => Some(TradeTick {
+                price: e.latest_price,
+                sol_amount: e.final_sol_amount,
+                is_buy: !e.is_close_long,
+                ts: e.timestamp.timestamp(),
+            }),
+            _ => None,
+        }
+    }
 
-        debug!("🔍 Querying user transaction information, user: {}, mint: {:?}, page: {}, limit: {}, order: {}", 
-               user, mint_account, page, limit, order_by);
+    /// Query the most recent trades for a mint in compact form
+    pub async fn query_recent_trades(
+        &self,
+        mint_account: &str,
+        limit: usize,
+    ) -> Result<RecentTradesResponse> {
+        let prefix = format!("tr:{}:", mint_account);
+        let trade_types = [
+            EVENT_TYPE_BUY_SELL,
+            EVENT_TYPE_LONG_SHORT,
+            EVENT_TYPE_FULL_CLOSE,
+            EVENT_TYPE_PARTIAL_CLOSE,
+        ];
 
-        // Build search prefix
-        let prefix = if let Some(mint) = mint_account {
-            format!("us:{}:{}:", user, mint)
-        } else {
-            format!("us:{}:", user)
-        };
+        debug!(
+            "🔍 Querying recent trades, mint: {}, limit: {}",
+            mint_account, limit
+        );
 
-        let mut all_transactions = Vec::new();
+        // Walk backwards from the newest slot, deserializing only trade event types
         let iter = self
             .db
-            .iterator(IteratorMode::From(prefix.as_bytes(), Direction::Forward));
+            .iterator_opt(IteratorMode::End, self.scan_read_options(&prefix));
+        let mut trades = Vec::new();
+
+        for (scanned, item) in iter.enumerate() {
+            if trades.len() >= limit {
+                break;
+            }
+            if (scanned + 1) % SCAN_YIELD_KEYS == 0 {
+                tokio::task::yield_now().await;
+            }
 
-        for item in iter {
             let (key, value) = item?;
             let key_str = String::from_utf8_lossy(&key);
 
-            // Check if still matches prefix
-            if !key_str.starts_with(&prefix) {
-                break;
+            // Key format: tr:{mint}:{slot}:{log_index}:{event_type}:{signature}
+            let event_type = Self::event_key_type(&key_str[prefix.len()..]);
+            if !trade_types.contains(&event_type) {
+                continue;
             }
 
-            // Parse user transaction data
-            match serde_json::from_slice::<UserTransactionData>(&value) {
-                Ok(transaction_data) => {
-                    all_transactions.push(transaction_data);
+            match serde_json::from_slice::<SpinPetEvent>(&value) {
+                Ok(event) => trades.extend(Self::trade_tick_from_event(&event)),
+                Err(e) => {
+                    error!("❌ Failed to parse event data: {}, key: {}", e, key_str);
                 }
+            }
+        }
+
+        Ok(RecentTradesResponse {
+            mint_account: mint_account.to_string(),
+            trades,
+        })
+    }
+
+    /// Count a mint's events into `bucket_secs` buckets over `[from, to)` using stored event timestamps
+    pub async fn query_event_histogram(
+        &self,
+        mint_account: &str,
+        bucket_secs: u64,
+        from: i64,
+        to: i64,
+    ) -> Result<ActivityHistogramResponse> {
+        let prefix = format!("tr:{}:", mint_account);
+        let bucket_secs = bucket_secs.max(1);
+        let width = bucket_secs as i64;
+
+        debug!(
+            "🔍 Querying event histogram, mint: {}, bucket: {}s, range: {}..{}",
+            mint_account, bucket_secs, from, to
+        );
+
+        // Zero-filled buckets aligned to multiples of bucket_secs
+        let first_bucket = from - from.rem_euclid(width);
+        let mut buckets: Vec<ActivityBucket> = (first_bucket..to)
+            .step_by(bucket_secs as usize)
+            .map(|bucket_ts| ActivityBucket {
+                bucket_ts,
+                count: 0,
+            })
+            .collect();
+
+        let max_scan_keys = self.config.database.max_scan_keys;
+        let mut scanned = 0usize;
+        let mut truncated = false;
+
+        let iter = self
+            .db
+            .iterator_opt(IteratorMode::End, self.scan_read_options(&prefix));
+
+        for item in iter {
+            if max_scan_keys > 0 && scanned >= max_scan_keys {
+                truncated = true;
+                break;
+            }
+            scanned += 1;
+            if scanned % SCAN_YIELD_KEYS == 0 {
+                tokio::task::yield_now().await;
+            }
+
+            let (key, value) = item?;
+            let ts = match serde_json::from_slice::<SpinPetEvent>(&value) {
+                Ok(event) => event.timestamp().timestamp(),
                 Err(e) => {
                     error!(
-                        "❌ Failed to parse user transaction data: {}, key: {}",
-                        e, key_str
+                        "❌ Failed to parse event data: {}, key: {}",
+                        e,
+                        String::from_utf8_lossy(&key)
                     );
                     continue;
                 }
+            };
+            if ts < from || ts >= to {
+                continue;
             }
-        }
 
-        // Sort by slot
-        match order_by.as_str() {
-            "slot_asc" => {
-                all_transactions.sort_by(|a, b| a.slot.cmp(&b.slot));
-            }
-            "slot_desc" => {
-                all_transactions.sort_by(|a, b| b.slot.cmp(&a.slot));
-            }
-            _ => {
-                // Default sort by slot descending
-                all_transactions.sort_by(|a, b| b.slot.cmp(&a.slot));
+            let index = ((ts - first_bucket) / width) as usize;
+            if let Some(bucket) = buckets.get_mut(index) {
+                bucket.count += 1;
             }
         }
 
-        let total = all_transactions.len();
-        let offset = (page - 1) * limit;
-        let has_prev = page > 1;
+        if truncated {
+            warn!(
+                "⚠️ Event histogram for {} hit max_scan_keys ({}), older events are not counted",
+                mint_account, max_scan_keys
+            );
+        }
+
+        Ok(ActivityHistogramResponse {
+            mint_account: mint_account.to_string(),
+            bucket_secs,
+            from,
+            to,
+            buckets,
+            truncated,
+        })
+    }
+
+    /// Latest price, 24h volume and price change, open orders by side and liquidations of a
+    /// mint in one read; None when the mint has no detail record
+    pub async fn query_ticker(&self, mint_account: &str) -> Result<Option<MintTickerResponse>> {
+        let Some(detail) = self.get_mint_detail(mint_account)? else {
+            return Ok(None);
+        };
+        let window_start = Utc::now().timestamp() - 86_400;
+
+        // Volume: BuySell events newest first, until one is older than the window
+        let prefix = format!("tr:{}:", mint_account);
+        let max_scan_keys = self.config.database.max_scan_keys;
+        let mut scanned = 0usize;
+        let mut volume_24h = 0u64;
+        let mut trades_24h = 0u64;
+        let mut truncated = false;
+        for item in self
+            .db
+            .iterator_opt(IteratorMode::End, self.scan_read_options(&prefix))
+        {
+            if max_scan_keys > 0 && scanned >= max_scan_keys {
+                truncated = true;
+                break;
+            }
+            scanned += 1;
+            if scanned % SCAN_YIELD_KEYS == 0 {
+                tokio::task::yield_now().await;
+            }
+
+            let (key, value) = item?;
+            let key_str = String::from_utf8_lossy(&key);
+            if Self::event_key_type(&key_str[prefix.len()..]) != EVENT_TYPE_BUY_SELL {
+                continue;
+            }
+            match serde_json::from_slice::<SpinPetEvent>(&value) {
+                Ok(SpinPetEvent::BuySell(e)) => {
+                    if e.timestamp.timestamp() < window_start {
+                        break;
+                    }
+                    volume_24h = volume_24h.saturating_add(e.sol_amount);
+                    trades_24h += 1;
+                }
+                Ok(_) => {}
+                Err(e) => error!("❌ Failed to parse event data: {}, key: {}", e, key_str),
+            }
+        }
+
+        // 24h open: close of the last 5m candle before the window start, which is the price the
+        // window started at; only a mint without earlier candles falls back to the first open
+        let kline_prefix = format!("{}:{}:", KLINE_INTERVAL_5M, mint_account);
+        let start_key = self.generate_kline_key(
+            KLINE_INTERVAL_5M,
+            mint_account,
+            self.calculate_time_bucket(window_start.max(0) as u64, KLINE_INTERVAL_5M),
+        );
+        let mut previous_close = None;
+        for item in self.db.iterator_opt(
+            IteratorMode::From(start_key.as_bytes(), Direction::Reverse),
+            self.scan_read_options(&kline_prefix),
+        ) {
+            let (key, value) = item?;
+            if !key.starts_with(kline_prefix.as_bytes()) {
+                break;
+            }
+            if *key == *start_key.as_bytes() {
+                continue;
+            }
+            previous_close = Some(serde_json::from_slice::<KlineData>(&value)?.close);
+            break;
+        }
+        let open_price_24h = match previous_close {
+            Some(close) => Some(close),
+            None => match self
+                .db
+                .iterator_opt(
+                    IteratorMode::From(start_key.as_bytes(), Direction::Forward),
+                    self.scan_read_options(&kline_prefix),
+                )
+                .next()
+            {
+                Some(item) => Some(serde_json::from_slice::<KlineData>(&item?.1)?.open),
+                None => None,
+            },
+        };
+        let price_change_24h_pct = match (open_price_24h, detail.latest_price) {
+            (Some(open), Some(latest)) if open > 0.0 => {
+                Some((self.convert_price_to_f64(latest) - open) / open * 100.0)
+            }
+            _ => None,
+        };
+
+        let open_long_orders = self
+            .count_open_orders(mint_account, OrderType::Long)
+            .await?;
+        let open_short_orders = self
+            .count_open_orders(mint_account, OrderType::Short)
+            .await?;
+
+        Ok(Some(MintTickerResponse {
+            mint_account: mint_account.to_string(),
+            latest_price: detail.latest_price,
+            latest_trade_time: detail.latest_trade_time,
+            volume_24h,
+            trades_24h,
+            open_price_24h,
+            price_change_24h_pct,
+            open_long_orders,
+            open_short_orders,
+            total_force_liquidations: detail.total_force_liquidations,
+            truncated,
+        }))
+    }
+
+    /// Number of open orders of one side of a mint
+    async fn count_open_orders(&self, mint_account: &str, side: OrderType) -> Result<usize> {
+        let prefix = format!("or:{}:{}:", mint_account, side.key_segment());
+        let mut count = 0;
+        for item in self.prefix_scan(&prefix) {
+            item?;
+            count += 1;
+            if count % SCAN_YIELD_KEYS == 0 {
+                tokio::task::yield_now().await;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Query order information
+    pub async fn query_orders(&self, query: OrderQuery) -> Result<OrderQueryResponse> {
+        let mint_account = &query.mint_account;
+        let order_type = &query.order_type;
+        let page = query.page.unwrap_or(1);
+        let limit = query.limit.unwrap_or(50);
+
+        debug!(
+            "🔍 Querying order information, mint: {}, type: {}, page: {}, limit: {}",
+            mint_account, order_type, page, limit
+        );
+
+        let side = OrderType::from_query_name(order_type)
+            .ok_or_else(|| anyhow::anyhow!("Invalid order type: {}", order_type))?;
+        let orders = self
+            .load_sorted_orders(mint_account, side, query.include_expired)
+            .await?;
+
+        let total = orders.len();
+
+        let offset = (page - 1) * limit;
+        let has_prev = page > 1;
         let has_next = offset + limit < total;
 
-        // Pagination
-        let transactions = all_transactions
+        // Apply pagination
+        let orders = orders
             .into_iter()
             .skip(offset)
             .take(limit)
             .collect::<Vec<_>>();
 
-        Ok(UserQueryResponse {
-            transactions,
+        Ok(OrderQueryResponse {
+            orders,
             total,
+            order_type: order_type.clone(),
+            mint_account: mint_account.clone(),
             page,
             limit,
             has_next,
             has_prev,
-            user: user.clone(),
-            mint_account: mint_account.clone(),
         })
     }
 
-    /// Get mint detail information for a mint account
-    fn get_mint_detail(&self, mint_account: &str) -> Result<Option<MintDetailData>> {
-        let key = self.generate_mint_detail_key(mint_account);
-        if let Some(data) = self.db.get(key.as_bytes())? {
-            match serde_json::from_slice::<MintDetailData>(&data) {
-                Ok(detail) => Ok(Some(detail)),
+    /// Move open orders whose end_time is before `now` (unix seconds) to the expired set
+    pub async fn expire_orders(&self, now: i64) -> Result<usize> {
+        self.ensure_writable("order expiry")?;
+        let prefix = "or:";
+        // end_time 0 means the order never carried one
+        let past_end_time =
+            |order: &OrderData| order.end_time != 0 && i64::from(order.end_time) < now;
+
+        let mut candidates = Vec::new();
+        for (scanned, item) in self.prefix_scan(prefix).enumerate() {
+            if (scanned + 1) % SCAN_YIELD_KEYS == 0 {
+                tokio::task::yield_now().await;
+            }
+            let (key, value) = item?;
+            let key_str = String::from_utf8_lossy(&key);
+
+            // Check if still matches prefix
+            if !key_str.starts_with(prefix) {
+                break;
+            }
+
+            match serde_json::from_slice::<OrderData>(&value) {
+                Ok(order) if past_end_time(&order) => candidates.push(key_str.to_string()),
+                Ok(_) => {}
+                Err(e) => error!("❌ Failed to parse order data: {}, key: {}", e, key_str),
+            }
+        }
+
+        // Each order is re-read under its mint lock, which store_event holds while writing order
+        // changes, so an order closed or updated since the scan isn't resurrected or overwritten
+        let mut expired = 0;
+        for (checked, key) in candidates.into_iter().enumerate() {
+            if (checked + 1) % SCAN_YIELD_KEYS == 0 {
+                tokio::task::yield_now().await;
+            }
+            // Key format: or:{mint_account}:{up|dn}:{order_pda}
+            let rest = &key[prefix.len()..];
+            let mint_account = rest.split(':').next().unwrap_or_default();
+            let _guard = self.mint_lock(mint_account).lock().await;
+
+            let Some(value) = self.db.get(key.as_bytes())? else {
+                continue;
+            };
+            let mut order = match serde_json::from_slice::<OrderData>(&value) {
+                Ok(order) if past_end_time(&order) => order,
+                _ => continue,
+            };
+            order.status = OrderStatus::Expired;
+            let order_value = serde_json::to_vec(&order)?;
+
+            let mut batch = rocksdb::WriteBatch::default();
+            batch.delete(key.as_bytes());
+            batch.put(format!("ox:{}", rest).as_bytes(), &order_value);
+
+            // Keep the user's view of the order in sync
+            let user_order_key =
+                self.generate_user_order_key(&order.user, mint_account, &order.order_pda);
+            if self.db.get(user_order_key.as_bytes())?.is_some() {
+                batch.put(user_order_key.as_bytes(), &order_value);
+            }
+            self.db.write(batch)?;
+            expired += 1;
+        }
+
+        if expired > 0 {
+            info!("⏰ Moved {} orders past their end_time to expired", expired);
+        }
+        Ok(expired)
+    }
+
+    /// Scan a global `{prefix}{slot:010}:{signature}:...` index for a polling feed, newest first.
+    /// Without `since` the newest `limit` entries are returned; with it, only entries after the
+    /// cursor, oldest `limit` of them, so repeated polls page forward without gaps. Entries of one
+    /// transaction are never split across pages. Returns the entries and the next cursor.
+    fn scan_feed(
+        &self,
+        prefix: &str,
+        limit: usize,
+        since: Option<(u64, &str)>,
+    ) -> Result<(Vec<(Box<[u8]>, Box<[u8]>)>, Option<String>)> {
+        // Key suffix format: {slot:010}:{signature}:...
+        let cursor_of = |key: &[u8]| -> Option<String> {
+            let key_str = String::from_utf8_lossy(key);
+            let mut parts = key_str.get(prefix.len()..)?.split(':');
+            let slot = parts.next()?.parse::<u64>().ok()?;
+            Some(format!("{}:{}", slot, parts.next()?))
+        };
+
+        let mut entries = Vec::new();
+        match since {
+            None => {
+                let iter = self
+                    .db
+                    .iterator_opt(IteratorMode::End, self.scan_read_options(prefix));
+                for item in iter {
+                    if entries.len() >= limit {
+                        break;
+                    }
+                    entries.push(item?);
+                }
+            }
+            Some((slot, signature)) => {
+                // ';' sorts right after ':', so this skips every key of the cursor transaction
+                let start = format!("{}{:010}:{};", prefix, slot, signature);
+                let iter = self.db.iterator_opt(
+                    IteratorMode::From(start.as_bytes(), Direction::Forward),
+                    self.scan_read_options(prefix),
+                );
+                let mut last_cursor = None;
+                for item in iter {
+                    let (key, value) = item?;
+                    let cursor = cursor_of(&key);
+                    if entries.len() >= limit && cursor != last_cursor {
+                        break;
+                    }
+                    last_cursor = cursor;
+                    entries.push((key, value));
+                }
+                entries.reverse();
+            }
+        }
+
+        let next = match entries.first() {
+            Some((key, _)) => cursor_of(key),
+            None => since.map(|(slot, signature)| format!("{}:{}", slot, signature)),
+        };
+        Ok((entries, next))
+    }
+
+    /// Most recent events across all mints, newest first, optionally only those after `since`
+    pub async fn query_recent_events(
+        &self,
+        limit: usize,
+        since: Option<(u64, &str)>,
+    ) -> Result<RecentEventsResponse> {
+        debug!(
+            "🔍 Querying recent events, limit: {}, since: {:?}",
+            limit, since
+        );
+
+        let (entries, next) = self.scan_feed("slotidx:", limit, since)?;
+        let mut events = Vec::with_capacity(entries.len());
+        for (key, value) in entries {
+            match serde_json::from_slice::<SpinPetEvent>(&value) {
+                Ok(event) => events.push(event),
                 Err(e) => {
                     error!(
-                        "❌ Failed to parse mint detail data: {}, mint: {}",
-                        e, mint_account
+                        "❌ Failed to parse event data: {}, key: {}",
+                        e,
+                        String::from_utf8_lossy(&key)
                     );
-                    Ok(None)
                 }
             }
-        } else {
-            Ok(None)
         }
+
+        Ok(RecentEventsResponse { events, next })
     }
 
-    /// Enrich order data with token information
-    fn enrich_order_with_token_info(&self, mut order: OrderData) -> OrderData {
-        match self.get_mint_detail(&order.mint) {
-            Ok(Some(mint_detail)) => {
-                // Fill token information from mint detail
-                order.latest_price = mint_detail.latest_price.unwrap_or(0);
-                order.latest_trade_time = mint_detail.latest_trade_time.unwrap_or(0);
+    /// Global index settings, for endpoints that must report a disabled index
+    pub fn retention_config(&self) -> &crate::config::RetentionConfig {
+        &self.config.retention
+    }
 
-                // Prioritize MintDetailData.name/symbol over uri_data
-                order.name = mint_detail.name.unwrap_or_else(|| {
-                    mint_detail
-                        .uri_data
-                        .as_ref()
-                        .and_then(|uri| uri.name.clone())
-                        .unwrap_or_default()
-                });
+    /// Order lifecycle settings
+    pub fn order_config(&self) -> &crate::config::OrderConfig {
+        &self.config.orders
+    }
 
-                order.symbol = mint_detail.symbol.unwrap_or_else(|| {
-                    mint_detail
-                        .uri_data
-                        .as_ref()
-                        .and_then(|uri| uri.symbol.clone())
-                        .unwrap_or_default()
-                });
+    /// Kline dual-write writer, when `tsdb.enabled`
+    pub fn tsdb_writer(&self) -> Option<Arc<TsdbWriter>> {
+        self.tsdb.clone()
+    }
 
-                order.image = mint_detail
-                    .uri_data
-                    .as_ref()
-                    .and_then(|uri| uri.image.clone())
-                    .unwrap_or_default();
-            }
-            Ok(None) => {
-                // Set default values if mint detail not found
-                order.latest_price = 0;
-                order.latest_trade_time = 0;
-                order.name = String::new();
-                order.symbol = String::new();
-                order.image = String::new();
-            }
-            Err(e) => {
-                error!("❌ Failed to get mint detail for {}: {}", order.mint, e);
-                // Set default values on error
-                order.latest_price = 0;
-                order.latest_trade_time = 0;
-                order.name = String::new();
-                order.symbol = String::new();
-                order.image = String::new();
+    /// Newest slot among stored events
+    pub fn latest_slot(&self) -> u64 {
+        self.latest_slot.load(Ordering::SeqCst)
+    }
+
+    /// Raise the newest stored slot, persisting it with the event's batch
+    fn advance_latest_slot(&self, batch: &mut rocksdb::WriteBatch, slot: u64) {
+        if self.latest_slot.fetch_max(slot, Ordering::SeqCst) < slot {
+            batch.put(LATEST_SLOT_KEY.as_bytes(), slot.to_string().as_bytes());
+        }
+    }
+
+    /// Delete keys under `prefix` whose slot (the `slot_field`-th `:` segment after the prefix)
+    /// is older than `retention_slots` behind the newest stored slot; 0 keeps everything
+    fn prune_before(&self, prefix: &str, slot_field: usize, retention_slots: u64) -> Result<usize> {
+        let cutoff = self.latest_slot().saturating_sub(retention_slots);
+        if retention_slots == 0 || cutoff == 0 {
+            return Ok(0);
+        }
+
+        let mut batch = rocksdb::WriteBatch::default();
+        let mut pruned = 0;
+        for item in self.prefix_scan(prefix) {
+            let (key, _) = item?;
+            let key_str = String::from_utf8_lossy(&key);
+            let slot = key_str[prefix.len()..]
+                .split(':')
+                .nth(slot_field)
+                .and_then(|s| s.parse::<u64>().ok());
+            if matches!(slot, Some(slot) if slot < cutoff) {
+                batch.delete(&key);
+                pruned += 1;
+                if batch.len() >= PRUNE_BATCH_SIZE {
+                    self.db.write(std::mem::take(&mut batch))?;
+                }
             }
         }
-        order
+        if !batch.is_empty() {
+            self.db.write(batch)?;
+        }
+        Ok(pruned)
     }
 
-    /// Query user order information
-    pub async fn query_user_orders(&self, query: UserOrderQuery) -> Result<UserOrderQueryResponse> {
-        let user = &query.user;
-        let mint_account = &query.mint_account;
-        let page = query.page.unwrap_or(1);
-        let limit = query.limit.unwrap_or(50);
-        let order_by = query
-            .order_by
-            .unwrap_or_else(|| "start_time_desc".to_string());
+    /// Drop events and global index entries past their configured retention
+    pub async fn prune_retention(&self) -> Result<RetentionPruneResult> {
+        let retention = &self.config.retention;
+        let result = RetentionPruneResult {
+            // Key format: tr:{mint}:{slot}:{log_index}:{event_type}:{signature}, tm: alike
+            events: self.prune_before("tr:", 1, retention.events_retention_slots)?
+                + self.prune_before("tm:", 1, retention.events_retention_slots)?,
+            // Key format: slotidx:{slot}:{signature}:{event_type}
+            slot_index: self.prune_before(
+                "slotidx:",
+                0,
+                retention.index_retention_slots(&retention.slot_index),
+            )?,
+            // Key format: liq:{slot}:{signature}:{order_pda}
+            liquidations: self.prune_before(
+                "liq:",
+                0,
+                retention.index_retention_slots(&retention.liquidations),
+            )?,
+            // Key format: opda:{order_pda}:{slot}:{log_index}:{event_type}:{mint_account}:{signature}
+            order_timeline: self.prune_before(
+                "opda:",
+                1,
+                retention.index_retention_slots(&retention.order_timeline),
+            )?,
+            mint_snapshots: match retention.mint_snapshot_retention_secs {
+                0 => 0,
+                secs => {
+                    self.prune_mint_snapshots(Utc::now().timestamp().saturating_sub(secs as i64))?
+                }
+            },
+        };
 
-        debug!("🔍 Querying user order information, user: {}, mint: {:?}, page: {}, limit: {}, order: {}", 
-               user, mint_account, page, limit, order_by);
+        if result != RetentionPruneResult::default() {
+            info!("🧹 Retention sweep pruned {:?}", result);
+        }
+        Ok(result)
+    }
 
-        // Build search prefix
-        let prefix = if let Some(mint) = mint_account {
-            format!("uo:{}:{}:", user, mint)
-        } else {
-            format!("uo:{}:", user)
+    /// Config of a rebuildable global index, by its `retention` table name
+    pub fn rebuildable_index(&self, index: &str) -> Result<GlobalIndexConfig> {
+        let retention = &self.config.retention;
+        match index {
+            "slot_index" => Ok(retention.slot_index),
+            "liquidations" => Ok(retention.liquidations),
+            "order_timeline" => Ok(retention.order_timeline),
+            _ => Err(anyhow::anyhow!(
+                "unknown index {}, rebuildable indexes are: {}",
+                index,
+                REBUILDABLE_INDEXES.join(", ")
+            )),
+        }
+    }
+
+    /// Entry an event contributes to `index`, if any. `owners` maps order PDAs to the users of
+    /// their opening events, as liquidated orders are already deleted
+    fn index_entry(
+        &self,
+        index: &str,
+        event: &SpinPetEvent,
+        owners: &HashMap<String, String>,
+    ) -> Result<Option<(String, Vec<u8>)>> {
+        Ok(match (index, event) {
+            ("slot_index", _) => Some((
+                self.generate_slot_index_key(event),
+                serde_json::to_vec(event)?,
+            )),
+            ("order_timeline", _) => match self.generate_order_timeline_key(event) {
+                Some(timeline_key) => Some((timeline_key, serde_json::to_vec(event)?)),
+                None => None,
+            },
+            ("liquidations", SpinPetEvent::ForceLiquidate(e)) => {
+                let liquidation = LiquidationRecord {
+                    mint_account: e.mint_account.clone(),
+                    order_pda: e.order_pda.clone(),
+                    owner: owners.get(&e.order_pda).cloned(),
+                    liquidator: e.payer.clone(),
+                    slot: e.slot,
+                    signature: e.signature.clone(),
+                    ts: e.timestamp.timestamp(),
+                };
+                Some((
+                    self.generate_liquidation_key(e.slot, &e.signature, &e.order_pda),
+                    serde_json::to_vec(&liquidation)?,
+                ))
+            }
+            _ => None,
+        })
+    }
+
+    /// Owners of the orders opened under the mint of a `tr:` rebuild cursor, up to the cursor.
+    /// Events are scanned mint by mint, so only that mint's liquidations can follow an opening
+    /// event a resumed rebuild has already passed
+    fn order_owners_before(&self, cursor: &[u8]) -> Result<HashMap<String, String>> {
+        let mut owners = HashMap::new();
+        let cursor_str = String::from_utf8_lossy(cursor);
+        let Some(mint_account) = cursor_str
+            .strip_prefix("tr:")
+            .and_then(|rest| rest.split(':').next())
+        else {
+            return Ok(owners);
         };
-        let mut all_orders = Vec::new();
-        let iter = self
-            .db
-            .iterator(IteratorMode::From(prefix.as_bytes(), Direction::Forward));
 
-        for item in iter {
+        let prefix = format!("tr:{}:", mint_account);
+        for item in self.prefix_scan(&prefix) {
             let (key, value) = item?;
-            let key_str = String::from_utf8_lossy(&key);
-
-            // Check if still matches prefix
-            if !key_str.starts_with(&prefix) {
+            if &*key > cursor {
                 break;
             }
+            let key_str = String::from_utf8_lossy(&key);
+            if Self::event_key_type(&key_str[prefix.len()..]) != EVENT_TYPE_LONG_SHORT {
+                continue;
+            }
+            if let Ok(SpinPetEvent::LongShort(e)) = serde_json::from_slice(&value) {
+                owners.insert(e.order_pda, e.user);
+            }
+        }
+        Ok(owners)
+    }
 
-            // Parse order data - handle both old and new format
-            let mut order_data = match serde_json::from_slice::<serde_json::Value>(&value) {
-                Ok(json_value) => {
-                    // Try to parse as new format first
-                    if let Ok(order) = serde_json::from_value::<OrderData>(json_value.clone()) {
-                        order
-                    } else {
-                        // Parse as old format and add default token info
-                        match serde_json::from_value::<serde_json::Value>(json_value) {
-                            Ok(old_order) => {
-                                let new_order = OrderData {
-                                    order_type: old_order
-                                        .get("order_type")
-                                        .and_then(|v| v.as_u64())
-                                        .unwrap_or(0)
-                                        as u8,
-                                    mint: old_order
-                                        .get("mint")
-                                        .and_then(|v| v.as_str())
-                                        .unwrap_or("")
-                                        .to_string(),
-                                    user: old_order
-                                        .get("user")
-                                        .and_then(|v| v.as_str())
-                                        .unwrap_or("")
-                                        .to_string(),
-                                    lock_lp_start_price: old_order
-                                        .get("lock_lp_start_price")
-                                        .and_then(|v| v.as_str())
-                                        .and_then(|s| s.parse().ok())
-                                        .unwrap_or(0),
-                                    lock_lp_end_price: old_order
-                                        .get("lock_lp_end_price")
-                                        .and_then(|v| v.as_str())
-                                        .and_then(|s| s.parse().ok())
-                                        .unwrap_or(0),
-                                    lock_lp_sol_amount: old_order
-                                        .get("lock_lp_sol_amount")
-                                        .and_then(|v| v.as_u64())
-                                        .unwrap_or(0),
-                                    lock_lp_token_amount: old_order
-                                        .get("lock_lp_token_amount")
-                                        .and_then(|v| v.as_u64())
-                                        .unwrap_or(0),
-                                    start_time: old_order
-                                        .get("start_time")
-                                        .and_then(|v| v.as_u64())
-                                        .unwrap_or(0)
-                                        as u32,
-                                    end_time: old_order
-                                        .get("end_time")
-                                        .and_then(|v| v.as_u64())
-                                        .unwrap_or(0)
-                                        as u32,
-                                    margin_sol_amount: old_order
-                                        .get("margin_sol_amount")
-                                        .and_then(|v| v.as_u64())
-                                        .unwrap_or(0),
-                                    borrow_amount: old_order
-                                        .get("borrow_amount")
-                                        .and_then(|v| v.as_u64())
-                                        .unwrap_or(0),
-                                    position_asset_amount: old_order
-                                        .get("position_asset_amount")
-                                        .and_then(|v| v.as_u64())
-                                        .unwrap_or(0),
-                                    borrow_fee: old_order
-                                        .get("borrow_fee")
-                                        .and_then(|v| v.as_u64())
-                                        .unwrap_or(0)
-                                        as u16,
-                                    order_pda: old_order
-                                        .get("order_pda")
-                                        .and_then(|v| v.as_str())
-                                        .unwrap_or("")
-                                        .to_string(),
-                                    // Initialize new fields with defaults
-                                    latest_price: 0,
-                                    latest_trade_time: 0,
-                                    name: String::new(),
-                                    symbol: String::new(),
-                                    image: String::new(),
-                                };
-                                new_order
-                            }
-                            Err(e) => {
-                                error!(
-                                    "❌ Failed to parse old order format: {}, key: {}",
-                                    e, key_str
-                                );
-                                continue;
-                            }
+    /// Repopulate a global index from the `tr:` event records, e.g. after enabling it on an
+    /// existing DB. The scan position is saved every `retention.rebuild_batch_size` events, so
+    /// an interrupted rebuild resumes where it stopped. Events whose persistence policy skips
+    /// the `tr:` record can't be recovered. Fails while another rebuild of `index` is running
+    pub fn rebuild_index(&self, index: &str) -> Result<IndexRebuildResult> {
+        if !self.rebuildable_index(index)?.enabled {
+            return Err(anyhow::anyhow!("retention.{}.enabled is false", index));
+        }
+        if !self
+            .rebuilding_indexes
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(index.to_string())
+        {
+            return Err(anyhow::anyhow!("{} index rebuild already running", index));
+        }
+        let result = self.run_index_rebuild(index);
+        self.rebuilding_indexes
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(index);
+        result
+    }
+
+    /// Whether a rebuild of `index` is running
+    pub fn index_rebuild_running(&self, index: &str) -> bool {
+        self.rebuilding_indexes
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains(index)
+    }
+
+    fn run_index_rebuild(&self, index: &str) -> Result<IndexRebuildResult> {
+        self.ensure_writable("index rebuild")?;
+
+        let cursor_key = format!("{}{}", REBUILD_CURSOR_PREFIX, index);
+        let cursor = self.db.get(cursor_key.as_bytes())?;
+        let start = cursor.clone().unwrap_or_else(|| b"tr:".to_vec());
+        let mut result = IndexRebuildResult {
+            index: index.to_string(),
+            scanned: 0,
+            written: 0,
+            resumed: cursor.is_some(),
+        };
+        info!(
+            "🔨 Rebuilding {} index from {}",
+            index,
+            String::from_utf8_lossy(&start)
+        );
+
+        let batch_size = self.config.retention.rebuild_batch_size.max(1);
+        let mut owners = match &cursor {
+            Some(cursor) if index == "liquidations" => self.order_owners_before(cursor)?,
+            _ => HashMap::new(),
+        };
+        let mut batch = rocksdb::WriteBatch::default();
+        let iter = self.db.iterator_opt(
+            IteratorMode::From(start.as_slice(), Direction::Forward),
+            self.scan_read_options("tr:"),
+        );
+        for item in iter {
+            let (key, value) = item?;
+            if cursor.as_deref() == Some(&*key) {
+                continue;
+            }
+            result.scanned += 1;
+
+            match serde_json::from_slice::<SpinPetEvent>(&value) {
+                Ok(event) => {
+                    if index == "liquidations" {
+                        if let SpinPetEvent::LongShort(e) = &event {
+                            owners.insert(e.order_pda.clone(), e.user.clone());
                         }
                     }
+                    if let Some((index_key, index_value)) =
+                        self.index_entry(index, &event, &owners)?
+                    {
+                        batch.put(index_key.as_bytes(), index_value);
+                        result.written += 1;
+                    }
                 }
+                Err(e) => warn!(
+                    "⚠️ Skipping unparseable event {}: {}",
+                    String::from_utf8_lossy(&key),
+                    e
+                ),
+            }
+
+            // The cursor is written with the entries it covers; a low disk stops the rebuild
+            // there, to resume from the cursor later
+            if result.scanned % batch_size == 0 {
+                self.ensure_writable("index rebuild")?;
+                batch.put(cursor_key.as_bytes(), &key);
+                self.db.write(std::mem::take(&mut batch))?;
+                debug!(
+                    "🔨 {} index rebuild at {} ({} events scanned)",
+                    index,
+                    String::from_utf8_lossy(&key),
+                    result.scanned
+                );
+            }
+        }
+
+        batch.delete(cursor_key.as_bytes());
+        batch.delete(format!("{}{}", INDEX_DISABLED_PREFIX, index).as_bytes());
+        self.db.write(batch)?;
+        info!(
+            "✅ Rebuilt {} index: {} entries from {} events",
+            index, result.written, result.scanned
+        );
+        Ok(result)
+    }
+
+    /// Enabled global indexes missing events: disabled at an earlier startup or with an
+    /// interrupted rebuild. Disabled indexes are marked so enabling them later is noticed
+    pub fn pending_index_rebuilds(&self) -> Result<Vec<&'static str>> {
+        let mut pending = Vec::new();
+        for index in REBUILDABLE_INDEXES {
+            let disabled_key = format!("{}{}", INDEX_DISABLED_PREFIX, index);
+            if !self.rebuildable_index(index)?.enabled {
+                self.db.put(disabled_key.as_bytes(), b"1")?;
+            } else if self.db.get(disabled_key.as_bytes())?.is_some()
+                || self
+                    .db
+                    .get(format!("{}{}", REBUILD_CURSOR_PREFIX, index).as_bytes())?
+                    .is_some()
+            {
+                pending.push(index);
+            }
+        }
+        Ok(pending)
+    }
+
+    /// Most recent force liquidations across all mints, newest first, optionally only those after `since`
+    pub async fn query_recent_liquidations(
+        &self,
+        limit: usize,
+        since: Option<(u64, &str)>,
+    ) -> Result<RecentLiquidationsResponse> {
+        debug!(
+            "🔍 Querying recent liquidations, limit: {}, since: {:?}",
+            limit, since
+        );
+
+        let (entries, next) = self.scan_feed("liq:", limit, since)?;
+        let mut liquidations = Vec::with_capacity(entries.len());
+        for (key, value) in entries {
+            match serde_json::from_slice::<LiquidationRecord>(&value) {
+                Ok(liquidation) => liquidations.push(liquidation),
                 Err(e) => {
                     error!(
-                        "❌ Failed to parse user order data: {}, key: {}",
+                        "❌ Failed to parse liquidation data: {}, key: {}",
+                        e,
+                        String::from_utf8_lossy(&key)
+                    );
+                }
+            }
+        }
+
+        Ok(RecentLiquidationsResponse { liquidations, next })
+    }
+
+    /// All events of one order (open, partial closes, full close or liquidation) in slot order,
+    /// only those under `mint_account` when given
+    pub async fn query_order_timeline(
+        &self,
+        order_pda: &str,
+        mint_account: Option<&str>,
+    ) -> Result<OrderTimelineResponse> {
+        let prefix = format!("opda:{}:", order_pda);
+        debug!(
+            "🔍 Querying order timeline, order_pda: {}, mint: {:?}",
+            order_pda, mint_account
+        );
+
+        let mut mints = std::collections::BTreeSet::new();
+        let mut events = Vec::new();
+        for item in self.prefix_scan(&prefix) {
+            let (key, value) = item?;
+            match serde_json::from_slice::<SpinPetEvent>(&value) {
+                Ok(event) => {
+                    mints.insert(event.mint_account().to_string());
+                    if mint_account.is_none() || mint_account == Some(event.mint_account()) {
+                        events.push(event);
+                    }
+                }
+                Err(e) => {
+                    error!(
+                        "❌ Failed to parse event data: {}, key: {}",
+                        e,
+                        String::from_utf8_lossy(&key)
+                    );
+                }
+            }
+        }
+
+        Ok(OrderTimelineResponse {
+            order_pda: order_pda.to_string(),
+            mint_account: mint_account.map(str::to_string),
+            mints: mints.into_iter().collect(),
+            events,
+        })
+    }
+
+    /// Every event stored for one slot, across all mints, for reorg reconciliation
+    pub async fn query_events_by_slot(&self, slot: u64) -> Result<SlotEventsResponse> {
+        let prefix = format!("slotidx:{:010}:", slot);
+        debug!("🔍 Querying events by slot: {}", slot);
+
+        let mut events = Vec::new();
+        for item in self.prefix_scan(&prefix) {
+            let (key, value) = item?;
+            match serde_json::from_slice::<SpinPetEvent>(&value) {
+                Ok(event) => events.push(event),
+                Err(e) => {
+                    error!(
+                        "❌ Failed to parse event data: {}, key: {}",
+                        e,
+                        String::from_utf8_lossy(&key)
+                    );
+                }
+            }
+        }
+
+        Ok(SlotEventsResponse { slot, events })
+    }
+
+    /// Query user transaction information
+    pub async fn query_user_transactions(&self, query: UserQuery) -> Result<UserQueryResponse> {
+        let user = &query.user;
+        let mint_account = &query.mint_account;
+        let page = query.page.unwrap_or(1);
+        let limit = query.limit.unwrap_or(50);
+        let order_by = query.order_by.unwrap_or_else(|| "slot_desc".to_string());
+
+        debug!("🔍 Querying user transaction information, user: {}, mint: {:?}, page: {}, limit: {}, order: {}", 
+               user, mint_account, page, limit, order_by);
+
+        // Build search prefix
+        let prefix = if let Some(mint) = mint_account {
+            format!("us:{}:{}:", user, mint)
+        } else {
+            format!("us:{}:", user)
+        };
+
+        let mut all_transactions = Vec::new();
+        let iter = self.prefix_scan(&prefix);
+
+        for (scanned, item) in iter.enumerate() {
+            if (scanned + 1) % SCAN_YIELD_KEYS == 0 {
+                tokio::task::yield_now().await;
+            }
+            let (key, value) = item?;
+            let key_str = String::from_utf8_lossy(&key);
+
+            // Check if still matches prefix
+            if !key_str.starts_with(&prefix) {
+                break;
+            }
+
+            // Parse user transaction data
+            match serde_json::from_slice::<UserTransactionData>(&value) {
+                Ok(transaction_data) => {
+                    all_transactions.push(transaction_data);
+                }
+                Err(e) => {
+                    error!(
+                        "❌ Failed to parse user transaction data: {}, key: {}",
                         e, key_str
                     );
                     continue;
                 }
-            };
-
-            // Enrich with token information
-            order_data = self.enrich_order_with_token_info(order_data);
-            all_orders.push(order_data);
+            }
         }
 
-        // Sort by start_time
+        // Sort by slot
         match order_by.as_str() {
-            "start_time_asc" => {
-                all_orders.sort_by(|a, b| a.start_time.cmp(&b.start_time));
+            "slot_asc" => {
+                all_transactions.sort_by(|a, b| a.slot.cmp(&b.slot));
             }
-            "start_time_desc" => {
-                all_orders.sort_by(|a, b| b.start_time.cmp(&a.start_time));
+            "slot_desc" => {
+                all_transactions.sort_by(|a, b| b.slot.cmp(&a.slot));
             }
             _ => {
-                // Default sort by start_time descending
-                all_orders.sort_by(|a, b| b.start_time.cmp(&a.start_time));
+                // Default sort by slot descending
+                all_transactions.sort_by(|a, b| b.slot.cmp(&a.slot));
+            }
+        }
+
+        let total = all_transactions.len();
+        let offset = (page - 1) * limit;
+        let has_prev = page > 1;
+        let has_next = offset + limit < total;
+
+        // Pagination
+        let transactions = all_transactions
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .collect::<Vec<_>>();
+
+        Ok(UserQueryResponse {
+            transactions,
+            total,
+            page,
+            limit,
+            has_next,
+            has_prev,
+            user: user.clone(),
+            mint_account: mint_account.clone(),
+        })
+    }
+
+    /// Generate mint detail snapshot key
+    /// Format: insnap:{mint_account}:{ts:010}
+    fn generate_mint_snapshot_key(&self, mint_account: &str, ts: i64) -> String {
+        format!("insnap:{}:{:010}", mint_account, ts)
+    }
+
+    /// Snapshot the details of the mints updated since the previous snapshot at `ts` (Unix
+    /// seconds); returns the number of snapshots written
+    pub fn snapshot_mint_details(&self, ts: i64) -> Result<usize> {
+        self.ensure_writable("mint detail snapshot")?;
+        let mints = std::mem::take(
+            &mut *self
+                .snapshot_mints
+                .lock()
+                .unwrap_or_else(|e| e.into_inner()),
+        );
+
+        let mut batch = rocksdb::WriteBatch::default();
+        for mint_account in &mints {
+            if let Some(detail) = self.get_mint_detail(mint_account)? {
+                batch.put(
+                    self.generate_mint_snapshot_key(mint_account, ts).as_bytes(),
+                    serde_json::to_vec(&detail)?,
+                );
             }
         }
+        let written = batch.len();
+        if written > 0 {
+            self.db.write(batch)?;
+        }
+        Ok(written)
+    }
+
+    /// Snapshots of a mint's detail taken within `[from, to]` (Unix seconds), oldest first
+    pub async fn query_mint_detail_history(
+        &self,
+        mint_account: &str,
+        from: Option<i64>,
+        to: Option<i64>,
+    ) -> Result<MintDetailHistoryResponse> {
+        let prefix = format!("insnap:{}:", mint_account);
+        let start = self.generate_mint_snapshot_key(mint_account, from.unwrap_or(0).max(0));
+        let to = to.unwrap_or(i64::MAX);
+
+        let mut snapshots = Vec::new();
+        let iter = self.db.iterator_opt(
+            IteratorMode::From(start.as_bytes(), Direction::Forward),
+            self.scan_read_options(&prefix),
+        );
+        for (scanned, item) in iter.enumerate() {
+            if (scanned + 1) % SCAN_YIELD_KEYS == 0 {
+                tokio::task::yield_now().await;
+            }
+            let (key, value) = item?;
+            let key_str = String::from_utf8_lossy(&key);
+            let Some(ts) = key_str
+                .strip_prefix(&prefix)
+                .and_then(|ts| ts.parse::<i64>().ok())
+            else {
+                break;
+            };
+            if ts > to {
+                break;
+            }
+            match serde_json::from_slice::<MintDetailData>(&value) {
+                Ok(detail) => snapshots.push(MintDetailSnapshot { ts, detail }),
+                Err(e) => error!("❌ Failed to parse mint snapshot: {}, key: {}", e, key_str),
+            }
+        }
+
+        Ok(MintDetailHistoryResponse {
+            mint_account: mint_account.to_string(),
+            snapshots,
+        })
+    }
+
+    /// Delete `insnap:` snapshots taken before `cutoff` (Unix seconds)
+    fn prune_mint_snapshots(&self, cutoff: i64) -> Result<usize> {
+        let mut batch = rocksdb::WriteBatch::default();
+        let mut pruned = 0;
+        for item in self.prefix_scan("insnap:") {
+            let (key, _) = item?;
+            if !key.starts_with(b"insnap:") {
+                break;
+            }
+            // Key format: insnap:{mint}:{ts}
+            let key_str = String::from_utf8_lossy(&key);
+            let ts = key_str
+                .rsplit(':')
+                .next()
+                .and_then(|ts| ts.parse::<i64>().ok());
+            if matches!(ts, Some(ts) if ts < cutoff) {
+                batch.delete(&key);
+                pruned += 1;
+                if batch.len() >= PRUNE_BATCH_SIZE {
+                    self.db.write(std::mem::take(&mut batch))?;
+                }
+            }
+        }
+        if !batch.is_empty() {
+            self.db.write(batch)?;
+        }
+        Ok(pruned)
+    }
+
+    /// Get mint detail information for a mint account
+    fn get_mint_detail(&self, mint_account: &str) -> Result<Option<MintDetailData>> {
+        let key = self.generate_mint_detail_key(mint_account);
+        if let Some(data) = self.db.get(key.as_bytes())? {
+            match serde_json::from_slice::<MintDetailData>(&data) {
+                Ok(mut detail) => {
+                    self.apply_mint_counters(&mut detail)?;
+                    Ok(Some(detail))
+                }
+                Err(e) => {
+                    error!(
+                        "❌ Failed to parse mint detail data: {}, mint: {}",
+                        e, mint_account
+                    );
+                    Ok(None)
+                }
+            }
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Enrich order data with token information
+    fn enrich_order_with_token_info(&self, mut order: OrderData) -> OrderData {
+        match self.get_mint_detail(&order.mint) {
+            Ok(Some(mint_detail)) => {
+                // Fill token information from mint detail
+                order.latest_price = mint_detail.latest_price.unwrap_or(0);
+                order.latest_trade_time = mint_detail.latest_trade_time.unwrap_or(0);
+
+                // Prioritize MintDetailData.name/symbol over uri_data
+                order.name = mint_detail.name.unwrap_or_else(|| {
+                    mint_detail
+                        .uri_data
+                        .as_ref()
+                        .and_then(|uri| uri.name.clone())
+                        .unwrap_or_default()
+                });
+
+                order.symbol = mint_detail.symbol.unwrap_or_else(|| {
+                    mint_detail
+                        .uri_data
+                        .as_ref()
+                        .and_then(|uri| uri.symbol.clone())
+                        .unwrap_or_default()
+                });
+
+                order.image = mint_detail
+                    .uri_data
+                    .as_ref()
+                    .and_then(|uri| uri.image.clone())
+                    .unwrap_or_default();
+            }
+            Ok(None) => {
+                // Set default values if mint detail not found
+                order.latest_price = 0;
+                order.latest_trade_time = 0;
+                order.name = String::new();
+                order.symbol = String::new();
+                order.image = String::new();
+            }
+            Err(e) => {
+                error!("❌ Failed to get mint detail for {}: {}", order.mint, e);
+                // Set default values on error
+                order.latest_price = 0;
+                order.latest_trade_time = 0;
+                order.name = String::new();
+                order.symbol = String::new();
+                order.image = String::new();
+            }
+        }
+        order
+    }
+
+    /// Query user order information
+    pub async fn query_user_orders(&self, query: UserOrderQuery) -> Result<UserOrderQueryResponse> {
+        let user = &query.user;
+        let mint_account = &query.mint_account;
+        let page = query.page.unwrap_or(1);
+        let limit = query.limit.unwrap_or(50);
+        let order_by = query
+            .order_by
+            .unwrap_or_else(|| "start_time_desc".to_string());
+
+        debug!("🔍 Querying user order information, user: {}, mint: {:?}, page: {}, limit: {}, order: {}", 
+               user, mint_account, page, limit, order_by);
+
+        // Build search prefix
+        let prefix = if let Some(mint) = mint_account {
+            format!("uo:{}:{}:", user, mint)
+        } else {
+            format!("uo:{}:", user)
+        };
+        let mut all_orders = Vec::new();
+        let iter = self.prefix_scan(&prefix);
+
+        for (scanned, item) in iter.enumerate() {
+            if (scanned + 1) % SCAN_YIELD_KEYS == 0 {
+                tokio::task::yield_now().await;
+            }
+            let (key, value) = item?;
+            let key_str = String::from_utf8_lossy(&key);
+
+            // Check if still matches prefix
+            if !key_str.starts_with(&prefix) {
+                break;
+            }
+
+            // Parse order data - handle both old and new format
+            let mut order_data = match serde_json::from_slice::<serde_json::Value>(&value) {
+                Ok(json_value) => {
+                    // Try to parse as new format first
+                    if let Ok(order) = serde_json::from_value::<OrderData>(json_value.clone()) {
+                        order
+                    } else {
+                        // Parse as old format and add default token info
+                        match serde_json::from_value::<serde_json::Value>(json_value) {
+                            Ok(old_order) => {
+                                let new_order = OrderData {
+                                    order_type: old_order
+                                        .get("order_type")
+                                        .and_then(|v| v.as_u64())
+                                        .unwrap_or(0)
+                                        as u8,
+                                    mint: old_order
+                                        .get("mint")
+                                        .and_then(|v| v.as_str())
+                                        .unwrap_or("")
+                                        .to_string(),
+                                    user: old_order
+                                        .get("user")
+                                        .and_then(|v| v.as_str())
+                                        .unwrap_or("")
+                                        .to_string(),
+                                    lock_lp_start_price: old_order
+                                        .get("lock_lp_start_price")
+                                        .and_then(|v| v.as_str())
+                                        .and_then(|s| s.parse().ok())
+                                        .unwrap_or(0),
+                                    lock_lp_end_price: old_order
+                                        .get("lock_lp_end_price")
+                                        .and_then(|v| v.as_str())
+                                        .and_then(|s| s.parse().ok())
+                                        .unwrap_or(0),
+                                    lock_lp_sol_amount: old_order
+                                        .get("lock_lp_sol_amount")
+                                        .and_then(|v| v.as_u64())
+                                        .unwrap_or(0),
+                                    lock_lp_token_amount: old_order
+                                        .get("lock_lp_token_amount")
+                                        .and_then(|v| v.as_u64())
+                                        .unwrap_or(0),
+                                    start_time: old_order
+                                        .get("start_time")
+                                        .and_then(|v| v.as_u64())
+                                        .unwrap_or(0)
+                                        as u32,
+                                    end_time: old_order
+                                        .get("end_time")
+                                        .and_then(|v| v.as_u64())
+                                        .unwrap_or(0)
+                                        as u32,
+                                    margin_sol_amount: old_order
+                                        .get("margin_sol_amount")
+                                        .and_then(|v| v.as_u64())
+                                        .unwrap_or(0),
+                                    borrow_amount: old_order
+                                        .get("borrow_amount")
+                                        .and_then(|v| v.as_u64())
+                                        .unwrap_or(0),
+                                    position_asset_amount: old_order
+                                        .get("position_asset_amount")
+                                        .and_then(|v| v.as_u64())
+                                        .unwrap_or(0),
+                                    borrow_fee: old_order
+                                        .get("borrow_fee")
+                                        .and_then(|v| v.as_u64())
+                                        .unwrap_or(0)
+                                        as u16,
+                                    order_pda: old_order
+                                        .get("order_pda")
+                                        .and_then(|v| v.as_str())
+                                        .unwrap_or("")
+                                        .to_string(),
+                                    // Initialize new fields with defaults
+                                    latest_price: 0,
+                                    latest_trade_time: 0,
+                                    name: String::new(),
+                                    symbol: String::new(),
+                                    image: String::new(),
+                                    status: OrderStatus::Open,
+                                };
+                                new_order
+                            }
+                            Err(e) => {
+                                error!(
+                                    "❌ Failed to parse old order format: {}, key: {}",
+                                    e, key_str
+                                );
+                                continue;
+                            }
+                        }
+                    }
+                }
+                Err(e) => {
+                    error!(
+                        "❌ Failed to parse user order data: {}, key: {}",
+                        e, key_str
+                    );
+                    continue;
+                }
+            };
+
+            // Enrich with token information
+            order_data = self.enrich_order_with_token_info(order_data);
+            all_orders.push(order_data);
+        }
+
+        // Sort by start_time
+        match order_by.as_str() {
+            "start_time_asc" => {
+                all_orders.sort_by(|a, b| a.start_time.cmp(&b.start_time));
+            }
+            "start_time_desc" => {
+                all_orders.sort_by(|a, b| b.start_time.cmp(&a.start_time));
+            }
+            _ => {
+                // Default sort by start_time descending
+                all_orders.sort_by(|a, b| b.start_time.cmp(&a.start_time));
+            }
+        }
+
+        let total = all_orders.len();
+        let offset = (page - 1) * limit;
+        let has_prev = page > 1;
+        let has_next = offset + limit < total;
+
+        // Pagination
+        let orders = all_orders
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .collect::<Vec<_>>();
+
+        Ok(UserOrderQueryResponse {
+            orders,
+            total,
+            user: user.clone(),
+            mint_account: mint_account.clone(),
+            page,
+            limit,
+            has_next,
+            has_prev,
+        })
+    }
+
+    /// Sum a user's open orders by side without loading and enriching the full order list
+    pub async fn query_user_position_summary(
+        &self,
+        user: &str,
+    ) -> Result<UserPositionSummaryResponse> {
+        debug!("🔍 Querying user position summary, user: {}", user);
+
+        let prefix = format!("uo:{}:", user);
+        let mut summary = UserPositionSummaryResponse {
+            user: user.to_string(),
+            ..Default::default()
+        };
+        for (scanned, item) in self.prefix_scan(&prefix).enumerate() {
+            if (scanned + 1) % SCAN_YIELD_KEYS == 0 {
+                tokio::task::yield_now().await;
+            }
+            let (key, value) = item?;
+            if !key.starts_with(prefix.as_bytes()) {
+                break;
+            }
+
+            let order = match serde_json::from_slice::<OrderAmounts>(&value) {
+                Ok(order) => order,
+                Err(e) => {
+                    error!(
+                        "❌ Failed to parse user order data: {}, key: {}",
+                        e,
+                        String::from_utf8_lossy(&key)
+                    );
+                    continue;
+                }
+            };
+            if order.status != OrderStatus::Open {
+                continue;
+            }
+            match OrderType::from_raw(order.order_type) {
+                OrderType::Long => summary.longs.add(&order),
+                OrderType::Short => summary.shorts.add(&order),
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Create test order data for testing purposes
+    #[allow(dead_code)]
+    pub fn create_test_order_data(&self, user: &str, mint: &str, order_pda: &str) -> Result<()> {
+        let test_order = OrderData {
+            order_type: 2, // up order (short)
+            mint: mint.to_string(),
+            user: user.to_string(),
+            lock_lp_start_price: 100000000000000000000u128, // Example price
+            lock_lp_end_price: 120000000000000000000u128,   // Example price
+            lock_lp_sol_amount: 1000000000,                 // 1 SOL in lamports
+            lock_lp_token_amount: 500000000,
+            start_time: 1758343400,       // Example timestamp
+            end_time: 1758343800,         // Example timestamp
+            margin_sol_amount: 500000000, // 0.5 SOL
+            borrow_amount: 1500000000,
+            position_asset_amount: 2000000000,
+            borrow_fee: 250,
+            order_pda: order_pda.to_string(),
+            // Initialize with defaults - will be enriched later
+            latest_price: 0,
+            latest_trade_time: 0,
+            name: String::new(),
+            symbol: String::new(),
+            image: String::new(),
+            status: OrderStatus::Open,
+        };
+
+        // Store the order data
+        let order_key =
+            self.generate_order_key(mint, OrderType::from_raw(test_order.order_type), order_pda);
+        let user_order_key = self.generate_user_order_key(user, mint, order_pda);
+        let order_value = serde_json::to_vec(&test_order)?;
+
+        // Use a batch for atomicity
+        let mut batch = rocksdb::WriteBatch::default();
+        batch.put(order_key.as_bytes(), &order_value);
+        batch.put(user_order_key.as_bytes(), &order_value);
+
+        self.db.write(batch)?;
+
+        info!(
+            "💾 Test order created successfully: user={}, mint={}, order_pda={}",
+            user, mint, order_pda
+        );
+        Ok(())
+    }
+
+    /// Query kline data
+    pub async fn query_kline_data(&self, query: KlineQuery) -> Result<KlineQueryResponse> {
+        let mint_account = &query.mint_account;
+        let interval = &query.interval;
+        let page = query.page.unwrap_or(1);
+        let limit = query.limit.unwrap_or(50);
+        let order_by = query.order_by.unwrap_or_else(|| "time_desc".to_string());
+
+        // Validate interval
+        if !matches!(interval.as_str(), "s1" | "s30" | "m5") {
+            return Err(anyhow::anyhow!(
+                "Invalid interval: {}, must be one of: s1, s30, m5",
+                interval
+            ));
+        }
+
+        debug!(
+            "🔍 Querying kline data, mint: {}, interval: {}, page: {}, limit: {}, order: {}",
+            mint_account, interval, page, limit, order_by
+        );
+
+        // Build prefix key for the specific mint and interval
+        let prefix = format!("{}:{}:", interval, mint_account);
+
+        // Collect all matching kline data
+        let mut all_klines = Vec::new();
+
+        let iter = self.prefix_scan(&prefix);
+
+        for (scanned, item) in iter.enumerate() {
+            if (scanned + 1) % SCAN_YIELD_KEYS == 0 {
+                tokio::task::yield_now().await;
+            }
+            let (key, value) = item?;
+            let key_str = String::from_utf8_lossy(&key);
+
+            // Check if still matches prefix
+            if !key_str.starts_with(&prefix) {
+                break;
+            }
+
+            // Parse kline data
+            match serde_json::from_slice::<KlineData>(&value) {
+                Ok(kline_data) => all_klines.push(kline_data),
+                Err(e) => {
+                    error!("❌ Failed to parse kline data: {}, key: {}", e, key_str);
+                    continue;
+                }
+            }
+        }
+
+        if let Some(min_update_count) = query.min_update_count {
+            all_klines.retain(|kline| kline.update_count >= min_update_count);
+        }
+
+        if query.fill_gaps {
+            all_klines.sort_by(|a, b| a.time.cmp(&b.time));
+            all_klines = fill_kline_gaps(all_klines, Self::interval_seconds(interval));
+        }
+
+        // Sort by time
+        match order_by.as_str() {
+            "time_asc" => {
+                all_klines.sort_by(|a, b| a.time.cmp(&b.time));
+            }
+            "time_desc" => {
+                all_klines.sort_by(|a, b| b.time.cmp(&a.time));
+            }
+            _ => {
+                // Default sort by time descending (newest first)
+                all_klines.sort_by(|a, b| b.time.cmp(&a.time));
+            }
+        }
+
+        let total = all_klines.len();
+        let offset = (page - 1) * limit;
+        let has_prev = page > 1;
+        let has_next = offset + limit < total;
+
+        // Pagination
+        let klines = all_klines
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .collect::<Vec<_>>();
+
+        debug!(
+            "🔍 Retrieved {} klines for mint: {}, interval: {}",
+            klines.len(),
+            mint_account,
+            interval
+        );
+
+        Ok(KlineQueryResponse {
+            klines,
+            total,
+            page,
+            limit,
+            has_next,
+            has_prev,
+            interval: interval.clone(),
+            mint_account: mint_account.clone(),
+            bucket_secs: Self::interval_seconds(interval),
+            alignment_epoch: KLINE_ALIGNMENT_EPOCH,
+        })
+    }
+
+    /// Query the latest `limit` klines for several mints, aligned to a shared time window
+    pub async fn query_kline_batch(
+        &self,
+        mints: &[String],
+        interval: &str,
+        limit: usize,
+    ) -> Result<KlineBatchResponse> {
+        let results: Vec<Result<KlineQueryResponse>> = stream::iter(mints)
+            .map(|mint| {
+                self.query_kline_data(KlineQuery {
+                    mint_account: mint.clone(),
+                    interval: interval.to_string(),
+                    page: Some(1),
+                    limit: Some(limit),
+                    order_by: Some("time_desc".to_string()),
+                    fill_gaps: false,
+                    min_update_count: None,
+                })
+            })
+            .buffer_unordered(KLINE_BATCH_CONCURRENCY)
+            .collect()
+            .await;
+
+        let mut series = std::collections::BTreeMap::new();
+        for result in results {
+            let response = result?;
+            let mut klines = response.klines;
+            klines.reverse();
+            series.insert(response.mint_account, klines);
+        }
+
+        // Shared window: from the latest first candle, if every series still has data there
+        let start = series
+            .values()
+            .filter_map(|k| k.first())
+            .map(|k| k.time)
+            .max();
+        let ends: Vec<u64> = series
+            .values()
+            .filter_map(|k| k.last())
+            .map(|k| k.time)
+            .collect();
+        let (window_start, window_end) = match (start, ends.iter().min(), ends.iter().max()) {
+            (Some(start), Some(&min_end), Some(&end)) if start <= min_end => {
+                for klines in series.values_mut() {
+                    klines.retain(|k| k.time >= start);
+                }
+                (Some(start), Some(end))
+            }
+            _ => (None, None),
+        };
+
+        Ok(KlineBatchResponse {
+            interval: interval.to_string(),
+            series,
+            window_start,
+            window_end,
+        })
+    }
+
+    /// Newest kline of each mint: one reverse seek per mint instead of a prefix scan
+    pub async fn query_latest_klines(
+        &self,
+        mints: &[String],
+        interval: &str,
+    ) -> Result<LatestKlinesResponse> {
+        if !matches!(interval, "s1" | "s30" | "m5") {
+            return Err(anyhow::anyhow!(
+                "Invalid interval: {}, must be one of: s1, s30, m5",
+                interval
+            ));
+        }
+
+        let mut klines = std::collections::BTreeMap::new();
+        for mint in mints {
+            let prefix = format!("{}:{}:", interval, mint);
+            // '~' sorts after every digit, so the first key at or before it is the newest bucket
+            let seek_key = format!("{}~", prefix);
+            let mut iter = self
+                .db
+                .iterator(IteratorMode::From(seek_key.as_bytes(), Direction::Reverse));
+            let Some(item) = iter.next() else { continue };
+            let (key, value) = item?;
+            if !key.starts_with(prefix.as_bytes()) {
+                continue;
+            }
+            match serde_json::from_slice::<KlineData>(&value) {
+                Ok(kline) => {
+                    klines.insert(mint.clone(), kline);
+                }
+                Err(e) => error!(
+                    "❌ Failed to parse kline data: {}, key: {}",
+                    e,
+                    String::from_utf8_lossy(&key)
+                ),
+            }
+        }
+
+        Ok(LatestKlinesResponse {
+            interval: interval.to_string(),
+            klines,
+        })
+    }
+
+    /// Get database statistics
+    pub fn get_stats(&self) -> Result<String> {
+        let stats = self.db.property_value("rocksdb.stats")?;
+        Ok(stats.unwrap_or_else(|| "No stats available".to_string()))
+    }
+
+    /// Structured gauges read from individual RocksDB properties
+    pub fn get_db_metrics(&self) -> Result<DbMetrics> {
+        let property = |name: &str| -> Result<u64> {
+            Ok(self
+                .db
+                .property_value(name)?
+                .and_then(|value| value.trim().parse().ok())
+                .unwrap_or(0))
+        };
+
+        Ok(DbMetrics {
+            estimate_num_keys: property("rocksdb.estimate-num-keys")?,
+            cur_size_all_mem_tables: property("rocksdb.cur-size-all-mem-tables")?,
+            num_files_at_level0: property("rocksdb.num-files-at-level0")?,
+            estimate_live_data_size: property("rocksdb.estimate-live-data-size")?,
+            total_sst_files_size: property("rocksdb.total-sst-files-size")?,
+            estimate_pending_compaction_bytes: property(
+                "rocksdb.estimate-pending-compaction-bytes",
+            )?,
+            num_running_compactions: property("rocksdb.num-running-compactions")?,
+            num_running_flushes: property("rocksdb.num-running-flushes")?,
+        })
+    }
+
+    /// Free space watch of the database path, when `database.min_free_disk_mb` is set
+    pub fn disk_guard(&self) -> Option<&Arc<DiskSpaceGuard>> {
+        self.disk_guard.as_ref()
+    }
+
+    /// Whether the database disk is below `database.min_free_disk_mb` and writes are refused
+    pub fn is_read_only(&self) -> bool {
+        self.disk_guard
+            .as_ref()
+            .is_some_and(|guard| guard.is_degraded())
+    }
+
+    /// Refuse a background write (`what`) while the database disk is low
+    fn ensure_writable(&self, what: &str) -> Result<()> {
+        if self.is_read_only() {
+            return Err(anyhow::anyhow!(
+                "database disk below database.min_free_disk_mb, {} skipped",
+                what
+            ));
+        }
+        Ok(())
+    }
+
+    /// Block time to processing delay of live events, for /metrics
+    pub fn receive_latency(&self) -> &ReceiveLatencyHistogram {
+        &self.receive_latency
+    }
+
+    /// Record how long after its block time a live event was processed, also storing it under
+    /// `lat:{event key}` when `database.store_event_latency` is on; None without a block time
+    pub fn record_receive_latency(
+        &self,
+        event: &SpinPetEvent,
+        processed_at: DateTime<Utc>,
+    ) -> Result<Option<u64>> {
+        let Some(latency_ms) = receive_latency_ms(event, processed_at) else {
+            return Ok(None);
+        };
+        self.receive_latency.observe(latency_ms);
+        if self.config.database.store_event_latency {
+            let key = format!("lat:{}", self.generate_event_key(event));
+            self.db
+                .put(key.as_bytes(), latency_ms.to_string().as_bytes())?;
+        }
+        Ok(Some(latency_ms))
+    }
+
+    /// Exact bytes stored under `key` (single point lookup), None when the key is absent
+    pub fn get_raw(&self, key: &str) -> Result<Option<RawKeyValue>> {
+        use base64::engine::Engine;
+
+        Ok(self.db.get(key.as_bytes())?.map(|value| RawKeyValue {
+            key: key.to_string(),
+            size_bytes: value.len(),
+            value_base64: base64::engine::general_purpose::STANDARD.encode(&value),
+            json: serde_json::from_slice(&value).ok(),
+        }))
+    }
+
+    /// Archived WAL files (`{wal_archive_dir}/archive`), oldest first; empty when archival is off
+    pub fn archived_wal_files(&self) -> Result<Vec<String>> {
+        let Some(wal_dir) = &self.config.database.wal_archive_dir else {
+            return Ok(Vec::new());
+        };
+        let archive = std::path::Path::new(wal_dir).join("archive");
+        if !archive.exists() {
+            return Ok(Vec::new());
+        }
+
+        // WAL file names are zero-padded log numbers, so name order is write order
+        let mut files: Vec<String> = std::fs::read_dir(&archive)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "log"))
+            .map(|entry| entry.path().to_string_lossy().to_string())
+            .collect();
+        files.sort();
+        Ok(files)
+    }
+
+    /// Create a consistent, hard-linked snapshot of the DB at `path` (must not exist yet)
+    pub fn create_checkpoint(&self, path: &str) -> Result<CheckpointInfo> {
+        self.ensure_writable("checkpoint")?;
+        let target = std::path::Path::new(path);
+        if target.exists() {
+            return Err(anyhow::anyhow!("Checkpoint path already exists: {}", path));
+        }
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        rocksdb::checkpoint::Checkpoint::new(&self.db)?.create_checkpoint(target)?;
+
+        let size_bytes = std::fs::read_dir(target)?
+            .filter_map(|entry| entry.ok()?.metadata().ok())
+            .filter(|metadata| metadata.is_file())
+            .map(|metadata| metadata.len())
+            .sum();
+        info!("📸 Checkpoint created at {} ({} bytes)", path, size_bytes);
+
+        Ok(CheckpointInfo {
+            path: path.to_string(),
+            size_bytes,
+        })
+    }
+}
+
+/// Periodically write a checkpoint to `{checkpoint_dir}/checkpoint-{unix_time}`
+pub async fn start_checkpoint_task(
+    event_storage: Arc<EventStorage>,
+    checkpoint_dir: String,
+    interval_secs: u64,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+        // The first tick fires immediately; skip it so startup isn't slowed by a snapshot
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+
+            if event_storage.is_read_only() {
+                continue;
+            }
+
+            let path = format!("{}/checkpoint-{}", checkpoint_dir, Utc::now().timestamp());
+            if let Err(e) = event_storage.create_checkpoint(&path) {
+                warn!("⚠️ Scheduled checkpoint failed: {}", e);
+            }
+        }
+    })
+}
+
+/// Periodically prune events and global index entries past their retention
+pub async fn start_retention_task(
+    event_storage: Arc<EventStorage>,
+    interval_secs: u64,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = event_storage.prune_retention().await {
+                warn!("⚠️ Retention sweep failed: {}", e);
+            }
+        }
+    })
+}
+
+/// Periodically snapshot the details of recently updated mints
+pub async fn start_mint_snapshot_task(
+    event_storage: Arc<EventStorage>,
+    interval_secs: u64,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+
+        loop {
+            interval.tick().await;
+            if event_storage.is_read_only() {
+                continue;
+            }
+
+            match event_storage.snapshot_mint_details(Utc::now().timestamp()) {
+                Ok(written) if written > 0 => debug!("📸 Snapshotted {} mint details", written),
+                Ok(_) => {}
+                Err(e) => warn!("⚠️ Mint detail snapshot failed: {}", e),
+            }
+        }
+    })
+}
+
+/// Refetch metadata older than `refetch_after_secs`, at most `per_minute` mints each minute
+pub async fn start_uri_refetch_task(
+    event_storage: Arc<EventStorage>,
+    refetch_after_secs: u64,
+    per_minute: usize,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+        // A slow gateway stretches a sweep past the minute; don't catch up with a burst
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            if event_storage.is_read_only() {
+                continue;
+            }
+
+            let cutoff = Utc::now() - chrono::Duration::seconds(refetch_after_secs as i64);
+            match event_storage
+                .refetch_stale_uri_data(cutoff, per_minute)
+                .await
+            {
+                Ok(result) if result.refetched > 0 => info!(
+                    "🔁 Refetched metadata of {} mints, {} changed",
+                    result.refetched, result.updated
+                ),
+                Ok(_) => {}
+                Err(e) => warn!("⚠️ Metadata refetch failed: {}", e),
+            }
+        }
+    })
+}
+
+/// Rebuild global indexes one after another on a blocking thread
+pub async fn start_index_rebuild_task(
+    event_storage: Arc<EventStorage>,
+    indexes: Vec<&'static str>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::task::spawn_blocking(move || {
+        for index in indexes {
+            if let Err(e) = event_storage.rebuild_index(index) {
+                warn!("⚠️ Rebuild of the {} index failed: {}", index, e);
+            }
+        }
+    })
+}
+
+/// Periodically move orders past their end_time out of the open set
+pub async fn start_order_expiry_task(
+    event_storage: Arc<EventStorage>,
+    interval_secs: u64,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+
+        loop {
+            interval.tick().await;
+            if event_storage.is_read_only() {
+                continue;
+            }
+
+            if let Err(e) = event_storage.expire_orders(Utc::now().timestamp()).await {
+                warn!("⚠️ Order expiry sweep failed: {}", e);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use tempfile::TempDir;
+
+    fn create_test_config(temp_dir: &TempDir) -> crate::config::Config {
+        crate::config::Config::for_tests(temp_dir.path().to_str().unwrap())
+    }
+
+    fn create_long_short_event(
+        mint: &str,
+        order_pda: &str,
+        order_type: u8,
+        lock_lp_start_price: u128,
+        position_asset_amount: u64,
+    ) -> SpinPetEvent {
+        SpinPetEvent::LongShort(LongShortEvent {
+            payer: "test_payer".to_string(),
+            mint_account: mint.to_string(),
+            order_pda: order_pda.to_string(),
+            latest_price: lock_lp_start_price,
+            order_type,
+            mint: mint.to_string(),
+            user: "test_user".to_string(),
+            lock_lp_start_price,
+            lock_lp_end_price: lock_lp_start_price * 2,
+            lock_lp_sol_amount: 1_000_000_000,
+            lock_lp_token_amount: 500_000_000,
+            start_time: 1758343400,
+            end_time: 1758343800,
+            margin_sol_amount: 500_000_000,
+            borrow_amount: 1_500_000_000,
+            position_asset_amount,
+            borrow_fee: 250,
+            timestamp: Utc::now(),
+            timestamp_source: TimestampSource::ServerReceive,
+            log_index: 0,
+            signature: format!("sig_{}", order_pda),
+            slot: 100,
+        })
+    }
+
+    fn create_buy_sell_event(
+        mint: &str,
+        signature: &str,
+        sol_amount: u64,
+        latest_price: u128,
+    ) -> SpinPetEvent {
+        SpinPetEvent::BuySell(BuySellEvent {
+            payer: "test_payer".to_string(),
+            mint_account: mint.to_string(),
+            is_buy: true,
+            token_amount: 1_000_000,
+            sol_amount,
+            latest_price,
+            timestamp: Utc::now(),
+            timestamp_source: TimestampSource::ServerReceive,
+            log_index: 0,
+            signature: signature.to_string(),
+            slot: 100,
+        })
+    }
+
+    fn create_token_created_event(mint: &str, uri: &str) -> SpinPetEvent {
+        SpinPetEvent::TokenCreated(TokenCreatedEvent {
+            payer: "test_payer".to_string(),
+            mint_account: mint.to_string(),
+            curve_account: "test_curve".to_string(),
+            pool_token_account: "test_pool_token".to_string(),
+            pool_sol_account: "test_pool_sol".to_string(),
+            fee_recipient: "test_fee_recipient".to_string(),
+            base_fee_recipient: "test_base_fee_recipient".to_string(),
+            params_account: "test_params_account".to_string(),
+            name: "Test Token".to_string(),
+            symbol: "TEST".to_string(),
+            uri: uri.to_string(),
+            swap_fee: 100,
+            borrow_fee: 200,
+            fee_discount_flag: 0,
+            timestamp: Utc::now(),
+            timestamp_source: TimestampSource::ServerReceive,
+            log_index: 0,
+            signature: format!("sig_{}", mint),
+            slot: 100,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_event_storage() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = create_test_config(&temp_dir);
+
+        let storage = EventStorage::new(&config).unwrap();
+
+        let mint_detail = MintDetailData {
+            mint_account: "test_mint".to_string(),
+            payer: Some("test_payer".to_string()),
+            curve_account: Some("test_curve".to_string()),
+            pool_token_account: Some("test_pool_token".to_string()),
+            pool_sol_account: Some("test_pool_sol".to_string()),
+            fee_recipient: Some("test_fee_recipient".to_string()),
+            base_fee_recipient: Some("test_base_fee_recipient".to_string()),
+            params_account: Some("test_params_account".to_string()),
+            name: Some("Test Token".to_string()),
+            symbol: Some("TEST".to_string()),
+            uri: Some("test_uri".to_string()),
+            swap_fee: Some(100),
+            borrow_fee: Some(200),
+            fee_discount_flag: Some(0),
+            create_timestamp: Some(Utc::now().timestamp()),
+            latest_price: Some(1000000),
+            latest_trade_time: Some(Utc::now().timestamp()),
+            total_sol_amount: 1000,
+            total_margin_sol_amount: 2000,
+            total_force_liquidations: 10,
+            total_close_profit: 500,
+            total_realized_pnl: -250,
+            created_by: Some("test_user".to_string()),
+            last_updated_at: Some(Utc::now()),
+            uri_data: None,
+            uri_last_fetched_at: None,
+            rejected_price_count: 0,
+            pending_price: None,
+            pending_price_count: 0,
+        };
+
+        let key = storage.generate_mint_detail_key(&mint_detail.mint_account);
+        let value = serde_json::to_vec(&mint_detail).unwrap();
+        storage.db.put(key.as_bytes(), &value).unwrap();
+
+        let query = MintDetailsQuery {
+            mint_accounts: vec![mint_detail.mint_account.clone()],
+        };
+
+        let result = storage.query_mint_details(query).await.unwrap();
+        assert_eq!(result.details.len(), 1);
+        assert_eq!(result.details[0].mint_account, mint_detail.mint_account);
+        assert_eq!(result.details[0].name, mint_detail.name);
+
+        // Also test get_stats
+        let stats = storage.get_stats().unwrap();
+        assert!(stats.contains("Total Keys:"));
+    }
+
+    #[tokio::test]
+    async fn test_query_order_book_aggregates_levels() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = EventStorage::new(&create_test_config(&temp_dir)).unwrap();
+        let mint = "book_mint";
+
+        // Longs (dn): two orders share the 300 level
+        for event in [
+            create_long_short_event(mint, "long_a", 1, 300, 10),
+            create_long_short_event(mint, "long_b", 1, 300, 15),
+            create_long_short_event(mint, "long_c", 1, 200, 7),
+            // Shorts (up): two orders share the 500 level
+            create_long_short_event(mint, "short_a", 2, 600, 4),
+            create_long_short_event(mint, "short_b", 2, 500, 3),
+            create_long_short_event(mint, "short_c", 2, 500, 8),
+            // Another mint must not leak into this book
+            create_long_short_event("other_mint", "other_a", 1, 300, 99),
+        ] {
+            storage.store_event(event).await.unwrap();
+        }
+
+        let book = storage
+            .query_order_book(OrderBookQuery {
+                mint_account: mint.to_string(),
+                depth: None,
+            })
+            .await
+            .unwrap();
+
+        // Longs are sorted by price descending
+        assert_eq!(book.longs.len(), 2);
+        assert_eq!(book.longs[0].price, 300);
+        assert_eq!(book.longs[0].position_asset_amount, 25);
+        assert_eq!(book.longs[0].order_count, 2);
+        assert_eq!(book.longs[1].price, 200);
+        assert_eq!(book.longs[1].position_asset_amount, 7);
+
+        // Shorts are sorted by price ascending
+        assert_eq!(book.shorts.len(), 2);
+        assert_eq!(book.shorts[0].price, 500);
+        assert_eq!(book.shorts[0].position_asset_amount, 11);
+        assert_eq!(book.shorts[0].order_count, 2);
+        assert_eq!(book.shorts[1].price, 600);
+        assert_eq!(book.shorts[1].position_asset_amount, 4);
+
+        // Depth limits the number of levels per side
+        let shallow = storage
+            .query_order_book(OrderBookQuery {
+                mint_account: mint.to_string(),
+                depth: Some(1),
+            })
+            .await
+            .unwrap();
+        assert_eq!(shallow.longs.len(), 1);
+        assert_eq!(shallow.shorts.len(), 1);
+        assert_eq!(shallow.longs[0].position_asset_amount, 25);
+    }
+
+    #[tokio::test]
+    async fn test_empty_uri_uses_onchain_fallback() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.ipfs.use_onchain_fallback = true;
+        let storage = EventStorage::new(&config).unwrap();
+
+        storage
+            .store_event(create_token_created_event("fallback_mint", ""))
+            .await
+            .unwrap();
+
+        let result = storage
+            .query_mint_details(MintDetailsQuery {
+                mint_accounts: vec!["fallback_mint".to_string()],
+            })
+            .await
+            .unwrap();
+        let uri_data = result.details[0].uri_data.as_ref().unwrap();
+        assert_eq!(uri_data.name.as_deref(), Some("Test Token"));
+        assert_eq!(uri_data.symbol.as_deref(), Some("TEST"));
+        assert!(uri_data.image.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_uri_data_populates_all_mints() {
+        use axum::{extract::Path, routing::get, Json, Router};
+
+        // Mock gateway answering every hash with metadata named after it
+        let gateway = Router::new().route(
+            "/ipfs/:hash",
+            get(|Path(hash): Path<String>| async move {
+                Json(serde_json::json!({ "name": hash, "symbol": "MOCK" }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, gateway).await.unwrap() });
+
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.ipfs.gateway_url = format!("http://{}/ipfs/", addr);
+        config.ipfs.max_retries = 1;
+        let storage = EventStorage::new(&config).unwrap();
+
+        // Seed details directly, as a bulk import would, without the TokenCreated fetch
+        let mints: Vec<String> = (0..5).map(|i| format!("prefetch_mint_{}", i)).collect();
+        for (i, mint) in mints.iter().enumerate() {
+            let detail = MintDetailData {
+                mint_account: mint.clone(),
+                uri: Some(format!("ipfs://hash{}", i)),
+                ..Default::default()
+            };
+            storage
+                .db
+                .put(
+                    storage.generate_mint_detail_key(mint).as_bytes(),
+                    serde_json::to_vec(&detail).unwrap(),
+                )
+                .unwrap();
+        }
+
+        let mut requested = mints.clone();
+        requested.push("unknown_mint".to_string());
+        let limiter = Semaphore::new(2);
+        let result = storage
+            .prefetch_uri_data(requested, &limiter)
+            .await
+            .unwrap();
+        assert_eq!(result.requested, 6);
+        assert_eq!(result.fetched, 5);
+        assert_eq!(result.failed, vec!["unknown_mint".to_string()]);
+
+        for (i, mint) in mints.iter().enumerate() {
+            let detail = storage.get_mint_detail(mint).unwrap().unwrap();
+            let uri_data = detail.uri_data.unwrap();
+            assert_eq!(uri_data.name, Some(format!("hash{}", i)));
+            assert_eq!(uri_data.symbol.as_deref(), Some("MOCK"));
+        }
+        assert_eq!(limiter.available_permits(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_stale_uri_data_refetched_only_rewritten_when_changed() {
+        use axum::{extract::Path, routing::get, Json, Router};
+        use std::sync::atomic::AtomicUsize;
+
+        // Mock gateway: "updated" now has an image, "same" answers what is already stored
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&requests);
+        let gateway = Router::new().route(
+            "/ipfs/:hash",
+            get(move |Path(hash): Path<String>| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    let image = (hash == "updated").then_some("https://img/new.png");
+                    Json(serde_json::json!({ "name": hash, "image": image }))
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, gateway).await.unwrap() });
+
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.ipfs.gateway_url = format!("http://{}/ipfs/", addr);
+        config.ipfs.max_retries = 1;
+        let storage = EventStorage::new(&config).unwrap();
+
+        let now = Utc::now();
+        let stale = now - chrono::Duration::hours(2);
+        for (mint, fetched_at) in [("updated", stale), ("same", stale), ("fresh", now)] {
+            let detail = MintDetailData {
+                mint_account: mint.to_string(),
+                uri: Some(format!("ipfs://{}", mint)),
+                uri_data: Some(TokenUriData {
+                    name: Some(mint.to_string()),
+                    ..Default::default()
+                }),
+                last_updated_at: Some(stale),
+                uri_last_fetched_at: Some(fetched_at),
+                ..Default::default()
+            };
+            storage
+                .db
+                .put(
+                    storage.generate_mint_detail_key(mint).as_bytes(),
+                    serde_json::to_vec(&detail).unwrap(),
+                )
+                .unwrap();
+        }
+
+        let cutoff = now - chrono::Duration::hours(1);
+        let result = storage.refetch_stale_uri_data(cutoff, 10).await.unwrap();
+        assert_eq!(
+            result,
+            UriRefetchResult {
+                refetched: 2,
+                updated: 1
+            }
+        );
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+        let updated = storage.get_mint_detail("updated").unwrap().unwrap();
+        let uri_data = updated.uri_data.unwrap();
+        assert_eq!(uri_data.image.as_deref(), Some("https://img/new.png"));
+        assert!(updated.last_updated_at.unwrap() > stale);
+        assert!(updated.uri_last_fetched_at.unwrap() >= now);
+
+        // Unchanged metadata is left as it was; only the fetch time moves
+        let same = storage.get_mint_detail("same").unwrap().unwrap();
+        assert_eq!(same.uri_data.unwrap().name.as_deref(), Some("same"));
+        assert_eq!(same.last_updated_at, Some(stale));
+        assert!(same.uri_last_fetched_at.unwrap() >= now);
+
+        // Both are fresh now, so the next sweep requests nothing
+        let result = storage.refetch_stale_uri_data(cutoff, 10).await.unwrap();
+        assert_eq!(result, UriRefetchResult::default());
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_creations_share_one_uri_fetch() {
+        use axum::{extract::State, routing::get, Json, Router};
+        use std::sync::atomic::AtomicUsize;
+
+        // Slow gateway counting its requests, so the second creation arrives mid-fetch
+        let requests = Arc::new(AtomicUsize::new(0));
+        let gateway = Router::new()
+            .route(
+                "/ipfs/:hash",
+                get(|State(requests): State<Arc<AtomicUsize>>| async move {
+                    requests.fetch_add(1, Ordering::SeqCst);
+                    sleep(Duration::from_millis(200)).await;
+                    Json(serde_json::json!({ "name": "Copycat", "symbol": "COPY" }))
+                }),
+            )
+            .with_state(Arc::clone(&requests));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, gateway).await.unwrap() });
+
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.ipfs.gateway_url = format!("http://{}/ipfs/", addr);
+        config.ipfs.max_retries = 1;
+        let storage = Arc::new(EventStorage::new(&config).unwrap());
+
+        let creations: Vec<_> = ["copy_mint_a", "copy_mint_b"]
+            .into_iter()
+            .map(|mint| {
+                let storage = Arc::clone(&storage);
+                tokio::spawn(async move {
+                    storage
+                        .store_event(create_token_created_event(mint, "ipfs://samehash"))
+                        .await
+                })
+            })
+            .collect();
+        for creation in creations {
+            creation.await.unwrap().unwrap();
+        }
+
+        // Both mints are filled in by the background fetch
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let filled = ["copy_mint_a", "copy_mint_b"].iter().all(|mint| {
+                    storage
+                        .get_mint_detail(mint)
+                        .unwrap()
+                        .is_some_and(|detail| detail.uri_data.is_some())
+                });
+                if filled {
+                    break;
+                }
+                sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("uri data was not stored for both mints");
+
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        assert!(storage.uri_fetches.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_wal_archive_dir_opens_and_lists_archive() {
+        let temp_dir = TempDir::new().unwrap();
+        let wal_temp_dir = TempDir::new().unwrap();
+        let wal_dir = wal_temp_dir.path().join("wal");
+        let mut config = create_test_config(&temp_dir);
+        config.database.wal_archive_dir = Some(wal_dir.to_string_lossy().to_string());
+        let storage = EventStorage::new(&config).unwrap();
+
+        storage
+            .store_event(create_buy_sell_event("wal_mint", "wal_sig", 1_000_000, 1))
+            .await
+            .unwrap();
+
+        // The live WAL is written to the configured directory, not the DB path
+        let live_logs = std::fs::read_dir(&wal_dir)
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "log"))
+            .count();
+        assert!(live_logs > 0);
+
+        let archived = storage.archived_wal_files().unwrap();
+        assert!(archived.iter().all(|path| path.ends_with(".log")));
+
+        // Without an archive dir there is nothing to list
+        let plain_dir = TempDir::new().unwrap();
+        let plain = EventStorage::new(&create_test_config(&plain_dir)).unwrap();
+        assert!(plain.archived_wal_files().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_event_histogram_counts_per_bucket() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = EventStorage::new(&create_test_config(&temp_dir)).unwrap();
+
+        // Hourly buckets starting at an hour boundary
+        let base = 1_700_000_000 - 1_700_000_000 % 3600;
+        let offsets = [
+            10,            // bucket 0
+            3599,          // bucket 0
+            3600,          // bucket 1
+            2 * 3600 + 5,  // bucket 2
+            2 * 3600 + 60, // bucket 2
+            2 * 3600 + 61, // bucket 2
+            4 * 3600,      // at `to`, excluded
+            -1,            // before `from`, excluded
+        ];
+        for (i, offset) in offsets.iter().enumerate() {
+            let mut event =
+                create_buy_sell_event("hist_mint", &format!("hist_sig_{}", i), 1_000_000, 1);
+            if let SpinPetEvent::BuySell(e) = &mut event {
+                e.timestamp = DateTime::<Utc>::from_timestamp(base + offset, 0).unwrap();
+                e.slot = 100 + i as u64;
+            }
+            storage.store_event(event).await.unwrap();
+        }
+        storage
+            .store_event(create_buy_sell_event(
+                "other_mint",
+                "other_sig",
+                1_000_000,
+                1,
+            ))
+            .await
+            .unwrap();
+
+        let histogram = storage
+            .query_event_histogram("hist_mint", 3600, base, base + 4 * 3600)
+            .await
+            .unwrap();
+        assert!(!histogram.truncated);
+        let counts: Vec<(i64, u64)> = histogram
+            .buckets
+            .iter()
+            .map(|b| (b.bucket_ts - base, b.count))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![(0, 2), (3600, 1), (2 * 3600, 3), (3 * 3600, 0)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_oversized_or_non_json_metadata_is_rejected() {
+        use axum::{body::Body, http::header, response::Response, routing::get, Router};
+
+        // Valid JSON streamed without Content-Length, so only the read cap can stop it
+        fn huge() -> Response {
+            let mut parts = vec![b"{\"name\": \"".to_vec()];
+            parts.extend((0..64).map(|_| vec![b'x'; 64 * 1024]));
+            parts.push(b"\"}".to_vec());
+            let chunks = futures::stream::iter(parts.into_iter().map(Ok::<_, std::io::Error>));
+            Response::builder()
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from_stream(chunks))
+                .unwrap()
+        }
+        let gateway = Router::new()
+            .route("/ipfs/huge", get(|| async { huge() }))
+            .route(
+                "/ipfs/html",
+                get(|| async {
+                    Response::builder()
+                        .header(header::CONTENT_TYPE, "text/html")
+                        .body(Body::from("{\"name\": \"Html\"}"))
+                        .unwrap()
+                }),
+            )
+            .route(
+                "/ipfs/ok",
+                get(|| async { axum::Json(serde_json::json!({ "name": "Fine" })) }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, gateway).await.unwrap() });
+
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.ipfs.gateway_url = format!("http://{}/ipfs/", addr);
+        config.ipfs.max_retries = 1;
+        config.ipfs.max_metadata_bytes = 1024;
+        let storage = EventStorage::new(&config).unwrap();
+
+        assert!(storage.fetch_token_uri_data("ipfs://huge").await.is_none());
+        assert!(storage.fetch_token_uri_data("ipfs://html").await.is_none());
+        let ok = storage.fetch_token_uri_data("ipfs://ok").await.unwrap();
+        assert_eq!(ok.name.as_deref(), Some("Fine"));
+    }
+
+    #[tokio::test]
+    async fn test_query_scans_skip_block_cache() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = EventStorage::new(&create_test_config(&temp_dir)).unwrap();
+        assert!(!storage.scan_fills_cache());
+
+        for event in [
+            create_long_short_event("scan_mint", "scan_a", 1, 300, 10),
+            create_long_short_event("scan_mint", "scan_b", 2, 500, 5),
+            create_long_short_event("scan_mint_2", "scan_c", 1, 300, 1),
+        ] {
+            storage.store_event(event).await.unwrap();
+        }
+
+        // The bounded iterator yields only keys under the prefix, without manual checks
+        let keys: Vec<String> = storage
+            .prefix_scan("tr:scan_mint:")
+            .map(|item| String::from_utf8_lossy(&item.unwrap().0).to_string())
+            .collect();
+        assert_eq!(keys.len(), 2);
+        assert!(keys.iter().all(|k| k.starts_with("tr:scan_mint:")));
+
+        let events = storage
+            .query_events(EventQuery {
+                mint_account: "scan_mint".to_string(),
+                page: Some(1),
+                limit: Some(10),
+                order_by: Some("slot_asc".to_string()),
+            })
+            .await
+            .unwrap();
+        assert_eq!(events.events.len(), 2);
+
+        drop(storage);
+        let mut config = create_test_config(&temp_dir);
+        config.database.cache_scans = true;
+        let storage = EventStorage::new(&config).unwrap();
+        assert!(storage.scan_fills_cache());
+        assert_eq!(storage.prefix_scan("tr:scan_mint:").count(), 2);
+    }
+
+    async fn latest_5m_close(storage: &EventStorage, mint: &str) -> Option<f64> {
+        storage
+            .query_kline_data(KlineQuery {
+                mint_account: mint.to_string(),
+                interval: KLINE_INTERVAL_5M.to_string(),
+                page: Some(1),
+                limit: Some(1),
+                order_by: Some("time_desc".to_string()),
+                fill_gaps: false,
+                min_update_count: None,
+            })
+            .await
+            .unwrap()
+            .klines
+            .first()
+            .map(|k| k.close)
+    }
+
+    #[tokio::test]
+    async fn test_kline_response_reports_bucket_alignment() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = EventStorage::new(&create_test_config(&temp_dir)).unwrap();
+
+        let mut trade = create_buy_sell_event("aligned_mint", "aligned_sig", 1_000_000, 1);
+        trade.set_timestamp(
+            Utc.timestamp_opt(1_700_000_123, 0).unwrap(),
+            TimestampSource::BlockTime,
+        );
+        storage.store_event(trade).await.unwrap();
+
+        let response = storage
+            .query_kline_data(KlineQuery {
+                mint_account: "aligned_mint".to_string(),
+                interval: KLINE_INTERVAL_5M.to_string(),
+                page: Some(1),
+                limit: Some(10),
+                order_by: Some("time_desc".to_string()),
+                fill_gaps: false,
+                min_update_count: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(response.bucket_secs, 300);
+        assert_eq!(response.alignment_epoch, 0);
+        // 22:15:23 UTC falls in the candle opening at 22:15:00
+        assert_eq!(response.klines[0].time, 1_700_000_100);
+        assert_eq!(
+            (response.klines[0].time - response.alignment_epoch) % response.bucket_secs,
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_index_only_policy_skips_event_record() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.persistence.long_short = PersistencePolicy::IndexOnly;
+        let storage = EventStorage::new(&config).unwrap();
+
+        let event = create_long_short_event("idx_mint", "idx_pda", 1, 1_000, 10);
+        let event_key = storage.generate_event_key(&event);
+        storage.store_event(event).await.unwrap();
+
+        assert!(storage.db.get(event_key.as_bytes()).unwrap().is_none());
+        let order_key = storage.generate_order_key("idx_mint", OrderType::Long, "idx_pda");
+        assert!(storage.db.get(order_key.as_bytes()).unwrap().is_some());
+        let user_order_key = storage.generate_user_order_key("test_user", "idx_mint", "idx_pda");
+        assert!(storage.db.get(user_order_key.as_bytes()).unwrap().is_some());
+
+        // Event queries degrade to an empty page rather than failing
+        let events = storage
+            .query_events(EventQuery {
+                mint_account: "idx_mint".to_string(),
+                page: Some(1),
+                limit: Some(10),
+                order_by: None,
+            })
+            .await
+            .unwrap();
+        assert!(events.events.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_index_only_replay_does_not_reapply_totals() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.persistence.long_short = PersistencePolicy::IndexOnly;
+        let storage = EventStorage::new(&config).unwrap();
+
+        let event = create_long_short_event("idx_replay_mint", "idx_replay_pda", 1, 1_000, 10);
+        storage.store_event(event.clone()).await.unwrap();
+        let margin = |storage: &EventStorage| {
+            storage
+                .get_mint_detail("idx_replay_mint")
+                .unwrap()
+                .unwrap()
+                .total_margin_sol_amount
+        };
+        assert_eq!(margin(&storage), 500_000_000);
+
+        // Only the tm: marker is left to recognise the replay by
+        storage.store_event(event).await.unwrap();
+        assert_eq!(margin(&storage), 500_000_000);
+    }
+
+    #[tokio::test]
+    async fn test_query_events_truncates_at_scan_cap() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.database.max_scan_keys = 5;
+        let storage = EventStorage::new(&config).unwrap();
+
+        for slot in 1..=12u64 {
+            let mut event =
+                create_buy_sell_event("hot_mint", &format!("hot_{}", slot), 1_000_000, 1);
+            if let SpinPetEvent::BuySell(e) = &mut event {
+                e.slot = slot;
+            }
+            storage.store_event(event).await.unwrap();
+        }
+
+        let query = |order_by: &str| EventQuery {
+            mint_account: "hot_mint".to_string(),
+            page: Some(1),
+            limit: Some(50),
+            order_by: Some(order_by.to_string()),
+        };
+
+        // Only the newest 5 events are examined and returned
+        let response = storage.query_events(query("slot_desc")).await.unwrap();
+        assert!(response.truncated);
+        assert_eq!(response.total, 5);
+        assert!(!response.has_next);
+        let slots: Vec<u64> = response
+            .events
+            .iter()
+            .map(|e| storage.get_event_slot(e))
+            .collect();
+        assert_eq!(slots, vec![12, 11, 10, 9, 8]);
+
+        let response = storage.query_events(query("slot_asc")).await.unwrap();
+        let slots: Vec<u64> = response
+            .events
+            .iter()
+            .map(|e| storage.get_event_slot(e))
+            .collect();
+        assert_eq!(slots, vec![8, 9, 10, 11, 12]);
+
+        // A mint under the cap is returned in full
+        config.database.max_scan_keys = 100;
+        drop(storage);
+        let storage = EventStorage::new(&config).unwrap();
+        let response = storage.query_events(query("slot_desc")).await.unwrap();
+        assert!(!response.truncated);
+        assert_eq!(response.total, 12);
+    }
+
+    #[tokio::test]
+    async fn test_query_events_keeps_emission_order_within_transaction() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = create_test_config(&temp_dir);
+        let storage = EventStorage::new(&config).unwrap();
+
+        // Two trades emitted by one transaction, stored out of order
+        for (log_index, sol_amount) in [(1, 2_000), (0, 1_000)] {
+            let mut event = create_buy_sell_event("multi_mint", "multi_sig", sol_amount, 1);
+            event.set_log_index(log_index);
+            storage.store_event(event).await.unwrap();
+        }
+
+        let query = |order_by: &str| EventQuery {
+            mint_account: "multi_mint".to_string(),
+            page: Some(1),
+            limit: Some(50),
+            order_by: Some(order_by.to_string()),
+        };
+        let sol_amounts = |response: EventQueryResponse| -> Vec<u64> {
+            response
+                .events
+                .iter()
+                .map(|e| match e {
+                    SpinPetEvent::BuySell(e) => e.sol_amount,
+                    other => panic!("unexpected event: {:?}", other),
+                })
+                .collect()
+        };
+
+        let response = storage.query_events(query("slot_asc")).await.unwrap();
+        assert_eq!(sol_amounts(response), vec![1_000, 2_000]);
+        let response = storage.query_events(query("slot_desc")).await.unwrap();
+        assert_eq!(sol_amounts(response), vec![2_000, 1_000]);
+    }
+
+    #[tokio::test]
+    async fn test_query_kline_batch_returns_series_per_mint() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = create_test_config(&temp_dir);
+        let storage = EventStorage::new(&config).unwrap();
+        let price = |p: u128| p * PRICE_PRECISION;
+
+        let timestamp = Utc::now();
+        for (mint, close) in [("batch_a", 1), ("batch_b", 2), ("batch_c", 3)] {
+            let mut event =
+                create_buy_sell_event(mint, &format!("{}_sig", mint), 1_000_000, price(close));
+            if let SpinPetEvent::BuySell(e) = &mut event {
+                e.timestamp = timestamp;
+            }
+            storage.store_event(event).await.unwrap();
+        }
+
+        let mints: Vec<String> = ["batch_a", "batch_b", "batch_c", "batch_empty"]
+            .iter()
+            .map(|m| m.to_string())
+            .collect();
+        let response = storage.query_kline_batch(&mints, "m5", 10).await.unwrap();
+
+        assert_eq!(response.interval, "m5");
+        assert_eq!(response.series.len(), 4);
+        for (mint, close) in [("batch_a", 1.0), ("batch_b", 2.0), ("batch_c", 3.0)] {
+            let klines = &response.series[mint];
+            assert_eq!(klines.len(), 1, "{} should have its own candle", mint);
+            assert_eq!(klines[0].close, close);
+        }
+        assert!(response.series["batch_empty"].is_empty());
+
+        // All candles land in the same 5m bucket, so the window collapses to it
+        let bucket = response.series["batch_a"][0].time;
+        assert_eq!(response.window_start, Some(bucket));
+        assert_eq!(response.window_end, Some(bucket));
+    }
+
+    #[tokio::test]
+    async fn test_continuous_open_chains_consecutive_buckets() {
+        let price = |p: u128| p * PRICE_PRECISION;
+        let at = |secs: i64| DateTime::<Utc>::from_timestamp(secs, 0).unwrap();
+
+        for continuous_open in [true, false] {
+            let temp_dir = TempDir::new().unwrap();
+            let mut config = create_test_config(&temp_dir);
+            config.kline.continuous_open = continuous_open;
+            let storage = EventStorage::new(&config).unwrap();
+
+            // Three consecutive 1s buckets, each with two trades
+            let base = 1_700_000_000;
+            for (offset, first, last) in [(0, 10, 12), (1, 15, 11), (2, 9, 14)] {
+                for p in [first, last] {
+                    storage
+                        .process_kline_data("chain_mint", price(p), at(base + offset), 0)
+                        .await
+                        .unwrap();
+                }
+            }
+
+            let klines = storage
+                .query_kline_data(KlineQuery {
+                    mint_account: "chain_mint".to_string(),
+                    interval: "s1".to_string(),
+                    page: Some(1),
+                    limit: Some(10),
+                    order_by: Some("time_asc".to_string()),
+                    fill_gaps: false,
+                    min_update_count: None,
+                })
+                .await
+                .unwrap()
+                .klines;
+            assert_eq!(klines.len(), 3);
+            assert_eq!(klines[0].open, 10.0);
+
+            let opens: Vec<f64> = klines.iter().map(|k| k.open).collect();
+            if continuous_open {
+                assert_eq!(opens, vec![10.0, 12.0, 11.0]);
+                for pair in klines.windows(2) {
+                    assert_eq!(pair[1].open, pair[0].close);
+                }
+            } else {
+                assert_eq!(opens, vec![10.0, 15.0, 9.0]);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_seed_open_after_restart_continues_adjacent_candle() {
+        let price = |p: u128| p * PRICE_PRECISION;
+        let at = |secs: i64| DateTime::<Utc>::from_timestamp(secs, 0).unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.kline.continuous_open = false;
+        config.kline.seed_open_after_restart = true;
+
+        // 1_700_000_100 is an m5 boundary, so all trades below share one m5 candle
+        let base = 1_700_000_100;
+        let storage = EventStorage::new(&config).unwrap();
+        storage
+            .process_kline_data("restart_mint", price(2), at(base), 0)
+            .await
+            .unwrap();
+        drop(storage);
+
+        // Restart, then trades in the next two s30 buckets
+        let storage = EventStorage::new(&config).unwrap();
+        for (offset, p) in [(30, 3), (60, 5)] {
+            storage
+                .process_kline_data("restart_mint", price(p), at(base + offset), 0)
+                .await
+                .unwrap();
+        }
+
+        async fn opens(storage: &EventStorage, interval: &str) -> Vec<f64> {
+            storage
+                .query_kline_data(KlineQuery {
+                    mint_account: "restart_mint".to_string(),
+                    interval: interval.to_string(),
+                    page: Some(1),
+                    limit: Some(10),
+                    order_by: Some("time_asc".to_string()),
+                    fill_gaps: false,
+                    min_update_count: None,
+                })
+                .await
+                .unwrap()
+                .klines
+                .iter()
+                .map(|k| k.open)
+                .collect()
+        }
+
+        // The first post-restart s30 candle continues from the pre-restart close; later ones
+        // open at their first trade as continuous_open is off
+        assert_eq!(opens(&storage, "s30").await, vec![2.0, 2.0, 5.0]);
+        // The m5 candle spanning the restart keeps its original open
+        assert_eq!(opens(&storage, "m5").await, vec![2.0]);
+    }
+
+    #[tokio::test]
+    async fn test_strict_kline_ordering_keeps_newest_close() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = EventStorage::new(&create_test_config(&temp_dir)).unwrap();
+        let now = DateTime::<Utc>::from_timestamp(1_700_000_000, 0).unwrap();
+        let close = || async {
+            storage
+                .query_kline_data(KlineQuery {
+                    mint_account: "strict_mint".to_string(),
+                    interval: "s1".to_string(),
+                    page: Some(1),
+                    limit: Some(1),
+                    order_by: None,
+                    fill_gaps: false,
+                    min_update_count: None,
+                })
+                .await
+                .unwrap()
+                .klines[0]
+                .clone()
+        };
+
+        storage.begin_strict_kline_ordering();
+        for (slot, p) in [(200, 10), (150, 30)] {
+            storage
+                .process_kline_data("strict_mint", p * PRICE_PRECISION, now, slot)
+                .await
+                .unwrap();
+        }
+        let kline = close().await;
+        assert_eq!(kline.close.round(), 10.0);
+        assert_eq!(kline.high.round(), 30.0);
+        assert_eq!(kline.last_slot, 200);
+
+        // Outside strict sections the latest processed trade is the close again
+        storage.end_strict_kline_ordering();
+        assert!(!storage.strict_kline_ordering());
+        storage
+            .process_kline_data("strict_mint", 20 * PRICE_PRECISION, now, 120)
+            .await
+            .unwrap();
+        assert_eq!(close().await.close.round(), 20.0);
+    }
+
+    #[tokio::test]
+    async fn test_fill_gaps_carries_previous_close() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = EventStorage::new(&create_test_config(&temp_dir)).unwrap();
+        let at = |secs: i64| DateTime::<Utc>::from_timestamp(secs, 0).unwrap();
+
+        let base = 1_700_000_000;
+        for (offset, p) in [(0, 10), (0, 12), (4, 20)] {
+            storage
+                .process_kline_data("gap_mint", p * PRICE_PRECISION, at(base + offset), 0)
+                .await
+                .unwrap();
+        }
+
+        let query = |fill_gaps| KlineQuery {
+            mint_account: "gap_mint".to_string(),
+            interval: "s1".to_string(),
+            page: Some(1),
+            limit: Some(10),
+            order_by: Some("time_asc".to_string()),
+            fill_gaps,
+            min_update_count: None,
+        };
+        let sparse = storage.query_kline_data(query(false)).await.unwrap();
+        assert_eq!(sparse.klines.len(), 2);
+
+        let filled = storage.query_kline_data(query(true)).await.unwrap();
+        assert_eq!(filled.total, 5);
+        let times: Vec<u64> = filled.klines.iter().map(|k| k.time).collect();
+        let base = base as u64;
+        assert_eq!(times, (base..=base + 4).collect::<Vec<_>>());
+        for gap in &filled.klines[1..4] {
+            assert!(gap.is_gap);
+            assert_eq!(gap.volume, 0.0);
+            assert_eq!(
+                (gap.open, gap.high, gap.low, gap.close),
+                (12.0, 12.0, 12.0, 12.0)
+            );
+        }
+        assert!(!filled.klines[0].is_gap);
+        assert!(!filled.klines[4].is_gap);
+        assert_eq!(filled.klines[4].close, 20.0);
+    }
+
+    #[tokio::test]
+    async fn test_query_latest_klines_returns_newest_candle_per_mint() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = EventStorage::new(&create_test_config(&temp_dir)).unwrap();
+        let at = |secs: i64| DateTime::<Utc>::from_timestamp(secs, 0).unwrap();
+
+        // "mint_a" is a key prefix of "mint_ab"; each seek must stay within its own mint
+        let base = 1_700_000_000;
+        for (mint, offset, p) in [
+            ("mint_a", 0, 10),
+            ("mint_a", 7, 11),
+            ("mint_ab", 3, 20),
+            ("mint_ab", 1, 21),
+            ("mint_b", 5, 30),
+        ] {
+            storage
+                .process_kline_data(mint, p * PRICE_PRECISION, at(base + offset), 0)
+                .await
+                .unwrap();
+        }
+
+        let mints: Vec<String> = ["mint_a", "mint_ab", "mint_b", "mint_none"]
+            .iter()
+            .map(|m| m.to_string())
+            .collect();
+        let latest = storage.query_latest_klines(&mints, "s1").await.unwrap();
+        assert_eq!(latest.interval, "s1");
+        let newest: Vec<(&str, u64, f64)> = latest
+            .klines
+            .iter()
+            .map(|(mint, k)| (mint.as_str(), k.time - base as u64, k.close))
+            .collect();
+        assert_eq!(
+            newest,
+            vec![
+                ("mint_a", 7, 11.0),
+                ("mint_ab", 3, 20.0),
+                ("mint_b", 5, 30.0)
+            ]
+        );
+
+        assert!(storage.query_latest_klines(&mints, "h1").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_min_update_count_excludes_single_trade_candles() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = EventStorage::new(&create_test_config(&temp_dir)).unwrap();
+        let at = |secs: i64| DateTime::<Utc>::from_timestamp(secs, 0).unwrap();
+
+        // Three trades in the first second, one in the second, two in the third
+        let base = 1_700_000_000;
+        for (offset, p) in [(0, 10), (0, 11), (0, 12), (1, 13), (2, 14), (2, 15)] {
+            storage
+                .process_kline_data("thin_mint", p * PRICE_PRECISION, at(base + offset), 0)
+                .await
+                .unwrap();
+        }
+
+        let query = |min_update_count| KlineQuery {
+            mint_account: "thin_mint".to_string(),
+            interval: "s1".to_string(),
+            page: Some(1),
+            limit: Some(10),
+            order_by: Some("time_asc".to_string()),
+            fill_gaps: false,
+            min_update_count,
+        };
+        let all = storage.query_kline_data(query(None)).await.unwrap();
+        assert_eq!(all.total, 3);
+
+        let dense = storage.query_kline_data(query(Some(2))).await.unwrap();
+        assert_eq!(dense.total, 2);
+        let counts: Vec<(u64, u32)> = dense
+            .klines
+            .iter()
+            .map(|k| (k.time - base as u64, k.update_count))
+            .collect();
+        assert_eq!(counts, vec![(0, 3), (2, 2)]);
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_price_skips_candle_update() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.kline.skip_unchanged_price = true;
+        let storage = EventStorage::new(&config).unwrap();
+
+        let base = 1_700_000_000;
+        for (signature, p) in [("flat_1", 10), ("flat_2", 10), ("flat_3", 12)] {
+            let mut trade =
+                create_buy_sell_event("flat_mint", signature, 1_000_000, p * PRICE_PRECISION);
+            trade.set_timestamp(
+                Utc.timestamp_opt(base, 0).unwrap(),
+                TimestampSource::BlockTime,
+            );
+            storage.store_event(trade).await.unwrap();
+        }
+
+        let klines = storage
+            .query_kline_data(KlineQuery {
+                mint_account: "flat_mint".to_string(),
+                interval: KLINE_INTERVAL_1S.to_string(),
+                page: Some(1),
+                limit: Some(10),
+                order_by: Some("time_asc".to_string()),
+                fill_gaps: false,
+                min_update_count: None,
+            })
+            .await
+            .unwrap()
+            .klines;
+        // The repeated price of flat_2 left the candle alone, the move to 12 updated it
+        assert_eq!(klines.len(), 1);
+        assert_eq!(klines[0].update_count, 2);
+        assert_eq!((klines[0].open, klines[0].close), (10.0, 12.0));
+    }
+
+    #[tokio::test]
+    async fn test_low_disk_refuses_event_storage() {
+        struct LowDisk(AtomicU64);
+        impl crate::services::disk_guard::DiskSpaceProbe for LowDisk {
+            fn free_bytes(&self, _path: &std::path::Path) -> Result<u64> {
+                Ok(self.0.load(Ordering::Relaxed))
+            }
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let mut storage = EventStorage::new(&create_test_config(&temp_dir)).unwrap();
+        let probe = Arc::new(LowDisk(AtomicU64::new(10 * 1024 * 1024)));
+        let guard = Arc::new(DiskSpaceGuard::new("unused", 64, probe.clone()));
+        storage.disk_guard = Some(Arc::clone(&guard));
+
+        assert!(guard.check());
+        assert!(storage.is_read_only());
+        let trade = create_buy_sell_event("disk_mint", "disk_sig", 1_000_000, PRICE_PRECISION);
+        assert!(storage.store_event(trade.clone()).await.is_err());
+        let key = storage.generate_event_key(&trade);
+        assert!(storage.db.get(key.as_bytes()).unwrap().is_none());
+
+        // Background writers are refused too
+        let checkpoint_dir = temp_dir.path().join("low_disk_checkpoint");
+        assert!(storage
+            .create_checkpoint(checkpoint_dir.to_str().unwrap())
+            .is_err());
+        assert!(!checkpoint_dir.exists());
+        assert!(storage.snapshot_mint_details(1_700_000_000).is_err());
+        assert!(storage.expire_orders(i64::MAX).await.is_err());
+
+        // Storage resumes once space is back
+        probe.0.store(128 * 1024 * 1024, Ordering::Relaxed);
+        assert!(!guard.check());
+        storage.store_event(trade).await.unwrap();
+        assert!(storage.db.get(key.as_bytes()).unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_schema_migration_upgrades_stored_values() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = create_test_config(&temp_dir);
+
+        // v1 value: order margin stored under the old field name
+        let key = "or:mig_mint:dn:mig_pda";
+        {
+            let storage = EventStorage::with_migrations(&config, 1, Vec::new()).unwrap();
+            assert_eq!(storage.stored_schema_version().unwrap(), 1);
+            let v1 = serde_json::json!({"order_pda": "mig_pda", "margin": 500});
+            storage
+                .db
+                .put(key.as_bytes(), serde_json::to_vec(&v1).unwrap())
+                .unwrap();
+        }
+
+        let rename_margin = |mut value: serde_json::Value| -> Result<serde_json::Value> {
+            let margin = value["margin"].take();
+            let object = value
+                .as_object_mut()
+                .ok_or_else(|| anyhow::anyhow!("order value is not an object"))?;
+            object.remove("margin");
+            object.insert("margin_sol_amount".to_string(), margin);
+            Ok(value)
+        };
+        let storage = EventStorage::with_migrations(
+            &config,
+            2,
+            vec![SchemaMigration {
+                from_version: 1,
+                prefix: "or:",
+                migrate: rename_margin,
+                rekey: None,
+            }],
+        )
+        .unwrap();
+
+        let value: serde_json::Value =
+            serde_json::from_slice(&storage.db.get(key.as_bytes()).unwrap().unwrap()).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({"order_pda": "mig_pda", "margin_sol_amount": 500})
+        );
+        assert_eq!(storage.stored_schema_version().unwrap(), 2);
+        drop(storage);
+
+        // A build older than the stored schema refuses to open it
+        assert!(EventStorage::with_migrations(&config, 1, Vec::new()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_legacy_event_keys_migrated_and_not_duplicated_on_replay() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = create_test_config(&temp_dir);
+        let mut event = create_buy_sell_event("legacy_mint", "legacy_sig", 1_000, PRICE_PRECISION);
+        event.set_log_index(2);
+
+        // v1 record: no log index in the key or the value
+        let legacy_key = format!("tr:legacy_mint:{:010}:BuySell:legacy_sig", event.slot());
+        {
+            let storage = EventStorage::with_migrations(&config, 1, Vec::new()).unwrap();
+            let mut v1 = serde_json::to_value(&event).unwrap();
+            v1.as_object_mut().unwrap().remove("log_index");
+            storage
+                .db
+                .put(legacy_key.as_bytes(), serde_json::to_vec(&v1).unwrap())
+                .unwrap();
+        }
+
+        let storage = EventStorage::new(&config).unwrap();
+        assert_eq!(storage.stored_schema_version().unwrap(), SCHEMA_VERSION);
+        let tr_keys = |storage: &EventStorage| -> Vec<String> {
+            storage
+                .prefix_scan("tr:legacy_mint:")
+                .map(|item| String::from_utf8_lossy(&item.unwrap().0).to_string())
+                .collect()
+        };
+        assert_eq!(
+            tr_keys(&storage),
+            vec![format!(
+                "tr:legacy_mint:{:010}:0000:BuySell:legacy_sig",
+                event.slot()
+            )]
+        );
+
+        // Replaying the event is recognised as stored and moves the record to its real index
+        storage.store_event(event.clone()).await.unwrap();
+        assert_eq!(tr_keys(&storage), vec![storage.generate_event_key(&event)]);
+        assert_eq!(latest_5m_close(&storage, "legacy_mint").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_legacy_order_timeline_keys_migrated_to_per_event_keys() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = create_test_config(&temp_dir);
+        let mut event = create_long_short_event("opda_mint", "opda_pda", 1, 20, 10);
+        event.set_log_index(1);
+
+        // v2 entry: no log index or signature in the key
+        {
+            let storage = EventStorage::with_migrations(&config, 2, Vec::new()).unwrap();
+            let legacy_key = format!(
+                "opda:opda_pda:{:010}:{}:opda_mint",
+                event.slot(),
+                EVENT_TYPE_LONG_SHORT
+            );
+            storage
+                .db
+                .put(legacy_key.as_bytes(), serde_json::to_vec(&event).unwrap())
+                .unwrap();
+        }
+
+        let storage = EventStorage::new(&config).unwrap();
+        let opda_keys = |storage: &EventStorage| -> Vec<String> {
+            storage
+                .prefix_scan("opda:opda_pda:")
+                .map(|item| String::from_utf8_lossy(&item.unwrap().0).to_string())
+                .collect()
+        };
+        assert_eq!(
+            opda_keys(&storage),
+            vec![storage.generate_order_timeline_key(&event).unwrap()]
+        );
+
+        // A second event of the order in the same slot no longer overwrites the first
+        let mut second = event.clone();
+        if let SpinPetEvent::LongShort(e) = &mut second {
+            e.signature = "sig_opda_second".to_string();
+            e.log_index = 0;
+        }
+        storage.store_event(second).await.unwrap();
+        let timeline = storage
+            .query_order_timeline("opda_pda", None)
+            .await
+            .unwrap();
+        let signatures: Vec<&str> = timeline.events.iter().map(|e| e.signature()).collect();
+        assert_eq!(signatures, vec!["sig_opda_second", "sig_opda_pda"]);
+    }
+
+    #[tokio::test]
+    async fn test_dust_trade_does_not_move_candle() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.kline.min_trade_sol = 1_000_000;
+        let storage = EventStorage::new(&config).unwrap();
+        let price = |p: u128| p * PRICE_PRECISION;
+
+        // A dust trade alone produces no candle
+        storage
+            .store_event(create_buy_sell_event("dust_mint", "dust_1", 10, price(5)))
+            .await
+            .unwrap();
+        assert_eq!(latest_5m_close(&storage, "dust_mint").await, None);
+
+        storage
+            .store_event(create_buy_sell_event(
+                "dust_mint",
+                "big_1",
+                2_000_000,
+                price(2),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(latest_5m_close(&storage, "dust_mint").await, Some(2.0));
+
+        // Sub-threshold trade is stored but leaves the candle untouched
+        storage
+            .store_event(create_buy_sell_event(
+                "dust_mint",
+                "dust_2",
+                999_999,
+                price(9),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(latest_5m_close(&storage, "dust_mint").await, Some(2.0));
+
+        storage
+            .store_event(create_buy_sell_event(
+                "dust_mint",
+                "big_2",
+                1_000_000,
+                price(3),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(latest_5m_close(&storage, "dust_mint").await, Some(3.0));
+
+        let events = storage
+            .query_events(EventQuery {
+                mint_account: "dust_mint".to_string(),
+                page: Some(1),
+                limit: Some(10),
+                order_by: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(events.events.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_realized_pnl_tracks_losses_and_gains() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = EventStorage::new(&create_test_config(&temp_dir)).unwrap();
+        let mint = "pnl_mint";
+
+        let full_close = |order_pda: &str, final_sol_amount: u64| {
+            SpinPetEvent::FullClose(FullCloseEvent {
+                payer: "test_payer".to_string(),
+                user_sol_account: "test_user_sol".to_string(),
+                mint_account: mint.to_string(),
+                is_close_long: true,
+                final_token_amount: 0,
+                final_sol_amount,
+                user_close_profit: 0,
+                latest_price: 20,
+                order_pda: order_pda.to_string(),
+                timestamp: Utc::now(),
+                timestamp_source: TimestampSource::ServerReceive,
+                log_index: 0,
+                signature: format!("close_{}", order_pda),
+                slot: 101,
+            })
+        };
+        let storage = &storage;
+        let realized_pnl = move || async move {
+            storage
+                .query_mint_details(MintDetailsQuery {
+                    mint_accounts: vec![mint.to_string()],
+                })
+                .await
+                .unwrap()
+                .details[0]
+                .total_realized_pnl
+        };
+
+        // Both orders open with 500_000_000 lamports of margin
+        for pda in ["pnl_loss", "pnl_win"] {
+            storage
+                .store_event(create_long_short_event(mint, pda, 1, 20, 10))
+                .await
+                .unwrap();
+        }
+        assert_eq!(realized_pnl().await, 0);
+
+        // Losing close returns less than the margin
+        storage
+            .store_event(full_close("pnl_loss", 400_000_000))
+            .await
+            .unwrap();
+        assert_eq!(realized_pnl().await, -100_000_000);
+
+        // Winning close returns more than the margin
+        storage
+            .store_event(full_close("pnl_win", 750_000_000))
+            .await
+            .unwrap();
+        assert_eq!(realized_pnl().await, 150_000_000);
+    }
+
+    #[tokio::test]
+    async fn test_order_timeline_returns_lifecycle_in_slot_order() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = EventStorage::new(&create_test_config(&temp_dir)).unwrap();
+        let mint = "timeline_mint";
+
+        let mut open = create_long_short_event(mint, "timeline_pda", 1, 20, 10);
+        if let SpinPetEvent::LongShort(e) = &mut open {
+            e.slot = 200;
+        }
+        let partial_close = SpinPetEvent::PartialClose(PartialCloseEvent {
+            payer: "test_payer".to_string(),
+            user_sol_account: "test_user_sol".to_string(),
+            mint_account: mint.to_string(),
+            is_close_long: true,
+            final_token_amount: 4,
+            final_sol_amount: 300,
+            user_close_profit: 2,
+            latest_price: 25,
+            order_pda: "timeline_pda".to_string(),
+            order_type: 1,
+            mint: mint.to_string(),
+            user: "test_user".to_string(),
+            lock_lp_start_price: 20,
+            lock_lp_end_price: 40,
+            lock_lp_sol_amount: 1_000_000_000,
+            lock_lp_token_amount: 500_000_000,
+            start_time: 1758343400,
+            end_time: 1758343800,
+            margin_sol_amount: 300_000_000,
+            borrow_amount: 900_000_000,
+            position_asset_amount: 6,
+            borrow_fee: 250,
+            timestamp: Utc::now(),
+            timestamp_source: TimestampSource::ServerReceive,
+            log_index: 0,
+            signature: "sig_timeline_partial".to_string(),
+            slot: 1_000,
+        });
+        let full_close = SpinPetEvent::FullClose(FullCloseEvent {
+            payer: "test_payer".to_string(),
+            user_sol_account: "test_user_sol".to_string(),
+            mint_account: mint.to_string(),
+            is_close_long: true,
+            final_token_amount: 6,
+            final_sol_amount: 450,
+            user_close_profit: 3,
+            latest_price: 30,
+            order_pda: "timeline_pda".to_string(),
+            timestamp: Utc::now(),
+            timestamp_source: TimestampSource::ServerReceive,
+            log_index: 0,
+            signature: "sig_timeline_close".to_string(),
+            slot: 10_000,
+        });
+
+        // Stored out of order, alongside another order on the same mint
+        for event in [
+            full_close,
+            create_long_short_event(mint, "other_pda", 1, 20, 10),
+            open,
+            partial_close,
+        ] {
+            storage.store_event(event).await.unwrap();
+        }
+
+        let timeline = storage
+            .query_order_timeline("timeline_pda", None)
+            .await
+            .unwrap();
+        assert_eq!(timeline.order_pda, "timeline_pda");
+        let steps: Vec<(u64, &str)> = timeline
+            .events
+            .iter()
+            .map(|event| match event {
+                SpinPetEvent::LongShort(e) => (e.slot, "open"),
+                SpinPetEvent::PartialClose(e) => (e.slot, "partial_close"),
+                SpinPetEvent::FullClose(e) => (e.slot, "full_close"),
+                _ => (0, "other"),
+            })
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                (200, "open"),
+                (1_000, "partial_close"),
+                (10_000, "full_close")
+            ]
+        );
+
+        assert!(storage
+            .query_order_timeline("unknown_pda", None)
+            .await
+            .unwrap()
+            .events
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_order_timeline_separates_pda_shared_by_two_mints() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = EventStorage::new(&create_test_config(&temp_dir)).unwrap();
+
+        // Same PDA string, slot and event type under two mints
+        for mint in ["shared_mint_b", "shared_mint_a"] {
+            storage
+                .store_event(create_long_short_event(mint, "shared_pda", 1, 20, 10))
+                .await
+                .unwrap();
+        }
+
+        let all = storage
+            .query_order_timeline("shared_pda", None)
+            .await
+            .unwrap();
+        assert_eq!(all.mints, vec!["shared_mint_a", "shared_mint_b"]);
+        assert_eq!(all.mint_account, None);
+        assert_eq!(all.events.len(), 2);
+
+        let one = storage
+            .query_order_timeline("shared_pda", Some("shared_mint_b"))
+            .await
+            .unwrap();
+        assert_eq!(one.mint_account.as_deref(), Some("shared_mint_b"));
+        assert_eq!(one.mints, all.mints);
+        assert_eq!(one.events.len(), 1);
+        assert_eq!(one.events[0].mint_account(), "shared_mint_b");
+    }
+
+    #[tokio::test]
+    async fn test_mint_detail_snapshots_record_history_of_updated_mints() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.stats.mint_snapshot_interval_secs = 60;
+        let storage = EventStorage::new(&config).unwrap();
+        let mint = "snapshot_mint";
+
+        storage
+            .store_event(create_token_created_event(mint, ""))
+            .await
+            .unwrap();
+        storage
+            .store_event(create_buy_sell_event(mint, "snap_sig_1", 1_000, 10))
+            .await
+            .unwrap();
+        assert_eq!(storage.snapshot_mint_details(1_000).unwrap(), 1);
+        // Mints not updated since the previous snapshot are skipped
+        assert_eq!(storage.snapshot_mint_details(1_500).unwrap(), 0);
+        storage
+            .store_event(create_buy_sell_event(mint, "snap_sig_2", 1_000, 20))
+            .await
+            .unwrap();
+        assert_eq!(storage.snapshot_mint_details(2_000).unwrap(), 1);
+
+        let history = storage
+            .query_mint_detail_history(mint, None, None)
+            .await
+            .unwrap();
+        let points: Vec<_> = history
+            .snapshots
+            .iter()
+            .map(|snapshot| (snapshot.ts, snapshot.detail.latest_price))
+            .collect();
+        assert_eq!(points, vec![(1_000, Some(10)), (2_000, Some(20))]);
+
+        let later = storage
+            .query_mint_detail_history(mint, Some(1_500), Some(2_000))
+            .await
+            .unwrap();
+        assert_eq!(later.snapshots.len(), 1);
+        assert_eq!(later.snapshots[0].ts, 2_000);
+
+        // Retention drops snapshots older than the cutoff
+        assert_eq!(storage.prune_mint_snapshots(1_500).unwrap(), 1);
+        let pruned = storage
+            .query_mint_detail_history(mint, None, None)
+            .await
+            .unwrap();
+        assert_eq!(pruned.snapshots.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_user_position_summary_sums_open_orders_by_side() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = EventStorage::new(&create_test_config(&temp_dir)).unwrap();
+
+        for event in [
+            create_long_short_event("summary_mint_a", "long_a", 1, 20, 100),
+            create_long_short_event("summary_mint_b", "long_b", 1, 30, 200),
+            create_long_short_event("summary_mint_a", "short_a", 2, 40, 300),
+        ] {
+            storage.store_event(event).await.unwrap();
+        }
+        // Expired orders are no longer open
+        let mut expired = serde_json::json!({
+            "order_type": 2,
+            "margin_sol_amount": 7,
+            "borrow_amount": 7,
+            "position_asset_amount": 7,
+            "status": "expired",
+        });
+        storage
+            .db
+            .put(b"uo:test_user:summary_mint_b:short_x", expired.to_string())
+            .unwrap();
+        expired["status"] = "open".into();
+        storage
+            .db
+            .put(b"uo:other_user:summary_mint_b:short_y", expired.to_string())
+            .unwrap();
+
+        let summary = storage
+            .query_user_position_summary("test_user")
+            .await
+            .unwrap();
+        assert_eq!(summary.user, "test_user");
+        assert_eq!(
+            summary.longs,
+            PositionSideSummary {
+                order_count: 2,
+                margin_sol_amount: 1_000_000_000,
+                borrow_amount: 3_000_000_000,
+                position_asset_amount: 300,
+            }
+        );
+        assert_eq!(
+            summary.shorts,
+            PositionSideSummary {
+                order_count: 1,
+                margin_sol_amount: 500_000_000,
+                borrow_amount: 1_500_000_000,
+                position_asset_amount: 300,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_query_recent_trades_projects_direction() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = EventStorage::new(&create_test_config(&temp_dir)).unwrap();
+        let mint = "trade_mint";
+
+        let with_slot = |mut event: SpinPetEvent, slot: u64| {
+            match &mut event {
+                SpinPetEvent::BuySell(e) => e.slot = slot,
+                SpinPetEvent::LongShort(e) => e.slot = slot,
+                SpinPetEvent::TokenCreated(e) => e.slot = slot,
+                _ => {}
+            }
+            event
+        };
+        let full_close = SpinPetEvent::FullClose(FullCloseEvent {
+            payer: "test_payer".to_string(),
+            user_sol_account: "test_user_sol".to_string(),
+            mint_account: mint.to_string(),
+            is_close_long: true,
+            final_token_amount: 10,
+            final_sol_amount: 700,
+            user_close_profit: 5,
+            latest_price: 40,
+            order_pda: "long_a".to_string(),
+            timestamp: Utc::now(),
+            timestamp_source: TimestampSource::ServerReceive,
+            log_index: 0,
+            signature: "sig_close".to_string(),
+            slot: 105,
+        });
+
+        for event in [
+            with_slot(create_token_created_event(mint, ""), 100),
+            with_slot(create_buy_sell_event(mint, "sig_buy", 500, 10), 101),
+            with_slot(create_long_short_event(mint, "long_a", 1, 20, 5), 102),
+            with_slot(create_long_short_event(mint, "short_a", 2, 30, 5), 103),
+            full_close,
+        ] {
+            storage.store_event(event).await.unwrap();
+        }
+
+        let response = storage.query_recent_trades(mint, 10).await.unwrap();
+        // TokenCreated is not a trade; newest first
+        assert_eq!(response.trades.len(), 4);
+
+        let close = &response.trades[0];
+        assert_eq!(
+            (close.price, close.sol_amount, close.is_buy),
+            (40, 700, false)
+        );
+        let short = &response.trades[1];
+        assert_eq!(
+            (short.price, short.sol_amount, short.is_buy),
+            (30, 500_000_000, false)
+        );
+        let long = &response.trades[2];
+        assert_eq!(
+            (long.price, long.sol_amount, long.is_buy),
+            (20, 500_000_000, true)
+        );
+        let buy = &response.trades[3];
+        assert_eq!((buy.price, buy.sol_amount, buy.is_buy), (10, 500, true));
+        assert!(buy.ts > 0);
+
+        let limited = storage.query_recent_trades(mint, 2).await.unwrap();
+        assert_eq!(limited.trades.len(), 2);
+        assert_eq!(limited.trades[0].price, 40);
+    }
+
+    #[tokio::test]
+    async fn test_expiry_sweep_moves_past_end_time_orders() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = create_test_config(&temp_dir);
+        let storage = EventStorage::new(&config).unwrap();
+
+        // end_time 1758343800
+        let stale = create_long_short_event("exp_mint", "stale_pda", 1, 2_000, 10);
+        let mut live = create_long_short_event("exp_mint", "live_pda", 1, 1_000, 10);
+        if let SpinPetEvent::LongShort(e) = &mut live {
+            e.end_time = 1758350000;
+        }
+        storage.store_event(stale).await.unwrap();
+        storage.store_event(live).await.unwrap();
+
+        let expired = storage.expire_orders(1758343900).await.unwrap();
+        assert_eq!(expired, 1);
+
+        let query = |include_expired| OrderQuery {
+            mint_account: "exp_mint".to_string(),
+            order_type: "down_orders".to_string(),
+            page: Some(1),
+            limit: Some(10),
+            include_expired,
+        };
+        let open = storage.query_orders(query(false)).await.unwrap();
+        assert_eq!(open.total, 1);
+        assert_eq!(open.orders[0].order_pda, "live_pda");
+        assert_eq!(open.orders[0].status, OrderStatus::Open);
+
+        let all = storage.query_orders(query(true)).await.unwrap();
+        assert_eq!(all.total, 2);
+        let stale = all
+            .orders
+            .iter()
+            .find(|o| o.order_pda == "stale_pda")
+            .unwrap();
+        assert_eq!(stale.status, OrderStatus::Expired);
+
+        // The user's order view is marked expired too, and a second sweep is a no-op
+        let user_order_key = storage.generate_user_order_key("test_user", "exp_mint", "stale_pda");
+        let user_order: OrderData =
+            serde_json::from_slice(&storage.db.get(user_order_key).unwrap().unwrap()).unwrap();
+        assert_eq!(user_order.status, OrderStatus::Expired);
+        assert_eq!(storage.expire_orders(1758343900).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_expiry_sweep_skips_order_closed_after_scan() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Arc::new(EventStorage::new(&create_test_config(&temp_dir)).unwrap());
+        storage
+            .store_event(create_long_short_event(
+                "race_mint",
+                "race_pda",
+                1,
+                2_000,
+                10,
+            ))
+            .await
+            .unwrap();
+        let order_key = storage.generate_order_key("race_mint", OrderType::Long, "race_pda");
+
+        // The sweep finds the order, then waits for the mint lock while the order is closed
+        let guard = storage.mint_lock("race_mint").lock().await;
+        let sweep = tokio::spawn({
+            let storage = Arc::clone(&storage);
+            async move { storage.expire_orders(1758343900).await }
+        });
+        tokio::task::yield_now().await;
+        storage.db.delete(order_key.as_bytes()).unwrap();
+        drop(guard);
+
+        assert_eq!(sweep.await.unwrap().unwrap(), 0);
+        let expired_key =
+            storage.generate_expired_order_key("race_mint", OrderType::Long, "race_pda");
+        assert!(storage.db.get(expired_key.as_bytes()).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_opens_with_same_keys() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = create_test_config(&temp_dir);
+        let storage = EventStorage::new(&config).unwrap();
+
+        for i in 0..5 {
+            storage
+                .store_event(create_buy_sell_event(
+                    "ckpt_mint",
+                    &format!("ckpt_{}", i),
+                    1_000_000,
+                    1,
+                ))
+                .await
+                .unwrap();
+        }
+
+        let checkpoint_dir = TempDir::new().unwrap();
+        let path = checkpoint_dir.path().join("snapshot");
+        let info = storage.create_checkpoint(path.to_str().unwrap()).unwrap();
+        assert!(info.size_bytes > 0);
+
+        // Existing paths are refused
+        assert!(storage.create_checkpoint(path.to_str().unwrap()).is_err());
+
+        let keys = |db: &DB| -> Vec<Box<[u8]>> {
+            db.iterator(IteratorMode::Start)
+                .map(|item| item.unwrap().0)
+                .collect()
+        };
+        let snapshot = DB::open_for_read_only(&Options::default(), &path, false).unwrap();
+        let snapshot_keys = keys(&snapshot);
+        assert!(!snapshot_keys.is_empty());
+        assert_eq!(snapshot_keys, keys(&storage.db));
+    }
+
+    #[tokio::test]
+    async fn test_excluded_wallet_trades_skip_mint_totals() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.stats.excluded_wallets.insert("mm_bot".to_string());
+        let storage = EventStorage::new(&config).unwrap();
+
+        let mut bot_trade = create_buy_sell_event("wash_mint", "bot_sig", 5_000_000, 7);
+        if let SpinPetEvent::BuySell(e) = &mut bot_trade {
+            e.payer = "mm_bot".to_string();
+        }
+        storage.store_event(bot_trade).await.unwrap();
+
+        let detail = storage.get_mint_detail("wash_mint").unwrap().unwrap();
+        assert_eq!(detail.total_sol_amount, 0);
+        // The price still follows the excluded trade, and the raw event is kept
+        assert_eq!(detail.latest_price, Some(7));
+        let events = storage
+            .query_events(EventQuery {
+                mint_account: "wash_mint".to_string(),
+                page: Some(1),
+                limit: Some(10),
+                order_by: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(events.events.len(), 1);
+
+        storage
+            .store_event(create_buy_sell_event("wash_mint", "user_sig", 1_000_000, 8))
+            .await
+            .unwrap();
+        let detail = storage.get_mint_detail("wash_mint").unwrap().unwrap();
+        assert_eq!(detail.total_sol_amount, 1_000_000);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_mint_detail_updates_keep_exact_totals() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Arc::new(EventStorage::new(&create_test_config(&temp_dir)).unwrap());
+
+        let tasks: Vec<_> = (1..=200u64)
+            .map(|i| {
+                let storage = storage.clone();
+                tokio::spawn(async move {
+                    let event = create_buy_sell_event("hot_mint", &format!("hot_sig_{}", i), i, 5);
+                    storage.process_event_for_mint_detail(&event).await
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        let detail = storage.get_mint_detail("hot_mint").unwrap().unwrap();
+        assert_eq!(detail.total_sol_amount, (1..=200u64).sum::<u64>());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_merge_counters_sum_exactly() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.database.merge_counters = true;
+        let storage = Arc::new(EventStorage::new(&config).unwrap());
+
+        let tasks: Vec<_> = (1..=200u64)
+            .map(|i| {
+                let storage = storage.clone();
+                tokio::spawn(async move {
+                    let event =
+                        create_buy_sell_event("merge_mint", &format!("merge_sig_{}", i), i, 5);
+                    storage.process_event_for_mint_detail(&event).await
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        // The in: record never carries the total, the counter holds all 200 merges
+        let raw = storage.db.get(b"in:merge_mint").unwrap().unwrap();
+        let stored: MintDetailData = serde_json::from_slice(&raw).unwrap();
+        assert_eq!(stored.total_sol_amount, 0);
+
+        let expected = (1..=200u64).sum::<u64>();
+        let detail = storage.get_mint_detail("merge_mint").unwrap().unwrap();
+        assert_eq!(detail.total_sol_amount, expected);
+        let response = storage
+            .query_mint_details(MintDetailsQuery {
+                mint_accounts: vec!["merge_mint".to_string()],
+            })
+            .await
+            .unwrap();
+        assert_eq!(response.details[0].total_sol_amount, expected);
+    }
+
+    #[tokio::test]
+    async fn test_price_jump_outside_bound_skips_kline_and_detail() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.kline.max_price_jump_ratio = 100.0;
+        let storage = EventStorage::new(&config).unwrap();
+        let mint = "jump_mint";
+
+        for (sig, price) in [("jump_1", 10), ("jump_2", 10_000), ("jump_3", 12)] {
+            storage
+                .store_event(create_buy_sell_event(
+                    mint,
+                    sig,
+                    1_000,
+                    price * PRICE_PRECISION,
+                ))
+                .await
+                .unwrap();
+        }
+
+        let detail = storage.get_mint_detail(mint).unwrap().unwrap();
+        assert_eq!(detail.latest_price, Some(12 * PRICE_PRECISION));
+        assert_eq!(detail.rejected_price_count, 1);
+
+        let klines = storage
+            .query_kline_data(KlineQuery {
+                mint_account: mint.to_string(),
+                interval: "m5".to_string(),
+                page: Some(1),
+                limit: Some(10),
+                order_by: None,
+                fill_gaps: false,
+                min_update_count: None,
+            })
+            .await
+            .unwrap()
+            .klines;
+        assert!(klines.iter().all(|k| k.high <= 12.0));
+        assert_eq!(klines[0].close, 12.0);
+
+        // The rejected event itself is still stored
+        let events = storage
+            .query_events(EventQuery {
+                mint_account: mint.to_string(),
+                page: Some(1),
+                limit: Some(10),
+                order_by: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(events.events.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_confirmed_price_jump_is_accepted_as_repricing() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.kline.max_price_jump_ratio = 100.0;
+        config.kline.price_jump_confirmations = 3;
+        let storage = EventStorage::new(&config).unwrap();
+        let mint = "reprice_mint";
+
+        // An isolated outlier resets the run, so only the last three agreeing prices confirm
+        for (sig, price) in [
+            ("reprice_1", 10),
+            ("reprice_2", 10_000),
+            ("reprice_3", 11),
+            ("reprice_4", 5_000),
+            ("reprice_5", 5_500),
+        ] {
+            storage
+                .store_event(create_buy_sell_event(
+                    mint,
+                    sig,
+                    1_000,
+                    price * PRICE_PRECISION,
+                ))
+                .await
+                .unwrap();
+        }
+        let detail = storage.get_mint_detail(mint).unwrap().unwrap();
+        assert_eq!(detail.latest_price, Some(11 * PRICE_PRECISION));
+        assert_eq!(detail.pending_price_count, 2);
+        assert_eq!(latest_5m_close(&storage, mint).await, Some(11.0));
+
+        storage
+            .store_event(create_buy_sell_event(
+                mint,
+                "reprice_6",
+                1_000,
+                6_000 * PRICE_PRECISION,
+            ))
+            .await
+            .unwrap();
+
+        let detail = storage.get_mint_detail(mint).unwrap().unwrap();
+        assert_eq!(detail.latest_price, Some(6_000 * PRICE_PRECISION));
+        assert_eq!(detail.rejected_price_count, 3);
+        assert_eq!(detail.pending_price, None);
+        assert_eq!(detail.pending_price_count, 0);
+        assert_eq!(latest_5m_close(&storage, mint).await, Some(6_000.0));
+    }
+
+    #[tokio::test]
+    async fn test_milestone_discount_writes_one_user_transaction() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = EventStorage::new(&create_test_config(&temp_dir)).unwrap();
+
+        storage
+            .store_event(SpinPetEvent::MilestoneDiscount(MilestoneDiscountEvent {
+                payer: "milestone_payer".to_string(),
+                mint_account: "milestone_mint".to_string(),
+                curve_account: "test_curve".to_string(),
+                swap_fee: 50,
+                borrow_fee: 100,
+                fee_discount_flag: 1,
+                timestamp: Utc::now(),
+                timestamp_source: TimestampSource::ServerReceive,
+                log_index: 0,
+                signature: "milestone_sig".to_string(),
+                slot: 300,
+            }))
+            .await
+            .unwrap();
+
+        let prefix = "us:milestone_payer:";
+        let keys: Vec<String> = storage
+            .prefix_scan(prefix)
+            .map(|item| String::from_utf8_lossy(&item.unwrap().0).into_owned())
+            .take_while(|key| key.starts_with(prefix))
+            .collect();
+        assert_eq!(keys, vec!["us:milestone_payer:milestone_mint:0000000300"]);
+
+        let value = storage.db.get(keys[0].as_bytes()).unwrap().unwrap();
+        let record: UserTransactionData = serde_json::from_slice(&value).unwrap();
+        assert_eq!(record.event_type, "milestone_discount");
+        assert_eq!(record.signature, "milestone_sig");
+    }
+
+    #[tokio::test]
+    async fn test_events_by_slot_span_all_mints() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = EventStorage::new(&create_test_config(&temp_dir)).unwrap();
+
+        let at_slot = |event: SpinPetEvent, slot: u64| {
+            let mut event = event;
+            match &mut event {
+                SpinPetEvent::BuySell(e) => e.slot = slot,
+                SpinPetEvent::LongShort(e) => e.slot = slot,
+                _ => unreachable!(),
+            }
+            event
+        };
+        let events = vec![
+            at_slot(
+                create_buy_sell_event("slot_mint_a", "slot_sig_1", 1_000, 5),
+                500,
+            ),
+            at_slot(
+                create_long_short_event("slot_mint_b", "slot_pda", 1, 20, 10),
+                500,
+            ),
+            at_slot(
+                create_buy_sell_event("slot_mint_a", "slot_sig_2", 1_000, 6),
+                501,
+            ),
+        ];
+        for event in events {
+            storage.store_event(event).await.unwrap();
+        }
+
+        let at_500 = storage.query_events_by_slot(500).await.unwrap();
+        assert_eq!(at_500.slot, 500);
+        assert_eq!(at_500.events.len(), 2);
+        let mut mints: Vec<&str> = at_500.events.iter().map(|e| e.mint_account()).collect();
+        mints.sort();
+        assert_eq!(mints, vec!["slot_mint_a", "slot_mint_b"]);
+        assert!(at_500.events.iter().all(|e| e.slot() == 500));
+
+        assert_eq!(
+            storage
+                .query_events_by_slot(501)
+                .await
+                .unwrap()
+                .events
+                .len(),
+            1
+        );
+        assert!(storage
+            .query_events_by_slot(502)
+            .await
+            .unwrap()
+            .events
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_ticker_open_is_close_before_window() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.kline.continuous_open = false;
+        let storage = EventStorage::new(&config).unwrap();
+        let mint = "ticker_open_mint";
+
+        let mut old_trade = create_buy_sell_event(mint, "open_old", 500, 2 * PRICE_PRECISION);
+        if let SpinPetEvent::BuySell(e) = &mut old_trade {
+            e.timestamp = Utc::now() - chrono::Duration::hours(25);
+        }
+        storage.store_event(old_trade).await.unwrap();
+        for (signature, price) in [("open_new_1", 4), ("open_new_2", 6)] {
+            storage
+                .store_event(create_buy_sell_event(
+                    mint,
+                    signature,
+                    500,
+                    price * PRICE_PRECISION,
+                ))
+                .await
+                .unwrap();
+        }
+
+        // The window opened at the last price before it, not at its first trade
+        let ticker = storage.query_ticker(mint).await.unwrap().unwrap();
+        assert_eq!(ticker.trades_24h, 2);
+        assert_eq!(ticker.open_price_24h, Some(2.0));
+        let change = ticker.price_change_24h_pct.unwrap();
+        assert!((change - 200.0).abs() < 1e-9, "change {}", change);
+    }
+
+    #[tokio::test]
+    async fn test_ticker_combines_price_volume_and_counts() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = EventStorage::new(&create_test_config(&temp_dir)).unwrap();
+        let mint = "ticker_mint";
+        let first_price = 2 * PRICE_PRECISION;
+        let latest_price = 3 * PRICE_PRECISION;
+
+        for (pda, order_type) in [("long_1", 1), ("long_2", 1), ("short_1", 2)] {
+            storage
+                .store_event(create_long_short_event(
+                    mint,
+                    pda,
+                    order_type,
+                    first_price,
+                    10,
+                ))
+                .await
+                .unwrap();
+        }
+        for (signature, price) in [("tick_1", first_price), ("tick_2", latest_price)] {
+            storage
+                .store_event(create_buy_sell_event(mint, signature, 500, price))
+                .await
+                .unwrap();
+        }
+        storage
+            .store_event(SpinPetEvent::ForceLiquidate(ForceLiquidateEvent {
+                payer: "liquidator".to_string(),
+                mint_account: mint.to_string(),
+                order_pda: "long_2".to_string(),
+                timestamp: Utc::now(),
+                timestamp_source: TimestampSource::ServerReceive,
+                log_index: 0,
+                signature: "ticker_liq".to_string(),
+                slot: 101,
+            }))
+            .await
+            .unwrap();
+
+        let detail = storage.get_mint_detail(mint).unwrap().unwrap();
+        let ticker = storage.query_ticker(mint).await.unwrap().unwrap();
+        assert_eq!(ticker.latest_price, Some(latest_price));
+        assert_eq!(ticker.latest_price, detail.latest_price);
+        assert_eq!(ticker.volume_24h, 1_000);
+        assert_eq!(ticker.trades_24h, 2);
+
+        // Opened at the first trade price, now 50% higher
+        assert_eq!(ticker.open_price_24h, Some(2.0));
+        let change = ticker.price_change_24h_pct.unwrap();
+        assert!((change - 50.0).abs() < 1e-9, "change {}", change);
+
+        assert_eq!(ticker.open_long_orders, 1);
+        assert_eq!(ticker.open_short_orders, 1);
+        assert_eq!(ticker.total_force_liquidations, 1);
+        assert_eq!(
+            ticker.total_force_liquidations,
+            detail.total_force_liquidations
+        );
+        assert!(!ticker.truncated);
+
+        assert!(storage
+            .query_ticker("unknown_mint")
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_order_type_is_quarantined() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = create_test_config(&temp_dir);
+        assert_eq!(
+            config.orders.unknown_order_type,
+            UnknownOrderTypeMode::Quarantine
+        );
+        let storage = EventStorage::new(&config).unwrap();
+        let mint = "odd_mint";
+
+        storage
+            .store_event(create_long_short_event(mint, "odd_pda", 0, 1_000, 10))
+            .await
+            .unwrap();
+        storage
+            .store_event(create_long_short_event(mint, "long_pda", 1, 1_000, 10))
+            .await
+            .unwrap();
+
+        let quarantined = storage
+            .db
+            .get(
+                storage
+                    .generate_quarantined_order_key(mint, "odd_pda")
+                    .as_bytes(),
+            )
+            .unwrap()
+            .unwrap();
+        let order: OrderData = serde_json::from_slice(&quarantined).unwrap();
+        assert_eq!(order.order_type, 0);
+
+        // Neither side's index nor the user's orders picked it up
+        for order_type in [OrderType::Long, OrderType::Short] {
+            let key = storage.generate_order_key(mint, order_type, "odd_pda");
+            assert!(storage.db.get(key.as_bytes()).unwrap().is_none());
+        }
+        let user_key = storage.generate_user_order_key("test_user", mint, "odd_pda");
+        assert!(storage.db.get(user_key.as_bytes()).unwrap().is_none());
 
-        let total = all_orders.len();
-        let offset = (page - 1) * limit;
-        let has_prev = page > 1;
-        let has_next = offset + limit < total;
+        // Known types are indexed as usual
+        let long_key = storage.generate_order_key(mint, OrderType::Long, "long_pda");
+        assert!(storage.db.get(long_key.as_bytes()).unwrap().is_some());
+    }
 
-        // Pagination
-        let orders = all_orders
-            .into_iter()
-            .skip(offset)
-            .take(limit)
-            .collect::<Vec<_>>();
+    #[tokio::test]
+    async fn test_full_close_of_short_removes_up_order() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = EventStorage::new(&create_test_config(&temp_dir)).unwrap();
+        let mint = "short_mint";
+
+        // Key generation from the raw type agrees with close-type inference
+        for raw in [1, 2] {
+            let order_type = OrderType::from_raw(raw);
+            assert_eq!(
+                storage.generate_order_key(mint, order_type, "pda"),
+                storage.generate_order_key(mint, OrderType::from_close_long(raw == 1), "pda")
+            );
+        }
 
-        Ok(UserOrderQueryResponse {
-            orders,
-            total,
-            user: user.clone(),
-            mint_account: mint_account.clone(),
-            page,
-            limit,
-            has_next,
-            has_prev,
-        })
+        storage
+            .store_event(create_long_short_event(mint, "short_pda", 2, 1_000, 10))
+            .await
+            .unwrap();
+        let order_key = storage.generate_order_key(mint, OrderType::Short, "short_pda");
+        assert!(order_key.contains(":up:"));
+        assert!(storage.db.get(order_key.as_bytes()).unwrap().is_some());
+
+        storage
+            .store_event(SpinPetEvent::FullClose(FullCloseEvent {
+                payer: "test_payer".to_string(),
+                user_sol_account: "test_user_sol".to_string(),
+                mint_account: mint.to_string(),
+                is_close_long: false,
+                final_token_amount: 0,
+                final_sol_amount: 0,
+                user_close_profit: 0,
+                latest_price: 20,
+                order_pda: "short_pda".to_string(),
+                timestamp: Utc::now(),
+                timestamp_source: TimestampSource::ServerReceive,
+                log_index: 0,
+                signature: "close_short_pda".to_string(),
+                slot: 101,
+            }))
+            .await
+            .unwrap();
+        assert!(storage.db.get(order_key.as_bytes()).unwrap().is_none());
+        let user_order_key = storage.generate_user_order_key("test_user", mint, "short_pda");
+        assert!(storage.db.get(user_order_key.as_bytes()).unwrap().is_none());
     }
 
-    /// Create test order data for testing purposes
-    #[allow(dead_code)]
-    pub fn create_test_order_data(&self, user: &str, mint: &str, order_pda: &str) -> Result<()> {
-        let test_order = OrderData {
-            order_type: 2, // up order (short)
-            mint: mint.to_string(),
-            user: user.to_string(),
-            lock_lp_start_price: 100000000000000000000u128, // Example price
-            lock_lp_end_price: 120000000000000000000u128,   // Example price
-            lock_lp_sol_amount: 1000000000,                 // 1 SOL in lamports
-            lock_lp_token_amount: 500000000,
-            start_time: 1758343400,       // Example timestamp
-            end_time: 1758343800,         // Example timestamp
-            margin_sol_amount: 500000000, // 0.5 SOL
-            borrow_amount: 1500000000,
-            position_asset_amount: 2000000000,
-            borrow_fee: 250,
-            order_pda: order_pda.to_string(),
-            // Initialize with defaults - will be enriched later
-            latest_price: 0,
-            latest_trade_time: 0,
-            name: String::new(),
-            symbol: String::new(),
-            image: String::new(),
-        };
-
-        // Store the order data
-        let order_key = self.generate_order_key(mint, test_order.order_type, order_pda);
-        let user_order_key = self.generate_user_order_key(user, mint, order_pda);
-        let order_value = serde_json::to_vec(&test_order)?;
+    #[tokio::test]
+    async fn test_recent_liquidations_interleave_mints_by_slot() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = create_test_config(&temp_dir);
+        let storage = EventStorage::new(&config).unwrap();
 
-        // Use a batch for atomicity
-        let mut batch = rocksdb::WriteBatch::default();
-        batch.put(order_key.as_bytes(), &order_value);
-        batch.put(user_order_key.as_bytes(), &order_value);
+        for (mint, pda, order_type) in [
+            ("liq_mint_a", "pda_a1", 1),
+            ("liq_mint_a", "pda_a2", 2),
+            ("liq_mint_b", "pda_b1", 1),
+        ] {
+            storage
+                .store_event(create_long_short_event(mint, pda, order_type, 1000, 1000))
+                .await
+                .unwrap();
+        }
 
-        self.db.write(batch)?;
+        for (mint, pda, slot) in [
+            ("liq_mint_a", "pda_a1", 200),
+            ("liq_mint_b", "pda_b1", 300),
+            ("liq_mint_a", "pda_a2", 400),
+        ] {
+            storage
+                .store_event(SpinPetEvent::ForceLiquidate(ForceLiquidateEvent {
+                    payer: "liquidator".to_string(),
+                    mint_account: mint.to_string(),
+                    order_pda: pda.to_string(),
+                    timestamp: Utc::now(),
+                    timestamp_source: TimestampSource::ServerReceive,
+                    log_index: 0,
+                    signature: format!("liq_sig_{}", pda),
+                    slot,
+                }))
+                .await
+                .unwrap();
+        }
 
-        info!(
-            "💾 Test order created successfully: user={}, mint={}, order_pda={}",
-            user, mint, order_pda
+        let recent = storage.query_recent_liquidations(10, None).await.unwrap();
+        let order: Vec<(&str, u64)> = recent
+            .liquidations
+            .iter()
+            .map(|l| (l.mint_account.as_str(), l.slot))
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                ("liq_mint_a", 400),
+                ("liq_mint_b", 300),
+                ("liq_mint_a", 200)
+            ]
         );
-        Ok(())
+        assert!(recent
+            .liquidations
+            .iter()
+            .all(|l| l.owner.as_deref() == Some("test_user") && l.liquidator == "liquidator"));
+
+        let limited = storage.query_recent_liquidations(1, None).await.unwrap();
+        assert_eq!(limited.liquidations.len(), 1);
+        assert_eq!(limited.liquidations[0].order_pda, "pda_a2");
+        assert_eq!(limited.next.as_deref(), Some("400:liq_sig_pda_a2"));
+
+        // Polling from the newest cursor returns nothing until a new liquidation lands
+        let since = parse_feed_cursor(limited.next.as_deref().unwrap()).unwrap();
+        let polled = storage
+            .query_recent_liquidations(10, Some((since.0, &since.1)))
+            .await
+            .unwrap();
+        assert!(polled.liquidations.is_empty());
+        assert_eq!(polled.next, limited.next);
     }
 
-    /// Query kline data
-    pub async fn query_kline_data(&self, query: KlineQuery) -> Result<KlineQueryResponse> {
-        let mint_account = &query.mint_account;
-        let interval = &query.interval;
-        let page = query.page.unwrap_or(1);
-        let limit = query.limit.unwrap_or(50);
-        let order_by = query.order_by.unwrap_or_else(|| "time_desc".to_string());
-
-        // Validate interval
-        if !matches!(interval.as_str(), "s1" | "s30" | "m5") {
-            return Err(anyhow::anyhow!(
-                "Invalid interval: {}, must be one of: s1, s30, m5",
-                interval
-            ));
+    #[tokio::test]
+    async fn test_rebuild_populates_index_enabled_after_events_were_stored() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.retention.liquidations.enabled = false;
+        config.retention.rebuild_batch_size = 2;
+        let storage = EventStorage::new(&config).unwrap();
+        assert!(storage.pending_index_rebuilds().unwrap().is_empty());
+
+        for (pda, slot) in [("rebuild_pda_1", 200), ("rebuild_pda_2", 300)] {
+            storage
+                .store_event(create_long_short_event("rebuild_mint", pda, 1, 1000, 1000))
+                .await
+                .unwrap();
+            storage
+                .store_event(SpinPetEvent::ForceLiquidate(ForceLiquidateEvent {
+                    payer: "liquidator".to_string(),
+                    mint_account: "rebuild_mint".to_string(),
+                    order_pda: pda.to_string(),
+                    timestamp: Utc::now(),
+                    timestamp_source: TimestampSource::ServerReceive,
+                    log_index: 0,
+                    signature: format!("rebuild_sig_{}", pda),
+                    slot,
+                }))
+                .await
+                .unwrap();
         }
+        assert!(storage
+            .query_recent_liquidations(10, None)
+            .await
+            .unwrap()
+            .liquidations
+            .is_empty());
+        drop(storage);
+
+        config.retention.liquidations.enabled = true;
+        let storage = EventStorage::new(&config).unwrap();
+        assert_eq!(
+            storage.pending_index_rebuilds().unwrap(),
+            vec!["liquidations"]
+        );
 
-        debug!(
-            "🔍 Querying kline data, mint: {}, interval: {}, page: {}, limit: {}, order: {}",
-            mint_account, interval, page, limit, order_by
+        let result = storage.rebuild_index("liquidations").unwrap();
+        assert_eq!(result.scanned, 4);
+        assert_eq!(result.written, 2);
+        assert!(!result.resumed);
+        assert!(storage
+            .db
+            .get(format!("{}liquidations", REBUILD_CURSOR_PREFIX).as_bytes())
+            .unwrap()
+            .is_none());
+        assert!(storage.pending_index_rebuilds().unwrap().is_empty());
+
+        // Owners come from the opening events, as the liquidated orders are gone
+        let recent = storage.query_recent_liquidations(10, None).await.unwrap();
+        let liquidated: Vec<(&str, u64, Option<&str>)> = recent
+            .liquidations
+            .iter()
+            .map(|l| (l.order_pda.as_str(), l.slot, l.owner.as_deref()))
+            .collect();
+        assert_eq!(
+            liquidated,
+            vec![
+                ("rebuild_pda_2", 300, Some("test_user")),
+                ("rebuild_pda_1", 200, Some("test_user"))
+            ]
         );
 
-        // Build prefix key for the specific mint and interval
-        let prefix = format!("{}:{}:", interval, mint_account);
+        assert!(storage.rebuild_index("creator_index").is_err());
+    }
 
-        // Collect all matching kline data
-        let mut all_klines = Vec::new();
+    #[tokio::test]
+    async fn test_resumed_rebuild_keeps_owners_of_orders_opened_before_cursor() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.retention.liquidations.enabled = false;
+        let storage = EventStorage::new(&config).unwrap();
 
-        let iter = self
+        let open = create_long_short_event("resume_mint", "resume_pda", 1, 1000, 1000);
+        storage.store_event(open.clone()).await.unwrap();
+        storage
+            .store_event(SpinPetEvent::ForceLiquidate(ForceLiquidateEvent {
+                payer: "liquidator".to_string(),
+                mint_account: "resume_mint".to_string(),
+                order_pda: "resume_pda".to_string(),
+                timestamp: Utc::now(),
+                timestamp_source: TimestampSource::ServerReceive,
+                log_index: 0,
+                signature: "resume_liquidation".to_string(),
+                slot: 200,
+            }))
+            .await
+            .unwrap();
+        drop(storage);
+
+        // An earlier rebuild stopped right after the opening event
+        config.retention.liquidations.enabled = true;
+        let storage = EventStorage::new(&config).unwrap();
+        storage
             .db
-            .iterator(IteratorMode::From(prefix.as_bytes(), Direction::Forward));
+            .put(
+                format!("{}liquidations", REBUILD_CURSOR_PREFIX).as_bytes(),
+                storage.generate_event_key(&open).as_bytes(),
+            )
+            .unwrap();
+
+        // A second rebuild of the same index is refused while one runs
+        storage
+            .rebuilding_indexes
+            .lock()
+            .unwrap()
+            .insert("liquidations".to_string());
+        assert!(storage.index_rebuild_running("liquidations"));
+        assert!(storage.rebuild_index("liquidations").is_err());
+        storage
+            .rebuilding_indexes
+            .lock()
+            .unwrap()
+            .remove("liquidations");
+
+        let result = storage.rebuild_index("liquidations").unwrap();
+        assert!(result.resumed);
+        assert_eq!(result.written, 1);
+        assert!(!storage.index_rebuild_running("liquidations"));
+        let recent = storage.query_recent_liquidations(10, None).await.unwrap();
+        assert_eq!(recent.liquidations[0].owner.as_deref(), Some("test_user"));
+    }
 
-        for item in iter {
-            let (key, value) = item?;
-            let key_str = String::from_utf8_lossy(&key);
+    #[tokio::test]
+    async fn test_slot_index_retention_keeps_longer_lived_events() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config(&temp_dir);
+        config.retention.events_retention_slots = 1_000;
+        config.retention.slot_index.retention_slots = 50;
+        let storage = EventStorage::new(&config).unwrap();
 
-            // Check if still matches prefix
-            if !key_str.starts_with(&prefix) {
-                break;
+        for (signature, slot) in [("old_sig_1", 100), ("old_sig_2", 120), ("new_sig", 200)] {
+            let mut event = create_buy_sell_event("retention_mint", signature, 1_000, 5);
+            if let SpinPetEvent::BuySell(e) = &mut event {
+                e.slot = slot;
             }
+            storage.store_event(event).await.unwrap();
+        }
+        assert_eq!(storage.latest_slot(), 200);
+
+        let result = storage.prune_retention().await.unwrap();
+        assert_eq!(result.slot_index, 2);
+        assert_eq!(result.events, 0);
+
+        // Global entries before slot 150 are gone, the tr: events behind them are not
+        assert!(storage
+            .query_events_by_slot(100)
+            .await
+            .unwrap()
+            .events
+            .is_empty());
+        assert!(storage
+            .query_events_by_slot(120)
+            .await
+            .unwrap()
+            .events
+            .is_empty());
+        assert_eq!(
+            storage
+                .query_events_by_slot(200)
+                .await
+                .unwrap()
+                .events
+                .len(),
+            1
+        );
+        let events = storage
+            .query_events(EventQuery {
+                mint_account: "retention_mint".to_string(),
+                page: Some(1),
+                limit: Some(10),
+                order_by: Some("slot_asc".to_string()),
+            })
+            .await
+            .unwrap();
+        assert_eq!(events.events.len(), 3);
+
+        // An index never keeps more than the events it mirrors
+        config.retention.slot_index.retention_slots = 0;
+        assert_eq!(
+            config
+                .retention
+                .index_retention_slots(&config.retention.slot_index),
+            1_000
+        );
+    }
 
-            // Parse kline data
-            match serde_json::from_slice::<KlineData>(&value) {
-                Ok(kline_data) => all_klines.push(kline_data),
-                Err(e) => {
-                    error!("❌ Failed to parse kline data: {}, key: {}", e, key_str);
-                    continue;
-                }
+    #[tokio::test]
+    async fn test_recent_events_since_cursor_returns_only_new_events() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = EventStorage::new(&create_test_config(&temp_dir)).unwrap();
+
+        let buy_at = |mint: &str, signature: &str, slot: u64| {
+            let mut event = create_buy_sell_event(mint, signature, 1_000, 5);
+            if let SpinPetEvent::BuySell(e) = &mut event {
+                e.slot = slot;
             }
+            event
+        };
+        for (mint, signature, slot) in [
+            ("feed_mint_a", "feed_sig_1", 600),
+            ("feed_mint_b", "feed_sig_2", 601),
+        ] {
+            storage
+                .store_event(buy_at(mint, signature, slot))
+                .await
+                .unwrap();
         }
 
-        // Sort by time
-        match order_by.as_str() {
-            "time_asc" => {
-                all_klines.sort_by(|a, b| a.time.cmp(&b.time));
-            }
-            "time_desc" => {
-                all_klines.sort_by(|a, b| b.time.cmp(&a.time));
+        let first = storage.query_recent_events(10, None).await.unwrap();
+        assert_eq!(first.events.len(), 2);
+        assert_eq!(first.events[0].signature(), "feed_sig_2");
+        assert_eq!(first.next.as_deref(), Some("601:feed_sig_2"));
+
+        // Same slot as the cursor but a later signature, then two newer slots
+        for (mint, signature, slot) in [
+            ("feed_mint_a", "feed_sig_3", 601),
+            ("feed_mint_b", "feed_sig_4", 602),
+            ("feed_mint_a", "feed_sig_5", 603),
+        ] {
+            storage
+                .store_event(buy_at(mint, signature, slot))
+                .await
+                .unwrap();
+        }
+
+        let since = parse_feed_cursor(first.next.as_deref().unwrap()).unwrap();
+        let polled = storage
+            .query_recent_events(10, Some((since.0, &since.1)))
+            .await
+            .unwrap();
+        let signatures: Vec<&str> = polled.events.iter().map(|e| e.signature()).collect();
+        assert_eq!(signatures, vec!["feed_sig_5", "feed_sig_4", "feed_sig_3"]);
+        assert_eq!(polled.next.as_deref(), Some("603:feed_sig_5"));
+
+        // A small limit pages forward from the cursor without skipping anything
+        let paged = storage
+            .query_recent_events(2, Some((since.0, &since.1)))
+            .await
+            .unwrap();
+        let signatures: Vec<&str> = paged.events.iter().map(|e| e.signature()).collect();
+        assert_eq!(signatures, vec!["feed_sig_4", "feed_sig_3"]);
+        assert_eq!(paged.next.as_deref(), Some("602:feed_sig_4"));
+
+        assert_eq!(parse_feed_cursor("bad"), None);
+        assert_eq!(parse_feed_cursor("12:"), None);
+    }
+
+    /// Records every batch written to it
+    #[derive(Default)]
+    struct MockTsdbSink {
+        writes: std::sync::Mutex<Vec<String>>,
+    }
+
+    #[async_trait::async_trait]
+    impl crate::services::tsdb_sink::TsdbSink for MockTsdbSink {
+        async fn write(&self, body: String) -> Result<()> {
+            self.writes.lock().unwrap().push(body);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tsdb_dual_write_batches_klines_in_line_protocol() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = create_test_config(&temp_dir);
+        let mut storage = EventStorage::new(&config).unwrap();
+        let sink = Arc::new(MockTsdbSink::default());
+        let tsdb_config = crate::config::TsdbConfig {
+            enabled: true,
+            batch_size: 6,
+            ..Default::default()
+        };
+        storage.tsdb = Some(Arc::new(TsdbWriter::new(&tsdb_config, sink.clone())));
+
+        let traded_at = DateTime::from_timestamp(1_758_343_500, 0).unwrap();
+        let trade = |signature: &str| {
+            let mut event = create_buy_sell_event("tsdb_mint", signature, 1_000_000, 1);
+            if let SpinPetEvent::BuySell(ref mut buy_sell) = event {
+                buy_sell.timestamp = traded_at;
             }
-            _ => {
-                // Default sort by time descending (newest first)
-                all_klines.sort_by(|a, b| b.time.cmp(&a.time));
+            event
+        };
+
+        // One trade updates three intervals, below the batch size: nothing is written yet
+        storage.store_event(trade("tsdb_sig_1")).await.unwrap();
+        assert!(sink.writes.lock().unwrap().is_empty());
+
+        // The second trade fills the batch, which is flushed in the background
+        storage.store_event(trade("tsdb_sig_2")).await.unwrap();
+        for _ in 0..100 {
+            if !sink.writes.lock().unwrap().is_empty() {
+                break;
             }
+            sleep(Duration::from_millis(10)).await;
         }
 
-        let total = all_klines.len();
-        let offset = (page - 1) * limit;
-        let has_prev = page > 1;
-        let has_next = offset + limit < total;
+        let writes = sink.writes.lock().unwrap().clone();
+        assert_eq!(writes.len(), 1);
+        let lines: Vec<&str> = writes[0].lines().collect();
+        assert_eq!(lines.len(), 6);
+        for (line, interval) in lines[3..].iter().zip(["s1", "s30", "m5"]) {
+            assert!(line.starts_with(&format!("kline,mint=tsdb_mint,interval={} open=", interval)));
+            assert!(line.contains(",update_count=2i,is_final=false "));
+        }
+        assert!(lines[5].ends_with(" 1758343500000000000"));
 
-        // Pagination
-        let klines = all_klines
-            .into_iter()
-            .skip(offset)
-            .take(limit)
-            .collect::<Vec<_>>();
+        // Nothing is left buffered
+        assert_eq!(storage.tsdb_writer().unwrap().flush().await, 0);
+    }
 
-        debug!(
-            "🔍 Retrieved {} klines for mint: {}, interval: {}",
-            klines.len(),
-            mint_account,
-            interval
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_token_created_stores_single_mint_marker() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = create_test_config(&temp_dir);
+        let storage = Arc::new(EventStorage::new(&config).unwrap());
+
+        // The same mint created twice at once, e.g. reprocessed at a different slot
+        let created = |signature: &str, slot: u64| {
+            let mut event = create_token_created_event("race_mint", "");
+            if let SpinPetEvent::TokenCreated(ref mut token_event) = event {
+                token_event.signature = signature.to_string();
+                token_event.slot = slot;
+            }
+            event
+        };
+        let (first, second) = tokio::join!(
+            tokio::spawn({
+                let storage = Arc::clone(&storage);
+                let event = created("race_sig_1", 100);
+                async move { storage.store_event(event).await }
+            }),
+            tokio::spawn({
+                let storage = Arc::clone(&storage);
+                let event = created("race_sig_2", 101);
+                async move { storage.store_event(event).await }
+            }),
         );
+        first.unwrap().unwrap();
+        second.unwrap().unwrap();
 
-        Ok(KlineQueryResponse {
-            klines,
-            total,
-            page,
-            limit,
-            has_next,
-            has_prev,
-            interval: interval.clone(),
-            mint_account: mint_account.clone(),
-        })
+        let markers: Vec<String> = storage
+            .db
+            .iterator(IteratorMode::From("mt:".as_bytes(), Direction::Forward))
+            .map(|item| String::from_utf8(item.unwrap().0.to_vec()).unwrap())
+            .take_while(|key| key.starts_with("mt:"))
+            .filter(|key| key.ends_with(":race_mint"))
+            .collect();
+        assert_eq!(markers.len(), 1, "markers: {:?}", markers);
+
+        let mints = storage
+            .query_mints(MintQuery {
+                page: Some(1),
+                limit: Some(10),
+                sort_by: None,
+                cursor: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(mints.mints, vec!["race_mint".to_string()]);
+
+        // The detail written with the marker is completed by the TokenCreated event
+        let result = storage
+            .query_mint_details(MintDetailsQuery {
+                mint_accounts: vec!["race_mint".to_string()],
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.details[0].name.as_deref(), Some("Test Token"));
     }
 
-    /// Get database statistics
-    pub fn get_stats(&self) -> Result<String> {
-        let stats = self.db.property_value("rocksdb.stats")?;
-        Ok(stats.unwrap_or_else(|| "No stats available".to_string()))
-    }
-}
+    #[tokio::test]
+    async fn test_db_metrics_report_populated_database() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = EventStorage::new(&create_test_config(&temp_dir)).unwrap();
+
+        for i in 0..50 {
+            storage
+                .store_event(create_buy_sell_event(
+                    "metrics_mint",
+                    &format!("metrics_sig_{}", i),
+                    1_000_000,
+                    1,
+                ))
+                .await
+                .unwrap();
+        }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use chrono::Utc;
-    use tempfile::TempDir;
+        // Unflushed writes live in the memtables
+        let metrics = storage.get_db_metrics().unwrap();
+        assert!(metrics.estimate_num_keys >= 50, "{:?}", metrics);
+        assert!(metrics.cur_size_all_mem_tables > 0);
+
+        // After a flush they show up as level-0 SST files
+        storage.db.flush().unwrap();
+        let metrics = storage.get_db_metrics().unwrap();
+        assert!(metrics.num_files_at_level0 >= 1, "{:?}", metrics);
+        assert!(metrics.total_sst_files_size > 0);
+        assert!(metrics.estimate_live_data_size > 0);
+        assert!(metrics.estimate_num_keys < 1_000_000);
+
+        let exposition = metrics.to_prometheus();
+        assert!(exposition.contains("# TYPE rocksdb_estimate_num_keys gauge\n"));
+        assert!(exposition.contains(&format!(
+            "rocksdb_num_files_at_level0 {}\n",
+            metrics.num_files_at_level0
+        )));
+    }
 
     #[tokio::test]
-    async fn test_event_storage() {
+    async fn test_receive_latency_recorded_from_block_time() {
         let temp_dir = TempDir::new().unwrap();
-        let config = crate::config::Config {
-            server: crate::config::ServerConfig {
-                host: "localhost".to_string(),
-                port: 8080,
-            },
-            cors: crate::config::CorsConfig {
-                enabled: true,
-                allow_origins: vec!["*".to_string()],
-            },
-            logging: crate::config::LoggingConfig {
-                level: "debug".to_string(),
-            },
-            solana: crate::config::SolanaConfig {
-                rpc_url: "http://localhost:8899".to_string(),
-                ws_url: "ws://localhost:8900".to_string(),
-                program_id: "JBMmrp6jhksqnxDBskkmVvWHhJLaPBjgiMHEroJbUTBZ".to_string(),
-                enable_event_listener: false,
-                commitment: "processed".to_string(),
-                reconnect_interval: 1,
-                max_reconnect_attempts: 20,
-                event_buffer_size: 1000,
-                event_batch_size: 100,
-                ping_interval_seconds: 60,
-            },
-            database: crate::config::DatabaseConfig {
-                rocksdb_path: temp_dir.path().to_str().unwrap().to_string(),
-            },
-            ipfs: crate::config::IpfsConfig {
-                gateway_url: "https://crimson-binding-tarantula-509.mypinata.cloud/ipfs/"
-                    .to_string(),
-                request_timeout_seconds: 30,
-                max_retries: 3,
-                retry_delay_seconds: 5,
-            },
-            kline: crate::config::KlineServiceConfig {
-                enable_kline_service: false,
-                connection_timeout_secs: 60,
-                max_subscriptions_per_client: 100,
-                history_data_limit: 100,
-                ping_interval_secs: 25,
-                ping_timeout_secs: 60,
-            },
-        };
-
+        let mut config = create_test_config(&temp_dir);
+        config.database.store_event_latency = true;
         let storage = EventStorage::new(&config).unwrap();
 
-        let mint_detail = MintDetailData {
-            mint_account: "test_mint".to_string(),
-            payer: Some("test_payer".to_string()),
-            curve_account: Some("test_curve".to_string()),
-            pool_token_account: Some("test_pool_token".to_string()),
-            pool_sol_account: Some("test_pool_sol".to_string()),
-            fee_recipient: Some("test_fee_recipient".to_string()),
-            base_fee_recipient: Some("test_base_fee_recipient".to_string()),
-            params_account: Some("test_params_account".to_string()),
-            name: Some("Test Token".to_string()),
-            symbol: Some("TEST".to_string()),
-            uri: Some("test_uri".to_string()),
-            swap_fee: Some(100),
-            borrow_fee: Some(200),
-            fee_discount_flag: Some(0),
-            create_timestamp: Some(Utc::now().timestamp()),
-            latest_price: Some(1000000),
-            latest_trade_time: Some(Utc::now().timestamp()),
-            total_sol_amount: 1000,
-            total_margin_sol_amount: 2000,
-            total_force_liquidations: 10,
-            total_close_profit: 500,
-            created_by: Some("test_user".to_string()),
-            last_updated_at: Some(Utc::now()),
-            uri_data: None,
-        };
-
-        let key = storage.generate_mint_detail_key(&mint_detail.mint_account);
-        let value = serde_json::to_vec(&mint_detail).unwrap();
-        storage.db.put(key.as_bytes(), &value).unwrap();
-
-        let query = MintDetailsQuery {
-            mint_accounts: vec![mint_detail.mint_account.clone()],
-        };
+        let block_time = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+        let mut event = create_buy_sell_event("latency_mint", "latency_sig", 1, 1);
+        event.set_timestamp(block_time, TimestampSource::BlockTime);
 
-        let result = storage.query_mint_details(query).await.unwrap();
-        assert_eq!(result.details.len(), 1);
-        assert_eq!(result.details[0].mint_account, mint_detail.mint_account);
-        assert_eq!(result.details[0].name, mint_detail.name);
+        let processed_at = block_time + chrono::Duration::milliseconds(1_500);
+        assert_eq!(
+            storage
+                .record_receive_latency(&event, processed_at)
+                .unwrap(),
+            Some(1_500)
+        );
 
-        // Also test get_stats
-        let stats = storage.get_stats().unwrap();
-        assert!(stats.contains("Total Keys:"));
+        let histogram = storage.receive_latency();
+        assert_eq!(histogram.totals(), (1, 1_500));
+        assert_eq!(histogram.bucket_count(1_000), Some(0));
+        assert_eq!(histogram.bucket_count(2_500), Some(1));
+        assert!(histogram
+            .to_prometheus()
+            .contains("spin_event_receive_latency_ms_bucket{le=\"2500\"} 1\n"));
+
+        let key = format!("lat:{}", storage.generate_event_key(&event));
+        assert_eq!(storage.db.get(key.as_bytes()).unwrap().unwrap(), b"1500");
+
+        // Without a block time there is nothing to measure against
+        event.set_timestamp(processed_at, TimestampSource::ServerReceive);
+        assert_eq!(
+            storage
+                .record_receive_latency(&event, processed_at)
+                .unwrap(),
+            None
+        );
+        assert_eq!(storage.receive_latency().totals().0, 1);
     }
 }
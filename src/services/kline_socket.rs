@@ -3,31 +3,58 @@
 
 use anyhow::Result;
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
-use socketioxide::extract::{Data, SocketRef};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize, Serializer};
+use socketioxide::extract::{Data, SocketRef, TryData};
 use socketioxide::SocketIo;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::RwLock;
+use tokio::sync::{watch, Mutex, RwLock};
 use tracing::{debug, info, warn};
 use utoipa::ToSchema;
 
-use crate::models::{KlineData, KlineQuery};
-use crate::services::event_service::StatsEventHandler;
-use crate::services::event_storage::EventStorage;
-use crate::solana::events::SpinPetEvent;
+use crate::config::KlineHistoryLimits;
+use crate::models::{KlineData, KlineQuery, MintWatchersResponse};
+use crate::services::event_storage::{EventStorage, OrderData, KLINE_ALIGNMENT_EPOCH};
+use crate::services::state_memory::SET_ENTRY_OVERHEAD_BYTES;
+use crate::solana::events::{BuySellEvent, OrderType, PartialCloseEvent, SpinPetEvent};
 use crate::solana::EventHandler;
 
+/// 可按类型订阅的事件, 与事件 JSON 中的 event_type 一致
+const EVENT_TYPE_CHANNELS: [&str; 7] = [
+    "TokenCreated",
+    "BuySell",
+    "LongShort",
+    "ForceLiquidate",
+    "FullClose",
+    "PartialClose",
+    "MilestoneDiscount",
+];
+
 /// K线推送服务配置
 #[derive(Debug, Clone)]
 pub struct KlineConfig {
     pub connection_timeout: Duration,        // 连接超时时间 (默认60秒)
     pub max_subscriptions_per_client: usize, // 每客户端最大订阅数 (默认100)
-    #[allow(dead_code)]
-    pub history_data_limit: usize, // 历史数据默认条数 (默认100)
+    pub history_data_limit: usize,           // 历史数据默认条数 (默认100)
     pub ping_interval: Duration,             // 心跳间隔 (默认25秒)
     pub ping_timeout: Duration,              // 心跳超时 (默认60秒)
+    pub subscribe_on_connect: bool,          // 是否接受连接 auth 中的初始订阅 (默认开启)
+    pub history_limits: KlineHistoryLimits,  // 订阅时按周期推送的历史条数
+    pub finalization_interval: Duration,     // 收盘通知检查间隔 (默认1秒, 0为关闭)
+    pub batch_finalizations: bool,           // 是否按订阅者合并收盘通知 (默认关闭)
+    pub session_resume_grace: Duration,      // 断线后可凭会话令牌恢复订阅的时长 (默认0, 关闭)
+    pub imbalance_window: Duration,          // 买卖失衡滚动窗口 (默认60秒, 0为关闭)
+    pub min_connection_age: Duration,        // 连接建立后免于清理的宽限期 (默认0, 关闭)
+    pub broadcast_throttle: Duration,        // 每个 mint:interval 实时推送的合并窗口 (默认0, 关闭)
+    pub max_emit_failures: u32,              // 连续定向发送失败多少次后移除客户端 (默认5, 0为关闭)
+    pub type_channels: bool,                 // 是否接受按事件类型的跨 mint 订阅 (默认开启)
+    pub watchers_cache: Duration,            // 观看人数查询结果缓存时长 (默认1秒, 0为不缓存)
+    pub skip_unchanged_price: bool,          // 价格未变化的事件不推送K线 (默认关闭)
+    pub binary_history: bool,                // 允许连接协商以 gzip 二进制帧接收历史数据 (默认关闭)
+    pub reconcile_interval: Duration,        // 与命名空间实际连接核对的间隔 (默认60秒, 0为关闭)
 }
 
 impl Default for KlineConfig {
@@ -38,6 +65,20 @@ impl Default for KlineConfig {
             history_data_limit: 100,
             ping_interval: Duration::from_secs(25),
             ping_timeout: Duration::from_secs(60),
+            subscribe_on_connect: true,
+            history_limits: KlineHistoryLimits::default(),
+            finalization_interval: Duration::from_secs(1),
+            batch_finalizations: false,
+            session_resume_grace: Duration::ZERO,
+            imbalance_window: Duration::from_secs(60),
+            min_connection_age: Duration::ZERO,
+            broadcast_throttle: Duration::ZERO,
+            max_emit_failures: 5,
+            type_channels: true,
+            watchers_cache: Duration::from_secs(1),
+            skip_unchanged_price: false,
+            binary_history: false,
+            reconcile_interval: Duration::from_secs(60),
         }
     }
 }
@@ -50,6 +91,30 @@ impl KlineConfig {
             history_data_limit: config.history_data_limit,
             ping_interval: Duration::from_secs(config.ping_interval_secs),
             ping_timeout: Duration::from_secs(config.ping_timeout_secs),
+            subscribe_on_connect: config.subscribe_on_connect,
+            history_limits: config.history_limits,
+            finalization_interval: Duration::from_secs(config.finalization_interval_secs),
+            batch_finalizations: config.batch_finalizations,
+            session_resume_grace: Duration::from_secs(config.session_resume_grace_secs),
+            imbalance_window: Duration::from_secs(config.imbalance_window_secs),
+            min_connection_age: Duration::from_secs(config.min_connection_age_secs),
+            broadcast_throttle: Duration::from_millis(config.broadcast_throttle_ms),
+            max_emit_failures: config.max_emit_failures,
+            type_channels: config.type_channels,
+            watchers_cache: Duration::from_millis(config.watchers_cache_ms),
+            skip_unchanged_price: config.skip_unchanged_price,
+            binary_history: config.binary_history,
+            reconcile_interval: Duration::from_secs(config.reconcile_interval_secs),
+        }
+    }
+
+    /// 订阅时推送的历史K线条数, 未配置的周期使用 history_data_limit
+    pub fn subscribe_history_limit(&self, interval: &str) -> usize {
+        match interval {
+            "s1" => self.history_limits.s1,
+            "s30" => self.history_limits.s30,
+            "m5" => self.history_limits.m5,
+            _ => self.history_data_limit,
         }
     }
 }
@@ -68,6 +133,27 @@ pub struct ClientConnection {
     pub kline_data_sent_count: u64,     // kline_data 发送次数
     pub history_data_sent_count: u64,   // history_data 发送次数
     pub total_messages_sent: u64,       // 总消息发送次数
+    pub last_pong: Option<Instant>,     // 最近一次 heartbeat_ack 时间 (从未回应为 None)
+    pub emit_failures: u32,             // 连续定向发送失败次数, 成功发送后清零
+}
+
+impl ClientConnection {
+    /// 新建连接记录: 连接时间与最后活动时间均为 `now`, 尚无订阅和发送计数
+    pub fn new(socket_id: String, now: Instant) -> Self {
+        Self {
+            socket_id,
+            subscriptions: HashSet::new(),
+            last_activity: now,
+            connection_time: now,
+            subscription_count: 0,
+            user_agent: None,
+            kline_data_sent_count: 0,
+            history_data_sent_count: 0,
+            total_messages_sent: 0,
+            last_pong: None,
+            emit_failures: 0,
+        }
+    }
 }
 
 /// 订阅管理器
@@ -81,6 +167,54 @@ pub struct SubscriptionManager {
 
     // 反向索引: SocketId -> 订阅键集合 (用于快速清理)
     pub client_subscriptions: HashMap<String, HashSet<String>>,
+
+    // 会话令牌: SocketId -> 令牌 (开启会话恢复时)
+    pub session_tokens: HashMap<String, String>,
+
+    // 断线待恢复的会话: 令牌 -> 断线前的订阅
+    pub parked_sessions: HashMap<String, ParkedSession>,
+
+    // 买卖失衡推送订阅: mint_account -> SocketId集合 (随该 mint 的最后一个订阅移除)
+    pub imbalance_subscribers: HashMap<String, HashSet<String>>,
+
+    // 按事件类型的跨 mint 订阅: event_type -> SocketId集合
+    pub type_subscribers: HashMap<String, HashSet<String>>,
+
+    // 协商了二进制历史数据的连接
+    pub binary_history_clients: HashSet<String>,
+}
+
+/// 断线后保留的会话, 宽限期内以相同令牌重连即可恢复
+#[derive(Debug, Clone)]
+pub struct ParkedSession {
+    pub subscriptions: Vec<(String, String)>, // (mint, interval)
+    pub disconnected_at: Instant,
+}
+
+/// 订阅索引中引用了已不存在连接的订阅者
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct DanglingSubscriber {
+    pub mint: String,
+    pub interval: String,
+    pub socket_id: String,
+}
+
+/// 订阅索引审计结果; repair 时为已移除的条目
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct SubscriptionAudit {
+    pub dangling_subscribers: Vec<DanglingSubscriber>,
+    pub empty_intervals: Vec<String>, // "mint:interval" 格式
+    pub empty_mints: Vec<String>,
+    pub orphan_client_subscriptions: Vec<String>, // 反向索引中无连接的 SocketId
+}
+
+impl SubscriptionAudit {
+    pub fn is_clean(&self) -> bool {
+        self.dangling_subscribers.is_empty()
+            && self.empty_intervals.is_empty()
+            && self.empty_mints.is_empty()
+            && self.orphan_client_subscriptions.is_empty()
+    }
 }
 
 impl SubscriptionManager {
@@ -89,6 +223,11 @@ impl SubscriptionManager {
             connections: HashMap::new(),
             mint_subscribers: HashMap::new(),
             client_subscriptions: HashMap::new(),
+            session_tokens: HashMap::new(),
+            parked_sessions: HashMap::new(),
+            imbalance_subscribers: HashMap::new(),
+            type_subscribers: HashMap::new(),
+            binary_history_clients: HashSet::new(),
         }
     }
 
@@ -155,8 +294,98 @@ impl SubscriptionManager {
         }
 
         // 从反向索引移除
-        if let Some(subscriptions) = self.client_subscriptions.get_mut(socket_id) {
-            subscriptions.remove(&subscription_key);
+        let mint_prefix = format!("{}:", mint);
+        let still_subscribed = match self.client_subscriptions.get_mut(socket_id) {
+            Some(subscriptions) => {
+                subscriptions.remove(&subscription_key);
+                subscriptions
+                    .iter()
+                    .any(|key| key.starts_with(&mint_prefix))
+            }
+            None => false,
+        };
+
+        // 该 mint 已无订阅时, 同时停止失衡推送
+        if !still_subscribed {
+            if let Some(client_set) = self.imbalance_subscribers.get_mut(mint) {
+                client_set.remove(socket_id);
+                if client_set.is_empty() {
+                    self.imbalance_subscribers.remove(mint);
+                }
+            }
+        }
+    }
+
+    /// 为已订阅该 mint 的客户端开启买卖失衡推送
+    pub fn add_imbalance_subscription(&mut self, socket_id: &str, mint: &str) {
+        self.imbalance_subscribers
+            .entry(mint.to_string())
+            .or_default()
+            .insert(socket_id.to_string());
+    }
+
+    pub fn get_imbalance_subscribers(&self, mint: &str) -> Vec<String> {
+        self.imbalance_subscribers
+            .get(mint)
+            .map(|client_set| client_set.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// 订阅某一事件类型 (不区分 mint)
+    pub fn add_type_subscription(&mut self, socket_id: &str, event_type: &str) {
+        self.type_subscribers
+            .entry(event_type.to_string())
+            .or_default()
+            .insert(socket_id.to_string());
+    }
+
+    pub fn remove_type_subscription(&mut self, socket_id: &str, event_type: &str) {
+        if let Some(client_set) = self.type_subscribers.get_mut(event_type) {
+            client_set.remove(socket_id);
+            if client_set.is_empty() {
+                self.type_subscribers.remove(event_type);
+            }
+        }
+    }
+
+    pub fn get_type_subscribers(&self, event_type: &str) -> Vec<String> {
+        self.type_subscribers
+            .get(event_type)
+            .map(|client_set| client_set.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// 订阅了该 mint 任一周期的客户端 (去重)
+    pub fn get_mint_subscribers(&self, mint: &str) -> Vec<String> {
+        self.mint_subscribers
+            .get(mint)
+            .map(|interval_map| {
+                interval_map
+                    .values()
+                    .flatten()
+                    .cloned()
+                    .collect::<HashSet<_>>()
+                    .into_iter()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// 该 mint 各周期的订阅人数及去重后的总人数 (不含 socket id)
+    pub fn watcher_counts(&self, mint: &str) -> MintWatchersResponse {
+        MintWatchersResponse {
+            mint_account: mint.to_string(),
+            intervals: self
+                .mint_subscribers
+                .get(mint)
+                .map(|interval_map| {
+                    interval_map
+                        .iter()
+                        .map(|(interval, clients)| (interval.clone(), clients.len()))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            total: self.get_mint_subscribers(mint).len(),
         }
     }
 
@@ -168,7 +397,10 @@ impl SubscriptionManager {
             .unwrap_or_default()
     }
 
-    pub fn remove_client(&mut self, socket_id: &str) {
+    /// 清除客户端的全部订阅但保留连接, 返回被移除的 (mint, interval)
+    pub fn remove_all_subscriptions(&mut self, socket_id: &str) -> Vec<(String, String)> {
+        let mut removed = Vec::new();
+
         // 获取该客户端的所有订阅
         if let Some(subscriptions) = self.client_subscriptions.remove(socket_id) {
             for subscription_key in subscriptions {
@@ -176,12 +408,146 @@ impl SubscriptionManager {
                 if parts.len() == 2 {
                     let (mint, interval) = (parts[0], parts[1]);
                     self.remove_subscription(socket_id, mint, interval);
+                    removed.push((mint.to_string(), interval.to_string()));
                 }
             }
         }
 
+        removed
+    }
+
+    pub fn remove_client(&mut self, socket_id: &str) {
+        self.remove_all_subscriptions(socket_id);
+        self.remove_all_type_subscriptions(socket_id);
+
         // 移除连接记录
         self.connections.remove(socket_id);
+        self.session_tokens.remove(socket_id);
+        self.binary_history_clients.remove(socket_id);
+    }
+
+    fn remove_all_type_subscriptions(&mut self, socket_id: &str) {
+        for client_set in self.type_subscribers.values_mut() {
+            client_set.remove(socket_id);
+        }
+        self.type_subscribers
+            .retain(|_, client_set| !client_set.is_empty());
+    }
+
+    /// 断线时移除客户端, 若持有会话令牌则保留其 K线订阅以便重连恢复 (类型订阅不保留)
+    pub fn park_client(&mut self, socket_id: &str) {
+        let subscriptions = self.remove_all_subscriptions(socket_id);
+        self.remove_all_type_subscriptions(socket_id);
+        self.connections.remove(socket_id);
+        self.binary_history_clients.remove(socket_id);
+
+        if let Some(token) = self.session_tokens.remove(socket_id) {
+            self.parked_sessions.insert(
+                token,
+                ParkedSession {
+                    subscriptions,
+                    disconnected_at: Instant::now(),
+                },
+            );
+        }
+    }
+
+    /// 以令牌恢复宽限期内断线的会话, 把订阅挂到新连接上; 返回恢复的 (mint, interval)
+    pub fn resume_session(
+        &mut self,
+        socket_id: &str,
+        token: &str,
+        grace: Duration,
+    ) -> Option<Vec<(String, String)>> {
+        let parked = self.parked_sessions.remove(token)?;
+        if parked.disconnected_at.elapsed() > grace {
+            return None;
+        }
+
+        let restored = parked
+            .subscriptions
+            .into_iter()
+            .filter(|(mint, interval)| self.add_subscription(socket_id, mint, interval).is_ok())
+            .collect();
+        Some(restored)
+    }
+
+    /// 清理超过宽限期的断线会话, 返回清理数量
+    pub fn prune_parked_sessions(&mut self, grace: Duration) -> usize {
+        let before = self.parked_sessions.len();
+        self.parked_sessions
+            .retain(|_, parked| parked.disconnected_at.elapsed() <= grace);
+        before - self.parked_sessions.len()
+    }
+
+    /// 检查订阅索引: 引用不存在连接的订阅者、空的周期集合与空的 mint 映射
+    pub fn audit(&self) -> SubscriptionAudit {
+        let mut audit = SubscriptionAudit::default();
+        for (mint, interval_map) in &self.mint_subscribers {
+            if interval_map.is_empty() {
+                audit.empty_mints.push(mint.clone());
+            }
+            for (interval, client_set) in interval_map {
+                if client_set.is_empty() {
+                    audit.empty_intervals.push(format!("{}:{}", mint, interval));
+                }
+                audit.dangling_subscribers.extend(
+                    client_set
+                        .iter()
+                        .filter(|socket_id| !self.connections.contains_key(*socket_id))
+                        .map(|socket_id| DanglingSubscriber {
+                            mint: mint.clone(),
+                            interval: interval.clone(),
+                            socket_id: socket_id.clone(),
+                        }),
+                );
+            }
+        }
+        audit.orphan_client_subscriptions = self
+            .client_subscriptions
+            .keys()
+            .filter(|socket_id| !self.connections.contains_key(*socket_id))
+            .cloned()
+            .collect();
+        audit
+    }
+
+    /// 移除 audit 发现的不一致条目 (连同因此变空的集合), 返回审计结果
+    pub fn repair(&mut self) -> SubscriptionAudit {
+        let audit = self.audit();
+        for dangling in &audit.dangling_subscribers {
+            if let Some(client_set) = self
+                .mint_subscribers
+                .get_mut(&dangling.mint)
+                .and_then(|interval_map| interval_map.get_mut(&dangling.interval))
+            {
+                client_set.remove(&dangling.socket_id);
+            }
+        }
+        for interval_map in self.mint_subscribers.values_mut() {
+            interval_map.retain(|_, client_set| !client_set.is_empty());
+        }
+        self.mint_subscribers
+            .retain(|_, interval_map| !interval_map.is_empty());
+        for socket_id in &audit.orphan_client_subscriptions {
+            self.client_subscriptions.remove(socket_id);
+        }
+        audit
+    }
+
+    /// 移除 live_sockets 中不存在的连接 (错过了断开事件), 并清理其留下的空集合; 返回移除的 SocketId
+    pub fn reconcile_connections(&mut self, live_sockets: &HashSet<String>) -> Vec<String> {
+        let stale: Vec<String> = self
+            .connections
+            .keys()
+            .filter(|socket_id| !live_sockets.contains(*socket_id))
+            .cloned()
+            .collect();
+        for socket_id in &stale {
+            self.remove_client(socket_id);
+        }
+        self.repair();
+        stale
     }
 
     pub fn update_activity(&mut self, socket_id: &str) {
@@ -189,6 +555,120 @@ impl SubscriptionManager {
             client.last_activity = Instant::now();
         }
     }
+
+    /// 记录一次 kline_data 推送, 成功发送同样计为活动
+    pub fn record_kline_sent(&mut self, socket_id: &str) {
+        if let Some(client) = self.connections.get_mut(socket_id) {
+            client.kline_data_sent_count += 1;
+            client.total_messages_sent += 1;
+            client.last_activity = Instant::now();
+            client.emit_failures = 0;
+        }
+    }
+
+    /// 记录一次 history_data 推送, 成功发送同样计为活动
+    pub fn record_history_sent(&mut self, socket_id: &str) {
+        if let Some(client) = self.connections.get_mut(socket_id) {
+            client.history_data_sent_count += 1;
+            client.total_messages_sent += 1;
+            client.last_activity = Instant::now();
+            client.emit_failures = 0;
+        }
+    }
+
+    /// 记录一次其他推送 (失衡、收盘通知), 成功发送同样计为活动
+    pub fn record_push(&mut self, socket_id: &str) {
+        if let Some(client) = self.connections.get_mut(socket_id) {
+            client.total_messages_sent += 1;
+            client.last_activity = Instant::now();
+            client.emit_failures = 0;
+        }
+    }
+
+    /// 记录一次定向发送失败; 连续失败达到 max_failures (0 为不清理) 时移除该客户端, 返回是否已移除
+    pub fn record_emit_failure(&mut self, socket_id: &str, max_failures: u32) -> bool {
+        let Some(client) = self.connections.get_mut(socket_id) else {
+            return false;
+        };
+        client.emit_failures += 1;
+        if max_failures == 0 || client.emit_failures < max_failures {
+            return false;
+        }
+
+        self.remove_client(socket_id);
+        true
+    }
+
+    /// 记录客户端对 heartbeat 的回应
+    pub fn record_pong(&mut self, socket_id: &str) {
+        if let Some(client) = self.connections.get_mut(socket_id) {
+            client.last_pong = Some(Instant::now());
+        }
+    }
+
+    /// 失活连接: 回应过心跳的客户端按 ping_interval + ping_timeout 内是否有新回应判定,
+    /// 从未回应的客户端按 connection_timeout 内是否有活动判定;
+    /// 连接建立未满 min_connection_age 的客户端一律保留
+    pub fn inactive_clients(&self, now: Instant, config: &KlineConfig) -> Vec<String> {
+        self.connections
+            .iter()
+            .filter(|(_, conn)| {
+                now.duration_since(conn.connection_time) >= config.min_connection_age
+            })
+            .filter(|(_, conn)| match conn.last_pong {
+                Some(pong) => now.duration_since(pong) > config.ping_interval + config.ping_timeout,
+                None => now.duration_since(conn.last_activity) > config.connection_timeout,
+            })
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+
+    /// 至少 idle_for 没有活动的客户端, 最久未活动的在前
+    pub fn idle_clients_oldest_first(&self, now: Instant, idle_for: Duration) -> Vec<String> {
+        let mut idle: Vec<(&String, Instant)> = self
+            .connections
+            .iter()
+            .filter(|(_, conn)| now.duration_since(conn.last_activity) >= idle_for)
+            .map(|(id, conn)| (id, conn.last_activity))
+            .collect();
+        idle.sort_by_key(|(_, last_activity)| *last_activity);
+        idle.into_iter().map(|(id, _)| id.clone()).collect()
+    }
+
+    /// 订阅状态的内存占用估算 (字节): 各映射中的字符串长度加每项固定开销
+    pub fn estimated_bytes(&self) -> usize {
+        const ENTRY: usize = SET_ENTRY_OVERHEAD_BYTES;
+        let set = |client_set: &HashSet<String>| {
+            client_set.iter().map(|v| v.len() + ENTRY).sum::<usize>()
+        };
+        let keyed_sets = |map: &HashMap<String, HashSet<String>>| {
+            map.iter()
+                .map(|(key, client_set)| key.len() + ENTRY + set(client_set))
+                .sum::<usize>()
+        };
+
+        let connections: usize = self
+            .connections
+            .iter()
+            .map(|(id, conn)| {
+                id.len()
+                    + ENTRY
+                    + std::mem::size_of::<ClientConnection>()
+                    + set(&conn.subscriptions)
+            })
+            .sum();
+        let mint_subscribers: usize = self
+            .mint_subscribers
+            .iter()
+            .map(|(mint, interval_map)| mint.len() + ENTRY + keyed_sets(interval_map))
+            .sum();
+
+        connections
+            + mint_subscribers
+            + keyed_sets(&self.client_subscriptions)
+            + keyed_sets(&self.imbalance_subscribers)
+            + keyed_sets(&self.type_subscribers)
+    }
 }
 
 /// 实时K线推送消息
@@ -215,6 +695,104 @@ pub struct KlineRealtimeData {
     pub update_count: u32,   // 更新次数
 }
 
+/// 买卖失衡推送消息
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ImbalanceMessage {
+    pub symbol: String,       // mint_account
+    pub window_secs: u64,     // 滚动窗口长度（秒）
+    pub buy_sol_amount: u64,  // 窗口内买入 SOL 总量
+    pub sell_sol_amount: u64, // 窗口内卖出 SOL 总量
+    pub imbalance: f64,       // (买 - 卖) / (买 + 卖), 范围 [-1, 1]
+    pub timestamp: u64,       // 推送时间戳（毫秒）
+}
+
+/// 订单更新推送消息 (部分平仓后仓位变化)
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct OrderUpdatedMessage {
+    pub symbol: String,   // mint_account
+    pub order: OrderData, // 更新后的订单
+    pub timestamp: u64,   // 推送时间戳（毫秒）
+}
+
+/// 单个 mint 的滚动买卖成交窗口
+#[derive(Debug, Default)]
+pub struct TradeImbalanceWindow {
+    trades: VecDeque<(i64, bool, u64)>, // (成交时间戳秒, 是否买入, SOL 数量)
+    buy_sol_amount: u64,
+    sell_sol_amount: u64,
+}
+
+impl TradeImbalanceWindow {
+    /// 记录一笔成交, 并移出早于 `time - window_secs` 的成交
+    pub fn record(&mut self, time: i64, is_buy: bool, sol_amount: u64, window_secs: u64) {
+        self.trades.push_back((time, is_buy, sol_amount));
+        if is_buy {
+            self.buy_sol_amount = self.buy_sol_amount.saturating_add(sol_amount);
+        } else {
+            self.sell_sol_amount = self.sell_sol_amount.saturating_add(sol_amount);
+        }
+
+        let cutoff = time.saturating_sub(window_secs as i64);
+        while let Some(&(trade_time, trade_is_buy, trade_amount)) = self.trades.front() {
+            if trade_time > cutoff {
+                break;
+            }
+            self.trades.pop_front();
+            if trade_is_buy {
+                self.buy_sol_amount = self.buy_sol_amount.saturating_sub(trade_amount);
+            } else {
+                self.sell_sol_amount = self.sell_sol_amount.saturating_sub(trade_amount);
+            }
+        }
+    }
+
+    pub fn buy_sol_amount(&self) -> u64 {
+        self.buy_sol_amount
+    }
+
+    pub fn sell_sol_amount(&self) -> u64 {
+        self.sell_sol_amount
+    }
+
+    /// (买 - 卖) / (买 + 卖), 窗口为空时为 0
+    pub fn imbalance(&self) -> f64 {
+        let total = self.buy_sol_amount as f64 + self.sell_sol_amount as f64;
+        if total == 0.0 {
+            0.0
+        } else {
+            (self.buy_sol_amount as f64 - self.sell_sol_amount as f64) / total
+        }
+    }
+}
+
+/// 实时推送节流: 按 mint:interval 保存窗口内最新的K线, 窗口结束时取出发送
+#[derive(Debug, Default)]
+pub struct BroadcastThrottle {
+    pending: std::sync::Mutex<HashMap<String, KlineData>>,
+}
+
+impl BroadcastThrottle {
+    /// 记录最新K线状态; 返回 true 表示开启了新窗口, 调用方需在窗口结束时 take
+    pub fn offer(&self, key: &str, kline: &KlineData) -> bool {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        pending.insert(key.to_string(), kline.clone()).is_none()
+    }
+
+    /// 取出窗口内最新的K线并关闭窗口
+    pub fn take(&self, key: &str) -> Option<KlineData> {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        pending.remove(key)
+    }
+
+    /// 收盘K线已直接推送, 丢弃同一根K线待发送的实时状态
+    pub fn discard_candle(&self, key: &str, time: u64) {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        if pending.get(key).is_some_and(|kline| kline.time == time) {
+            pending.remove(key);
+        }
+    }
+}
+
 /// 历史数据响应
 #[derive(Debug, Serialize, ToSchema)]
 pub struct KlineHistoryResponse {
@@ -225,12 +803,48 @@ pub struct KlineHistoryResponse {
     pub total_count: usize,
 }
 
+/// 连接 auth: 可携带初始订阅 ({ symbol, interval }) 和上次连接的会话令牌
+#[derive(Debug, Default, Deserialize)]
+pub struct ConnectAuth {
+    pub symbol: Option<String>,
+    pub interval: Option<String>,
+    pub subscription_id: Option<String>,
+    pub session_token: Option<String>,
+    #[serde(default)]
+    pub exclude_current: bool,
+    #[serde(default)]
+    pub binary_history: bool, // 请求以 gzip 二进制帧接收 history_data
+}
+
+impl ConnectAuth {
+    /// symbol 和 interval 都存在时才视为初始订阅
+    fn initial_subscription(&self) -> Option<SubscribeRequest> {
+        Some(SubscribeRequest {
+            symbol: self.symbol.clone()?,
+            interval: self.interval.clone()?,
+            subscription_id: self.subscription_id.clone(),
+            imbalance: false,
+            exclude_current: self.exclude_current,
+        })
+    }
+}
+
 /// Socket.IO 请求消息
 #[derive(Debug, Deserialize)]
 pub struct SubscribeRequest {
     pub symbol: String,                  // mint_account
     pub interval: String,                // s1, s30, m5
     pub subscription_id: Option<String>, // 客户端订阅ID
+    #[serde(default)]
+    pub imbalance: bool, // 是否同时接收该 mint 的 imbalance 推送
+    #[serde(default)]
+    pub exclude_current: bool, // 历史数据是否省略进行中的最新K线 (交给实时推送)
+}
+
+/// 按事件类型订阅/取消订阅请求 (跨 mint)
+#[derive(Debug, Deserialize)]
+pub struct TypeSubscribeRequest {
+    pub event_type: String, // 如 TokenCreated, ForceLiquidate
 }
 
 #[derive(Debug, Deserialize)]
@@ -247,6 +861,51 @@ pub struct HistoryRequest {
     pub limit: Option<usize>,
     #[allow(dead_code)]
     pub from: Option<u64>, // 开始时间戳（秒）
+    #[serde(default)]
+    pub exclude_current: bool, // 是否省略进行中的最新K线
+    #[serde(default)]
+    pub min_update_count: Option<u32>, // 省略成交次数少于该值的K线
+}
+
+/// 预热状态: 初始追赶完成前, 订阅只收到 warming_up 通知, 历史数据延迟到就绪后推送
+pub struct WarmupGate {
+    ready_tx: watch::Sender<bool>,
+}
+
+impl WarmupGate {
+    pub fn new(ready: bool) -> Self {
+        let (ready_tx, _) = watch::channel(ready);
+        Self { ready_tx }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        *self.ready_tx.borrow()
+    }
+
+    /// 标记就绪, 唤醒所有等待中的订阅
+    pub fn mark_ready(&self) {
+        self.ready_tx.send_replace(true);
+    }
+
+    /// 等待直到就绪 (已就绪时立即返回)
+    pub async fn wait_ready(&self) {
+        let mut ready_rx = self.ready_tx.subscribe();
+        let _ = ready_rx.wait_for(|ready| *ready).await;
+    }
+}
+
+impl Default for WarmupGate {
+    fn default() -> Self {
+        Self::new(true)
+    }
+}
+
+/// 预热期间先发送通知, 然后等待就绪
+async fn wait_for_warmup<F: FnOnce()>(warmup: &WarmupGate, notify_warming_up: F) {
+    if !warmup.is_ready() {
+        notify_warming_up();
+        warmup.wait_ready().await;
+    }
 }
 
 /// K线推送服务
@@ -255,6 +914,10 @@ pub struct KlineSocketService {
     pub event_storage: Arc<EventStorage>,                // 现有事件存储
     pub subscriptions: Arc<RwLock<SubscriptionManager>>, // 订阅管理
     pub config: KlineConfig,                             // 配置参数
+    pub warmup: Arc<WarmupGate>,                         // 预热状态
+    closed_candles: RwLock<HashMap<String, u64>>, // 已通知收盘的K线时间 (mint:interval -> time)
+    throttle: Arc<BroadcastThrottle>,             // 实时推送节流窗口
+    watchers: std::sync::Mutex<HashMap<String, (Instant, MintWatchersResponse)>>, // 观看人数缓存
 }
 
 impl KlineSocketService {
@@ -274,6 +937,10 @@ impl KlineSocketService {
             event_storage,
             subscriptions: Arc::new(RwLock::new(SubscriptionManager::new())),
             config,
+            warmup: Arc::new(WarmupGate::default()),
+            closed_candles: RwLock::new(HashMap::new()),
+            throttle: Arc::new(BroadcastThrottle::default()),
+            watchers: std::sync::Mutex::new(HashMap::new()),
         };
 
         Ok((service, layer))
@@ -283,6 +950,7 @@ impl KlineSocketService {
     pub fn setup_socket_handlers(&self) {
         let subscriptions = Arc::clone(&self.subscriptions);
         let event_storage = Arc::clone(&self.event_storage);
+        let warmup = Arc::clone(&self.warmup);
 
         // 设置默认命名空间（避免default namespace not found错误）
         self.socketio.ns("/", |_socket: SocketRef| {
@@ -293,185 +961,185 @@ impl KlineSocketService {
         self.socketio.ns("/kline", {
             let subscriptions = subscriptions.clone();
             let event_storage = event_storage.clone();
+            let warmup = warmup.clone();
+            let kline_config = Arc::new(self.config.clone());
+            let subscribe_on_connect = kline_config.subscribe_on_connect;
 
-            move |socket: SocketRef| {
+            move |socket: SocketRef, TryData(auth): TryData<ConnectAuth>| {
                 info!("🔌 New client connected to /kline: {}", socket.id);
 
                 // 保存 socket_id 用于后续使用
                 let socket_id = socket.id.to_string();
 
-                // 注册客户端连接
-                {
-                    let subscriptions = subscriptions.clone();
-                    let socket_id_clone = socket_id.clone();
-                    tokio::spawn(async move {
-                        let mut manager = subscriptions.write().await;
-                        manager.connections.insert(
-                            socket_id_clone.clone(),
-                            ClientConnection {
-                                socket_id: socket_id_clone,
-                                subscriptions: HashSet::new(),
-                                last_activity: Instant::now(),
-                                connection_time: Instant::now(),
-                                subscription_count: 0,
-                                user_agent: None,
-                                kline_data_sent_count: 0,
-                                history_data_sent_count: 0,
-                                total_messages_sent: 0,
-                            },
-                        );
-                    });
-                }
+                // 连接 auth 中的初始订阅 ({ symbol, interval }), 未携带或格式不符时忽略
+                let auth = auth.unwrap_or_default();
+                let initial_subscription =
+                    auth.initial_subscription().filter(|_| subscribe_on_connect);
+
+                // 开启会话恢复时下发令牌, 客户端重连时放入 auth.session_token
+                let session_token = (!kline_config.session_resume_grace.is_zero())
+                    .then(|| uuid::Uuid::new_v4().to_string());
+                let resume_token = auth.session_token.filter(|_| session_token.is_some());
+
+                // 服务端开启 binary_history 时, 按连接协商历史数据的二进制传输
+                let binary_history = auth.binary_history && kline_config.binary_history;
 
                 // 发送连接成功消息
                 let welcome_msg = serde_json::json!({
                     "client_id": socket_id,
                     "server_time": Utc::now().timestamp(),
                     "supported_symbols": [],
-                    "supported_intervals": ["s1", "s30", "m5"]
+                    "supported_intervals": ["s1", "s30", "m5"],
+                    "session_token": session_token,
+                    "binary_history": binary_history
                 });
 
                 if let Err(e) = socket.emit("connection_success", &welcome_msg) {
                     warn!("Failed to send welcome message: {}", e);
                 }
 
+                // 注册客户端连接, 并处理 auth 中的初始订阅
+                {
+                    let socket = socket.clone();
+                    let subscriptions = subscriptions.clone();
+                    let event_storage = event_storage.clone();
+                    let warmup = warmup.clone();
+                    let kline_config = kline_config.clone();
+                    tokio::spawn(async move {
+                        if binary_history {
+                            subscriptions
+                                .write()
+                                .await
+                                .binary_history_clients
+                                .insert(socket.id.to_string());
+                        }
+                        register_client(
+                            &socket,
+                            initial_subscription,
+                            &subscriptions,
+                            &event_storage,
+                            &warmup,
+                            &kline_config,
+                        )
+                        .await;
+                        if let Some(session_token) = session_token {
+                            start_session(
+                                &socket,
+                                session_token,
+                                resume_token,
+                                &subscriptions,
+                                &kline_config,
+                            )
+                            .await;
+                        }
+                    });
+                }
+
                 // 订阅事件处理器
                 socket.on("subscribe", {
                     let subscriptions = subscriptions.clone();
                     let event_storage = event_storage.clone();
+                    let warmup = warmup.clone();
+                    let kline_config = kline_config.clone();
 
                     move |socket: SocketRef, Data(data): Data<SubscribeRequest>| {
                         let subscriptions = subscriptions.clone();
                         let event_storage = event_storage.clone();
+                        let warmup = warmup.clone();
+                        let kline_config = kline_config.clone();
+
+                        tokio::spawn(async move {
+                            subscribe_client(
+                                &socket,
+                                data,
+                                &subscriptions,
+                                &event_storage,
+                                &warmup,
+                                &kline_config,
+                            )
+                            .await;
+                        });
+                    }
+                });
+
+                // 取消订阅事件处理器
+                socket.on("unsubscribe", {
+                    let subscriptions = subscriptions.clone();
+
+                    move |socket: SocketRef, Data(data): Data<UnsubscribeRequest>| {
+                        let subscriptions = subscriptions.clone();
 
                         tokio::spawn(async move {
                             info!(
-                                "📊 Subscribe request from {}: {} {}",
+                                "🚫 Unsubscribe request from {}: {} {}",
                                 socket.id, data.symbol, data.interval
                             );
 
-                            // 验证订阅请求
-                            if let Err(e) = validate_subscribe_request(&data) {
-                                let _ = socket.emit(
-                                    "error",
-                                    &serde_json::json!({
-                                        "code": 1001,
-                                        "message": e.to_string()
-                                    }),
-                                );
-                                return;
-                            }
-
-                            // 添加订阅
+                            // 移除订阅
                             {
                                 let mut manager = subscriptions.write().await;
-                                if let Err(e) = manager.add_subscription(
+                                manager.remove_subscription(
                                     &socket.id.to_string(),
                                     &data.symbol,
                                     &data.interval,
-                                ) {
-                                    let _ = socket.emit(
-                                        "error",
-                                        &serde_json::json!({
-                                            "code": 1002,
-                                            "message": e.to_string()
-                                        }),
-                                    );
-                                    return;
-                                }
-
-                                // 更新活动时间
+                                );
                                 manager.update_activity(&socket.id.to_string());
                             }
 
-                            // 加入对应的房间
+                            // 离开对应的房间
                             let room_name = format!("kline:{}:{}", data.symbol, data.interval);
-                            info!("🏠 Client {} joining room: {}", socket.id, room_name);
-                            socket.join(room_name.clone());
-
-                            // 检查订阅者状态
-                            {
-                                let manager = subscriptions.read().await;
-                                let subscribers =
-                                    manager.get_subscribers(&data.symbol, &data.interval);
-                                info!(
-                                    "📈 Current subscribers for {}:{}: {:?}",
-                                    data.symbol, data.interval, subscribers
-                                );
-                                info!("📋 Total active connections: {}", manager.connections.len());
-                            }
-
-                            // 推送历史数据
-                            if let Ok(history) =
-                                get_kline_history(&event_storage, &data.symbol, &data.interval, 100)
-                                    .await
-                            {
-                                if let Err(e) = socket.emit("history_data", &history) {
-                                    warn!("Failed to send history data: {}", e);
-                                } else {
-                                    // 更新历史数据发送计数
-                                    {
-                                        let mut manager = subscriptions.write().await;
-                                        if let Some(client) =
-                                            manager.connections.get_mut(&socket.id.to_string())
-                                        {
-                                            client.history_data_sent_count += 1;
-                                            client.total_messages_sent += 1;
-                                        }
-                                    }
-                                }
-                            }
+                            socket.leave(room_name);
 
-                            // 确认订阅成功
+                            // 确认取消订阅
                             let _ = socket.emit(
-                                "subscription_confirmed",
+                                "unsubscribe_confirmed",
                                 &serde_json::json!({
                                     "symbol": data.symbol,
                                     "interval": data.interval,
                                     "subscription_id": data.subscription_id,
-                                    "success": true,
-                                    "message": "订阅成功"
+                                    "success": true
                                 }),
                             );
                         });
                     }
                 });
 
-                // 取消订阅事件处理器
-                socket.on("unsubscribe", {
+                // 按事件类型订阅处理器
+                socket.on("subscribe_type", {
                     let subscriptions = subscriptions.clone();
+                    let kline_config = kline_config.clone();
 
-                    move |socket: SocketRef, Data(data): Data<UnsubscribeRequest>| {
+                    move |socket: SocketRef, Data(data): Data<TypeSubscribeRequest>| {
                         let subscriptions = subscriptions.clone();
+                        let kline_config = kline_config.clone();
 
                         tokio::spawn(async move {
-                            info!(
-                                "🚫 Unsubscribe request from {}: {} {}",
-                                socket.id, data.symbol, data.interval
-                            );
+                            subscribe_type_client(&socket, data, &subscriptions, &kline_config)
+                                .await;
+                        });
+                    }
+                });
 
-                            // 移除订阅
+                // 取消按事件类型订阅处理器
+                socket.on("unsubscribe_type", {
+                    let subscriptions = subscriptions.clone();
+
+                    move |socket: SocketRef, Data(data): Data<TypeSubscribeRequest>| {
+                        let subscriptions = subscriptions.clone();
+
+                        tokio::spawn(async move {
                             {
                                 let mut manager = subscriptions.write().await;
-                                manager.remove_subscription(
-                                    &socket.id.to_string(),
-                                    &data.symbol,
-                                    &data.interval,
-                                );
-                                manager.update_activity(&socket.id.to_string());
+                                let socket_id = socket.id.to_string();
+                                manager.remove_type_subscription(&socket_id, &data.event_type);
+                                manager.update_activity(&socket_id);
                             }
+                            socket.leave(format!("type:{}", data.event_type));
 
-                            // 离开对应的房间
-                            let room_name = format!("kline:{}:{}", data.symbol, data.interval);
-                            socket.leave(room_name);
-
-                            // 确认取消订阅
                             let _ = socket.emit(
-                                "unsubscribe_confirmed",
+                                "unsubscribe_type_confirmed",
                                 &serde_json::json!({
-                                    "symbol": data.symbol,
-                                    "interval": data.interval,
-                                    "subscription_id": data.subscription_id,
+                                    "event_type": data.event_type,
                                     "success": true
                                 }),
                             );
@@ -479,6 +1147,19 @@ impl KlineSocketService {
                     }
                 });
 
+                // 取消全部订阅事件处理器 (保留连接)
+                socket.on("unsubscribe_all", {
+                    let subscriptions = subscriptions.clone();
+
+                    move |socket: SocketRef| {
+                        let subscriptions = subscriptions.clone();
+
+                        tokio::spawn(async move {
+                            unsubscribe_all_client(&socket, &subscriptions).await;
+                        });
+                    }
+                });
+
                 // 历史数据事件处理器
                 socket.on("history", {
                     let event_storage = event_storage.clone();
@@ -505,23 +1186,23 @@ impl KlineSocketService {
                                 &data.symbol,
                                 &data.interval,
                                 data.limit.unwrap_or(100),
+                                data.exclude_current,
+                                data.min_update_count,
                             )
                             .await
                             {
                                 Ok(history) => {
-                                    if let Err(e) = socket.emit("history_data", &history) {
+                                    let binary = subscriptions
+                                        .read()
+                                        .await
+                                        .binary_history_clients
+                                        .contains(&socket.id.to_string());
+                                    if let Err(e) = emit_history(&socket, &history, binary) {
                                         warn!("Failed to send history data: {}", e);
                                     } else {
                                         // 更新历史数据发送计数
-                                        {
-                                            let mut manager = subscriptions.write().await;
-                                            if let Some(client) =
-                                                manager.connections.get_mut(&socket.id.to_string())
-                                            {
-                                                client.history_data_sent_count += 1;
-                                                client.total_messages_sent += 1;
-                                            }
-                                        }
+                                        let mut manager = subscriptions.write().await;
+                                        manager.record_history_sent(&socket.id.to_string());
                                     }
                                 }
                                 Err(e) => {
@@ -538,9 +1219,24 @@ impl KlineSocketService {
                     }
                 });
 
+                // 心跳回应处理器
+                socket.on("heartbeat_ack", {
+                    let subscriptions = subscriptions.clone();
+
+                    move |socket: SocketRef| {
+                        let subscriptions = subscriptions.clone();
+
+                        tokio::spawn(async move {
+                            let mut manager = subscriptions.write().await;
+                            manager.record_pong(&socket.id.to_string());
+                        });
+                    }
+                });
+
                 // 连接断开事件处理器
                 socket.on_disconnect({
                     let subscriptions = subscriptions.clone();
+                    let resumable = !kline_config.session_resume_grace.is_zero();
 
                     move |socket: SocketRef| {
                         let subscriptions = subscriptions.clone();
@@ -548,9 +1244,13 @@ impl KlineSocketService {
                         tokio::spawn(async move {
                             info!("🔌 Client disconnected: {}", socket.id);
 
-                            // 清理客户端连接
+                            // 清理客户端连接 (开启会话恢复时保留订阅至宽限期结束)
                             let mut manager = subscriptions.write().await;
-                            manager.remove_client(&socket.id.to_string());
+                            if resumable {
+                                manager.park_client(&socket.id.to_string());
+                            } else {
+                                manager.remove_client(&socket.id.to_string());
+                            }
                         });
                     }
                 });
@@ -558,117 +1258,306 @@ impl KlineSocketService {
         });
     }
 
-    /// 广播K线更新到订阅者
+    /// 广播K线更新到订阅者; 开启 broadcast_throttle 时, 窗口内的实时更新合并为窗口结束时的最新状态,
+    /// 收盘K线始终立即推送
     pub async fn broadcast_kline_update(
         &self,
         mint_account: &str,
         interval: &str,
         kline_data: &KlineData,
     ) -> Result<()> {
-        let room_name = format!("kline:{}:{}", mint_account, interval);
-
-        let update_message = KlineUpdateMessage {
-            symbol: mint_account.to_string(),
-            interval: interval.to_string(),
-            subscription_id: None,
-            data: KlineRealtimeData {
-                time: kline_data.time,
-                open: kline_data.open,
-                high: kline_data.high,
-                low: kline_data.low,
-                close: kline_data.close,
-                volume: kline_data.volume,
-                is_final: kline_data.is_final,
-                update_type: if kline_data.is_final {
-                    "final".to_string()
-                } else {
-                    "realtime".to_string()
-                },
-                update_count: kline_data.update_count,
-            },
-            timestamp: Utc::now().timestamp_millis() as u64,
-        };
+        let window = self.config.broadcast_throttle;
+        if !window.is_zero() {
+            let key = format!("{}:{}", mint_account, interval);
+            if kline_data.is_final {
+                self.throttle.discard_candle(&key, kline_data.time);
+            } else {
+                if self.throttle.offer(&key, kline_data) {
+                    let throttle = Arc::clone(&self.throttle);
+                    let socketio = self.socketio.clone();
+                    let subscriptions = Arc::clone(&self.subscriptions);
+                    let mint_account = mint_account.to_string();
+                    let interval = interval.to_string();
+                    let max_emit_failures = self.config.max_emit_failures;
+                    tokio::spawn(async move {
+                        tokio::time::sleep(window).await;
+                        if let Some(kline) = throttle.take(&key) {
+                            if let Err(e) = emit_kline_update(
+                                &socketio,
+                                &subscriptions,
+                                &mint_account,
+                                &interval,
+                                &kline,
+                                max_emit_failures,
+                            )
+                            .await
+                            {
+                                warn!("❌ Failed to broadcast throttled kline update: {}", e);
+                            }
+                        }
+                    });
+                }
+                return Ok(());
+            }
+        }
 
-        info!("📡 Broadcasting kline update to room: {}", room_name);
-        info!("📊 Update message: time={}, open={}, high={}, low={}, close={}, volume={}, is_final={}, update_count={}",
-            update_message.data.time, update_message.data.open, update_message.data.high,
-            update_message.data.low, update_message.data.close, update_message.data.volume,
-            update_message.data.is_final, update_message.data.update_count);
+        emit_kline_update(
+            &self.socketio,
+            &self.subscriptions,
+            mint_account,
+            interval,
+            kline_data,
+            self.config.max_emit_failures,
+        )
+        .await
+    }
 
-        // 在发送前检查房间中的实际连接
+    /// 记录一次定向发送失败, 连续失败达到 max_emit_failures 时移除该客户端
+    async fn record_emit_failure(&self, socket_id: &str) {
+        let max_failures = self.config.max_emit_failures;
+        if self
+            .subscriptions
+            .write()
+            .await
+            .record_emit_failure(socket_id, max_failures)
         {
-            let manager = self.subscriptions.read().await;
-            let subscribers = manager.get_subscribers(mint_account, interval);
-            info!(
-                "📋 Room {} has {} subscribers: {:?}",
-                room_name,
-                subscribers.len(),
-                subscribers
+            warn!(
+                "🧹 Removed client {} after {} consecutive failed emits",
+                socket_id, max_failures
             );
         }
+    }
+
+    /// 向开启失衡推送的订阅者发送 imbalance 消息
+    pub async fn broadcast_imbalance(&self, message: &ImbalanceMessage) -> Result<usize> {
+        let subscribers = {
+            let manager = self.subscriptions.read().await;
+            manager.get_imbalance_subscribers(&message.symbol)
+        };
+
+        let ns = self
+            .socketio
+            .of("/kline")
+            .ok_or_else(|| anyhow::anyhow!("Namespace /kline not found"))?;
+        for socket_id in &subscribers {
+            match ns.to(socket_id.clone()).emit("imbalance", message).await {
+                Ok(_) => self.subscriptions.write().await.record_push(socket_id),
+                Err(e) => {
+                    warn!("❌ Failed to send imbalance to socket {}: {}", socket_id, e);
+                    self.record_emit_failure(socket_id).await;
+                }
+            }
+        }
+
+        Ok(subscribers.len())
+    }
 
-        // 发送到 /kline 命名空间的房间
-        let result = self
+    /// 向订阅了该 mint 的客户端发送 order_updated 消息, 返回成功发送的客户端数
+    pub async fn broadcast_order_updated(&self, message: &OrderUpdatedMessage) -> Result<usize> {
+        let subscribers = {
+            let manager = self.subscriptions.read().await;
+            manager.get_mint_subscribers(&message.symbol)
+        };
+
+        let ns = self
             .socketio
+            .of("/kline")
+            .ok_or_else(|| anyhow::anyhow!("Namespace /kline not found"))?;
+        let mut sent = 0;
+        for socket_id in &subscribers {
+            match ns
+                .to(socket_id.clone())
+                .emit("order_updated", message)
+                .await
+            {
+                Ok(_) => {
+                    sent += 1;
+                    self.subscriptions.write().await.record_push(socket_id);
+                }
+                Err(e) => {
+                    warn!(
+                        "❌ Failed to send order_updated to socket {}: {}",
+                        socket_id, e
+                    );
+                    self.record_emit_failure(socket_id).await;
+                }
+            }
+        }
+
+        Ok(sent)
+    }
+
+    /// 把事件推送到其类型房间 type:{event_type} (不区分 mint), 返回类型订阅者数
+    pub async fn broadcast_typed_event(&self, event: &SpinPetEvent) -> Result<usize> {
+        let event_type = event.event_type();
+        let subscribers = {
+            let manager = self.subscriptions.read().await;
+            manager.get_type_subscribers(event_type)
+        };
+        if subscribers.is_empty() {
+            return Ok(0);
+        }
+
+        let room_name = format!("type:{}", event_type);
+        self.socketio
             .of("/kline")
             .ok_or_else(|| anyhow::anyhow!("Namespace /kline not found"))?
             .to(room_name.clone())
-            .emit("kline_data", &update_message)
-            .await;
+            .emit("event", event)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to broadcast to room {}: {}", room_name, e))?;
 
-        match result {
-            Ok(_) => {
-                info!(
-                    "✅ Successfully broadcasted kline update to room {}",
-                    room_name
-                );
+        let mut manager = self.subscriptions.write().await;
+        for socket_id in &subscribers {
+            manager.record_push(socket_id);
+        }
+        Ok(subscribers.len())
+    }
 
-                // 验证消息确实发送到了客户端 - 尝试直接发送到socket
-                {
-                    let manager = self.subscriptions.read().await;
-                    let subscribers = manager.get_subscribers(mint_account, interval);
-                    info!(
-                        "🔍 Attempting direct send to {} subscribers",
-                        subscribers.len()
+    /// 找出已订阅周期中已收盘且尚未通知过的最新K线
+    async fn collect_closed_candles(&self, now: u64) -> Vec<KlineUpdateMessage> {
+        let pairs: Vec<(String, String)> = {
+            let manager = self.subscriptions.read().await;
+            manager
+                .mint_subscribers
+                .iter()
+                .flat_map(|(mint, intervals)| {
+                    intervals
+                        .iter()
+                        .filter(|(_, clients)| !clients.is_empty())
+                        .map(move |(interval, _)| (mint.clone(), interval.clone()))
+                })
+                .collect()
+        };
+
+        // 无人订阅的周期不再保留通知记录
+        self.closed_candles.write().await.retain(|key, _| {
+            pairs
+                .iter()
+                .any(|(mint, interval)| *key == format!("{}:{}", mint, interval))
+        });
+
+        let mut closed = Vec::new();
+        for (mint, interval) in pairs {
+            let Some(period) = interval_seconds(&interval) else {
+                continue;
+            };
+            let query = KlineQuery {
+                mint_account: mint.clone(),
+                interval: interval.clone(),
+                page: Some(1),
+                limit: Some(1),
+                order_by: Some("time_desc".to_string()),
+                fill_gaps: false,
+                min_update_count: None,
+            };
+            let kline = match self.event_storage.query_kline_data(query).await {
+                Ok(response) => match response.klines.into_iter().next() {
+                    Some(kline) => kline,
+                    None => continue,
+                },
+                Err(e) => {
+                    warn!(
+                        "⚠️ Failed to load latest kline for {}:{}: {}",
+                        mint, interval, e
                     );
+                    continue;
+                }
+            };
 
-                    for socket_id in &subscribers {
-                        // 尝试直接发送给特定socket (在 /kline 命名空间中)
-                        if let Some(ns) = self.socketio.of("/kline") {
-                            if let Err(e) = ns
-                                .to(socket_id.clone())
-                                .emit("direct_kline_test", &update_message)
-                                .await
-                            {
-                                warn!(
-                                    "❌ Failed to send direct test to socket {}: {}",
-                                    socket_id, e
-                                );
-                            } else {
-                                info!("✅ Direct test sent to socket {}", socket_id);
-                            }
-                        }
-                    }
+            // 周期尚未结束
+            if kline.time + period > now {
+                continue;
+            }
+
+            {
+                let mut announced = self.closed_candles.write().await;
+                let key = format!("{}:{}", mint, interval);
+                if announced.get(&key).is_some_and(|&time| time >= kline.time) {
+                    continue;
                 }
+                announced.insert(key, kline.time);
+            }
+
+            closed.push(KlineUpdateMessage {
+                symbol: mint,
+                interval,
+                subscription_id: None,
+                data: KlineRealtimeData {
+                    time: kline.time,
+                    open: kline.open,
+                    high: kline.high,
+                    low: kline.low,
+                    close: kline.close,
+                    volume: kline.volume,
+                    is_final: true,
+                    update_type: "final".to_string(),
+                    update_count: kline.update_count,
+                },
+                timestamp: Utc::now().timestamp_millis() as u64,
+            });
+        }
+
+        closed
+    }
 
-                // 更新所有订阅了该房间的客户端的 kline_data 发送计数
+    /// 执行一轮收盘通知, 返回发送的消息数
+    /// batch_finalizations 开启时每个订阅者只收到一条 candles_closed 数组消息
+    pub async fn run_finalization_pass(&self, now: u64) -> Result<usize> {
+        let closed = self.collect_closed_candles(now).await;
+        if closed.is_empty() {
+            return Ok(0);
+        }
+
+        let ns = self
+            .socketio
+            .of("/kline")
+            .ok_or_else(|| anyhow::anyhow!("Namespace /kline not found"))?;
+        let mut sent = 0;
+
+        if self.config.batch_finalizations {
+            let batches = {
+                let manager = self.subscriptions.read().await;
+                group_closed_by_subscriber(&manager, &closed)
+            };
+            for (socket_id, candles) in batches {
+                match ns
+                    .to(socket_id.clone())
+                    .emit("candles_closed", &candles)
+                    .await
                 {
-                    let mut manager = self.subscriptions.write().await;
-                    let subscribers = manager.get_subscribers(mint_account, interval);
-                    for socket_id in subscribers {
-                        if let Some(client) = manager.connections.get_mut(&socket_id) {
-                            client.kline_data_sent_count += 1;
-                            client.total_messages_sent += 1;
-                        }
+                    Ok(_) => {
+                        sent += 1;
+                        self.subscriptions.write().await.record_push(&socket_id);
+                    }
+                    Err(e) => {
+                        warn!("❌ Failed to send candles_closed to {}: {}", socket_id, e);
+                        self.record_emit_failure(&socket_id).await;
                     }
                 }
             }
-            Err(e) => {
-                warn!("❌ Failed to broadcast to room {}: {}", room_name, e);
+        } else {
+            for candle in &closed {
+                let room_name = format!("kline:{}:{}", candle.symbol, candle.interval);
+                match ns.to(room_name.clone()).emit("candle_closed", candle).await {
+                    Ok(_) => {
+                        sent += 1;
+                        let mut manager = self.subscriptions.write().await;
+                        for socket_id in manager.get_subscribers(&candle.symbol, &candle.interval) {
+                            manager.record_push(&socket_id);
+                        }
+                    }
+                    Err(e) => warn!("❌ Failed to send candle_closed to {}: {}", room_name, e),
+                }
             }
         }
 
-        Ok(())
+        debug!(
+            "🕯️ Announced {} closed candles in {} messages",
+            closed.len(),
+            sent
+        );
+        Ok(sent)
     }
 
     /// 获取服务统计信息
@@ -688,6 +1577,45 @@ impl KlineSocketService {
         })
     }
 
+    /// 与 /kline 命名空间的实际连接核对, 移除已不存在的 socket; 命名空间未注册时不做处理
+    pub async fn reconcile_connections(&self) -> Vec<String> {
+        let Some(ns) = self.socketio.of("/kline") else {
+            return Vec::new();
+        };
+        let live_sockets: HashSet<String> = ns
+            .sockets()
+            .iter()
+            .map(|socket| socket.id.to_string())
+            .collect();
+        self.subscriptions
+            .write()
+            .await
+            .reconcile_connections(&live_sockets)
+    }
+
+    /// 某 mint 的观看人数, 在 watchers_cache 内复用上次结果以免频繁争用订阅读锁
+    pub async fn get_watchers(&self, mint: &str) -> MintWatchersResponse {
+        let now = Instant::now();
+        {
+            let cache = self.watchers.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some((read_at, counts)) = cache.get(mint) {
+                if now.duration_since(*read_at) < self.config.watchers_cache {
+                    return counts.clone();
+                }
+            }
+        }
+
+        let counts = self.subscriptions.read().await.watcher_counts(mint);
+        if !self.config.watchers_cache.is_zero() {
+            let mut cache = self.watchers.lock().unwrap_or_else(|e| e.into_inner());
+            cache.retain(|_, (read_at, _)| {
+                now.duration_since(*read_at) < self.config.watchers_cache
+            });
+            cache.insert(mint.to_string(), (now, counts.clone()));
+        }
+        counts
+    }
+
     /// 获取详细的订阅状态和通讯统计
     pub async fn get_subscription_details(&self) -> serde_json::Value {
         let manager = self.subscriptions.read().await;
@@ -709,7 +1637,8 @@ impl KlineSocketService {
                 "message_stats": {
                     "kline_data_sent": client.kline_data_sent_count,
                     "history_data_sent": client.history_data_sent_count,
-                    "total_messages_sent": client.total_messages_sent
+                    "total_messages_sent": client.total_messages_sent,
+                    "emit_failures": client.emit_failures
                 }
             }));
         }
@@ -739,97 +1668,603 @@ impl KlineSocketService {
     }
 }
 
-/// 验证订阅请求
-fn validate_subscribe_request(req: &SubscribeRequest) -> Result<()> {
-    // 验证时间间隔
-    if !["s1", "s30", "m5"].contains(&req.interval.as_str()) {
-        return Err(anyhow::anyhow!(
-            "Invalid interval: {}, must be one of: s1, s30, m5",
-            req.interval
-        ));
+/// 订阅流程的消息出口: 实际为 SocketRef, 测试中可替换为记录器
+trait SubscriptionSink {
+    fn client_id(&self) -> String;
+    fn emit_message<T: Serialize + ?Sized>(&self, event: &str, data: &T) -> Result<()>;
+    fn emit_binary(&self, event: &str, data: Vec<u8>) -> Result<()>;
+    fn join_room(&self, room: String);
+    fn leave_room(&self, room: String);
+}
+
+impl SubscriptionSink for SocketRef {
+    fn client_id(&self) -> String {
+        self.id.to_string()
     }
 
-    // 验证symbol格式（基本的Solana地址格式检查）
-    if req.symbol.len() < 32 || req.symbol.len() > 44 {
-        return Err(anyhow::anyhow!("Invalid symbol format"));
+    fn emit_message<T: Serialize + ?Sized>(&self, event: &str, data: &T) -> Result<()> {
+        self.emit(event, data)
+            .map_err(|e| anyhow::anyhow!("Failed to emit {}: {}", event, e))
     }
 
-    Ok(())
-}
+    fn emit_binary(&self, event: &str, data: Vec<u8>) -> Result<()> {
+        self.emit(event, &BinaryPayload(data))
+            .map_err(|e| anyhow::anyhow!("Failed to emit {}: {}", event, e))
+    }
 
-/// 获取历史K线数据
-async fn get_kline_history(
-    event_storage: &Arc<EventStorage>,
-    symbol: &str,
-    interval: &str,
-    limit: usize,
-) -> Result<KlineHistoryResponse> {
-    let query = KlineQuery {
-        mint_account: symbol.to_string(),
-        interval: interval.to_string(),
-        page: Some(1),
-        limit: Some(limit),
-        order_by: Some("time_desc".to_string()),
-    };
+    fn join_room(&self, room: String) {
+        self.join(room);
+    }
 
-    let response = event_storage.query_kline_data(query).await?;
+    fn leave_room(&self, room: String) {
+        self.leave(room);
+    }
+}
 
-    let data: Vec<KlineRealtimeData> = response
-        .klines
-        .into_iter()
-        .map(|kline| KlineRealtimeData {
-            time: kline.time,
-            open: kline.open,
-            high: kline.high,
-            low: kline.low,
-            close: kline.close,
-            volume: kline.volume,
-            is_final: kline.is_final,
-            update_type: if kline.is_final {
-                "final".to_string()
-            } else {
-                "realtime".to_string()
-            },
-            update_count: kline.update_count,
-        })
-        .collect();
+/// 以 serialize_bytes 序列化, socketioxide 将其作为二进制附件发送
+struct BinaryPayload(Vec<u8>);
 
-    Ok(KlineHistoryResponse {
-        symbol: symbol.to_string(),
-        interval: interval.to_string(),
-        data,
-        has_more: response.has_next,
-        total_count: response.total,
-    })
+impl Serialize for BinaryPayload {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.0)
+    }
 }
 
-/// 连接清理任务
-pub async fn start_connection_cleanup_task(
-    subscriptions: Arc<RwLock<SubscriptionManager>>,
-    config: KlineConfig,
-) -> tokio::task::JoinHandle<()> {
-    tokio::spawn(async move {
-        let mut interval = tokio::time::interval(Duration::from_secs(30)); // 每30秒清理一次
-
-        loop {
-            interval.tick().await;
+/// gzip 压缩的 JSON
+fn gzip_json<T: Serialize + ?Sized>(data: &T) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    serde_json::to_writer(&mut encoder, data)?;
+    Ok(encoder.finish()?)
+}
 
-            let now = Instant::now();
-            let inactive_clients: Vec<String>;
+/// 推送 history_data: 协商了二进制传输的连接收到 gzip 压缩 JSON 的二进制帧, 其余为文本帧
+fn emit_history<S: SubscriptionSink>(
+    sink: &S,
+    history: &KlineHistoryResponse,
+    binary: bool,
+) -> Result<()> {
+    if binary {
+        sink.emit_binary("history_data", gzip_json(history)?)
+    } else {
+        sink.emit_message("history_data", history)
+    }
+}
 
-            // 查找超时的连接
+/// 登记新连接; 若连接 auth 携带了初始订阅, 立即按 subscribe 流程处理
+async fn register_client<S: SubscriptionSink + Sync>(
+    sink: &S,
+    initial_subscription: Option<SubscribeRequest>,
+    subscriptions: &RwLock<SubscriptionManager>,
+    event_storage: &Arc<EventStorage>,
+    warmup: &WarmupGate,
+    config: &KlineConfig,
+) {
+    let socket_id = sink.client_id();
+    {
+        let mut manager = subscriptions.write().await;
+        manager.connections.insert(
+            socket_id.clone(),
+            ClientConnection::new(socket_id.clone(), Instant::now()),
+        );
+    }
+
+    if let Some(data) = initial_subscription {
+        info!(
+            "🔗 Subscribe-on-connect from {}: {} {}",
+            socket_id, data.symbol, data.interval
+        );
+        subscribe_client(sink, data, subscriptions, event_storage, warmup, config).await;
+    }
+}
+
+/// 为连接绑定会话令牌; 若携带的旧令牌仍在宽限期内, 恢复其订阅并重新加入房间, 返回恢复的订阅数
+async fn start_session<S: SubscriptionSink + Sync>(
+    sink: &S,
+    session_token: String,
+    resume_token: Option<String>,
+    subscriptions: &RwLock<SubscriptionManager>,
+    config: &KlineConfig,
+) -> usize {
+    let socket_id = sink.client_id();
+    let restored = {
+        let mut manager = subscriptions.write().await;
+        let restored = resume_token.and_then(|token| {
+            manager.resume_session(&socket_id, &token, config.session_resume_grace)
+        });
+        // 令牌每次连接轮换, 下次重连使用本次 connection_success 中下发的令牌
+        manager
+            .session_tokens
+            .insert(socket_id.clone(), session_token);
+        restored
+    };
+
+    let Some(restored) = restored else {
+        return 0;
+    };
+    info!(
+        "♻️ Session resumed for {}: {} subscriptions restored",
+        socket_id,
+        restored.len()
+    );
+
+    for (mint, interval) in &restored {
+        sink.join_room(format!("kline:{}:{}", mint, interval));
+    }
+
+    let _ = sink.emit_message(
+        "session_resumed",
+        &serde_json::json!({
+            "subscriptions": restored
+                .iter()
+                .map(|(mint, interval)| serde_json::json!({ "symbol": mint, "interval": interval }))
+                .collect::<Vec<_>>(),
+            "success": true
+        }),
+    );
+
+    restored.len()
+}
+
+/// 处理订阅: 验证 -> 登记 -> 加入房间 -> (预热等待) -> 推送历史 -> 确认
+async fn subscribe_client<S: SubscriptionSink + Sync>(
+    sink: &S,
+    data: SubscribeRequest,
+    subscriptions: &RwLock<SubscriptionManager>,
+    event_storage: &Arc<EventStorage>,
+    warmup: &WarmupGate,
+    config: &KlineConfig,
+) {
+    let socket_id = sink.client_id();
+    info!(
+        "📊 Subscribe request from {}: {} {}",
+        socket_id, data.symbol, data.interval
+    );
+
+    // 验证订阅请求
+    if let Err(e) = validate_subscribe_request(&data) {
+        let _ = sink.emit_message(
+            "error",
+            &serde_json::json!({
+                "code": 1001,
+                "message": e.to_string()
+            }),
+        );
+        return;
+    }
+
+    // 添加订阅
+    {
+        let mut manager = subscriptions.write().await;
+        if let Err(e) = manager.add_subscription(&socket_id, &data.symbol, &data.interval) {
+            let _ = sink.emit_message(
+                "error",
+                &serde_json::json!({
+                    "code": 1002,
+                    "message": e.to_string()
+                }),
+            );
+            return;
+        }
+
+        // 按需开启买卖失衡推送
+        if data.imbalance && !config.imbalance_window.is_zero() {
+            manager.add_imbalance_subscription(&socket_id, &data.symbol);
+        }
+
+        // 更新活动时间
+        manager.update_activity(&socket_id);
+    }
+
+    // 加入对应的房间
+    let room_name = format!("kline:{}:{}", data.symbol, data.interval);
+    info!("🏠 Client {} joining room: {}", socket_id, room_name);
+    sink.join_room(room_name);
+
+    // 检查订阅者状态
+    {
+        let manager = subscriptions.read().await;
+        let subscribers = manager.get_subscribers(&data.symbol, &data.interval);
+        info!(
+            "📈 Current subscribers for {}:{}: {:?}",
+            data.symbol, data.interval, subscribers
+        );
+        info!("📋 Total active connections: {}", manager.connections.len());
+    }
+
+    // 预热期间延迟历史数据, 避免推送不完整的数据
+    wait_for_warmup(warmup, || {
+        let _ = sink.emit_message(
+            "warming_up",
+            &serde_json::json!({
+                "symbol": data.symbol,
+                "interval": data.interval,
+                "message": "服务预热中, 历史数据将在就绪后推送"
+            }),
+        );
+    })
+    .await;
+
+    // 推送历史数据 (条数按周期配置)
+    let history_limit = config.subscribe_history_limit(&data.interval);
+    if let Ok(history) = get_kline_history(
+        event_storage,
+        &data.symbol,
+        &data.interval,
+        history_limit,
+        data.exclude_current,
+        None,
+    )
+    .await
+    {
+        let binary = subscriptions
+            .read()
+            .await
+            .binary_history_clients
+            .contains(&socket_id);
+        if let Err(e) = emit_history(sink, &history, binary) {
+            warn!("Failed to send history data: {}", e);
+        } else {
+            // 更新历史数据发送计数
+            let mut manager = subscriptions.write().await;
+            manager.record_history_sent(&socket_id);
+        }
+    }
+
+    // 确认订阅成功
+    let _ = sink.emit_message(
+        "subscription_confirmed",
+        &serde_json::json!({
+            "symbol": data.symbol,
+            "interval": data.interval,
+            "subscription_id": data.subscription_id,
+            "bucket_secs": EventStorage::interval_seconds(&data.interval),
+            "alignment_epoch": KLINE_ALIGNMENT_EPOCH,
+            "success": true,
+            "message": "订阅成功"
+        }),
+    );
+}
+
+/// 取消客户端的全部订阅并离开对应房间, 连接保持不变; 返回移除的订阅数
+async fn unsubscribe_all_client<S: SubscriptionSink + Sync>(
+    sink: &S,
+    subscriptions: &RwLock<SubscriptionManager>,
+) -> usize {
+    let socket_id = sink.client_id();
+    let removed = {
+        let mut manager = subscriptions.write().await;
+        let removed = manager.remove_all_subscriptions(&socket_id);
+        manager.update_activity(&socket_id);
+        removed
+    };
+    info!(
+        "🚫 Unsubscribe-all request from {}: {} subscriptions removed",
+        socket_id,
+        removed.len()
+    );
+
+    for (mint, interval) in &removed {
+        sink.leave_room(format!("kline:{}:{}", mint, interval));
+    }
+
+    // 确认取消全部订阅
+    let _ = sink.emit_message(
+        "unsubscribe_all_confirmed",
+        &serde_json::json!({
+            "removed": removed.len(),
+            "success": true
+        }),
+    );
+
+    removed.len()
+}
+
+/// 处理按事件类型订阅: 验证类型 -> 登记 -> 加入房间 type:{event_type} -> 确认
+async fn subscribe_type_client<S: SubscriptionSink + Sync>(
+    sink: &S,
+    data: TypeSubscribeRequest,
+    subscriptions: &RwLock<SubscriptionManager>,
+    config: &KlineConfig,
+) -> bool {
+    let socket_id = sink.client_id();
+    info!(
+        "🏷️ Type subscribe request from {}: {}",
+        socket_id, data.event_type
+    );
+
+    let error = if !config.type_channels {
+        Some("Event type subscriptions are disabled".to_string())
+    } else if !EVENT_TYPE_CHANNELS.contains(&data.event_type.as_str()) {
+        Some(format!(
+            "Invalid event_type: {}, must be one of: {}",
+            data.event_type,
+            EVENT_TYPE_CHANNELS.join(", ")
+        ))
+    } else {
+        None
+    };
+    if let Some(message) = error {
+        let _ = sink.emit_message(
+            "error",
+            &serde_json::json!({
+                "code": 1004,
+                "message": message
+            }),
+        );
+        return false;
+    }
+
+    {
+        let mut manager = subscriptions.write().await;
+        manager.add_type_subscription(&socket_id, &data.event_type);
+        manager.update_activity(&socket_id);
+    }
+    sink.join_room(format!("type:{}", data.event_type));
+
+    let _ = sink.emit_message(
+        "subscribe_type_confirmed",
+        &serde_json::json!({
+            "event_type": data.event_type,
+            "success": true
+        }),
+    );
+    true
+}
+
+/// K线周期长度 (秒)
+fn interval_seconds(interval: &str) -> Option<u64> {
+    match interval {
+        "s1" => Some(1),
+        "s30" => Some(30),
+        "m5" => Some(300),
+        _ => None,
+    }
+}
+
+/// 按订阅者分组收盘K线: socket_id -> 该连接订阅的收盘K线
+fn group_closed_by_subscriber(
+    manager: &SubscriptionManager,
+    closed: &[KlineUpdateMessage],
+) -> HashMap<String, Vec<KlineUpdateMessage>> {
+    let mut batches: HashMap<String, Vec<KlineUpdateMessage>> = HashMap::new();
+    for candle in closed {
+        for socket_id in manager.get_subscribers(&candle.symbol, &candle.interval) {
+            batches.entry(socket_id).or_default().push(candle.clone());
+        }
+    }
+    batches
+}
+
+/// 验证订阅请求
+fn validate_subscribe_request(req: &SubscribeRequest) -> Result<()> {
+    // 验证时间间隔
+    if !["s1", "s30", "m5"].contains(&req.interval.as_str()) {
+        return Err(anyhow::anyhow!(
+            "Invalid interval: {}, must be one of: s1, s30, m5",
+            req.interval
+        ));
+    }
+
+    // 验证symbol格式（基本的Solana地址格式检查）
+    if req.symbol.len() < 32 || req.symbol.len() > 44 {
+        return Err(anyhow::anyhow!("Invalid symbol format"));
+    }
+
+    Ok(())
+}
+
+/// 向 kline:{mint}:{interval} 房间发送 kline_data
+async fn emit_kline_update(
+    socketio: &SocketIo,
+    subscriptions: &RwLock<SubscriptionManager>,
+    mint_account: &str,
+    interval: &str,
+    kline_data: &KlineData,
+    max_emit_failures: u32,
+) -> Result<()> {
+    let room_name = format!("kline:{}:{}", mint_account, interval);
+
+    let update_message = KlineUpdateMessage {
+        symbol: mint_account.to_string(),
+        interval: interval.to_string(),
+        subscription_id: None,
+        data: KlineRealtimeData {
+            time: kline_data.time,
+            open: kline_data.open,
+            high: kline_data.high,
+            low: kline_data.low,
+            close: kline_data.close,
+            volume: kline_data.volume,
+            is_final: kline_data.is_final,
+            update_type: if kline_data.is_final {
+                "final".to_string()
+            } else {
+                "realtime".to_string()
+            },
+            update_count: kline_data.update_count,
+        },
+        timestamp: Utc::now().timestamp_millis() as u64,
+    };
+
+    info!("📡 Broadcasting kline update to room: {}", room_name);
+    info!("📊 Update message: time={}, open={}, high={}, low={}, close={}, volume={}, is_final={}, update_count={}",
+        update_message.data.time, update_message.data.open, update_message.data.high,
+        update_message.data.low, update_message.data.close, update_message.data.volume,
+        update_message.data.is_final, update_message.data.update_count);
+
+    // 在发送前检查房间中的实际连接
+    {
+        let manager = subscriptions.read().await;
+        let subscribers = manager.get_subscribers(mint_account, interval);
+        info!(
+            "📋 Room {} has {} subscribers: {:?}",
+            room_name,
+            subscribers.len(),
+            subscribers
+        );
+    }
+
+    // 发送到 /kline 命名空间的房间
+    let result = socketio
+        .of("/kline")
+        .ok_or_else(|| anyhow::anyhow!("Namespace /kline not found"))?
+        .to(room_name.clone())
+        .emit("kline_data", &update_message)
+        .await;
+
+    match result {
+        Ok(_) => {
+            info!(
+                "✅ Successfully broadcasted kline update to room {}",
+                room_name
+            );
+
+            // 验证消息确实发送到了客户端 - 尝试直接发送到socket
+            let mut failed = HashSet::new();
             {
                 let manager = subscriptions.read().await;
-                inactive_clients = manager
-                    .connections
-                    .iter()
-                    .filter(|(_, conn)| {
-                        now.duration_since(conn.last_activity) > config.connection_timeout
-                    })
-                    .map(|(id, _)| id.clone())
-                    .collect();
+                let subscribers = manager.get_subscribers(mint_account, interval);
+                info!(
+                    "🔍 Attempting direct send to {} subscribers",
+                    subscribers.len()
+                );
+
+                for socket_id in &subscribers {
+                    // 尝试直接发送给特定socket (在 /kline 命名空间中)
+                    if let Some(ns) = socketio.of("/kline") {
+                        if let Err(e) = ns
+                            .to(socket_id.clone())
+                            .emit("direct_kline_test", &update_message)
+                            .await
+                        {
+                            warn!(
+                                "❌ Failed to send direct test to socket {}: {}",
+                                socket_id, e
+                            );
+                            failed.insert(socket_id.clone());
+                        } else {
+                            info!("✅ Direct test sent to socket {}", socket_id);
+                        }
+                    }
+                }
             }
 
+            // 更新订阅了该房间的客户端的 kline_data 发送计数; 定向发送失败的客户端累计失败次数
+            {
+                let mut manager = subscriptions.write().await;
+                let subscribers = manager.get_subscribers(mint_account, interval);
+                for socket_id in subscribers {
+                    if !failed.contains(&socket_id) {
+                        manager.record_kline_sent(&socket_id);
+                    } else if manager.record_emit_failure(&socket_id, max_emit_failures) {
+                        warn!(
+                            "🧹 Removed client {} after {} consecutive failed emits",
+                            socket_id, max_emit_failures
+                        );
+                    }
+                }
+            }
+        }
+        Err(e) => {
+            warn!("❌ Failed to broadcast to room {}: {}", room_name, e);
+
+            // 房间广播失败时, 房间内每个订阅者都累计一次失败
+            let mut manager = subscriptions.write().await;
+            for socket_id in manager.get_subscribers(mint_account, interval) {
+                if manager.record_emit_failure(&socket_id, max_emit_failures) {
+                    warn!(
+                        "🧹 Removed client {} after {} consecutive failed emits",
+                        socket_id, max_emit_failures
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// 获取历史K线数据; exclude_current 时省略尚未收盘的最新K线, 避免与实时推送重复绘制;
+/// min_update_count 时省略成交次数不足的K线
+async fn get_kline_history(
+    event_storage: &Arc<EventStorage>,
+    symbol: &str,
+    interval: &str,
+    limit: usize,
+    exclude_current: bool,
+    min_update_count: Option<u32>,
+) -> Result<KlineHistoryResponse> {
+    let query = KlineQuery {
+        mint_account: symbol.to_string(),
+        interval: interval.to_string(),
+        page: Some(1),
+        limit: Some(limit),
+        order_by: Some("time_desc".to_string()),
+        fill_gaps: false,
+        min_update_count,
+    };
+
+    let mut response = event_storage.query_kline_data(query).await?;
+
+    // 按时间倒序, 第一条即最新K线
+    if exclude_current {
+        let now = Utc::now().timestamp() as u64;
+        let period = interval_seconds(interval).unwrap_or(0);
+        if response
+            .klines
+            .first()
+            .is_some_and(|kline| !kline.is_final && kline.time + period > now)
+        {
+            response.klines.remove(0);
+        }
+    }
+
+    let data: Vec<KlineRealtimeData> = response
+        .klines
+        .into_iter()
+        .map(|kline| KlineRealtimeData {
+            time: kline.time,
+            open: kline.open,
+            high: kline.high,
+            low: kline.low,
+            close: kline.close,
+            volume: kline.volume,
+            is_final: kline.is_final,
+            update_type: if kline.is_final {
+                "final".to_string()
+            } else {
+                "realtime".to_string()
+            },
+            update_count: kline.update_count,
+        })
+        .collect();
+
+    Ok(KlineHistoryResponse {
+        symbol: symbol.to_string(),
+        interval: interval.to_string(),
+        data,
+        has_more: response.has_next,
+        total_count: response.total,
+    })
+}
+
+/// 连接清理任务
+pub async fn start_connection_cleanup_task(
+    subscriptions: Arc<RwLock<SubscriptionManager>>,
+    config: KlineConfig,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(30)); // 每30秒清理一次
+
+        loop {
+            interval.tick().await;
+
+            // 查找超时的连接
+            let inactive_clients = subscriptions
+                .read()
+                .await
+                .inactive_clients(Instant::now(), &config);
+
             // 清理超时连接
             if !inactive_clients.is_empty() {
                 let mut manager = subscriptions.write().await;
@@ -839,6 +2274,15 @@ pub async fn start_connection_cleanup_task(
                 }
             }
 
+            // 清理超过宽限期的断线会话
+            {
+                let mut manager = subscriptions.write().await;
+                let pruned = manager.prune_parked_sessions(config.session_resume_grace);
+                if pruned > 0 {
+                    debug!("🧹 Dropped {} expired parked sessions", pruned);
+                }
+            }
+
             // 记录统计信息
             let manager = subscriptions.read().await;
             debug!(
@@ -854,6 +2298,67 @@ pub async fn start_connection_cleanup_task(
     })
 }
 
+/// 连接核对任务: 定期移除命名空间中已不存在的 socket 对应的连接
+pub async fn start_connection_reconcile_task(
+    service: Arc<KlineSocketService>,
+    period: Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+
+        loop {
+            interval.tick().await;
+
+            let stale = service.reconcile_connections().await;
+            if !stale.is_empty() {
+                warn!(
+                    "🧹 Removed {} connections missing from the /kline namespace: {:?}",
+                    stale.len(),
+                    stale
+                );
+            }
+        }
+    })
+}
+
+/// 收盘通知任务
+pub async fn start_kline_finalization_task(
+    service: Arc<KlineSocketService>,
+    period: Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+
+        loop {
+            interval.tick().await;
+
+            let now = Utc::now().timestamp() as u64;
+            if let Err(e) = service.run_finalization_pass(now).await {
+                warn!("❌ Kline finalization pass failed: {}", e);
+            }
+        }
+    })
+}
+
+/// 心跳任务: 每个 ping_interval 向 /kline 广播 heartbeat, 客户端以 heartbeat_ack 回应
+pub async fn start_heartbeat_task(service: Arc<KlineSocketService>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(service.config.ping_interval);
+
+        loop {
+            interval.tick().await;
+
+            let Some(ns) = service.socketio.of("/kline") else {
+                continue;
+            };
+            let heartbeat = serde_json::json!({ "server_time": Utc::now().timestamp() });
+            if let Err(e) = ns.emit("heartbeat", &heartbeat).await {
+                warn!("❌ Failed to broadcast heartbeat: {}", e);
+            }
+        }
+    })
+}
+
 /// 性能监控任务
 pub async fn start_performance_monitoring_task(
     subscriptions: Arc<RwLock<SubscriptionManager>>,
@@ -901,21 +2406,98 @@ pub async fn start_performance_monitoring_task(
     })
 }
 
-/// 扩展的事件处理器，增加K线实时推送功能
+/// K线实时推送事件处理器 (需在 stats 处理器之后运行, 以读取已更新的K线数据)
 pub struct KlineEventHandler {
-    pub stats_handler: Arc<StatsEventHandler>,
     pub kline_service: Arc<KlineSocketService>,
+    imbalance_windows: Mutex<HashMap<String, TradeImbalanceWindow>>, // 仅保留有失衡订阅者的 mint
+    last_prices: std::sync::Mutex<HashMap<String, u128>>, // 每个 mint 最近推送的价格 (skip_unchanged_price)
 }
 
 impl KlineEventHandler {
-    pub fn new(
-        stats_handler: Arc<StatsEventHandler>,
-        kline_service: Arc<KlineSocketService>,
-    ) -> Self {
+    pub fn new(kline_service: Arc<KlineSocketService>) -> Self {
         Self {
-            stats_handler,
             kline_service,
+            imbalance_windows: Mutex::new(HashMap::new()),
+            last_prices: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 开启 skip_unchanged_price 时, 价格与该 mint 上次推送的价格相同则返回 true, 并记录本次价格
+    fn price_unchanged(&self, mint_account: &str, latest_price: u128) -> bool {
+        if !self.kline_service.config.skip_unchanged_price {
+            return false;
+        }
+        let mut last_prices = self.last_prices.lock().unwrap_or_else(|e| e.into_inner());
+        last_prices.insert(mint_account.to_string(), latest_price) == Some(latest_price)
+    }
+
+    /// 将买卖成交计入滚动窗口, 有失衡订阅者时推送最新 imbalance
+    async fn update_imbalance(&self, event: &BuySellEvent) -> Result<()> {
+        let window = self.kline_service.config.imbalance_window;
+        if window.is_zero() {
+            return Ok(());
+        }
+
+        let has_subscribers = {
+            let manager = self.kline_service.subscriptions.read().await;
+            manager
+                .imbalance_subscribers
+                .contains_key(&event.mint_account)
+        };
+        let message = {
+            let mut windows = self.imbalance_windows.lock().await;
+            if !has_subscribers {
+                windows.remove(&event.mint_account);
+                return Ok(());
+            }
+            let trades = windows.entry(event.mint_account.clone()).or_default();
+            trades.record(
+                event.timestamp.timestamp(),
+                event.is_buy,
+                event.sol_amount,
+                window.as_secs(),
+            );
+            ImbalanceMessage {
+                symbol: event.mint_account.clone(),
+                window_secs: window.as_secs(),
+                buy_sol_amount: trades.buy_sol_amount(),
+                sell_sol_amount: trades.sell_sol_amount(),
+                imbalance: trades.imbalance(),
+                timestamp: Utc::now().timestamp_millis() as u64,
+            }
+        };
+
+        self.kline_service.broadcast_imbalance(&message).await?;
+        Ok(())
+    }
+
+    /// 部分平仓后存储中的最新订单; 持久化策略未保存订单索引时为 None
+    async fn order_updated_message(
+        &self,
+        event: &PartialCloseEvent,
+    ) -> Result<Option<OrderUpdatedMessage>> {
+        let order = self
+            .kline_service
+            .event_storage
+            .get_order_by_pda(
+                &event.mint_account,
+                OrderType::from_raw(event.order_type),
+                &event.order_pda,
+            )
+            .await?;
+        Ok(order.map(|order| OrderUpdatedMessage {
+            symbol: event.mint_account.clone(),
+            order,
+            timestamp: Utc::now().timestamp_millis() as u64,
+        }))
+    }
+
+    /// 将部分平仓后的订单以 order_updated 推送给该 mint 的订阅者
+    async fn push_order_updated(&self, event: &PartialCloseEvent) -> Result<()> {
+        if let Some(message) = self.order_updated_message(event).await? {
+            self.kline_service.broadcast_order_updated(&message).await?;
         }
+        Ok(())
     }
 
     /// 提取事件中的价格信息
@@ -935,20 +2517,38 @@ impl KlineEventHandler {
         }
     }
 
-    /// 触发K线数据推送
+    /// 当前有订阅者的周期 (按 s1, s30, m5 顺序)
+    async fn subscribed_intervals(&self, mint_account: &str) -> Vec<&'static str> {
+        let manager = self.kline_service.subscriptions.read().await;
+        ["s1", "s30", "m5"]
+            .into_iter()
+            .filter(|interval| !manager.get_subscribers(mint_account, interval).is_empty())
+            .collect()
+    }
+
+    /// 触发K线数据推送, 返回实际查询并推送的周期
     async fn trigger_kline_push(
         &self,
         mint_account: &str,
         latest_price: u128,
         timestamp: DateTime<Utc>,
-    ) -> Result<()> {
+    ) -> Result<Vec<&'static str>> {
         info!(
             "🔔 Triggering kline push for mint: {}, price: {}, timestamp: {}",
             mint_account, latest_price, timestamp
         );
-        let intervals = ["s1", "s30", "m5"];
 
-        for interval in intervals {
+        // 只为有订阅者的周期查询和广播; 存储层仍会写入全部周期
+        let intervals = self.subscribed_intervals(mint_account).await;
+        if intervals.is_empty() {
+            debug!(
+                "🚫 No subscribers for {}, skipping kline push",
+                mint_account
+            );
+            return Ok(intervals);
+        }
+
+        for &interval in &intervals {
             info!(
                 "📊 Processing interval: {} for mint: {}",
                 interval, mint_account
@@ -986,7 +2586,7 @@ impl KlineEventHandler {
             }
         }
 
-        Ok(())
+        Ok(intervals)
     }
 
     /// 获取最新K线数据
@@ -1003,6 +2603,8 @@ impl KlineEventHandler {
             page: Some(1),
             limit: Some(1),
             order_by: Some("time_desc".to_string()),
+            fill_gaps: false,
+            min_update_count: None,
         };
 
         let response = self
@@ -1024,11 +2626,13 @@ impl EventHandler for KlineEventHandler {
     async fn handle_event(&self, event: SpinPetEvent) -> anyhow::Result<()> {
         info!("🎯 KlineEventHandler received event: {:?}", event);
 
-        // 1. 调用现有的统计和存储逻辑
-        self.stats_handler.handle_event(event.clone()).await?;
-
-        // 2. 提取价格信息并触发实时推送
-        if let Some((mint_account, latest_price, timestamp)) = self.extract_price_info(&event) {
+        // 提取价格信息并触发实时推送 (价格未变化时K线未更新, 不重复推送)
+        let price_info =
+            self.extract_price_info(&event)
+                .filter(|(mint_account, latest_price, _)| {
+                    !self.price_unchanged(mint_account, *latest_price)
+                });
+        if let Some((mint_account, latest_price, timestamp)) = price_info {
             info!(
                 "💰 Extracted price info: mint={}, price={}, timestamp={}",
                 mint_account, latest_price, timestamp
@@ -1049,11 +2653,43 @@ impl EventHandler for KlineEventHandler {
             }
         } else {
             debug!(
-                "🚫 Event does not contain price info, skipping kline push: {:?}",
+                "🚫 Event does not contain a new price, skipping kline push: {:?}",
                 event
             );
         }
 
+        if let SpinPetEvent::BuySell(buy_sell) = &event {
+            if let Err(e) = self.update_imbalance(buy_sell).await {
+                warn!(
+                    "❌ Failed to push imbalance for {}: {}",
+                    buy_sell.mint_account, e
+                );
+            }
+        }
+
+        if let SpinPetEvent::PartialClose(partial_close) = &event {
+            if let Err(e) = self.push_order_updated(partial_close).await {
+                warn!(
+                    "❌ Failed to push order_updated for {}: {}",
+                    partial_close.order_pda, e
+                );
+            }
+        }
+
+        if let Err(e) = self.kline_service.broadcast_typed_event(&event).await {
+            warn!(
+                "❌ Failed to push {} to its type room: {}",
+                event.event_type(),
+                e
+            );
+        }
+
+        Ok(())
+    }
+
+    /// 回放/补数据的历史事件不推送给实时订阅者 (存储由 stats 处理器完成)
+    async fn handle_replayed_event(&self, event: SpinPetEvent) -> anyhow::Result<()> {
+        debug!("⏪ Skipping kline push for replayed event: {:?}", event);
         Ok(())
     }
 
@@ -1066,58 +2702,16 @@ impl EventHandler for KlineEventHandler {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::{
-        Config, CorsConfig, DatabaseConfig, IpfsConfig, KlineServiceConfig, LoggingConfig,
-        ServerConfig, SolanaConfig,
-    };
+    use crate::config::Config;
+    use std::collections::BTreeMap;
     use std::time::Duration;
     use tempfile::TempDir;
 
     fn create_test_config() -> Config {
         let temp_dir = TempDir::new().unwrap();
-        Config {
-            server: ServerConfig {
-                host: "localhost".to_string(),
-                port: 8080,
-            },
-            cors: CorsConfig {
-                enabled: true,
-                allow_origins: vec!["*".to_string()],
-            },
-            logging: LoggingConfig {
-                level: "debug".to_string(),
-            },
-            solana: SolanaConfig {
-                rpc_url: "http://localhost:8899".to_string(),
-                ws_url: "ws://localhost:8900".to_string(),
-                program_id: "JBMmrp6jhksqnxDBskkmVvWHhJLaPBjgiMHEroJbUTBZ".to_string(),
-                enable_event_listener: false,
-                commitment: "processed".to_string(),
-                reconnect_interval: 1,
-                max_reconnect_attempts: 20,
-                event_buffer_size: 1000,
-                event_batch_size: 100,
-                ping_interval_seconds: 60,
-                process_failed_transactions: true,
-            },
-            database: DatabaseConfig {
-                rocksdb_path: temp_dir.path().to_str().unwrap().to_string(),
-            },
-            ipfs: IpfsConfig {
-                gateway_url: "https://gateway.pinata.cloud/ipfs/".to_string(),
-                request_timeout_seconds: 30,
-                max_retries: 3,
-                retry_delay_seconds: 5,
-            },
-            kline: KlineServiceConfig {
-                enable_kline_service: true,
-                connection_timeout_secs: 60,
-                max_subscriptions_per_client: 100,
-                history_data_limit: 100,
-                ping_interval_secs: 25,
-                ping_timeout_secs: 60,
-            },
-        }
+        let mut config = Config::for_tests(temp_dir.path().to_str().unwrap());
+        config.solana.process_failed_transactions = true;
+        config
     }
 
     #[test]
@@ -1140,15 +2734,8 @@ mod tests {
         manager.connections.insert(
             socket_id.to_string(),
             ClientConnection {
-                socket_id: socket_id.to_string(),
-                subscriptions: HashSet::new(),
-                last_activity: Instant::now(),
-                connection_time: Instant::now(),
-                subscription_count: 0,
                 user_agent: Some("test_client".to_string()),
-                kline_data_sent_count: 0,
-                history_data_sent_count: 0,
-                total_messages_sent: 0,
+                ..ClientConnection::new(socket_id.to_string(), Instant::now())
             },
         );
 
@@ -1156,27 +2743,177 @@ mod tests {
         let result = manager.add_subscription(socket_id, "test_mint", "s1");
         assert!(result.is_ok());
 
-        // 验证订阅已添加
-        assert_eq!(manager.connections[socket_id].subscription_count, 1);
-        assert!(manager.connections[socket_id]
-            .subscriptions
-            .contains("test_mint:s1"));
+        // 验证订阅已添加
+        assert_eq!(manager.connections[socket_id].subscription_count, 1);
+        assert!(manager.connections[socket_id]
+            .subscriptions
+            .contains("test_mint:s1"));
+
+        // 测试获取订阅者
+        let subscribers = manager.get_subscribers("test_mint", "s1");
+        assert_eq!(subscribers.len(), 1);
+        assert_eq!(subscribers[0], socket_id);
+
+        // 测试移除订阅
+        manager.remove_subscription(socket_id, "test_mint", "s1");
+        assert_eq!(manager.connections[socket_id].subscription_count, 0);
+        assert!(!manager.connections[socket_id]
+            .subscriptions
+            .contains("test_mint:s1"));
+
+        // 测试清理客户端
+        manager.remove_client(socket_id);
+        assert!(!manager.connections.contains_key(socket_id));
+    }
+
+    #[test]
+    fn test_push_only_client_is_not_reaped() {
+        let config = KlineConfig::default();
+        let mut manager = SubscriptionManager::new();
+        let stale = Instant::now() - config.connection_timeout * 2;
+        for socket_id in ["push_only", "silent", "acked"] {
+            manager.connections.insert(
+                socket_id.to_string(),
+                ClientConnection::new(socket_id.to_string(), stale),
+            );
+        }
+
+        // 只接收推送、从不发送请求的客户端
+        manager.record_kline_sent("push_only");
+        // 回应过心跳的客户端按心跳判定, 即使最后活动已超时
+        manager.record_pong("acked");
+
+        let mut inactive = manager.inactive_clients(Instant::now(), &config);
+        inactive.sort();
+        assert_eq!(inactive, vec!["silent".to_string()]);
+        assert_eq!(manager.connections["push_only"].kline_data_sent_count, 1);
+
+        // 心跳回应超时后即判定失活
+        let later = Instant::now() + config.ping_interval + config.ping_timeout * 2;
+        assert!(manager
+            .inactive_clients(later, &config)
+            .contains(&"acked".to_string()));
+    }
+
+    #[test]
+    fn test_repeated_emit_failures_remove_client() {
+        let mut manager = SubscriptionManager::new();
+        let socket_id = "dead_socket";
+        manager.connections.insert(
+            socket_id.to_string(),
+            ClientConnection::new(socket_id.to_string(), Instant::now()),
+        );
+        manager
+            .add_subscription(socket_id, "test_mint", "s1")
+            .unwrap();
+        manager
+            .add_subscription(socket_id, "test_mint", "m5")
+            .unwrap();
+
+        // 中途成功发送会清零失败计数
+        assert!(!manager.record_emit_failure(socket_id, 3));
+        assert!(!manager.record_emit_failure(socket_id, 3));
+        manager.record_push(socket_id);
+        assert_eq!(manager.connections[socket_id].emit_failures, 0);
+
+        // 连续失败达到阈值后移除客户端及其订阅
+        assert!(!manager.record_emit_failure(socket_id, 3));
+        assert!(!manager.record_emit_failure(socket_id, 3));
+        assert!(manager.record_emit_failure(socket_id, 3));
+        assert!(!manager.connections.contains_key(socket_id));
+        assert!(manager.get_subscribers("test_mint", "s1").is_empty());
+        assert!(manager.get_subscribers("test_mint", "m5").is_empty());
+        assert!(!manager.client_subscriptions.contains_key(socket_id));
+        assert!(!manager.record_emit_failure(socket_id, 3));
+    }
+
+    #[test]
+    fn test_fresh_idle_client_survives_cleanup_within_min_age() {
+        let config = KlineConfig {
+            min_connection_age: Duration::from_secs(100),
+            ..KlineConfig::default()
+        };
+        let mut manager = SubscriptionManager::new();
+        let now = Instant::now();
+        // 刚连接的客户端 (宽限期内) 与已连接较久的客户端, 两者都早已没有活动
+        for (socket_id, connected) in [
+            ("fresh", now - Duration::from_secs(90)),
+            ("old", now - Duration::from_secs(300)),
+        ] {
+            manager.connections.insert(
+                socket_id.to_string(),
+                ClientConnection {
+                    last_activity: now - config.connection_timeout * 2,
+                    ..ClientConnection::new(socket_id.to_string(), connected)
+                },
+            );
+        }
+
+        assert_eq!(
+            manager.inactive_clients(now, &config),
+            vec!["old".to_string()]
+        );
 
-        // 测试获取订阅者
-        let subscribers = manager.get_subscribers("test_mint", "s1");
-        assert_eq!(subscribers.len(), 1);
-        assert_eq!(subscribers[0], socket_id);
+        // 宽限期结束后按最后活动判定, 成功推送计为活动
+        let later = now + Duration::from_secs(30);
+        manager.record_push("old");
+        assert_eq!(
+            manager.inactive_clients(later, &config),
+            vec!["fresh".to_string()]
+        );
+        assert_eq!(manager.connections["old"].total_messages_sent, 1);
+    }
 
-        // 测试移除订阅
-        manager.remove_subscription(socket_id, "test_mint", "s1");
-        assert_eq!(manager.connections[socket_id].subscription_count, 0);
-        assert!(!manager.connections[socket_id]
-            .subscriptions
-            .contains("test_mint:s1"));
+    #[test]
+    fn test_audit_detects_and_repair_removes_dangling_subscribers() {
+        let mut manager = SubscriptionManager::new();
+        let socket_id = "live_socket";
+        manager.connections.insert(
+            socket_id.to_string(),
+            ClientConnection::new(socket_id.to_string(), Instant::now()),
+        );
+        manager
+            .add_subscription(socket_id, "audit_mint", "s1")
+            .unwrap();
+        assert!(manager.audit().is_clean());
+
+        // 模拟遗漏的清理路径: 已断开的连接仍留在索引中
+        manager
+            .mint_subscribers
+            .entry("audit_mint".to_string())
+            .or_default()
+            .entry("s1".to_string())
+            .or_default()
+            .insert("gone_socket".to_string());
+        manager
+            .mint_subscribers
+            .entry("stale_mint".to_string())
+            .or_default()
+            .insert("m5".to_string(), HashSet::new());
+        manager
+            .client_subscriptions
+            .insert("gone_socket".to_string(), HashSet::new());
+
+        let audit = manager.audit();
+        assert_eq!(
+            audit.dangling_subscribers,
+            vec![DanglingSubscriber {
+                mint: "audit_mint".to_string(),
+                interval: "s1".to_string(),
+                socket_id: "gone_socket".to_string(),
+            }]
+        );
+        assert_eq!(audit.empty_intervals, vec!["stale_mint:m5".to_string()]);
+        assert_eq!(
+            audit.orphan_client_subscriptions,
+            vec!["gone_socket".to_string()]
+        );
 
-        // 测试清理客户端
-        manager.remove_client(socket_id);
-        assert!(!manager.connections.contains_key(socket_id));
+        let repaired = manager.repair();
+        assert_eq!(repaired.dangling_subscribers.len(), 1);
+        assert!(manager.audit().is_clean());
+        assert_eq!(manager.get_subscribers("audit_mint", "s1"), vec![socket_id]);
+        assert!(!manager.mint_subscribers.contains_key("stale_mint"));
     }
 
     #[test]
@@ -1188,15 +2925,9 @@ mod tests {
         manager.connections.insert(
             socket_id.to_string(),
             ClientConnection {
-                socket_id: socket_id.to_string(),
-                subscriptions: HashSet::new(),
-                last_activity: Instant::now(),
-                connection_time: Instant::now(),
                 subscription_count: 100, // 已达到限制
                 user_agent: Some("test_client".to_string()),
-                kline_data_sent_count: 0,
-                history_data_sent_count: 0,
-                total_messages_sent: 0,
+                ..ClientConnection::new(socket_id.to_string(), Instant::now())
             },
         );
 
@@ -1216,6 +2947,8 @@ mod tests {
             symbol: "JBMmrp6jhksqnxDBskkmVvWHhJLaPBjgiMHEroJbUTBZ".to_string(),
             interval: "s1".to_string(),
             subscription_id: Some("test_123".to_string()),
+            imbalance: false,
+            exclude_current: false,
         };
         assert!(validate_subscribe_request(&valid_request).is_ok());
 
@@ -1224,6 +2957,8 @@ mod tests {
             symbol: "JBMmrp6jhksqnxDBskkmVvWHhJLaPBjgiMHEroJbUTBZ".to_string(),
             interval: "invalid".to_string(),
             subscription_id: Some("test_123".to_string()),
+            imbalance: false,
+            exclude_current: false,
         };
         assert!(validate_subscribe_request(&invalid_interval).is_err());
 
@@ -1232,6 +2967,8 @@ mod tests {
             symbol: "short".to_string(), // 太短
             interval: "s1".to_string(),
             subscription_id: Some("test_123".to_string()),
+            imbalance: false,
+            exclude_current: false,
         };
         assert!(validate_subscribe_request(&invalid_symbol).is_err());
     }
@@ -1254,6 +2991,57 @@ mod tests {
         assert_eq!(stats["monitored_mints"], 0);
     }
 
+    #[tokio::test]
+    async fn test_watchers_report_per_interval_counts() {
+        let config = create_test_config();
+        let event_storage = Arc::new(EventStorage::new(&config).unwrap());
+        let (service, _layer) =
+            KlineSocketService::new(event_storage, KlineConfig::from_config(&config.kline))
+                .unwrap();
+
+        {
+            let mut manager = service.subscriptions.write().await;
+            for socket_id in ["watcher_a", "watcher_b", "watcher_c"] {
+                manager.connections.insert(
+                    socket_id.to_string(),
+                    ClientConnection::new(socket_id.to_string(), Instant::now()),
+                );
+            }
+            manager
+                .add_subscription("watcher_a", "watched_mint", "s1")
+                .unwrap();
+            manager
+                .add_subscription("watcher_a", "watched_mint", "m5")
+                .unwrap();
+            manager
+                .add_subscription("watcher_b", "watched_mint", "s1")
+                .unwrap();
+            manager
+                .add_subscription("watcher_c", "other_mint", "s1")
+                .unwrap();
+        }
+
+        let watchers = service.get_watchers("watched_mint").await;
+        assert_eq!(watchers.mint_account, "watched_mint");
+        assert_eq!(
+            watchers.intervals,
+            BTreeMap::from([("m5".to_string(), 1), ("s1".to_string(), 2)])
+        );
+        assert_eq!(watchers.total, 2);
+        let json = serde_json::to_string(&watchers).unwrap();
+        assert!(!json.contains("watcher_a"));
+
+        // Within watchers_cache the earlier counts are served without reading the subscriptions
+        service
+            .subscriptions
+            .write()
+            .await
+            .add_subscription("watcher_c", "watched_mint", "s30")
+            .unwrap();
+        assert_eq!(service.get_watchers("watched_mint").await.total, 2);
+        assert_eq!(service.get_watchers("unwatched_mint").await.total, 0);
+    }
+
     #[test]
     fn test_kline_data_conversion() {
         let original_kline = KlineData {
@@ -1265,6 +3053,8 @@ mod tests {
             volume: 0.0,
             is_final: false,
             update_count: 5,
+            is_gap: false,
+            last_slot: 0,
         };
 
         let realtime_data = KlineRealtimeData {
@@ -1288,4 +3078,1227 @@ mod tests {
         assert_eq!(realtime_data.update_type, "realtime");
         assert_eq!(realtime_data.update_count, 5);
     }
+
+    #[tokio::test]
+    async fn test_subscribe_waits_for_warmup() {
+        let warmup = Arc::new(WarmupGate::new(false));
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+        // 模拟订阅流程: 预热通知 -> 等待就绪 -> 推送历史
+        let handle = tokio::spawn({
+            let warmup = Arc::clone(&warmup);
+            async move {
+                wait_for_warmup(&warmup, || tx.send("warming_up").unwrap()).await;
+                tx.send("history_data").unwrap();
+            }
+        });
+
+        assert_eq!(rx.recv().await, Some("warming_up"));
+        let pending = tokio::time::timeout(Duration::from_millis(100), rx.recv()).await;
+        assert!(
+            pending.is_err(),
+            "history must not be sent while warming up"
+        );
+
+        warmup.mark_ready();
+        assert_eq!(rx.recv().await, Some("history_data"));
+        handle.await.unwrap();
+
+        // 就绪后的订阅不再收到预热通知
+        let mut notified = false;
+        wait_for_warmup(&warmup, || notified = true).await;
+        assert!(!notified);
+    }
+
+    /// 记录所有发出消息的测试连接
+    #[derive(Default)]
+    struct RecordingSink {
+        id: Option<String>,
+        events: std::sync::Mutex<Vec<(String, serde_json::Value)>>,
+        binary_events: std::sync::Mutex<Vec<(String, Vec<u8>)>>,
+        rooms: std::sync::Mutex<Vec<String>>,
+    }
+
+    impl SubscriptionSink for RecordingSink {
+        fn client_id(&self) -> String {
+            self.id
+                .clone()
+                .unwrap_or_else(|| "recording_client".to_string())
+        }
+
+        fn emit_message<T: Serialize + ?Sized>(&self, event: &str, data: &T) -> Result<()> {
+            self.events
+                .lock()
+                .unwrap()
+                .push((event.to_string(), serde_json::to_value(data)?));
+            Ok(())
+        }
+
+        fn emit_binary(&self, event: &str, data: Vec<u8>) -> Result<()> {
+            self.binary_events
+                .lock()
+                .unwrap()
+                .push((event.to_string(), data));
+            Ok(())
+        }
+
+        fn join_room(&self, room: String) {
+            self.rooms.lock().unwrap().push(room);
+        }
+
+        fn leave_room(&self, room: String) {
+            self.rooms.lock().unwrap().retain(|r| r != &room);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_auth_subscription_receives_history_on_connect() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config();
+        config.database.rocksdb_path = temp_dir.path().to_str().unwrap().to_string();
+        let event_storage = Arc::new(EventStorage::new(&config).unwrap());
+
+        let mint = "So11111111111111111111111111111111111111112";
+        event_storage
+            .store_event(SpinPetEvent::BuySell(crate::solana::events::BuySellEvent {
+                payer: "test_payer".to_string(),
+                mint_account: mint.to_string(),
+                is_buy: true,
+                token_amount: 1_000,
+                sol_amount: 1_000_000,
+                latest_price: 2 * crate::services::event_storage::PRICE_PRECISION,
+                timestamp: Utc::now(),
+                timestamp_source: crate::solana::events::TimestampSource::ServerReceive,
+                log_index: 0,
+                signature: "auth_sub_sig".to_string(),
+                slot: 1,
+            }))
+            .await
+            .unwrap();
+
+        let subscriptions = RwLock::new(SubscriptionManager::new());
+        let sink = RecordingSink::default();
+        let auth = serde_json::from_value::<SubscribeRequest>(serde_json::json!({
+            "symbol": mint,
+            "interval": "s1"
+        }))
+        .unwrap();
+
+        // 仅建立连接, 不发送 subscribe 事件
+        register_client(
+            &sink,
+            Some(auth),
+            &subscriptions,
+            &event_storage,
+            &WarmupGate::default(),
+            &KlineConfig::default(),
+        )
+        .await;
+
+        let events = sink.events.lock().unwrap().clone();
+        let names: Vec<&str> = events.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["history_data", "subscription_confirmed"]);
+        let history = &events[0].1;
+        assert_eq!(history["symbol"], mint);
+        assert_eq!(history["data"].as_array().unwrap().len(), 1);
+        assert_eq!(
+            *sink.rooms.lock().unwrap(),
+            vec![format!("kline:{}:s1", mint)]
+        );
+        assert_eq!(
+            subscriptions.read().await.get_subscribers(mint, "s1"),
+            vec!["recording_client".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_binary_history_is_gzip_compressed_json_frame() {
+        use flate2::read::GzDecoder;
+        use std::io::Read;
+
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config();
+        config.database.rocksdb_path = temp_dir.path().to_str().unwrap().to_string();
+        let event_storage = Arc::new(EventStorage::new(&config).unwrap());
+
+        let mint = "So11111111111111111111111111111111111111112";
+        event_storage
+            .store_event(SpinPetEvent::BuySell(crate::solana::events::BuySellEvent {
+                payer: "test_payer".to_string(),
+                mint_account: mint.to_string(),
+                is_buy: true,
+                token_amount: 1_000,
+                sol_amount: 1_000_000,
+                latest_price: 2 * crate::services::event_storage::PRICE_PRECISION,
+                timestamp: Utc::now(),
+                timestamp_source: crate::solana::events::TimestampSource::ServerReceive,
+                log_index: 0,
+                signature: "binary_history_sig".to_string(),
+                slot: 1,
+            }))
+            .await
+            .unwrap();
+
+        let subscriptions = RwLock::new(SubscriptionManager::new());
+        let subscribe = || {
+            serde_json::from_value::<SubscribeRequest>(serde_json::json!({
+                "symbol": mint,
+                "interval": "s1"
+            }))
+            .unwrap()
+        };
+
+        // 文本连接作为对照
+        let text_sink = RecordingSink {
+            id: Some("text_client".to_string()),
+            ..Default::default()
+        };
+        let binary_sink = RecordingSink {
+            id: Some("binary_client".to_string()),
+            ..Default::default()
+        };
+        subscriptions
+            .write()
+            .await
+            .binary_history_clients
+            .insert("binary_client".to_string());
+        for sink in [&text_sink, &binary_sink] {
+            register_client(
+                sink,
+                Some(subscribe()),
+                &subscriptions,
+                &event_storage,
+                &WarmupGate::default(),
+                &KlineConfig::default(),
+            )
+            .await;
+        }
+
+        let text_events = text_sink.events.lock().unwrap().clone();
+        assert_eq!(text_events[0].0, "history_data");
+        assert!(text_sink.binary_events.lock().unwrap().is_empty());
+
+        // 二进制连接: history_data 为二进制帧, 确认消息仍为文本
+        let events = binary_sink.events.lock().unwrap().clone();
+        let names: Vec<&str> = events.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["subscription_confirmed"]);
+        let binary_events = binary_sink.binary_events.lock().unwrap().clone();
+        assert_eq!(binary_events.len(), 1);
+        assert_eq!(binary_events[0].0, "history_data");
+
+        let mut json = String::new();
+        GzDecoder::new(binary_events[0].1.as_slice())
+            .read_to_string(&mut json)
+            .unwrap();
+        let history: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(history, text_events[0].1);
+        assert_eq!(history["data"].as_array().unwrap().len(), 1);
+
+        // 断开后不再保留协商结果
+        subscriptions.write().await.remove_client("binary_client");
+        assert!(subscriptions.read().await.binary_history_clients.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_removes_connection_missing_from_namespace() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config();
+        config.database.rocksdb_path = temp_dir.path().to_str().unwrap().to_string();
+        let event_storage = Arc::new(EventStorage::new(&config).unwrap());
+        let (service, _layer) =
+            KlineSocketService::new(Arc::clone(&event_storage), KlineConfig::default()).unwrap();
+        service.setup_socket_handlers();
+
+        // 模拟错过断开事件的连接: 已登记并订阅, 但命名空间中没有对应的 socket
+        let mint = "So11111111111111111111111111111111111111112";
+        let stale = RecordingSink {
+            id: Some("stale_socket".to_string()),
+            ..Default::default()
+        };
+        register_client(
+            &stale,
+            Some(SubscribeRequest {
+                symbol: mint.to_string(),
+                interval: "s1".to_string(),
+                subscription_id: None,
+                imbalance: false,
+                exclude_current: false,
+            }),
+            &service.subscriptions,
+            &event_storage,
+            &WarmupGate::default(),
+            &service.config,
+        )
+        .await;
+        assert_eq!(
+            service
+                .subscriptions
+                .read()
+                .await
+                .get_subscribers(mint, "s1"),
+            vec!["stale_socket".to_string()]
+        );
+
+        assert_eq!(
+            service.reconcile_connections().await,
+            vec!["stale_socket".to_string()]
+        );
+        let manager = service.subscriptions.read().await;
+        assert!(manager.connections.is_empty());
+        assert!(manager.mint_subscribers.is_empty());
+        assert!(manager.client_subscriptions.is_empty());
+        assert!(manager.audit().is_clean());
+    }
+
+    #[tokio::test]
+    async fn test_exclude_current_omits_in_progress_candle_from_history() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config();
+        config.database.rocksdb_path = temp_dir.path().to_str().unwrap().to_string();
+        let event_storage = Arc::new(EventStorage::new(&config).unwrap());
+
+        // 一根已收盘的K线 (两分钟前) 和一根进行中的K线 (当前)
+        let mint = "So11111111111111111111111111111111111111112";
+        let now = Utc::now();
+        for (i, timestamp) in [now - chrono::Duration::seconds(120), now]
+            .into_iter()
+            .enumerate()
+        {
+            event_storage
+                .store_event(SpinPetEvent::BuySell(crate::solana::events::BuySellEvent {
+                    payer: "test_payer".to_string(),
+                    mint_account: mint.to_string(),
+                    is_buy: true,
+                    token_amount: 1_000,
+                    sol_amount: 1_000_000,
+                    latest_price: 2 * crate::services::event_storage::PRICE_PRECISION,
+                    timestamp,
+                    timestamp_source: crate::solana::events::TimestampSource::ServerReceive,
+                    log_index: 0,
+                    signature: format!("exclude_current_sig_{}", i),
+                    slot: 1 + i as u64,
+                }))
+                .await
+                .unwrap();
+        }
+        let closed_time = (now.timestamp() as u64 - 120) / 30 * 30;
+        let current_time = now.timestamp() as u64 / 30 * 30;
+
+        for (exclude_current, expected_times) in [
+            (false, vec![current_time, closed_time]),
+            (true, vec![closed_time]),
+        ] {
+            let sink = RecordingSink::default();
+            let request = serde_json::from_value::<SubscribeRequest>(serde_json::json!({
+                "symbol": mint,
+                "interval": "s30",
+                "exclude_current": exclude_current
+            }))
+            .unwrap();
+            register_client(
+                &sink,
+                Some(request),
+                &RwLock::new(SubscriptionManager::new()),
+                &event_storage,
+                &WarmupGate::default(),
+                &KlineConfig::default(),
+            )
+            .await;
+
+            let events = sink.events.lock().unwrap().clone();
+            assert_eq!(events[0].0, "history_data");
+            let times: Vec<u64> = events[0].1["data"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|kline| kline["time"].as_u64().unwrap())
+                .collect();
+            assert_eq!(
+                times, expected_times,
+                "exclude_current: {}",
+                exclude_current
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalid_auth_subscription_is_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config();
+        config.database.rocksdb_path = temp_dir.path().to_str().unwrap().to_string();
+        let event_storage = Arc::new(EventStorage::new(&config).unwrap());
+
+        let subscriptions = RwLock::new(SubscriptionManager::new());
+        let sink = RecordingSink::default();
+        let auth = SubscribeRequest {
+            symbol: "short".to_string(),
+            interval: "h1".to_string(),
+            subscription_id: None,
+            imbalance: false,
+            exclude_current: false,
+        };
+
+        register_client(
+            &sink,
+            Some(auth),
+            &subscriptions,
+            &event_storage,
+            &WarmupGate::default(),
+            &KlineConfig::default(),
+        )
+        .await;
+
+        // 与显式 subscribe 一致的校验: 返回错误, 连接仍被登记
+        let events = sink.events.lock().unwrap().clone();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].0, "error");
+        assert_eq!(events[0].1["code"], 1001);
+        assert!(subscriptions
+            .read()
+            .await
+            .connections
+            .contains_key("recording_client"));
+    }
+
+    #[tokio::test]
+    async fn test_kline_push_skips_unsubscribed_intervals() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config();
+        config.database.rocksdb_path = temp_dir.path().to_str().unwrap().to_string();
+        let event_storage = Arc::new(EventStorage::new(&config).unwrap());
+        let (service, _layer) =
+            KlineSocketService::new(event_storage, KlineConfig::from_config(&config.kline))
+                .unwrap();
+        service.setup_socket_handlers();
+        let handler = KlineEventHandler::new(Arc::new(service));
+
+        let mint = "So11111111111111111111111111111111111111112";
+        let price = 2 * crate::services::event_storage::PRICE_PRECISION;
+        let timestamp = Utc::now();
+
+        // 无订阅者时不做任何查询
+        let pushed = handler
+            .trigger_kline_push(mint, price, timestamp)
+            .await
+            .unwrap();
+        assert!(pushed.is_empty());
+
+        // 仅订阅 s1
+        register_client(
+            &RecordingSink::default(),
+            Some(SubscribeRequest {
+                symbol: mint.to_string(),
+                interval: "s1".to_string(),
+                subscription_id: None,
+                imbalance: false,
+                exclude_current: false,
+            }),
+            &handler.kline_service.subscriptions,
+            &handler.kline_service.event_storage,
+            &WarmupGate::default(),
+            &KlineConfig::default(),
+        )
+        .await;
+
+        let pushed = handler
+            .trigger_kline_push(mint, price, timestamp)
+            .await
+            .unwrap();
+        assert_eq!(pushed, vec!["s1"]);
+    }
+
+    #[tokio::test]
+    async fn test_partial_close_broadcasts_order_updated_with_reduced_position() {
+        use crate::solana::events::{LongShortEvent, TimestampSource};
+
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config();
+        config.database.rocksdb_path = temp_dir.path().to_str().unwrap().to_string();
+        let event_storage = Arc::new(EventStorage::new(&config).unwrap());
+        let (service, _layer) = KlineSocketService::new(
+            Arc::clone(&event_storage),
+            KlineConfig::from_config(&config.kline),
+        )
+        .unwrap();
+        service.setup_socket_handlers();
+        let handler = KlineEventHandler::new(Arc::new(service));
+
+        let mint = "So11111111111111111111111111111111111111112";
+        let order_pda = "position_pda";
+        event_storage
+            .store_event(SpinPetEvent::LongShort(LongShortEvent {
+                payer: "test_payer".to_string(),
+                mint_account: mint.to_string(),
+                order_pda: order_pda.to_string(),
+                latest_price: 20,
+                order_type: 1,
+                mint: mint.to_string(),
+                user: "test_user".to_string(),
+                lock_lp_start_price: 20,
+                lock_lp_end_price: 40,
+                lock_lp_sol_amount: 1_000_000_000,
+                lock_lp_token_amount: 500_000_000,
+                start_time: 1758343400,
+                end_time: 1758343800,
+                margin_sol_amount: 500_000_000,
+                borrow_amount: 1_500_000_000,
+                position_asset_amount: 10,
+                borrow_fee: 250,
+                timestamp: Utc::now(),
+                timestamp_source: TimestampSource::ServerReceive,
+                log_index: 0,
+                signature: "sig_position_open".to_string(),
+                slot: 100,
+            }))
+            .await
+            .unwrap();
+        let partial_close = PartialCloseEvent {
+            payer: "test_payer".to_string(),
+            user_sol_account: "test_user_sol".to_string(),
+            mint_account: mint.to_string(),
+            is_close_long: true,
+            final_token_amount: 4,
+            final_sol_amount: 300,
+            user_close_profit: 2,
+            latest_price: 25,
+            order_pda: order_pda.to_string(),
+            order_type: 1,
+            mint: mint.to_string(),
+            user: "test_user".to_string(),
+            lock_lp_start_price: 20,
+            lock_lp_end_price: 40,
+            lock_lp_sol_amount: 1_000_000_000,
+            lock_lp_token_amount: 500_000_000,
+            start_time: 1758343400,
+            end_time: 1758343800,
+            margin_sol_amount: 300_000_000,
+            borrow_amount: 900_000_000,
+            position_asset_amount: 6,
+            borrow_fee: 250,
+            timestamp: Utc::now(),
+            timestamp_source: TimestampSource::ServerReceive,
+            log_index: 0,
+            signature: "sig_position_partial".to_string(),
+            slot: 101,
+        };
+        event_storage
+            .store_event(SpinPetEvent::PartialClose(partial_close.clone()))
+            .await
+            .unwrap();
+
+        // 订阅了同一 mint 两个周期的客户端只收到一条
+        let sink = RecordingSink::default();
+        let subscribe = |interval: &str| SubscribeRequest {
+            symbol: mint.to_string(),
+            interval: interval.to_string(),
+            subscription_id: None,
+            imbalance: false,
+            exclude_current: false,
+        };
+        register_client(
+            &sink,
+            Some(subscribe("s1")),
+            &handler.kline_service.subscriptions,
+            &handler.kline_service.event_storage,
+            &WarmupGate::default(),
+            &KlineConfig::default(),
+        )
+        .await;
+        subscribe_client(
+            &sink,
+            subscribe("m5"),
+            &handler.kline_service.subscriptions,
+            &handler.kline_service.event_storage,
+            &WarmupGate::default(),
+            &KlineConfig::default(),
+        )
+        .await;
+
+        let message = handler
+            .order_updated_message(&partial_close)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(message.symbol, mint);
+        assert_eq!(message.order.order_pda, order_pda);
+        assert_eq!(message.order.position_asset_amount, 6);
+        assert_eq!(message.order.margin_sol_amount, 300_000_000);
+
+        let subscriptions = &handler.kline_service.subscriptions;
+        let before = subscriptions.read().await.connections["recording_client"].total_messages_sent;
+        let sent = handler
+            .kline_service
+            .broadcast_order_updated(&message)
+            .await
+            .unwrap();
+        assert_eq!(sent, 1);
+        assert_eq!(
+            subscriptions.read().await.connections["recording_client"].total_messages_sent,
+            before + 1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_type_subscription_receives_only_its_type_across_mints() {
+        use crate::solana::events::{BuySellEvent, TimestampSource, TokenCreatedEvent};
+
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config();
+        config.database.rocksdb_path = temp_dir.path().to_str().unwrap().to_string();
+        let event_storage = Arc::new(EventStorage::new(&config).unwrap());
+        let (service, _layer) =
+            KlineSocketService::new(event_storage, KlineConfig::from_config(&config.kline))
+                .unwrap();
+        service.setup_socket_handlers();
+
+        let sink = RecordingSink::default();
+        register_client(
+            &sink,
+            None,
+            &service.subscriptions,
+            &service.event_storage,
+            &WarmupGate::default(),
+            &service.config,
+        )
+        .await;
+        let subscribe_type = |event_type: &str| TypeSubscribeRequest {
+            event_type: event_type.to_string(),
+        };
+        assert!(
+            !subscribe_type_client(
+                &sink,
+                subscribe_type("Unknown"),
+                &service.subscriptions,
+                &service.config
+            )
+            .await
+        );
+        assert!(
+            subscribe_type_client(
+                &sink,
+                subscribe_type("TokenCreated"),
+                &service.subscriptions,
+                &service.config
+            )
+            .await
+        );
+        assert_eq!(*sink.rooms.lock().unwrap(), vec!["type:TokenCreated"]);
+
+        let token_created = |mint: &str| {
+            SpinPetEvent::TokenCreated(TokenCreatedEvent {
+                payer: "test_payer".to_string(),
+                mint_account: mint.to_string(),
+                curve_account: "curve".to_string(),
+                pool_token_account: "pool_token".to_string(),
+                pool_sol_account: "pool_sol".to_string(),
+                fee_recipient: "fee".to_string(),
+                base_fee_recipient: "base_fee".to_string(),
+                params_account: "params".to_string(),
+                name: "Token".to_string(),
+                symbol: "TKN".to_string(),
+                uri: String::new(),
+                swap_fee: 0,
+                borrow_fee: 0,
+                fee_discount_flag: 0,
+                timestamp: Utc::now(),
+                timestamp_source: TimestampSource::ServerReceive,
+                signature: format!("create_{}", mint),
+                slot: 1,
+                log_index: 0,
+            })
+        };
+        let buy = SpinPetEvent::BuySell(BuySellEvent {
+            payer: "test_payer".to_string(),
+            mint_account: "mint_a".to_string(),
+            is_buy: true,
+            token_amount: 1_000,
+            sol_amount: 1_000_000,
+            latest_price: 2 * crate::services::event_storage::PRICE_PRECISION,
+            timestamp: Utc::now(),
+            timestamp_source: TimestampSource::ServerReceive,
+            log_index: 0,
+            signature: "buy_sig".to_string(),
+            slot: 2,
+        });
+
+        let mut delivered = Vec::new();
+        for event in [token_created("mint_a"), buy, token_created("mint_b")] {
+            delivered.push(service.broadcast_typed_event(&event).await.unwrap());
+        }
+        assert_eq!(delivered, vec![1, 0, 1]);
+        assert_eq!(
+            service.subscriptions.read().await.connections["recording_client"].total_messages_sent,
+            2
+        );
+
+        // 断开后不再保留类型订阅
+        service
+            .subscriptions
+            .write()
+            .await
+            .remove_client("recording_client");
+        assert!(service
+            .subscriptions
+            .read()
+            .await
+            .type_subscribers
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_throttle_coalesces_rapid_updates() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config();
+        config.database.rocksdb_path = temp_dir.path().to_str().unwrap().to_string();
+        config.kline.broadcast_throttle_ms = 50;
+        let event_storage = Arc::new(EventStorage::new(&config).unwrap());
+        let kline_config = KlineConfig::from_config(&config.kline);
+        let (service, _layer) =
+            KlineSocketService::new(Arc::clone(&event_storage), kline_config.clone()).unwrap();
+        service.setup_socket_handlers();
+
+        let mint = "So11111111111111111111111111111111111111112";
+        register_client(
+            &RecordingSink::default(),
+            Some(SubscribeRequest {
+                symbol: mint.to_string(),
+                interval: "s1".to_string(),
+                subscription_id: None,
+                imbalance: false,
+                exclude_current: false,
+            }),
+            &service.subscriptions,
+            &event_storage,
+            &WarmupGate::default(),
+            &kline_config,
+        )
+        .await;
+        let kline_data_sent = || async {
+            service.subscriptions.read().await.connections["recording_client"].kline_data_sent_count
+        };
+        let candle = |update_count: u32, is_final: bool| KlineData {
+            time: 1_700_000_000,
+            open: 1.0,
+            high: update_count as f64,
+            low: 1.0,
+            close: update_count as f64,
+            volume: update_count as f64,
+            is_final,
+            update_count,
+            is_gap: false,
+            last_slot: 0,
+        };
+
+        // 窗口内的多次更新只保留最新状态, 窗口结束前不发送
+        for update_count in 1..=20 {
+            service
+                .broadcast_kline_update(mint, "s1", &candle(update_count, false))
+                .await
+                .unwrap();
+        }
+        assert_eq!(kline_data_sent().await, 0);
+        {
+            let pending = service.throttle.pending.lock().unwrap();
+            let latest = &pending[&format!("{}:s1", mint)];
+            assert_eq!((latest.close, latest.update_count), (20.0, 20));
+        }
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(kline_data_sent().await, 1);
+        assert!(service.throttle.pending.lock().unwrap().is_empty());
+
+        // 收盘K线不经过节流, 立即发送并丢弃同一根K线的待发送状态
+        service
+            .broadcast_kline_update(mint, "s1", &candle(21, false))
+            .await
+            .unwrap();
+        service
+            .broadcast_kline_update(mint, "s1", &candle(21, true))
+            .await
+            .unwrap();
+        assert_eq!(kline_data_sent().await, 2);
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(kline_data_sent().await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_replayed_event_is_stored_but_not_broadcast() {
+        use crate::services::event_service::StatsEventHandler;
+        use crate::solana::CompositeEventHandler;
+
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config();
+        config.database.rocksdb_path = temp_dir.path().to_str().unwrap().to_string();
+        let event_storage = Arc::new(EventStorage::new(&config).unwrap());
+        let (service, _layer) = KlineSocketService::new(
+            Arc::clone(&event_storage),
+            KlineConfig::from_config(&config.kline),
+        )
+        .unwrap();
+        service.setup_socket_handlers();
+        let service = Arc::new(service);
+        let pipeline = CompositeEventHandler::new(vec![
+            Arc::new(StatsEventHandler::new(Arc::clone(&event_storage))),
+            Arc::new(KlineEventHandler::new(Arc::clone(&service))),
+        ]);
+
+        let mint = "So11111111111111111111111111111111111111112";
+        register_client(
+            &RecordingSink::default(),
+            Some(SubscribeRequest {
+                symbol: mint.to_string(),
+                interval: "s1".to_string(),
+                subscription_id: None,
+                imbalance: false,
+                exclude_current: false,
+            }),
+            &service.subscriptions,
+            &event_storage,
+            &WarmupGate::default(),
+            &KlineConfig::default(),
+        )
+        .await;
+
+        let trade = |signature: &str| {
+            SpinPetEvent::BuySell(crate::solana::events::BuySellEvent {
+                payer: "test_payer".to_string(),
+                mint_account: mint.to_string(),
+                is_buy: true,
+                token_amount: 1_000,
+                sol_amount: 1_000_000,
+                latest_price: 2 * crate::services::event_storage::PRICE_PRECISION,
+                timestamp: Utc::now(),
+                timestamp_source: crate::solana::events::TimestampSource::ServerReceive,
+                log_index: 0,
+                signature: signature.to_string(),
+                slot: 1,
+            })
+        };
+        let stored_events = || async {
+            event_storage
+                .query_events(crate::services::event_storage::EventQuery {
+                    mint_account: mint.to_string(),
+                    page: Some(1),
+                    limit: Some(10),
+                    order_by: None,
+                })
+                .await
+                .unwrap()
+                .events
+                .len()
+        };
+        let kline_data_sent = || async {
+            service.subscriptions.read().await.connections["recording_client"].kline_data_sent_count
+        };
+
+        pipeline
+            .handle_replayed_event(trade("replayed_sig"))
+            .await
+            .unwrap();
+        assert_eq!(stored_events().await, 1);
+        assert_eq!(kline_data_sent().await, 0);
+
+        pipeline.handle_event(trade("live_sig")).await.unwrap();
+        assert_eq!(stored_events().await, 2);
+        assert_eq!(kline_data_sent().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_history_count_follows_interval() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config();
+        config.database.rocksdb_path = temp_dir.path().to_str().unwrap().to_string();
+        config.kline.history_limits = crate::config::KlineHistoryLimits {
+            s1: 3,
+            s30: 4,
+            m5: 5,
+        };
+        let event_storage = Arc::new(EventStorage::new(&config).unwrap());
+        let kline_config = KlineConfig::from_config(&config.kline);
+
+        // 10 笔交易, 间隔超过 5 分钟, 每个周期各产生 10 根K线
+        let mint = "So11111111111111111111111111111111111111112";
+        let start = Utc::now() - chrono::Duration::hours(2);
+        for i in 0..10 {
+            event_storage
+                .store_event(SpinPetEvent::BuySell(crate::solana::events::BuySellEvent {
+                    payer: "test_payer".to_string(),
+                    mint_account: mint.to_string(),
+                    is_buy: true,
+                    token_amount: 1_000,
+                    sol_amount: 1_000_000,
+                    latest_price: 2 * crate::services::event_storage::PRICE_PRECISION,
+                    timestamp: start + chrono::Duration::seconds(i * 301),
+                    timestamp_source: crate::solana::events::TimestampSource::ServerReceive,
+                    log_index: 0,
+                    signature: format!("history_sig_{}", i),
+                    slot: i as u64,
+                }))
+                .await
+                .unwrap();
+        }
+
+        for (interval, expected) in [("m5", 5), ("s1", 3)] {
+            let sink = RecordingSink::default();
+            register_client(
+                &sink,
+                Some(SubscribeRequest {
+                    symbol: mint.to_string(),
+                    interval: interval.to_string(),
+                    subscription_id: None,
+                    imbalance: false,
+                    exclude_current: false,
+                }),
+                &RwLock::new(SubscriptionManager::new()),
+                &event_storage,
+                &WarmupGate::default(),
+                &kline_config,
+            )
+            .await;
+
+            let events = sink.events.lock().unwrap().clone();
+            assert_eq!(events[0].0, "history_data");
+            assert_eq!(
+                events[0].1["data"].as_array().unwrap().len(),
+                expected,
+                "history count for {}",
+                interval
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unsubscribe_all_keeps_connection() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config();
+        config.database.rocksdb_path = temp_dir.path().to_str().unwrap().to_string();
+        let event_storage = Arc::new(EventStorage::new(&config).unwrap());
+
+        let subscriptions = RwLock::new(SubscriptionManager::new());
+        let sink = RecordingSink::default();
+        let kline_config = KlineConfig::default();
+        register_client(
+            &sink,
+            None,
+            &subscriptions,
+            &event_storage,
+            &WarmupGate::default(),
+            &kline_config,
+        )
+        .await;
+
+        let mint_a = "So11111111111111111111111111111111111111112";
+        let mint_b = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+        for (mint, interval) in [(mint_a, "s1"), (mint_a, "m5"), (mint_b, "s30")] {
+            subscribe_client(
+                &sink,
+                SubscribeRequest {
+                    symbol: mint.to_string(),
+                    interval: interval.to_string(),
+                    subscription_id: None,
+                    imbalance: false,
+                    exclude_current: false,
+                },
+                &subscriptions,
+                &event_storage,
+                &WarmupGate::default(),
+                &kline_config,
+            )
+            .await;
+        }
+        assert_eq!(sink.rooms.lock().unwrap().len(), 3);
+
+        let removed = unsubscribe_all_client(&sink, &subscriptions).await;
+        assert_eq!(removed, 3);
+
+        let manager = subscriptions.read().await;
+        let client = manager.connections.get("recording_client").unwrap();
+        assert_eq!(client.subscription_count, 0);
+        assert!(client.subscriptions.is_empty());
+        assert!(manager.get_subscribers(mint_a, "s1").is_empty());
+        assert!(manager.mint_subscribers.is_empty());
+        assert!(sink.rooms.lock().unwrap().is_empty());
+
+        let events = sink.events.lock().unwrap().clone();
+        let (name, payload) = events.last().unwrap();
+        assert_eq!(name, "unsubscribe_all_confirmed");
+        assert_eq!(payload["removed"], 3);
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_with_session_token_restores_subscriptions() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config();
+        config.database.rocksdb_path = temp_dir.path().to_str().unwrap().to_string();
+        let event_storage = Arc::new(EventStorage::new(&config).unwrap());
+
+        let subscriptions = RwLock::new(SubscriptionManager::new());
+        let kline_config = KlineConfig {
+            session_resume_grace: Duration::from_secs(30),
+            ..KlineConfig::default()
+        };
+        let mint_a = "So11111111111111111111111111111111111111112";
+        let mint_b = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+        // 首次连接: 登记, 绑定令牌, 订阅两个周期
+        let first = RecordingSink {
+            id: Some("first_socket".to_string()),
+            ..RecordingSink::default()
+        };
+        register_client(
+            &first,
+            None,
+            &subscriptions,
+            &event_storage,
+            &WarmupGate::default(),
+            &kline_config,
+        )
+        .await;
+        let restored = start_session(
+            &first,
+            "token_1".to_string(),
+            None,
+            &subscriptions,
+            &kline_config,
+        )
+        .await;
+        assert_eq!(restored, 0);
+        for (mint, interval) in [(mint_a, "s1"), (mint_b, "m5")] {
+            subscribe_client(
+                &first,
+                SubscribeRequest {
+                    symbol: mint.to_string(),
+                    interval: interval.to_string(),
+                    subscription_id: None,
+                    imbalance: false,
+                    exclude_current: false,
+                },
+                &subscriptions,
+                &event_storage,
+                &WarmupGate::default(),
+                &kline_config,
+            )
+            .await;
+        }
+
+        // 断线: 订阅被保留, 旧连接不再是订阅者
+        subscriptions.write().await.park_client("first_socket");
+        {
+            let manager = subscriptions.read().await;
+            assert!(!manager.connections.contains_key("first_socket"));
+            assert!(manager.get_subscribers(mint_a, "s1").is_empty());
+            assert_eq!(manager.parked_sessions.len(), 1);
+        }
+
+        // 新 socket id 携带旧令牌重连
+        let second = RecordingSink {
+            id: Some("second_socket".to_string()),
+            ..RecordingSink::default()
+        };
+        register_client(
+            &second,
+            None,
+            &subscriptions,
+            &event_storage,
+            &WarmupGate::default(),
+            &kline_config,
+        )
+        .await;
+        let restored = start_session(
+            &second,
+            "token_2".to_string(),
+            Some("token_1".to_string()),
+            &subscriptions,
+            &kline_config,
+        )
+        .await;
+        assert_eq!(restored, 2);
+
+        let mut rooms = second.rooms.lock().unwrap().clone();
+        rooms.sort();
+        assert_eq!(
+            rooms,
+            vec![
+                format!("kline:{}:s1", mint_a),
+                format!("kline:{}:m5", mint_b)
+            ]
+        );
+        let events = second.events.lock().unwrap().clone();
+        assert_eq!(events.last().unwrap().0, "session_resumed");
+
+        let manager = subscriptions.read().await;
+        assert_eq!(
+            manager.get_subscribers(mint_a, "s1"),
+            vec!["second_socket".to_string()]
+        );
+        assert_eq!(
+            manager.get_subscribers(mint_b, "m5"),
+            vec!["second_socket".to_string()]
+        );
+        assert!(manager.parked_sessions.is_empty());
+        assert_eq!(
+            manager
+                .session_tokens
+                .get("second_socket")
+                .map(String::as_str),
+            Some("token_2")
+        );
+        drop(manager);
+
+        // 令牌只能使用一次, 过期或未知的令牌不恢复任何订阅
+        let third = RecordingSink {
+            id: Some("third_socket".to_string()),
+            ..RecordingSink::default()
+        };
+        register_client(
+            &third,
+            None,
+            &subscriptions,
+            &event_storage,
+            &WarmupGate::default(),
+            &kline_config,
+        )
+        .await;
+        let restored = start_session(
+            &third,
+            "token_3".to_string(),
+            Some("token_1".to_string()),
+            &subscriptions,
+            &kline_config,
+        )
+        .await;
+        assert_eq!(restored, 0);
+        assert!(third.rooms.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_batched_finalizations_send_one_message_per_subscriber() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = create_test_config();
+        config.database.rocksdb_path = temp_dir.path().to_str().unwrap().to_string();
+        let event_storage = Arc::new(EventStorage::new(&config).unwrap());
+
+        // 两个代币各有一根已收盘的 s1 K线
+        let mints = [
+            "So11111111111111111111111111111111111111112",
+            "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+        ];
+        let trade_time = Utc::now() - chrono::Duration::minutes(10);
+        for mint in mints {
+            event_storage
+                .store_event(SpinPetEvent::BuySell(crate::solana::events::BuySellEvent {
+                    payer: "test_payer".to_string(),
+                    mint_account: mint.to_string(),
+                    is_buy: true,
+                    token_amount: 1_000,
+                    sol_amount: 1_000_000,
+                    latest_price: 2 * crate::services::event_storage::PRICE_PRECISION,
+                    timestamp: trade_time,
+                    timestamp_source: crate::solana::events::TimestampSource::ServerReceive,
+                    log_index: 0,
+                    signature: format!("closed_sig_{}", mint),
+                    slot: 1,
+                }))
+                .await
+                .unwrap();
+        }
+        let now = Utc::now().timestamp() as u64;
+
+        for (batch_finalizations, expected_messages) in [(true, 1), (false, 2)] {
+            let kline_config = KlineConfig {
+                batch_finalizations,
+                ..KlineConfig::default()
+            };
+            let (service, _layer) =
+                KlineSocketService::new(Arc::clone(&event_storage), kline_config.clone()).unwrap();
+            service.setup_socket_handlers();
+
+            let sink = RecordingSink::default();
+            register_client(
+                &sink,
+                None,
+                &service.subscriptions,
+                &event_storage,
+                &WarmupGate::default(),
+                &kline_config,
+            )
+            .await;
+            for mint in mints {
+                subscribe_client(
+                    &sink,
+                    SubscribeRequest {
+                        symbol: mint.to_string(),
+                        interval: "s1".to_string(),
+                        subscription_id: None,
+                        imbalance: false,
+                        exclude_current: false,
+                    },
+                    &service.subscriptions,
+                    &event_storage,
+                    &WarmupGate::default(),
+                    &kline_config,
+                )
+                .await;
+            }
+
+            let closed = service.collect_closed_candles(now).await;
+            assert_eq!(closed.len(), 2);
+            assert!(closed.iter().all(|c| c.data.is_final));
+            let batches = group_closed_by_subscriber(&*service.subscriptions.read().await, &closed);
+            assert_eq!(batches.len(), 1);
+            assert_eq!(batches["recording_client"].len(), 2);
+
+            // 已通知的K线不会重复发送; 重置后统计一轮实际发送的消息数
+            assert_eq!(service.run_finalization_pass(now).await.unwrap(), 0);
+            service.closed_candles.write().await.clear();
+            assert_eq!(
+                service.run_finalization_pass(now).await.unwrap(),
+                expected_messages
+            );
+        }
+    }
+
+    #[test]
+    fn test_trade_imbalance_window_tracks_window_contents() {
+        let mut window = TradeImbalanceWindow::default();
+        assert_eq!(window.imbalance(), 0.0);
+
+        window.record(1_000, true, 300, 60);
+        window.record(1_010, false, 100, 60);
+        window.record(1_020, true, 100, 60);
+        assert_eq!(window.buy_sol_amount(), 400);
+        assert_eq!(window.sell_sol_amount(), 100);
+        assert!((window.imbalance() - 0.6).abs() < 1e-9);
+
+        // t=1060 时 t=1000 的买单移出窗口
+        window.record(1_060, false, 200, 60);
+        assert_eq!(window.buy_sol_amount(), 100);
+        assert_eq!(window.sell_sol_amount(), 300);
+        assert!((window.imbalance() + 0.5).abs() < 1e-9);
+
+        // 长时间无成交后只剩最新一笔
+        window.record(2_000, false, 50, 60);
+        assert_eq!(window.buy_sol_amount(), 0);
+        assert_eq!(window.sell_sol_amount(), 50);
+        assert_eq!(window.imbalance(), -1.0);
+    }
+
+    #[test]
+    fn test_imbalance_subscription_ends_with_last_mint_subscription() {
+        let mut manager = SubscriptionManager::new();
+        manager.connections.insert(
+            "client".to_string(),
+            ClientConnection::new("client".to_string(), Instant::now()),
+        );
+        manager.add_subscription("client", "mint_a", "s1").unwrap();
+        manager.add_subscription("client", "mint_a", "m5").unwrap();
+        manager.add_imbalance_subscription("client", "mint_a");
+
+        manager.remove_subscription("client", "mint_a", "s1");
+        assert_eq!(manager.get_imbalance_subscribers("mint_a"), vec!["client"]);
+
+        manager.remove_subscription("client", "mint_a", "m5");
+        assert!(manager.get_imbalance_subscribers("mint_a").is_empty());
+        assert!(!manager.imbalance_subscribers.contains_key("mint_a"));
+    }
 }
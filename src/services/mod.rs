@@ -1,7 +1,21 @@
+pub mod bus_sink;
+pub mod disk_guard;
+pub mod event_latency;
 pub mod event_service;
 pub mod event_storage;
 pub mod kline_socket;
+pub mod rate_limit;
+pub mod response_cache;
+pub mod state_memory;
+pub mod tsdb_sink;
 
+pub use bus_sink::*;
+pub use disk_guard::*;
+pub use event_latency::*;
 pub use event_service::*;
 pub use event_storage::*;
 pub use kline_socket::*;
+pub use rate_limit::*;
+pub use response_cache::*;
+pub use state_memory::*;
+pub use tsdb_sink::*;
@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::config::ApiKeyConfig;
+
+/// Token bucket refilled continuously, holding at most one minute's worth of requests
+struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    updated_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(requests_per_minute: u32, now: Instant) -> Self {
+        let capacity = requests_per_minute as f64;
+        Self {
+            capacity,
+            tokens: capacity,
+            refill_per_sec: capacity / 60.0,
+            updated_at: now,
+        }
+    }
+
+    /// Take one token, or return the seconds until one is available
+    fn try_take(&mut self, now: Instant) -> Result<(), u64> {
+        let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.updated_at = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(((1.0 - self.tokens) / self.refill_per_sec).ceil() as u64)
+        }
+    }
+}
+
+/// Outcome of checking a request's `X-API-Key`
+#[derive(Debug, PartialEq, Eq)]
+pub enum ApiKeyDecision {
+    Allowed,
+    /// Missing or unknown key while `api_keys.enforce` is on
+    Unauthorized,
+    /// Over the key's (or the anonymous tier's) limit; seconds until the next request is allowed
+    RateLimited(u64),
+}
+
+/// Validates API keys and applies per-key token-bucket rate limits.
+/// Requests without a valid key share one anonymous bucket unless enforcement rejects them
+pub struct ApiKeyLimiter {
+    enforce: bool,
+    anonymous_limit: u32,
+    limits: HashMap<String, u32>,
+    /// Keyed by API key, None for the anonymous tier
+    buckets: Mutex<HashMap<Option<String>, TokenBucket>>,
+}
+
+impl ApiKeyLimiter {
+    pub fn new(config: &ApiKeyConfig) -> Self {
+        Self {
+            enforce: config.enforce,
+            anonymous_limit: config.anonymous_requests_per_minute,
+            limits: config
+                .keys
+                .iter()
+                .map(|entry| (entry.key.clone(), entry.requests_per_minute))
+                .collect(),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn check(&self, api_key: Option<&str>) -> ApiKeyDecision {
+        let known_key = api_key.filter(|key| self.limits.contains_key(*key));
+        if known_key.is_none() && self.enforce {
+            return ApiKeyDecision::Unauthorized;
+        }
+
+        let limit = known_key.map_or(self.anonymous_limit, |key| self.limits[key]);
+        if limit == 0 {
+            return ApiKeyDecision::Allowed;
+        }
+
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let bucket = buckets
+            .entry(known_key.map(str::to_string))
+            .or_insert_with(|| TokenBucket::new(limit, now));
+        match bucket.try_take(now) {
+            Ok(()) => ApiKeyDecision::Allowed,
+            Err(retry_after) => ApiKeyDecision::RateLimited(retry_after),
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::config::ResponseCacheConfig;
+
+/// Endpoints whose responses may be cached, as named in `response_cache.ttl_ms`
+pub const CACHEABLE_ENDPOINTS: [&str; 2] = ["mints", "mint_details"];
+
+struct CacheEntry {
+    value: Arc<dyn Any + Send + Sync>,
+    expires_at: Instant,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct CacheEntries {
+    entries: HashMap<String, CacheEntry>,
+    tick: u64,
+}
+
+/// In-memory LRU of query responses keyed by endpoint and normalized query parameters.
+/// Entries expire after their endpoint's TTL, so fresh data surfaces within it
+pub struct ResponseCache {
+    ttls: HashMap<String, Duration>,
+    max_entries: usize,
+    cache: Mutex<CacheEntries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ResponseCache {
+    pub fn new(config: &ResponseCacheConfig) -> Self {
+        let mut ttls = HashMap::new();
+        for (endpoint, ttl_ms) in &config.ttl_ms {
+            if !CACHEABLE_ENDPOINTS.contains(&endpoint.as_str()) {
+                warn!(
+                    "⚠️ response_cache.ttl_ms.{} ignored, cacheable endpoints are: {}",
+                    endpoint,
+                    CACHEABLE_ENDPOINTS.join(", ")
+                );
+            } else if *ttl_ms > 0 {
+                info!("🗃️ Caching {} responses for {}ms", endpoint, ttl_ms);
+                ttls.insert(endpoint.clone(), Duration::from_millis(*ttl_ms));
+            }
+        }
+        Self {
+            ttls,
+            max_entries: config.max_entries.max(1),
+            cache: Mutex::new(CacheEntries::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Cached response for `key`; None on a miss or when the endpoint isn't cached
+    pub fn get<T: Clone + 'static>(&self, endpoint: &str, key: &str) -> Option<T> {
+        if !self.ttls.contains_key(endpoint) {
+            return None;
+        }
+
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        cache.tick += 1;
+        let tick = cache.tick;
+        let cache_key = format!("{}:{}", endpoint, key);
+        let now = Instant::now();
+        if cache
+            .entries
+            .get(&cache_key)
+            .is_some_and(|entry| entry.expires_at <= now)
+        {
+            cache.entries.remove(&cache_key);
+        }
+        let value = cache.entries.get_mut(&cache_key).and_then(|entry| {
+            entry.last_used = tick;
+            entry.value.downcast_ref::<T>().cloned()
+        });
+
+        let counter = if value.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        value
+    }
+
+    /// Store a response for its endpoint's TTL, evicting the least recently used entry when full
+    pub fn insert<T: Send + Sync + 'static>(&self, endpoint: &str, key: &str, value: T) {
+        let Some(ttl) = self.ttls.get(endpoint) else {
+            return;
+        };
+
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        cache.entries.retain(|_, entry| entry.expires_at > now);
+        let cache_key = format!("{}:{}", endpoint, key);
+        if cache.entries.len() >= self.max_entries && !cache.entries.contains_key(&cache_key) {
+            if let Some(oldest) = cache
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            {
+                cache.entries.remove(&oldest);
+            }
+        }
+
+        cache.tick += 1;
+        let last_used = cache.tick;
+        cache.entries.insert(
+            cache_key,
+            CacheEntry {
+                value: Arc::new(value),
+                expires_at: now + *ttl,
+                last_used,
+            },
+        );
+    }
+
+    /// (hits, misses) of cached endpoints since startup
+    pub fn counters(&self) -> (u64, u64) {
+        (
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Hit and miss counters in the Prometheus text format
+    pub fn to_prometheus(&self) -> String {
+        let (hits, misses) = self.counters();
+        format!(
+            "# TYPE spin_response_cache_hits_total counter\nspin_response_cache_hits_total {}\n\
+             # TYPE spin_response_cache_misses_total counter\nspin_response_cache_misses_total {}\n",
+            hits, misses
+        )
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
+
+use crate::services::kline_socket::SubscriptionManager;
+
+/// Seconds between state memory estimates
+const STATE_MEMORY_CHECK_INTERVAL_SECS: u64 = 60;
+
+/// Bookkeeping of one set entry beyond its string bytes (String header plus hash slot)
+pub const SET_ENTRY_OVERHEAD_BYTES: usize = 48;
+
+/// Estimated heap use of the processed-signature dedup cache
+pub fn dedup_cache_bytes(cache: &HashSet<String>) -> usize {
+    cache
+        .iter()
+        .map(|signature| signature.len() + SET_ENTRY_OVERHEAD_BYTES)
+        .sum()
+}
+
+/// Estimated size of the in-memory dedup and subscription state
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StateMemoryEstimate {
+    pub dedup_entries: usize,
+    pub dedup_bytes: usize,
+    pub connections: usize,
+    pub subscription_bytes: usize,
+}
+
+impl StateMemoryEstimate {
+    pub fn total_bytes(&self) -> usize {
+        self.dedup_bytes + self.subscription_bytes
+    }
+
+    /// Gauges in the Prometheus text format; a zero budget means no cap
+    pub fn to_prometheus(&self, budget_bytes: usize) -> String {
+        [
+            ("dedup_entries", self.dedup_entries),
+            ("dedup_bytes", self.dedup_bytes),
+            ("connections", self.connections),
+            ("subscription_bytes", self.subscription_bytes),
+            ("budget_bytes", budget_bytes),
+        ]
+        .iter()
+        .map(|(name, value)| {
+            format!("# TYPE spin_state_memory_{name} gauge\nspin_state_memory_{name} {value}\n")
+        })
+        .collect()
+    }
+}
+
+/// Soft memory cap over the dedup cache and the K-line subscription state. Over budget, the
+/// dedup cache is trimmed to half of what the subscriptions leave free, then the longest-idle
+/// connections are reaped until the estimate fits
+pub struct StateMemoryGuard {
+    budget_bytes: usize,
+    dedup_cache: Option<Arc<RwLock<HashSet<String>>>>,
+    subscriptions: Option<Arc<RwLock<SubscriptionManager>>>,
+    idle_after: Duration,
+}
+
+impl StateMemoryGuard {
+    pub fn new(
+        max_state_memory_mb: u64,
+        dedup_cache: Option<Arc<RwLock<HashSet<String>>>>,
+        subscriptions: Option<Arc<RwLock<SubscriptionManager>>>,
+        idle_after: Duration,
+    ) -> Self {
+        Self {
+            budget_bytes: (max_state_memory_mb as usize).saturating_mul(1024 * 1024),
+            dedup_cache,
+            subscriptions,
+            idle_after,
+        }
+    }
+
+    /// Budget in bytes; zero when no cap is configured
+    pub fn budget_bytes(&self) -> usize {
+        self.budget_bytes
+    }
+
+    pub async fn estimate(&self) -> StateMemoryEstimate {
+        let mut estimate = StateMemoryEstimate::default();
+        if let Some(cache) = &self.dedup_cache {
+            let cache = cache.read().await;
+            estimate.dedup_entries = cache.len();
+            estimate.dedup_bytes = dedup_cache_bytes(&cache);
+        }
+        if let Some(subscriptions) = &self.subscriptions {
+            let manager = subscriptions.read().await;
+            estimate.connections = manager.connections.len();
+            estimate.subscription_bytes = manager.estimated_bytes();
+        }
+        estimate
+    }
+
+    /// Estimate the state and trim it when over budget; returns the estimate after trimming
+    pub async fn enforce(&self) -> StateMemoryEstimate {
+        let estimate = self.estimate().await;
+        if self.budget_bytes == 0 || estimate.total_bytes() <= self.budget_bytes {
+            return estimate;
+        }
+        warn!(
+            "⚠️ State memory estimate {} bytes exceeds server.max_state_memory_mb ({} bytes)",
+            estimate.total_bytes(),
+            self.budget_bytes
+        );
+
+        if let Some(cache) = &self.dedup_cache {
+            let allowed = self
+                .budget_bytes
+                .saturating_sub(estimate.subscription_bytes)
+                / 2;
+            let mut cache = cache.write().await;
+            let mut bytes = dedup_cache_bytes(&cache);
+            let before = cache.len();
+            cache.retain(|signature| {
+                if bytes <= allowed {
+                    return true;
+                }
+                bytes -= signature.len() + SET_ENTRY_OVERHEAD_BYTES;
+                false
+            });
+            warn!(
+                "🧹 Trimmed dedup cache from {} to {} signatures",
+                before,
+                cache.len()
+            );
+        }
+
+        if let Some(subscriptions) = &self.subscriptions {
+            let dedup_bytes = self.estimate().await.dedup_bytes;
+            let allowed = self.budget_bytes.saturating_sub(dedup_bytes);
+            let mut manager = subscriptions.write().await;
+            let mut reaped = 0;
+            for socket_id in manager.idle_clients_oldest_first(Instant::now(), self.idle_after) {
+                if manager.estimated_bytes() <= allowed {
+                    break;
+                }
+                manager.remove_client(&socket_id);
+                reaped += 1;
+            }
+            if reaped > 0 {
+                warn!("🧹 Reaped {} idle connections over memory budget", reaped);
+            }
+        }
+
+        self.estimate().await
+    }
+}
+
+/// Periodically log the state memory estimate and enforce `server.max_state_memory_mb`
+pub async fn start_state_memory_task(guard: Arc<StateMemoryGuard>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval =
+            tokio::time::interval(Duration::from_secs(STATE_MEMORY_CHECK_INTERVAL_SECS));
+
+        loop {
+            interval.tick().await;
+            let estimate = guard.enforce().await;
+            let message = format!(
+                "🧠 State memory: {} bytes ({} dedup signatures, {} connections)",
+                estimate.total_bytes(),
+                estimate.dedup_entries,
+                estimate.connections
+            );
+            if guard.budget_bytes() > 0 {
+                info!("{}", message);
+            } else {
+                debug!("{}", message);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_over_budget_dedup_cache_is_trimmed() {
+        let cache = Arc::new(RwLock::new(
+            (0..50_000)
+                .map(|i| format!("signature_{:080}", i))
+                .collect::<HashSet<_>>(),
+        ));
+        // 50k signatures of ~90 bytes plus overhead is well over the 1 MB budget
+        let guard = StateMemoryGuard::new(
+            1,
+            Some(Arc::clone(&cache)),
+            Some(Arc::new(RwLock::new(SubscriptionManager::new()))),
+            Duration::from_secs(60),
+        );
+        let before = guard.estimate().await;
+        assert!(before.total_bytes() > guard.budget_bytes());
+
+        let after = guard.enforce().await;
+        assert!(after.dedup_entries < before.dedup_entries);
+        assert!(after.dedup_bytes <= guard.budget_bytes() / 2);
+        assert_eq!(cache.read().await.len(), after.dedup_entries);
+
+        // Within budget nothing more is removed
+        assert_eq!(guard.enforce().await, after);
+    }
+}
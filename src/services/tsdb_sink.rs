@@ -0,0 +1,156 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use crate::config::TsdbConfig;
+use crate::models::KlineData;
+use crate::services::disk_guard::DiskSpaceGuard;
+
+/// Timeout of a single batch write to the TSDB endpoint
+const TSDB_WRITE_TIMEOUT_SECS: u64 = 10;
+
+/// Destination of batched line-protocol points
+#[async_trait]
+pub trait TsdbSink: Send + Sync {
+    async fn write(&self, body: String) -> Result<()>;
+}
+
+/// Posts line protocol to an HTTP write endpoint such as InfluxDB's `/api/v2/write`
+pub struct HttpLineSink {
+    client: reqwest::Client,
+    url: String,
+    auth_header: Option<String>,
+}
+
+impl HttpLineSink {
+    pub fn new(config: &TsdbConfig) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(TSDB_WRITE_TIMEOUT_SECS))
+            .build()?;
+        Ok(Self {
+            client,
+            url: config.url.clone(),
+            auth_header: config.auth_header.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl TsdbSink for HttpLineSink {
+    async fn write(&self, body: String) -> Result<()> {
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header("content-type", "text/plain; charset=utf-8")
+            .body(body);
+        if let Some(auth) = &self.auth_header {
+            request = request.header("authorization", auth);
+        }
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Buffers kline updates as line-protocol points and writes them to a sink in batches.
+/// Write failures are logged and the batch dropped; RocksDB stays the source of truth
+pub struct TsdbWriter {
+    sink: Arc<dyn TsdbSink>,
+    measurement: String,
+    batch_size: usize,
+    buffer: Mutex<Vec<String>>,
+}
+
+impl TsdbWriter {
+    pub fn new(config: &TsdbConfig, sink: Arc<dyn TsdbSink>) -> Self {
+        Self {
+            sink,
+            measurement: config.measurement.clone(),
+            batch_size: config.batch_size.max(1),
+            buffer: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Writer posting to `tsdb.url`, or None when the dual-write is disabled
+    pub fn from_config(config: &TsdbConfig) -> Result<Option<Arc<Self>>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        if config.url.is_empty() {
+            anyhow::bail!("tsdb.url must be set when tsdb.enabled is true");
+        }
+        info!(
+            "📈 TSDB dual-write enabled: {} (measurement {}, batch {})",
+            config.url, config.measurement, config.batch_size
+        );
+        let sink = Arc::new(HttpLineSink::new(config)?);
+        Ok(Some(Arc::new(Self::new(config, sink))))
+    }
+
+    /// One line-protocol point; later points for the same candle overwrite earlier ones
+    pub fn line(&self, mint_account: &str, interval: &str, kline: &KlineData) -> String {
+        format!(
+            "{},mint={},interval={} open={},high={},low={},close={},volume={},update_count={}i,is_final={} {}",
+            self.measurement,
+            mint_account,
+            interval,
+            kline.open,
+            kline.high,
+            kline.low,
+            kline.close,
+            kline.volume,
+            kline.update_count,
+            kline.is_final,
+            kline.time as u128 * 1_000_000_000
+        )
+    }
+
+    /// Buffer a candle update; returns true once the batch is full and should be flushed
+    pub fn record(&self, mint_account: &str, interval: &str, kline: &KlineData) -> bool {
+        let line = self.line(mint_account, interval, kline);
+        let mut buffer = self.buffer.lock().unwrap_or_else(|e| e.into_inner());
+        buffer.push(line);
+        buffer.len() >= self.batch_size
+    }
+
+    /// Write all buffered points, returning how many were written
+    pub async fn flush(&self) -> usize {
+        let lines = std::mem::take(&mut *self.buffer.lock().unwrap_or_else(|e| e.into_inner()));
+        if lines.is_empty() {
+            return 0;
+        }
+
+        let count = lines.len();
+        match self.sink.write(lines.join("\n")).await {
+            Ok(()) => {
+                debug!("📈 Wrote {} kline points to TSDB", count);
+                count
+            }
+            Err(e) => {
+                warn!("⚠️ TSDB write of {} kline points failed: {}", count, e);
+                0
+            }
+        }
+    }
+}
+
+/// Periodically flush buffered kline points so partial batches are not held back; points stay
+/// buffered while the database disk is low (`database.min_free_disk_mb`)
+pub async fn start_tsdb_flush_task(
+    writer: Arc<TsdbWriter>,
+    interval_ms: u64,
+    disk_guard: Option<Arc<DiskSpaceGuard>>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_millis(interval_ms));
+
+        loop {
+            interval.tick().await;
+            if disk_guard.as_ref().is_some_and(|guard| guard.is_degraded()) {
+                continue;
+            }
+            writer.flush().await;
+        }
+    })
+}
@@ -1,9 +1,13 @@
 use anyhow::Result;
+use async_trait::async_trait;
 use serde_json::Value;
-use solana_client::rpc_client::RpcClient;
+use solana_client::client_error::{ClientError, ClientErrorKind};
+use solana_client::rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient};
 use solana_client::rpc_config::{
     RpcTransactionConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter,
 };
+use solana_client::rpc_custom_error::JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY;
+use solana_client::rpc_request::RpcError;
 use solana_sdk::commitment_config::CommitmentConfig;
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signature::Signature;
@@ -46,6 +50,55 @@ impl Default for ConnectionStats {
     }
 }
 
+/// Source of full transaction details, abstracted so listener logic can run without a live RPC node
+#[async_trait]
+pub trait TransactionFetcher: Send + Sync {
+    async fn get_transaction_with_logs(&self, signature: &str) -> Result<Value>;
+
+    /// Successful program transaction signatures within [from_slot, to_slot], oldest first
+    async fn get_signatures_for_slot_range(
+        &self,
+        from_slot: u64,
+        to_slot: u64,
+    ) -> Result<Vec<String>>;
+
+    /// Unix block time of a slot, None while the node has no block time for it
+    async fn get_block_time(&self, slot: u64) -> Result<Option<i64>>;
+
+    /// Current chain head slot
+    async fn get_head_slot(&self) -> Result<u64>;
+}
+
+/// Page size for getSignaturesForAddress (RPC maximum)
+const SIGNATURE_PAGE_LIMIT: usize = 1000;
+
+/// How a failed getTransaction call is handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TransactionFetchFailure {
+    /// The node returned no transaction; retried, then reported as an empty result
+    NotAvailable,
+    /// Transport failure or unhealthy node; retried, then returned as an error
+    Transient,
+    /// Rejected by the node; returned as an error without retrying
+    Hard,
+}
+
+impl TransactionFetchFailure {
+    fn classify(error: &ClientError) -> Self {
+        match error.kind() {
+            // A null result fails to deserialize into a transaction
+            ClientErrorKind::SerdeJson(_) => Self::NotAvailable,
+            ClientErrorKind::Io(_) | ClientErrorKind::Reqwest(_) => Self::Transient,
+            ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. })
+                if *code == JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY =>
+            {
+                Self::Transient
+            }
+            _ => Self::Hard,
+        }
+    }
+}
+
 /// Solana RPC client wrapper with reconnection capabilities
 pub struct SolanaClient {
     rpc_url: String,
@@ -57,6 +110,11 @@ pub struct SolanaClient {
     max_reconnect_attempts: u32,
     base_reconnect_interval: u64, // seconds
     max_reconnect_interval: u64,  // seconds
+    slot_commitment: CommitmentConfig,
+    head_slot_ttl: Duration,
+    head_slot_cache: Arc<RwLock<Option<(u64, Instant)>>>,
+    transaction_fetch_attempts: u32,
+    transaction_fetch_retry_delay: Duration,
 }
 
 impl SolanaClient {
@@ -78,9 +136,29 @@ impl SolanaClient {
             max_reconnect_attempts: 10,
             base_reconnect_interval: 1, // Start with 1 second
             max_reconnect_interval: 30, // Max 30 seconds
+            slot_commitment: CommitmentConfig::processed(),
+            head_slot_ttl: Duration::from_millis(1000),
+            head_slot_cache: Arc::new(RwLock::new(None)),
+            transaction_fetch_attempts: 1,
+            transaction_fetch_retry_delay: Duration::from_millis(500),
         })
     }
 
+    /// Set the commitment used for head slot queries and how long a fetched head slot is reused
+    pub fn with_head_slot_config(mut self, commitment: &str, ttl_ms: u64) -> Result<Self> {
+        self.slot_commitment = CommitmentConfig::from_str(commitment)
+            .map_err(|e| anyhow::anyhow!("Invalid commitment '{}': {}", commitment, e))?;
+        self.head_slot_ttl = Duration::from_millis(ttl_ms);
+        Ok(self)
+    }
+
+    /// Set how many getTransaction calls a fetch makes and the delay before the first retry
+    pub fn with_transaction_fetch_retry(mut self, attempts: u32, retry_delay_ms: u64) -> Self {
+        self.transaction_fetch_attempts = attempts.max(1);
+        self.transaction_fetch_retry_delay = Duration::from_millis(retry_delay_ms);
+        self
+    }
+
     /// Create a new Solana client with custom reconnection settings
     #[allow(dead_code)]
     pub fn new_with_config(
@@ -255,6 +333,38 @@ impl SolanaClient {
         .await
     }
 
+    /// Chain head slot at the configured commitment, cached for the head slot TTL.
+    /// Single attempt without reconnect retries so monitoring never stalls on a bad RPC.
+    pub async fn get_slot(&self) -> Result<u64> {
+        if let Some((slot, fetched_at)) = *self.head_slot_cache.read().await {
+            if fetched_at.elapsed() < self.head_slot_ttl {
+                return Ok(slot);
+            }
+        }
+
+        let slot = self
+            .client
+            .read()
+            .await
+            .get_slot_with_commitment(self.slot_commitment)
+            .map_err(|e| anyhow::anyhow!("getSlot failed: {}", e))?;
+        debug!("Fetched head slot: {}", slot);
+        *self.head_slot_cache.write().await = Some((slot, Instant::now()));
+        Ok(slot)
+    }
+
+    /// Get the block time of a slot; None when the node can't provide it (yet)
+    pub async fn get_block_time(&self, slot: u64) -> Result<Option<i64>> {
+        self.execute_with_retry(move |client| match client.get_block_time(slot) {
+            Ok(block_time) => Ok(Some(block_time)),
+            Err(e) => {
+                debug!("Block time not available for slot {}: {}", slot, e);
+                Ok(None)
+            }
+        })
+        .await
+    }
+
     /// Get program logs with automatic reconnection
     #[allow(dead_code)]
     pub async fn get_program_logs(&self, _limit: usize) -> Result<Vec<String>> {
@@ -324,33 +434,126 @@ impl SolanaClient {
         }).await
     }
 
-    /// Get transaction with full logs including CPI calls
+    /// Get transaction with full logs including CPI calls. Calls the node again with
+    /// exponential backoff while the transaction is not available yet or the request fails in
+    /// transit; an empty object means it never became available.
     pub async fn get_transaction_with_logs(&self, signature: &str) -> Result<Value> {
-        let signature_str = signature.to_string();
-        self.execute_with_retry(move |client| {
-            let sig = Signature::from_str(&signature_str)?;
-            // Use confirmed instead of finalized for faster response
-            let config = RpcTransactionConfig {
-                encoding: Some(UiTransactionEncoding::Json),
-                commitment: Some(CommitmentConfig::confirmed()),
-                max_supported_transaction_version: Some(0),
-            };
+        let sig = Signature::from_str(signature)?;
+        let mut delay = self.transaction_fetch_retry_delay;
+
+        for attempt in 1..=self.transaction_fetch_attempts {
+            let result = self
+                .execute_with_retry(move |client| {
+                    // Use confirmed instead of finalized for faster response
+                    let config = RpcTransactionConfig {
+                        encoding: Some(UiTransactionEncoding::Json),
+                        commitment: Some(CommitmentConfig::confirmed()),
+                        max_supported_transaction_version: Some(0),
+                    };
+                    Ok(client.get_transaction_with_config(&sig, config))
+                })
+                .await?;
 
-            match client.get_transaction_with_config(&sig, config) {
+            let error = match result {
                 Ok(transaction) => {
                     // Convert the transaction to JSON for easier parsing
                     let json = serde_json::to_value(&transaction)?;
-                    debug!("Got transaction details for {}", signature_str);
-                    Ok(json)
+                    debug!("Got transaction details for {}", signature);
+                    return Ok(json);
                 }
-                Err(e) => {
-                    // Transaction might not be available yet, return empty result instead of error
-                    debug!("Transaction {} not available yet: {}", signature_str, e);
-                    Ok(serde_json::json!({}))
+                Err(e) => e,
+            };
+
+            let failure = TransactionFetchFailure::classify(&error);
+            let last_attempt = attempt == self.transaction_fetch_attempts;
+            match failure {
+                TransactionFetchFailure::Hard => {
+                    return Err(anyhow::anyhow!(
+                        "getTransaction {} failed: {}",
+                        signature,
+                        error
+                    ));
+                }
+                TransactionFetchFailure::NotAvailable if last_attempt => {
+                    // Return empty result instead of error, the caller decides whether to wait longer
+                    debug!("Transaction {} not available yet: {}", signature, error);
+                    return Ok(serde_json::json!({}));
+                }
+                TransactionFetchFailure::Transient if last_attempt => {
+                    return Err(anyhow::anyhow!(
+                        "getTransaction {} failed after {} attempts: {}",
+                        signature,
+                        attempt,
+                        error
+                    ));
+                }
+                _ => {
+                    debug!(
+                        "getTransaction {} {:?} (attempt {}/{}): {}, retrying in {:?}",
+                        signature, failure, attempt, self.transaction_fetch_attempts, error, delay
+                    );
+                    sleep(delay).await;
+                    delay *= 2;
                 }
             }
-        })
-        .await
+        }
+
+        Ok(serde_json::json!({}))
+    }
+
+    /// Get successful program transaction signatures within a slot range, oldest first
+    pub async fn get_signatures_for_slot_range(
+        &self,
+        from_slot: u64,
+        to_slot: u64,
+    ) -> Result<Vec<String>> {
+        let program_id = self.program_id;
+        let mut signatures = Vec::new();
+        let mut before: Option<Signature> = None;
+
+        // Results come back newest first, so page backwards until we pass from_slot
+        loop {
+            let page = self
+                .execute_with_retry(move |client| {
+                    let config = GetConfirmedSignaturesForAddress2Config {
+                        before,
+                        until: None,
+                        limit: Some(SIGNATURE_PAGE_LIMIT),
+                        commitment: Some(CommitmentConfig::confirmed()),
+                    };
+                    Ok(client.get_signatures_for_address_with_config(&program_id, config)?)
+                })
+                .await?;
+
+            let page_len = page.len();
+            let mut reached_start = false;
+            for status in page.iter() {
+                if status.slot < from_slot {
+                    reached_start = true;
+                    break;
+                }
+                if status.slot <= to_slot && status.err.is_none() {
+                    signatures.push(status.signature.clone());
+                }
+            }
+
+            if reached_start || page_len < SIGNATURE_PAGE_LIMIT {
+                break;
+            }
+            before = match page.last() {
+                Some(status) => Some(Signature::from_str(&status.signature)?),
+                None => break,
+            };
+        }
+
+        signatures.reverse();
+        debug!(
+            "Found {} signatures in slot range {}..={}",
+            signatures.len(),
+            from_slot,
+            to_slot
+        );
+        Ok(signatures)
     }
 
     /// Check RPC connection status with automatic reconnection attempt
@@ -403,6 +606,29 @@ impl SolanaClient {
     }
 }
 
+#[async_trait]
+impl TransactionFetcher for SolanaClient {
+    async fn get_transaction_with_logs(&self, signature: &str) -> Result<Value> {
+        SolanaClient::get_transaction_with_logs(self, signature).await
+    }
+
+    async fn get_signatures_for_slot_range(
+        &self,
+        from_slot: u64,
+        to_slot: u64,
+    ) -> Result<Vec<String>> {
+        SolanaClient::get_signatures_for_slot_range(self, from_slot, to_slot).await
+    }
+
+    async fn get_block_time(&self, slot: u64) -> Result<Option<i64>> {
+        SolanaClient::get_block_time(self, slot).await
+    }
+
+    async fn get_head_slot(&self) -> Result<u64> {
+        self.get_slot().await
+    }
+}
+
 /// Transaction details structure
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
@@ -464,4 +690,83 @@ mod tests {
         assert_eq!(stats.failed_requests, 0);
         assert_eq!(stats.reconnect_attempts, 0);
     }
+
+    fn client_with_rpc(rpc: RpcClient) -> SolanaClient {
+        let client = SolanaClient::new("http://localhost:8899", "11111111111111111111111111111111")
+            .unwrap()
+            .with_head_slot_config("confirmed", 60_000)
+            .unwrap();
+        *client.client.try_write().unwrap() = rpc;
+        client
+    }
+
+    #[tokio::test]
+    async fn test_get_slot_parses_and_caches_head_slot() {
+        let mut mocks = solana_client::mock_sender::Mocks::default();
+        mocks.insert(
+            solana_client::rpc_request::RpcRequest::GetSlot,
+            serde_json::json!(250_000_123u64),
+        );
+        let client = client_with_rpc(RpcClient::new_mock_with_mocks(
+            "succeeds".to_string(),
+            mocks,
+        ));
+
+        assert_eq!(client.get_slot().await.unwrap(), 250_000_123);
+        assert_eq!(
+            crate::services::compute_lag_slots(Some(250_000_123), Some(250_000_100)),
+            Some(23)
+        );
+
+        // Within the TTL the cached value is served without another RPC call
+        *client.client.write().await = RpcClient::new_mock("fails".to_string());
+        assert_eq!(client.get_slot().await.unwrap(), 250_000_123);
+    }
+
+    #[tokio::test]
+    async fn test_get_slot_error_reports_unknown_lag() {
+        let client = client_with_rpc(RpcClient::new_mock("fails".to_string()));
+
+        let head_slot = client.get_slot().await.ok();
+        assert!(head_slot.is_none());
+        assert_eq!(
+            crate::services::compute_lag_slots(head_slot, Some(10)),
+            None
+        );
+        assert_eq!(crate::services::compute_lag_slots(Some(10), None), None);
+    }
+
+    // The blocking RpcClient needs block_in_place, which requires the multi-threaded runtime
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_get_transaction_retries_until_available() {
+        let signature = Signature::from([8; 64]).to_string();
+        let not_yet_available = || {
+            let mut mocks = solana_client::mock_sender::Mocks::default();
+            mocks.insert(
+                solana_client::rpc_request::RpcRequest::GetTransaction,
+                Value::Null,
+            );
+            RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks)
+        };
+
+        // The first call finds nothing, the retry gets the transaction
+        let client = client_with_rpc(not_yet_available()).with_transaction_fetch_retry(3, 10);
+        let transaction = client.get_transaction_with_logs(&signature).await.unwrap();
+        assert_eq!(transaction["slot"], 2);
+        assert_eq!(client.get_connection_stats().await.total_requests, 2);
+
+        // A single attempt reports the transaction as not available yet
+        let client = client_with_rpc(not_yet_available()).with_transaction_fetch_retry(1, 10);
+        let transaction = client.get_transaction_with_logs(&signature).await.unwrap();
+        assert_eq!(transaction, serde_json::json!({}));
+        assert_eq!(client.get_connection_stats().await.total_requests, 1);
+    }
+
+    #[test]
+    fn test_invalid_commitment_is_rejected() {
+        let client = SolanaClient::new("http://localhost:8899", "11111111111111111111111111111111")
+            .unwrap()
+            .with_head_slot_config("eventually", 1000);
+        assert!(client.is_err());
+    }
 }
@@ -4,9 +4,18 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DisplayFromStr};
 use solana_sdk::pubkey::Pubkey;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use tracing::{debug, warn};
 use utoipa::ToSchema;
 
+use crate::config::{
+    SolanaConfig, DEFAULT_MAX_TOKEN_NAME_LEN, DEFAULT_MAX_TOKEN_SYMBOL_LEN,
+    DEFAULT_MAX_TOKEN_URI_LEN,
+};
+use crate::utils::AmountU64;
+
 /// Event discriminators - correct discriminators from IDL file
 pub const TOKEN_CREATED_EVENT_DISCRIMINATOR: [u8; 8] = [96, 122, 113, 138, 50, 227, 149, 57];
 pub const BUY_SELL_EVENT_DISCRIMINATOR: [u8; 8] = [98, 208, 120, 60, 93, 32, 19, 180];
@@ -16,6 +25,11 @@ pub const FULL_CLOSE_EVENT_DISCRIMINATOR: [u8; 8] = [22, 244, 113, 245, 154, 168
 pub const PARTIAL_CLOSE_EVENT_DISCRIMINATOR: [u8; 8] = [133, 94, 3, 222, 24, 68, 69, 155];
 pub const MILESTONE_DISCOUNT_EVENT_DISCRIMINATOR: [u8; 8] = [130, 232, 11, 37, 34, 185, 136, 128];
 
+/// PDA seeds from the IDL; each account is derived from the constant seed and the mint
+pub const CURVE_ACCOUNT_SEED: &[u8] = b"borrowing_curve";
+pub const POOL_TOKEN_ACCOUNT_SEED: &[u8] = b"pool_token";
+pub const POOL_SOL_ACCOUNT_SEED: &[u8] = b"pool_sol";
+
 /// Unified enum for all Spin Pet events
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(tag = "event_type")]
@@ -29,6 +43,249 @@ pub enum SpinPetEvent {
     MilestoneDiscount(MilestoneDiscountEvent),
 }
 
+/// Where an event's timestamp came from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampSource {
+    /// On-chain block time of the event's slot
+    BlockTime,
+    /// Time the server received the event
+    #[default]
+    ServerReceive,
+    /// Extrapolated from the slot during replay, because the transaction carried no block time
+    SlotDerived,
+}
+
+impl SpinPetEvent {
+    /// Overwrite the event timestamp and record where it came from
+    pub fn set_timestamp(&mut self, timestamp: DateTime<Utc>, source: TimestampSource) {
+        let (ts, src) = match self {
+            SpinPetEvent::TokenCreated(e) => (&mut e.timestamp, &mut e.timestamp_source),
+            SpinPetEvent::BuySell(e) => (&mut e.timestamp, &mut e.timestamp_source),
+            SpinPetEvent::LongShort(e) => (&mut e.timestamp, &mut e.timestamp_source),
+            SpinPetEvent::ForceLiquidate(e) => (&mut e.timestamp, &mut e.timestamp_source),
+            SpinPetEvent::FullClose(e) => (&mut e.timestamp, &mut e.timestamp_source),
+            SpinPetEvent::PartialClose(e) => (&mut e.timestamp, &mut e.timestamp_source),
+            SpinPetEvent::MilestoneDiscount(e) => (&mut e.timestamp, &mut e.timestamp_source),
+        };
+        *ts = timestamp;
+        *src = source;
+    }
+
+    /// Stored event timestamp (block time or server receive time, see `timestamp_source`)
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        match self {
+            SpinPetEvent::TokenCreated(e) => e.timestamp,
+            SpinPetEvent::BuySell(e) => e.timestamp,
+            SpinPetEvent::LongShort(e) => e.timestamp,
+            SpinPetEvent::ForceLiquidate(e) => e.timestamp,
+            SpinPetEvent::FullClose(e) => e.timestamp,
+            SpinPetEvent::PartialClose(e) => e.timestamp,
+            SpinPetEvent::MilestoneDiscount(e) => e.timestamp,
+        }
+    }
+
+    /// Where `timestamp` came from
+    pub fn timestamp_source(&self) -> TimestampSource {
+        match self {
+            SpinPetEvent::TokenCreated(e) => e.timestamp_source,
+            SpinPetEvent::BuySell(e) => e.timestamp_source,
+            SpinPetEvent::LongShort(e) => e.timestamp_source,
+            SpinPetEvent::ForceLiquidate(e) => e.timestamp_source,
+            SpinPetEvent::FullClose(e) => e.timestamp_source,
+            SpinPetEvent::PartialClose(e) => e.timestamp_source,
+            SpinPetEvent::MilestoneDiscount(e) => e.timestamp_source,
+        }
+    }
+
+    /// Mint the event belongs to
+    pub fn mint_account(&self) -> &str {
+        match self {
+            SpinPetEvent::TokenCreated(e) => &e.mint_account,
+            SpinPetEvent::BuySell(e) => &e.mint_account,
+            SpinPetEvent::LongShort(e) => &e.mint_account,
+            SpinPetEvent::ForceLiquidate(e) => &e.mint_account,
+            SpinPetEvent::FullClose(e) => &e.mint_account,
+            SpinPetEvent::PartialClose(e) => &e.mint_account,
+            SpinPetEvent::MilestoneDiscount(e) => &e.mint_account,
+        }
+    }
+
+    /// Transaction signature the event was emitted in
+    pub fn signature(&self) -> &str {
+        match self {
+            SpinPetEvent::TokenCreated(e) => &e.signature,
+            SpinPetEvent::BuySell(e) => &e.signature,
+            SpinPetEvent::LongShort(e) => &e.signature,
+            SpinPetEvent::ForceLiquidate(e) => &e.signature,
+            SpinPetEvent::FullClose(e) => &e.signature,
+            SpinPetEvent::PartialClose(e) => &e.signature,
+            SpinPetEvent::MilestoneDiscount(e) => &e.signature,
+        }
+    }
+
+    /// snake_case event type, as used in `solana.event_min_lengths`
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            SpinPetEvent::TokenCreated(_) => "token_created",
+            SpinPetEvent::BuySell(_) => "buy_sell",
+            SpinPetEvent::LongShort(_) => "long_short",
+            SpinPetEvent::ForceLiquidate(_) => "force_liquidate",
+            SpinPetEvent::FullClose(_) => "full_close",
+            SpinPetEvent::PartialClose(_) => "partial_close",
+            SpinPetEvent::MilestoneDiscount(_) => "milestone_discount",
+        }
+    }
+
+    /// Variant name, as in the `event_type` JSON tag
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            SpinPetEvent::TokenCreated(_) => "TokenCreated",
+            SpinPetEvent::BuySell(_) => "BuySell",
+            SpinPetEvent::LongShort(_) => "LongShort",
+            SpinPetEvent::ForceLiquidate(_) => "ForceLiquidate",
+            SpinPetEvent::FullClose(_) => "FullClose",
+            SpinPetEvent::PartialClose(_) => "PartialClose",
+            SpinPetEvent::MilestoneDiscount(_) => "MilestoneDiscount",
+        }
+    }
+
+    /// Order PDA of order events
+    pub fn order_pda(&self) -> Option<&str> {
+        match self {
+            SpinPetEvent::LongShort(e) => Some(&e.order_pda),
+            SpinPetEvent::ForceLiquidate(e) => Some(&e.order_pda),
+            SpinPetEvent::FullClose(e) => Some(&e.order_pda),
+            SpinPetEvent::PartialClose(e) => Some(&e.order_pda),
+            _ => None,
+        }
+    }
+
+    /// Pool price after the event, for events that move it
+    pub fn latest_price(&self) -> Option<u128> {
+        match self {
+            SpinPetEvent::BuySell(e) => Some(e.latest_price),
+            SpinPetEvent::LongShort(e) => Some(e.latest_price),
+            SpinPetEvent::FullClose(e) => Some(e.latest_price),
+            SpinPetEvent::PartialClose(e) => Some(e.latest_price),
+            _ => None,
+        }
+    }
+
+    /// Slot the event was observed in
+    pub fn slot(&self) -> u64 {
+        match self {
+            SpinPetEvent::TokenCreated(e) => e.slot,
+            SpinPetEvent::BuySell(e) => e.slot,
+            SpinPetEvent::LongShort(e) => e.slot,
+            SpinPetEvent::ForceLiquidate(e) => e.slot,
+            SpinPetEvent::FullClose(e) => e.slot,
+            SpinPetEvent::PartialClose(e) => e.slot,
+            SpinPetEvent::MilestoneDiscount(e) => e.slot,
+        }
+    }
+
+    /// Position of the event among its transaction's events, in emission order
+    pub fn log_index(&self) -> u32 {
+        match self {
+            SpinPetEvent::TokenCreated(e) => e.log_index,
+            SpinPetEvent::BuySell(e) => e.log_index,
+            SpinPetEvent::LongShort(e) => e.log_index,
+            SpinPetEvent::ForceLiquidate(e) => e.log_index,
+            SpinPetEvent::FullClose(e) => e.log_index,
+            SpinPetEvent::PartialClose(e) => e.log_index,
+            SpinPetEvent::MilestoneDiscount(e) => e.log_index,
+        }
+    }
+
+    pub fn set_log_index(&mut self, log_index: u32) {
+        match self {
+            SpinPetEvent::TokenCreated(e) => e.log_index = log_index,
+            SpinPetEvent::BuySell(e) => e.log_index = log_index,
+            SpinPetEvent::LongShort(e) => e.log_index = log_index,
+            SpinPetEvent::ForceLiquidate(e) => e.log_index = log_index,
+            SpinPetEvent::FullClose(e) => e.log_index = log_index,
+            SpinPetEvent::PartialClose(e) => e.log_index = log_index,
+            SpinPetEvent::MilestoneDiscount(e) => e.log_index = log_index,
+        }
+    }
+}
+
+/// Direction of a margin order, carried on chain as the raw `order_type` byte.
+///
+/// Longs (1) are stored as "dn" (down) orders because they are liquidated as the price
+/// falls; shorts (2) are "up" orders. Keep every mapping of the raw value going through here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderType {
+    Long,
+    Short,
+}
+
+impl OrderType {
+    /// Decode a raw `order_type`; anything but 2 is a long, as the order indexes have always keyed it
+    pub fn from_raw(raw: u8) -> Self {
+        if raw == 2 {
+            OrderType::Short
+        } else {
+            OrderType::Long
+        }
+    }
+
+    /// Decode a raw `order_type`; None for anything but 1 or 2
+    pub fn try_from_raw(raw: u8) -> Option<Self> {
+        match raw {
+            1 => Some(OrderType::Long),
+            2 => Some(OrderType::Short),
+            _ => None,
+        }
+    }
+
+    /// Raw on-chain value
+    pub fn raw(self) -> u8 {
+        match self {
+            OrderType::Long => 1,
+            OrderType::Short => 2,
+        }
+    }
+
+    /// Type of the order a FullClose closes
+    pub fn from_close_long(is_close_long: bool) -> Self {
+        if is_close_long {
+            OrderType::Long
+        } else {
+            OrderType::Short
+        }
+    }
+
+    pub fn is_long(self) -> bool {
+        self == OrderType::Long
+    }
+
+    /// Segment used in the `or:`/`ox:` order keys
+    pub fn key_segment(self) -> &'static str {
+        match self {
+            OrderType::Long => "dn",
+            OrderType::Short => "up",
+        }
+    }
+
+    /// Name of the side in order queries (`down_orders` / `up_orders`)
+    pub fn query_name(self) -> &'static str {
+        match self {
+            OrderType::Long => "down_orders",
+            OrderType::Short => "up_orders",
+        }
+    }
+
+    pub fn from_query_name(name: &str) -> Option<Self> {
+        match name {
+            "down_orders" => Some(OrderType::Long),
+            "up_orders" => Some(OrderType::Short),
+            _ => None,
+        }
+    }
+}
+
 /// Token creation event - exactly matches original Anchor structure
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct TokenCreatedEvent {
@@ -49,8 +306,13 @@ pub struct TokenCreatedEvent {
 
     #[schema(value_type = String)]
     pub timestamp: DateTime<Utc>,
+    #[serde(default)]
+    pub timestamp_source: TimestampSource,
     pub signature: String,
     pub slot: u64,
+    /// Position among the transaction's events, in emission order
+    #[serde(default)]
+    pub log_index: u32,
 }
 
 /// Buy/Sell event - exactly matches original Anchor structure
@@ -60,14 +322,21 @@ pub struct BuySellEvent {
     pub payer: String,
     pub mint_account: String,
     pub is_buy: bool,
+    #[serde_as(as = "AmountU64")]
     pub token_amount: u64,
+    #[serde_as(as = "AmountU64")]
     pub sol_amount: u64,
     #[serde_as(as = "DisplayFromStr")]
     pub latest_price: u128,
     #[schema(value_type = String)]
     pub timestamp: DateTime<Utc>,
+    #[serde(default)]
+    pub timestamp_source: TimestampSource,
     pub signature: String,
     pub slot: u64,
+    /// Position among the transaction's events, in emission order
+    #[serde(default)]
+    pub log_index: u32,
 }
 
 /// Long/Short event - exactly matches original Anchor structure
@@ -86,18 +355,28 @@ pub struct LongShortEvent {
     pub lock_lp_start_price: u128,
     #[serde_as(as = "DisplayFromStr")]
     pub lock_lp_end_price: u128,
+    #[serde_as(as = "AmountU64")]
     pub lock_lp_sol_amount: u64,
+    #[serde_as(as = "AmountU64")]
     pub lock_lp_token_amount: u64,
     pub start_time: u32,
     pub end_time: u32,
+    #[serde_as(as = "AmountU64")]
     pub margin_sol_amount: u64,
+    #[serde_as(as = "AmountU64")]
     pub borrow_amount: u64,
+    #[serde_as(as = "AmountU64")]
     pub position_asset_amount: u64,
     pub borrow_fee: u16,
     #[schema(value_type = String)]
     pub timestamp: DateTime<Utc>,
+    #[serde(default)]
+    pub timestamp_source: TimestampSource,
     pub signature: String,
     pub slot: u64,
+    /// Position among the transaction's events, in emission order
+    #[serde(default)]
+    pub log_index: u32,
 }
 
 /// Force liquidation event - exactly matches original Anchor structure
@@ -108,8 +387,13 @@ pub struct ForceLiquidateEvent {
     pub order_pda: String,
     #[schema(value_type = String)]
     pub timestamp: DateTime<Utc>,
+    #[serde(default)]
+    pub timestamp_source: TimestampSource,
     pub signature: String,
     pub slot: u64,
+    /// Position among the transaction's events, in emission order
+    #[serde(default)]
+    pub log_index: u32,
 }
 
 /// Full close event - exactly matches original Anchor structure
@@ -120,16 +404,24 @@ pub struct FullCloseEvent {
     pub user_sol_account: String,
     pub mint_account: String,
     pub is_close_long: bool,
+    #[serde_as(as = "AmountU64")]
     pub final_token_amount: u64,
+    #[serde_as(as = "AmountU64")]
     pub final_sol_amount: u64,
+    #[serde_as(as = "AmountU64")]
     pub user_close_profit: u64,
     #[serde_as(as = "DisplayFromStr")]
     pub latest_price: u128,
     pub order_pda: String,
     #[schema(value_type = String)]
     pub timestamp: DateTime<Utc>,
+    #[serde(default)]
+    pub timestamp_source: TimestampSource,
     pub signature: String,
     pub slot: u64,
+    /// Position among the transaction's events, in emission order
+    #[serde(default)]
+    pub log_index: u32,
 }
 
 /// Partial close event - exactly matches original Anchor structure
@@ -140,8 +432,11 @@ pub struct PartialCloseEvent {
     pub user_sol_account: String,
     pub mint_account: String,
     pub is_close_long: bool,
+    #[serde_as(as = "AmountU64")]
     pub final_token_amount: u64,
+    #[serde_as(as = "AmountU64")]
     pub final_sol_amount: u64,
+    #[serde_as(as = "AmountU64")]
     pub user_close_profit: u64,
     #[serde_as(as = "DisplayFromStr")]
     pub latest_price: u128,
@@ -154,18 +449,28 @@ pub struct PartialCloseEvent {
     pub lock_lp_start_price: u128,
     #[serde_as(as = "DisplayFromStr")]
     pub lock_lp_end_price: u128,
+    #[serde_as(as = "AmountU64")]
     pub lock_lp_sol_amount: u64,
+    #[serde_as(as = "AmountU64")]
     pub lock_lp_token_amount: u64,
     pub start_time: u32,
     pub end_time: u32,
+    #[serde_as(as = "AmountU64")]
     pub margin_sol_amount: u64,
+    #[serde_as(as = "AmountU64")]
     pub borrow_amount: u64,
+    #[serde_as(as = "AmountU64")]
     pub position_asset_amount: u64,
     pub borrow_fee: u16,
     #[schema(value_type = String)]
     pub timestamp: DateTime<Utc>,
+    #[serde(default)]
+    pub timestamp_source: TimestampSource,
     pub signature: String,
     pub slot: u64,
+    /// Position among the transaction's events, in emission order
+    #[serde(default)]
+    pub log_index: u32,
 }
 
 /// Milestone Discount event - exactly matches original Anchor structure
@@ -179,21 +484,289 @@ pub struct MilestoneDiscountEvent {
     pub fee_discount_flag: u8, // 手续费折扣标志 0: 原价 1: 5折 2: 2.5折  3: 1.25折
     #[schema(value_type = String)]
     pub timestamp: DateTime<Utc>,
+    #[serde(default)]
+    pub timestamp_source: TimestampSource,
     pub signature: String,
     pub slot: u64,
+    /// Position among the transaction's events, in emission order
+    #[serde(default)]
+    pub log_index: u32,
+}
+
+/// Maximum accepted byte lengths of the TokenCreated name/symbol/uri strings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenStringLimits {
+    pub max_name_len: usize,
+    pub max_symbol_len: usize,
+    pub max_uri_len: usize,
+}
+
+impl Default for TokenStringLimits {
+    fn default() -> Self {
+        Self {
+            max_name_len: DEFAULT_MAX_TOKEN_NAME_LEN,
+            max_symbol_len: DEFAULT_MAX_TOKEN_SYMBOL_LEN,
+            max_uri_len: DEFAULT_MAX_TOKEN_URI_LEN,
+        }
+    }
+}
+
+impl From<&SolanaConfig> for TokenStringLimits {
+    fn from(config: &SolanaConfig) -> Self {
+        Self {
+            max_name_len: config.max_token_name_len,
+            max_symbol_len: config.max_token_symbol_len,
+            max_uri_len: config.max_token_uri_len,
+        }
+    }
+}
+
+/// Minimum data lengths (after the discriminator) each event type must have
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventMinLengths {
+    pub token_created: usize,
+    pub buy_sell: usize,
+    pub long_short: usize,
+    pub force_liquidate: usize,
+    pub full_close: usize,
+    pub partial_close: usize,
+    pub milestone_discount: usize,
+}
+
+impl Default for EventMinLengths {
+    fn default() -> Self {
+        // Byte layouts the parsers read; overrides can only raise these
+        Self {
+            token_created: 261,
+            buy_sell: 97,
+            long_short: 259,
+            force_liquidate: 96,
+            full_close: 169,
+            partial_close: 316,
+            milestone_discount: 99,
+        }
+    }
+}
+
+impl EventMinLengths {
+    /// Minimum length of an event type by its snake_case name
+    pub fn get(&self, event_type: &str) -> usize {
+        match event_type {
+            "token_created" => self.token_created,
+            "buy_sell" => self.buy_sell,
+            "long_short" => self.long_short,
+            "force_liquidate" => self.force_liquidate,
+            "full_close" => self.full_close,
+            "partial_close" => self.partial_close,
+            "milestone_discount" => self.milestone_discount,
+            _ => 0,
+        }
+    }
+
+    /// Defaults with per-event-type overrides applied; values below the parsed layout are ignored
+    pub fn with_overrides(overrides: &HashMap<String, usize>) -> Self {
+        let mut lengths = Self::default();
+        for (event_type, &min_len) in overrides {
+            let slot = match event_type.as_str() {
+                "token_created" => &mut lengths.token_created,
+                "buy_sell" => &mut lengths.buy_sell,
+                "long_short" => &mut lengths.long_short,
+                "force_liquidate" => &mut lengths.force_liquidate,
+                "full_close" => &mut lengths.full_close,
+                "partial_close" => &mut lengths.partial_close,
+                "milestone_discount" => &mut lengths.milestone_discount,
+                _ => {
+                    warn!(
+                        "⚠️ Unknown event type in solana.event_min_lengths: {}",
+                        event_type
+                    );
+                    continue;
+                }
+            };
+            if min_len < *slot {
+                warn!(
+                    "⚠️ solana.event_min_lengths.{} = {} is below the parsed layout, using {}",
+                    event_type, min_len, *slot
+                );
+            } else {
+                *slot = min_len;
+            }
+        }
+        lengths
+    }
+}
+
+impl From<&SolanaConfig> for EventMinLengths {
+    fn from(config: &SolanaConfig) -> Self {
+        Self::with_overrides(&config.event_min_lengths)
+    }
+}
+
+/// Discriminator and snake_case name of every event type, in parser-stats order
+const EVENT_TYPES: [([u8; 8], &str); 7] = [
+    (TOKEN_CREATED_EVENT_DISCRIMINATOR, "token_created"),
+    (BUY_SELL_EVENT_DISCRIMINATOR, "buy_sell"),
+    (LONG_SHORT_EVENT_DISCRIMINATOR, "long_short"),
+    (FORCE_LIQUIDATE_EVENT_DISCRIMINATOR, "force_liquidate"),
+    (FULL_CLOSE_EVENT_DISCRIMINATOR, "full_close"),
+    (PARTIAL_CLOSE_EVENT_DISCRIMINATOR, "partial_close"),
+    (MILESTONE_DISCOUNT_EVENT_DISCRIMINATOR, "milestone_discount"),
+];
+
+/// Running counters of what the parser saw, shared by all clones of an `EventParser`
+#[derive(Debug, Default)]
+pub struct ParserStats {
+    data_lines: AtomicU64,
+    parsed: [AtomicU64; 7],
+    unknown_discriminator: AtomicU64,
+    length_insufficient: [AtomicU64; 7],
+}
+
+impl ParserStats {
+    pub fn snapshot(&self) -> ParserStatsSnapshot {
+        let per_type = |counters: &[AtomicU64; 7]| {
+            EVENT_TYPES
+                .iter()
+                .zip(counters)
+                .map(|((_, name), count)| (name.to_string(), count.load(Ordering::Relaxed)))
+                .collect()
+        };
+        ParserStatsSnapshot {
+            data_lines: self.data_lines.load(Ordering::Relaxed),
+            parsed: per_type(&self.parsed),
+            unknown_discriminator: self.unknown_discriminator.load(Ordering::Relaxed),
+            length_insufficient: per_type(&self.length_insufficient),
+        }
+    }
+}
+
+/// Point-in-time copy of the parser counters
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ParserStatsSnapshot {
+    /// `Program data:` lines seen inside the target program
+    pub data_lines: u64,
+    /// Successfully parsed events per event type
+    pub parsed: BTreeMap<String, u64>,
+    /// Data whose discriminator matched no known event (or was shorter than one)
+    pub unknown_discriminator: u64,
+    /// Events rejected for being shorter than the minimum length, per event type
+    pub length_insufficient: BTreeMap<String, u64>,
+}
+
+impl ParserStatsSnapshot {
+    /// Counters in the Prometheus text format
+    pub fn to_prometheus(&self) -> String {
+        let mut output = format!(
+            "# TYPE spin_parser_data_lines_total counter\nspin_parser_data_lines_total {}\n\
+             # TYPE spin_parser_unknown_discriminator_total counter\nspin_parser_unknown_discriminator_total {}\n",
+            self.data_lines, self.unknown_discriminator
+        );
+        for (name, counts) in [
+            ("parsed", &self.parsed),
+            ("length_insufficient", &self.length_insufficient),
+        ] {
+            output.push_str(&format!("# TYPE spin_parser_{name}_total counter\n"));
+            for (event_type, count) in counts {
+                output.push_str(&format!(
+                    "spin_parser_{name}_total{{type=\"{event_type}\"}} {count}\n"
+                ));
+            }
+        }
+        output
+    }
 }
 
 /// Event parser
 #[derive(Clone)]
 pub struct EventParser {
-    #[allow(dead_code)]
     pub program_id: Pubkey,
+    string_limits: TokenStringLimits,
+    min_lengths: EventMinLengths,
+    verify_pdas: bool,
+    stats: Arc<ParserStats>,
 }
 
 impl EventParser {
     pub fn new(program_id: &str) -> anyhow::Result<Self> {
         let program_id = program_id.parse::<Pubkey>()?;
-        Ok(Self { program_id })
+        Ok(Self {
+            program_id,
+            string_limits: TokenStringLimits::default(),
+            min_lengths: EventMinLengths::default(),
+            verify_pdas: false,
+            stats: Arc::new(ParserStats::default()),
+        })
+    }
+
+    /// Counters shared by this parser and its clones
+    pub fn stats(&self) -> Arc<ParserStats> {
+        Arc::clone(&self.stats)
+    }
+
+    /// Replace the TokenCreated string length caps
+    pub fn with_string_limits(mut self, string_limits: TokenStringLimits) -> Self {
+        self.string_limits = string_limits;
+        self
+    }
+
+    /// Replace the per-event-type minimum data lengths
+    pub fn with_min_lengths(mut self, min_lengths: EventMinLengths) -> Self {
+        self.min_lengths = min_lengths;
+        self
+    }
+
+    /// Reject events whose curve/pool accounts are not derived from the program id
+    pub fn with_pda_verification(mut self, verify_pdas: bool) -> Self {
+        self.verify_pdas = verify_pdas;
+        self
+    }
+
+    /// Check the event's curve/pool accounts against the PDAs of its mint. Only accounts whose
+    /// seeds are all in the event can be checked: order PDAs use a client-chosen seed
+    pub fn verify_event_pdas(&self, event: &SpinPetEvent) -> anyhow::Result<()> {
+        let accounts: Vec<(&str, &[u8], &str)> = match event {
+            SpinPetEvent::TokenCreated(e) => vec![
+                (
+                    "curve_account",
+                    CURVE_ACCOUNT_SEED,
+                    e.curve_account.as_str(),
+                ),
+                (
+                    "pool_token_account",
+                    POOL_TOKEN_ACCOUNT_SEED,
+                    e.pool_token_account.as_str(),
+                ),
+                (
+                    "pool_sol_account",
+                    POOL_SOL_ACCOUNT_SEED,
+                    e.pool_sol_account.as_str(),
+                ),
+            ],
+            SpinPetEvent::MilestoneDiscount(e) => {
+                vec![(
+                    "curve_account",
+                    CURVE_ACCOUNT_SEED,
+                    e.curve_account.as_str(),
+                )]
+            }
+            _ => return Ok(()),
+        };
+
+        let mint = event.mint_account().parse::<Pubkey>()?;
+        for (name, seed, account) in accounts {
+            let (expected, _) =
+                Pubkey::find_program_address(&[seed, mint.as_ref()], &self.program_id);
+            if account != expected.to_string() {
+                anyhow::bail!(
+                    "{} {} is not the program PDA {} for mint {}",
+                    name,
+                    account,
+                    expected,
+                    mint
+                );
+            }
+        }
+        Ok(())
     }
 
     /// Parse events with call stack tracking to capture CPI events
@@ -251,6 +824,7 @@ impl EventParser {
             // Parse "Program data:" logs when in target program context
             if in_target_program && log.starts_with("Program data:") {
                 debug!("Found Program data in target program context at log[{}]", i);
+                self.stats.data_lines.fetch_add(1, Ordering::Relaxed);
 
                 if let Some(data_part) = log.strip_prefix("Program data: ") {
                     let data_part = data_part.trim();
@@ -262,11 +836,23 @@ impl EventParser {
 
                             // Parse event from data
                             match self.parse_event_data(&data, signature, slot) {
-                                Ok(Some(event)) => {
+                                Ok(Some(mut event)) => {
+                                    if self.verify_pdas {
+                                        if let Err(e) = self.verify_event_pdas(&event) {
+                                            warn!(
+                                                "🚫 Rejected {} event in {}: {}",
+                                                event.type_name(),
+                                                signature,
+                                                e
+                                            );
+                                            continue;
+                                        }
+                                    }
                                     debug!(
                                         "Successfully parsed event from CPI context: {:?}",
                                         event
                                     );
+                                    event.set_log_index(events.len() as u32);
                                     events.push(event);
                                 }
                                 Ok(None) => {
@@ -301,8 +887,35 @@ impl EventParser {
         None
     }
 
-    /// Parse event data
-    fn parse_event_data(
+    /// Parse event data, recording the outcome in the parser stats
+    pub(crate) fn parse_event_data(
+        &self,
+        data: &[u8],
+        signature: &str,
+        slot: u64,
+    ) -> anyhow::Result<Option<SpinPetEvent>> {
+        let result = self.decode_event_data(data, signature, slot);
+        let event_type = EVENT_TYPES
+            .iter()
+            .position(|(discriminator, _)| data.get(0..8) == Some(&discriminator[..]));
+        match (event_type, &result) {
+            (None, _) => {
+                self.stats
+                    .unknown_discriminator
+                    .fetch_add(1, Ordering::Relaxed);
+            }
+            (Some(i), Ok(Some(_))) => {
+                self.stats.parsed[i].fetch_add(1, Ordering::Relaxed);
+            }
+            (Some(i), Err(_)) if data.len() - 8 < self.min_lengths.get(EVENT_TYPES[i].1) => {
+                self.stats.length_insufficient[i].fetch_add(1, Ordering::Relaxed);
+            }
+            _ => {}
+        }
+        result
+    }
+
+    fn decode_event_data(
         &self,
         data: &[u8],
         signature: &str,
@@ -331,7 +944,7 @@ impl EventParser {
                 debug!("🪙 Matched TokenCreatedEvent, discriminator: {:?}", d);
                 let event =
                     self.parse_token_created_event(event_data, signature, slot, timestamp)?;
-                Ok(Some(SpinPetEvent::TokenCreated(event)))
+                Ok(event.map(SpinPetEvent::TokenCreated))
             }
             d if d == BUY_SELL_EVENT_DISCRIMINATOR => {
                 debug!("💰 Matched BuySellEvent, discriminator: {:?}", d);
@@ -380,15 +993,16 @@ impl EventParser {
         signature: &str,
         slot: u64,
         timestamp: DateTime<Utc>,
-    ) -> anyhow::Result<TokenCreatedEvent> {
+    ) -> anyhow::Result<Option<TokenCreatedEvent>> {
         debug!(
             "🪙 Starting to parse TokenCreatedEvent, data length: {}",
             data.len()
         );
 
-        if data.len() < 261 {
+        if data.len() < self.min_lengths.token_created {
             return Err(anyhow::anyhow!(
-                "TokenCreatedEvent data length insufficient, need at least 261 bytes, actual: {}",
+                "TokenCreatedEvent data length insufficient, need at least {} bytes, actual: {}",
+                self.min_lengths.token_created,
                 data.len()
             ));
         }
@@ -460,91 +1074,28 @@ impl EventParser {
             offset
         );
 
-        // Parse name
-        if offset + 4 > data.len() {
-            return Err(anyhow::anyhow!(
-                "Data insufficient to read name length, offset: {}, data length: {}",
-                offset,
-                data.len()
-            ));
-        }
-        let name_len = u32::from_le_bytes(
-            data[offset..offset + 4]
-                .try_into()
-                .map_err(|e| anyhow::anyhow!("Failed to parse name length: {}", e))?,
-        ) as usize;
-        offset += 4;
-        debug!("🔍 name length: {}", name_len);
-
-        if offset + name_len > data.len() {
-            return Err(anyhow::anyhow!(
-                "Data insufficient to read name data, need: {}, remaining: {}",
-                name_len,
-                data.len() - offset
-            ));
-        }
-        let name = String::from_utf8(data[offset..offset + name_len].to_vec())
-            .map_err(|e| anyhow::anyhow!("Failed to parse name string: {}", e))?;
-        offset += name_len;
-        debug!("✅ name: {}", name);
-
-        // Parse symbol
-        if offset + 4 > data.len() {
-            return Err(anyhow::anyhow!(
-                "Data insufficient to read symbol length, offset: {}, data length: {}",
-                offset,
-                data.len()
-            ));
-        }
-        let symbol_len = u32::from_le_bytes(
-            data[offset..offset + 4]
-                .try_into()
-                .map_err(|e| anyhow::anyhow!("Failed to parse symbol length: {}", e))?,
-        ) as usize;
-        offset += 4;
-        debug!("🔍 symbol length: {}", symbol_len);
-
-        if offset + symbol_len > data.len() {
-            return Err(anyhow::anyhow!(
-                "Data insufficient to read symbol data, need: {}, remaining: {}",
-                symbol_len,
-                data.len() - offset
-            ));
-        }
-        let symbol = String::from_utf8(data[offset..offset + symbol_len].to_vec())
-            .map_err(|e| anyhow::anyhow!("Failed to parse symbol string: {}", e))?;
-        offset += symbol_len;
-        debug!("✅ symbol: {}", symbol);
-
-        // Parse uri
-        if offset + 4 > data.len() {
-            return Err(anyhow::anyhow!(
-                "Data insufficient to read uri length, offset: {}, data length: {}",
-                offset,
-                data.len()
-            ));
-        }
-        let uri_len = u32::from_le_bytes(
-            data[offset..offset + 4]
-                .try_into()
-                .map_err(|e| anyhow::anyhow!("Failed to parse uri length: {}", e))?,
-        ) as usize;
-        offset += 4;
-        debug!("🔍 uri length: {}", uri_len);
-
-        if offset + uri_len > data.len() {
-            return Err(anyhow::anyhow!(
-                "Data insufficient to read uri data, need: {}, remaining: {}",
-                uri_len,
-                data.len() - offset
-            ));
-        }
-        let uri = String::from_utf8(data[offset..offset + uri_len].to_vec())
-            .map_err(|e| anyhow::anyhow!("Failed to parse uri string: {}", e))?;
-        debug!("✅ uri: {}", uri);
+        let Some(name) =
+            Self::read_borsh_string(data, &mut offset, "name", self.string_limits.max_name_len)?
+        else {
+            return Ok(None);
+        };
+        let Some(symbol) = Self::read_borsh_string(
+            data,
+            &mut offset,
+            "symbol",
+            self.string_limits.max_symbol_len,
+        )?
+        else {
+            return Ok(None);
+        };
+        let Some(uri) =
+            Self::read_borsh_string(data, &mut offset, "uri", self.string_limits.max_uri_len)?
+        else {
+            return Ok(None);
+        };
 
         debug!("🎉 TokenCreatedEvent parsed");
-        Ok(TokenCreatedEvent {
+        Ok(Some(TokenCreatedEvent {
             payer: payer.to_string(),
             mint_account: mint_account.to_string(),
             curve_account: curve_account.to_string(),
@@ -560,9 +1111,57 @@ impl EventParser {
             borrow_fee,
             fee_discount_flag,
             timestamp,
+            timestamp_source: TimestampSource::ServerReceive,
+            log_index: 0,
             signature: signature.to_string(),
             slot,
-        })
+        }))
+    }
+
+    /// Read a Borsh string (4-byte length + UTF-8 data) at `offset`, advancing it past the string.
+    /// Returns None (after logging) when the length prefix exceeds `max_len`.
+    fn read_borsh_string(
+        data: &[u8],
+        offset: &mut usize,
+        field: &str,
+        max_len: usize,
+    ) -> anyhow::Result<Option<String>> {
+        if *offset + 4 > data.len() {
+            return Err(anyhow::anyhow!(
+                "Data insufficient to read {} length, offset: {}, data length: {}",
+                field,
+                offset,
+                data.len()
+            ));
+        }
+        let len = u32::from_le_bytes(
+            data[*offset..*offset + 4]
+                .try_into()
+                .map_err(|e| anyhow::anyhow!("Failed to parse {} length: {}", field, e))?,
+        ) as usize;
+        *offset += 4;
+        debug!("🔍 {} length: {}", field, len);
+
+        if len > max_len {
+            warn!(
+                "⚠️ TokenCreatedEvent {} length {} exceeds limit {}, skipping event",
+                field, len, max_len
+            );
+            return Ok(None);
+        }
+        if *offset + len > data.len() {
+            return Err(anyhow::anyhow!(
+                "Data insufficient to read {} data, need: {}, remaining: {}",
+                field,
+                len,
+                data.len() - *offset
+            ));
+        }
+        let value = String::from_utf8(data[*offset..*offset + len].to_vec())
+            .map_err(|e| anyhow::anyhow!("Failed to parse {} string: {}", field, e))?;
+        *offset += len;
+        debug!("✅ {}: {}", field, value);
+        Ok(Some(value))
     }
 
     /// Parse BuySellEvent
@@ -578,9 +1177,10 @@ impl EventParser {
             data.len()
         );
 
-        if data.len() < 97 {
+        if data.len() < self.min_lengths.buy_sell {
             return Err(anyhow::anyhow!(
-                "BuySellEvent data length insufficient, need at least 97 bytes, actual: {}",
+                "BuySellEvent data length insufficient, need at least {} bytes, actual: {}",
+                self.min_lengths.buy_sell,
                 data.len()
             ));
         }
@@ -632,6 +1232,8 @@ impl EventParser {
             sol_amount,
             latest_price,
             timestamp,
+            timestamp_source: TimestampSource::ServerReceive,
+            log_index: 0,
             signature: signature.to_string(),
             slot,
         })
@@ -650,9 +1252,10 @@ impl EventParser {
             data.len()
         );
 
-        if data.len() < 259 {
+        if data.len() < self.min_lengths.long_short {
             return Err(anyhow::anyhow!(
-                "LongShortEvent data length insufficient, need at least 259 bytes, actual: {}",
+                "LongShortEvent data length insufficient, need at least {} bytes, actual: {}",
+                self.min_lengths.long_short,
                 data.len()
             ));
         }
@@ -794,6 +1397,8 @@ impl EventParser {
             position_asset_amount,
             borrow_fee,
             timestamp,
+            timestamp_source: TimestampSource::ServerReceive,
+            log_index: 0,
             signature: signature.to_string(),
             slot,
         })
@@ -812,9 +1417,10 @@ impl EventParser {
             data.len()
         );
 
-        if data.len() < 96 {
+        if data.len() < self.min_lengths.force_liquidate {
             return Err(anyhow::anyhow!(
-                "ForceLiquidateEvent data length insufficient, need at least 96 bytes, actual: {}",
+                "ForceLiquidateEvent data length insufficient, need at least {} bytes, actual: {}",
+                self.min_lengths.force_liquidate,
                 data.len()
             ));
         }
@@ -840,6 +1446,8 @@ impl EventParser {
             mint_account: mint_account.to_string(),
             order_pda: order_pda.to_string(),
             timestamp,
+            timestamp_source: TimestampSource::ServerReceive,
+            log_index: 0,
             signature: signature.to_string(),
             slot,
         })
@@ -858,9 +1466,10 @@ impl EventParser {
             data.len()
         );
 
-        if data.len() < 169 {
+        if data.len() < self.min_lengths.full_close {
             return Err(anyhow::anyhow!(
-                "FullCloseEvent data length insufficient, need at least 169 bytes, actual: {}",
+                "FullCloseEvent data length insufficient, need at least {} bytes, actual: {}",
+                self.min_lengths.full_close,
                 data.len()
             ));
         }
@@ -933,6 +1542,8 @@ impl EventParser {
             latest_price,
             order_pda: order_pda.to_string(),
             timestamp,
+            timestamp_source: TimestampSource::ServerReceive,
+            log_index: 0,
             signature: signature.to_string(),
             slot,
         })
@@ -951,9 +1562,10 @@ impl EventParser {
             data.len()
         );
 
-        if data.len() < 316 {
+        if data.len() < self.min_lengths.partial_close {
             return Err(anyhow::anyhow!(
-                "PartialCloseEvent data length insufficient, need at least 316 bytes, actual: {}",
+                "PartialCloseEvent data length insufficient, need at least {} bytes, actual: {}",
+                self.min_lengths.partial_close,
                 data.len()
             ));
         }
@@ -1133,6 +1745,8 @@ impl EventParser {
             position_asset_amount,
             borrow_fee,
             timestamp,
+            timestamp_source: TimestampSource::ServerReceive,
+            log_index: 0,
             signature: signature.to_string(),
             slot,
         })
@@ -1151,8 +1765,9 @@ impl EventParser {
             data.len()
         );
 
-        if data.len() < 99 {
-            return Err(anyhow::anyhow!("MilestoneDiscountEvent data length insufficient, need at least 99 bytes, actual: {}", data.len()));
+        if data.len() < self.min_lengths.milestone_discount {
+            return Err(anyhow::anyhow!("MilestoneDiscountEvent data length insufficient, need at least {} bytes, actual: {}",
+ self.min_lengths.milestone_discount, data.len()));
         }
 
         debug!("🔍 Parsing payer (0..32)");
@@ -1199,6 +1814,8 @@ impl EventParser {
             borrow_fee,
             fee_discount_flag,
             timestamp,
+            timestamp_source: TimestampSource::ServerReceive,
+            log_index: 0,
             signature: signature.to_string(),
             slot,
         })
@@ -1209,6 +1826,25 @@ impl EventParser {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_order_type_round_trips_raw_values() {
+        for order_type in [OrderType::Long, OrderType::Short] {
+            assert_eq!(OrderType::from_raw(order_type.raw()), order_type);
+            assert_eq!(
+                OrderType::from_query_name(order_type.query_name()),
+                Some(order_type)
+            );
+            assert_eq!(OrderType::from_close_long(order_type.is_long()), order_type);
+        }
+        assert_eq!(OrderType::from_raw(1).key_segment(), "dn");
+        assert_eq!(OrderType::from_raw(2).key_segment(), "up");
+        // Unknown raw values keep the historical "dn" keying
+        assert_eq!(OrderType::from_raw(0), OrderType::Long);
+        assert_eq!(OrderType::try_from_raw(0), None);
+        assert_eq!(OrderType::try_from_raw(2), Some(OrderType::Short));
+        assert_eq!(OrderType::from_query_name("sideways"), None);
+    }
+
     #[test]
     fn test_event_discriminator_constants() {
         // Test discriminator constants from IDL file
@@ -1239,4 +1875,236 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_min_length_override_accepts_longer_buy_sell() {
+        use crate::solana::selftest::PARSER_FIXTURES;
+
+        let fixture = PARSER_FIXTURES
+            .iter()
+            .find(|f| f.variant == "BuySell")
+            .unwrap();
+        let mut data = base64::engine::general_purpose::STANDARD
+            .decode(fixture.data_base64)
+            .unwrap();
+        // A new optional trailing field appended by the program
+        data.extend_from_slice(&42u64.to_le_bytes());
+
+        let overrides = HashMap::from([
+            ("buy_sell".to_string(), 105),
+            // Lowering below the parsed layout would read out of bounds, so it is ignored
+            ("long_short".to_string(), 10),
+        ]);
+        let min_lengths = EventMinLengths::with_overrides(&overrides);
+        assert_eq!(min_lengths.buy_sell, 105);
+        assert_eq!(min_lengths.long_short, 259);
+
+        let parser = EventParser::new("JBMmrp6jhksqnxDBskkmVvWHhJLaPBjgiMHEroJbUTBZ")
+            .unwrap()
+            .with_min_lengths(min_lengths);
+        let event = parser.parse_event_data(&data, "sig", 1).unwrap();
+        assert!(matches!(event, Some(SpinPetEvent::BuySell(_))));
+
+        // A buffer without the new field is now rejected with the configured length
+        let err = parser
+            .parse_event_data(&data[..data.len() - 8], "sig", 1)
+            .unwrap_err();
+        assert!(err.to_string().contains("need at least 105 bytes"));
+    }
+
+    #[test]
+    fn test_oversized_token_string_skips_event() {
+        use crate::solana::selftest::PARSER_FIXTURES;
+
+        let parser = EventParser::new("JBMmrp6jhksqnxDBskkmVvWHhJLaPBjgiMHEroJbUTBZ").unwrap();
+        let fixture_base64 = |variant: &str| {
+            PARSER_FIXTURES
+                .iter()
+                .find(|f| f.variant == variant)
+                .unwrap()
+                .data_base64
+        };
+        let mut data = base64::engine::general_purpose::STANDARD
+            .decode(fixture_base64("TokenCreated"))
+            .unwrap();
+
+        // Absurd name length prefix right after the discriminator and fixed fields
+        data[8 + 261..8 + 265].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(parser.parse_event_data(&data, "sig", 1).unwrap().is_none());
+
+        // The rest of the transaction still parses
+        let logs = vec![
+            "Program JBMmrp6jhksqnxDBskkmVvWHhJLaPBjgiMHEroJbUTBZ invoke [1]".to_string(),
+            format!(
+                "Program data: {}",
+                base64::engine::general_purpose::STANDARD.encode(&data)
+            ),
+            format!("Program data: {}", fixture_base64("BuySell")),
+            "Program JBMmrp6jhksqnxDBskkmVvWHhJLaPBjgiMHEroJbUTBZ success".to_string(),
+        ];
+        let events = parser
+            .parse_events_with_call_stack(&logs, "sig", 1)
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0], SpinPetEvent::BuySell(_)));
+
+        // A length that fits in the data but exceeds a configured cap is skipped too
+        let original = base64::engine::general_purpose::STANDARD
+            .decode(fixture_base64("TokenCreated"))
+            .unwrap();
+        let strict = parser.clone().with_string_limits(TokenStringLimits {
+            max_symbol_len: 3,
+            ..TokenStringLimits::default()
+        });
+        assert!(strict
+            .parse_event_data(&original, "sig", 1)
+            .unwrap()
+            .is_none());
+        assert!(parser
+            .parse_event_data(&original, "sig", 1)
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn test_non_derivable_pda_rejected_when_verification_on() {
+        use crate::solana::selftest::PARSER_FIXTURES;
+
+        let engine = base64::engine::general_purpose::STANDARD;
+        let parser = EventParser::new("JBMmrp6jhksqnxDBskkmVvWHhJLaPBjgiMHEroJbUTBZ").unwrap();
+        let mut data = engine
+            .decode(
+                PARSER_FIXTURES
+                    .iter()
+                    .find(|f| f.variant == "TokenCreated")
+                    .unwrap()
+                    .data_base64,
+            )
+            .unwrap();
+
+        // Accounts at 64..160 after the discriminator, derived from the mint at 32..64
+        let mint = Pubkey::try_from(&data[8 + 32..8 + 64]).unwrap();
+        for (offset, seed) in [
+            (64, CURVE_ACCOUNT_SEED),
+            (96, POOL_TOKEN_ACCOUNT_SEED),
+            (128, POOL_SOL_ACCOUNT_SEED),
+        ] {
+            let (pda, _) = Pubkey::find_program_address(&[seed, mint.as_ref()], &parser.program_id);
+            data[8 + offset..8 + offset + 32].copy_from_slice(pda.as_ref());
+        }
+        let mut spoofed = data.clone();
+        spoofed[8 + 64..8 + 96].copy_from_slice(Pubkey::new_unique().as_ref());
+
+        let logs = |data: &[u8]| {
+            vec![
+                "Program JBMmrp6jhksqnxDBskkmVvWHhJLaPBjgiMHEroJbUTBZ invoke [1]".to_string(),
+                format!("Program data: {}", engine.encode(data)),
+                "Program JBMmrp6jhksqnxDBskkmVvWHhJLaPBjgiMHEroJbUTBZ success".to_string(),
+            ]
+        };
+        let verifying = parser.clone().with_pda_verification(true);
+        assert_eq!(
+            verifying
+                .parse_events_with_call_stack(&logs(&data), "sig", 1)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(verifying
+            .parse_events_with_call_stack(&logs(&spoofed), "sig", 1)
+            .unwrap()
+            .is_empty());
+
+        // Without verification the spoofed account is accepted as before
+        assert_eq!(
+            parser
+                .parse_events_with_call_stack(&logs(&spoofed), "sig", 1)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_parser_stats_count_outcomes_per_type() {
+        use crate::solana::selftest::PARSER_FIXTURES;
+
+        let parser = EventParser::new("JBMmrp6jhksqnxDBskkmVvWHhJLaPBjgiMHEroJbUTBZ").unwrap();
+        let engine = base64::engine::general_purpose::STANDARD;
+        let buy_sell = PARSER_FIXTURES
+            .iter()
+            .find(|f| f.variant == "BuySell")
+            .unwrap()
+            .data_base64;
+        let truncated = {
+            let data = engine.decode(buy_sell).unwrap();
+            engine.encode(&data[..data.len() - 1])
+        };
+        let mut unknown = vec![1u8; 8];
+        unknown.extend_from_slice(&[0u8; 32]);
+
+        let logs = vec![
+            "Program JBMmrp6jhksqnxDBskkmVvWHhJLaPBjgiMHEroJbUTBZ invoke [1]".to_string(),
+            format!("Program data: {}", buy_sell),
+            format!("Program data: {}", buy_sell),
+            format!("Program data: {}", engine.encode(&unknown)),
+            format!("Program data: {}", truncated),
+            "Program JBMmrp6jhksqnxDBskkmVvWHhJLaPBjgiMHEroJbUTBZ success".to_string(),
+            // Outside the target program, not counted
+            format!("Program data: {}", buy_sell),
+        ];
+        // Clones share the counters
+        let events = parser
+            .clone()
+            .parse_events_with_call_stack(&logs, "sig", 1)
+            .unwrap();
+        assert_eq!(events.len(), 2);
+
+        let stats = parser.stats().snapshot();
+        assert_eq!(stats.data_lines, 4);
+        assert_eq!(stats.parsed["buy_sell"], 2);
+        assert_eq!(stats.parsed["long_short"], 0);
+        assert_eq!(stats.unknown_discriminator, 1);
+        assert_eq!(stats.length_insufficient["buy_sell"], 1);
+        assert_eq!(stats.parsed.len(), 7);
+
+        let metrics = stats.to_prometheus();
+        assert!(metrics.contains("spin_parser_data_lines_total 4\n"));
+        assert!(metrics.contains("spin_parser_parsed_total{type=\"buy_sell\"} 2\n"));
+        assert!(metrics.contains("spin_parser_unknown_discriminator_total 1\n"));
+        assert!(metrics.contains("spin_parser_length_insufficient_total{type=\"buy_sell\"} 1\n"));
+    }
+
+    #[tokio::test]
+    async fn test_amounts_serialize_as_numbers_and_parse_from_strings() {
+        let event = BuySellEvent {
+            payer: "payer".to_string(),
+            mint_account: "mint".to_string(),
+            is_buy: true,
+            token_amount: u64::MAX,
+            sol_amount: (1 << 53) + 1,
+            latest_price: 300,
+            timestamp: Utc::now(),
+            timestamp_source: TimestampSource::ServerReceive,
+            log_index: 0,
+            signature: "sig".to_string(),
+            slot: 42,
+        };
+
+        let json = serde_json::to_value(&event).unwrap();
+        assert!(json["sol_amount"].is_u64());
+
+        // The `server.bigint_as_string` response form still reads back exactly
+        let response = crate::models::ApiResponse::success(event);
+        let mut json = crate::utils::with_amounts_as_strings(async {
+            serde_json::to_value(&response).unwrap()
+        })
+        .await;
+        let json = json["data"].take();
+        assert_eq!(json["token_amount"], u64::MAX.to_string());
+        assert_eq!(json["sol_amount"], "9007199254740993");
+        let decoded: BuySellEvent = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.token_amount, u64::MAX);
+        assert_eq!(decoded.sol_amount, (1 << 53) + 1);
+    }
 }
@@ -1,7 +1,7 @@
 #![allow(dead_code)]
 
 use super::client::SolanaClient;
-use super::events::{EventParser, SpinPetEvent};
+use super::events::{EventMinLengths, EventParser, OrderType, SpinPetEvent, TokenStringLimits};
 use crate::config::SolanaConfig;
 use async_trait::async_trait;
 use futures_util::{SinkExt, StreamExt};
@@ -60,7 +60,11 @@ impl EventHandler for DefaultEventHandler {
                 info!("   - Block height: {}", e.slot);
             }
             SpinPetEvent::LongShort(e) => {
-                let direction = if e.order_type == 1 { "long" } else { "short" };
+                let direction = if OrderType::from_raw(e.order_type).is_long() {
+                    "long"
+                } else {
+                    "short"
+                };
                 info!(
                     "📈 Long/Short event: {} went {} on token {} (order PDA: {})",
                     e.payer, direction, e.mint_account, e.order_pda
@@ -157,7 +161,10 @@ impl SolanaEventListener {
         client: Arc<SolanaClient>,
         event_handler: Arc<dyn EventHandler>,
     ) -> anyhow::Result<Self> {
-        let event_parser = EventParser::new(&config.program_id)?;
+        let event_parser = EventParser::new(&config.program_id)?
+            .with_string_limits(TokenStringLimits::from(&config))
+            .with_min_lengths(EventMinLengths::from(&config))
+            .with_pda_verification(config.verify_pdas);
         let (event_sender, event_receiver) = mpsc::unbounded_channel();
         let (reconnect_sender, reconnect_receiver) = mpsc::unbounded_channel();
 
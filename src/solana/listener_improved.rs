@@ -1,24 +1,61 @@
-use super::client::SolanaClient;
-use super::events::{EventParser, SpinPetEvent};
-use crate::config::SolanaConfig;
+use super::client::{SolanaClient, TransactionFetcher};
+use super::events::{
+    EventMinLengths, EventParser, OrderType, ParserStats, SpinPetEvent, TimestampSource,
+    TokenStringLimits,
+};
+use crate::config::{SolanaConfig, TimestampSourceMode};
 use async_trait::async_trait;
-use futures_util::{SinkExt, StreamExt};
+use chrono::{DateTime, TimeZone, Utc};
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
 use rand;
+use serde::Serialize;
 use serde_json::{json, Value};
-use std::collections::HashSet;
+use std::collections::{HashSet, VecDeque};
+use std::future::Future;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use tokio::sync::{broadcast, mpsc};
+use tokio::sync::{broadcast, mpsc, Semaphore};
 use tokio::time::{interval, sleep, Duration};
-use tokio_tungstenite::{connect_async, tungstenite::Message};
+use tokio_tungstenite::{connect_async, tungstenite, tungstenite::Message};
 use tracing::{debug, error, info, warn};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
+/// Outcome of a message read while waiting for the logsSubscribe reply
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubscribeReply {
+    /// The subscription was accepted; holds the subscription id
+    Confirmed(Value),
+    /// The RPC answered the request with a JSON-RPC error
+    Failed(String),
+    /// Not the reply to the subscribe request
+    Unrelated,
+}
+
+/// Classify `message` as the reply to the subscribe request with id `request_id`
+pub fn classify_subscribe_reply(message: &Value, request_id: &str) -> SubscribeReply {
+    if message.get("id").and_then(|id| id.as_str()) != Some(request_id) {
+        return SubscribeReply::Unrelated;
+    }
+    if let Some(error) = message.get("error") {
+        let code = error.get("code").and_then(|c| c.as_i64()).unwrap_or(0);
+        let text = error
+            .get("message")
+            .and_then(|m| m.as_str())
+            .unwrap_or("unknown error");
+        return SubscribeReply::Failed(format!("{} ({})", text, code));
+    }
+    match message.get("result") {
+        Some(result) => SubscribeReply::Confirmed(result.clone()),
+        None => SubscribeReply::Failed("reply has neither result nor error".to_string()),
+    }
+}
+
 /// Event listener trait
 #[async_trait]
 pub trait EventListener {
     async fn start(&mut self) -> anyhow::Result<()>;
-    #[allow(dead_code)]
     async fn stop(&mut self) -> anyhow::Result<()>;
     fn is_running(&self) -> bool;
 }
@@ -28,6 +65,17 @@ pub trait EventListener {
 pub trait EventHandler: Send + Sync {
     async fn handle_event(&self, event: SpinPetEvent) -> anyhow::Result<()>;
 
+    /// Handle an event re-ingested by replay/backfill; handlers that push to live clients skip it
+    async fn handle_replayed_event(&self, event: SpinPetEvent) -> anyhow::Result<()> {
+        self.handle_event(event).await
+    }
+
+    /// Called before a backfill applies its (slot-ordered) events
+    fn begin_backfill(&self) {}
+
+    /// Called once a backfill has finished, whether or not it succeeded
+    fn end_backfill(&self) {}
+
     /// Downcast support for trait objects
     fn as_any(&self) -> &dyn std::any::Any;
 }
@@ -61,7 +109,11 @@ impl EventHandler for DefaultEventHandler {
                 info!("   - Block height: {}", e.slot);
             }
             SpinPetEvent::LongShort(e) => {
-                let direction = if e.order_type == 1 { "long" } else { "short" };
+                let direction = if OrderType::from_raw(e.order_type).is_long() {
+                    "long"
+                } else {
+                    "short"
+                };
                 info!(
                     "📈 Long/Short event: {} went {} on token {} (order PDA: {})",
                     e.payer, direction, e.mint_account, e.order_pda
@@ -130,14 +182,169 @@ impl EventHandler for DefaultEventHandler {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
-enum ConnectionState {
+/// Runs a chain of handlers in order; a failing handler is logged and does not stop the rest
+pub struct CompositeEventHandler {
+    handlers: Vec<Arc<dyn EventHandler>>,
+}
+
+impl CompositeEventHandler {
+    pub fn new(handlers: Vec<Arc<dyn EventHandler>>) -> Self {
+        Self { handlers }
+    }
+
+    /// Handlers in dispatch order
+    pub fn handlers(&self) -> &[Arc<dyn EventHandler>] {
+        &self.handlers
+    }
+}
+
+#[async_trait]
+impl EventHandler for CompositeEventHandler {
+    async fn handle_event(&self, event: SpinPetEvent) -> anyhow::Result<()> {
+        for (index, handler) in self.handlers.iter().enumerate() {
+            if let Err(e) = handler.handle_event(event.clone()).await {
+                error!("❌ Event handler #{} failed: {}", index, e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn handle_replayed_event(&self, event: SpinPetEvent) -> anyhow::Result<()> {
+        for (index, handler) in self.handlers.iter().enumerate() {
+            if let Err(e) = handler.handle_replayed_event(event.clone()).await {
+                error!(
+                    "❌ Event handler #{} failed on replayed event: {}",
+                    index, e
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn begin_backfill(&self) {
+        for handler in &self.handlers {
+            handler.begin_backfill();
+        }
+    }
+
+    fn end_backfill(&self) {
+        for handler in &self.handlers {
+            handler.end_backfill();
+        }
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// State of the listener's WebSocket connection (the RPC client tracks its own `ConnectionState`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, ToSchema)]
+pub enum ListenerConnectionState {
+    #[default]
     Disconnected,
     Connecting,
     Connected,
     Reconnecting,
 }
 
+/// One listener connection state change
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct ConnectionEvent {
+    #[schema(value_type = String)]
+    pub timestamp: DateTime<Utc>,
+    pub state: ListenerConnectionState,
+    /// Error that caused the change, if any
+    pub reason: Option<String>,
+}
+
+/// Current connection state and when it was entered
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct ListenerConnectionStatus {
+    pub state: ListenerConnectionState,
+    /// When `state` was entered (listener creation before the first transition)
+    #[schema(value_type = String)]
+    pub since: DateTime<Utc>,
+    /// State before the last transition; None before the first one
+    pub previous_state: Option<ListenerConnectionState>,
+    /// State changes since the listener was created
+    pub transitions: u64,
+    /// Most recent state changes, oldest first (`solana.reconnect_history_size`)
+    #[serde(skip)]
+    history: VecDeque<ConnectionEvent>,
+    #[serde(skip)]
+    history_size: usize,
+}
+
+impl ListenerConnectionStatus {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            state: ListenerConnectionState::Disconnected,
+            since: now,
+            previous_state: None,
+            transitions: 0,
+            history: VecDeque::new(),
+            history_size: 0,
+        }
+    }
+
+    /// Keep the last `history_size` state changes; 0 keeps none
+    pub fn with_history(mut self, history_size: usize) -> Self {
+        self.history_size = history_size;
+        self
+    }
+
+    /// Move to `state`; setting the current state again keeps its timestamp
+    pub fn transition_at(&mut self, state: ListenerConnectionState, now: DateTime<Utc>) {
+        self.transition_with_reason_at(state, now, None);
+    }
+
+    pub fn transition(&mut self, state: ListenerConnectionState) {
+        self.transition_at(state, Utc::now());
+    }
+
+    /// Move to `state`, recording why in the history
+    pub fn transition_with_reason(&mut self, state: ListenerConnectionState, reason: String) {
+        self.transition_with_reason_at(state, Utc::now(), Some(reason));
+    }
+
+    pub fn transition_with_reason_at(
+        &mut self,
+        state: ListenerConnectionState,
+        now: DateTime<Utc>,
+        reason: Option<String>,
+    ) {
+        if state == self.state {
+            return;
+        }
+        self.previous_state = Some(self.state);
+        self.state = state;
+        self.since = now;
+        self.transitions += 1;
+
+        if self.history_size > 0 {
+            if self.history.len() >= self.history_size {
+                self.history.pop_front();
+            }
+            self.history.push_back(ConnectionEvent {
+                timestamp: now,
+                state,
+                reason,
+            });
+        }
+    }
+
+    /// Capacity of the state change history; 0 when disabled
+    pub fn history_size(&self) -> usize {
+        self.history_size
+    }
+
+    /// Recorded state changes, oldest first
+    pub fn history(&self) -> Vec<ConnectionEvent> {
+        self.history.iter().cloned().collect()
+    }
+}
+
 /// Improved Solana event listener with robust reconnection
 pub struct SolanaEventListener {
     config: SolanaConfig,
@@ -146,10 +353,14 @@ pub struct SolanaEventListener {
     event_handler: Arc<dyn EventHandler>,
     // Use broadcast channel to avoid "channel closed" errors
     event_broadcaster: broadcast::Sender<SpinPetEvent>,
-    connection_state: Arc<tokio::sync::RwLock<ConnectionState>>,
+    connection_state: Arc<tokio::sync::RwLock<ListenerConnectionStatus>>,
     reconnect_attempts: Arc<tokio::sync::RwLock<u32>>,
+    // Times the connection loop was re-armed after exhausting its reconnect attempts
+    restart_cycles: Arc<AtomicU32>,
     should_stop: Arc<tokio::sync::RwLock<bool>>,
     processed_signatures: Arc<tokio::sync::RwLock<HashSet<String>>>,
+    // Bounds concurrent CPI full-transaction fetches
+    cpi_fetch_limiter: Arc<Semaphore>,
     is_running: bool,
 }
 
@@ -160,8 +371,14 @@ impl SolanaEventListener {
         client: Arc<SolanaClient>,
         event_handler: Arc<dyn EventHandler>,
     ) -> anyhow::Result<Self> {
-        let event_parser = EventParser::new(&config.program_id)?;
+        let event_parser = EventParser::new(&config.program_id)?
+            .with_string_limits(TokenStringLimits::from(&config))
+            .with_min_lengths(EventMinLengths::from(&config))
+            .with_pda_verification(config.verify_pdas);
         let (event_broadcaster, _) = broadcast::channel(1000);
+        let cpi_fetch_limiter = Arc::new(Semaphore::new(config.max_inflight_cpi_fetches.max(1)));
+        let connection_status =
+            ListenerConnectionStatus::new(Utc::now()).with_history(config.reconnect_history_size);
 
         Ok(Self {
             config,
@@ -169,10 +386,12 @@ impl SolanaEventListener {
             event_parser,
             event_handler,
             event_broadcaster,
-            connection_state: Arc::new(tokio::sync::RwLock::new(ConnectionState::Disconnected)),
+            connection_state: Arc::new(tokio::sync::RwLock::new(connection_status)),
             reconnect_attempts: Arc::new(tokio::sync::RwLock::new(0)),
+            restart_cycles: Arc::new(AtomicU32::new(0)),
             should_stop: Arc::new(tokio::sync::RwLock::new(false)),
             processed_signatures: Arc::new(tokio::sync::RwLock::new(HashSet::new())),
+            cpi_fetch_limiter,
             is_running: false,
         })
     }
@@ -228,99 +447,201 @@ impl SolanaEventListener {
         let event_broadcaster = self.event_broadcaster.clone();
         let connection_state = Arc::clone(&self.connection_state);
         let reconnect_attempts = Arc::clone(&self.reconnect_attempts);
+        let restart_cycles = Arc::clone(&self.restart_cycles);
         let should_stop = Arc::clone(&self.should_stop);
         let processed_signatures = Arc::clone(&self.processed_signatures);
+        let cpi_fetch_limiter = Arc::clone(&self.cpi_fetch_limiter);
 
         tokio::spawn(async move {
             info!("🔄 Starting connection loop");
 
-            loop {
-                // Check if we should stop
-                if *should_stop.read().await {
-                    info!("Connection loop received stop signal");
-                    break;
-                }
+            Self::supervise_connection_loop(
+                Duration::from_secs(config.listener_restart_cooldown_secs),
+                config.max_listener_restart_cycles,
+                &restart_cycles,
+                &should_stop,
+                || {
+                    Self::run_connection_cycle(
+                        &config,
+                        &client,
+                        &event_parser,
+                        &event_broadcaster,
+                        &connection_state,
+                        &reconnect_attempts,
+                        &should_stop,
+                        &processed_signatures,
+                        &cpi_fetch_limiter,
+                    )
+                },
+            )
+            .await;
 
-                *connection_state.write().await = ConnectionState::Connecting;
-                info!("🔌 Attempting to connect to WebSocket: {}", config.ws_url);
-
-                match Self::connect_and_listen(
-                    &config,
-                    &client,
-                    &event_parser,
-                    &event_broadcaster,
-                    &connection_state,
-                    &should_stop,
-                    &processed_signatures,
-                )
-                .await
-                {
-                    Ok(()) => {
-                        info!("✅ WebSocket connection completed normally");
-                        *reconnect_attempts.write().await = 0;
-                    }
-                    Err(e) => {
-                        error!("❌ WebSocket connection failed: {}", e);
-                        let mut attempts = reconnect_attempts.write().await;
-                        *attempts += 1;
-
-                        if *attempts > config.max_reconnect_attempts {
-                            error!(
-                                "❌ Max reconnection attempts ({}) exceeded",
-                                config.max_reconnect_attempts
-                            );
-                            *connection_state.write().await = ConnectionState::Disconnected;
-                            break;
-                        }
+            connection_state.write().await.transition_with_reason(
+                ListenerConnectionState::Disconnected,
+                "connection loop ended".to_string(),
+            );
+            info!("🔄 Connection loop ended");
+        });
 
-                        *connection_state.write().await = ConnectionState::Reconnecting;
+        Ok(())
+    }
 
-                        // Exponential backoff with jitter
-                        let base_delay = config.reconnect_interval;
-                        let exponential_delay =
-                            std::cmp::min(base_delay * 2_u64.pow((*attempts - 1).min(5)), 60);
-                        let jitter = (rand::random::<f64>() * 2.0) as u64;
-                        let delay = exponential_delay + jitter;
+    /// Run `run_cycle` (one connection loop that returns once it stops or gives up) and
+    /// re-arm it `cooldown` after it gives up, at most `max_restart_cycles` times
+    async fn supervise_connection_loop<F, Fut>(
+        cooldown: Duration,
+        max_restart_cycles: u32,
+        restart_cycles: &AtomicU32,
+        should_stop: &tokio::sync::RwLock<bool>,
+        mut run_cycle: F,
+    ) where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        loop {
+            run_cycle().await;
+
+            if *should_stop.read().await {
+                return;
+            }
+            let cycles = restart_cycles.load(Ordering::Relaxed);
+            if cycles >= max_restart_cycles {
+                error!(
+                    "❌ Listener stays disconnected after {} restart cycles",
+                    cycles
+                );
+                return;
+            }
 
-                        warn!(
-                            "🔄 Reconnection attempt {} of {} in {} seconds",
-                            *attempts, config.max_reconnect_attempts, delay
-                        );
+            warn!(
+                "⏳ Reconnection attempts exhausted, restarting connection loop in {:?} (cycle {} of {})",
+                cooldown,
+                cycles + 1,
+                max_restart_cycles
+            );
+            sleep(cooldown).await;
+            if *should_stop.read().await {
+                return;
+            }
+            restart_cycles.fetch_add(1, Ordering::Relaxed);
+        }
+    }
 
-                        drop(attempts);
-                        sleep(Duration::from_secs(delay)).await;
-                    }
-                }
+    /// Connect with exponential backoff until stopped or `max_reconnect_attempts` is exceeded
+    #[allow(clippy::too_many_arguments)]
+    async fn run_connection_cycle(
+        config: &SolanaConfig,
+        client: &Arc<SolanaClient>,
+        event_parser: &EventParser,
+        event_broadcaster: &broadcast::Sender<SpinPetEvent>,
+        connection_state: &Arc<tokio::sync::RwLock<ListenerConnectionStatus>>,
+        reconnect_attempts: &Arc<tokio::sync::RwLock<u32>>,
+        should_stop: &Arc<tokio::sync::RwLock<bool>>,
+        processed_signatures: &Arc<tokio::sync::RwLock<HashSet<String>>>,
+        cpi_fetch_limiter: &Arc<Semaphore>,
+    ) {
+        *reconnect_attempts.write().await = 0;
+
+        loop {
+            // Check if we should stop
+            if *should_stop.read().await {
+                info!("Connection loop received stop signal");
+                break;
             }
 
-            *connection_state.write().await = ConnectionState::Disconnected;
-            info!("🔄 Connection loop ended");
-        });
+            connection_state
+                .write()
+                .await
+                .transition(ListenerConnectionState::Connecting);
+            info!("🔌 Attempting to connect to WebSocket: {}", config.ws_url);
+
+            match Self::connect_and_listen(
+                config,
+                client,
+                event_parser,
+                event_broadcaster,
+                connection_state,
+                should_stop,
+                processed_signatures,
+                cpi_fetch_limiter,
+            )
+            .await
+            {
+                Ok(()) => {
+                    info!("✅ WebSocket connection completed normally");
+                    *reconnect_attempts.write().await = 0;
+                }
+                Err(e) => {
+                    error!("❌ WebSocket connection failed: {}", e);
+                    let mut attempts = reconnect_attempts.write().await;
+                    *attempts += 1;
+
+                    if *attempts > config.max_reconnect_attempts {
+                        error!(
+                            "❌ Max reconnection attempts ({}) exceeded",
+                            config.max_reconnect_attempts
+                        );
+                        connection_state.write().await.transition_with_reason(
+                            ListenerConnectionState::Disconnected,
+                            format!(
+                                "max reconnection attempts ({}) exceeded: {}",
+                                config.max_reconnect_attempts, e
+                            ),
+                        );
+                        break;
+                    }
 
-        Ok(())
+                    connection_state.write().await.transition_with_reason(
+                        ListenerConnectionState::Reconnecting,
+                        e.to_string(),
+                    );
+
+                    // Exponential backoff with jitter
+                    let base_delay = config.reconnect_interval;
+                    let exponential_delay =
+                        std::cmp::min(base_delay * 2_u64.pow((*attempts - 1).min(5)), 60);
+                    let jitter = (rand::random::<f64>() * 2.0) as u64;
+                    let delay = exponential_delay + jitter;
+
+                    warn!(
+                        "🔄 Reconnection attempt {} of {} in {} seconds",
+                        *attempts, config.max_reconnect_attempts, delay
+                    );
+
+                    drop(attempts);
+                    sleep(Duration::from_secs(delay)).await;
+                }
+            }
+        }
     }
 
     /// Connect and listen to WebSocket
+    #[allow(clippy::too_many_arguments)]
     async fn connect_and_listen(
         config: &SolanaConfig,
         client: &Arc<SolanaClient>,
         event_parser: &EventParser,
         event_broadcaster: &broadcast::Sender<SpinPetEvent>,
-        connection_state: &Arc<tokio::sync::RwLock<ConnectionState>>,
+        connection_state: &Arc<tokio::sync::RwLock<ListenerConnectionStatus>>,
         should_stop: &Arc<tokio::sync::RwLock<bool>>,
         processed_signatures: &Arc<tokio::sync::RwLock<HashSet<String>>>,
+        cpi_fetch_limiter: &Arc<Semaphore>,
     ) -> anyhow::Result<()> {
         let (ws_stream, _) = connect_async(&config.ws_url).await?;
         info!("🔗 WebSocket connected successfully");
 
-        *connection_state.write().await = ConnectionState::Connected;
+        connection_state
+            .write()
+            .await
+            .transition(ListenerConnectionState::Connected);
 
         let (mut write, mut read) = ws_stream.split();
 
         // Subscribe to program logs
+        let subscribe_id = Uuid::new_v4().to_string();
         let subscribe_request = json!({
             "jsonrpc": "2.0",
-            "id": Uuid::new_v4().to_string(),
+            "id": subscribe_id,
             "method": "logsSubscribe",
             "params": [
                 {
@@ -336,6 +657,25 @@ impl SolanaEventListener {
         write.send(subscribe_msg).await?;
         info!("📡 Subscribed to program logs: {}", config.program_id);
 
+        // A rejected subscription never delivers, so it fails the connection like a dropped socket
+        let confirmation = Self::await_subscribe_reply(&mut read, &mut write, &subscribe_id);
+        let subscription_id = if config.subscribe_confirm_timeout_secs > 0 {
+            tokio::time::timeout(
+                Duration::from_secs(config.subscribe_confirm_timeout_secs),
+                confirmation,
+            )
+            .await
+            .map_err(|_| {
+                anyhow::anyhow!(
+                    "logsSubscribe not confirmed within {} seconds",
+                    config.subscribe_confirm_timeout_secs
+                )
+            })??
+        } else {
+            confirmation.await?
+        };
+        info!("✅ Subscription confirmed: ID = {}", subscription_id);
+
         // Shared writer for ping and other operations
         let shared_writer = Arc::new(Mutex::new(write));
         let (ping_stop_sender, mut ping_stop_receiver) = mpsc::unbounded_channel::<()>();
@@ -389,11 +729,40 @@ impl SolanaEventListener {
             info!("💓 Ping task stopped");
         });
 
-        // Message handling loop
-        let event_broadcaster_clone = event_broadcaster.clone();
-        let event_parser_clone = event_parser.clone();
-        let client_clone = Arc::clone(client);
-        let processed_signatures_clone = Arc::clone(processed_signatures);
+        // Messages are processed by workers so a slow parse/store can't stall the socket
+        let worker_broadcaster = event_broadcaster.clone();
+        let worker_parser = Arc::new(event_parser.clone());
+        let worker_client: Arc<dyn TransactionFetcher> = client.clone();
+        let worker_processed = Arc::clone(processed_signatures);
+        let worker_config = Arc::new(config.clone());
+        let worker_limiter = Arc::clone(cpi_fetch_limiter);
+        let message_sender = Self::spawn_message_workers(
+            config.message_workers,
+            config.message_queue_size,
+            move |text| {
+                let broadcaster = worker_broadcaster.clone();
+                let parser = Arc::clone(&worker_parser);
+                let client = Arc::clone(&worker_client);
+                let processed = Arc::clone(&worker_processed);
+                let config = Arc::clone(&worker_config);
+                let limiter = Arc::clone(&worker_limiter);
+                async move {
+                    if let Err(e) = Self::handle_websocket_message(
+                        &text,
+                        &parser,
+                        &broadcaster,
+                        &client,
+                        &processed,
+                        &config,
+                        &limiter,
+                    )
+                    .await
+                    {
+                        error!("Failed to process WebSocket message: {}", e);
+                    }
+                }
+            },
+        );
         let should_stop_clone = Arc::clone(should_stop);
 
         info!("🎧 Starting to listen for WebSocket messages");
@@ -407,17 +776,10 @@ impl SolanaEventListener {
             match msg {
                 Ok(Message::Text(text)) => {
                     debug!("📨 Received text message");
-                    if let Err(e) = Self::handle_websocket_message(
-                        &text,
-                        &event_parser_clone,
-                        &event_broadcaster_clone,
-                        &client_clone,
-                        &processed_signatures_clone,
-                        config,
-                    )
-                    .await
-                    {
-                        error!("Failed to process WebSocket message: {}", e);
+                    // Waits only when the queue is full
+                    if message_sender.send(text).await.is_err() {
+                        error!("🧵 Message workers stopped, dropping connection");
+                        break;
                     }
                 }
                 Ok(Message::Close(_)) => {
@@ -445,23 +807,98 @@ impl SolanaEventListener {
             }
         }
 
-        // Stop ping task
+        // Stop ping task; workers drain the queued messages and exit once the sender is dropped
         let _ = ping_stop_sender.send(());
+        drop(message_sender);
         warn!("🎧 WebSocket message listener ended");
 
         Ok(())
     }
 
+    /// Read until the reply to the subscribe request `request_id` arrives, answering pings on the
+    /// way; a JSON-RPC error reply or a closed socket is an error
+    async fn await_subscribe_reply<R, W>(
+        read: &mut R,
+        write: &mut W,
+        request_id: &str,
+    ) -> anyhow::Result<Value>
+    where
+        R: Stream<Item = Result<Message, tungstenite::Error>> + Unpin,
+        W: Sink<Message, Error = tungstenite::Error> + Unpin,
+    {
+        while let Some(msg) = read.next().await {
+            match msg? {
+                Message::Text(text) => {
+                    let Ok(reply) = serde_json::from_str::<Value>(&text) else {
+                        debug!("Ignoring non-JSON message before subscription reply");
+                        continue;
+                    };
+                    match classify_subscribe_reply(&reply, request_id) {
+                        SubscribeReply::Confirmed(subscription_id) => return Ok(subscription_id),
+                        SubscribeReply::Failed(error) => {
+                            return Err(anyhow::anyhow!("logsSubscribe rejected: {}", error))
+                        }
+                        SubscribeReply::Unrelated => {
+                            debug!("Ignoring message before subscription reply")
+                        }
+                    }
+                }
+                Message::Ping(data) => write.send(Message::Pong(data)).await?,
+                Message::Close(_) => break,
+                _ => {}
+            }
+        }
+        Err(anyhow::anyhow!(
+            "WebSocket closed before logsSubscribe was confirmed"
+        ))
+    }
+
+    /// Spawn `workers` tasks handling raw WebSocket messages from a bounded queue of
+    /// `queue_size`, returning the queue's sender. Workers exit when the sender is dropped.
+    fn spawn_message_workers<F, Fut>(
+        workers: usize,
+        queue_size: usize,
+        handle: F,
+    ) -> mpsc::Sender<String>
+    where
+        F: Fn(String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel::<String>(queue_size.max(1));
+        let receiver = Arc::new(Mutex::new(receiver));
+        let handle = Arc::new(handle);
+
+        for worker in 0..workers.max(1) {
+            let receiver = Arc::clone(&receiver);
+            let handle = Arc::clone(&handle);
+            tokio::spawn(async move {
+                loop {
+                    // The lock is released before the message is handled
+                    let message = receiver.lock().await.recv().await;
+                    match message {
+                        Some(text) => handle(text).await,
+                        None => break,
+                    }
+                }
+                debug!("🧵 Message worker {} stopped", worker);
+            });
+        }
+
+        sender
+    }
+
     /// Handle WebSocket messages (same logic as before)
     async fn handle_websocket_message(
         message: &str,
         event_parser: &EventParser,
         event_broadcaster: &broadcast::Sender<SpinPetEvent>,
-        client: &Arc<SolanaClient>,
+        client: &Arc<dyn TransactionFetcher>,
         processed_signatures: &Arc<tokio::sync::RwLock<HashSet<String>>>,
         config: &SolanaConfig,
+        cpi_fetch_limiter: &Arc<Semaphore>,
     ) -> anyhow::Result<()> {
         debug!("📨 Processing WebSocket message");
+        let received_at = Utc::now();
 
         let json_msg: Value = serde_json::from_str(message)?;
 
@@ -492,19 +929,11 @@ impl SolanaEventListener {
                     };
 
                     // Check transaction success
-                    let transaction_error = value.get("err");
-                    let is_transaction_success =
-                        transaction_error.is_none() || transaction_error == Some(&Value::Null);
-
-                    if !is_transaction_success {
-                        if let Some(error_detail) = transaction_error {
-                            debug!(
-                                "❌ Transaction {} failed with error: {}",
-                                signature, error_detail
-                            );
-                        } else {
-                            debug!("❌ Transaction {} failed with unknown error", signature);
-                        }
+                    if let Some(failure) = Self::transaction_failure(value.get("err")) {
+                        debug!(
+                            "❌ Transaction {} failed with error: {}",
+                            signature, failure
+                        );
 
                         // Skip failed transactions unless explicitly configured to process them
                         if !config.process_failed_transactions {
@@ -515,7 +944,7 @@ impl SolanaEventListener {
                         }
                     }
 
-                    // Check if already processed
+                    // Check and claim under one write lock, so concurrent workers never both process a signature
                     {
                         let mut processed = processed_signatures.write().await;
                         if processed.contains(signature) {
@@ -552,71 +981,252 @@ impl SolanaEventListener {
                                 || log.contains("invoke [4]")
                         });
 
+                        // Resolve the timestamp once per transaction, all its events share the slot
+                        let event_time = if all_events.is_empty() && !has_cpi {
+                            (received_at, TimestampSource::ServerReceive)
+                        } else {
+                            Self::resolve_event_timestamp(
+                                client.as_ref(),
+                                config,
+                                slot,
+                                received_at,
+                            )
+                            .await
+                        };
+                        for event in &mut all_events {
+                            event.set_timestamp(event_time.0, event_time.1);
+                        }
+
+                        // Broadcast events found directly in the WebSocket logs
+                        Self::broadcast_events(event_broadcaster, &all_events, signature);
+
+                        // CPI events are fetched off the read loop so a slow RPC can't stall it
                         if has_cpi {
-                            info!("Detected CPI calls, fetching full transaction details");
-
-                            match client.get_transaction_with_logs(signature).await {
-                                Ok(tx_details) => {
-                                    if let Some(meta) =
-                                        tx_details.get("meta").and_then(|m| m.as_object())
-                                    {
-                                        if let Some(full_logs) =
-                                            meta.get("logMessages").and_then(|l| l.as_array())
-                                        {
-                                            let full_log_strings: Vec<String> = full_logs
-                                                .iter()
-                                                .filter_map(|l| l.as_str())
-                                                .map(|s| s.to_string())
-                                                .collect();
-
-                                            match event_parser.parse_events_with_call_stack(
-                                                &full_log_strings,
-                                                signature,
-                                                slot,
-                                            ) {
-                                                Ok(events) => {
-                                                    for event in events {
-                                                        if !Self::event_exists_in_list(
-                                                            &all_events,
-                                                            &event,
-                                                        ) {
-                                                            all_events.push(event);
-                                                        }
-                                                    }
-                                                }
-                                                Err(e) => {
-                                                    error!("Failed to parse full transaction events: {}", e);
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                                Err(e) => {
-                                    warn!("Failed to get transaction details: {}", e);
-                                }
+                            if Self::cpi_fetch_within_age(client.as_ref(), config, slot).await {
+                                info!("Detected CPI calls, scheduling full transaction fetch");
+                                Self::spawn_cpi_fetch(
+                                    Arc::clone(client),
+                                    event_parser.clone(),
+                                    event_broadcaster.clone(),
+                                    Arc::clone(cpi_fetch_limiter),
+                                    signature.to_string(),
+                                    slot,
+                                    event_time,
+                                    all_events,
+                                    config.cpi_fetch_retries,
+                                    Duration::from_millis(config.cpi_fetch_retry_delay_ms),
+                                );
+                            } else {
+                                debug!(
+                                    "⏭️ Skipping CPI fetch for {} at slot {}: older than {} slots",
+                                    signature, slot, config.cpi_fetch_max_slot_age
+                                );
                             }
                         }
+                    }
+                }
+            }
+        }
 
-                        // Broadcast events
-                        if !all_events.is_empty() {
-                            info!(
-                                "✅ Broadcasting {} events for transaction {}",
-                                all_events.len(),
-                                signature
-                            );
-
-                            for event in all_events {
-                                if let Err(e) = event_broadcaster.send(event) {
-                                    error!("Failed to broadcast event: {}", e);
-                                }
+        Ok(())
+    }
+
+    /// Description of a transaction's `err` field, None when it reports success.
+    /// RPCs send success as an absent or null `err`, and some as `{}` or `""`; failures are
+    /// an error name string (`"AccountNotFound"`) or an object such as
+    /// `{"InstructionError": [0, {"Custom": 6001}]}`
+    fn transaction_failure(err: Option<&Value>) -> Option<String> {
+        match err? {
+            Value::Null => None,
+            Value::String(name) if name.is_empty() => None,
+            Value::String(name) => Some(name.clone()),
+            Value::Object(fields) if fields.is_empty() => None,
+            Value::Object(fields) => match fields.get("InstructionError") {
+                Some(Value::Array(parts)) if parts.len() == 2 => Some(format!(
+                    "InstructionError at instruction {}: {}",
+                    parts[0], parts[1]
+                )),
+                _ => Some(Value::Object(fields.clone()).to_string()),
+            },
+            other => Some(other.to_string()),
+        }
+    }
+
+    /// Whether a CPI transaction at `slot` is recent enough for a full fetch under
+    /// `solana.cpi_fetch_max_slot_age`; an unknown head slot still fetches
+    async fn cpi_fetch_within_age(
+        client: &dyn TransactionFetcher,
+        config: &SolanaConfig,
+        slot: u64,
+    ) -> bool {
+        if config.cpi_fetch_max_slot_age == 0 {
+            return true;
+        }
+        match client.get_head_slot().await {
+            Ok(head_slot) => head_slot.saturating_sub(slot) <= config.cpi_fetch_max_slot_age,
+            Err(e) => {
+                debug!("Head slot unavailable for CPI age check: {}", e);
+                true
+            }
+        }
+    }
+
+    /// Resolve an event timestamp according to `solana.timestamp_source`
+    async fn resolve_event_timestamp(
+        client: &dyn TransactionFetcher,
+        config: &SolanaConfig,
+        slot: u64,
+        received_at: DateTime<Utc>,
+    ) -> (DateTime<Utc>, TimestampSource) {
+        let block_time = match config.timestamp_source {
+            TimestampSourceMode::ServerReceive => None,
+            TimestampSourceMode::BlockTime => client.get_block_time(slot).await.ok().flatten(),
+            TimestampSourceMode::PreferBlockTime => {
+                let budget = Duration::from_millis(config.block_time_fetch_timeout_ms);
+                match tokio::time::timeout(budget, client.get_block_time(slot)).await {
+                    Ok(result) => result.ok().flatten(),
+                    Err(_) => {
+                        debug!(
+                            "Block time for slot {} not resolved within {:?}",
+                            slot, budget
+                        );
+                        None
+                    }
+                }
+            }
+        };
+
+        match block_time.and_then(|t| Utc.timestamp_opt(t, 0).single()) {
+            Some(timestamp) => (timestamp, TimestampSource::BlockTime),
+            None => {
+                if config.timestamp_source == TimestampSourceMode::BlockTime {
+                    warn!(
+                        "⚠️ Block time unavailable for slot {}, falling back to receive time",
+                        slot
+                    );
+                }
+                (received_at, TimestampSource::ServerReceive)
+            }
+        }
+    }
+
+    /// Fetch full transaction details for CPI calls in a background task bounded by the limiter.
+    /// While the RPC does not have the transaction yet the fetch is retried up to `retries` times
+    #[allow(clippy::too_many_arguments)]
+    fn spawn_cpi_fetch(
+        client: Arc<dyn TransactionFetcher>,
+        event_parser: EventParser,
+        event_broadcaster: broadcast::Sender<SpinPetEvent>,
+        cpi_fetch_limiter: Arc<Semaphore>,
+        signature: String,
+        slot: u64,
+        event_time: (DateTime<Utc>, TimestampSource),
+        known_events: Vec<SpinPetEvent>,
+        retries: u32,
+        retry_delay: Duration,
+    ) {
+        tokio::spawn(async move {
+            for attempt in 0..=retries {
+                if attempt > 0 {
+                    sleep(retry_delay).await;
+                    debug!(
+                        "🔁 Retrying CPI fetch for {} ({}/{})",
+                        signature, attempt, retries
+                    );
+                }
+
+                // Wait for a free slot in the CPI fetch pool, released again between retries
+                let full_log_strings = {
+                    let _permit = match Arc::clone(&cpi_fetch_limiter).acquire_owned().await {
+                        Ok(permit) => permit,
+                        Err(e) => {
+                            warn!("CPI fetch limiter closed: {}", e);
+                            return;
+                        }
+                    };
+                    match client.get_transaction_with_logs(&signature).await {
+                        Ok(tx_details) => Self::transaction_log_messages(&tx_details),
+                        Err(e) => {
+                            warn!("Failed to get transaction details: {}", e);
+                            None
+                        }
+                    }
+                };
+
+                // Transaction not available yet
+                let Some(full_log_strings) = full_log_strings else {
+                    continue;
+                };
+
+                match event_parser.parse_events_with_call_stack(&full_log_strings, &signature, slot)
+                {
+                    Ok(events) => {
+                        // Skip events already broadcast from the WebSocket logs
+                        let known_count = known_events.len();
+                        let mut all_events = known_events;
+                        for mut event in events {
+                            if !Self::event_exists_in_list(&all_events, &event) {
+                                event.set_timestamp(event_time.0, event_time.1);
+                                all_events.push(event);
                             }
                         }
+                        Self::broadcast_events(
+                            &event_broadcaster,
+                            &all_events[known_count..],
+                            &signature,
+                        );
+                    }
+                    Err(e) => {
+                        error!("Failed to parse full transaction events: {}", e);
                     }
                 }
+                return;
             }
+
+            if retries > 0 {
+                warn!(
+                    "⚠️ Transaction {} still unavailable after {} CPI fetch retries, keeping WebSocket log events only",
+                    signature, retries
+                );
+            }
+        });
+    }
+
+    /// Log messages of a fetched transaction, None when the RPC returned no transaction
+    fn transaction_log_messages(tx_details: &Value) -> Option<Vec<String>> {
+        let full_logs = tx_details
+            .get("meta")
+            .and_then(|m| m.get("logMessages"))
+            .and_then(|l| l.as_array())?;
+        Some(
+            full_logs
+                .iter()
+                .filter_map(|l| l.as_str())
+                .map(|s| s.to_string())
+                .collect(),
+        )
+    }
+
+    fn broadcast_events(
+        event_broadcaster: &broadcast::Sender<SpinPetEvent>,
+        events: &[SpinPetEvent],
+        signature: &str,
+    ) {
+        if events.is_empty() {
+            return;
         }
 
-        Ok(())
+        info!(
+            "✅ Broadcasting {} events for transaction {}",
+            events.len(),
+            signature
+        );
+
+        for event in events {
+            if let Err(e) = event_broadcaster.send(event.clone()) {
+                error!("Failed to broadcast event: {}", e);
+            }
+        }
     }
 
     fn event_exists_in_list(events: &[SpinPetEvent], new_event: &SpinPetEvent) -> bool {
@@ -645,17 +1255,38 @@ impl SolanaEventListener {
         }
     }
 
+    /// Shared handle to the processed-signature dedup cache
+    pub fn processed_signatures(&self) -> Arc<tokio::sync::RwLock<HashSet<String>>> {
+        Arc::clone(&self.processed_signatures)
+    }
+
+    /// Times the connection loop was re-armed after giving up on reconnecting
+    pub fn restart_cycles(&self) -> u32 {
+        self.restart_cycles.load(Ordering::Relaxed)
+    }
+
+    /// Counters of the listener's event parser
+    pub fn parser_stats(&self) -> Arc<ParserStats> {
+        self.event_parser.stats()
+    }
+
+    pub fn connection_status(&self) -> Arc<tokio::sync::RwLock<ListenerConnectionStatus>> {
+        Arc::clone(&self.connection_state)
+    }
+
     #[allow(dead_code)]
     pub async fn get_connection_health(&self) -> serde_json::Value {
         let processed_count = self.processed_signatures.read().await.len();
         let current_attempts = *self.reconnect_attempts.read().await;
-        let connection_state = self.connection_state.read().await.clone();
+        let connection_state = self.connection_state.read().await.state;
 
         serde_json::json!({
             "is_running": self.is_running,
             "connection_state": format!("{:?}", connection_state),
             "reconnect_attempts": current_attempts,
             "max_reconnect_attempts": self.config.max_reconnect_attempts,
+            "restart_cycles": self.restart_cycles(),
+            "max_listener_restart_cycles": self.config.max_listener_restart_cycles,
             "should_stop": *self.should_stop.read().await,
             "ws_url": self.config.ws_url,
             "program_id": self.config.program_id,
@@ -748,7 +1379,6 @@ impl EventListenerManager {
         }
     }
 
-    #[allow(dead_code)]
     pub async fn stop(&mut self) -> anyhow::Result<()> {
         if let Some(listener) = &mut self.listener {
             listener.stop().await
@@ -761,6 +1391,22 @@ impl EventListenerManager {
         self.listener.as_ref().map_or(false, |l| l.is_running())
     }
 
+    pub fn processed_signatures(&self) -> Option<Arc<tokio::sync::RwLock<HashSet<String>>>> {
+        self.listener.as_ref().map(|l| l.processed_signatures())
+    }
+
+    pub fn restart_cycles(&self) -> u32 {
+        self.listener.as_ref().map_or(0, |l| l.restart_cycles())
+    }
+
+    pub fn parser_stats(&self) -> Option<Arc<ParserStats>> {
+        self.listener.as_ref().map(|l| l.parser_stats())
+    }
+
+    pub fn connection_status(&self) -> Option<Arc<tokio::sync::RwLock<ListenerConnectionStatus>>> {
+        self.listener.as_ref().map(|l| l.connection_status())
+    }
+
     #[allow(dead_code)]
     pub async fn get_connection_health(&self) -> Option<serde_json::Value> {
         if let Some(listener) = &self.listener {
@@ -770,3 +1416,698 @@ impl EventListenerManager {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::solana::events::BUY_SELL_EVENT_DISCRIMINATOR;
+    use base64::engine::Engine;
+    use solana_sdk::pubkey::Pubkey;
+
+    const PROGRAM_ID: &str = "JBMmrp6jhksqnxDBskkmVvWHhJLaPBjgiMHEroJbUTBZ";
+
+    /// Fetcher that waits before returning a fixed transaction body and block time
+    struct MockFetcher {
+        delay: Duration,
+        response: Value,
+        block_time: Option<i64>,
+        head_slot: u64,
+    }
+
+    #[async_trait]
+    impl TransactionFetcher for MockFetcher {
+        async fn get_transaction_with_logs(&self, _signature: &str) -> anyhow::Result<Value> {
+            sleep(self.delay).await;
+            Ok(self.response.clone())
+        }
+
+        async fn get_signatures_for_slot_range(
+            &self,
+            _from_slot: u64,
+            _to_slot: u64,
+        ) -> anyhow::Result<Vec<String>> {
+            Ok(Vec::new())
+        }
+
+        async fn get_block_time(&self, _slot: u64) -> anyhow::Result<Option<i64>> {
+            sleep(self.delay).await;
+            Ok(self.block_time)
+        }
+
+        async fn get_head_slot(&self) -> anyhow::Result<u64> {
+            Ok(self.head_slot)
+        }
+    }
+
+    /// Fetcher whose transaction is unavailable (empty body) for the first `unavailable_for` calls
+    struct LateTransactionFetcher {
+        unavailable_for: u32,
+        calls: AtomicU32,
+        response: Value,
+    }
+
+    #[async_trait]
+    impl TransactionFetcher for LateTransactionFetcher {
+        async fn get_transaction_with_logs(&self, _signature: &str) -> anyhow::Result<Value> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.unavailable_for {
+                return Ok(json!({}));
+            }
+            Ok(self.response.clone())
+        }
+
+        async fn get_signatures_for_slot_range(
+            &self,
+            _from_slot: u64,
+            _to_slot: u64,
+        ) -> anyhow::Result<Vec<String>> {
+            Ok(Vec::new())
+        }
+
+        async fn get_block_time(&self, _slot: u64) -> anyhow::Result<Option<i64>> {
+            Ok(None)
+        }
+
+        async fn get_head_slot(&self) -> anyhow::Result<u64> {
+            Ok(42)
+        }
+    }
+
+    fn create_test_config() -> SolanaConfig {
+        let mut config = Config::for_tests("").solana;
+        config.max_inflight_cpi_fetches = 1;
+        config
+    }
+
+    fn buy_sell_data_log() -> String {
+        let mut data = BUY_SELL_EVENT_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.push(1);
+        data.extend_from_slice(&100u64.to_le_bytes());
+        data.extend_from_slice(&200u64.to_le_bytes());
+        data.extend_from_slice(&300u128.to_le_bytes());
+        format!(
+            "Program data: {}",
+            base64::engine::general_purpose::STANDARD.encode(data)
+        )
+    }
+
+    fn cpi_logs(inner: Vec<String>) -> Vec<String> {
+        let mut logs = vec![
+            format!("Program {} invoke [1]", PROGRAM_ID),
+            "Program 11111111111111111111111111111111 invoke [2]".to_string(),
+            "Program 11111111111111111111111111111111 success".to_string(),
+        ];
+        logs.extend(inner);
+        logs.push(format!("Program {} success", PROGRAM_ID));
+        logs
+    }
+
+    fn log_notification(signature: &str, logs: &[String]) -> String {
+        json!({
+            "jsonrpc": "2.0",
+            "method": "logsNotification",
+            "params": {
+                "result": {
+                    "context": { "slot": 42 },
+                    "value": { "signature": signature, "err": null, "logs": logs }
+                },
+                "subscription": 1
+            }
+        })
+        .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_slow_cpi_fetch_does_not_block_next_message() {
+        let config = create_test_config();
+        let event_parser = EventParser::new(PROGRAM_ID).unwrap();
+        let (event_broadcaster, mut receiver) = broadcast::channel(16);
+        let client: Arc<dyn TransactionFetcher> = Arc::new(MockFetcher {
+            delay: Duration::from_secs(30),
+            response: json!({}),
+            block_time: None,
+            head_slot: 42,
+        });
+        let processed = Arc::new(tokio::sync::RwLock::new(HashSet::new()));
+        let limiter = Arc::new(Semaphore::new(config.max_inflight_cpi_fetches));
+
+        let cpi_message = log_notification("cpi_sig", &cpi_logs(vec![]));
+        let plain_message = log_notification(
+            "plain_sig",
+            &[
+                format!("Program {} invoke [1]", PROGRAM_ID),
+                buy_sell_data_log(),
+                format!("Program {} success", PROGRAM_ID),
+            ],
+        );
+
+        let started = std::time::Instant::now();
+        for message in [&cpi_message, &plain_message] {
+            SolanaEventListener::handle_websocket_message(
+                message,
+                &event_parser,
+                &event_broadcaster,
+                &client,
+                &processed,
+                &config,
+                &limiter,
+            )
+            .await
+            .unwrap();
+        }
+
+        let event = tokio::time::timeout(Duration::from_secs(2), receiver.recv())
+            .await
+            .expect("non-CPI event should be broadcast while CPI fetch is pending")
+            .unwrap();
+        match event {
+            SpinPetEvent::BuySell(e) => assert_eq!(e.signature, "plain_sig"),
+            other => panic!("unexpected event: {:?}", other),
+        }
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn test_cpi_fetch_skips_events_already_in_ws_logs() {
+        let config = create_test_config();
+        let event_parser = EventParser::new(PROGRAM_ID).unwrap();
+        let (event_broadcaster, mut receiver) = broadcast::channel(16);
+        let logs = cpi_logs(vec![buy_sell_data_log()]);
+        let client: Arc<dyn TransactionFetcher> = Arc::new(MockFetcher {
+            delay: Duration::from_millis(10),
+            response: json!({ "meta": { "logMessages": logs } }),
+            block_time: None,
+            head_slot: 42,
+        });
+        let processed = Arc::new(tokio::sync::RwLock::new(HashSet::new()));
+        let limiter = Arc::new(Semaphore::new(config.max_inflight_cpi_fetches));
+
+        SolanaEventListener::handle_websocket_message(
+            &log_notification("dup_sig", &logs),
+            &event_parser,
+            &event_broadcaster,
+            &client,
+            &processed,
+            &config,
+            &limiter,
+        )
+        .await
+        .unwrap();
+
+        // The event from the WebSocket logs is broadcast once
+        assert!(receiver.recv().await.is_ok());
+
+        // The CPI fetch finds the same event and must not broadcast it again
+        let duplicate = tokio::time::timeout(Duration::from_millis(300), receiver.recv()).await;
+        assert!(duplicate.is_err(), "duplicate CPI event was broadcast");
+    }
+
+    #[tokio::test]
+    async fn test_cpi_fetch_skipped_beyond_max_slot_age() {
+        let mut config = create_test_config();
+        config.cpi_fetch_max_slot_age = 100;
+        let event_parser = EventParser::new(PROGRAM_ID).unwrap();
+        let ws_logs = cpi_logs(vec![]);
+        let full_logs = cpi_logs(vec![buy_sell_data_log()]);
+
+        // The message is at slot 42: 1000 slots behind is too old, 58 behind is recent
+        for (head_slot, expect_fetch) in [(1_042, false), (100, true)] {
+            let (event_broadcaster, mut receiver) = broadcast::channel(16);
+            let client: Arc<dyn TransactionFetcher> = Arc::new(MockFetcher {
+                delay: Duration::from_millis(0),
+                response: json!({ "meta": { "logMessages": full_logs } }),
+                block_time: None,
+                head_slot,
+            });
+            let processed = Arc::new(tokio::sync::RwLock::new(HashSet::new()));
+            let limiter = Arc::new(Semaphore::new(config.max_inflight_cpi_fetches));
+
+            SolanaEventListener::handle_websocket_message(
+                &log_notification("cpi_sig", &ws_logs),
+                &event_parser,
+                &event_broadcaster,
+                &client,
+                &processed,
+                &config,
+                &limiter,
+            )
+            .await
+            .unwrap();
+
+            // The event only exists in the full transaction, so it arrives only if it was fetched
+            let fetched = tokio::time::timeout(Duration::from_millis(300), receiver.recv()).await;
+            assert_eq!(fetched.is_ok(), expect_fetch, "head slot {}", head_slot);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cpi_fetch_retries_until_transaction_available() {
+        let mut config = create_test_config();
+        config.cpi_fetch_retries = 3;
+        config.cpi_fetch_retry_delay_ms = 20;
+        let event_parser = EventParser::new(PROGRAM_ID).unwrap();
+        let (event_broadcaster, mut receiver) = broadcast::channel(16);
+        let fetcher = Arc::new(LateTransactionFetcher {
+            unavailable_for: 2,
+            calls: AtomicU32::new(0),
+            response: json!({ "meta": { "logMessages": cpi_logs(vec![buy_sell_data_log()]) } }),
+        });
+        let client: Arc<dyn TransactionFetcher> = fetcher.clone();
+        let processed = Arc::new(tokio::sync::RwLock::new(HashSet::new()));
+        let limiter = Arc::new(Semaphore::new(config.max_inflight_cpi_fetches));
+
+        SolanaEventListener::handle_websocket_message(
+            &log_notification("late_sig", &cpi_logs(vec![])),
+            &event_parser,
+            &event_broadcaster,
+            &client,
+            &processed,
+            &config,
+            &limiter,
+        )
+        .await
+        .unwrap();
+
+        // The CPI event only appears once the third fetch finds the transaction
+        let event = tokio::time::timeout(Duration::from_secs(2), receiver.recv())
+            .await
+            .expect("CPI event should be broadcast after a retry")
+            .unwrap();
+        match event {
+            SpinPetEvent::BuySell(e) => assert_eq!(e.signature, "late_sig"),
+            other => panic!("unexpected event: {:?}", other),
+        }
+        assert_eq!(fetcher.calls.load(Ordering::SeqCst), 3);
+
+        // Retrying never re-broadcasts, and stops once the transaction was found
+        let duplicate = tokio::time::timeout(Duration::from_millis(200), receiver.recv()).await;
+        assert!(duplicate.is_err(), "CPI event was broadcast twice");
+        assert_eq!(fetcher.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_transaction_err_representations() {
+        let cases = [
+            (None, None),
+            (Some(json!(null)), None),
+            (Some(json!({})), None),
+            (Some(json!("")), None),
+            (
+                Some(json!("AccountNotFound")),
+                Some("AccountNotFound".to_string()),
+            ),
+            (
+                Some(json!({ "InstructionError": [2, { "Custom": 6001 }] })),
+                Some("InstructionError at instruction 2: {\"Custom\":6001}".to_string()),
+            ),
+            (
+                Some(json!({ "InstructionError": [0, "InvalidAccountData"] })),
+                Some("InstructionError at instruction 0: \"InvalidAccountData\"".to_string()),
+            ),
+            (
+                Some(json!({ "InsufficientFundsForRent": { "account_index": 1 } })),
+                Some("{\"InsufficientFundsForRent\":{\"account_index\":1}}".to_string()),
+            ),
+        ];
+        for (err, expected) in cases {
+            assert_eq!(
+                SolanaEventListener::transaction_failure(err.as_ref()),
+                expected,
+                "err: {:?}",
+                err
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_empty_err_object_is_processed_as_success() {
+        let config = create_test_config();
+        let event_parser = EventParser::new(PROGRAM_ID).unwrap();
+        let (event_broadcaster, mut receiver) = broadcast::channel(16);
+        let client: Arc<dyn TransactionFetcher> = Arc::new(MockFetcher {
+            delay: Duration::from_millis(0),
+            response: json!({}),
+            block_time: None,
+            head_slot: 42,
+        });
+        let processed = Arc::new(tokio::sync::RwLock::new(HashSet::new()));
+        let limiter = Arc::new(Semaphore::new(config.max_inflight_cpi_fetches));
+        let logs = [
+            format!("Program {} invoke [1]", PROGRAM_ID),
+            buy_sell_data_log(),
+            format!("Program {} success", PROGRAM_ID),
+        ];
+
+        // process_failed_transactions is off, so only successful transactions are broadcast
+        for (signature, err, expect_event) in [
+            ("empty_err_sig", json!({}), true),
+            (
+                "failed_sig",
+                json!({ "InstructionError": [0, { "Custom": 1 }] }),
+                false,
+            ),
+        ] {
+            let message = json!({
+                "jsonrpc": "2.0",
+                "method": "logsNotification",
+                "params": {
+                    "result": {
+                        "context": { "slot": 42 },
+                        "value": { "signature": signature, "err": err, "logs": logs }
+                    },
+                    "subscription": 1
+                }
+            })
+            .to_string();
+            SolanaEventListener::handle_websocket_message(
+                &message,
+                &event_parser,
+                &event_broadcaster,
+                &client,
+                &processed,
+                &config,
+                &limiter,
+            )
+            .await
+            .unwrap();
+            assert_eq!(receiver.try_recv().is_ok(), expect_event, "{}", signature);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_supervisor_rearms_connection_loop_after_cooldown() {
+        let cooldown = Duration::from_millis(100);
+        let restart_cycles = AtomicU32::new(0);
+        let should_stop = tokio::sync::RwLock::new(false);
+        let cycle_starts = std::sync::Mutex::new(Vec::new());
+
+        // Every cycle gives up immediately, as if max_reconnect_attempts were exhausted
+        let started = std::time::Instant::now();
+        SolanaEventListener::supervise_connection_loop(
+            cooldown,
+            2,
+            &restart_cycles,
+            &should_stop,
+            || {
+                cycle_starts.lock().unwrap().push(started.elapsed());
+                async {}
+            },
+        )
+        .await;
+
+        let cycle_starts = cycle_starts.into_inner().unwrap();
+        assert_eq!(cycle_starts.len(), 3, "initial loop plus two restarts");
+        assert_eq!(restart_cycles.load(Ordering::Relaxed), 2);
+        for pair in cycle_starts.windows(2) {
+            assert!(
+                pair[1] - pair[0] >= cooldown,
+                "restarted before the cool-down"
+            );
+        }
+
+        // Restarts disabled: the listener stays down after the first loop gives up
+        let no_restarts = AtomicU32::new(0);
+        let mut runs = 0;
+        SolanaEventListener::supervise_connection_loop(
+            cooldown,
+            0,
+            &no_restarts,
+            &should_stop,
+            || {
+                runs += 1;
+                async {}
+            },
+        )
+        .await;
+        assert_eq!(runs, 1);
+        assert_eq!(no_restarts.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn test_cleared_dedup_cache_reprocesses_signature() {
+        let config = create_test_config();
+        let client = Arc::new(SolanaClient::new(&config.rpc_url, &config.program_id).unwrap());
+        let listener =
+            SolanaEventListener::new(config.clone(), client, RecordingHandler::new(false)).unwrap();
+        let processed = listener.processed_signatures();
+
+        let event_parser = EventParser::new(PROGRAM_ID).unwrap();
+        let (event_broadcaster, mut receiver) = broadcast::channel(16);
+        let fetcher: Arc<dyn TransactionFetcher> = Arc::new(MockFetcher {
+            delay: Duration::from_millis(0),
+            response: json!({}),
+            block_time: None,
+            head_slot: 42,
+        });
+        let limiter = Arc::new(Semaphore::new(config.max_inflight_cpi_fetches));
+        let message = log_notification(
+            "seen_sig",
+            &[
+                format!("Program {} invoke [1]", PROGRAM_ID),
+                buy_sell_data_log(),
+                format!("Program {} success", PROGRAM_ID),
+            ],
+        );
+
+        for _ in 0..2 {
+            SolanaEventListener::handle_websocket_message(
+                &message,
+                &event_parser,
+                &event_broadcaster,
+                &fetcher,
+                &processed,
+                &config,
+                &limiter,
+            )
+            .await
+            .unwrap();
+        }
+        assert!(receiver.try_recv().is_ok());
+        assert!(
+            receiver.try_recv().is_err(),
+            "duplicate was not deduplicated"
+        );
+
+        // After an admin reset the same signature is treated as new
+        listener.processed_signatures().write().await.clear();
+        SolanaEventListener::handle_websocket_message(
+            &message,
+            &event_parser,
+            &event_broadcaster,
+            &fetcher,
+            &processed,
+            &config,
+            &limiter,
+        )
+        .await
+        .unwrap();
+        assert!(receiver.try_recv().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_timestamp_source_modes() {
+        let received_at = Utc.timestamp_opt(1_758_343_500, 0).unwrap();
+        let block_time = 1_758_343_400;
+        let fast = MockFetcher {
+            delay: Duration::from_millis(0),
+            response: json!({}),
+            block_time: Some(block_time),
+            head_slot: 42,
+        };
+        let slow = MockFetcher {
+            delay: Duration::from_secs(5),
+            response: json!({}),
+            block_time: Some(block_time),
+            head_slot: 42,
+        };
+        let mut config = create_test_config();
+        config.block_time_fetch_timeout_ms = 50;
+
+        config.timestamp_source = TimestampSourceMode::ServerReceive;
+        let resolved =
+            SolanaEventListener::resolve_event_timestamp(&fast, &config, 42, received_at).await;
+        assert_eq!(resolved, (received_at, TimestampSource::ServerReceive));
+
+        config.timestamp_source = TimestampSourceMode::BlockTime;
+        let resolved =
+            SolanaEventListener::resolve_event_timestamp(&fast, &config, 42, received_at).await;
+        assert_eq!(resolved.0.timestamp(), block_time);
+        assert_eq!(resolved.1, TimestampSource::BlockTime);
+
+        config.timestamp_source = TimestampSourceMode::PreferBlockTime;
+        let resolved =
+            SolanaEventListener::resolve_event_timestamp(&fast, &config, 42, received_at).await;
+        assert_eq!(resolved.0.timestamp(), block_time);
+        assert_eq!(resolved.1, TimestampSource::BlockTime);
+
+        // Block time not resolved within the budget falls back to receive time
+        let resolved =
+            SolanaEventListener::resolve_event_timestamp(&slow, &config, 42, received_at).await;
+        assert_eq!(resolved, (received_at, TimestampSource::ServerReceive));
+    }
+
+    /// Handler that records the signatures it sees, optionally failing afterwards
+    struct RecordingHandler {
+        seen: Mutex<Vec<String>>,
+        fail: bool,
+    }
+
+    impl RecordingHandler {
+        fn new(fail: bool) -> Arc<Self> {
+            Arc::new(Self {
+                seen: Mutex::new(Vec::new()),
+                fail,
+            })
+        }
+    }
+
+    #[async_trait]
+    impl EventHandler for RecordingHandler {
+        async fn handle_event(&self, event: SpinPetEvent) -> anyhow::Result<()> {
+            if let SpinPetEvent::BuySell(e) = event {
+                self.seen.lock().await.push(e.signature);
+            }
+            if self.fail {
+                return Err(anyhow::anyhow!("recording handler failure"));
+            }
+            Ok(())
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+    }
+
+    #[tokio::test]
+    async fn test_composite_handler_dispatches_to_all() {
+        let first = RecordingHandler::new(true);
+        let second = RecordingHandler::new(false);
+        let composite = CompositeEventHandler::new(vec![
+            Arc::clone(&first) as Arc<dyn EventHandler>,
+            Arc::clone(&second) as Arc<dyn EventHandler>,
+        ]);
+
+        let event_parser = EventParser::new(PROGRAM_ID).unwrap();
+        let logs = vec![
+            format!("Program {} invoke [1]", PROGRAM_ID),
+            buy_sell_data_log(),
+            format!("Program {} success", PROGRAM_ID),
+        ];
+        let event = event_parser
+            .parse_events_with_call_stack(&logs, "composite_sig", 42)
+            .unwrap()
+            .remove(0);
+
+        // The first handler's error must not stop the second
+        composite.handle_event(event).await.unwrap();
+        assert_eq!(*first.seen.lock().await, vec!["composite_sig".to_string()]);
+        assert_eq!(*second.seen.lock().await, vec!["composite_sig".to_string()]);
+        assert_eq!(composite.handlers().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_slow_message_does_not_block_reading_next_messages() {
+        let handled = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&handled);
+        let sender = SolanaEventListener::spawn_message_workers(2, 4, move |text: String| {
+            let recorded = Arc::clone(&recorded);
+            async move {
+                if text == "slow" {
+                    sleep(Duration::from_secs(30)).await;
+                }
+                recorded.lock().await.push(text);
+            }
+        });
+
+        // The read loop hands every message off immediately while the first one is still running
+        for text in ["slow", "a", "b", "c"] {
+            tokio::time::timeout(Duration::from_millis(100), sender.send(text.to_string()))
+                .await
+                .expect("read loop blocked by a slow message")
+                .unwrap();
+        }
+
+        tokio::time::timeout(Duration::from_secs(2), async {
+            while handled.lock().await.len() < 3 {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("messages behind the slow one were not processed");
+        assert_eq!(*handled.lock().await, vec!["a", "b", "c"]);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_error_reply_fails_subscription() {
+        let error_reply = json!({
+            "jsonrpc": "2.0",
+            "id": "sub-1",
+            "error": { "code": -32602, "message": "Invalid params" }
+        });
+        assert_eq!(
+            classify_subscribe_reply(&error_reply, "sub-1"),
+            SubscribeReply::Failed("Invalid params (-32602)".to_string())
+        );
+        assert_eq!(
+            classify_subscribe_reply(
+                &json!({"jsonrpc": "2.0", "id": "sub-1", "result": 7}),
+                "sub-1"
+            ),
+            SubscribeReply::Confirmed(json!(7))
+        );
+        assert_eq!(
+            classify_subscribe_reply(&error_reply, "other"),
+            SubscribeReply::Unrelated
+        );
+
+        // Waiting for the reply surfaces the error so the connection loop reconnects
+        let mut read = futures_util::stream::iter(vec![
+            Ok(Message::Ping(vec![1])),
+            Ok(Message::Text(error_reply.to_string())),
+        ]);
+        let mut write = futures_util::sink::drain().sink_map_err(|never| match never {});
+        let result =
+            SolanaEventListener::await_subscribe_reply(&mut read, &mut write, "sub-1").await;
+        assert!(result.unwrap_err().to_string().contains("Invalid params"));
+    }
+
+    #[test]
+    fn test_reconnect_history_records_reasons_and_is_bounded() {
+        use ListenerConnectionState::*;
+
+        let start = Utc::now();
+        let mut status = ListenerConnectionStatus::new(start).with_history(5);
+        for cycle in 0..2i64 {
+            let at = start + chrono::Duration::seconds(cycle * 10);
+            status.transition_at(Connecting, at);
+            status.transition_at(Connected, at);
+            status.transition_with_reason_at(
+                Reconnecting,
+                at + chrono::Duration::seconds(5),
+                Some(format!("WebSocket closed ({})", cycle)),
+            );
+        }
+
+        // Six transitions, the history keeps the last five
+        let history = status.history();
+        assert_eq!(status.transitions, 6);
+        assert_eq!(
+            history.iter().map(|e| e.state).collect::<Vec<_>>(),
+            vec![Connected, Reconnecting, Connecting, Connected, Reconnecting]
+        );
+        assert_eq!(history[1].reason.as_deref(), Some("WebSocket closed (0)"));
+        assert_eq!(history[4].reason.as_deref(), Some("WebSocket closed (1)"));
+        assert_eq!(history[4].timestamp, start + chrono::Duration::seconds(15));
+        assert_eq!(history[2].reason, None);
+
+        // Re-entering the current state records nothing
+        status.transition_with_reason_at(Reconnecting, start, Some("again".to_string()));
+        assert_eq!(status.history().len(), 5);
+
+        let mut disabled = ListenerConnectionStatus::new(start);
+        disabled.transition_with_reason(Reconnecting, "closed".to_string());
+        assert!(disabled.history().is_empty());
+    }
+}
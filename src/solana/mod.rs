@@ -2,6 +2,7 @@ pub mod client;
 pub mod events;
 pub mod listener;
 pub mod listener_improved;
+pub mod selftest;
 
 pub use events::*;
 // Use the improved implementation with broadcast channels
@@ -0,0 +1,160 @@
+// Startup parser self-test against embedded event fixtures
+
+use base64::engine::Engine;
+
+use super::events::{EventParser, SpinPetEvent};
+
+/// Known-good "Program data:" payload for one event variant
+pub struct ParserFixture {
+    pub variant: &'static str,
+    pub data_base64: &'static str,
+    /// Checks the variant-specific key fields decoded from the fixture
+    pub check: fn(&SpinPetEvent) -> bool,
+}
+
+/// Payer and mint shared by every fixture
+const FIXTURE_PAYER: &str = "4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi";
+const FIXTURE_MINT: &str = "8qbHbw2BbbTHBW1sbeqakYXVKRQM8Ne7pLK7m6CVfeR";
+const FIXTURE_ORDER_PDA: &str = "cGfHiC6Kgg3FpFZvgwGcswsCRtp4aBP2fzuXRQPizuN";
+const FIXTURE_PRICE: u128 = 15 * 10_u128.pow(26);
+
+/// One fixture per event variant, encoded with the discriminators and layouts from the IDL
+pub const PARSER_FIXTURES: &[ParserFixture] = &[
+    ParserFixture {
+        variant: "TokenCreated",
+        data_base64: "YHpxijLjlTkBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQICAgICAgICAgICAgICAgICAgICAgICAgICAgICAgICAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBgYGBgYGBgYGBgYGBgYGBgYGBgYGBgYGBgYGBgYGBgYHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwgICAgICAgICAgICAgICAgICAgICAgICAgICAgICAgIZAAsAQEIAAAAU3BpbiBQZXQEAAAAU1BJTh0AAABodHRwczovL2V4YW1wbGUuY29tL3NwaW4uanNvbg==",
+        check: |e| matches!(e, SpinPetEvent::TokenCreated(e) if e.symbol == "SPIN" && e.borrow_fee == 300 && e.uri == "https://example.com/spin.json"),
+    },
+    ParserFixture {
+        variant: "BuySell",
+        data_base64: "YtB4PF0gE7QBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQICAgICAgICAgICAgICAgICAgICAgICAgICAgICAgICAUBLTAAAAAAAgIQeAAAAAAAAAABcW8C471rF2AQAAAAA",
+        check: |e| matches!(e, SpinPetEvent::BuySell(e) if e.is_buy && e.token_amount == 5_000_000 && e.sol_amount == 2_000_000 && e.latest_price == FIXTURE_PRICE),
+    },
+    ParserFixture {
+        variant: "LongShort",
+        data_base64: "G0UUdDr6X9wBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQICAgICAgICAgICAgICAgICAgICAgICAgICAgICAgICCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkAAABcW8C471rF2AQAAAAAAQICAgICAgICAgICAgICAgICAgICAgICAgICAgICAgICCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoAAABcW8C471rF2AQAAAAAAAAAuLaAcd+1irEJAAAAAADKmjsAAAAAAGXNHQAAAADoMM5oeDLOaABlzR0AAAAAAC9oWQAAAACQ0AMAAAAAAPoA",
+        check: |e| matches!(e, SpinPetEvent::LongShort(e) if e.order_type == 1 && e.lock_lp_end_price == 2 * FIXTURE_PRICE && e.borrow_amount == 1_500_000_000 && e.borrow_fee == 250),
+    },
+    ParserFixture {
+        variant: "ForceLiquidate",
+        data_base64: "6sS3aSgazjABAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQICAgICAgICAgICAgICAgICAgICAgICAgICAgICAgICCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQk=",
+        check: |e| matches!(e, SpinPetEvent::ForceLiquidate(e) if e.order_pda == FIXTURE_ORDER_PDA),
+    },
+    ParserFixture {
+        variant: "FullClose",
+        data_base64: "FvRx9ZqobYsBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKAgICAgICAgICAgICAgICAgICAgICAgICAgICAgICAgIBAAAAAAAAAAAARsMjAAAAAADh9QUAAAAAAAAAXFvAuO9axdgEAAAAAAkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJ",
+        check: |e| matches!(e, SpinPetEvent::FullClose(e) if e.is_close_long && e.final_sol_amount == 600_000_000 && e.user_close_profit == 100_000_000 && e.order_pda == FIXTURE_ORDER_PDA),
+    },
+    ParserFixture {
+        variant: "PartialClose",
+        data_base64: "hV4D3hhERZsBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKAgICAgICAgICAgICAgICAgICAgICAgICAgICAgICAgIBoIYBAAAAAAAAo+ERAAAAAIDw+gIAAAAAAAAAXFvAuO9axdgEAAAAAAkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJAQICAgICAgICAgICAgICAgICAgICAgICAgICAgICAgICCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoKCgoAAABcW8C471rF2AQAAAAAAAAAuLaAcd+1irEJAAAAAADKmjsAAAAAAGXNHQAAAADoMM5oeDLOaICy5g4AAAAAgBe0LAAAAABI6AEAAAAAAPoA",
+        check: |e| matches!(e, SpinPetEvent::PartialClose(e) if e.order_type == 1 && e.user_close_profit == 50_000_000 && e.margin_sol_amount == 250_000_000 && e.borrow_fee == 250),
+    },
+    ParserFixture {
+        variant: "MilestoneDiscount",
+        data_base64: "gugLJSK5iIABAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQICAgICAgICAgICAgICAgICAgICAgICAgICAgICAgICAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwNQAMgAAg==",
+        check: |e| matches!(e, SpinPetEvent::MilestoneDiscount(e) if e.swap_fee == 80 && e.borrow_fee == 200 && e.fee_discount_flag == 2),
+    },
+];
+
+/// Payer and mint of a parsed event
+fn event_identity(event: &SpinPetEvent) -> (&str, &str) {
+    match event {
+        SpinPetEvent::TokenCreated(e) => (&e.payer, &e.mint_account),
+        SpinPetEvent::BuySell(e) => (&e.payer, &e.mint_account),
+        SpinPetEvent::LongShort(e) => (&e.payer, &e.mint_account),
+        SpinPetEvent::ForceLiquidate(e) => (&e.payer, &e.mint_account),
+        SpinPetEvent::FullClose(e) => (&e.payer, &e.mint_account),
+        SpinPetEvent::PartialClose(e) => (&e.payer, &e.mint_account),
+        SpinPetEvent::MilestoneDiscount(e) => (&e.payer, &e.mint_account),
+    }
+}
+
+fn variant_name(event: &SpinPetEvent) -> &'static str {
+    match event {
+        SpinPetEvent::TokenCreated(_) => "TokenCreated",
+        SpinPetEvent::BuySell(_) => "BuySell",
+        SpinPetEvent::LongShort(_) => "LongShort",
+        SpinPetEvent::ForceLiquidate(_) => "ForceLiquidate",
+        SpinPetEvent::FullClose(_) => "FullClose",
+        SpinPetEvent::PartialClose(_) => "PartialClose",
+        SpinPetEvent::MilestoneDiscount(_) => "MilestoneDiscount",
+    }
+}
+
+impl EventParser {
+    /// Parse every embedded fixture and verify its variant and key fields
+    pub fn run_selftest(&self) -> anyhow::Result<()> {
+        self.check_fixtures(PARSER_FIXTURES)
+    }
+
+    fn check_fixtures(&self, fixtures: &[ParserFixture]) -> anyhow::Result<()> {
+        let mut failures = Vec::new();
+
+        for fixture in fixtures {
+            let data = base64::engine::general_purpose::STANDARD.decode(fixture.data_base64)?;
+            match self.parse_event_data(&data, "selftest", 0) {
+                Ok(Some(event)) if variant_name(&event) != fixture.variant => failures.push(
+                    format!("{}: parsed as {}", fixture.variant, variant_name(&event)),
+                ),
+                Ok(Some(event)) => {
+                    if event_identity(&event) != (FIXTURE_PAYER, FIXTURE_MINT)
+                        || !(fixture.check)(&event)
+                    {
+                        failures.push(format!("{}: key fields do not match", fixture.variant));
+                    }
+                }
+                Ok(None) => {
+                    failures.push(format!("{}: discriminator not recognized", fixture.variant))
+                }
+                Err(e) => failures.push(format!("{}: {}", fixture.variant, e)),
+            }
+        }
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!(
+                "Parser self-test failed: {}",
+                failures.join("; ")
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parser() -> EventParser {
+        EventParser::new("JBMmrp6jhksqnxDBskkmVvWHhJLaPBjgiMHEroJbUTBZ").unwrap()
+    }
+
+    #[test]
+    fn test_selftest_passes_on_committed_fixtures() {
+        assert_eq!(PARSER_FIXTURES.len(), 7);
+        parser().run_selftest().unwrap();
+    }
+
+    #[test]
+    fn test_selftest_fails_on_discriminator_mismatch() {
+        // An edited discriminator constant no longer matches the fixture bytes;
+        // flipping the fixture's discriminator produces the same mismatch
+        let fixture = &PARSER_FIXTURES[1];
+        let mut data = base64::engine::general_purpose::STANDARD
+            .decode(fixture.data_base64)
+            .unwrap();
+        data[0] ^= 0xff;
+        let mutated = base64::engine::general_purpose::STANDARD.encode(&data);
+        let fixtures = [ParserFixture {
+            variant: fixture.variant,
+            data_base64: Box::leak(mutated.into_boxed_str()),
+            check: fixture.check,
+        }];
+
+        let err = parser().check_fixtures(&fixtures).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("BuySell: discriminator not recognized"));
+    }
+}
@@ -1,2 +1,170 @@
 // Utility functions module
-// Currently empty, reserved for future expansion
+
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use serde::{Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use serde_with::{DeserializeAs, DisplayFromStr, PickFirst, Same, SerializeAs};
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::future::Future;
+
+tokio::task_local! {
+    /// Set for the duration of a request served with `server.bigint_as_string`
+    static AMOUNTS_AS_STRINGS_REQUEST: bool;
+}
+
+thread_local! {
+    /// Set only while an API response body of such a request is being serialized, so storage
+    /// writes made by the same request keep numeric amounts
+    static AMOUNTS_AS_STRINGS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Run a request with the u64 amounts of its API response written as strings
+pub async fn with_amounts_as_strings<F: Future>(request: F) -> F::Output {
+    AMOUNTS_AS_STRINGS_REQUEST.scope(true, request).await
+}
+
+/// `serialize_with` hook for API response bodies: writes every `AmountU64` field as a string
+/// when the current request runs under `with_amounts_as_strings`
+pub fn serialize_response_data<T: Serialize, S: Serializer>(
+    data: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let as_strings = AMOUNTS_AS_STRINGS_REQUEST
+        .try_with(|as_strings| *as_strings)
+        .unwrap_or(false);
+    let previous = AMOUNTS_AS_STRINGS.with(|flag| flag.replace(as_strings));
+    let result = data.serialize(serializer);
+    AMOUNTS_AS_STRINGS.with(|flag| flag.set(previous));
+    result
+}
+
+/// serde_with adapter for u64 amounts: written as a JSON number, or as a decimal string in
+/// `server.bigint_as_string` responses; read from either form
+pub struct AmountU64;
+
+impl SerializeAs<u64> for AmountU64 {
+    fn serialize_as<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        if AMOUNTS_AS_STRINGS.with(Cell::get) {
+            serializer.collect_str(value)
+        } else {
+            serializer.serialize_u64(*value)
+        }
+    }
+}
+
+impl<'de> DeserializeAs<'de, u64> for AmountU64 {
+    fn deserialize_as<D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        <PickFirst<(Same, DisplayFromStr)>>::deserialize_as(deserializer)
+    }
+}
+
+/// Response fields carrying a timestamp, stored either as Unix seconds or RFC3339 strings
+const TIMESTAMP_FIELDS: &[&str] = &[
+    "time",
+    "timestamp",
+    "ts",
+    "create_timestamp",
+    "created_at",
+    "latest_trade_time",
+    "last_updated_at",
+    "start_time",
+    "end_time",
+    "since",
+    "started_at",
+];
+
+/// Timestamp representation requested via `?ts_format=`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampFormat {
+    Seconds,
+    Millis,
+    Iso,
+}
+
+impl std::str::FromStr for TimestampFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "s" => Ok(Self::Seconds),
+            "ms" => Ok(Self::Millis),
+            "iso" => Ok(Self::Iso),
+            _ => Err(anyhow::anyhow!("ts_format must be one of: ms, s, iso")),
+        }
+    }
+}
+
+/// Read a timestamp field given as Unix seconds or an RFC3339 string
+fn parse_timestamp(value: &Value) -> Option<DateTime<Utc>> {
+    match value {
+        Value::Number(n) => Utc.timestamp_opt(n.as_i64()?, 0).single(),
+        Value::String(s) => DateTime::parse_from_rfc3339(s)
+            .ok()
+            .map(|dt| dt.with_timezone(&Utc)),
+        _ => None,
+    }
+}
+
+fn format_timestamp(dt: DateTime<Utc>, format: TimestampFormat) -> Value {
+    match format {
+        TimestampFormat::Seconds => Value::from(dt.timestamp()),
+        TimestampFormat::Millis => Value::from(dt.timestamp_millis()),
+        TimestampFormat::Iso => Value::from(dt.to_rfc3339_opts(SecondsFormat::AutoSi, true)),
+    }
+}
+
+/// Rewrite every known timestamp field in a JSON response to the requested format
+pub fn normalize_timestamps(value: &mut Value, format: TimestampFormat) {
+    match value {
+        Value::Object(map) => {
+            for (key, field) in map.iter_mut() {
+                if TIMESTAMP_FIELDS.contains(&key.as_str()) {
+                    if let Some(dt) = parse_timestamp(field) {
+                        *field = format_timestamp(dt, format);
+                        continue;
+                    }
+                }
+                normalize_timestamps(field, format);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                normalize_timestamps(item, format);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Flatten a JSON record into `column -> cell` pairs for CSV output: nested objects become
+/// `parent.child` columns, arrays are kept as JSON text and null is an empty cell
+pub fn flatten_json_record(value: &Value) -> BTreeMap<String, String> {
+    let mut cells = BTreeMap::new();
+    flatten_into(&mut cells, String::new(), value);
+    cells
+}
+
+fn flatten_into(cells: &mut BTreeMap<String, String>, column: String, value: &Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, field) in map {
+                let nested = if column.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", column, key)
+                };
+                flatten_into(cells, nested, field);
+            }
+        }
+        Value::Null => {
+            cells.insert(column, String::new());
+        }
+        Value::String(s) => {
+            cells.insert(column, s.clone());
+        }
+        other => {
+            cells.insert(column, other.to_string());
+        }
+    }
+}
@@ -56,6 +56,22 @@ socket.on('unsubscribe_confirmed', (data) => {
     console.log('🚫 取消订阅确认:', JSON.stringify(data, null, 2));
 });
 
+socket.on('unsubscribe_all_confirmed', (data) => {
+    console.log('🧹 取消全部订阅确认:', JSON.stringify(data, null, 2));
+});
+
+socket.on('session_resumed', (data) => {
+    console.log(`♻️ 会话已恢复, 恢复 ${data.subscriptions.length} 个订阅:`, JSON.stringify(data.subscriptions));
+});
+
+socket.on('candle_closed', (data) => {
+    console.log(`🕯️ K线收盘 (${data.symbol}@${data.interval}):`, data.data);
+});
+
+socket.on('candles_closed', (data) => {
+    console.log(`🕯️ 批量K线收盘: ${data.length} 根`);
+});
+
 socket.on('history_data', (data) => {
     console.log(`📈 历史数据 (${data.symbol}@${data.interval}):`, {
         symbol: data.symbol,
@@ -70,6 +86,11 @@ socket.on('history_data', (data) => {
     }
 });
 
+// 回应服务端心跳, 避免被判定为失活连接
+socket.on('heartbeat', () => {
+    socket.emit('heartbeat_ack');
+});
+
 socket.on('kline_data', (data) => {
     console.log(`📊 实时K线更新 (${data.symbol}@${data.interval}):`, {
         symbol: data.symbol,
@@ -145,6 +166,12 @@ function startTests() {
         }
     }, 15000);
     
+    // 测试 6: 一次取消全部订阅 (连接保持)
+    setTimeout(() => {
+        console.log('\n🧹 测试 6: 取消全部订阅');
+        socket.emit('unsubscribe_all');
+    }, 22000);
+    
     // 测试结束
     setTimeout(() => {
         console.log('\n🏁 测试完成，断开连接');
@@ -178,4 +205,5 @@ console.log('  4. 测试取消订阅功能');
 console.log('  5. 测试重新订阅功能');
 console.log('  6. 压力测试多个订阅');
 console.log('  7. 观察实时数据推送');
-console.log('  8. 清理并断开连接\n');
\ No newline at end of file
+console.log('  8. 取消全部订阅');
+console.log('  9. 清理并断开连接\n');
\ No newline at end of file